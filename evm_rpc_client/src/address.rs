@@ -1,7 +1,7 @@
 use libsecp256k1::PublicKey;
+use std::collections::BTreeSet;
 use std::fmt;
 use std::fmt::{Display, Formatter};
-use std::str::FromStr;
 
 use crate::eth_types::Address;
 
@@ -24,7 +24,9 @@ fn keccak(bytes: &[u8]) -> [u8; 32] {
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum AddressValidationError {
     Invalid { error: String },
+    InvalidChecksum { expected: Address },
     NotSupported(Address),
+    IsKnownErc20Contract(Address),
 }
 
 impl Display for AddressValidationError {
@@ -33,20 +35,43 @@ impl Display for AddressValidationError {
             AddressValidationError::Invalid { error } => {
                 write!(f, r#"Invalid address: {error}"#)
             }
+            AddressValidationError::InvalidChecksum { expected } => {
+                write!(f, r#"Invalid EIP-55 checksum, expected {expected}"#)
+            }
             AddressValidationError::NotSupported(address) => {
                 write!(f, r#"Address {address} is not supported"#)
             }
+            AddressValidationError::IsKnownErc20Contract(address) => {
+                write!(
+                    f,
+                    r#"Address {address} is the contract of a supported ERC-20 token"#
+                )
+            }
         }
     }
 }
 
 /// Validate whether the given address can be used as the destination of an Ethereum transaction.
-pub fn validate_address_as_destination(address: &str) -> Result<Address, AddressValidationError> {
-    let address =
-        Address::from_str(address).map_err(|e| AddressValidationError::Invalid { error: e })?;
+///
+/// `known_erc20_contracts` are rejected as well, since sending the native token to an ERC-20
+/// contract usually locks it. Pass an empty set when withdrawing ERC-20 tokens or to send
+/// to a contract on purpose.
+pub fn validate_address_as_destination(
+    address: &str,
+    known_erc20_contracts: &BTreeSet<Address>,
+) -> Result<Address, AddressValidationError> {
+    let parsed = Address::from_str_ignoring_checksum(address)
+        .map_err(|e| AddressValidationError::Invalid { error: e })?;
+    if !parsed.matches_checksum(address) {
+        return Err(AddressValidationError::InvalidChecksum { expected: parsed });
+    }
+    let address = parsed;
     if address == Address::ZERO {
         return Err(AddressValidationError::NotSupported(address));
     }
+    if known_erc20_contracts.contains(&address) {
+        return Err(AddressValidationError::IsKnownErc20Contract(address));
+    }
 
     Ok(address)
 }
@@ -1,33 +1,89 @@
 mod validate_address_as_destination {
 
+    use std::collections::BTreeSet;
     use std::str::FromStr;
 
     use crate::address::{validate_address_as_destination, Address, AddressValidationError};
     use assert_matches::assert_matches;
+    use proptest::array::uniform20;
+    use proptest::prelude::any;
     use proptest::{prop_assert_eq, prop_assume, proptest};
 
     #[test]
     fn should_fail_when_contract_creation_address_as_destination() {
         assert_eq!(
-            validate_address_as_destination("0x0000000000000000000000000000000000000000"),
+            validate_address_as_destination(
+                "0x0000000000000000000000000000000000000000",
+                &BTreeSet::new()
+            ),
             Err(AddressValidationError::NotSupported(Address::ZERO))
         );
     }
 
+    #[test]
+    fn should_fail_when_known_erc20_contract_as_destination() {
+        let usdt = Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
+        let known_erc20_contracts = BTreeSet::from([usdt]);
+
+        assert_eq!(
+            validate_address_as_destination(
+                "0xdAC17F958D2ee523a2206206994597C13D831ec7",
+                &known_erc20_contracts
+            ),
+            Err(AddressValidationError::IsKnownErc20Contract(usdt))
+        );
+        assert_eq!(
+            validate_address_as_destination(
+                "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+                &known_erc20_contracts
+            ),
+            Ok(Address::from_str("0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB").unwrap())
+        );
+        // contract destinations explicitly allowed
+        assert_eq!(
+            validate_address_as_destination(
+                "0xdAC17F958D2ee523a2206206994597C13D831ec7",
+                &BTreeSet::new()
+            ),
+            Ok(usdt)
+        );
+    }
+
     proptest! {
         #[test]
-        fn should_validate_non_zero_addresses(valid_address in "0x[0-9a-fA-F]{40}") {
+        fn should_validate_non_zero_addresses(valid_address in "0x([0-9a-f]{40}|[0-9A-F]{40})") {
             prop_assume!(valid_address != "0x0000000000000000000000000000000000000000");
             let address = Address::from_str(&valid_address).unwrap();
-            prop_assert_eq!(validate_address_as_destination(&valid_address), Ok(address));
+            prop_assert_eq!(validate_address_as_destination(&valid_address, &BTreeSet::new()), Ok(address));
         }
     }
 
+    proptest! {
+        #[test]
+        fn should_validate_checksummed_addresses(bytes in uniform20(any::<u8>())) {
+            let address = Address::new(bytes);
+            prop_assume!(address != Address::ZERO);
+            prop_assert_eq!(validate_address_as_destination(&address.to_checksum_string(), &BTreeSet::new()), Ok(address));
+        }
+    }
+
+    #[test]
+    fn should_fail_when_checksum_invalid() {
+        let expected = Address::from_str("0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB").unwrap();
+        assert_eq!(
+            validate_address_as_destination(
+                "0xDbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+                &BTreeSet::new()
+            ),
+            Err(AddressValidationError::InvalidChecksum { expected })
+        );
+    }
+
     proptest! {
         #[test]
         fn should_fail_when_address_too_short(invalid_address in "0x[0-9a-fA-F]{0, 39}") {
             assert_matches!(
-                validate_address_as_destination(&invalid_address),
+                validate_address_as_destination(&invalid_address, &BTreeSet::new()),
                 Err(AddressValidationError::Invalid { .. })
             );
 
@@ -38,7 +94,7 @@ mod validate_address_as_destination {
         #[test]
         fn should_fail_when_address_too_long(invalid_address in "0x[0-9a-fA-F]{41,100}") {
             assert_matches!(
-                validate_address_as_destination(&invalid_address),
+                validate_address_as_destination(&invalid_address, &BTreeSet::new()),
                 Err(AddressValidationError::Invalid { .. })
             );
 
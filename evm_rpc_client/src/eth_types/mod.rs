@@ -40,6 +40,71 @@ impl Address {
     pub const fn into_bytes(self) -> [u8; 20] {
         self.0
     }
+
+    /// Render the address using EIP-55 mixed-case checksum encoding.
+    /// https://eips.ethereum.org/EIPS/eip-55
+    pub fn to_checksum_string(&self) -> String {
+        let mut addr_chars = [0u8; 20 * 2];
+        hex::encode_to_slice(self.0, &mut addr_chars)
+            .expect("bug: failed to encode an address as hex");
+
+        let checksum = keccak(&addr_chars[..]);
+        let mut cs_nibbles = [0u8; 32 * 2];
+        for i in 0..32 {
+            cs_nibbles[2 * i] = checksum[i] >> 4;
+            cs_nibbles[2 * i + 1] = checksum[i] & 0x0f;
+        }
+        let mut result = String::with_capacity(2 + addr_chars.len());
+        result.push_str("0x");
+        for (a, cs) in addr_chars.iter().zip(cs_nibbles.iter()) {
+            let ascii_byte = if *cs >= 0x08 {
+                a.to_ascii_uppercase()
+            } else {
+                *a
+            };
+            result.push(char::from(ascii_byte));
+        }
+        result
+    }
+
+    /// Parse a `0x`-prefixed address that must be rendered with its EIP-55 checksum.
+    /// Contrary to [`Address::from_str`], all-lowercase and all-uppercase addresses are rejected,
+    /// unless this is how their checksum renders them.
+    pub fn from_str_checksum(s: &str) -> Result<Self, String> {
+        let address = Self::from_str_ignoring_checksum(s)?;
+        if address.to_checksum_string() != s {
+            return Err(format!(
+                "address has an invalid EIP-55 checksum, expected {}",
+                address
+            ));
+        }
+        Ok(address)
+    }
+
+    /// Parse a `0x`-prefixed hexadecimal address in any casing, without verifying its checksum.
+    /// Used to replay addresses recorded before checksums were verified.
+    pub fn from_str_ignoring_checksum(s: &str) -> Result<Self, String> {
+        if !s.starts_with("0x") {
+            return Err("address doesn't start with '0x'".to_string());
+        }
+        let mut bytes = [0u8; 20];
+        hex::decode_to_slice(&s[2..], &mut bytes)
+            .map_err(|e| format!("address is not hex: {}", e))?;
+        Ok(Self(bytes))
+    }
+
+    /// Whether `s` is an acceptable rendering of this address.
+    /// All-lowercase and all-uppercase addresses carry no checksum and are always accepted,
+    /// while mixed-case addresses must match the EIP-55 checksum.
+    pub(crate) fn matches_checksum(&self, s: &str) -> bool {
+        let hex_digits = s.strip_prefix("0x").unwrap_or(s);
+        let has_lowercase = hex_digits.chars().any(|c| c.is_ascii_lowercase());
+        let has_uppercase = hex_digits.chars().any(|c| c.is_ascii_uppercase());
+        if !(has_lowercase && has_uppercase) {
+            return true;
+        }
+        self.to_checksum_string()[2..] == *hex_digits
+    }
 }
 
 impl LowerHex for Address {
@@ -86,13 +151,14 @@ impl FromStr for Address {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if !s.starts_with("0x") {
-            return Err("address doesn't start with '0x'".to_string());
+        let address = Self::from_str_ignoring_checksum(s)?;
+        if !address.matches_checksum(s) {
+            return Err(format!(
+                "address has an invalid EIP-55 checksum, expected {}",
+                address
+            ));
         }
-        let mut bytes = [0u8; 20];
-        hex::decode_to_slice(&s[2..], &mut bytes)
-            .map_err(|e| format!("address is not hex: {}", e))?;
-        Ok(Self(bytes))
+        Ok(address)
     }
 }
 
@@ -104,28 +170,7 @@ impl fmt::Debug for Address {
 
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Display address using EIP-55
-        // https://eips.ethereum.org/EIPS/eip-55
-        let mut addr_chars = [0u8; 20 * 2];
-        hex::encode_to_slice(self.0, &mut addr_chars)
-            .expect("bug: failed to encode an address as hex");
-
-        let checksum = keccak(&addr_chars[..]);
-        let mut cs_nibbles = [0u8; 32 * 2];
-        for i in 0..32 {
-            cs_nibbles[2 * i] = checksum[i] >> 4;
-            cs_nibbles[2 * i + 1] = checksum[i] & 0x0f;
-        }
-        write!(f, "0x")?;
-        for (a, cs) in addr_chars.iter().zip(cs_nibbles.iter()) {
-            let ascii_byte = if *cs >= 0x08 {
-                a.to_ascii_uppercase()
-            } else {
-                *a
-            };
-            write!(f, "{}", char::from(ascii_byte))?;
-        }
-        Ok(())
+        f.write_str(&self.to_checksum_string())
     }
 }
 
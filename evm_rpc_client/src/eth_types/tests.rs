@@ -1,4 +1,7 @@
 use crate::eth_types::Address;
+use assert_matches::assert_matches;
+use proptest::array::uniform20;
+use proptest::prelude::any;
 use proptest::{prop_assert, prop_assert_eq, proptest};
 use std::str::FromStr;
 
@@ -8,13 +11,38 @@ mod from_string {
 
     proptest! {
         #[test]
-        fn should_accept_20_bytes_address(valid_address in "0x[0-9a-fA-F]{40}") {
+        fn should_accept_20_bytes_address(valid_address in "0x([0-9a-f]{40}|[0-9A-F]{40})") {
             let address = Address::from_str(&valid_address).unwrap();
             let raw_bytes = hex::decode(&valid_address[2..]).unwrap();
             prop_assert_eq!(address.as_ref(), &raw_bytes[..]);
         }
     }
 
+    proptest! {
+        #[test]
+        fn should_accept_checksummed_address(bytes in uniform20(any::<u8>())) {
+            let address = Address::new(bytes);
+            prop_assert_eq!(Address::from_str(&address.to_checksum_string()), Ok(address));
+        }
+    }
+
+    #[test]
+    fn should_fail_when_mixed_case_address_has_invalid_checksum() {
+        let valid = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        // flip the case of the first letter
+        let invalid = "0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+        assert!(Address::from_str(valid).is_ok());
+        assert_matches!(
+            Address::from_str(invalid),
+            Err(err) if err.starts_with("address has an invalid EIP-55 checksum")
+        );
+        assert_eq!(
+            Address::from_str(&invalid.to_lowercase()),
+            Address::from_str(valid)
+        );
+    }
+
     proptest! {
         #[test]
         fn should_fail_when_address_too_short(invalid_address in "0x[0-9a-fA-F]{0, 39}") {
@@ -30,9 +58,107 @@ mod from_string {
     }
 }
 
+mod from_str_checksum {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn should_accept_checksummed_address(bytes in uniform20(any::<u8>())) {
+            let address = Address::new(bytes);
+            prop_assert_eq!(Address::from_str_checksum(&address.to_checksum_string()), Ok(address));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn should_accept_lowercase_address_only_when_checksum_is_lowercase(bytes in uniform20(any::<u8>())) {
+            let address = Address::new(bytes);
+            let lowercase = format!("{address:x}");
+            prop_assert_eq!(
+                Address::from_str_checksum(&lowercase).is_ok(),
+                lowercase == address.to_checksum_string()
+            );
+        }
+    }
+
+    #[test]
+    fn should_accept_addresses_without_letters() {
+        for address in [
+            "0x0000000000000000000000000000000000000000",
+            "0x1111111111111111111111111111111111111111",
+            "0x9999999999999999999999999999999999999999",
+        ] {
+            assert_eq!(
+                Address::from_str_checksum(address),
+                Address::from_str(address)
+            );
+        }
+        assert_eq!(
+            Address::from_str_checksum("0x0000000000000000000000000000000000000000"),
+            Ok(Address::ZERO)
+        );
+    }
+
+    #[test]
+    fn should_validate_checksum_of_letter_at_every_byte() {
+        let all_letters = Address::new([0xff; 20]);
+        let mut addresses = vec![all_letters];
+        for i in 0..20 {
+            let mut bytes = [0_u8; 20];
+            bytes[i] = 0xab;
+            addresses.push(Address::new(bytes));
+        }
+
+        for address in addresses {
+            let checksummed = address.to_checksum_string();
+            assert_eq!(Address::from_str_checksum(&checksummed), Ok(address));
+
+            for (position, c) in checksummed.char_indices().skip(2) {
+                if !c.is_ascii_alphabetic() {
+                    continue;
+                }
+                let mut flipped = checksummed.clone();
+                let flipped_char = if c.is_ascii_lowercase() {
+                    c.to_ascii_uppercase()
+                } else {
+                    c.to_ascii_lowercase()
+                };
+                flipped.replace_range(position..=position, &flipped_char.to_string());
+                assert_matches!(
+                    Address::from_str_checksum(&flipped),
+                    Err(err) if err.starts_with("address has an invalid EIP-55 checksum"),
+                    "flipped case at position {position} of {checksummed} was accepted"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn should_reject_unprefixed_or_invalid_length_addresses() {
+        assert!(Address::from_str_checksum("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_err());
+        assert!(Address::from_str_checksum("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA").is_err());
+        assert!(
+            Address::from_str_checksum("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed00").is_err()
+        );
+    }
+}
+
+#[test]
+fn should_serialize_as_lowercase_hex() {
+    let address = Address::from_str("0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359").unwrap();
+
+    assert_eq!(
+        serde_json::to_string(&address).unwrap(),
+        "\"0xfb6916095ca1df60bb79ce92ce3ea74c37c5d359\""
+    );
+    assert_eq!(
+        serde_json::from_str::<Address>("\"0xfb6916095ca1df60bb79ce92ce3ea74c37c5d359\"").unwrap(),
+        address
+    );
+}
+
 mod from_32_bytes {
     use super::*;
-    use assert_matches::assert_matches;
 
     #[test]
     fn should_deserialize_address_from_32_bytes_hex_string() {
@@ -83,7 +209,7 @@ mod from_32_bytes {
 #[test]
 fn should_display_using_mixed_case() {
     assert_eq!(
-        Address::from_str("0x7574EB42CA208A4f6960ECCAfDF186D627DCC175")
+        Address::from_str_ignoring_checksum("0x7574EB42CA208A4f6960ECCAfDF186D627DCC175")
             .unwrap()
             .to_string(),
         "0x7574eB42cA208A4f6960ECCAfDF186D627dCC175"
@@ -111,3 +237,27 @@ fn should_display_eip_55_test_cases() {
         assert_eq!(&addr.to_string(), example);
     }
 }
+
+#[test]
+fn should_render_checksum_string_as_display() {
+    let address = Address::from_str("0xfb6916095ca1df60bb79ce92ce3ea74c37c5d359").unwrap();
+    assert_eq!(
+        address.to_checksum_string(),
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359"
+    );
+    assert_eq!(address.to_string(), address.to_checksum_string());
+}
+
+#[test]
+fn should_encode_same_bytes_regardless_of_casing() {
+    let lowercase = Address::from_str("0xfb6916095ca1df60bb79ce92ce3ea74c37c5d359").unwrap();
+    let checksummed = Address::from_str("0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359").unwrap();
+    assert_eq!(lowercase, checksummed);
+
+    let encoded = minicbor::to_vec(checksummed).unwrap();
+    assert_eq!(encoded, minicbor::to_vec(lowercase).unwrap());
+    // CBOR byte string of length 20 followed by the raw address bytes.
+    assert_eq!(encoded[0], 0x54);
+    assert_eq!(&encoded[1..], checksummed.as_ref());
+    assert_eq!(minicbor::decode::<Address>(&encoded).unwrap(), lowercase);
+}
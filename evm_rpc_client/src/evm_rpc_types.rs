@@ -23,6 +23,26 @@ pub struct RpcConfig {
 
     #[serde(rename = "responseConsensus")]
     pub response_consensus: Option<ConsensusStrategy>,
+
+    /// If set, providers that errored are ignored as long as the others agree,
+    /// see [`PartialOnError`]. Otherwise any error fails the consensus.
+    #[serde(rename = "partialOnError")]
+    pub partial_on_error: Option<PartialOnError>,
+}
+
+/// Accepts the response of the providers that did not error, if they all returned the same
+/// response and there are at least `min_success` of them.
+#[derive(Clone, Debug, PartialEq, Eq, Default, CandidType, Deserialize)]
+pub struct PartialOnError {
+    #[serde(rename = "minSuccess")]
+    pub min_success: Option<u8>,
+}
+
+impl PartialOnError {
+    pub fn min_success_or_default(&self) -> u8 {
+        const DEFAULT_MIN_SUCCESS: u8 = 2;
+        self.min_success.unwrap_or(DEFAULT_MIN_SUCCESS).max(1)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Default, CandidType, Deserialize)]
@@ -42,6 +62,7 @@ impl From<GetLogsRpcConfig> for RpcConfig {
         Self {
             response_size_estimate: config.response_size_estimate,
             response_consensus: config.response_consensus,
+            partial_on_error: None,
         }
     }
 }
@@ -439,21 +460,30 @@ pub enum MultiRpcResult<T> {
 }
 
 impl<T> MultiRpcResult<T> {
+    /// Maps the successful value(s), leaving errors and the providers of inconsistent results untouched.
+    ///
+    /// The closure is called once per successful result, hence `FnMut`.
     pub fn map<R>(self, mut f: impl FnMut(T) -> R) -> MultiRpcResult<R> {
+        self.map_consistent(|result| result.map(&mut f))
+    }
+
+    /// Maps the successful value(s) with a fallible function. An error returned by `f` replaces the
+    /// corresponding result, while existing errors are propagated unchanged.
+    pub fn and_then<R>(self, mut f: impl FnMut(T) -> RpcResult<R>) -> MultiRpcResult<R> {
+        self.map_consistent(|result| result.and_then(&mut f))
+    }
+
+    /// Maps every single result, be it the consistent one or each of the inconsistent ones.
+    pub fn map_consistent<R>(
+        self,
+        mut f: impl FnMut(RpcResult<T>) -> RpcResult<R>,
+    ) -> MultiRpcResult<R> {
         match self {
-            MultiRpcResult::Consistent(result) => MultiRpcResult::Consistent(result.map(f)),
+            MultiRpcResult::Consistent(result) => MultiRpcResult::Consistent(f(result)),
             MultiRpcResult::Inconsistent(results) => MultiRpcResult::Inconsistent(
                 results
                     .into_iter()
-                    .map(|(service, result)| {
-                        (
-                            service,
-                            match result {
-                                Ok(ok) => Ok(f(ok)),
-                                Err(err) => Err(err),
-                            },
-                        )
-                    })
+                    .map(|(service, result)| (service, f(result)))
                     .collect(),
             ),
         }
@@ -496,6 +526,14 @@ pub enum RpcError {
     JsonRpcError(JsonRpcError),
     #[error("Validation error: {0}")]
     ValidationError(ValidationError),
+    /// The provider refused to return all the logs matching an `eth_getLogs` request,
+    /// the request should be retried with a smaller block range.
+    #[error("Result set too large: {0}")]
+    ResultSetTooLarge(JsonRpcError),
+    /// The provider kept answering with HTTP 429 Too Many Requests and should not be
+    /// queried again for `retry_after_secs` seconds.
+    #[error("Rate limited by the provider, retry after {retry_after_secs} seconds")]
+    RateLimited { retry_after_secs: u64 },
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, CandidType, Deserialize, Error)]
@@ -882,6 +920,12 @@ pub struct GetTransactionCountArgs {
     pub block: BlockTag,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct GetCodeArgs {
+    pub address: Hex20,
+    pub block: BlockTag,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
 pub struct CallArgs {
     pub transaction: TransactionRequest,
@@ -1,14 +1,29 @@
+//! Client of Ethereum JSON-RPC providers for canisters.
+//!
+//! An [`EvmRpcClient`] sends its requests in one of two [`CallerService`] modes:
+//! * [`CallerService::EvmRpcCanisterClient`] calls the EVM RPC canister, which queries
+//!   the providers and aggregates their responses.
+//! * [`CallerService::RpcHttpOutCallClient`] queries the providers directly with HTTP
+//!   outcalls of the calling canister, see [`native_http`].
+//!
+//! Both modes need the `canister` feature to actually reach the Internet Computer.
+//! Without it, calls fail with an error while the types and the provider selection
+//! can be used and tested on any target. A client built with
+//! [`EvmRpcClient::builder_without_logger`] does not log its calls.
+
 #[cfg(test)]
 mod tests;
 
 use async_trait::async_trait;
 use candid::utils::ArgumentEncoder;
-use candid::{CandidType, Principal};
+use candid::{CandidType, Nat, Principal};
 use evm_rpc_types::CallArgs;
 use ic_canister_log::{log, Sink};
 use ic_cdk::call::RejectCode;
+use num_traits::ToPrimitive;
 use serde::de::DeserializeOwned;
-use std::fmt::Debug;
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug};
 use std::str::FromStr;
 
 pub mod address;
@@ -19,14 +34,16 @@ pub mod native_http;
 pub mod numeric;
 
 pub use evm_rpc_types::{
-    Block, BlockTag, ConsensusStrategy, EthMainnetService, FeeHistory, FeeHistoryArgs, GetLogsArgs,
-    GetTransactionCountArgs, Hex, Hex20, Hex256, Hex32, HexByte, HttpOutcallError, JsonRpcError,
-    LogEntry, MultiRpcResult, Nat256, ProviderError, RejectionCode, RpcApi, RpcConfig, RpcError,
-    RpcResult, RpcService, RpcServices, SendRawTransactionStatus, TransactionReceipt,
-    ValidationError,
+    Block, BlockTag, ConsensusStrategy, EthMainnetService, FeeHistory, FeeHistoryArgs, GetCodeArgs,
+    GetLogsArgs, GetTransactionCountArgs, Hex, Hex20, Hex256, Hex32, HexByte, HttpOutcallError,
+    JsonRpcError, LogEntry, MultiRpcResult, Nat256, PartialOnError, ProviderError, RejectionCode,
+    RpcApi, RpcConfig, RpcError, RpcResult, RpcService, RpcServices, SendRawTransactionStatus,
+    TransactionReceipt, ValidationError,
 };
 
+use crate::logs::NoOpSink;
 use crate::native_http::candid_rpc::CandidRpcClient;
+use crate::numeric::{BlockNumber, LogIndex};
 
 #[async_trait]
 pub trait InterCanisterCall {
@@ -53,6 +70,7 @@ pub enum CallerService {
 #[derive(Clone, Debug, Copy, Eq, PartialEq)]
 pub struct EvmRpcCanisterClinet {}
 
+#[cfg(feature = "canister")]
 #[async_trait]
 impl InterCanisterCall for EvmRpcCanisterClinet {
     async fn call<In, Out>(
@@ -70,25 +88,7 @@ impl InterCanisterCall for EvmRpcCanisterClinet {
             .with_cycles(cycles)
             .with_args(&args)
             .await
-            .map_err(|e| match e {
-                ic_cdk::call::CallFailed::InsufficientLiquidCycleBalance(
-                    _insufficient_liquid_cycle_balance,
-                ) => (
-                    RejectionCode::CanisterError,
-                    "Not enough cycles to make the call".to_string(),
-                ),
-                ic_cdk::call::CallFailed::CallPerformFailed(_call_perform_failed) => (
-                    RejectionCode::Unknown,
-                    "Failed to perfom the call, a retry should help".to_string(),
-                ),
-                ic_cdk::call::CallFailed::CallRejected(call_rejected) => (
-                    call_rejected
-                        .reject_code()
-                        .unwrap_or(RejectCode::SysUnknown)
-                        .into(),
-                    call_rejected.reject_message().to_string(),
-                ),
-            })?
+            .map_err(map_call_failed)?
             .candid();
 
         match res {
@@ -98,6 +98,55 @@ impl InterCanisterCall for EvmRpcCanisterClinet {
     }
 }
 
+/// Outside of a canister there is no one to call, so every call is rejected.
+#[cfg(not(feature = "canister"))]
+#[async_trait]
+impl InterCanisterCall for EvmRpcCanisterClinet {
+    async fn call<In, Out>(
+        &self,
+        _id: Principal,
+        _method: &str,
+        _args: In,
+        _cycles: u128,
+    ) -> Result<Out, (RejectionCode, String)>
+    where
+        In: ArgumentEncoder + Send + 'static,
+        Out: CandidType + DeserializeOwned + 'static,
+    {
+        Err((
+            RejectionCode::SysFatal,
+            CANISTER_FEATURE_REQUIRED.to_string(),
+        ))
+    }
+}
+
+#[cfg(not(feature = "canister"))]
+pub(crate) const CANISTER_FEATURE_REQUIRED: &str =
+    "calls to the Internet Computer require the `canister` feature of evm_rpc_client";
+
+#[cfg(feature = "canister")]
+fn map_call_failed(error: ic_cdk::call::CallFailed) -> (RejectionCode, String) {
+    match error {
+        ic_cdk::call::CallFailed::InsufficientLiquidCycleBalance(
+            _insufficient_liquid_cycle_balance,
+        ) => (
+            RejectionCode::CanisterError,
+            "Not enough cycles to make the call".to_string(),
+        ),
+        ic_cdk::call::CallFailed::CallPerformFailed(_call_perform_failed) => (
+            RejectionCode::Unknown,
+            "Failed to perfom the call, a retry should help".to_string(),
+        ),
+        ic_cdk::call::CallFailed::CallRejected(call_rejected) => (
+            call_rejected
+                .reject_code()
+                .unwrap_or(RejectCode::SysUnknown)
+                .into(),
+            call_rejected.reject_message().to_string(),
+        ),
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub struct OverrideRpcConfig {
     pub eth_get_block_by_number: Option<RpcConfig>,
@@ -107,10 +156,50 @@ pub struct OverrideRpcConfig {
     pub eth_get_transaction_count: Option<RpcConfig>,
     pub eth_send_raw_transaction: Option<RpcConfig>,
     pub eth_call: Option<RpcConfig>,
+    pub eth_get_code: Option<RpcConfig>,
+    /// Default for every field left unset in the per-method configs above.
+    pub global: Option<RpcConfig>,
+}
+
+impl OverrideRpcConfig {
+    /// Creates an override config where every method inherits from `global`.
+    /// Per-method configs can still be set, only their `None` fields are taken from `global`.
+    pub fn with_global_default(global: RpcConfig) -> Self {
+        Self {
+            global: Some(global),
+            ..Default::default()
+        }
+    }
+
+    fn resolve(&self, config: Option<RpcConfig>) -> Option<RpcConfig> {
+        match &self.global {
+            Some(global) => Some(merge_rpc_config(config, global)),
+            None => config,
+        }
+    }
+}
+
+/// Fills every `None` field of the per-method config from the global config.
+fn merge_rpc_config(config: Option<RpcConfig>, global: &RpcConfig) -> RpcConfig {
+    match config {
+        Some(config) => RpcConfig {
+            response_size_estimate: config
+                .response_size_estimate
+                .or(global.response_size_estimate),
+            response_consensus: config
+                .response_consensus
+                .or_else(|| global.response_consensus.clone()),
+            partial_on_error: config
+                .partial_on_error
+                .or_else(|| global.partial_on_error.clone()),
+        },
+        None => global.clone(),
+    }
 }
 
 // Clinet for making intercanister calls to evm_rpc_canister
-pub struct EvmRpcClient<L: Sink> {
+#[derive(Clone)]
+pub struct EvmRpcClient<L: Sink = NoOpSink> {
     caller_service: CallerService,
     logger: L,
     providers: RpcServices,
@@ -118,6 +207,14 @@ pub struct EvmRpcClient<L: Sink> {
     override_rpc_config: OverrideRpcConfig,
     min_attached_cycles: u128,
     max_num_retries: u32,
+    log_arguments: bool,
+}
+
+impl EvmRpcClient {
+    /// Builder of a client that does not log its calls.
+    pub fn builder_without_logger(caller_service: CallerService) -> EvmRpcClientBuilder {
+        EvmRpcClientBuilder::new(caller_service, NoOpSink)
+    }
 }
 
 impl<L: Sink> EvmRpcClient<L> {
@@ -136,40 +233,69 @@ impl<L: Sink> EvmRpcClient<L> {
                 .await
             }
             CallerService::RpcHttpOutCallClient => {
-                CandidRpcClient::new(
-                    self.providers.clone(),
-                    self.override_rpc_config.eth_call.clone(),
-                )
-                .expect("Failed to create candid client")
-                .eth_call(call_args, self.min_attached_cycles)
-                .await
+                match self.candid_rpc_client(self.override_rpc_config.eth_call.clone()) {
+                    Ok(client) => client.eth_call(call_args, self.min_attached_cycles).await,
+                    Err(error) => MultiRpcResult::Consistent(Err(error)),
+                }
             }
         }
     }
 
     pub async fn eth_get_block_by_number(&self, block: BlockTag) -> MultiRpcResult<Block> {
+        self.eth_get_block_by_number_with_cycles(block, self.min_attached_cycles)
+            .await
+    }
+
+    /// Polls the latest block and returns it only if its number is greater than `last_known_number`.
+    ///
+    /// Canisters cannot subscribe to new block headers, so callers poll instead and use this
+    /// to detect advancement without inspecting the block themselves.
+    pub async fn poll_for_new_block(
+        &self,
+        last_known_number: BlockNumber,
+        cycles: u128,
+    ) -> MultiRpcResult<Option<Block>> {
+        let result = self
+            .eth_get_block_by_number_with_cycles(BlockTag::Latest, cycles)
+            .await;
+        new_block_since(result, last_known_number)
+    }
+
+    async fn eth_get_block_by_number_with_cycles(
+        &self,
+        block: BlockTag,
+        cycles: u128,
+    ) -> MultiRpcResult<Block> {
         match self.caller_service {
             CallerService::EvmRpcCanisterClient => {
-                self.call_internal(
+                self.call_internal_with_cycles(
                     "eth_getBlockByNumber",
                     self.override_rpc_config.eth_get_block_by_number.clone(),
                     block,
+                    cycles,
                 )
                 .await
             }
             CallerService::RpcHttpOutCallClient => {
-                CandidRpcClient::new(
-                    self.providers.clone(),
-                    self.override_rpc_config.eth_get_block_by_number.clone(),
-                )
-                .expect("Failed to create candid client")
-                .eth_get_block_by_number(block, self.min_attached_cycles)
-                .await
+                match self
+                    .candid_rpc_client(self.override_rpc_config.eth_get_block_by_number.clone())
+                {
+                    Ok(client) => client.eth_get_block_by_number(block, cycles).await,
+                    Err(error) => MultiRpcResult::Consistent(Err(error)),
+                }
             }
         }
     }
 
+    /// Fetches the logs matching `args`.
+    ///
+    /// Providers rejecting a block range because it matches too many logs are retried
+    /// with smaller ranges, see [`get_logs_by_halving_range`].
     pub async fn eth_get_logs(&self, args: GetLogsArgs) -> MultiRpcResult<Vec<LogEntry>> {
+        get_logs_by_halving_range(args, |args| self.eth_get_logs_once(args)).await
+    }
+
+    async fn eth_get_logs_once(&self, args: GetLogsArgs) -> MultiRpcResult<Vec<LogEntry>> {
         match self.caller_service {
             CallerService::EvmRpcCanisterClient => {
                 self.call_internal(
@@ -180,13 +306,10 @@ impl<L: Sink> EvmRpcClient<L> {
                 .await
             }
             CallerService::RpcHttpOutCallClient => {
-                CandidRpcClient::new(
-                    self.providers.clone(),
-                    self.override_rpc_config.eth_get_logs.clone(),
-                )
-                .expect("Failed to create candid client")
-                .eth_get_logs(args, self.min_attached_cycles)
-                .await
+                match self.candid_rpc_client(self.override_rpc_config.eth_get_logs.clone()) {
+                    Ok(client) => client.eth_get_logs(args, self.min_attached_cycles).await,
+                    Err(error) => MultiRpcResult::Consistent(Err(error)),
+                }
             }
         }
     }
@@ -202,13 +325,10 @@ impl<L: Sink> EvmRpcClient<L> {
                 .await
             }
             CallerService::RpcHttpOutCallClient => {
-                CandidRpcClient::new(
-                    self.providers.clone(),
-                    self.override_rpc_config.eth_fee_history.clone(),
-                )
-                .expect("Failed to create candid client")
-                .eth_fee_history(args, self.min_attached_cycles)
-                .await
+                match self.candid_rpc_client(self.override_rpc_config.eth_fee_history.clone()) {
+                    Ok(client) => client.eth_fee_history(args, self.min_attached_cycles).await,
+                    Err(error) => MultiRpcResult::Consistent(Err(error)),
+                }
             }
         }
     }
@@ -227,16 +347,23 @@ impl<L: Sink> EvmRpcClient<L> {
                 .await
             }
             CallerService::RpcHttpOutCallClient => {
-                CandidRpcClient::new(
-                    self.providers.clone(),
-                    self.override_rpc_config.eth_get_transaction_receipt.clone(),
-                )
-                .expect("Failed to create candid client")
-                .eth_get_transaction_receipt(
-                    Hex32::from_str(&transaction_hash).unwrap(),
-                    self.min_attached_cycles,
-                )
-                .await
+                let client = match self
+                    .candid_rpc_client(self.override_rpc_config.eth_get_transaction_receipt.clone())
+                {
+                    Ok(client) => client,
+                    Err(error) => return MultiRpcResult::Consistent(Err(error)),
+                };
+                match Hex32::from_str(&transaction_hash) {
+                    Ok(transaction_hash) => {
+                        client
+                            .eth_get_transaction_receipt(transaction_hash, self.min_attached_cycles)
+                            .await
+                    }
+                    Err(error) => MultiRpcResult::Consistent(Err(ValidationError::Custom(
+                        format!("invalid transaction hash: {error}"),
+                    )
+                    .into())),
+                }
             }
         }
     }
@@ -255,13 +382,16 @@ impl<L: Sink> EvmRpcClient<L> {
                 .await
             }
             CallerService::RpcHttpOutCallClient => {
-                CandidRpcClient::new(
-                    self.providers.clone(),
-                    self.override_rpc_config.eth_get_transaction_count.clone(),
-                )
-                .expect("Failed to create candid client")
-                .eth_get_transaction_count(args, self.min_attached_cycles)
-                .await
+                match self
+                    .candid_rpc_client(self.override_rpc_config.eth_get_transaction_count.clone())
+                {
+                    Ok(client) => {
+                        client
+                            .eth_get_transaction_count(args, self.min_attached_cycles)
+                            .await
+                    }
+                    Err(error) => MultiRpcResult::Consistent(Err(error)),
+                }
             }
         }
     }
@@ -280,20 +410,65 @@ impl<L: Sink> EvmRpcClient<L> {
                 .await
             }
             CallerService::RpcHttpOutCallClient => {
-                CandidRpcClient::new(
-                    self.providers.clone(),
-                    self.override_rpc_config.eth_send_raw_transaction.clone(),
-                )
-                .expect("Failed to create candid client")
-                .eth_send_raw_transaction(
-                    Hex::from_str(&raw_signed_tx_hex).unwrap(),
-                    self.min_attached_cycles,
+                let client = match self
+                    .candid_rpc_client(self.override_rpc_config.eth_send_raw_transaction.clone())
+                {
+                    Ok(client) => client,
+                    Err(error) => return MultiRpcResult::Consistent(Err(error)),
+                };
+                match Hex::from_str(&raw_signed_tx_hex) {
+                    Ok(raw_signed_tx_hex) => {
+                        client
+                            .eth_send_raw_transaction(raw_signed_tx_hex, self.min_attached_cycles)
+                            .await
+                    }
+                    Err(error) => MultiRpcResult::Consistent(Err(ValidationError::Custom(
+                        format!("invalid raw transaction: {error}"),
+                    )
+                    .into())),
+                }
+            }
+        }
+    }
+
+    /// Returns the code deployed at the given address, which is empty for externally owned accounts.
+    ///
+    /// The EVM RPC canister does not expose `eth_getCode`, so this is only supported
+    /// with [`CallerService::RpcHttpOutCallClient`].
+    pub async fn eth_get_code(&self, args: GetCodeArgs) -> MultiRpcResult<Hex> {
+        match self.caller_service {
+            CallerService::EvmRpcCanisterClient => {
+                MultiRpcResult::Consistent(Err(ValidationError::Custom(
+                    "eth_getCode is not supported by the EVM RPC canister".to_string(),
                 )
-                .await
+                .into()))
             }
+            CallerService::RpcHttpOutCallClient => {
+                match self.candid_rpc_client(self.override_rpc_config.eth_get_code.clone()) {
+                    Ok(client) => client.eth_get_code(args, self.min_attached_cycles).await,
+                    Err(error) => MultiRpcResult::Consistent(Err(error)),
+                }
+            }
+        }
+    }
+
+    /// Arguments of a call as they appear in the logs, only if the client was built
+    /// with [`EvmRpcClientBuilder::with_log_arguments`] since they can be large.
+    fn arguments_to_log<In: Debug>(&self, args: &In) -> String {
+        if self.log_arguments {
+            format!(" with arguments '{args:?}'")
+        } else {
+            String::new()
         }
     }
 
+    fn candid_rpc_client(&self, config: Option<RpcConfig>) -> RpcResult<CandidRpcClient> {
+        CandidRpcClient::new(
+            self.providers.clone(),
+            self.override_rpc_config.resolve(config),
+        )
+    }
+
     async fn call_internal<In, Out>(
         &self,
         method: &str,
@@ -304,23 +479,63 @@ impl<L: Sink> EvmRpcClient<L> {
         In: CandidType + Send + Clone + Debug + 'static,
         Out: CandidType + DeserializeOwned + Debug + 'static,
     {
+        self.call_internal_with_cycles(method, config, args, self.min_attached_cycles)
+            .await
+    }
+
+    async fn call_internal_with_cycles<In, Out>(
+        &self,
+        method: &str,
+        config: Option<RpcConfig>,
+        args: In,
+        min_attached_cycles: u128,
+    ) -> MultiRpcResult<Out>
+    where
+        In: CandidType + Send + Clone + Debug + 'static,
+        Out: CandidType + DeserializeOwned + Debug + 'static,
+    {
+        self.call_with_retries(
+            &EvmRpcCanisterClinet {},
+            method,
+            config,
+            args,
+            min_attached_cycles,
+        )
+        .await
+    }
+
+    /// Calls `method` of the EVM RPC canister, doubling the attached cycles on every
+    /// `TooFewCycles` error up to `max_num_retries` times.
+    async fn call_with_retries<R, In, Out>(
+        &self,
+        runtime: &R,
+        method: &str,
+        config: Option<RpcConfig>,
+        args: In,
+        min_attached_cycles: u128,
+    ) -> MultiRpcResult<Out>
+    where
+        R: InterCanisterCall + Sync,
+        In: CandidType + Send + Clone + Debug + 'static,
+        Out: CandidType + DeserializeOwned + Debug + 'static,
+    {
+        let config = self.override_rpc_config.resolve(config);
         let mut retries = 0;
-        let mut attached_cycles = self.min_attached_cycles;
+        let mut attached_cycles = min_attached_cycles;
 
         loop {
             log!(
                 self.logger,
-                "[{}]: Calling providers {:?} for {} with arguments '{:?}' and {} cycles (retry {})",
+                "[{}]: Calling providers {:?} for {}{} and {} cycles (retry {})",
                 self.evm_canister_id,
                 self.providers,
                 method,
-                args,
+                self.arguments_to_log(&args),
                 attached_cycles,
                 retries
             );
-            let client = EvmRpcCanisterClinet {};
 
-            let result: MultiRpcResult<Out> = client
+            let result: MultiRpcResult<Out> = runtime
                 .call(
                     self.evm_canister_id,
                     method,
@@ -345,7 +560,20 @@ impl<L: Sink> EvmRpcClient<L> {
             if let Some(expected) = max_expected_too_few_cycles_error(&result) {
                 if retries < self.max_num_retries {
                     retries += 1;
+                    let error = format!(
+                        "too few cycles, expected at least {expected} but attached {attached_cycles}"
+                    );
                     attached_cycles = attached_cycles.saturating_mul(2).max(expected);
+                    log!(
+                        self.logger,
+                        "{}",
+                        RetryAttemptLog {
+                            method,
+                            attempt: retries,
+                            cycles: attached_cycles,
+                            error,
+                        }
+                    );
                     continue;
                 } else {
                     log!(
@@ -361,6 +589,126 @@ impl<L: Sink> EvmRpcClient<L> {
     }
 }
 
+/// Log entry of a call that is retried with more cycles.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetryAttemptLog<'a> {
+    pub method: &'a str,
+    /// Number of the retry, starting at 1.
+    pub attempt: u32,
+    /// Cycles attached to the retried call.
+    pub cycles: u128,
+    pub error: String,
+}
+
+impl fmt::Display for RetryAttemptLog<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Retry attempt {} of {} with {} cycles after error: {}",
+            self.attempt, self.method, self.cycles, self.error
+        )
+    }
+}
+
+fn new_block_since(
+    result: MultiRpcResult<Block>,
+    last_known_number: BlockNumber,
+) -> MultiRpcResult<Option<Block>> {
+    result
+        .map(|block| (BlockNumber::from(block.number.clone()) > last_known_number).then_some(block))
+}
+
+/// JSON-RPC error code returned by providers when `eth_getLogs` matches too many logs.
+const TOO_MANY_LOGS_ERROR_CODE: i64 = -32005;
+
+/// Maximum number of `eth_getLogs` requests sent by [`get_logs_by_halving_range`], including
+/// the request for the whole block range. Every request is an HTTPS outcall to each provider.
+const MAX_GET_LOGS_REQUESTS: usize = 16;
+
+/// Calls `get_logs` on the block range of `args` and, as long as some provider answers that
+/// the range matches too many logs, on both halves of the range instead.
+///
+/// The logs of all sub-ranges are merged in block order and deduplicated by transaction hash
+/// and log index. The first result of a sub-range that is not consistently successful is
+/// returned as is. In particular, a sub-range that still matches too many logs once halving
+/// it would exceed [`MAX_GET_LOGS_REQUESTS`] requests is returned as an error, and the caller
+/// is expected to retry with a smaller block range.
+async fn get_logs_by_halving_range<F, Fut>(
+    args: GetLogsArgs,
+    get_logs: F,
+) -> MultiRpcResult<Vec<LogEntry>>
+where
+    F: Fn(GetLogsArgs) -> Fut,
+    Fut: std::future::Future<Output = MultiRpcResult<Vec<LogEntry>>>,
+{
+    let mut pending = vec![args];
+    let mut logs = Vec::new();
+    let mut requests = 0_usize;
+    while let Some(args) = pending.pop() {
+        let result = get_logs(args.clone()).await;
+        requests += 1;
+        // every pending range, including both halves, still needs one request
+        let within_budget = requests + pending.len() + 2 <= MAX_GET_LOGS_REQUESTS;
+        if within_budget && has_too_many_logs_error(&result) {
+            if let Some((lower, upper)) = split_block_range(&args) {
+                pending.push(upper);
+                pending.push(lower);
+                continue;
+            }
+        }
+        match result {
+            MultiRpcResult::Consistent(Ok(entries)) => logs.extend(entries),
+            result => return result,
+        }
+    }
+    MultiRpcResult::Consistent(Ok(dedup_logs(logs)))
+}
+
+fn has_too_many_logs_error<Out>(result: &MultiRpcResult<Out>) -> bool {
+    multi_rpc_result_iter(result).any(|res| {
+        matches!(
+            res,
+            Err(RpcError::JsonRpcError(JsonRpcError { code, .. })) if *code == TOO_MANY_LOGS_ERROR_CODE
+        )
+    })
+}
+
+/// Splits the block range `[from, to]` of `args` into `[from, mid]` and `[mid + 1, to]`.
+/// Returns `None` unless both bounds are block numbers and the range spans at least 2 blocks.
+fn split_block_range(args: &GetLogsArgs) -> Option<(GetLogsArgs, GetLogsArgs)> {
+    let block_number = |tag: &Option<BlockTag>| match tag {
+        Some(BlockTag::Number(number)) => Nat::from(number.clone()).0.to_u64(),
+        _ => None,
+    };
+    let from = block_number(&args.from_block)?;
+    let to = block_number(&args.to_block)?;
+    if from >= to {
+        return None;
+    }
+    let mid = from + (to - from) / 2;
+    let with_range = |from: u64, to: u64| GetLogsArgs {
+        from_block: Some(BlockTag::Number(Nat256::from(from))),
+        to_block: Some(BlockTag::Number(Nat256::from(to))),
+        ..args.clone()
+    };
+    Some((with_range(from, mid), with_range(mid + 1, to)))
+}
+
+/// Removes logs already seen with the same transaction hash and log index,
+/// keeping pending logs that have neither.
+fn dedup_logs(logs: Vec<LogEntry>) -> Vec<LogEntry> {
+    let mut seen = BTreeSet::new();
+    logs.into_iter()
+        .filter(|log| match (&log.transaction_hash, &log.log_index) {
+            (Some(hash), Some(index)) => seen.insert((
+                <[u8; 32]>::from(hash.clone()),
+                LogIndex::from(index.clone()),
+            )),
+            _ => true,
+        })
+        .collect()
+}
+
 fn max_expected_too_few_cycles_error<Out>(result: &MultiRpcResult<Out>) -> Option<u128> {
     multi_rpc_result_iter(result)
         .filter_map(|res| match res {
@@ -383,7 +731,13 @@ fn multi_rpc_result_iter<Out>(
         }
     }
 }
-pub struct EvmRpcClientBuilder<L: Sink> {
+
+/// Principal of the EVM RPC canister used unless [`EvmRpcClientBuilder::with_evm_canister_id`]
+/// is called.
+pub const DEFAULT_EVM_RPC_CANISTER_ID: &str = "sosge-5iaaa-aaaag-alcla-cai";
+
+#[derive(Clone)]
+pub struct EvmRpcClientBuilder<L: Sink = NoOpSink> {
     caller_service: CallerService,
     logger: L,
     providers: RpcServices,
@@ -391,6 +745,7 @@ pub struct EvmRpcClientBuilder<L: Sink> {
     override_rpc_config: OverrideRpcConfig,
     min_attached_cycles: u128,
     max_num_retries: u32,
+    log_arguments: bool,
 }
 
 impl<L: Sink> EvmRpcClientBuilder<L> {
@@ -403,10 +758,11 @@ impl<L: Sink> EvmRpcClientBuilder<L> {
             caller_service,
             logger,
             providers: DEFAULT_PROVIDERS,
-            evm_canister_id: Principal::from_text("sosge-5iaaa-aaaag-alcla-cai").unwrap(),
+            evm_canister_id: Principal::from_text(DEFAULT_EVM_RPC_CANISTER_ID).unwrap(),
             override_rpc_config: Default::default(),
             min_attached_cycles: DEFAULT_MIN_ATTACHED_CYCLES,
             max_num_retries: DEFAULT_MAX_NUM_RETRIES,
+            log_arguments: false,
         }
     }
 
@@ -450,8 +806,37 @@ impl<L: Sink> EvmRpcClientBuilder<L> {
         self
     }
 
+    /// Whether to log the full arguments of every call, which is disabled by default
+    /// since arguments such as raw transactions can be large.
+    pub fn with_log_arguments(mut self, log_arguments: bool) -> Self {
+        self.log_arguments = log_arguments;
+        self
+    }
+
     pub fn build(self) -> EvmRpcClient<L> {
-        EvmRpcClient {
+        self.build_verbose().0
+    }
+
+    /// Builds the client together with a report of its effective settings and of the
+    /// settings that are likely mistakes.
+    pub fn build_verbose(self) -> (EvmRpcClient<L>, BuildReport) {
+        let mut warnings = vec![];
+        if self.min_attached_cycles < 1_000_000_000 {
+            warnings
+                .push("min_attached_cycles is below 1B (may cause frequent retries)".to_string());
+        }
+        if self.max_num_retries == 0 {
+            warnings.push("max_num_retries is 0 (errors will not be retried)".to_string());
+        }
+        if self.caller_service == CallerService::EvmRpcCanisterClient
+            && self.evm_canister_id == Principal::from_text(DEFAULT_EVM_RPC_CANISTER_ID).unwrap()
+        {
+            warnings.push(
+                "evm_canister_id is the default (did you forget to call with_evm_canister_id?)"
+                    .to_string(),
+            );
+        }
+        let client = EvmRpcClient {
             caller_service: self.caller_service,
             logger: self.logger,
             providers: self.providers,
@@ -459,6 +844,41 @@ impl<L: Sink> EvmRpcClientBuilder<L> {
             override_rpc_config: self.override_rpc_config,
             min_attached_cycles: self.min_attached_cycles,
             max_num_retries: self.max_num_retries,
+            log_arguments: self.log_arguments,
+        };
+        let report = BuildReport {
+            selected_providers: selected_providers(&client.providers),
+            effective_min_cycles: client.min_attached_cycles,
+            effective_max_retries: client.max_num_retries,
+            warnings,
+        };
+        (client, report)
+    }
+}
+
+/// Effective settings of a client returned by [`EvmRpcClientBuilder::build_verbose`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildReport {
+    /// Providers explicitly selected. Empty when the EVM RPC canister picks its default
+    /// providers of the chain.
+    pub selected_providers: Vec<RpcService>,
+    pub effective_min_cycles: u128,
+    pub effective_max_retries: u32,
+    pub warnings: Vec<String>,
+}
+
+fn selected_providers(providers: &RpcServices) -> Vec<RpcService> {
+    fn each<T: Clone>(services: &Option<Vec<T>>, f: fn(T) -> RpcService) -> Vec<RpcService> {
+        services.iter().flatten().cloned().map(f).collect()
+    }
+    match providers {
+        RpcServices::Custom { services, .. } => {
+            services.iter().cloned().map(RpcService::Custom).collect()
         }
+        RpcServices::EthMainnet(services) => each(services, RpcService::EthMainnet),
+        RpcServices::EthSepolia(services) => each(services, RpcService::EthSepolia),
+        RpcServices::ArbitrumOne(services) => each(services, RpcService::ArbitrumOne),
+        RpcServices::BaseMainnet(services) => each(services, RpcService::BaseMainnet),
+        RpcServices::OptimismMainnet(services) => each(services, RpcService::OptimismMainnet),
     }
 }
@@ -15,16 +15,25 @@ pub const INFO: PrintProxySink = PrintProxySink("INFO", &INFO_BUF);
 pub const DEBUG: PrintProxySink = PrintProxySink("DEBUG", &DEBUG_BUF);
 pub const TRACE_HTTP: PrintProxySink = PrintProxySink("TRACE_HTTP", &TRACE_HTTP_BUF);
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct PrintProxySink(&'static str, &'static GlobalBuffer);
 
 impl Sink for PrintProxySink {
     fn append(&self, entry: ic_canister_log::LogEntry) {
+        #[cfg(feature = "canister")]
         ic_cdk::println!("{} {}:{} {}", self.0, entry.file, entry.line, entry.message);
         self.1.append(entry)
     }
 }
 
+/// Sink discarding every entry, used by clients built without a logger.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoOpSink;
+
+impl Sink for NoOpSink {
+    fn append(&self, _entry: ic_canister_log::LogEntry) {}
+}
+
 #[derive(Clone, serde::Serialize, Deserialize, Debug, Copy)]
 pub enum Priority {
     Info,
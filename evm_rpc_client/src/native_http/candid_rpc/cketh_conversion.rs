@@ -109,6 +109,15 @@ pub(super) fn into_get_transaction_count_params(
     }
 }
 
+pub(super) fn into_get_code_params(
+    value: evm_rpc_types::GetCodeArgs,
+) -> crate::native_http::json::requests::GetCodeParams {
+    crate::native_http::json::requests::GetCodeParams {
+        address: Address::new(value.address.into()),
+        block: into_block_spec(value.block),
+    }
+}
+
 pub(super) fn from_transaction_receipt(
     value: crate::native_http::json::responses::TransactionReceipt,
 ) -> evm_rpc_types::TransactionReceipt {
@@ -8,6 +8,9 @@ use ethers_core::{types::Transaction, utils::rlp};
 
 pub mod cketh_conversion;
 
+#[cfg(test)]
+mod tests;
+
 fn process_result<T>(result: Result<T, MultiCallError<T>>) -> MultiRpcResult<T> {
     match result {
         Ok(value) => MultiRpcResult::Consistent(Ok(value)),
@@ -160,6 +163,20 @@ impl CandidRpcClient {
         )
         .map(from_data)
     }
+
+    pub async fn eth_get_code(
+        &self,
+        args: evm_rpc_types::GetCodeArgs,
+        cycles_available: u128,
+    ) -> MultiRpcResult<evm_rpc_types::Hex> {
+        use crate::native_http::candid_rpc::cketh_conversion::{from_data, into_get_code_params};
+        process_result(
+            self.client
+                .eth_get_code(into_get_code_params(args), cycles_available)
+                .await,
+        )
+        .map(from_data)
+    }
 }
 
 fn get_transaction_hash(raw_signed_transaction_hex: &Hex) -> Option<Hex32> {
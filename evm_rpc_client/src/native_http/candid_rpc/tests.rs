@@ -0,0 +1,30 @@
+mod get_transaction_count {
+    use crate::evm_rpc_types::{BlockTag, GetTransactionCountArgs, Hex20, Nat256};
+    use crate::native_http::candid_rpc::cketh_conversion::into_get_transaction_count_params;
+    use serde_json::json;
+
+    const ADDRESS: [u8; 20] = [0xb4; 20];
+
+    #[test]
+    fn should_serialize_every_block_tag() {
+        for (block, expected_block) in [
+            (BlockTag::Latest, json!("latest")),
+            (BlockTag::Finalized, json!("finalized")),
+            (BlockTag::Safe, json!("safe")),
+            (BlockTag::Earliest, json!("earliest")),
+            (BlockTag::Pending, json!("pending")),
+            (BlockTag::Number(Nat256::from(0x1b4_u64)), json!("0x1b4")),
+        ] {
+            let params = into_get_transaction_count_params(GetTransactionCountArgs {
+                address: Hex20::from(ADDRESS),
+                block: block.clone(),
+            });
+
+            assert_eq!(
+                serde_json::to_value(params).unwrap(),
+                json!(["0xb4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4", expected_block]),
+                "unexpected serialization for {block:?}"
+            );
+        }
+    }
+}
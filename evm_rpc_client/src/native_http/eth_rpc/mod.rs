@@ -10,6 +10,8 @@ use crate::native_http::json::requests::JsonRpcRequest;
 use crate::native_http::json::responses::{
     Block, FeeHistory, JsonRpcReply, JsonRpcResult, LogEntry, TransactionReceipt,
 };
+use crate::native_http::rate_limit;
+use crate::native_http::util::time_nanos;
 use crate::numeric::{TransactionCount, Wei};
 
 use candid::candid_method;
@@ -18,11 +20,12 @@ use ic_cdk::management_canister::{
     transform_context_from_query, HttpHeader, HttpMethod, HttpRequestResult, TransformArgs,
 };
 
-use ic_cdk::query;
 use minicbor::{Decode, Encode};
 use serde::{de::DeserializeOwned, Serialize};
 use std::fmt;
 use std::fmt::Debug;
+use std::future::Future;
+use std::time::Duration;
 
 #[cfg(test)]
 mod tests;
@@ -40,6 +43,26 @@ const HTTP_MAX_SIZE: u64 = 2_000_000;
 
 pub const MAX_PAYLOAD_SIZE: u64 = HTTP_MAX_SIZE - HEADER_SIZE_LIMIT;
 
+const PAYLOAD_TOO_LARGE: u16 = 413;
+const TOO_MANY_REQUESTS: u16 = 429;
+const RETRY_AFTER_HEADER_LOWERCASE: &str = "retry-after";
+
+/// Maximum number of times a request rate limited by the provider is sent again.
+pub const MAX_RATE_LIMITED_RETRIES: u32 = 3;
+
+/// Delay before the first retry of a rate limited request if the provider
+/// did not specify one, doubled for every subsequent retry.
+pub const INITIAL_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Delay during which a provider that is still rate limiting requests after
+/// [`MAX_RATE_LIMITED_RETRIES`] retries is skipped, if it did not specify one.
+pub const DEFAULT_RATE_LIMIT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Longest delay waited for before retrying a rate limited request. A provider requesting
+/// a longer delay is skipped for that delay instead, so that the call does not stay
+/// suspended meanwhile.
+pub const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(5);
+
 /// Describes a payload transformation to execute before passing the HTTP response to consensus.
 /// The purpose of these transformations is to ensure that the response encoding is deterministic
 /// (the field order is the same).
@@ -102,10 +125,18 @@ impl ResponseTransform {
     }
 }
 
-#[query]
+#[cfg_attr(feature = "canister", ic_cdk::query)]
+#[cfg_attr(not(feature = "canister"), allow(dead_code))]
 #[candid_method(query)]
 fn cleanup_response(mut args: TransformArgs) -> HttpRequestResult {
-    args.response.headers.clear();
+    // The delay requested by a rate limiting provider is the only header used by `call`.
+    if http_status_code(&args.response) == TOO_MANY_REQUESTS {
+        args.response
+            .headers
+            .retain(|header| header.name.to_lowercase() == RETRY_AFTER_HEADER_LOWERCASE);
+    } else {
+        args.response.headers.clear();
+    }
     let status_ok = args.response.status >= 200u16 && args.response.status < 300u16;
     if status_ok && !args.context.is_empty() {
         let maybe_transform: Result<ResponseTransform, _> = minicbor::decode(&args.context[..]);
@@ -181,13 +212,7 @@ where
     };
     let api = resolve_api(provider.clone())?;
     let url = &api.url;
-    let mut headers = vec![HttpHeader {
-        name: "Content-Type".to_string(),
-        value: "application/json".to_string(),
-    }];
-    if let Some(vec) = api.headers {
-        headers.extend(vec);
-    }
+    let headers = request_headers(&api);
     let mut retries = 0;
     loop {
         let payload = serde_json::to_string(&rpc_request).unwrap();
@@ -219,8 +244,27 @@ where
             is_replicated: Some(false),
         };
 
-        let response = match http_request(request, effective_size_estimate, cycles_available).await
+        let response = match retry_on_too_many_requests(
+            || http_request(request.clone(), effective_size_estimate, cycles_available),
+            rate_limit::sleep,
+            time_nanos,
+        )
+        .await
         {
+            Ok((response, None)) => response,
+            Ok((_response, Some(retry_after))) => {
+                log!(
+                    DEBUG,
+                    "The {eth_method} request is rate limited, skipping provider for {retry_after:?}"
+                );
+                rate_limit::record_rate_limited(
+                    provider,
+                    time_nanos().saturating_add(retry_after.as_nanos() as u64),
+                );
+                return Err(RpcError::RateLimited {
+                    retry_after_secs: retry_after.as_secs(),
+                });
+            }
             Err(RpcError::HttpOutcallError(HttpOutcallError::IcError { code, message }))
                 if is_response_too_large(&message) =>
             {
@@ -249,6 +293,15 @@ where
         // even if the contained JsonRpcResult is an error.
         // If the server is not available, it will sometimes (wrongly) return HTML that will fail parsing as JSON.
         let http_status_code = http_status_code(&response);
+        if http_status_code == PAYLOAD_TOO_LARGE {
+            let new_estimate = response_size_estimate.adjust();
+            if response_size_estimate != new_estimate {
+                log!(DEBUG, "The {eth_method} response was rejected as too large for {response_size_estimate} bytes, retrying with {new_estimate}");
+                response_size_estimate = new_estimate;
+                retries += 1;
+                continue;
+            }
+        }
         if !is_successful_http_code(&http_status_code) {
             return Err(HttpOutcallError::InvalidHttpJsonRpcResponse {
                 status: http_status_code,
@@ -270,6 +323,119 @@ where
     }
 }
 
+/// Sends the request with `send`, sending it again up to [`MAX_RATE_LIMITED_RETRIES`] times
+/// while the provider answers with HTTP 429 Too Many Requests.
+///
+/// Before each retry, waits for the delay given by the `Retry-After` header of the response
+/// or, if there is none, for an exponential backoff starting at [`INITIAL_RATE_LIMIT_BACKOFF`].
+/// If the last response is still rate limited, or if the provider requests a delay longer
+/// than [`MAX_RATE_LIMIT_WAIT`], the response is returned together with the delay after
+/// which the provider may be queried again, [`DEFAULT_RATE_LIMIT_COOLDOWN`] if the provider
+/// did not specify one. `now` returns the current time in nanoseconds since the Unix epoch.
+pub async fn retry_on_too_many_requests<F, Fut, S, SleepFut, N>(
+    mut send: F,
+    sleep: S,
+    now: N,
+) -> Result<(HttpRequestResult, Option<Duration>), RpcError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<HttpRequestResult, RpcError>>,
+    S: Fn(Duration) -> SleepFut,
+    SleepFut: Future<Output = ()>,
+    N: Fn() -> u64,
+{
+    let mut retries = 0;
+    loop {
+        let response = send().await?;
+        if http_status_code(&response) != TOO_MANY_REQUESTS {
+            return Ok((response, None));
+        }
+        let requested_delay = retry_after(&response, now());
+        if retries >= MAX_RATE_LIMITED_RETRIES {
+            return Ok((
+                response,
+                Some(requested_delay.unwrap_or(DEFAULT_RATE_LIMIT_COOLDOWN)),
+            ));
+        }
+        let delay = requested_delay
+            .unwrap_or_else(|| INITIAL_RATE_LIMIT_BACKOFF.saturating_mul(1_u32 << retries));
+        if delay > MAX_RATE_LIMIT_WAIT {
+            return Ok((response, Some(delay)));
+        }
+        sleep(delay).await;
+        retries += 1;
+    }
+}
+
+/// Parses the `Retry-After` header of a response, given either as a delay in seconds or as
+/// an HTTP date, which is converted to a delay from `now_ns`.
+fn retry_after(response: &HttpRequestResult, now_ns: u64) -> Option<Duration> {
+    let value = response
+        .headers
+        .iter()
+        .find(|header| header.name.to_lowercase() == RETRY_AFTER_HEADER_LOWERCASE)?
+        .value
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let retry_at_secs = parse_http_date(value)?;
+    Some(Duration::from_secs(
+        retry_at_secs.saturating_sub(now_ns / 1_000_000_000),
+    ))
+}
+
+/// Parses an HTTP date in the preferred IMF-fixdate format, e.g.
+/// `Wed, 21 Oct 2015 07:28:00 GMT`, into seconds since the Unix epoch.
+fn parse_http_date(date: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (_weekday, date) = date.split_once(", ")?;
+    let parts: Vec<&str> = date.split(' ').collect();
+    let [day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    let day: u64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| m == month)? as u64 + 1;
+    let year: u64 = year.parse().ok()?;
+    let time: Vec<u64> = time
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let [hours, minutes, seconds] = time.as_slice() else {
+        return None;
+    };
+    if year < 1970 || !(1..=31).contains(&day) || *hours > 23 || *minutes > 59 || *seconds > 60 {
+        return None;
+    }
+    // Number of days since 1970-01-01 in the proleptic Gregorian calendar,
+    // see http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146_097 + day_of_era).checked_sub(719_468)?;
+    Some(days * 86_400 + hours * 3_600 + minutes * 60 + seconds)
+}
+
+/// Headers of a JSON-RPC request to `api`, including the custom headers of the provider.
+pub fn request_headers(api: &RpcApi) -> Vec<HttpHeader> {
+    let mut headers = vec![HttpHeader {
+        name: "Content-Type".to_string(),
+        value: "application/json".to_string(),
+    }];
+    if let Some(api_headers) = &api.headers {
+        headers.extend(api_headers.iter().cloned());
+    }
+    headers
+}
+
 pub fn resolve_api(service: RpcService) -> Result<RpcApi, ProviderError> {
     match service {
         RpcService::Custom(rpc_api) => Ok(rpc_api),
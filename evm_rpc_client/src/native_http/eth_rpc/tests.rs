@@ -405,3 +405,221 @@ fn transaction_receipt_normalization() {
     }"#,
     );
 }
+
+mod retry_on_too_many_requests {
+    use super::*;
+    use candid::Nat;
+    use ic_cdk::management_canister::HttpHeader;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    // Wed, 21 Oct 2015 07:28:00 GMT
+    const NOW_NS: u64 = 1_445_412_480_000_000_000;
+
+    fn response(status: u16, headers: Vec<HttpHeader>) -> HttpRequestResult {
+        HttpRequestResult {
+            status: Nat::from(status),
+            headers,
+            body: br#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#.to_vec(),
+        }
+    }
+
+    fn retry_after(seconds: &str) -> Vec<HttpHeader> {
+        vec![HttpHeader {
+            name: "Retry-After".to_string(),
+            value: seconds.to_string(),
+        }]
+    }
+
+    fn send_all(
+        responses: Vec<HttpRequestResult>,
+    ) -> (
+        Result<(HttpRequestResult, Option<Duration>), RpcError>,
+        Vec<Duration>,
+        usize,
+    ) {
+        let responses = RefCell::new(VecDeque::from(responses));
+        let sleeps = RefCell::new(vec![]);
+        let result = futures::executor::block_on(retry_on_too_many_requests(
+            || {
+                let response = responses
+                    .borrow_mut()
+                    .pop_front()
+                    .expect("BUG: unexpected request");
+                async move { Ok(response) }
+            },
+            |delay| {
+                sleeps.borrow_mut().push(delay);
+                async {}
+            },
+            || NOW_NS,
+        ));
+        (result, sleeps.into_inner(), responses.into_inner().len())
+    }
+
+    #[test]
+    fn should_retry_after_delay_requested_by_provider() {
+        let (result, sleeps, remaining) =
+            send_all(vec![response(429, retry_after("2")), response(200, vec![])]);
+
+        assert_eq!(result, Ok((response(200, vec![]), None)));
+        assert_eq!(sleeps, vec![Duration::from_secs(2)]);
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn should_back_off_exponentially_and_give_up() {
+        let (result, sleeps, remaining) = send_all(vec![
+            response(429, vec![]),
+            response(429, vec![]),
+            response(429, retry_after("not a delay")),
+            response(429, vec![]),
+            response(200, vec![]),
+        ]);
+
+        assert_eq!(
+            result,
+            Ok((response(429, vec![]), Some(DEFAULT_RATE_LIMIT_COOLDOWN)))
+        );
+        assert_eq!(
+            sleeps,
+            vec![
+                Duration::from_secs(1),
+                Duration::from_secs(2),
+                Duration::from_secs(4)
+            ]
+        );
+        assert_eq!(remaining, 1);
+    }
+
+    #[test]
+    fn should_retry_at_date_requested_by_provider() {
+        let (result, sleeps, remaining) = send_all(vec![
+            response(429, retry_after("Wed, 21 Oct 2015 07:28:05 GMT")),
+            response(200, vec![]),
+        ]);
+
+        assert_eq!(result, Ok((response(200, vec![]), None)));
+        assert_eq!(sleeps, vec![Duration::from_secs(5)]);
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn should_skip_provider_for_delay_requested_in_last_response() {
+        let (result, _sleeps, remaining) = send_all(vec![
+            response(429, vec![]),
+            response(429, vec![]),
+            response(429, vec![]),
+            response(429, retry_after("120")),
+        ]);
+
+        assert_eq!(
+            result,
+            Ok((
+                response(429, retry_after("120")),
+                Some(Duration::from_secs(120))
+            ))
+        );
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn should_skip_provider_without_waiting_for_long_delay() {
+        for delay in ["6", "120", "Wed, 21 Oct 2015 08:28:00 GMT"] {
+            let (result, sleeps, remaining) = send_all(vec![
+                response(429, retry_after(delay)),
+                response(200, vec![]),
+            ]);
+
+            let (last_response, cooldown) = result.unwrap();
+            assert_eq!(last_response, response(429, retry_after(delay)));
+            assert!(cooldown.unwrap() > MAX_RATE_LIMIT_WAIT);
+            assert_eq!(sleeps, vec![]);
+            assert_eq!(remaining, 1);
+        }
+    }
+
+    #[test]
+    fn should_parse_retry_after_header() {
+        let parse =
+            |headers| crate::native_http::eth_rpc::retry_after(&response(429, headers), NOW_NS);
+
+        assert_eq!(parse(retry_after("30")), Some(Duration::from_secs(30)));
+        assert_eq!(parse(retry_after(" 0 ")), Some(Duration::ZERO));
+        assert_eq!(
+            parse(retry_after("Wed, 21 Oct 2015 07:29:00 GMT")),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            parse(retry_after("Thu, 22 Oct 2015 07:28:00 GMT")),
+            Some(Duration::from_secs(86_400))
+        );
+        // a date in the past allows retrying immediately
+        assert_eq!(
+            parse(retry_after("Tue, 20 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            parse(vec![HttpHeader {
+                name: "retry-after".to_string(),
+                value: "2".to_string(),
+            }]),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(parse(retry_after("soon")), None);
+        assert_eq!(parse(retry_after("-1")), None);
+        assert_eq!(parse(retry_after("2015-10-21T07:29:00Z")), None);
+        assert_eq!(parse(vec![]), None);
+    }
+
+    #[test]
+    fn should_parse_http_date() {
+        use crate::native_http::eth_rpc::parse_http_date;
+
+        assert_eq!(parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(
+            parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(1_445_412_480)
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 12:00:00 GMT"),
+            Some(1_709_208_000)
+        );
+        assert_eq!(parse_http_date("Wed, 21 Oct 2015 07:28:00 UTC"), None);
+        assert_eq!(parse_http_date("Wed, 21 Foo 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_http_date("Wed, 21 Oct 2015 24:00:00 GMT"), None);
+        assert_eq!(parse_http_date("Wed, 21 Oct 2015 07:28 GMT"), None);
+        assert_eq!(parse_http_date("Wednesday, 21-Oct-15 07:28:00 GMT"), None);
+    }
+
+    #[test]
+    fn should_not_retry_other_errors() {
+        let (result, sleeps, remaining) =
+            send_all(vec![response(503, retry_after("2")), response(200, vec![])]);
+
+        assert_eq!(result, Ok((response(503, retry_after("2")), None)));
+        assert!(sleeps.is_empty());
+        assert_eq!(remaining, 1);
+    }
+
+    #[test]
+    fn should_keep_only_retry_after_header_of_rate_limited_response() {
+        let mut headers = retry_after("2");
+        headers.push(HttpHeader {
+            name: "Date".to_string(),
+            value: "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+        });
+        let cleanup = |response| {
+            cleanup_response(TransformArgs {
+                response,
+                context: vec![],
+            })
+        };
+
+        assert_eq!(
+            cleanup(response(429, headers.clone())),
+            response(429, retry_after("2"))
+        );
+        assert_eq!(cleanup(response(200, headers)), response(200, vec![]));
+    }
+}
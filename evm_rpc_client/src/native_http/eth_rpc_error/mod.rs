@@ -51,6 +51,37 @@ impl<T> From<SendRawTransactionError> for JsonRpcResult<T> {
     }
 }
 
+/// JSON-RPC errors returned by providers that refuse to return all the logs matching
+/// an `eth_getLogs` request, as `(code, message)` where `None` matches any code and the
+/// message matches if the lowercased error message contains it.
+pub const GET_LOGS_RESULT_SET_TOO_LARGE_ERRORS: &[(Option<i64>, &str)] = &[
+    // Ankr: "query returned more than 10000 results"
+    (None, "query returned more than"),
+    // Alchemy: "Log response size exceeded. You can make eth_getLogs requests with up to a
+    // 2K block range and no limit on the response size, or you can request any block range
+    // with a cap of 10K logs in the response."
+    (Some(-32_602), "log response size exceeded"),
+    // LlamaNodes: "query exceeds max results 20000, retry with the range 100-200"
+    (None, "exceeds max results"),
+    // dRPC: "response size should not greater than 10000000 bytes"
+    (None, "response size should not greater than"),
+    // Generic variants of the above, e.g. "response too large" or "too many logs"
+    (None, "response too large"),
+    (None, "too many logs"),
+];
+
+/// Returns true if the JSON-RPC error of an `eth_getLogs` request indicates that
+/// the response was too large and the block range should be reduced.
+pub fn is_result_set_too_large(code: i64, message: &str) -> bool {
+    let message = message.to_lowercase();
+    GET_LOGS_RESULT_SET_TOO_LARGE_ERRORS
+        .iter()
+        .any(|(expected_code, expected_message)| {
+            expected_code.is_none_or(|expected_code| expected_code == code)
+                && message.contains(expected_message)
+        })
+}
+
 pub trait ErrorParser {
     fn try_parse_send_raw_transaction_error(
         &self,
@@ -1,4 +1,6 @@
-use crate::native_http::eth_rpc_error::{sanitize_send_raw_transaction_result, Parser};
+use crate::native_http::eth_rpc_error::{
+    is_result_set_too_large, sanitize_send_raw_transaction_result, Parser,
+};
 
 #[test]
 fn should_sanitize_ok_response() {
@@ -45,6 +47,42 @@ fn should_not_modify_response_when_deserialization_fails() {
     check_sanitize_send_raw_transaction_result(&mut raw_response, unmodified_response);
 }
 
+#[test]
+fn should_classify_result_set_too_large_errors() {
+    for (code, message) in [
+        (-32_005, "query returned more than 10000 results"),
+        (
+            -32_602,
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range and no limit on the response size, or you can request any block range with a cap of 10K logs in the response.",
+        ),
+        (-32_000, "query exceeds max results 20000, retry with the range 100-200"),
+        (35, "response size should not greater than 10000000 bytes"),
+        (-32_000, "Response too large"),
+        (-32_603, "too many logs"),
+    ] {
+        assert!(
+            is_result_set_too_large(code, message),
+            "expected ({code}, {message}) to be classified as too large"
+        );
+    }
+}
+
+#[test]
+fn should_not_classify_other_errors_as_result_set_too_large() {
+    for (code, message) in [
+        (-32_000, "header not found"),
+        (-32_602, "invalid block range params"),
+        (-32_000, "nonce too low"),
+        // Alchemy only uses this message with the invalid params code
+        (-32_000, "Log response size exceeded."),
+    ] {
+        assert!(
+            !is_result_set_too_large(code, message),
+            "expected ({code}, {message}) not to be classified as too large"
+        );
+    }
+}
+
 fn check_sanitize_send_raw_transaction_result<T: AsRef<[u8]>>(
     raw_response: &mut Vec<u8>,
     expected: T,
@@ -1,9 +1,11 @@
 use crate::evm_rpc_types::{HttpOutcallError, ProviderError, RpcError, RpcResult};
 
+#[cfg(feature = "canister")]
+use crate::native_http::http_request::unreplicated_http_request;
 use crate::native_http::{
     accounting::{get_cost_with_collateral, get_http_request_cost},
     constants::{CONTENT_TYPE_HEADER_LOWERCASE, CONTENT_TYPE_VALUE},
-    http_request::{unreplicated_http_request, IcHttpRequest},
+    http_request::IcHttpRequest,
     util::canonicalize_json,
 };
 use crate::{RejectionCode, RpcApi};
@@ -11,7 +13,6 @@ use ic_cdk::management_canister::{
     transform_context_from_query, HttpHeader, HttpMethod, HttpRequestResult, TransformArgs,
 };
 
-use ic_cdk::query;
 use num_traits::ToPrimitive;
 
 pub async fn json_rpc_request(
@@ -60,27 +61,43 @@ pub async fn http_request(
         }
         .into());
     }
-    match unreplicated_http_request(request, cycles_cost).await {
-        Ok(response) => Ok(response),
-        Err(err) => {
-            let (code, message) = match err {
-                ic_cdk::call::Error::InsufficientLiquidCycleBalance(error) => {
-                    (RejectionCode::CanisterError, error.to_string())
-                }
-                ic_cdk::call::Error::CallPerformFailed(error) => {
-                    (RejectionCode::CanisterReject, error.to_string())
-                }
-                ic_cdk::call::Error::CallRejected(error) => {
-                    (RejectionCode::CanisterReject, error.to_string())
-                }
-                ic_cdk::call::Error::CandidDecodeFailed(error) => {
-                    (RejectionCode::CanisterError, error.to_string())
-                }
-            };
+    perform_http_request(request, cycles_cost)
+        .await
+        .map_err(|(code, message)| HttpOutcallError::IcError { code, message }.into())
+}
 
-            Err(HttpOutcallError::IcError { code, message }.into())
-        }
-    }
+#[cfg(feature = "canister")]
+async fn perform_http_request(
+    request: IcHttpRequest,
+    cycles_cost: u128,
+) -> Result<HttpRequestResult, (RejectionCode, String)> {
+    unreplicated_http_request(request, cycles_cost)
+        .await
+        .map_err(|err| match err {
+            ic_cdk::call::Error::InsufficientLiquidCycleBalance(error) => {
+                (RejectionCode::CanisterError, error.to_string())
+            }
+            ic_cdk::call::Error::CallPerformFailed(error) => {
+                (RejectionCode::CanisterReject, error.to_string())
+            }
+            ic_cdk::call::Error::CallRejected(error) => {
+                (RejectionCode::CanisterReject, error.to_string())
+            }
+            ic_cdk::call::Error::CandidDecodeFailed(error) => {
+                (RejectionCode::CanisterError, error.to_string())
+            }
+        })
+}
+
+#[cfg(not(feature = "canister"))]
+async fn perform_http_request(
+    _request: IcHttpRequest,
+    _cycles_cost: u128,
+) -> Result<HttpRequestResult, (RejectionCode, String)> {
+    Err((
+        RejectionCode::SysFatal,
+        crate::CANISTER_FEATURE_REQUIRED.to_string(),
+    ))
 }
 
 pub fn transform_http_request(args: TransformArgs) -> HttpRequestResult {
@@ -107,7 +124,8 @@ pub fn get_http_response_body(response: HttpRequestResult) -> Result<String, Rpc
     })
 }
 
-#[query(name = "__transform_json_rpc", hidden = true)]
+#[cfg(feature = "canister")]
+#[ic_cdk::query(name = "__transform_json_rpc", hidden = true)]
 fn transform(args: TransformArgs) -> HttpRequestResult {
     transform_http_request(args)
 }
@@ -1,4 +1,5 @@
-use candid::{CandidType, Principal};
+use candid::CandidType;
+#[cfg(feature = "canister")]
 use ic_cdk::call::{Call, CallResult};
 //use ic_cdk::api::call::{call_with_payment128, CallResult};
 use ic_cdk::management_canister::{HttpHeader, HttpMethod, HttpRequestResult, TransformContext};
@@ -33,12 +34,13 @@ pub struct IcHttpRequest {
 ///
 /// This call requires cycles payment. The required cycles is a function of the request size and max_response_bytes.
 /// Check [Gas and cycles cost](https://internetcomputer.org/docs/current/developer-docs/gas-cost) for more details.
+#[cfg(feature = "canister")]
 pub async fn unreplicated_http_request(
     arg: IcHttpRequest,
     cycles: u128,
 ) -> CallResult<HttpRequestResult> {
     Ok(
-        Call::unbounded_wait(Principal::management_canister(), "http_request")
+        Call::unbounded_wait(candid::Principal::management_canister(), "http_request")
             .with_arg(arg)
             .with_cycles(cycles)
             .await?
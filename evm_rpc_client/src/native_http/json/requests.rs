@@ -24,6 +24,22 @@ impl From<GetTransactionCountParams> for (Address, BlockSpec) {
     }
 }
 
+/// Parameters of the [`eth_getCode`](https://ethereum.org/en/developers/docs/apis/json-rpc/#eth_getcode) call.
+#[derive(Debug, Serialize, Clone)]
+#[serde(into = "(Address, BlockSpec)")]
+pub struct GetCodeParams {
+    /// The address whose code is requested.
+    pub address: Address,
+    /// Integer block number, or "latest" for the last mined block or "pending", "earliest" for not yet mined transactions.
+    pub block: BlockSpec,
+}
+
+impl From<GetCodeParams> for (Address, BlockSpec) {
+    fn from(params: GetCodeParams) -> Self {
+        (params.address, params.block)
+    }
+}
+
 /// Parameters of the [`eth_getLogs`](https://ethereum.org/en/developers/docs/apis/json-rpc/#eth_getlogs) call.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetLogsParam {
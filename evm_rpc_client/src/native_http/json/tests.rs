@@ -1,5 +1,7 @@
-use crate::native_http::json::requests::TransactionRequest;
+use crate::eth_types::Address;
+use crate::native_http::json::requests::{BlockSpec, BlockTag, GetCodeParams, TransactionRequest};
 use serde_json::json;
+use std::str::FromStr;
 
 #[test]
 fn should_serialize_transaction_request_with_access_list() {
@@ -98,3 +100,16 @@ fn should_serialize_transaction_request_with_access_list() {
 
     assert_eq!(expected_transaction_request, actual_transaction_request);
 }
+
+#[test]
+fn should_serialize_get_code_params() {
+    let params = GetCodeParams {
+        address: Address::from_str("0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34").unwrap(),
+        block: BlockSpec::Tag(BlockTag::Latest),
+    };
+
+    assert_eq!(
+        serde_json::to_value(params).unwrap(),
+        json!(["0xb44b5e756a894775fc32eddf3314bb1b1944dc34", "latest"])
+    );
+}
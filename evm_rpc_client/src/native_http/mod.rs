@@ -1,14 +1,16 @@
 //use crate::logs::{DEBUG, INFO};
 use crate::evm_rpc_types::{
-    ConsensusStrategy, ProviderError, RpcConfig, RpcError, RpcResult, RpcService, RpcServices,
+    ConsensusStrategy, PartialOnError, ProviderError, RpcConfig, RpcError, RpcResult, RpcService,
+    RpcServices,
 };
 use crate::logs::{DEBUG, INFO};
 use crate::numeric::TransactionCount;
 use eth_rpc::{HttpRequestResultPayload, ResponseSizeEstimate, HEADER_SIZE_LIMIT};
+use eth_rpc_error::is_result_set_too_large;
 use ic_canister_log::log;
 use json::requests::{
-    BlockSpec, EthCallParams, FeeHistoryParams, GetBlockByNumberParams, GetLogsParam,
-    GetTransactionCountParams,
+    BlockSpec, EthCallParams, FeeHistoryParams, GetBlockByNumberParams, GetCodeParams,
+    GetLogsParam, GetTransactionCountParams,
 };
 use json::responses::{
     Block, Data, FeeHistory, LogEntry, SendRawTransactionResult, TransactionReceipt,
@@ -16,9 +18,11 @@ use json::responses::{
 use json::Hash;
 use serde::{de::DeserializeOwned, Serialize};
 use std::fmt::Debug;
+use std::time::Duration;
+use util::time_nanos;
 
-//#[cfg(test)]
-//mod tests;
+#[cfg(test)]
+mod tests;
 
 pub mod accounting;
 pub mod candid_rpc;
@@ -28,8 +32,11 @@ pub mod eth_rpc_error;
 pub mod http;
 pub mod http_request;
 pub mod json;
+pub mod rate_limit;
 pub mod util;
+pub mod weighted;
 
+use std::cell::RefCell;
 use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(Clone, Copy, Default, Debug, Eq, PartialEq)]
@@ -52,6 +59,8 @@ pub struct Providers {
     chain: EthereumNetwork,
     /// *Non-empty* set of providers to query.
     services: BTreeSet<RpcService>,
+    /// Selection weight of each provider, when only a subset of `services` should be queried.
+    weights: Option<BTreeMap<RpcService, u32>>,
 }
 
 impl Providers {
@@ -74,8 +83,57 @@ impl Providers {
         Ok(Self {
             chain,
             services: providers,
+            weights: None,
         })
     }
+
+    /// Providers among which a weighted random subset is queried for each call
+    /// when using [`ConsensusStrategy::Threshold`].
+    /// Providers with a weight of 0 are never queried.
+    pub fn weighted(chain_id: u64, weights: Vec<(RpcService, u32)>) -> Result<Self, ProviderError> {
+        let mut weights_by_provider = BTreeMap::new();
+        for (provider, weight) in weights {
+            if weights_by_provider
+                .insert(provider.clone(), weight)
+                .is_some()
+            {
+                return Err(ProviderError::InvalidRpcConfig(format!(
+                    "duplicate weight for provider {:?}",
+                    provider
+                )));
+            }
+        }
+        let services: BTreeSet<_> = weights_by_provider
+            .iter()
+            .filter(|(_provider, weight)| **weight > 0)
+            .map(|(provider, _weight)| provider.clone())
+            .collect();
+
+        if services.is_empty() {
+            return Err(ProviderError::ProviderNotFound);
+        }
+
+        Ok(Self {
+            chain: EthereumNetwork::from(chain_id),
+            services,
+            weights: Some(weights_by_provider),
+        })
+    }
+
+    /// Providers to query for a single call.
+    fn select(&self, strategy: &ConsensusStrategy, seed: u64) -> BTreeSet<RpcService> {
+        match (&self.weights, strategy) {
+            (Some(weights), ConsensusStrategy::Threshold { total, min: _ }) => {
+                let count = total
+                    .map(|total| total as usize)
+                    .unwrap_or(self.services.len());
+                weighted::read_latency(|latency| {
+                    weighted::select_weighted(weights, latency, count, seed)
+                })
+            }
+            _ => self.services.clone(),
+        }
+    }
 }
 
 fn choose_providers<T>(
@@ -88,10 +146,10 @@ where
     T: Clone + Ord,
 {
     match strategy {
-        ConsensusStrategy::Equality => Ok(user_input
-            .unwrap_or_else(|| default_providers.to_vec())
-            .into_iter()
-            .collect()),
+        ConsensusStrategy::Equality => match user_input {
+            Some(providers) => deduplicate_providers(providers),
+            None => Ok(default_providers.iter().cloned().collect()),
+        },
         ConsensusStrategy::Threshold { total, min } => {
             // Ensure that
             // 0 < min <= total <= all_providers.len()
@@ -132,6 +190,7 @@ where
                     Ok(providers)
                 }
                 Some(providers) => {
+                    let providers = deduplicate_providers(providers)?;
                     if min > providers.len() as u8 {
                         return Err(ProviderError::InvalidRpcConfig(format!(
                             "min {} is greater than the number of specified providers {}",
@@ -148,13 +207,26 @@ where
                             )));
                         }
                     }
-                    Ok(providers.into_iter().collect())
+                    Ok(providers)
                 }
             }
         }
     }
 }
 
+/// Rejects user-supplied providers containing duplicates, which would otherwise silently
+/// reduce the number of queried providers below what the consensus strategy expects.
+fn deduplicate_providers<T: Ord>(providers: Vec<T>) -> Result<BTreeSet<T>, ProviderError> {
+    let supplied = providers.len();
+    let providers: BTreeSet<T> = providers.into_iter().collect();
+    if providers.len() != supplied {
+        return Err(ProviderError::InvalidRpcConfig(
+            "Duplicate providers supplied".to_string(),
+        ));
+    }
+    Ok(providers)
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct EthRpcClient {
     providers: Providers,
@@ -171,12 +243,39 @@ impl EthRpcClient {
         })
     }
 
-    fn chain(&self) -> EthereumNetwork {
-        self.providers.chain
+    /// Client querying a weighted random subset of `weights` for each call.
+    /// The consensus strategy must be [`ConsensusStrategy::Threshold`], whose `total` bounds
+    /// the number of providers queried per call.
+    pub fn weighted(
+        chain_id: u64,
+        weights: Vec<(RpcService, u32)>,
+        config: Option<RpcConfig>,
+    ) -> Result<Self, ProviderError> {
+        let config = config.unwrap_or_default();
+        let providers = Providers::weighted(chain_id, weights)?;
+        match config.response_consensus.clone().unwrap_or_default() {
+            ConsensusStrategy::Threshold { total, min } => {
+                let total = total.unwrap_or(providers.services.len() as u8);
+                if min == 0 || min > total || total as usize > providers.services.len() {
+                    return Err(ProviderError::InvalidRpcConfig(format!(
+                        "expected 0 < min <= total <= {}, got min {} and total {}",
+                        providers.services.len(),
+                        min,
+                        total
+                    )));
+                }
+            }
+            ConsensusStrategy::Equality => {
+                return Err(ProviderError::InvalidRpcConfig(
+                    "weighted providers require a threshold consensus strategy".to_string(),
+                ))
+            }
+        }
+        Ok(Self { providers, config })
     }
 
-    fn providers(&self) -> &BTreeSet<RpcService> {
-        &self.providers.services
+    fn chain(&self) -> EthereumNetwork {
+        self.providers.chain
     }
 
     fn response_size_estimate(&self, estimate: u64) -> ResponseSizeEstimate {
@@ -191,6 +290,10 @@ impl EthRpcClient {
             .unwrap_or_default()
     }
 
+    fn partial_on_error(&self) -> Option<&PartialOnError> {
+        self.config.partial_on_error.as_ref()
+    }
+
     /// Query all providers in parallel and return all results.
     /// It's up to the caller to decide how to handle the results, which could be inconsistent
     /// (e.g., if different providers gave different responses).
@@ -207,20 +310,30 @@ impl EthRpcClient {
         I: Serialize + Clone,
         O: DeserializeOwned + HttpRequestResultPayload,
     {
-        let providers = self.providers();
+        let providers = &self
+            .providers
+            .select(&self.consensus_strategy(), time_nanos());
         let results = {
             let mut fut = Vec::with_capacity(providers.len());
             for provider in providers {
                 log!(DEBUG, "[parallel_call]: will call provider: {:?}", provider);
                 fut.push(async {
-                    eth_rpc::call::<_, _>(
+                    let start = time_nanos();
+                    let result = eth_rpc::call::<_, _>(
                         provider,
                         method.clone(),
                         params.clone(),
                         response_size_estimate,
                         cycles_available,
                     )
-                    .await
+                    .await;
+                    let end = time_nanos();
+                    weighted::record_latency(
+                        provider,
+                        Duration::from_nanos(end.saturating_sub(start)),
+                        result.is_ok(),
+                    );
+                    result
                 });
             }
             futures::future::join_all(fut).await
@@ -240,7 +353,15 @@ impl EthRpcClient {
             cycles_available,
         )
         .await
-        .reduce(self.consensus_strategy())
+        .map_errors(|error| match error {
+            RpcError::JsonRpcError(error)
+                if is_result_set_too_large(error.code, &error.message) =>
+            {
+                RpcError::ResultSetTooLarge(error)
+            }
+            error => error,
+        })
+        .reduce(self.consensus_strategy(), self.partial_on_error())
     }
 
     pub async fn eth_get_block_by_number(
@@ -262,7 +383,7 @@ impl EthRpcClient {
             cycles_available,
         )
         .await
-        .reduce(self.consensus_strategy())
+        .reduce(self.consensus_strategy(), self.partial_on_error())
     }
 
     pub async fn eth_get_transaction_receipt(
@@ -277,7 +398,7 @@ impl EthRpcClient {
             cycles_available,
         )
         .await
-        .reduce(self.consensus_strategy())
+        .reduce(self.consensus_strategy(), self.partial_on_error())
     }
 
     pub async fn eth_fee_history(
@@ -293,7 +414,7 @@ impl EthRpcClient {
             cycles_available,
         )
         .await
-        .reduce(self.consensus_strategy())
+        .reduce(self.consensus_strategy(), self.partial_on_error())
     }
 
     pub async fn eth_send_raw_transaction(
@@ -310,7 +431,7 @@ impl EthRpcClient {
             cycles_available,
         )
         .await
-        .reduce(self.consensus_strategy())
+        .reduce(self.consensus_strategy(), self.partial_on_error())
     }
 
     pub async fn eth_get_transaction_count(
@@ -325,7 +446,7 @@ impl EthRpcClient {
             cycles_available,
         )
         .await
-        .reduce(self.consensus_strategy())
+        .reduce(self.consensus_strategy(), self.partial_on_error())
     }
 
     pub async fn eth_call(
@@ -340,7 +461,23 @@ impl EthRpcClient {
             cycles_available,
         )
         .await
-        .reduce(self.consensus_strategy())
+        .reduce(self.consensus_strategy(), self.partial_on_error())
+    }
+
+    pub async fn eth_get_code(
+        &self,
+        params: GetCodeParams,
+        cycles_available: u128,
+    ) -> Result<Data, MultiCallError<Data>> {
+        // Deployed code is at most 24 KiB (EIP-170), i.e. 48 KiB once hex-encoded.
+        self.parallel_call(
+            "eth_getCode",
+            params,
+            self.response_size_estimate(2 * 24_576 + 256 + HEADER_SIZE_LIMIT),
+            cycles_available,
+        )
+        .await
+        .reduce(self.consensus_strategy(), self.partial_on_error())
     }
 }
 
@@ -383,6 +520,17 @@ impl<T> MultiCallResults<T> {
         self.ok_results.is_empty() && self.errors.is_empty()
     }
 
+    fn map_errors<F: Fn(RpcError) -> RpcError>(self, f: F) -> Self {
+        Self {
+            ok_results: self.ok_results,
+            errors: self
+                .errors
+                .into_iter()
+                .map(|(provider, error)| (provider, f(error)))
+                .collect(),
+        }
+    }
+
     fn insert_once(&mut self, provider: RpcService, result: RpcResult<T>) {
         match result {
             Ok(value) => {
@@ -482,10 +630,41 @@ pub enum MultiCallError<T> {
 }
 
 impl<T: Debug + PartialEq + Clone + Serialize> MultiCallResults<T> {
-    pub fn reduce(self, strategy: ConsensusStrategy) -> Result<T, MultiCallError<T>> {
-        match strategy {
+    pub fn reduce(
+        self,
+        strategy: ConsensusStrategy,
+        partial_on_error: Option<&PartialOnError>,
+    ) -> Result<T, MultiCallError<T>> {
+        let result = match strategy {
             ConsensusStrategy::Equality => self.reduce_with_equality(),
             ConsensusStrategy::Threshold { total: _, min } => self.reduce_with_threshold(min),
+        };
+        match (result, partial_on_error) {
+            (Err(MultiCallError::InconsistentResults(results)), Some(partial_on_error)) => {
+                results.reduce_ignoring_errors(partial_on_error.min_success_or_default())
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Returns the response of the providers that did not error if they all agree
+    /// and there are at least `min_success` of them.
+    fn reduce_ignoring_errors(self, min_success: u8) -> Result<T, MultiCallError<T>> {
+        let mut ok_results = self.ok_results.values();
+        match ok_results.next() {
+            Some(first)
+                if self.ok_results.len() >= min_success as usize
+                    && ok_results.all(|result| result == first) =>
+            {
+                log!(
+                    INFO,
+                    "[reduce_ignoring_errors]: {} providers agree, ignoring errors {:?}",
+                    self.ok_results.len(),
+                    self.errors
+                );
+                Ok(first.clone())
+            }
+            _ => Err(MultiCallError::InconsistentResults(self)),
         }
     }
 
@@ -520,6 +699,15 @@ impl<T: Debug + PartialEq + Clone + Serialize> MultiCallResults<T> {
             // so there is at least one error
             return Err(self.expect_error());
         }
+        // Providers usually agree, in which case comparing the responses is much cheaper
+        // than serializing and hashing each of them to build the distribution.
+        let mut ok_results = self.ok_results.values();
+        let first = ok_results
+            .next()
+            .expect("BUG: there are at least min > 0 ok results");
+        if ok_results.all(|result| result == first) {
+            return Ok(first.clone());
+        }
         let distribution = ResponseDistribution::from_non_empty_iter(self.ok_results.clone());
         let (most_likely_response, providers) = distribution
             .most_frequent()
@@ -584,8 +772,7 @@ impl<T: Debug + PartialEq + Serialize> ResponseDistribution<T> {
     }
 
     pub fn insert_once(&mut self, provider: RpcService, result: T) {
-        use ic_sha3::Keccak256;
-        let hash = Keccak256::hash(serde_json::to_vec(&result).expect("BUG: failed to serialize"));
+        let hash = hash_response(&result);
         match self.hashes.get(&hash) {
             Some(existing_result) => {
                 assert_eq!(
@@ -609,3 +796,25 @@ impl<T: Debug + PartialEq + Serialize> ResponseDistribution<T> {
         }
     }
 }
+
+thread_local! {
+    /// Reused to serialize responses without allocating a new buffer for each of them.
+    static SERIALIZATION_BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+#[cfg(test)]
+thread_local! {
+    static HASHED_RESPONSES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+fn hash_response<T: Serialize>(response: &T) -> [u8; 32] {
+    use ic_sha3::Keccak256;
+    #[cfg(test)]
+    HASHED_RESPONSES.with(|count| count.set(count.get() + 1));
+    SERIALIZATION_BUFFER.with(|buffer| {
+        let mut buffer = buffer.borrow_mut();
+        buffer.clear();
+        serde_json::to_writer(&mut *buffer, response).expect("BUG: failed to serialize");
+        Keccak256::hash(&buffer[..])
+    })
+}
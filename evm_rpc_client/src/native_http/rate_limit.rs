@@ -0,0 +1,42 @@
+use crate::evm_rpc_types::RpcService;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+thread_local! {
+    static RATE_LIMITED_PROVIDERS: RefCell<BTreeMap<RpcService, u64>> = RefCell::default();
+}
+
+/// Records that `provider` kept rate limiting requests and should not be queried
+/// before `retry_after_ns` (IC time in nanoseconds).
+pub fn record_rate_limited(provider: &RpcService, retry_after_ns: u64) {
+    RATE_LIMITED_PROVIDERS.with(|providers| {
+        let mut providers = providers.borrow_mut();
+        let entry = providers.entry(provider.clone()).or_default();
+        *entry = (*entry).max(retry_after_ns);
+    });
+}
+
+/// Returns and forgets the providers rate limited since the last call,
+/// so that the caller can persist them.
+pub fn take_rate_limited() -> BTreeMap<RpcService, u64> {
+    RATE_LIMITED_PROVIDERS.with(|providers| std::mem::take(&mut *providers.borrow_mut()))
+}
+
+/// Resolves once `delay` has elapsed.
+///
+/// Canisters cannot block, so the wait is driven by a one-shot timer that wakes up the
+/// suspended call. Without the `canister` feature there are no timers and it resolves
+/// immediately.
+pub async fn sleep(delay: Duration) {
+    #[cfg(feature = "canister")]
+    {
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        ic_cdk_timers::set_timer(delay, move || {
+            let _ = sender.send(());
+        });
+        let _ = receiver.await;
+    }
+    #[cfg(not(feature = "canister"))]
+    let _ = delay;
+}
@@ -0,0 +1,279 @@
+use crate::evm_rpc_types::{JsonRpcError, RpcApi, RpcError, RpcResult, RpcService};
+use crate::native_http::{MultiCallError, MultiCallResults, ResponseDistribution};
+use serde::Serialize;
+use std::fmt::Debug;
+
+fn provider(id: u8) -> RpcService {
+    RpcService::Custom(RpcApi {
+        url: format!("https://rpc{id}.example.com"),
+        headers: None,
+    })
+}
+
+/// Threshold reduction as implemented before skipping the hashing of agreeing responses.
+fn reduce_with_threshold_by_hashing<T: Debug + PartialEq + Clone + Serialize>(
+    results: MultiCallResults<T>,
+    min: u8,
+) -> Result<T, MultiCallError<T>> {
+    if results.ok_results.len() < min as usize {
+        return Err(results.expect_error());
+    }
+    let distribution = ResponseDistribution::from_non_empty_iter(results.ok_results.clone());
+    let (most_likely_response, providers) = distribution
+        .most_frequent()
+        .expect("BUG: distribution should be non-empty");
+    if providers.len() >= min as usize {
+        Ok(most_likely_response.clone())
+    } else {
+        Err(MultiCallError::InconsistentResults(results))
+    }
+}
+
+mod reduce_with_threshold {
+    use super::*;
+    use crate::native_http::json::responses::LogEntry;
+    use crate::native_http::HASHED_RESPONSES;
+    use proptest::collection::btree_map;
+    use proptest::prelude::{any, prop_oneof, Strategy};
+    use proptest::{prop_assert_eq, proptest};
+
+    const LOG_ENTRY: &str = r#"{
+        "address": "0x7e41257f7b5c3dd3313ef02b1f4c864fe95bec2b",
+        "topics": [
+            "0x2a2607d40f4a6feb97c36e0efd57e0aa3e42e0332af4fceb78f21b7dffcbd657"
+        ],
+        "data": "0x00000000000000000000000055654e7405fcb336386ea8f36954a211b2cda764000000000000000000000000000000000000000000000000002386f26fc100000000000000000000000000000000000000000000000000000000000000000060000000000000000000000000000000000000000000000000000000000000003f62327071372d71677a7a692d74623564622d72357363692d637736736c2d6e646f756c2d666f7435742d347a7732702d657a6677692d74616a32792d76716500",
+        "blockNumber": "0x3aa4f4",
+        "transactionHash": "0x5618f72c485bd98a3df58d900eabe9e24bfaa972a6fe5227e02233fad2db1154",
+        "transactionIndex": "0x6",
+        "blockHash": "0x908e6b84d26d71421bfaa08e7966e0afcef3883a28a53a0a7a31104caf1e94c2",
+        "logIndex": "0x8",
+        "removed": false
+    }"#;
+
+    fn logs(count: usize) -> Vec<LogEntry> {
+        let log_entry: LogEntry = serde_json::from_str(LOG_ENTRY).unwrap();
+        vec![log_entry; count]
+    }
+
+    fn hashed_responses<R>(f: impl FnOnce() -> R) -> (R, usize) {
+        HASHED_RESPONSES.with(|count| count.set(0));
+        let result = f();
+        (result, HASHED_RESPONSES.with(|count| count.get()))
+    }
+
+    #[test]
+    fn should_not_hash_responses_when_providers_agree() {
+        let results = MultiCallResults::from_non_empty_iter(
+            (0..4_u8).map(|id| (provider(id), Ok(logs(500)))),
+        );
+
+        let (reduced, hashed) = hashed_responses(|| results.clone().reduce_with_threshold(3));
+        let (expected, hashed_before) =
+            hashed_responses(|| reduce_with_threshold_by_hashing(results, 3));
+
+        assert_eq!(reduced, Ok(logs(500)));
+        assert_eq!(reduced, expected);
+        assert_eq!(hashed, 0);
+        assert_eq!(hashed_before, 4);
+    }
+
+    #[test]
+    fn should_hash_responses_once_providers_disagree() {
+        let mut other_logs = logs(500);
+        other_logs[499].removed = true;
+        let results = MultiCallResults::from_non_empty_iter(
+            (0..3_u8)
+                .map(|id| (provider(id), Ok(logs(500))))
+                .chain(std::iter::once((provider(3), Ok(other_logs)))),
+        );
+
+        let (reduced, hashed) = hashed_responses(|| results.reduce_with_threshold(3));
+
+        assert_eq!(reduced, Ok(logs(500)));
+        assert_eq!(hashed, 4);
+    }
+
+    fn arb_rpc_result() -> impl Strategy<Value = RpcResult<u8>> {
+        prop_oneof![
+            3 => (0_u8..3).prop_map(Ok),
+            1 => (0_i64..2).prop_map(|code| Err(RpcError::JsonRpcError(JsonRpcError {
+                code,
+                message: "error".to_string(),
+            }))),
+        ]
+    }
+
+    fn arb_multi_call_results() -> impl Strategy<Value = MultiCallResults<u8>> {
+        btree_map(any::<u8>(), arb_rpc_result(), 1..=6).prop_map(|results| {
+            MultiCallResults::from_non_empty_iter(
+                results
+                    .into_iter()
+                    .map(|(id, result)| (provider(id), result)),
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn should_reduce_as_when_hashing_every_response(
+            results in arb_multi_call_results(),
+            min in 1_u8..=6,
+        ) {
+            prop_assert_eq!(
+                results.clone().reduce_with_threshold(min),
+                reduce_with_threshold_by_hashing(results, min)
+            );
+        }
+    }
+}
+
+mod reduce_with_partial_on_error {
+    use super::*;
+    use crate::evm_rpc_types::{
+        ConsensusStrategy, HttpOutcallError, PartialOnError, RejectionCode,
+    };
+    use assert_matches::assert_matches;
+
+    fn timeout() -> RpcError {
+        RpcError::HttpOutcallError(HttpOutcallError::IcError {
+            code: RejectionCode::SysTransient,
+            message: "timeout".to_string(),
+        })
+    }
+
+    fn results(responses: Vec<RpcResult<u64>>) -> MultiCallResults<u64> {
+        MultiCallResults::from_non_empty_iter(
+            responses
+                .into_iter()
+                .enumerate()
+                .map(|(id, result)| (provider(id as u8), result)),
+        )
+    }
+
+    fn reduce_partial(results: MultiCallResults<u64>) -> Result<u64, MultiCallError<u64>> {
+        results.reduce(
+            ConsensusStrategy::Equality,
+            Some(&PartialOnError::default()),
+        )
+    }
+
+    #[test]
+    fn should_accept_two_agreeing_providers_when_third_errors() {
+        let results = results(vec![Ok(1), Ok(1), Err(timeout())]);
+
+        assert_matches!(
+            results.clone().reduce(ConsensusStrategy::Equality, None),
+            Err(MultiCallError::InconsistentResults(_))
+        );
+        assert_eq!(reduce_partial(results), Ok(1));
+    }
+
+    #[test]
+    fn should_fail_when_only_one_provider_succeeds() {
+        let results = results(vec![Ok(1), Err(timeout()), Err(timeout())]);
+
+        assert_eq!(
+            reduce_partial(results.clone()),
+            Err(MultiCallError::InconsistentResults(results))
+        );
+    }
+
+    #[test]
+    fn should_fail_when_successful_providers_disagree() {
+        let results = results(vec![Ok(1), Ok(2), Err(timeout())]);
+
+        assert_eq!(
+            reduce_partial(results.clone()),
+            Err(MultiCallError::InconsistentResults(results))
+        );
+    }
+
+    #[test]
+    fn should_honor_min_success() {
+        let results = results(vec![Ok(1), Ok(1), Err(timeout()), Err(timeout())]);
+
+        assert_matches!(
+            results.clone().reduce(
+                ConsensusStrategy::Equality,
+                Some(&PartialOnError {
+                    min_success: Some(3)
+                })
+            ),
+            Err(MultiCallError::InconsistentResults(_))
+        );
+        assert_eq!(
+            results.reduce(
+                ConsensusStrategy::Equality,
+                Some(&PartialOnError {
+                    min_success: Some(2)
+                })
+            ),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    fn should_keep_consistent_error() {
+        let results = results(vec![Err(timeout()), Err(timeout()), Err(timeout())]);
+
+        assert_eq!(
+            reduce_partial(results),
+            Err(MultiCallError::ConsistentError(timeout()))
+        );
+    }
+}
+
+mod choose_providers {
+    use crate::evm_rpc_types::{ConsensusStrategy, EthMainnetService, ProviderError};
+    use crate::native_http::choose_providers;
+    use assert_matches::assert_matches;
+
+    const DUPLICATE_PROVIDERS: [EthMainnetService; 2] =
+        [EthMainnetService::Alchemy, EthMainnetService::Alchemy];
+
+    #[test]
+    fn should_reject_duplicate_providers_with_equality_strategy() {
+        let result = choose_providers(
+            Some(DUPLICATE_PROVIDERS.to_vec()),
+            &[],
+            &[],
+            ConsensusStrategy::Equality,
+        );
+
+        assert_matches!(result, Err(ProviderError::InvalidRpcConfig(_)));
+    }
+
+    #[test]
+    fn should_reject_duplicate_providers_with_threshold_strategy() {
+        let result = choose_providers(
+            Some(DUPLICATE_PROVIDERS.to_vec()),
+            &[],
+            &[],
+            ConsensusStrategy::Threshold {
+                total: None,
+                min: 1,
+            },
+        );
+
+        assert_matches!(result, Err(ProviderError::InvalidRpcConfig(_)));
+    }
+
+    #[test]
+    fn should_accept_distinct_providers() {
+        let result = choose_providers(
+            Some(vec![EthMainnetService::Alchemy, EthMainnetService::Ankr]),
+            &[],
+            &[],
+            ConsensusStrategy::Threshold {
+                total: Some(2),
+                min: 2,
+            },
+        );
+
+        assert_eq!(
+            result,
+            Ok([EthMainnetService::Alchemy, EthMainnetService::Ankr].into())
+        );
+    }
+}
@@ -25,3 +25,20 @@ pub fn hostname_from_url(url: &str) -> Option<String> {
         _ => None,
     })
 }
+
+/// Current time in nanoseconds since the UNIX epoch.
+///
+/// This is the IC time in a canister, and the system time otherwise.
+pub fn time_nanos() -> u64 {
+    #[cfg(feature = "canister")]
+    {
+        ic_cdk::api::time()
+    }
+    #[cfg(not(feature = "canister"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,174 @@
+use crate::evm_rpc_types::RpcService;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests;
+
+/// Number of most recent response times kept per provider to compute the percentiles.
+pub const LATENCY_SAMPLE_WINDOW: usize = 100;
+
+thread_local! {
+    static PROVIDER_LATENCY: RefCell<ProviderLatency> = RefCell::default();
+}
+
+/// Records how long `provider` took to answer a single request and whether it succeeded.
+pub fn record_latency(provider: &RpcService, elapsed: Duration, success: bool) {
+    PROVIDER_LATENCY.with(|latency| latency.borrow_mut().record(provider, elapsed, success));
+}
+
+pub fn read_latency<R>(f: impl FnOnce(&ProviderLatency) -> R) -> R {
+    PROVIDER_LATENCY.with(|latency| f(&latency.borrow()))
+}
+
+/// Average response time observed for each provider.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProviderLatency {
+    samples: BTreeMap<RpcService, LatencySamples>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct LatencySamples {
+    total_nanos: u128,
+    count: u64,
+    error_count: u64,
+    recent_nanos: VecDeque<u64>,
+}
+
+/// Latency percentiles over the last [`LATENCY_SAMPLE_WINDOW`] requests to a provider,
+/// and request counts since the canister started.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub p50_ns: u64,
+    pub p95_ns: u64,
+    pub call_count: u64,
+    pub error_count: u64,
+}
+
+impl ProviderLatency {
+    pub fn record(&mut self, provider: &RpcService, elapsed: Duration, success: bool) {
+        let samples = self.samples.entry(provider.clone()).or_default();
+        samples.total_nanos = samples.total_nanos.saturating_add(elapsed.as_nanos());
+        samples.count = samples.count.saturating_add(1);
+        if !success {
+            samples.error_count = samples.error_count.saturating_add(1);
+        }
+        if samples.recent_nanos.len() >= LATENCY_SAMPLE_WINDOW {
+            samples.recent_nanos.pop_front();
+        }
+        samples
+            .recent_nanos
+            .push_back(u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX));
+    }
+
+    /// Statistics of every provider queried so far.
+    pub fn stats(&self) -> BTreeMap<RpcService, LatencyStats> {
+        self.samples
+            .iter()
+            .map(|(provider, samples)| {
+                let mut sorted: Vec<u64> = samples.recent_nanos.iter().copied().collect();
+                sorted.sort_unstable();
+                let stats = LatencyStats {
+                    p50_ns: percentile(&sorted, 50),
+                    p95_ns: percentile(&sorted, 95),
+                    call_count: samples.count,
+                    error_count: samples.error_count,
+                };
+                (provider.clone(), stats)
+            })
+            .collect()
+    }
+
+    /// Average response time of `provider`, or `None` if it was never queried.
+    pub fn average(&self, provider: &RpcService) -> Option<Duration> {
+        let samples = self.samples.get(provider)?;
+        if samples.count == 0 {
+            return None;
+        }
+        let average_nanos = samples.total_nanos / samples.count as u128;
+        Some(Duration::from_nanos(
+            u64::try_from(average_nanos).unwrap_or(u64::MAX),
+        ))
+    }
+
+    /// Scales the configured weight of each provider by how fast it answers compared to the
+    /// fastest provider, so that lower-latency providers are picked more often.
+    /// Providers without any latency sample keep their configured weight and a non-zero
+    /// weight is never scaled down to zero.
+    fn effective_weights(&self, weights: &BTreeMap<RpcService, u32>) -> BTreeMap<RpcService, u64> {
+        let fastest = weights
+            .keys()
+            .filter_map(|provider| self.average(provider))
+            .min()
+            .map(|latency| latency.as_nanos().max(1));
+        weights
+            .iter()
+            .map(|(provider, &weight)| {
+                let weight = weight as u64;
+                let effective = match (fastest, self.average(provider)) {
+                    (Some(fastest), Some(average)) if weight > 0 => {
+                        let scaled = weight as u128 * fastest / average.as_nanos().max(1);
+                        (scaled as u64).max(1)
+                    }
+                    _ => weight,
+                };
+                (provider.clone(), effective)
+            })
+            .collect()
+    }
+}
+
+/// Picks up to `count` distinct providers, each draw being proportional to the provider's
+/// effective weight. Providers with a weight of 0 are never selected.
+pub fn select_weighted(
+    weights: &BTreeMap<RpcService, u32>,
+    latency: &ProviderLatency,
+    count: usize,
+    seed: u64,
+) -> BTreeSet<RpcService> {
+    let mut remaining = latency.effective_weights(weights);
+    remaining.retain(|_, weight| *weight > 0);
+    let mut rng = SplitMix64(seed);
+    let mut selected = BTreeSet::new();
+    while selected.len() < count {
+        let total: u64 = remaining.values().sum();
+        if total == 0 {
+            break;
+        }
+        let mut target = rng.next_u64() % total;
+        let chosen = remaining
+            .iter()
+            .find_map(|(provider, &weight)| {
+                if target < weight {
+                    Some(provider.clone())
+                } else {
+                    target -= weight;
+                    None
+                }
+            })
+            .expect("BUG: target is smaller than the total weight");
+        remaining.remove(&chosen);
+        selected.insert(chosen);
+    }
+    selected
+}
+
+/// Nearest-rank percentile of sorted samples, 0 if there are none.
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied().unwrap_or_default()
+}
+
+/// Small deterministic generator, good enough to spread requests among providers.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
@@ -0,0 +1,175 @@
+use crate::evm_rpc_types::{RpcApi, RpcService};
+use crate::native_http::weighted::{
+    select_weighted, LatencyStats, ProviderLatency, LATENCY_SAMPLE_WINDOW,
+};
+use proptest::{prop_assert, prop_assert_eq, proptest};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+fn provider(id: u8) -> RpcService {
+    RpcService::Custom(RpcApi {
+        url: format!("https://rpc{id}.example.com"),
+        headers: None,
+    })
+}
+
+fn weights(weights: &[(u8, u32)]) -> BTreeMap<RpcService, u32> {
+    weights
+        .iter()
+        .map(|(id, weight)| (provider(*id), *weight))
+        .collect()
+}
+
+mod select_weighted {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn should_never_select_provider_with_zero_weight(seed in proptest::num::u64::ANY, count in 1_usize..=5) {
+            let weights = weights(&[(0, 10), (1, 0), (2, 1), (3, 0), (4, 5)]);
+            let selected = select_weighted(&weights, &ProviderLatency::default(), count, seed);
+
+            prop_assert!(!selected.contains(&provider(1)));
+            prop_assert!(!selected.contains(&provider(3)));
+            prop_assert_eq!(selected.len(), count.min(3));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn should_never_select_slow_provider_with_zero_weight(seed in proptest::num::u64::ANY) {
+            let weights = weights(&[(0, 1), (1, 0), (2, 1)]);
+            let mut latency = ProviderLatency::default();
+            latency.record(&provider(0), Duration::from_secs(10), true);
+            latency.record(&provider(1), Duration::from_millis(1), true);
+            latency.record(&provider(2), Duration::from_secs(5), true);
+
+            let selected = select_weighted(&weights, &latency, 2, seed);
+
+            prop_assert!(!selected.contains(&provider(1)));
+            prop_assert_eq!(selected.len(), 2);
+        }
+    }
+
+    #[test]
+    fn should_select_nothing_when_all_weights_are_zero() {
+        let weights = weights(&[(0, 0), (1, 0)]);
+        assert!(select_weighted(&weights, &ProviderLatency::default(), 2, 42).is_empty());
+    }
+
+    #[test]
+    fn should_favor_providers_with_higher_weight() {
+        let weights = weights(&[(0, 1), (1, 99)]);
+        let heavy_picks = (0..1_000_u64)
+            .filter(|seed| {
+                select_weighted(&weights, &ProviderLatency::default(), 1, *seed)
+                    .contains(&provider(1))
+            })
+            .count();
+        assert!(
+            heavy_picks > 900,
+            "heavy provider picked {heavy_picks} times"
+        );
+    }
+
+    #[test]
+    fn should_favor_lower_latency_providers() {
+        let weights = weights(&[(0, 10), (1, 10)]);
+        let mut latency = ProviderLatency::default();
+        latency.record(&provider(0), Duration::from_millis(100), true);
+        latency.record(&provider(1), Duration::from_millis(2_000), true);
+
+        let fast_picks = (0..1_000_u64)
+            .filter(|seed| select_weighted(&weights, &latency, 1, *seed).contains(&provider(0)))
+            .count();
+        assert!(fast_picks > 800, "fast provider picked {fast_picks} times");
+    }
+}
+
+mod provider_latency {
+    use super::*;
+
+    #[test]
+    fn should_average_response_times() {
+        let mut latency = ProviderLatency::default();
+        assert_eq!(latency.average(&provider(0)), None);
+
+        latency.record(&provider(0), Duration::from_millis(100), true);
+        latency.record(&provider(0), Duration::from_millis(300), true);
+        latency.record(&provider(1), Duration::from_millis(50), true);
+
+        assert_eq!(
+            latency.average(&provider(0)),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            latency.average(&provider(1)),
+            Some(Duration::from_millis(50))
+        );
+    }
+
+    #[test]
+    fn should_track_stats_of_each_provider_separately() {
+        let mut latency = ProviderLatency::default();
+        for i in 1..=20 {
+            latency.record(&provider(0), Duration::from_millis(i * 100), true);
+            latency.record(&provider(1), Duration::from_millis(i * 10), i % 10 != 0);
+        }
+
+        let stats = latency.stats();
+        assert_eq!(
+            stats.get(&provider(0)),
+            Some(&LatencyStats {
+                p50_ns: 1_000_000_000,
+                p95_ns: 1_900_000_000,
+                call_count: 20,
+                error_count: 0,
+            })
+        );
+        assert_eq!(
+            stats.get(&provider(1)),
+            Some(&LatencyStats {
+                p50_ns: 100_000_000,
+                p95_ns: 190_000_000,
+                call_count: 20,
+                error_count: 2,
+            })
+        );
+        assert!(!stats.contains_key(&provider(2)));
+    }
+
+    #[test]
+    fn should_only_compute_percentiles_over_recent_calls() {
+        let mut latency = ProviderLatency::default();
+        for _ in 0..LATENCY_SAMPLE_WINDOW {
+            latency.record(&provider(0), Duration::from_secs(5), false);
+        }
+        for _ in 0..LATENCY_SAMPLE_WINDOW {
+            latency.record(&provider(0), Duration::from_millis(200), true);
+        }
+
+        assert_eq!(
+            latency.stats().get(&provider(0)),
+            Some(&LatencyStats {
+                p50_ns: 200_000_000,
+                p95_ns: 200_000_000,
+                call_count: 2 * LATENCY_SAMPLE_WINDOW as u64,
+                error_count: LATENCY_SAMPLE_WINDOW as u64,
+            })
+        );
+        // the average used for the weighting still covers every call
+        assert_eq!(
+            latency.average(&provider(0)),
+            Some(Duration::from_nanos(2_600_000_000))
+        );
+    }
+
+    #[test]
+    fn should_report_single_sample_as_every_percentile() {
+        let mut latency = ProviderLatency::default();
+        latency.record(&provider(0), Duration::from_nanos(42), true);
+
+        let stats = latency.stats()[&provider(0)].clone();
+        assert_eq!((stats.p50_ns, stats.p95_ns), (42, 42));
+    }
+}
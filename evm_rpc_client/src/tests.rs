@@ -46,3 +46,1098 @@ mod max_expected_too_few_cycles_error {
         assert_eq!(max_too_few_cycles, Some(893_894_400));
     }
 }
+
+mod poll_for_new_block {
+    use super::*;
+    use crate::evm_rpc_types::{Block, EthMainnetService, Hex, Hex20, Hex256, Hex32, Nat256};
+    use crate::new_block_since;
+    use crate::numeric::BlockNumber;
+
+    fn block(number: u64) -> Block {
+        Block {
+            base_fee_per_gas: Some(Nat256::from(8_876_901_983_u64)),
+            number: Nat256::from(number),
+            difficulty: Some(Nat256::ZERO),
+            extra_data: Hex::from(vec![]),
+            gas_limit: Nat256::from(30_000_000_u64),
+            gas_used: Nat256::from(2_858_256_u64),
+            hash: Hex32::from([1; 32]),
+            logs_bloom: Hex256::from([0; 256]),
+            miner: Hex20::from([2; 20]),
+            mix_hash: Hex32::from([3; 32]),
+            nonce: Nat256::ZERO,
+            parent_hash: Hex32::from([4; 32]),
+            receipts_root: Hex32::from([5; 32]),
+            sha3_uncles: Hex32::from([6; 32]),
+            size: Nat256::from(31_866_u64),
+            state_root: Hex32::from([7; 32]),
+            timestamp: Nat256::from(1_698_589_667_u64),
+            total_difficulty: None,
+            transactions: vec![],
+            transactions_root: None,
+            uncles: vec![],
+        }
+    }
+
+    #[test]
+    fn should_return_none_when_block_number_did_not_advance() {
+        let last_known_number = BlockNumber::from(18_466_887_u64);
+
+        let result = new_block_since(
+            MultiRpcResult::Consistent(Ok(block(18_466_887))),
+            last_known_number,
+        );
+
+        assert_eq!(result, MultiRpcResult::Consistent(Ok(None)));
+    }
+
+    #[test]
+    fn should_return_block_when_block_number_advanced() {
+        let last_known_number = BlockNumber::from(18_466_887_u64);
+
+        let result = new_block_since(
+            MultiRpcResult::Consistent(Ok(block(18_466_888))),
+            last_known_number,
+        );
+
+        assert_eq!(
+            result,
+            MultiRpcResult::Consistent(Ok(Some(block(18_466_888))))
+        );
+    }
+
+    #[test]
+    fn should_map_each_provider_result() {
+        let last_known_number = BlockNumber::from(18_466_887_u64);
+        let error = RpcError::ProviderError(ProviderError::TooFewCycles {
+            expected: 2,
+            received: 1,
+        });
+
+        let result = new_block_since(
+            MultiRpcResult::Inconsistent(vec![
+                (
+                    RpcService::EthMainnet(EthMainnetService::Alchemy),
+                    Ok(block(18_466_887)),
+                ),
+                (
+                    RpcService::EthMainnet(EthMainnetService::Ankr),
+                    Ok(block(18_466_888)),
+                ),
+                (
+                    RpcService::EthMainnet(EthMainnetService::PublicNode),
+                    Err(error.clone()),
+                ),
+            ]),
+            last_known_number,
+        );
+
+        assert_eq!(
+            result,
+            MultiRpcResult::Inconsistent(vec![
+                (RpcService::EthMainnet(EthMainnetService::Alchemy), Ok(None)),
+                (
+                    RpcService::EthMainnet(EthMainnetService::Ankr),
+                    Ok(Some(block(18_466_888))),
+                ),
+                (
+                    RpcService::EthMainnet(EthMainnetService::PublicNode),
+                    Err(error),
+                ),
+            ])
+        );
+    }
+}
+
+mod multi_rpc_result {
+    use super::*;
+    use crate::evm_rpc_types::{EthMainnetService, JsonRpcError, RpcResult, ValidationError};
+
+    const ALCHEMY: RpcService = RpcService::EthMainnet(EthMainnetService::Alchemy);
+    const ANKR: RpcService = RpcService::EthMainnet(EthMainnetService::Ankr);
+    const PUBLIC_NODE: RpcService = RpcService::EthMainnet(EthMainnetService::PublicNode);
+
+    fn json_rpc_error() -> RpcError {
+        RpcError::JsonRpcError(JsonRpcError {
+            code: -32000,
+            message: "header not found".to_string(),
+        })
+    }
+
+    fn too_few_cycles_error() -> RpcError {
+        RpcError::ProviderError(ProviderError::TooFewCycles {
+            expected: 2,
+            received: 1,
+        })
+    }
+
+    fn validation_error(message: &str) -> RpcError {
+        RpcError::ValidationError(ValidationError::Custom(message.to_string()))
+    }
+
+    fn parse(value: String) -> RpcResult<u64> {
+        value
+            .parse()
+            .map_err(|_| validation_error(&format!("not a number: {value}")))
+    }
+
+    fn inconsistent_results() -> MultiRpcResult<u64> {
+        MultiRpcResult::Inconsistent(vec![
+            (ALCHEMY, Ok(1)),
+            (ANKR, Ok(2)),
+            (PUBLIC_NODE, Err(json_rpc_error())),
+        ])
+    }
+
+    mod map {
+        use super::*;
+
+        #[test]
+        fn should_map_consistent_ok() {
+            let result = MultiRpcResult::Consistent(Ok(1_u64)).map(|n| n.to_string());
+
+            assert_eq!(result, MultiRpcResult::Consistent(Ok("1".to_string())));
+        }
+
+        #[test]
+        fn should_keep_consistent_error() {
+            let result =
+                MultiRpcResult::<u64>::Consistent(Err(json_rpc_error())).map(|n| n.to_string());
+
+            assert_eq!(result, MultiRpcResult::Consistent(Err(json_rpc_error())));
+        }
+
+        #[test]
+        fn should_map_each_inconsistent_ok_and_keep_errors() {
+            let result = inconsistent_results().map(|n| n * 10);
+
+            assert_eq!(
+                result,
+                MultiRpcResult::Inconsistent(vec![
+                    (ALCHEMY, Ok(10)),
+                    (ANKR, Ok(20)),
+                    (PUBLIC_NODE, Err(json_rpc_error())),
+                ])
+            );
+        }
+
+        #[test]
+        fn should_keep_order_and_providers_of_inconsistent_results() {
+            let result = MultiRpcResult::Inconsistent(vec![
+                (PUBLIC_NODE, Ok(3_u64)),
+                (ALCHEMY, Ok(1)),
+                (ANKR, Ok(2)),
+            ])
+            .map(|n| n);
+
+            assert_eq!(
+                result.expect_inconsistent(),
+                vec![(PUBLIC_NODE, Ok(3)), (ALCHEMY, Ok(1)), (ANKR, Ok(2))]
+            );
+        }
+
+        #[test]
+        fn should_call_closure_once_per_successful_result() {
+            let mut calls = 0;
+
+            let _ = inconsistent_results().map(|n| {
+                calls += 1;
+                n
+            });
+
+            assert_eq!(calls, 2);
+        }
+
+        #[test]
+        fn should_not_call_closure_on_consistent_error() {
+            let mut calls = 0;
+
+            let _ = MultiRpcResult::<u64>::Consistent(Err(too_few_cycles_error())).map(|n| {
+                calls += 1;
+                n
+            });
+
+            assert_eq!(calls, 0);
+        }
+
+        #[test]
+        fn should_map_empty_inconsistent_results() {
+            let result = MultiRpcResult::<u64>::Inconsistent(vec![]).map(|n| n.to_string());
+
+            assert_eq!(result, MultiRpcResult::Inconsistent(vec![]));
+        }
+    }
+
+    mod and_then {
+        use super::*;
+
+        #[test]
+        fn should_chain_consistent_ok() {
+            let result = MultiRpcResult::Consistent(Ok("42".to_string())).and_then(parse);
+
+            assert_eq!(result, MultiRpcResult::Consistent(Ok(42)));
+        }
+
+        #[test]
+        fn should_turn_consistent_ok_into_error() {
+            let result = MultiRpcResult::Consistent(Ok("abc".to_string())).and_then(parse);
+
+            assert_eq!(
+                result,
+                MultiRpcResult::Consistent(Err(validation_error("not a number: abc")))
+            );
+        }
+
+        #[test]
+        fn should_keep_consistent_error() {
+            let result = MultiRpcResult::Consistent(Err(too_few_cycles_error())).and_then(parse);
+
+            assert_eq!(
+                result,
+                MultiRpcResult::Consistent(Err(too_few_cycles_error()))
+            );
+        }
+
+        #[test]
+        fn should_chain_each_inconsistent_result() {
+            let result = MultiRpcResult::Inconsistent(vec![
+                (ALCHEMY, Ok("1".to_string())),
+                (ANKR, Ok("abc".to_string())),
+                (PUBLIC_NODE, Err(json_rpc_error())),
+            ])
+            .and_then(parse);
+
+            assert_eq!(
+                result,
+                MultiRpcResult::Inconsistent(vec![
+                    (ALCHEMY, Ok(1)),
+                    (ANKR, Err(validation_error("not a number: abc"))),
+                    (PUBLIC_NODE, Err(json_rpc_error())),
+                ])
+            );
+        }
+
+        #[test]
+        fn should_not_call_closure_on_errors() {
+            let mut calls = 0;
+
+            let _ = inconsistent_results().and_then(|n| {
+                calls += 1;
+                Ok(n)
+            });
+
+            assert_eq!(calls, 2);
+        }
+
+        #[test]
+        fn should_be_equivalent_to_map_when_closure_never_fails() {
+            assert_eq!(
+                inconsistent_results().and_then(|n| Ok(n + 1)),
+                inconsistent_results().map(|n| n + 1)
+            );
+        }
+    }
+
+    mod map_consistent {
+        use super::*;
+
+        #[test]
+        fn should_map_consistent_ok() {
+            let result = MultiRpcResult::Consistent(Ok(1_u64)).map_consistent(|r| r.map(|n| n + 1));
+
+            assert_eq!(result, MultiRpcResult::Consistent(Ok(2)));
+        }
+
+        #[test]
+        fn should_map_consistent_error() {
+            let result = MultiRpcResult::<u64>::Consistent(Err(json_rpc_error()))
+                .map_consistent(|r| r.map_err(|_| too_few_cycles_error()));
+
+            assert_eq!(
+                result,
+                MultiRpcResult::Consistent(Err(too_few_cycles_error()))
+            );
+        }
+
+        #[test]
+        fn should_recover_from_error() {
+            let result = MultiRpcResult::<u64>::Consistent(Err(json_rpc_error()))
+                .map_consistent(|r| r.or(Ok(0)));
+
+            assert_eq!(result, MultiRpcResult::Consistent(Ok(0)));
+        }
+
+        #[test]
+        fn should_map_every_inconsistent_result() {
+            let result = inconsistent_results().map_consistent(|r| Ok(r.is_ok()));
+
+            assert_eq!(
+                result,
+                MultiRpcResult::Inconsistent(vec![
+                    (ALCHEMY, Ok(true)),
+                    (ANKR, Ok(true)),
+                    (PUBLIC_NODE, Ok(false)),
+                ])
+            );
+        }
+
+        #[test]
+        fn should_call_closure_once_per_result() {
+            let mut calls = 0;
+
+            let _ = inconsistent_results().map_consistent(|r| {
+                calls += 1;
+                r
+            });
+
+            assert_eq!(calls, 3);
+        }
+
+        #[test]
+        fn should_not_turn_inconsistent_into_consistent() {
+            let result = inconsistent_results().map_consistent(|_| Ok(()));
+
+            assert_eq!(
+                result,
+                MultiRpcResult::Inconsistent(vec![
+                    (ALCHEMY, Ok(())),
+                    (ANKR, Ok(())),
+                    (PUBLIC_NODE, Ok(())),
+                ])
+            );
+        }
+
+        #[test]
+        fn should_be_identity_with_identity_closure() {
+            assert_eq!(
+                inconsistent_results().map_consistent(|r| r),
+                inconsistent_results()
+            );
+            assert_eq!(
+                MultiRpcResult::<u64>::Consistent(Err(json_rpc_error())).map_consistent(|r| r),
+                MultiRpcResult::Consistent(Err(json_rpc_error()))
+            );
+        }
+    }
+}
+
+mod clone {
+    use crate::evm_rpc_types::{EthMainnetService, RpcServices};
+    use crate::logs::INFO;
+    use crate::{CallerService, EvmRpcClient};
+    use candid::Principal;
+
+    fn client() -> EvmRpcClient<crate::logs::PrintProxySink> {
+        EvmRpcClient::builder(CallerService::EvmRpcCanisterClient, INFO)
+            .with_providers(RpcServices::EthMainnet(Some(vec![
+                EthMainnetService::Alchemy,
+                EthMainnetService::Ankr,
+            ])))
+            .with_min_attached_cycles(1_000)
+            .with_max_num_retries(3)
+            .build()
+    }
+
+    #[test]
+    fn should_not_affect_original_when_modifying_clone() {
+        let original = client();
+        let mut cloned = original.clone();
+
+        cloned.providers = RpcServices::EthMainnet(Some(vec![EthMainnetService::PublicNode]));
+        cloned.min_attached_cycles = 2_000;
+
+        assert_eq!(
+            original.providers,
+            RpcServices::EthMainnet(Some(vec![
+                EthMainnetService::Alchemy,
+                EthMainnetService::Ankr,
+            ]))
+        );
+        assert_eq!(original.min_attached_cycles, 1_000);
+        assert_eq!(
+            cloned.providers,
+            RpcServices::EthMainnet(Some(vec![EthMainnetService::PublicNode]))
+        );
+    }
+
+    #[test]
+    fn should_copy_all_fields_when_cloning() {
+        let original = client();
+        let cloned = original.clone();
+
+        assert_eq!(cloned.caller_service, original.caller_service);
+        assert_eq!(cloned.providers, original.providers);
+        assert_eq!(cloned.evm_canister_id, original.evm_canister_id);
+        assert_eq!(cloned.override_rpc_config, original.override_rpc_config);
+        assert_eq!(cloned.min_attached_cycles, original.min_attached_cycles);
+        assert_eq!(cloned.max_num_retries, original.max_num_retries);
+    }
+
+    #[test]
+    fn should_build_independent_clients_from_cloned_builder() {
+        let builder = EvmRpcClient::builder(CallerService::RpcHttpOutCallClient, INFO);
+        let evm_canister_id = Principal::from_text("7hfb6-caaaa-aaaar-qadga-cai").unwrap();
+
+        let custom = builder
+            .clone()
+            .with_evm_canister_id(evm_canister_id)
+            .with_providers(RpcServices::EthMainnet(Some(vec![
+                EthMainnetService::Llama,
+            ])))
+            .build();
+        let default = builder.build();
+
+        assert_eq!(custom.evm_canister_id, evm_canister_id);
+        assert_ne!(default.evm_canister_id, evm_canister_id);
+        assert_eq!(default.providers, RpcServices::EthMainnet(None));
+    }
+}
+
+mod http_headers {
+    use crate::evm_rpc_types::{RpcApi, RpcServices};
+    use crate::logs::INFO;
+    use crate::native_http::eth_rpc::request_headers;
+    use crate::{CallerService, EvmRpcClient};
+    use ic_cdk::management_canister::HttpHeader;
+
+    fn header(name: &str, value: &str) -> HttpHeader {
+        HttpHeader {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn custom_providers() -> RpcServices {
+        RpcServices::Custom {
+            chain_id: 1,
+            services: vec![
+                RpcApi {
+                    url: "https://eth.example.com".to_string(),
+                    headers: Some(vec![header("Authorization", "Bearer api-key")]),
+                },
+                RpcApi {
+                    url: "https://rpc.example.org".to_string(),
+                    headers: Some(vec![header("X-Client", "minter")]),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn should_send_headers_of_each_custom_provider_only() {
+        let client = EvmRpcClient::builder(CallerService::RpcHttpOutCallClient, INFO)
+            .with_providers(custom_providers())
+            .build();
+
+        let services = match client.providers {
+            RpcServices::Custom { services, .. } => services,
+            providers => panic!("BUG: unexpected providers {providers:?}"),
+        };
+        assert_eq!(
+            request_headers(&services[0]),
+            vec![
+                header("Content-Type", "application/json"),
+                header("Authorization", "Bearer api-key"),
+            ]
+        );
+        assert_eq!(
+            request_headers(&services[1]),
+            vec![
+                header("Content-Type", "application/json"),
+                header("X-Client", "minter"),
+            ]
+        );
+    }
+}
+
+mod merge_rpc_config {
+    use crate::evm_rpc_types::{ConsensusStrategy, RpcConfig};
+    use crate::{merge_rpc_config, OverrideRpcConfig};
+
+    fn global() -> RpcConfig {
+        RpcConfig {
+            response_size_estimate: Some(10_000),
+            response_consensus: Some(ConsensusStrategy::Threshold {
+                total: None,
+                min: 2,
+            }),
+            partial_on_error: None,
+        }
+    }
+
+    #[test]
+    fn should_use_global_when_no_override() {
+        assert_eq!(merge_rpc_config(None, &global()), global());
+    }
+
+    #[test]
+    fn should_inherit_unset_fields_from_global() {
+        let config = RpcConfig {
+            response_size_estimate: Some(2048),
+            response_consensus: None,
+            partial_on_error: None,
+        };
+
+        assert_eq!(
+            merge_rpc_config(Some(config), &global()),
+            RpcConfig {
+                response_size_estimate: Some(2048),
+                response_consensus: global().response_consensus,
+                partial_on_error: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_keep_fields_set_in_override() {
+        let config = RpcConfig {
+            response_size_estimate: None,
+            response_consensus: Some(ConsensusStrategy::Equality),
+            partial_on_error: None,
+        };
+
+        assert_eq!(
+            merge_rpc_config(Some(config), &global()),
+            RpcConfig {
+                response_size_estimate: Some(10_000),
+                response_consensus: Some(ConsensusStrategy::Equality),
+                partial_on_error: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_not_merge_without_global_default() {
+        let config = RpcConfig {
+            response_size_estimate: Some(2048),
+            response_consensus: None,
+            partial_on_error: None,
+        };
+        let override_rpc_config = OverrideRpcConfig {
+            eth_get_logs: Some(config.clone()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            override_rpc_config.resolve(override_rpc_config.eth_get_logs.clone()),
+            Some(config)
+        );
+        assert_eq!(override_rpc_config.resolve(None), None);
+    }
+
+    #[test]
+    fn should_resolve_per_method_config_against_global_default() {
+        let override_rpc_config = OverrideRpcConfig {
+            eth_get_logs: Some(RpcConfig {
+                response_size_estimate: Some(2048),
+                response_consensus: None,
+                partial_on_error: None,
+            }),
+            ..OverrideRpcConfig::with_global_default(global())
+        };
+
+        assert_eq!(
+            override_rpc_config.resolve(override_rpc_config.eth_get_logs.clone()),
+            Some(RpcConfig {
+                response_size_estimate: Some(2048),
+                response_consensus: global().response_consensus,
+                partial_on_error: None,
+            })
+        );
+        assert_eq!(
+            override_rpc_config.resolve(override_rpc_config.eth_call.clone()),
+            Some(global())
+        );
+    }
+}
+
+mod retry_attempt_log {
+    use crate::evm_rpc_types::{
+        EthMainnetService, Hex, MultiRpcResult, ProviderError, RejectionCode, RpcError, RpcServices,
+    };
+    use crate::{CallerService, EvmRpcClient, EvmRpcClientBuilder, InterCanisterCall};
+    use async_trait::async_trait;
+    use candid::utils::ArgumentEncoder;
+    use candid::{CandidType, Principal};
+    use ic_canister_log::{LogEntry, Sink};
+    use serde::de::DeserializeOwned;
+    use std::sync::{Arc, Mutex};
+
+    const RETRY_ATTEMPT_PREFIX: &str = "Retry attempt ";
+    const ARGUMENTS: &str = "0xf86c808504a817c800";
+
+    /// Keeps the messages of all entries, shared between the clones of the client.
+    #[derive(Clone, Default)]
+    struct CapturingSink(Arc<Mutex<Vec<String>>>);
+
+    impl CapturingSink {
+        fn messages(&self) -> Vec<String> {
+            self.0.lock().unwrap().clone()
+        }
+
+        fn retry_attempts(&self) -> Vec<String> {
+            self.messages()
+                .into_iter()
+                .filter(|message| message.starts_with(RETRY_ATTEMPT_PREFIX))
+                .collect()
+        }
+    }
+
+    impl Sink for CapturingSink {
+        fn append(&self, entry: LogEntry) {
+            self.0.lock().unwrap().push(entry.message);
+        }
+    }
+
+    /// Answers the first `too_few_cycles_errors` calls with a `TooFewCycles` error.
+    struct MockEvmRpcCanister {
+        too_few_cycles_errors: usize,
+        calls: Mutex<usize>,
+    }
+
+    impl MockEvmRpcCanister {
+        fn new(too_few_cycles_errors: usize) -> Self {
+            Self {
+                too_few_cycles_errors,
+                calls: Mutex::default(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl InterCanisterCall for MockEvmRpcCanister {
+        async fn call<In, Out>(
+            &self,
+            _id: Principal,
+            _method: &str,
+            _args: In,
+            cycles: u128,
+        ) -> Result<Out, (RejectionCode, String)>
+        where
+            In: ArgumentEncoder + Send + 'static,
+            Out: CandidType + DeserializeOwned + 'static,
+        {
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+            let response: MultiRpcResult<Hex> = if *calls <= self.too_few_cycles_errors {
+                MultiRpcResult::Consistent(Err(RpcError::ProviderError(
+                    ProviderError::TooFewCycles {
+                        expected: cycles + 1,
+                        received: cycles,
+                    },
+                )))
+            } else {
+                MultiRpcResult::Consistent(Ok(Hex::from(vec![0x2a])))
+            };
+            Ok(candid::decode_one(&candid::encode_one(&response).unwrap()).unwrap())
+        }
+    }
+
+    fn builder(logger: CapturingSink) -> EvmRpcClientBuilder<CapturingSink> {
+        EvmRpcClient::builder(CallerService::EvmRpcCanisterClient, logger)
+            .with_providers(RpcServices::EthMainnet(Some(vec![
+                EthMainnetService::Alchemy,
+            ])))
+            .with_min_attached_cycles(1_000)
+            .with_max_num_retries(5)
+    }
+
+    #[tokio::test]
+    async fn should_log_each_retry_attempt_once() {
+        for retries in 0..=5 {
+            let logger = CapturingSink::default();
+            let runtime = MockEvmRpcCanister::new(retries);
+
+            let result: MultiRpcResult<Hex> = builder(logger.clone())
+                .build()
+                .call_with_retries(
+                    &runtime,
+                    "eth_sendRawTransaction",
+                    None,
+                    ARGUMENTS.to_string(),
+                    1_000,
+                )
+                .await;
+
+            assert_eq!(
+                result,
+                MultiRpcResult::Consistent(Ok(Hex::from(vec![0x2a])))
+            );
+            assert_eq!(*runtime.calls.lock().unwrap(), retries + 1);
+            assert_eq!(logger.retry_attempts().len(), retries);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_log_attempt_cycles_and_error() {
+        let logger = CapturingSink::default();
+        let runtime = MockEvmRpcCanister::new(2);
+
+        let _result: MultiRpcResult<Hex> = builder(logger.clone())
+            .build()
+            .call_with_retries(
+                &runtime,
+                "eth_sendRawTransaction",
+                None,
+                ARGUMENTS.to_string(),
+                1_000,
+            )
+            .await;
+
+        assert_eq!(
+            logger.retry_attempts(),
+            vec![
+                "Retry attempt 1 of eth_sendRawTransaction with 2000 cycles after error: too few cycles, expected at least 1001 but attached 1000".to_string(),
+                "Retry attempt 2 of eth_sendRawTransaction with 4000 cycles after error: too few cycles, expected at least 2001 but attached 2000".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_only_log_arguments_when_enabled() {
+        for log_arguments in [false, true] {
+            let logger = CapturingSink::default();
+            let runtime = MockEvmRpcCanister::new(1);
+
+            let _result: MultiRpcResult<Hex> = builder(logger.clone())
+                .with_log_arguments(log_arguments)
+                .build()
+                .call_with_retries(
+                    &runtime,
+                    "eth_sendRawTransaction",
+                    None,
+                    ARGUMENTS.to_string(),
+                    1_000,
+                )
+                .await;
+
+            assert_eq!(
+                logger
+                    .messages()
+                    .iter()
+                    .any(|message| message.contains(ARGUMENTS)),
+                log_arguments
+            );
+        }
+    }
+}
+
+mod get_logs_by_halving_range {
+    use crate::evm_rpc_types::{
+        BlockTag, GetLogsArgs, Hex, Hex20, Hex32, JsonRpcError, LogEntry, MultiRpcResult, Nat256,
+        RpcError,
+    };
+    use crate::get_logs_by_halving_range;
+    use candid::Nat;
+    use num_traits::ToPrimitive;
+    use std::sync::Mutex;
+
+    fn args(from: u64, to: u64) -> GetLogsArgs {
+        GetLogsArgs {
+            from_block: Some(BlockTag::Number(Nat256::from(from))),
+            to_block: Some(BlockTag::Number(Nat256::from(to))),
+            addresses: vec![Hex20::from([1; 20])],
+            topics: None,
+        }
+    }
+
+    fn block_number(tag: &Option<BlockTag>) -> u64 {
+        match tag {
+            Some(BlockTag::Number(number)) => Nat::from(number.clone()).0.to_u64().unwrap(),
+            tag => panic!("unexpected block tag {tag:?}"),
+        }
+    }
+
+    fn log_entry(block: u64, tx: u8) -> LogEntry {
+        LogEntry {
+            address: Hex20::from([1; 20]),
+            topics: vec![],
+            data: Hex::from(vec![]),
+            block_number: Some(Nat256::from(block)),
+            transaction_hash: Some(Hex32::from([tx; 32])),
+            transaction_index: Some(Nat256::from(0_u8)),
+            block_hash: Some(Hex32::from([block as u8; 32])),
+            log_index: Some(Nat256::from(block)),
+            removed: false,
+        }
+    }
+
+    fn too_many_logs() -> RpcError {
+        RpcError::JsonRpcError(JsonRpcError {
+            code: -32005,
+            message: "query returned more than 10000 results".to_string(),
+        })
+    }
+
+    /// Provider rejecting every range of more than one block, with one log per block.
+    struct MockProvider {
+        calls: Mutex<Vec<(u64, u64)>>,
+    }
+
+    impl MockProvider {
+        fn new() -> Self {
+            Self {
+                calls: Mutex::default(),
+            }
+        }
+
+        async fn get_logs(&self, args: GetLogsArgs) -> MultiRpcResult<Vec<LogEntry>> {
+            let (from, to) = (block_number(&args.from_block), block_number(&args.to_block));
+            self.calls.lock().unwrap().push((from, to));
+            if to > from {
+                return MultiRpcResult::Consistent(Err(too_many_logs()));
+            }
+            MultiRpcResult::Consistent(Ok(vec![log_entry(from, from as u8)]))
+        }
+    }
+
+    #[tokio::test]
+    async fn should_retrieve_all_logs_by_halving_range() {
+        let provider = MockProvider::new();
+
+        let result =
+            get_logs_by_halving_range(args(100, 107), |args| provider.get_logs(args)).await;
+
+        assert_eq!(
+            result,
+            MultiRpcResult::Consistent(Ok((100..=107)
+                .map(|block| log_entry(block, block as u8))
+                .collect()))
+        );
+        let calls = provider.calls.lock().unwrap();
+        // 8 single blocks are reached after 7 splits, hence 15 calls.
+        assert_eq!(calls.len(), 15);
+        assert_eq!(calls[0], (100, 107));
+        assert_eq!(calls[1], (100, 103));
+        assert_eq!(calls.iter().filter(|(from, to)| from == to).count(), 8);
+    }
+
+    #[tokio::test]
+    async fn should_stop_halving_range_after_max_requests() {
+        use crate::MAX_GET_LOGS_REQUESTS;
+
+        let provider = MockProvider::new();
+
+        let result =
+            get_logs_by_halving_range(args(0, 1_000_000), |args| provider.get_logs(args)).await;
+
+        assert_eq!(result, MultiRpcResult::Consistent(Err(too_many_logs())));
+        let calls = provider.calls.lock().unwrap();
+        assert!(
+            calls.len() <= MAX_GET_LOGS_REQUESTS,
+            "{} calls",
+            calls.len()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_call_once_when_range_is_not_rejected() {
+        let calls = Mutex::new(0);
+        let logs = vec![log_entry(100, 1), log_entry(105, 2)];
+
+        let result = get_logs_by_halving_range(args(100, 109), |_args| {
+            *calls.lock().unwrap() += 1;
+            let logs = logs.clone();
+            async move { MultiRpcResult::Consistent(Ok(logs)) }
+        })
+        .await;
+
+        assert_eq!(result, MultiRpcResult::Consistent(Ok(logs)));
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_dedup_logs_of_sub_ranges() {
+        let result = get_logs_by_halving_range(args(100, 101), |args| async move {
+            let (from, to) = (block_number(&args.from_block), block_number(&args.to_block));
+            if to > from {
+                return MultiRpcResult::Consistent(Err(too_many_logs()));
+            }
+            // the same log is reported for both blocks
+            MultiRpcResult::Consistent(Ok(vec![log_entry(100, 1)]))
+        })
+        .await;
+
+        assert_eq!(
+            result,
+            MultiRpcResult::Consistent(Ok(vec![log_entry(100, 1)]))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_return_error_when_range_cannot_be_split() {
+        let latest = GetLogsArgs {
+            to_block: Some(BlockTag::Latest),
+            ..args(100, 100)
+        };
+        let calls = Mutex::new(0);
+
+        let result = get_logs_by_halving_range(latest, |_args| {
+            *calls.lock().unwrap() += 1;
+            async { MultiRpcResult::Consistent(Err(too_many_logs())) }
+        })
+        .await;
+
+        assert_eq!(result, MultiRpcResult::Consistent(Err(too_many_logs())));
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+}
+
+mod rpc_http_outcall_client {
+    use crate::evm_rpc_types::{
+        GetLogsArgs, MultiRpcResult, ProviderError, RpcApi, RpcError, RpcServices, ValidationError,
+    };
+    use crate::{CallerService, EvmRpcClient};
+    use assert_matches::assert_matches;
+
+    #[tokio::test]
+    async fn should_return_error_when_providers_are_not_supported() {
+        let client = EvmRpcClient::builder_without_logger(CallerService::RpcHttpOutCallClient)
+            .with_providers(RpcServices::EthMainnet(None))
+            .build();
+
+        let result = client
+            .eth_get_logs(GetLogsArgs {
+                from_block: None,
+                to_block: None,
+                addresses: vec![],
+                topics: None,
+            })
+            .await;
+
+        assert_eq!(
+            result,
+            MultiRpcResult::Consistent(Err(RpcError::ProviderError(
+                ProviderError::ProviderNotFound
+            )))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_return_error_on_invalid_transaction_hash() {
+        let client = EvmRpcClient::builder_without_logger(CallerService::RpcHttpOutCallClient)
+            .with_providers(RpcServices::Custom {
+                chain_id: 1,
+                services: vec![RpcApi {
+                    url: "https://ethereum-rpc.publicnode.com".to_string(),
+                    headers: None,
+                }],
+            })
+            .build();
+
+        let result = client
+            .eth_get_transaction_receipt("0xinvalid".to_string())
+            .await;
+
+        assert_matches!(
+            result,
+            MultiRpcResult::Consistent(Err(RpcError::ValidationError(ValidationError::Custom(message))))
+                if message.starts_with("invalid transaction hash")
+        );
+    }
+}
+
+mod build_report {
+    use crate::evm_rpc_types::{EthMainnetService, RpcApi, RpcService, RpcServices};
+    use crate::{BuildReport, CallerService, EvmRpcClient, EvmRpcClientBuilder};
+    use candid::Principal;
+
+    const LOW_CYCLES_WARNING: &str = "min_attached_cycles is below 1B (may cause frequent retries)";
+    const NO_RETRIES_WARNING: &str = "max_num_retries is 0 (errors will not be retried)";
+    const DEFAULT_CANISTER_WARNING: &str =
+        "evm_canister_id is the default (did you forget to call with_evm_canister_id?)";
+
+    fn builder() -> EvmRpcClientBuilder {
+        EvmRpcClient::builder_without_logger(CallerService::EvmRpcCanisterClient)
+            .with_evm_canister_id(Principal::from_text("7hfb6-caaaa-aaaar-qadga-cai").unwrap())
+            .with_providers(RpcServices::EthMainnet(Some(vec![
+                EthMainnetService::Alchemy,
+                EthMainnetService::Ankr,
+            ])))
+    }
+
+    #[test]
+    fn should_report_effective_settings_without_warnings() {
+        let (client, report) = builder()
+            .with_min_attached_cycles(3_000_000_000)
+            .with_max_num_retries(5)
+            .build_verbose();
+
+        assert_eq!(
+            report,
+            BuildReport {
+                selected_providers: vec![
+                    RpcService::EthMainnet(EthMainnetService::Alchemy),
+                    RpcService::EthMainnet(EthMainnetService::Ankr),
+                ],
+                effective_min_cycles: 3_000_000_000,
+                effective_max_retries: 5,
+                warnings: vec![],
+            }
+        );
+        assert_eq!(client.min_attached_cycles, 3_000_000_000);
+        assert_eq!(client.max_num_retries, 5);
+    }
+
+    #[test]
+    fn should_warn_when_min_attached_cycles_is_low() {
+        let (_client, report) = builder()
+            .with_min_attached_cycles(999_999_999)
+            .build_verbose();
+
+        assert_eq!(report.warnings, vec![LOW_CYCLES_WARNING.to_string()]);
+    }
+
+    #[test]
+    fn should_warn_when_errors_are_not_retried() {
+        let (_client, report) = builder().with_max_num_retries(0).build_verbose();
+
+        assert_eq!(report.warnings, vec![NO_RETRIES_WARNING.to_string()]);
+    }
+
+    #[test]
+    fn should_warn_when_evm_canister_id_is_default() {
+        let (_client, report) =
+            EvmRpcClient::builder_without_logger(CallerService::EvmRpcCanisterClient)
+                .build_verbose();
+
+        assert_eq!(report.warnings, vec![DEFAULT_CANISTER_WARNING.to_string()]);
+        assert_eq!(report.selected_providers, Vec::<RpcService>::new());
+    }
+
+    #[test]
+    fn should_not_warn_about_evm_canister_id_when_using_http_outcalls() {
+        let api = RpcApi {
+            url: "https://ethereum-rpc.publicnode.com".to_string(),
+            headers: None,
+        };
+        let (_client, report) =
+            EvmRpcClient::builder_without_logger(CallerService::RpcHttpOutCallClient)
+                .with_providers(RpcServices::Custom {
+                    chain_id: 1,
+                    services: vec![api.clone()],
+                })
+                .build_verbose();
+
+        assert_eq!(report.warnings, Vec::<String>::new());
+        assert_eq!(report.selected_providers, vec![RpcService::Custom(api)]);
+    }
+
+    #[test]
+    fn should_report_all_warnings() {
+        let (_client, report) =
+            EvmRpcClient::builder_without_logger(CallerService::EvmRpcCanisterClient)
+                .with_min_attached_cycles(0)
+                .with_max_num_retries(0)
+                .build_verbose();
+
+        assert_eq!(
+            report.warnings,
+            vec![
+                LOW_CYCLES_WARNING.to_string(),
+                NO_RETRIES_WARNING.to_string(),
+                DEFAULT_CANISTER_WARNING.to_string(),
+            ]
+        );
+    }
+}
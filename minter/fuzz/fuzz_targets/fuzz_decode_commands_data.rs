@@ -0,0 +1,17 @@
+#![no_main]
+
+use evm_minter::swap::command_data::{decode_commands_data, encode_commands_data};
+use libfuzzer_sys::fuzz_target;
+
+// Each line of the input is one hex-encoded command, as in `DexOrderArgs::commands_data`.
+fuzz_target!(|data: &[u8]| {
+    let Ok(commands_data) = std::str::from_utf8(data) else {
+        return;
+    };
+    let commands_data: Vec<String> = commands_data.split('\n').map(str::to_string).collect();
+    if let Ok(decoded) = decode_commands_data(&commands_data) {
+        assert_eq!(decoded.len(), commands_data.len());
+        let reencoded = encode_commands_data(&decoded);
+        assert_eq!(decode_commands_data(&reencoded), Ok(decoded));
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use evm_minter::rpc_declarations::parse_fee_history;
+use libfuzzer_sys::fuzz_target;
+
+// `parse_fee_history` is called with the fee history returned by RPC providers.
+fuzz_target!(|data: &[u8]| {
+    let Ok(fee_history) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Some(parsed) = parse_fee_history(fee_history.to_string()) {
+        // must not overflow, even for the largest block numbers
+        let _ = parsed.newest_block();
+        let reencoded = serde_json::to_string(&parsed).expect("parsed fee history must serialize");
+        assert_eq!(parse_fee_history(reencoded), Some(parsed));
+    }
+});
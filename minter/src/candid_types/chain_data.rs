@@ -11,4 +11,6 @@ pub struct ChainData {
     pub latest_block_number: Nat,
     pub fee_history: String,
     pub native_token_usd_price: Option<f64>,
+    // unix timestamp in seconds of the latest block
+    pub latest_block_timestamp: Option<u64>,
 }
@@ -1,5 +1,8 @@
 use crate::{
     numeric::{Erc20Value, GasAmount, Wei},
+    rpc_declarations::Data,
+    state::transactions::data::Command,
+    swap::command_data::decode_commands_data,
     tx::gas_usd::MaxFeeUsd,
 };
 use evm_rpc_client::eth_types::Address;
@@ -8,6 +11,45 @@ use std::str::FromStr;
 
 use super::*;
 
+/// Maximum number of commands, and of `commands_data` items, in a dex order.
+pub const MAX_DEX_ORDER_COMMANDS: usize = 16;
+
+/// Maximum size in bytes of a single decoded `commands_data` item.
+pub const MAX_COMMAND_DATA_BYTES: usize = 4 * 1024;
+
+/// Maximum size in bytes of all decoded `commands_data` items together.
+pub const MAX_COMMANDS_DATA_TOTAL_BYTES: usize = 8 * 1024;
+
+pub const MAX_TX_ID_LENGTH: usize = 128;
+
+/// Maximum length of the `max_gas_fee_usd` and `signing_fee` decimal strings.
+pub const MAX_FEE_USD_LENGTH: usize = 64;
+
+/// Dex orders whose deadline is further in the future are rejected.
+pub const MAX_DEADLINE_HORIZON_SECS: u64 = 24 * 60 * 60;
+
+/// Default time in seconds after which a refund swap that was not executed yet reverts, so
+/// that the refund is created again instead of remaining valid forever.
+pub const DEFAULT_REFUND_DEADLINE_WINDOW_SECS: u64 = 2 * 60 * 60;
+
+/// Refund swaps need at least this long to be signed and mined before their deadline.
+pub const MIN_REFUND_DEADLINE_WINDOW_SECS: u64 = 10 * 60;
+
+/// Deadlines are in seconds since the UNIX epoch, larger values (after the year 33658) are
+/// most likely in milliseconds or nanoseconds and would never expire on chain.
+pub const MAX_DEADLINE_SECS: u64 = 1_000_000_000_000;
+
+/// Converts the `deadline` of a dex order to seconds since the UNIX epoch, rejecting values
+/// that do not fit or that look like milliseconds or nanoseconds.
+pub fn deadline_secs(deadline: &Nat) -> Result<u64, DexOrderError> {
+    match deadline.0.to_u64() {
+        Some(deadline) if deadline <= MAX_DEADLINE_SECS => Ok(deadline),
+        _ => Err(DexOrderError::DeadlineNotInSeconds {
+            deadline: deadline.clone(),
+        }),
+    }
+}
+
 // candid file designed for operations sent by appic dex
 #[derive(CandidType, Deserialize, Clone, Debug, Encode, Decode, Eq, PartialEq)]
 pub struct DexOrderArgs {
@@ -42,20 +84,6 @@ impl DexOrderArgs {
         self.tx_id.to_lowercase()
     }
 
-    pub fn gas_limit(&self) -> Result<GasAmount, String> {
-        GasAmount::try_from(self.gas_limit.clone())
-            .map_err(|_| "ERROR: failed to convert Nat to u256".to_string())
-    }
-
-    pub fn recipient(&self) -> Result<Address, String> {
-        Address::from_str(&self.recipient).map_err(|_| "ERROR: Invalid recipient".to_string())
-    }
-
-    pub fn deadline(&self) -> Result<Erc20Value, String> {
-        Erc20Value::try_from(self.deadline.clone())
-            .map_err(|_| "ERROR: failed to convert Nat to u256".to_string())
-    }
-
     pub fn amount(&self) -> Nat {
         self.amount_in.clone()
     }
@@ -101,15 +129,6 @@ impl DexOrderArgs {
         .ok()
     }
 
-    pub fn erc20_ledger_burn_index(&self) -> LedgerBurnIndex {
-        LedgerBurnIndex::new(
-            self.erc20_ledger_burn_index
-                .0
-                .to_u64()
-                .expect("nat does not fit into u64"),
-        )
-    }
-
     pub fn dedicated_signing_fee_twin_usdc_amount(
         &self,
         twin_usdc_decimals: u8,
@@ -119,6 +138,233 @@ impl DexOrderArgs {
             .to_twin_usdc_amount(twin_usdc_decimals)
             .ok()
     }
+
+    /// Returns the deadline in seconds since the UNIX epoch, if it is after `now_secs` but not
+    /// more than [`MAX_DEADLINE_HORIZON_SECS`] after.
+    pub fn deadline(&self, now_secs: u64) -> Result<u64, DexOrderError> {
+        let deadline = deadline_secs(&self.deadline)?;
+        if deadline <= now_secs {
+            return Err(DexOrderError::DeadlineExpired { deadline, now_secs });
+        }
+        let max_deadline = now_secs.saturating_add(MAX_DEADLINE_HORIZON_SECS);
+        if deadline > max_deadline {
+            return Err(DexOrderError::DeadlineTooFar {
+                deadline,
+                max_deadline,
+            });
+        }
+        Ok(deadline)
+    }
+
+    /// Drops the commands and fees of the order, which are not needed to refund it and may
+    /// exceed the bounds checked by [`ValidatedDexOrderArgs::try_from`].
+    pub fn without_swap_details(self) -> Self {
+        Self {
+            commands: vec![],
+            commands_data: vec![],
+            max_gas_fee_usd: None,
+            signing_fee: None,
+            ..self
+        }
+    }
+
+    /// Validates a dex order received at `now_secs` (seconds since the UNIX epoch).
+    ///
+    /// On top of the checks of [`ValidatedDexOrderArgs::try_from`], the deadline is checked
+    /// by [`DexOrderArgs::deadline`].
+    pub fn validate(self, now_secs: u64) -> Result<ValidatedDexOrderArgs, DexOrderError> {
+        let validated = ValidatedDexOrderArgs::try_from(self)?;
+        validated.args.deadline(now_secs)?;
+        Ok(validated)
+    }
+
+    /// Checks the sizes of the variable-length fields, before anything is parsed.
+    fn check_bounds(&self) -> Result<(), DexOrderError> {
+        if self.commands.len() > MAX_DEX_ORDER_COMMANDS {
+            return Err(DexOrderError::InvalidCommand(format!(
+                "more than {MAX_DEX_ORDER_COMMANDS} commands"
+            )));
+        }
+        if self.commands_data.len() > MAX_DEX_ORDER_COMMANDS {
+            return Err(DexOrderError::InvalidCommandData(format!(
+                "more than {MAX_DEX_ORDER_COMMANDS} items"
+            )));
+        }
+        // A hex string prefixed with 0x encodes at most (len - 2) / 2 bytes.
+        let mut total_bytes: usize = 0;
+        for (index, item) in self.commands_data.iter().enumerate() {
+            let bytes = item.len().saturating_sub(2) / 2;
+            if bytes > MAX_COMMAND_DATA_BYTES {
+                return Err(DexOrderError::InvalidCommandData(format!(
+                    "item {index} is larger than {MAX_COMMAND_DATA_BYTES} bytes"
+                )));
+            }
+            total_bytes = total_bytes.saturating_add(bytes);
+        }
+        if total_bytes > MAX_COMMANDS_DATA_TOTAL_BYTES {
+            return Err(DexOrderError::InvalidCommandData(format!(
+                "larger than {MAX_COMMANDS_DATA_TOTAL_BYTES} bytes in total"
+            )));
+        }
+        for fee in [&self.max_gas_fee_usd, &self.signing_fee]
+            .into_iter()
+            .flatten()
+        {
+            if fee.len() > MAX_FEE_USD_LENGTH {
+                return Err(DexOrderError::InvalidMaxUsdFeeAmount(format!(
+                    "longer than {MAX_FEE_USD_LENGTH} bytes"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fields of a [`DexOrderArgs`] needed to refund it.
+///
+/// The twin USDC of a dex order is burned before the order is received, so that an order failing
+/// any other check of [`ValidatedDexOrderArgs`] is still refunded as long as these fields are valid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefundableDexOrderArgs {
+    pub args: DexOrderArgs,
+    pub amount_in: Erc20Value,
+    pub recipient: Address,
+    pub erc20_ledger_burn_index: LedgerBurnIndex,
+}
+
+impl RefundableDexOrderArgs {
+    pub fn tx_id(&self) -> String {
+        self.args.tx_id()
+    }
+}
+
+impl TryFrom<DexOrderArgs> for RefundableDexOrderArgs {
+    type Error = DexOrderError;
+
+    fn try_from(args: DexOrderArgs) -> Result<Self, Self::Error> {
+        if args.tx_id.len() > MAX_TX_ID_LENGTH {
+            return Err(DexOrderError::InvalidArgs(format!(
+                "invalid tx_id: longer than {MAX_TX_ID_LENGTH} bytes"
+            )));
+        }
+        let amount_in = Erc20Value::try_from(args.amount_in.clone())
+            .map_err(|_| DexOrderError::InvalidAmount)?;
+        let recipient =
+            Address::from_str(&args.recipient).map_err(DexOrderError::InvalidRecipient)?;
+        let erc20_ledger_burn_index = args
+            .erc20_ledger_burn_index
+            .0
+            .to_u64()
+            .map(LedgerBurnIndex::new)
+            .ok_or_else(|| {
+                DexOrderError::InvalidArgs(
+                    "invalid erc20_ledger_burn_index: nat does not fit into u64".to_string(),
+                )
+            })?;
+        Ok(Self {
+            args,
+            amount_in,
+            recipient,
+            erc20_ledger_burn_index,
+        })
+    }
+}
+
+/// [`DexOrderArgs`] whose fields were all validated when received from the appic DEX,
+/// so that malformed orders are refunded instead of being executed.
+///
+/// The conversion does not depend on the time, new orders go through [`DexOrderArgs::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidatedDexOrderArgs {
+    pub args: DexOrderArgs,
+    pub amount_in: Erc20Value,
+    pub min_amount_out: Erc20Value,
+    pub commands: Vec<Command>,
+    pub commands_data: Vec<Data>,
+    pub gas_limit: GasAmount,
+    pub deadline: Erc20Value,
+    pub recipient: Address,
+    pub erc20_ledger_burn_index: LedgerBurnIndex,
+}
+
+impl ValidatedDexOrderArgs {
+    pub fn tx_id(&self) -> String {
+        self.args.tx_id()
+    }
+
+    pub fn refundable(&self) -> RefundableDexOrderArgs {
+        RefundableDexOrderArgs {
+            args: self.args.clone(),
+            amount_in: self.amount_in,
+            recipient: self.recipient,
+            erc20_ledger_burn_index: self.erc20_ledger_burn_index,
+        }
+    }
+}
+
+impl TryFrom<DexOrderArgs> for ValidatedDexOrderArgs {
+    type Error = DexOrderError;
+
+    fn try_from(args: DexOrderArgs) -> Result<Self, Self::Error> {
+        let RefundableDexOrderArgs {
+            args,
+            amount_in,
+            recipient,
+            erc20_ledger_burn_index,
+        } = RefundableDexOrderArgs::try_from(args)?;
+        args.check_bounds()?;
+        let min_amount_out = Erc20Value::try_from(args.min_amount_out.clone())
+            .map_err(|_| DexOrderError::InvalidMinAmountIn)?;
+        let commands = args
+            .commands
+            .iter()
+            .map(|&command| Command::from_u8(command))
+            .collect::<Result<Vec<Command>, String>>()
+            .map_err(DexOrderError::InvalidCommand)?;
+        let commands_data =
+            decode_commands_data(&args.commands_data).map_err(DexOrderError::InvalidCommandData)?;
+        let gas_limit =
+            GasAmount::try_from(args.gas_limit.clone()).map_err(DexOrderError::InvalidGasLimit)?;
+        let deadline =
+            Erc20Value::try_from(args.deadline.clone()).map_err(DexOrderError::InvalidDeadline)?;
+        Ok(Self {
+            args,
+            amount_in,
+            min_amount_out,
+            commands,
+            commands_data,
+            gas_limit,
+            deadline,
+            recipient,
+            erc20_ledger_burn_index,
+        })
+    }
+}
+
+/// Fees and amounts a [`DexOrderArgs`] would currently result in, see `validate_dex_order`.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DexOrderQuote {
+    pub max_transaction_fee: Nat,
+    pub max_gas_fee_twin_usdc: Nat,
+    pub amount_in_minus_fees: Nat,
+    /// Whether the gas tank currently holds enough native tokens to cover `max_transaction_fee`.
+    pub gas_tank_has_capacity: bool,
+}
+
+/// Current fees of a swap with a given gas limit, see `get_swap_fee_quote`.
+/// Orders whose `amount_in` is below `total_fee_twin_usdc` are rejected.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SwapFeeQuote {
+    pub max_transaction_fee: Nat,
+    pub max_gas_fee_twin_usdc: Nat,
+    pub signing_fee_twin_usdc: Nat,
+    pub total_fee_twin_usdc: Nat,
+    /// Time at which the native token USD price used for the conversion was fetched.
+    pub native_token_usd_price_timestamp: u64,
+    /// Time at which the gas fee estimate `max_transaction_fee` is computed from was fetched.
+    pub gas_fee_estimate_timestamp: u64,
+    /// Whether the gas tank currently holds enough native tokens to cover `max_transaction_fee`.
+    pub gas_tank_has_capacity: bool,
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Encode, Decode)]
@@ -152,4 +398,27 @@ pub enum DexOrderError {
         #[cbor(n(1), with = "crate::cbor::nat")]
         available: Nat,
     },
+    #[n(12)]
+    DestinationNotAllowed,
+    #[n(13)]
+    InvalidArgs(#[n(0)] String),
+    #[n(14)]
+    DeadlineNotInSeconds {
+        #[cbor(n(0), with = "crate::cbor::nat")]
+        deadline: Nat,
+    },
+    #[n(15)]
+    DeadlineExpired {
+        #[n(0)]
+        deadline: u64,
+        #[n(1)]
+        now_secs: u64,
+    },
+    #[n(16)]
+    DeadlineTooFar {
+        #[n(0)]
+        deadline: u64,
+        #[n(1)]
+        max_deadline: u64,
+    },
 }
@@ -0,0 +1,307 @@
+use crate::candid_types::dex_orders::DexOrderError;
+use crate::candid_types::withdraw_erc20::{self, WithdrawErc20Error};
+use crate::candid_types::withdraw_native::WithdrawalError;
+use crate::candid_types::wrapped_icrc::{self, WrapIcrcError};
+use crate::candid_types::{
+    ActivateSwapError, BackfillScrapeError, RequestScrapingError, RevokeApprovalError,
+};
+use crate::icrc_21;
+use candid::{CandidType, Deserialize, Nat};
+
+#[cfg(test)]
+mod tests;
+
+/// Common interface over the minter's error enums so that callers and monitoring
+/// tools can classify failures without matching on every individual type.
+///
+/// Codes are stable and grouped by category:
+/// * `1..=999`: ICRC-21 consent message errors
+/// * `1000..=1999`: native withdrawals
+/// * `2000..=2999`: ERC-20 withdrawals
+/// * `3000..=3999`: wrapping of ICRC tokens
+/// * `4000..=4999`: DEX orders
+/// * `5000..=5999`: log scraping requests and backfills
+/// * `6000..=6999`: swap feature activation and approval revocation
+pub trait MinterError: std::fmt::Debug {
+    /// Stable numeric code identifying the error variant.
+    fn error_code(&self) -> u32;
+
+    /// Whether retrying the same request later may succeed.
+    fn is_retryable(&self) -> bool;
+
+    fn message(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn to_generic_error(&self) -> GenericError {
+        GenericError {
+            code: self.error_code(),
+            message: self.message(),
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GenericError {
+    pub code: u32,
+    pub message: String,
+}
+
+impl MinterError for WithdrawalError {
+    fn error_code(&self) -> u32 {
+        match self {
+            WithdrawalError::AmountTooLow { .. } => 1000,
+            WithdrawalError::InsufficientFunds { .. } => 1001,
+            WithdrawalError::InsufficientAllowance { .. } => 1002,
+            WithdrawalError::TemporarilyUnavailable(_) => 1003,
+            WithdrawalError::InvalidDestination(_) => 1004,
+            WithdrawalError::DestinationNotAllowed => 1005,
+            WithdrawalError::TooOld => 1006,
+            WithdrawalError::CreatedInFuture { .. } => 1007,
+            WithdrawalError::AmountTooLarge { .. } => 1008,
+            WithdrawalError::AlreadyProcessed { .. } => 1009,
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            WithdrawalError::TemporarilyUnavailable(_) | WithdrawalError::CreatedInFuture { .. }
+        )
+    }
+}
+
+impl MinterError for WithdrawErc20Error {
+    fn error_code(&self) -> u32 {
+        match self {
+            WithdrawErc20Error::TokenNotSupported { .. } => 2000,
+            WithdrawErc20Error::NativeLedgerError { .. } => 2001,
+            WithdrawErc20Error::NativeFeeTransferError { .. } => 2002,
+            WithdrawErc20Error::Erc20LedgerError { .. } => 2003,
+            WithdrawErc20Error::TemporarilyUnavailable(_) => 2004,
+            WithdrawErc20Error::InvalidDestination(_) => 2005,
+            WithdrawErc20Error::DestinationNotAllowed => 2006,
+            WithdrawErc20Error::AmountTooLarge { .. } => 2007,
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            WithdrawErc20Error::NativeLedgerError { error }
+            | WithdrawErc20Error::Erc20LedgerError { error, .. } => {
+                matches!(
+                    error,
+                    withdraw_erc20::LedgerError::TemporarilyUnavailable(_)
+                )
+            }
+            WithdrawErc20Error::NativeFeeTransferError { error } => {
+                matches!(error, withdraw_erc20::FeeError::TemporarilyUnavailable(_))
+            }
+            WithdrawErc20Error::TemporarilyUnavailable(_) => true,
+            WithdrawErc20Error::TokenNotSupported { .. }
+            | WithdrawErc20Error::InvalidDestination(_)
+            | WithdrawErc20Error::DestinationNotAllowed
+            | WithdrawErc20Error::AmountTooLarge { .. } => false,
+        }
+    }
+}
+
+impl MinterError for WrapIcrcError {
+    fn error_code(&self) -> u32 {
+        match self {
+            WrapIcrcError::TokenNotSupported { .. } => 3000,
+            WrapIcrcError::NativeLedgerError { .. } => 3001,
+            WrapIcrcError::NativeFeeTransferError { .. } => 3002,
+            WrapIcrcError::IcrcLedgerError { .. } => 3003,
+            WrapIcrcError::AmountTooLow => 3004,
+            WrapIcrcError::TemporarilyUnavailable(_) => 3005,
+            WrapIcrcError::InvalidDestination(_) => 3006,
+            WrapIcrcError::TransferFeeUnknow(_) => 3007,
+            WrapIcrcError::DestinationNotAllowed => 3008,
+            WrapIcrcError::NativeFeeAboveMaximum { .. } => 3009,
+            WrapIcrcError::AmountTooLarge { .. } => 3010,
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            WrapIcrcError::NativeLedgerError { error }
+            | WrapIcrcError::IcrcLedgerError { error, .. } => {
+                matches!(error, wrapped_icrc::LedgerError::TemporarilyUnavailable(_))
+            }
+            WrapIcrcError::NativeFeeTransferError { error } => {
+                matches!(error, wrapped_icrc::FeeError::TemporarilyUnavailable(_))
+            }
+            WrapIcrcError::TemporarilyUnavailable(_) | WrapIcrcError::TransferFeeUnknow(_) => true,
+            WrapIcrcError::TokenNotSupported { .. }
+            | WrapIcrcError::AmountTooLow
+            | WrapIcrcError::InvalidDestination(_)
+            | WrapIcrcError::DestinationNotAllowed
+            | WrapIcrcError::NativeFeeAboveMaximum { .. }
+            | WrapIcrcError::AmountTooLarge { .. } => false,
+        }
+    }
+}
+
+impl MinterError for DexOrderError {
+    fn error_code(&self) -> u32 {
+        match self {
+            DexOrderError::InvalidAmount => 4000,
+            DexOrderError::InvalidMinAmountIn => 4001,
+            DexOrderError::TemporarilyUnavailable(_) => 4002,
+            DexOrderError::InvalidMaxUsdFeeAmount(_) => 4003,
+            DexOrderError::MaxUsdFeeTooLow => 4004,
+            DexOrderError::UsdcAmountInTooLow => 4005,
+            DexOrderError::InvalidCommand(_) => 4006,
+            DexOrderError::InvalidCommandData(_) => 4007,
+            DexOrderError::InvalidRecipient(_) => 4008,
+            DexOrderError::InvalidGasLimit(_) => 4009,
+            DexOrderError::InvalidDeadline(_) => 4010,
+            DexOrderError::NotEnoughGasInGasTank { .. } => 4011,
+            DexOrderError::DestinationNotAllowed => 4012,
+            DexOrderError::InvalidArgs(_) => 4013,
+            DexOrderError::DeadlineNotInSeconds { .. } => 4014,
+            DexOrderError::DeadlineExpired { .. } => 4015,
+            DexOrderError::DeadlineTooFar { .. } => 4016,
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            DexOrderError::TemporarilyUnavailable(_) | DexOrderError::NotEnoughGasInGasTank { .. }
+        )
+    }
+}
+
+impl MinterError for RequestScrapingError {
+    fn error_code(&self) -> u32 {
+        match self {
+            RequestScrapingError::CalledTooManyTimes => 5000,
+            RequestScrapingError::InvalidBlockNumber => 5001,
+            RequestScrapingError::BlockAlreadyObserved => 5002,
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        matches!(self, RequestScrapingError::CalledTooManyTimes)
+    }
+}
+
+impl MinterError for BackfillScrapeError {
+    fn error_code(&self) -> u32 {
+        match self {
+            BackfillScrapeError::InvalidBlockNumber => 5100,
+            BackfillScrapeError::InvalidBlockRange => 5101,
+            BackfillScrapeError::BlockRangeTooLarge { .. } => 5102,
+            BackfillScrapeError::BlockNotScrapedYet { .. } => 5103,
+            BackfillScrapeError::ScrapingNotActive => 5104,
+            BackfillScrapeError::TemporarilyUnavailable(_) => 5105,
+            BackfillScrapeError::ScrapingFailed(_) => 5106,
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            BackfillScrapeError::TemporarilyUnavailable(_)
+            | BackfillScrapeError::ScrapingFailed(_) => true,
+            BackfillScrapeError::InvalidBlockNumber
+            | BackfillScrapeError::InvalidBlockRange
+            | BackfillScrapeError::BlockRangeTooLarge { .. }
+            | BackfillScrapeError::BlockNotScrapedYet { .. }
+            | BackfillScrapeError::ScrapingNotActive => false,
+        }
+    }
+}
+
+impl MinterError for ActivateSwapError {
+    fn error_code(&self) -> u32 {
+        match self {
+            ActivateSwapError::TokenNotFound { .. } => 6000,
+            ActivateSwapError::InvalidSwapContractAddress(_) => 6001,
+            ActivateSwapError::InvalidFeeValue(_) => 6002,
+            ActivateSwapError::FeeEstimateUnavailable => 6003,
+            ActivateSwapError::NativeLedgerError { .. } => 6004,
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            ActivateSwapError::NativeLedgerError { error } => {
+                matches!(
+                    error,
+                    withdraw_erc20::LedgerError::TemporarilyUnavailable(_)
+                )
+            }
+            ActivateSwapError::FeeEstimateUnavailable => true,
+            ActivateSwapError::TokenNotFound { .. }
+            | ActivateSwapError::InvalidSwapContractAddress(_)
+            | ActivateSwapError::InvalidFeeValue(_) => false,
+        }
+    }
+}
+
+impl MinterError for RevokeApprovalError {
+    fn error_code(&self) -> u32 {
+        match self {
+            RevokeApprovalError::InvalidAddress(_) => 6100,
+            RevokeApprovalError::ApprovalNotFound => 6101,
+            RevokeApprovalError::RevocationPending => 6102,
+            RevokeApprovalError::FeeEstimateUnavailable => 6103,
+            RevokeApprovalError::NativeLedgerError { .. } => 6104,
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            RevokeApprovalError::NativeLedgerError { error } => {
+                matches!(
+                    error,
+                    withdraw_erc20::LedgerError::TemporarilyUnavailable(_)
+                )
+            }
+            RevokeApprovalError::FeeEstimateUnavailable => true,
+            RevokeApprovalError::InvalidAddress(_)
+            | RevokeApprovalError::ApprovalNotFound
+            | RevokeApprovalError::RevocationPending => false,
+        }
+    }
+}
+
+/// Errors returned by `icrc21_canister_call_consent_message` as ICRC-21 generic errors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConsentMessageError {
+    UnsupportedLanguage,
+    ArgumentDecodingFailed(String),
+}
+
+impl MinterError for ConsentMessageError {
+    fn error_code(&self) -> u32 {
+        match self {
+            ConsentMessageError::UnsupportedLanguage => 1,
+            ConsentMessageError::ArgumentDecodingFailed(_) => 100,
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        false
+    }
+
+    fn message(&self) -> String {
+        match self {
+            ConsentMessageError::UnsupportedLanguage => "Unsupported language".to_string(),
+            ConsentMessageError::ArgumentDecodingFailed(reason) => {
+                format!("Failed to decode arguments: {reason}")
+            }
+        }
+    }
+}
+
+impl From<GenericError> for icrc_21::Error {
+    fn from(error: GenericError) -> Self {
+        icrc_21::Error::GenericError {
+            error_code: Nat::from(error.code),
+            description: error.message,
+        }
+    }
+}
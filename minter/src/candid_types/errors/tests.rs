@@ -0,0 +1,203 @@
+use crate::candid_types::dex_orders::DexOrderError;
+use crate::candid_types::errors::{ConsentMessageError, GenericError, MinterError};
+use crate::candid_types::withdraw_erc20::{self, WithdrawErc20Error};
+use crate::candid_types::withdraw_native::WithdrawalError;
+use crate::candid_types::wrapped_icrc::{self, WrapIcrcError};
+use crate::candid_types::{
+    ActivateSwapError, BackfillScrapeError, RequestScrapingError, RevokeApprovalError,
+};
+use candid::Nat;
+use std::collections::BTreeMap;
+
+fn all_errors() -> Vec<Box<dyn MinterError>> {
+    let erc20_ledger_error =
+        || withdraw_erc20::LedgerError::TemporarilyUnavailable("ledger".to_string());
+    let icrc_ledger_error =
+        || wrapped_icrc::LedgerError::TemporarilyUnavailable("ledger".to_string());
+    vec![
+        Box::new(ConsentMessageError::UnsupportedLanguage),
+        Box::new(ConsentMessageError::ArgumentDecodingFailed(
+            "bad".to_string(),
+        )),
+        Box::new(WithdrawalError::AmountTooLow {
+            min_withdrawal_amount: Nat::from(1_u8),
+        }),
+        Box::new(WithdrawalError::InsufficientFunds {
+            balance: Nat::from(1_u8),
+        }),
+        Box::new(WithdrawalError::InsufficientAllowance {
+            allowance: Nat::from(1_u8),
+        }),
+        Box::new(WithdrawalError::TemporarilyUnavailable("down".to_string())),
+        Box::new(WithdrawalError::InvalidDestination("0x".to_string())),
+        Box::new(WithdrawalError::DestinationNotAllowed),
+        Box::new(WithdrawalError::TooOld),
+        Box::new(WithdrawalError::CreatedInFuture { ledger_time: 1 }),
+        Box::new(WithdrawalError::AmountTooLarge {
+            max_withdrawal_amount: Nat::from(1_u8),
+        }),
+        Box::new(WithdrawalError::AlreadyProcessed {
+            duplicate_of: Nat::from(1_u8),
+        }),
+        Box::new(WithdrawErc20Error::TokenNotSupported {
+            supported_tokens: vec![],
+        }),
+        Box::new(WithdrawErc20Error::NativeLedgerError {
+            error: erc20_ledger_error(),
+        }),
+        Box::new(WithdrawErc20Error::NativeFeeTransferError {
+            error: withdraw_erc20::FeeError::TemporarilyUnavailable("fee".to_string()),
+        }),
+        Box::new(WithdrawErc20Error::Erc20LedgerError {
+            native_block_index: Nat::from(1_u8),
+            error: erc20_ledger_error(),
+        }),
+        Box::new(WithdrawErc20Error::TemporarilyUnavailable(
+            "down".to_string(),
+        )),
+        Box::new(WithdrawErc20Error::InvalidDestination("0x".to_string())),
+        Box::new(WithdrawErc20Error::DestinationNotAllowed),
+        Box::new(WithdrawErc20Error::AmountTooLarge {
+            max_withdrawal_amount: Nat::from(1_u8),
+        }),
+        Box::new(WrapIcrcError::TokenNotSupported {
+            supported_tokens: vec![],
+        }),
+        Box::new(WrapIcrcError::NativeLedgerError {
+            error: icrc_ledger_error(),
+        }),
+        Box::new(WrapIcrcError::NativeFeeTransferError {
+            error: wrapped_icrc::FeeError::TemporarilyUnavailable("fee".to_string()),
+        }),
+        Box::new(WrapIcrcError::IcrcLedgerError {
+            native_block_index: Nat::from(1_u8),
+            error: icrc_ledger_error(),
+        }),
+        Box::new(WrapIcrcError::AmountTooLow),
+        Box::new(WrapIcrcError::TemporarilyUnavailable("down".to_string())),
+        Box::new(WrapIcrcError::InvalidDestination("0x".to_string())),
+        Box::new(WrapIcrcError::TransferFeeUnknow("fee".to_string())),
+        Box::new(WrapIcrcError::DestinationNotAllowed),
+        Box::new(WrapIcrcError::NativeFeeAboveMaximum {
+            native_fee: Nat::from(2_u8),
+            maximum: Nat::from(1_u8),
+        }),
+        Box::new(WrapIcrcError::AmountTooLarge {
+            max_wrap_amount: Nat::from(1_u8),
+        }),
+        Box::new(DexOrderError::InvalidAmount),
+        Box::new(DexOrderError::InvalidMinAmountIn),
+        Box::new(DexOrderError::TemporarilyUnavailable("down".to_string())),
+        Box::new(DexOrderError::InvalidMaxUsdFeeAmount("fee".to_string())),
+        Box::new(DexOrderError::MaxUsdFeeTooLow),
+        Box::new(DexOrderError::UsdcAmountInTooLow),
+        Box::new(DexOrderError::InvalidCommand("cmd".to_string())),
+        Box::new(DexOrderError::InvalidCommandData("data".to_string())),
+        Box::new(DexOrderError::InvalidRecipient("0x".to_string())),
+        Box::new(DexOrderError::InvalidGasLimit("gas".to_string())),
+        Box::new(DexOrderError::InvalidDeadline("deadline".to_string())),
+        Box::new(DexOrderError::NotEnoughGasInGasTank {
+            requested: Nat::from(2_u8),
+            available: Nat::from(1_u8),
+        }),
+        Box::new(DexOrderError::DestinationNotAllowed),
+        Box::new(DexOrderError::InvalidArgs("recipient".to_string())),
+        Box::new(DexOrderError::DeadlineNotInSeconds {
+            deadline: Nat::from(1_u8),
+        }),
+        Box::new(DexOrderError::DeadlineExpired {
+            deadline: 1,
+            now_secs: 2,
+        }),
+        Box::new(DexOrderError::DeadlineTooFar {
+            deadline: 2,
+            max_deadline: 1,
+        }),
+        Box::new(RequestScrapingError::CalledTooManyTimes),
+        Box::new(RequestScrapingError::InvalidBlockNumber),
+        Box::new(RequestScrapingError::BlockAlreadyObserved),
+        Box::new(BackfillScrapeError::InvalidBlockNumber),
+        Box::new(BackfillScrapeError::InvalidBlockRange),
+        Box::new(BackfillScrapeError::BlockRangeTooLarge { max_blocks: 5_000 }),
+        Box::new(BackfillScrapeError::BlockNotScrapedYet {
+            last_scraped_block_number: Nat::from(1_u8),
+        }),
+        Box::new(BackfillScrapeError::ScrapingNotActive),
+        Box::new(BackfillScrapeError::TemporarilyUnavailable(
+            "busy".to_string(),
+        )),
+        Box::new(BackfillScrapeError::ScrapingFailed("error".to_string())),
+        Box::new(ActivateSwapError::TokenNotFound {
+            twin_usdc_ledger_id: candid::Principal::anonymous(),
+        }),
+        Box::new(ActivateSwapError::InvalidSwapContractAddress(
+            "0x".to_string(),
+        )),
+        Box::new(ActivateSwapError::InvalidFeeValue("fee".to_string())),
+        Box::new(ActivateSwapError::FeeEstimateUnavailable),
+        Box::new(ActivateSwapError::NativeLedgerError {
+            error: erc20_ledger_error(),
+        }),
+        Box::new(RevokeApprovalError::InvalidAddress("0x".to_string())),
+        Box::new(RevokeApprovalError::ApprovalNotFound),
+        Box::new(RevokeApprovalError::RevocationPending),
+        Box::new(RevokeApprovalError::FeeEstimateUnavailable),
+        Box::new(RevokeApprovalError::NativeLedgerError {
+            error: erc20_ledger_error(),
+        }),
+    ]
+}
+
+#[test]
+fn should_have_unique_error_codes() {
+    let mut seen = BTreeMap::new();
+    for error in all_errors() {
+        if let Some(previous) = seen.insert(error.error_code(), format!("{error:?}")) {
+            panic!(
+                "error code {} used by both {previous} and {error:?}",
+                error.error_code()
+            );
+        }
+    }
+}
+
+#[test]
+fn should_keep_icrc21_error_codes() {
+    assert_eq!(
+        ConsentMessageError::UnsupportedLanguage.to_generic_error(),
+        GenericError {
+            code: 1,
+            message: "Unsupported language".to_string(),
+        }
+    );
+    assert_eq!(
+        ConsentMessageError::ArgumentDecodingFailed("invalid".to_string()).to_generic_error(),
+        GenericError {
+            code: 100,
+            message: "Failed to decode arguments: invalid".to_string(),
+        }
+    );
+}
+
+#[test]
+fn should_flag_temporarily_unavailable_errors_as_retryable() {
+    for error in all_errors() {
+        let message = error.message();
+        if message.contains("TemporarilyUnavailable") {
+            assert!(error.is_retryable(), "{message} should be retryable");
+        }
+    }
+    assert!(!WithdrawalError::InvalidDestination("0x".to_string()).is_retryable());
+    assert!(WithdrawalError::CreatedInFuture { ledger_time: 1 }.is_retryable());
+    assert!(!WithdrawalError::TooOld.is_retryable());
+    assert!(!WithdrawErc20Error::Erc20LedgerError {
+        native_block_index: Nat::from(1_u8),
+        error: withdraw_erc20::LedgerError::InsufficientFunds {
+            balance: Nat::from(0_u8),
+            failed_burn_amount: Nat::from(1_u8),
+            token_symbol: "USDC".to_string(),
+            ledger_id: candid::Principal::anonymous(),
+        },
+    }
+    .is_retryable());
+}
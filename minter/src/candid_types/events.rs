@@ -1,7 +1,11 @@
 use crate::candid_types::dex_orders::DexOrderArgs;
+use crate::candid_types::{
+    AddOrRemove, CandidConfirmationPolicy, Erc20TransferKind, ListKind, SymbolCollisionPolicy,
+};
 use crate::lifecycle::InitArg;
 use crate::lifecycle::UpgradeArg;
 use candid::{CandidType, Deserialize, Nat, Principal};
+use icrc_ledger_types::icrc1::account::Account;
 use serde_bytes::ByteBuf;
 
 #[derive(CandidType, Deserialize, Debug, Clone)]
@@ -14,6 +18,28 @@ pub struct GetEventsArg {
 pub struct GetEventsResult {
     pub events: Vec<Event>,
     pub total_event_count: u64,
+    // absolute index of the first event that was not archived
+    pub archived_event_count: u64,
+    // events referring only to blocks before this one may have been pruned, see `compact_event_log`
+    pub earliest_unpruned_block: Nat,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompactionResult {
+    pub pruned_count: u64,
+    pub threshold_block: Nat,
+}
+
+/// Length-prefixed CBOR-encoded events, see `storage::raw_events_chunk` for the framing.
+#[derive(CandidType, Deserialize, Debug, Clone)]
+pub struct GetEventsRawResult {
+    pub events: ByteBuf,
+    pub next_start: u64,
+    pub total_event_count: u64,
+    // absolute index of the first event that was not archived
+    pub archived_event_count: u64,
+    // events can be archived once the log retains more events than this, see `archive_events`
+    pub max_retained_event_count: Option<u64>,
 }
 
 #[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -22,6 +48,12 @@ pub struct Event {
     pub payload: EventPayload,
 }
 
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProviderResponseHash {
+    pub provider: String,
+    pub hash: String,
+}
+
 #[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct EventSource {
     pub transaction_hash: String,
@@ -102,6 +134,7 @@ pub enum EventPayload {
         principal: Principal,
         erc20_contract_address: String,
         subaccount: Option<[u8; 32]>,
+        original_owner: Option<String>,
     },
     InvalidDeposit {
         event_source: EventSource,
@@ -124,6 +157,7 @@ pub enum EventPayload {
         created_at: Option<u64>,
         l1_fee: Option<Nat>,
         withdrawal_fee: Option<Nat>,
+        is_contract_destination: Option<bool>,
     },
     CreatedTransaction {
         withdrawal_id: Nat,
@@ -164,6 +198,8 @@ pub enum EventPayload {
         address: String,
         erc20_token_symbol: String,
         erc20_ledger_id: Principal,
+        transfer_kind: Option<Erc20TransferKind>,
+        symbol_collision_policy: Option<SymbolCollisionPolicy>,
     },
     AcceptedErc20WithdrawalRequest {
         max_transaction_fee: Nat,
@@ -179,6 +215,7 @@ pub enum EventPayload {
         l1_fee: Option<Nat>,
         withdrawal_fee: Option<Nat>,
         is_wrapped_mint: bool,
+        gas_limit: Option<Nat>,
     },
     FailedErc20WithdrawalRequest {
         withdrawal_id: Nat,
@@ -332,4 +369,144 @@ pub enum EventPayload {
         usdc_withdrawn: Nat,
         native_deposited: Nat,
     },
+    RemovedLog {
+        event_source: EventSource,
+    },
+    DepositReorged {
+        event_source: EventSource,
+    },
+    UpgradeGraceScrape {
+        start_block: Nat,
+        end_block: Nat,
+    },
+    UpdatedAddressList {
+        list: ListKind,
+        action: AddOrRemove,
+        addresses: Vec<String>,
+    },
+    RescrapedBlockRange {
+        from_block: Nat,
+        to_block: Nat,
+    },
+    SkippedBlockRange {
+        from_block: Nat,
+        to_block: Nat,
+    },
+    ChangedConfirmationPolicy {
+        from: CandidConfirmationPolicy,
+        to: CandidConfirmationPolicy,
+    },
+    ParkedTransaction {
+        withdrawal_id: Nat,
+        cancellation: UnsignedTransaction,
+    },
+    ProviderDisagreement {
+        method: String,
+        block_range_or_hash: String,
+        per_provider_hash: Vec<ProviderResponseHash>,
+        timestamp: u64,
+    },
+    ArchivedEvents {
+        from_index: u64,
+        up_to_index: u64,
+        hash: String,
+    },
+    ProviderRateLimited {
+        provider: String,
+        retry_after_ns: u64,
+    },
+    CancelledWithdrawalRequest {
+        withdrawal_id: Nat,
+        reimbursed_amount: Nat,
+        to: Principal,
+        to_subaccount: Option<[u8; 32]>,
+    },
+    EventLogCompacted {
+        pruned_count: u64,
+        threshold_block: Nat,
+    },
+    UpgradedWhileNotDrained {
+        in_flight_count: u64,
+    },
+    NativeLedgerMetadataMismatch {
+        symbol: String,
+        decimals: u8,
+    },
+    AcceptedApprovalRevocationRequest {
+        erc20_contract_address: String,
+        spender: String,
+        native_ledger_burn_index: Nat,
+    },
+    BackfillCompleted {
+        from_block: Nat,
+        to_block: Nat,
+        accepted_events: u64,
+        invalid_events: u64,
+        duplicate_events: u64,
+    },
+    RealizedSwapAmountOut {
+        withdrawal_id: Nat,
+        amount_out: Nat,
+    },
+    FeatureFlagChanged {
+        flag: String,
+        enabled: bool,
+    },
+    FeeCollected {
+        ledger_id: Principal,
+        amount: Nat,
+        block_index: Nat,
+    },
+    CollectedFeesWithdrawn {
+        ledger_id: Principal,
+        to: Account,
+        amount: Nat,
+        block_index: Nat,
+    },
+    FeeCapExceeded {
+        withdrawal_id: Nat,
+        max_fee_per_gas: Nat,
+        max_transaction_fee: Nat,
+        max_fee_per_gas_cap: Nat,
+        max_transaction_fee_cap: Nat,
+        timestamp: u64,
+    },
+    BroadcastAttempted {
+        withdrawal_id: Nat,
+        transaction_hash: String,
+        nonce: Nat,
+        timestamp: u64,
+    },
+    BroadcastReconciled {
+        withdrawal_id: Nat,
+        transaction_hash: String,
+        outcome: String,
+    },
+    QuarantinedWithdrawal {
+        withdrawal_id: Nat,
+    },
+    UnaccountedFeesFound {
+        ledger_id: Principal,
+        amount: Nat,
+    },
+    CollectedFeesWithdrawalStarted {
+        ledger_id: Principal,
+        to: Account,
+        transfer_amount: Nat,
+        ledger_fee: Nat,
+        created_at_time: u64,
+        amount: Nat,
+    },
+    CollectedFeesWithdrawalFailed {
+        ledger_id: Principal,
+        reason: String,
+    },
+    MissingFeesFound {
+        ledger_id: Principal,
+        amount: Nat,
+    },
+    NativeLedgerMetadataMatched {
+        symbol: String,
+        decimals: u8,
+    },
 }
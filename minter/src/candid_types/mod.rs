@@ -1,13 +1,19 @@
 use crate::candid_types::withdraw_native::SwapDetails;
 use crate::candid_types::wrapped_icrc::WrappedIcrcToken;
+use crate::instrumentation::EndpointStats;
 use crate::numeric::LedgerBurnIndex;
+use crate::rpc_client::providers::Provider;
 use crate::rpc_declarations::TransactionReceipt;
+use crate::state::balances::TokenVolume;
+use crate::state::daily_stats::TokenDailyStats;
 use crate::state::transactions::NativeWithdrawalRequest;
 use crate::state::transactions::{self, Erc20WithdrawalRequest};
+use crate::state::OnchainApproval;
 use crate::tx::gas_fees::TransactionPrice;
 use crate::tx::SignedEip1559TransactionRequest;
 use candid::{CandidType, Deserialize, Nat, Principal};
 use evm_rpc_client::eth_types::Address;
+use evm_rpc_client::native_http::weighted::LatencyStats;
 use icrc_ledger_types::icrc1::account::Account;
 use minicbor::{Decode, Encode};
 use serde::Serialize;
@@ -16,6 +22,7 @@ use std::str::FromStr;
 
 pub mod chain_data;
 pub mod dex_orders;
+pub mod errors;
 pub mod events;
 pub mod withdraw_erc20;
 pub mod withdraw_native;
@@ -30,6 +37,9 @@ pub struct Icrc28TrustedOriginsResponse {
 #[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct Eip1559TransactionPriceArg {
     pub erc20_ledger_id: Principal,
+    /// Destination of a native withdrawal, priced with the higher gas limit of contract
+    /// destinations if it was recently found to have deployed code.
+    pub destination: Option<String>,
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -38,6 +48,8 @@ pub struct Eip1559TransactionPrice {
     pub max_fee_per_gas: Nat,
     pub max_priority_fee_per_gas: Nat,
     pub max_transaction_fee: Nat,
+    /// Lowest `max_priority_fee_per_gas` of the network used by the minter.
+    pub min_priority_fee_per_gas: Option<Nat>,
     pub timestamp: Option<u64>,
 }
 
@@ -48,6 +60,7 @@ impl From<TransactionPrice> for Eip1559TransactionPrice {
             max_fee_per_gas: value.max_fee_per_gas.into(),
             max_priority_fee_per_gas: value.max_priority_fee_per_gas.into(),
             max_transaction_fee: value.max_transaction_fee().into(),
+            min_priority_fee_per_gas: None,
             timestamp: None,
         }
     }
@@ -58,6 +71,7 @@ pub struct Erc20Token {
     pub erc20_token_symbol: String,
     pub erc20_contract_address: String,
     pub ledger_canister_id: Principal,
+    pub transfer_kind: Erc20TransferKind,
 }
 
 impl From<crate::erc20::ERC20Token> for Erc20Token {
@@ -66,10 +80,39 @@ impl From<crate::erc20::ERC20Token> for Erc20Token {
             erc20_token_symbol: value.erc20_token_symbol.to_string(),
             erc20_contract_address: value.erc20_contract_address.to_string(),
             ledger_canister_id: value.erc20_ledger_id,
+            transfer_kind: value.transfer_kind.unwrap_or_default(),
         }
     }
 }
 
+/// How an ERC-20 token contract reports the outcome of `transfer`.
+#[derive(
+    CandidType,
+    Debug,
+    Default,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Encode,
+    Decode,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[cbor(index_only)]
+pub enum Erc20TransferKind {
+    /// Returns a boolean and reverts if the transfer fails.
+    #[default]
+    #[n(0)]
+    Standard,
+    /// Returns no value and may not revert if the transfer fails, e.g. older USDT forks.
+    /// Withdrawals are only considered successful if the receipt contains the `Transfer` log.
+    #[n(1)]
+    NonStandard,
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct GasTankBalance {
     pub native_balance: Nat,
@@ -82,6 +125,146 @@ pub struct Erc20Balance {
     pub balance: Nat,
 }
 
+/// Cumulative volumes of an ERC-20 token bridged through the minter.
+#[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct CandidTokenVolume {
+    pub erc20_contract_address: String,
+    // total amount of minted deposits
+    pub deposited: Nat,
+    // total amount of accepted withdrawal requests, including the reimbursed ones
+    pub withdrawn: Nat,
+    // total amount reimbursed for failed or cancelled withdrawals
+    pub reimbursed: Nat,
+}
+
+impl CandidTokenVolume {
+    pub fn new(erc20_contract_address: &Address, volume: &TokenVolume) -> Self {
+        Self {
+            erc20_contract_address: erc20_contract_address.to_string(),
+            deposited: volume.deposited.into(),
+            withdrawn: volume.withdrawn.into(),
+            reimbursed: volume.reimbursed.into(),
+        }
+    }
+}
+
+/// Minted deposits and successful withdrawals of a token during one UTC day.
+#[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct CandidDailyStats {
+    // number of days since the Unix epoch
+    pub day: u64,
+    // ledger id of the token
+    pub token: Principal,
+    pub deposit_count: u64,
+    // in the smallest unit of the token
+    pub deposit_volume: Nat,
+    pub withdrawal_count: u64,
+    // in the smallest unit of the token
+    pub withdrawal_volume: Nat,
+}
+
+impl CandidDailyStats {
+    pub fn new(day: u64, token: Principal, stats: &TokenDailyStats) -> Self {
+        Self {
+            day,
+            token,
+            deposit_count: stats.deposit_count,
+            deposit_volume: stats.deposit_volume.into(),
+            withdrawal_count: stats.withdrawal_count,
+            withdrawal_volume: stats.withdrawal_volume.into(),
+        }
+    }
+}
+
+/// ERC-20 approval granted by the minter that was not revoked yet.
+#[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct CandidOnchainApproval {
+    pub erc20_contract_address: String,
+    pub spender: String,
+    // IC time at which the approval request was accepted
+    pub granted_at: u64,
+    pub transaction_hash: String,
+}
+
+impl CandidOnchainApproval {
+    pub fn new(
+        (erc20_contract_address, spender): &(Address, Address),
+        approval: &OnchainApproval,
+    ) -> Self {
+        Self {
+            erc20_contract_address: erc20_contract_address.to_string(),
+            spender: spender.to_string(),
+            granted_at: approval.granted_at,
+            transaction_hash: approval.transaction_hash.to_string(),
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct MinterHealth {
+    pub endpoints: Vec<EndpointInstrumentation>,
+    pub last_observed_block_timestamp: Option<Nat>,
+    pub last_scraped_block_timestamp: Option<Nat>,
+    pub last_observed_block_lag_seconds: Option<u64>,
+    pub last_scraped_block_lag_seconds: Option<u64>,
+    // Number of block ranges whose logs were never scraped, see `get_scraping_gaps`
+    pub scraping_gap_count: u64,
+    // Timer-driven tasks currently holding their guard, e.g. `ScrapLogs` while logs are scraped
+    pub active_tasks: Vec<String>,
+    // Violated state invariants, e.g. pending deposits exceeding the tracked balance
+    pub invariant_violations: Option<String>,
+}
+
+/// Instructions executed by an update endpoint or a timer-driven task since the last reset.
+#[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct EndpointInstrumentation {
+    pub endpoint: String,
+    pub count: u64,
+    pub total_instructions: u64,
+    pub max_instructions: u64,
+}
+
+impl EndpointInstrumentation {
+    pub fn new(endpoint: &str, stats: &EndpointStats) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            count: stats.count,
+            total_instructions: stats.total_instructions,
+            max_instructions: stats.max_instructions,
+        }
+    }
+}
+
+/// Latency of the JSON-RPC calls to a provider since the last upgrade.
+#[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ProviderStat {
+    pub provider: String,
+    pub p50_ns: u64,
+    pub p95_ns: u64,
+    pub call_count: u64,
+    pub error_count: u64,
+    /// IC time in nanoseconds until which the provider is skipped because it rate limited
+    /// requests, if it is still cooling down.
+    pub rate_limited_until_ns: Option<u64>,
+}
+
+impl ProviderStat {
+    pub fn new(
+        provider: &Provider,
+        stats: &LatencyStats,
+        rate_limited_until_ns: Option<u64>,
+    ) -> Self {
+        Self {
+            provider: format!("{provider:?}"),
+            p50_ns: stats.p50_ns,
+            p95_ns: stats.p95_ns,
+            call_count: stats.call_count,
+            error_count: stats.error_count,
+            rate_limited_until_ns,
+        }
+    }
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct IcrcBalance {
     pub icrc_token: Principal,
@@ -117,6 +300,86 @@ pub struct MinterInfo {
     pub twin_usdc_info: Option<CandidTwinUsdcInfo>,
     pub canister_signing_fee_twin_usdc_value: Option<Nat>,
     pub next_swap_ledger_burn_index: Option<Nat>,
+    // estimated cycles spent on ledger burn calls since the last upgrade
+    pub total_ledger_call_cycles_spent: Option<Nat>,
+    pub confirmation_policy: Option<CandidConfirmationPolicy>,
+    // last observed block number after applying the confirmation policy
+    pub effective_confirmed_block_number: Option<Nat>,
+    pub pending_withdrawal_stats: Option<PendingWithdrawalStats>,
+    // time elapsed since the oldest withdrawal request whose transaction is not finalized yet
+    pub oldest_pending_withdrawal_age_ns: Option<u64>,
+    pub token_volumes: Option<Vec<CandidTokenVolume>>,
+    // hex-encoded SHA-256 hash of the installed Wasm module
+    pub wasm_hash: Option<String>,
+    // number of changes to the canister, including upgrades
+    pub canister_version: Option<u64>,
+    pub onchain_approvals: Option<Vec<CandidOnchainApproval>>,
+    // unix timestamps in seconds of the last observed and last scraped blocks
+    pub last_observed_block_timestamp: Option<Nat>,
+    pub last_scraped_block_timestamp: Option<Nat>,
+    // time elapsed since the last observed and last scraped blocks were produced
+    pub last_observed_block_lag_seconds: Option<u64>,
+    pub last_scraped_block_lag_seconds: Option<u64>,
+    // number of `sign_with_ecdsa` calls and cycles spent on them since the last upgrade
+    pub ecdsa_signing_calls: Option<u64>,
+    pub ecdsa_signing_cycles_spent: Option<Nat>,
+    // number of signing calls that the cycles balance can still pay for
+    pub estimated_remaining_signing_capacity: Option<u64>,
+    // transactions whose fees exceed these caps are not signed
+    pub max_fee_per_gas_cap: Option<Nat>,
+    pub max_transaction_fee_cap: Option<Nat>,
+    pub withdrawal_transaction_stats: Option<WithdrawalTransactionStats>,
+}
+
+/// Number of withdrawals in each state of their transaction. Finalized transactions that
+/// failed are also counted as reimbursed once their reimbursement is minted.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct WithdrawalTransactionStats {
+    pub pending: u32,
+    pub created: u32,
+    pub sent: u32,
+    pub finalized: u32,
+    pub reimbursed: u32,
+}
+
+/// Number of withdrawal requests of each kind whose transaction is not finalized yet.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct PendingWithdrawalStats {
+    pub native: u32,
+    pub erc20: u32,
+    pub icrc_wrap: u32,
+    // approvals of the swap contract are counted as swaps
+    pub swap: u32,
+}
+
+impl PendingWithdrawalStats {
+    pub fn total(&self) -> u32 {
+        self.native
+            .saturating_add(self.erc20)
+            .saturating_add(self.icrc_wrap)
+            .saturating_add(self.swap)
+    }
+}
+
+impl<'a> FromIterator<&'a transactions::WithdrawalRequest> for PendingWithdrawalStats {
+    fn from_iter<I: IntoIterator<Item = &'a transactions::WithdrawalRequest>>(iter: I) -> Self {
+        let mut stats = Self::default();
+        for request in iter {
+            let count = match request {
+                transactions::WithdrawalRequest::Native(_) => &mut stats.native,
+                transactions::WithdrawalRequest::Erc20(request)
+                    if request.is_wrapped_mint.unwrap_or_default() =>
+                {
+                    &mut stats.icrc_wrap
+                }
+                transactions::WithdrawalRequest::Erc20(_) => &mut stats.erc20,
+                transactions::WithdrawalRequest::Erc20Approve(_)
+                | transactions::WithdrawalRequest::Swap(_) => &mut stats.swap,
+            };
+            *count = count.saturating_add(1);
+        }
+        stats
+    }
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq)]
@@ -139,8 +402,9 @@ pub struct NativeTokenUsdPriceEstimate {
     pub timestamp: u64,
 }
 
-#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq, Hash, Encode, Decode)]
 pub struct Transaction {
+    #[n(0)]
     pub transaction_hash: String,
 }
 
@@ -186,6 +450,17 @@ pub enum CandidBlockTag {
     Finalized,
 }
 
+/// How many blocks a deposit must be buried under before it is scraped.
+#[derive(CandidType, Debug, Serialize, Deserialize, Clone, Copy, Encode, Decode, PartialEq, Eq)]
+pub enum CandidConfirmationPolicy {
+    /// Scrape up to the block with the given tag, minus the network specific safety margin.
+    #[n(0)]
+    BlockTag(#[n(0)] CandidBlockTag),
+    /// Scrape up to the latest block minus the given number of confirmations.
+    #[n(1)]
+    Confirmations(#[n(0)] u32),
+}
+
 impl From<NativeWithdrawalRequest> for RetrieveNativeRequest {
     fn from(value: NativeWithdrawalRequest) -> Self {
         Self {
@@ -194,6 +469,120 @@ impl From<NativeWithdrawalRequest> for RetrieveNativeRequest {
     }
 }
 
+/// Destination address list managed by the controller.
+#[derive(CandidType, Debug, Serialize, Deserialize, Clone, Copy, Encode, Decode, PartialEq, Eq)]
+#[cbor(index_only)]
+pub enum ListKind {
+    /// Destinations that are always refused.
+    #[n(0)]
+    Deny,
+    /// If non-empty, the only destinations that are accepted.
+    #[n(1)]
+    Allow,
+    /// Protected destinations, such as helper contracts, that are nevertheless accepted.
+    #[n(2)]
+    ProtectedOverride,
+}
+
+#[derive(CandidType, Debug, Serialize, Deserialize, Clone, Copy, Encode, Decode, PartialEq, Eq)]
+#[cbor(index_only)]
+pub enum AddOrRemove {
+    #[n(0)]
+    Add,
+    #[n(1)]
+    Remove,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum UpdateAddressListError {
+    InvalidAddress(String),
+    ListFull { max_entries: u64 },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DrainStatus {
+    pub draining: bool,
+    pub in_flight_count: u64,
+    // true once draining and nothing is in flight anymore, so that the canister can be stopped
+    pub safe_to_stop: bool,
+}
+
+/// Value of a feature flag, see `get_feature_flags`.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CandidFeatureFlag {
+    pub flag: String,
+    pub enabled: bool,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum SetFeatureFlagError {
+    UnknownFlag {
+        flag: String,
+        known_flags: Vec<String>,
+    },
+    // swapping cannot be enabled before the swap feature is activated
+    SwapFeatureNotActivated,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum WithdrawCollectedFeesError {
+    // no fee collector was configured with the init or upgrade arguments
+    NoFeeCollector,
+    AlreadyProcessing,
+    AmountExceedsCollectedFees { collected: Nat },
+    // the fee subaccount holds less than the collected fees according to the events while
+    // withdrawals are in flight, it is reconciled with them otherwise
+    BalanceBelowCollectedFees { balance: Nat, collected: Nat },
+    AmountTooLow { ledger_fee: Nat },
+    TemporarilyUnavailable(String),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ArchiveEventsError {
+    /// No maximum number of retained events is configured.
+    ArchivingDisabled,
+    /// The event log does not retain more events than the configured maximum.
+    BelowThreshold {
+        retained_event_count: u64,
+        max_retained_event_count: u64,
+    },
+    /// `up_to_index` must be past the first retained event and at most the total event count.
+    InvalidRange {
+        archived_event_count: u64,
+        total_event_count: u64,
+    },
+    InvalidHash(String),
+    /// The expected hash does not match the hash of the events to archive.
+    HashMismatch,
+    /// A single call archives at most `max_archived_event_count` events.
+    TooManyEvents {
+        max_archived_event_count: u64,
+    },
+    /// The events before `up_to_index` are still being archived.
+    ArchiveInProgress {
+        up_to_index: u64,
+    },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum CompactEventLogError {
+    /// `prune_threshold_blocks` is below the minimum number of blocks.
+    ThresholdTooLow {
+        min_prune_threshold_blocks: u64,
+    },
+    /// Only the events before `compactable_up_to_index` are settled.
+    UnsettledEvents {
+        compactable_up_to_index: u64,
+    },
+    ArchiveEvents(ArchiveEventsError),
+}
+
+impl From<ArchiveEventsError> for CompactEventLogError {
+    fn from(error: ArchiveEventsError) -> Self {
+        Self::ArchiveEvents(error)
+    }
+}
+
 #[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
 pub enum RetrieveWithdrawalStatus {
     NotFound,
@@ -203,16 +592,28 @@ pub enum RetrieveWithdrawalStatus {
     TxFinalized(TxFinalizedStatus),
 }
 
-#[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Encode, Decode)]
 pub enum TxFinalizedStatus {
+    #[n(0)]
     Success {
+        #[n(0)]
         transaction_hash: String,
+        #[cbor(n(1), with = "crate::cbor::nat::option")]
         effective_transaction_fee: Option<Nat>,
+        // Amount of output tokens received by the recipient of a swap, `None` for other
+        // withdrawals and for swaps finalized before it was recorded
+        #[cbor(n(2), with = "crate::cbor::nat::option")]
+        realized_amount_out: Option<Nat>,
     },
-    PendingReimbursement(Transaction),
+    #[n(1)]
+    PendingReimbursement(#[n(0)] Transaction),
+    #[n(2)]
     Reimbursed {
+        #[n(0)]
         transaction_hash: String,
+        #[cbor(n(1), with = "crate::cbor::nat")]
         reimbursed_amount: Nat,
+        #[cbor(n(2), with = "crate::cbor::nat")]
         reimbursed_in_block: Nat,
     },
 }
@@ -255,34 +656,94 @@ pub enum DepositStatus {
 
 pub type CandidSwapTxId = String;
 
-#[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Encode, Decode)]
 pub enum SwapStatus {
     // from minter status
+    #[n(0)]
     AcceptedSwap,
-    MintedToAppicDex(CandidSwapTxId),
-    NotifiedAppicDex(CandidSwapTxId),
+    #[n(1)]
+    MintedToAppicDex(#[n(0)] CandidSwapTxId),
+    #[n(2)]
+    NotifiedAppicDex(#[n(0)] CandidSwapTxId),
 
     // to minter status
-    PendingSwap(SwapDetails),
-    SwapTxCreated(SwapDetails),
-    SwapTxSent(Transaction),
-    SwapTxFinalized(TxFinalizedStatus),
+    #[n(3)]
+    PendingSwap(#[n(0)] SwapDetails),
+    #[n(4)]
+    SwapTxCreated(#[n(0)] SwapDetails),
+    #[n(5)]
+    SwapTxSent(#[n(0)] Transaction),
+    #[n(6)]
+    SwapTxFinalized(#[n(0)] TxFinalizedStatus),
     // in case the swap tx is failed and the refund is being processed
-    PendingFailedSwap(SwapDetails),
-    PendingRefundSwap(SwapDetails),
-    RefundSwapTxCreated(SwapDetails),
-    RefundSwapTxSent(Transaction),
-    RefundSwapTxFinalized(TxFinalizedStatus),
+    #[n(7)]
+    PendingFailedSwap(#[n(0)] SwapDetails),
+    #[n(8)]
+    PendingRefundSwap(#[n(0)] SwapDetails),
+    #[n(9)]
+    RefundSwapTxCreated(#[n(0)] SwapDetails),
+    #[n(10)]
+    RefundSwapTxSent(#[n(0)] Transaction),
+    #[n(11)]
+    RefundSwapTxFinalized(#[n(0)] TxFinalizedStatus),
 
+    #[n(12)]
     QuarantinedSwap,
 }
 
+#[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct SwapStatusTransition {
+    // IC time in nanoseconds of the event that caused the transition
+    pub timestamp: u64,
+    pub status: SwapStatus,
+}
+
+/// Expected outcome of a deposit, see `quote_deposit`.
+#[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DepositQuote {
+    // ERC-20 contract address, or `None` for the native token
+    pub token: Option<String>,
+    pub token_symbol: String,
+    // Decimals reported by the ledger, `None` for ERC-20 tokens
+    pub decimals: Option<u8>,
+    pub ledger_id: Principal,
+    pub amount: Nat,
+    // deposits are minted 1:1, the `deposit_native_fee` init argument is deprecated
+    pub expected_minted_amount: Nat,
+    pub confirmation_policy: CandidConfirmationPolicy,
+    // `None` when waiting for the safe or finalized block of the network
+    pub blocks_before_minting: Option<u64>,
+    pub scraping_interval_seconds: u64,
+    pub helper_contract_address: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ScrapingGap {
+    pub from_block: Nat,
+    pub to_block: Nat,
+    // IC time in nanoseconds of the event that first skipped a block of this range
+    pub skipped_at: u64,
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
 pub struct AddErc20Token {
     pub chain_id: Nat,
     pub address: String,
     pub erc20_token_symbol: String,
     pub erc20_ledger_id: Principal,
+    /// Defaults to [`Erc20TransferKind::Standard`].
+    pub transfer_kind: Option<Erc20TransferKind>,
+    /// Defaults to [`SymbolCollisionPolicy::Reject`].
+    pub symbol_collision_policy: Option<SymbolCollisionPolicy>,
+}
+
+/// What to do when adding a token whose symbol is already used by another token.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymbolCollisionPolicy {
+    #[default]
+    Reject,
+    /// Adds the token with the first free symbol among `<symbol>.2`, `<symbol>.3`, ...
+    Disambiguate,
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
@@ -292,6 +753,25 @@ pub enum RequestScrapingError {
     BlockAlreadyObserved,
 }
 
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum BackfillScrapeError {
+    InvalidBlockNumber,
+    InvalidBlockRange,
+    BlockRangeTooLarge { max_blocks: u64 },
+    BlockNotScrapedYet { last_scraped_block_number: Nat },
+    ScrapingNotActive,
+    TemporarilyUnavailable(String),
+    ScrapingFailed(String),
+}
+
+/// Number of events found by `backfill_scrape`, by outcome.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackfillSummary {
+    pub accepted_events: u64,
+    pub invalid_events: u64,
+    pub duplicate_events: u64,
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
 pub struct ActivateSwapReqest {
     pub twin_usdc_ledger_id: Principal,
@@ -300,3 +780,21 @@ pub struct ActivateSwapReqest {
     pub dex_canister_id: Principal,
     pub canister_signing_fee_twin_usdc_value: Nat,
 }
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum ActivateSwapError {
+    TokenNotFound { twin_usdc_ledger_id: Principal },
+    InvalidSwapContractAddress(String),
+    InvalidFeeValue(String),
+    FeeEstimateUnavailable,
+    NativeLedgerError { error: withdraw_erc20::LedgerError },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum RevokeApprovalError {
+    InvalidAddress(String),
+    ApprovalNotFound,
+    RevocationPending,
+    FeeEstimateUnavailable,
+    NativeLedgerError { error: withdraw_erc20::LedgerError },
+}
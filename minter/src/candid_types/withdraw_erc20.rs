@@ -41,8 +41,12 @@ pub enum WithdrawErc20Error {
         native_block_index: Nat,
         error: LedgerError,
     },
+    AmountTooLarge {
+        max_withdrawal_amount: Nat,
+    },
     TemporarilyUnavailable(String),
     InvalidDestination(String),
+    DestinationNotAllowed,
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
@@ -104,6 +108,14 @@ impl From<LedgerBurnError> for LedgerError {
                 token_symbol: ledger.token_symbol.to_string(),
                 ledger_id: ledger.id,
             },
+            LedgerBurnError::Duplicate { duplicate_of, .. } => {
+                panic!(
+                    "BUG: unexpected duplicate of burn {duplicate_of}, burns are not deduplicated"
+                )
+            }
+            LedgerBurnError::TooOld { .. } | LedgerBurnError::CreatedInFuture { .. } => {
+                panic!("BUG: unexpected created_at_time error, burns are not deduplicated")
+            }
         }
     }
 }
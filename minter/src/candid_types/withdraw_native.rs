@@ -6,15 +6,43 @@ use super::*;
 pub struct WithdrawalArg {
     pub amount: Nat,
     pub recipient: String,
+    /// Deduplicates retried calls on the native ledger: a withdrawal with the same
+    /// arguments and `created_at_time` returns the original request instead of burning again.
+    pub created_at_time: Option<u64>,
+    /// Allows withdrawing to the contract of a supported ERC-20 token, which is rejected by
+    /// default since most of them cannot receive the native token.
+    pub allow_contract_destination: Option<bool>,
 }
 
 #[derive(CandidType, Deserialize, Debug, PartialEq)]
 pub enum WithdrawalError {
-    AmountTooLow { min_withdrawal_amount: Nat },
-    InsufficientFunds { balance: Nat },
-    InsufficientAllowance { allowance: Nat },
+    AmountTooLow {
+        min_withdrawal_amount: Nat,
+    },
+    AmountTooLarge {
+        max_withdrawal_amount: Nat,
+    },
+    InsufficientFunds {
+        balance: Nat,
+    },
+    InsufficientAllowance {
+        allowance: Nat,
+    },
     TemporarilyUnavailable(String),
     InvalidDestination(String),
+    DestinationNotAllowed,
+    /// `created_at_time` is older than the deduplication window of the native ledger.
+    TooOld,
+    /// `created_at_time` is ahead of the native ledger time.
+    CreatedInFuture {
+        ledger_time: u64,
+    },
+    /// A burn with the same arguments and `created_at_time` was already executed at
+    /// `duplicate_of`, but the minter holds no withdrawal of the caller for it, e.g. because
+    /// it was cancelled.
+    AlreadyProcessed {
+        duplicate_of: Nat,
+    },
 }
 
 impl From<LedgerBurnError> for WithdrawalError {
@@ -36,10 +64,32 @@ impl From<LedgerBurnError> for WithdrawalError {
             } => {
                 panic!("BUG: withdrawal amount {failed_burn_amount} on the Native ledger {ledger:?} should always be higher than the ledger transaction fee {minimum_burn_amount}")
             }
+            LedgerBurnError::Duplicate { duplicate_of, .. } => {
+                panic!("BUG: duplicate burns must be resolved to the original withdrawal {duplicate_of}")
+            }
+            LedgerBurnError::TooOld { .. } => Self::TooOld,
+            LedgerBurnError::CreatedInFuture { ledger_time, .. } => {
+                Self::CreatedInFuture { ledger_time }
+            }
         }
     }
 }
 
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+pub enum CancelError {
+    /// No withdrawal request with the given burn index is known to the minter.
+    NotFound,
+    /// The withdrawal request was not issued by the caller.
+    NotOwner,
+    /// Only withdrawals of the native token can be cancelled.
+    NotCancellable,
+    /// The minter already started processing the withdrawal request.
+    TooLate {
+        status: RetrieveWithdrawalStatus,
+    },
+    TemporarilyUnavailable(String),
+}
+
 #[derive(CandidType, Deserialize, Clone, Eq, PartialEq, Debug)]
 pub enum WithdrawalSearchParameter {
     ByWithdrawalId(u64),
@@ -72,6 +122,22 @@ pub struct WithdrawalDetail {
     pub status: WithdrawalStatus,
 }
 
+/// Successfully finalized native or ERC-20 withdrawal, see `get_finalized_transactions`.
+#[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct FinalizedWithdrawal {
+    pub native_burn_index: u64,
+    /// `None` for wrapped ICRC tokens, whose symbol is not known to the minter.
+    pub token_symbol: Option<String>,
+    pub destination: String,
+    /// Amount received by the destination.
+    pub amount: Nat,
+    pub transaction_hash: String,
+    pub effective_fee: Nat,
+    pub l1_fee: Option<Nat>,
+    /// IC time at which the transaction was found finalized.
+    pub finalized_at: u64,
+}
+
 #[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
 pub enum WithdrawalStatus {
     Pending,
@@ -80,14 +146,22 @@ pub enum WithdrawalStatus {
     TxFinalized(TxFinalizedStatus),
 }
 
-#[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(CandidType, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Encode, Decode)]
 pub struct SwapDetails {
+    #[n(0)]
     pub tx_id: CandidSwapTxId,
+    #[n(1)]
     pub withdrawal_id: u64,
+    #[n(2)]
     pub token_in: String,
+    #[cbor(n(3), with = "crate::cbor::nat")]
     pub amount_in: Nat,
+    #[cbor(n(4), with = "crate::cbor::nat")]
     pub min_amount_out: Nat,
+    #[n(5)]
     pub recipient: String,
+    #[cbor(n(6), with = "crate::cbor::nat")]
     pub deadline: Nat,
+    #[n(7)]
     pub is_refund: bool,
 }
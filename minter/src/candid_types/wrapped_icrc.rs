@@ -1,4 +1,5 @@
 use crate::icrc_client::LedgerBurnError;
+use crate::state::{IcrcLedgerMetadata, WrappedIcrcDeployment};
 
 use super::*;
 
@@ -7,6 +8,9 @@ pub struct WrapIcrcArg {
     pub amount: Nat,
     pub icrc_ledger_id: Principal,
     pub recipient: String,
+    /// Aborts the wrap, before any tokens are burnt or locked,
+    /// if the native fee estimated for it is higher than this.
+    pub max_native_fee: Option<Nat>,
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
@@ -30,6 +34,43 @@ pub struct WrappedIcrcToken {
     pub deployed_wrapped_erc20: String,
 }
 
+/// Wrapped ICRC token with its deployment and the metadata of its base ledger.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WrappedIcrcTokenInfo {
+    pub base_token: Principal,
+    pub deployed_wrapped_erc20: String,
+    pub deployment_transaction_hash: Option<String>,
+    pub deployment_block_number: Option<Nat>,
+    // Metadata of the base ledger, `None` until the ledger was queried
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    pub transfer_fee: Option<Nat>,
+    // Whether burns of the wrapped token are accepted, wrapped ICRC tokens cannot be
+    // deactivated yet so this is always true
+    pub active: bool,
+}
+
+impl WrappedIcrcTokenInfo {
+    pub fn new(
+        base_token: Principal,
+        deployed_wrapped_erc20: &Address,
+        deployment: Option<&WrappedIcrcDeployment>,
+        metadata: Option<&IcrcLedgerMetadata>,
+    ) -> Self {
+        Self {
+            base_token,
+            deployed_wrapped_erc20: deployed_wrapped_erc20.to_string(),
+            deployment_transaction_hash: deployment
+                .map(|deployment| deployment.transaction_hash.to_string()),
+            deployment_block_number: deployment.map(|deployment| deployment.block_number.into()),
+            symbol: metadata.map(|metadata| metadata.symbol.clone()),
+            decimals: metadata.map(|metadata| metadata.decimals),
+            transfer_fee: metadata.map(|metadata| metadata.transfer_fee.into()),
+            active: true,
+        }
+    }
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
 pub enum WrapIcrcError {
     TokenNotSupported {
@@ -48,9 +89,17 @@ pub enum WrapIcrcError {
         error: LedgerError,
     },
     AmountTooLow,
+    AmountTooLarge {
+        max_wrap_amount: Nat,
+    },
     TemporarilyUnavailable(String),
     InvalidDestination(String),
     TransferFeeUnknow(String),
+    DestinationNotAllowed,
+    NativeFeeAboveMaximum {
+        native_fee: Nat,
+        maximum: Nat,
+    },
 }
 
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
@@ -112,6 +161,14 @@ impl From<LedgerBurnError> for LedgerError {
                 token_symbol: ledger.token_symbol.to_string(),
                 ledger_id: ledger.id,
             },
+            LedgerBurnError::Duplicate { duplicate_of, .. } => {
+                panic!(
+                    "BUG: unexpected duplicate of burn {duplicate_of}, burns are not deduplicated"
+                )
+            }
+            LedgerBurnError::TooOld { .. } | LedgerBurnError::CreatedInFuture { .. } => {
+                panic!("BUG: unexpected created_at_time error, burns are not deduplicated")
+            }
         }
     }
 }
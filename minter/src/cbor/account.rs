@@ -0,0 +1,62 @@
+use super::principal::CborPrincipal;
+use crate::state::transactions::Subaccount;
+use icrc_ledger_types::icrc1::account::Account;
+use minicbor;
+use minicbor::decode::{Decoder, Error};
+use minicbor::encode::{Encoder, Write};
+use minicbor::{Decode, Encode};
+
+/// Encodes an ICRC-1 account as a map of its owner and optional subaccount.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+struct CborAccount {
+    #[n(0)]
+    owner: CborPrincipal,
+    #[n(1)]
+    subaccount: Option<Subaccount>,
+}
+
+impl From<Account> for CborAccount {
+    fn from(account: Account) -> Self {
+        Self {
+            owner: CborPrincipal(account.owner),
+            subaccount: account.subaccount.map(Subaccount),
+        }
+    }
+}
+
+impl From<CborAccount> for Account {
+    fn from(account: CborAccount) -> Self {
+        Self {
+            owner: account.owner.0,
+            subaccount: account.subaccount.map(|subaccount| subaccount.0),
+        }
+    }
+}
+
+pub fn decode<Ctx>(d: &mut Decoder<'_>, ctx: &mut Ctx) -> Result<Account, Error> {
+    Ok(CborAccount::decode(d, ctx)?.into())
+}
+
+pub fn encode<Ctx, W: Write>(
+    v: &Account,
+    e: &mut Encoder<W>,
+    ctx: &mut Ctx,
+) -> Result<(), minicbor::encode::Error<W::Error>> {
+    CborAccount::from(*v).encode(e, ctx)
+}
+
+pub mod option {
+    use super::*;
+
+    pub fn decode<Ctx>(d: &mut Decoder<'_>, ctx: &mut Ctx) -> Result<Option<Account>, Error> {
+        Ok(Option::<CborAccount>::decode(d, ctx)?.map(Account::from))
+    }
+
+    pub fn encode<Ctx, W: Write>(
+        v: &Option<Account>,
+        e: &mut Encoder<W>,
+        ctx: &mut Ctx,
+    ) -> Result<(), minicbor::encode::Error<W::Error>> {
+        (*v).map(CborAccount::from).encode(e, ctx)
+    }
+}
@@ -1,3 +1,4 @@
+pub mod account;
 pub mod id;
 pub mod nat;
 pub mod principal;
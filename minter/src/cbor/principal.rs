@@ -17,14 +17,17 @@ pub fn encode<Ctx, W: Write>(
     Ok(())
 }
 
+/// Wrapper to encode principals nested in other types, e.g. as a tuple element.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, minicbor::Encode, minicbor::Decode,
+)]
+#[cbor(transparent)]
+pub struct CborPrincipal(#[cbor(n(0), with = "crate::cbor::principal")] pub Principal);
+
 pub mod option {
     use super::*;
     use minicbor::{Decode, Encode};
 
-    #[derive(Encode, Decode)]
-    #[cbor(transparent)]
-    struct CborPrincipal(#[cbor(n(0), with = "crate::cbor::principal")] pub Principal);
-
     pub fn decode<Ctx>(d: &mut Decoder<'_>, ctx: &mut Ctx) -> Result<Option<Principal>, Error> {
         Ok(Option::<CborPrincipal>::decode(d, ctx)?.map(|n| n.0))
     }
@@ -37,3 +40,39 @@ pub mod option {
         (*v).map(CborPrincipal).encode(e, ctx)
     }
 }
+
+/// Encodes a map keyed by principals as a CBOR map.
+pub mod btree_map {
+    use super::*;
+    use minicbor::{Decode, Encode};
+    use std::collections::BTreeMap;
+
+    pub fn decode<'b, Ctx, V: Decode<'b, Ctx>>(
+        d: &mut Decoder<'b>,
+        ctx: &mut Ctx,
+    ) -> Result<BTreeMap<Principal, V>, Error> {
+        let len = d
+            .map()?
+            .ok_or_else(|| Error::message("expected a definite length map"))?;
+        let mut map = BTreeMap::new();
+        for _ in 0..len {
+            let key = CborPrincipal::decode(d, ctx)?;
+            let value = V::decode(d, ctx)?;
+            map.insert(key.0, value);
+        }
+        Ok(map)
+    }
+
+    pub fn encode<Ctx, W: Write, V: Encode<Ctx>>(
+        v: &BTreeMap<Principal, V>,
+        e: &mut Encoder<W>,
+        ctx: &mut Ctx,
+    ) -> Result<(), minicbor::encode::Error<W::Error>> {
+        e.map(v.len() as u64)?;
+        for (key, value) in v {
+            CborPrincipal(*key).encode(e, ctx)?;
+            value.encode(e, ctx)?;
+        }
+        Ok(())
+    }
+}
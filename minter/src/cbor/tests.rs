@@ -1,6 +1,7 @@
 use crate::checked_amount::CheckedAmountOf;
 use candid::{Nat, Principal};
 use ethnum::{u256, U256};
+use icrc_ledger_types::icrc1::account::Account;
 use minicbor::{Decode, Encode};
 use phantom_newtype::Id;
 use proptest::collection::vec as pvec;
@@ -51,6 +52,18 @@ struct OptPrincipalContainer {
     pub value: Option<Principal>,
 }
 
+#[derive(Debug, PartialEq, Eq, Encode, Decode)]
+struct OptAccountContainer {
+    #[cbor(n(0), with = "crate::cbor::account::option")]
+    pub value: Option<Account>,
+}
+
+#[derive(Debug, PartialEq, Eq, Encode, Decode)]
+struct PrincipalMapContainer {
+    #[cbor(n(0), with = "crate::cbor::principal::btree_map")]
+    pub value: std::collections::BTreeMap<Principal, u64>,
+}
+
 #[derive(Debug, PartialEq, Eq, Encode, Decode)]
 struct U256NewtypeContainer {
     #[cbor(n(0))]
@@ -119,4 +132,31 @@ proptest! {
             value: p.map(|principal| Principal::from_slice(&principal)),
         })?;
     }
+
+    #[test]
+    fn opt_account_encoding_roundtrip(
+        account in proptest::option::of((
+            pvec(any::<u8>(), 0..30),
+            proptest::option::of(proptest::array::uniform32(any::<u8>())),
+        ))
+    ) {
+        check_roundtrip(&OptAccountContainer {
+            value: account.map(|(owner, subaccount)| Account {
+                owner: Principal::from_slice(&owner),
+                subaccount,
+            }),
+        })?;
+    }
+
+    #[test]
+    fn principal_map_encoding_roundtrip(
+        entries in proptest::collection::btree_map(pvec(any::<u8>(), 0..30), any::<u64>(), 0..10)
+    ) {
+        check_roundtrip(&PrincipalMapContainer {
+            value: entries
+                .into_iter()
+                .map(|(principal, value)| (Principal::from_slice(&principal), value))
+                .collect(),
+        })?;
+    }
 }
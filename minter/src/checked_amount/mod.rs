@@ -41,6 +41,10 @@ use std::ops::Rem;
 /// assert_eq!(three_apples.checked_mul(2_u8), Some(Apples::from(6_u8)));
 /// assert_eq!(Apples::MAX.checked_mul(2_u8), None);
 ///
+/// // Checked multiplication by an amount of the same unit
+/// assert_eq!(three_apples.checked_mul_unit(Apples::TWO).map(|a| a.into_inner()), Some(6_u8.into()));
+/// assert_eq!(Apples::MAX.checked_mul_unit(Apples::TWO), None);
+///
 /// // Ceiling checked division by scalar
 /// assert_eq!(three_apples.checked_div_ceil(0_u8), None);
 /// assert_eq!(three_apples.checked_div_ceil(2_u8), Some(Apples::TWO));
@@ -57,6 +61,9 @@ use std::ops::Rem;
 /// ```
 pub struct CheckedAmountOf<Unit>(ethnum::u256, PhantomData<Unit>);
 
+/// Unit of the product of two amounts of `Unit`, see [`CheckedAmountOf::checked_mul_unit`].
+pub struct Square<Unit>(PhantomData<Unit>);
+
 impl<Unit> CheckedAmountOf<Unit> {
     pub const ZERO: Self = Self(ethnum::u256::ZERO, PhantomData);
     pub const ONE: Self = Self(ethnum::u256::ONE, PhantomData);
@@ -121,6 +128,11 @@ impl<Unit> CheckedAmountOf<Unit> {
         self.0.checked_mul(factor.into()).map(Self::from_inner)
     }
 
+    /// Multiplies two amounts of `Unit`, whose product is an amount of `Square<Unit>`.
+    pub fn checked_mul_unit(self, rhs: Self) -> Option<CheckedAmountOf<Square<Unit>>> {
+        self.checked_mul(rhs.0).map(|value| value.change_units())
+    }
+
     pub fn checked_div_ceil<T: Into<ethnum::u256>>(self, rhs: T) -> Option<Self> {
         let rhs = rhs.into();
         if rhs == ethnum::u256::ZERO {
@@ -47,5 +47,152 @@ mod checked_div_ceil {
     }
 }
 
+mod checked_mul {
+    use super::Amount;
+    use proptest::prelude::any;
+    use proptest::{prop_assert_eq, proptest};
+
+    proptest! {
+        #[test]
+        fn should_multiply_by_u64(amount in any::<u128>(), factor in any::<u64>()) {
+            let product = Amount::from(amount).checked_mul(factor).unwrap();
+
+            prop_assert_eq!(product.0, ethnum::u256::from(amount) * ethnum::u256::from(factor));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn should_be_none_on_overflow(factor in 2_u64..=u64::MAX) {
+            prop_assert_eq!(Amount::MAX.checked_mul(factor), None);
+            prop_assert_eq!(Amount::MAX.div_by_two().checked_add(Amount::ONE).unwrap().checked_mul(2_u8), None);
+        }
+    }
+
+    #[test]
+    fn should_handle_zero_and_one() {
+        assert_eq!(Amount::MAX.checked_mul(0_u8), Some(Amount::ZERO));
+        assert_eq!(Amount::MAX.checked_mul(1_u8), Some(Amount::MAX));
+        assert_eq!(Amount::ZERO.checked_mul(u64::MAX), Some(Amount::ZERO));
+        assert_eq!(
+            Amount::MAX.div_by_two().checked_mul(2_u8),
+            Amount::MAX.checked_sub(Amount::ONE)
+        );
+    }
+}
+
+mod checked_div_floor {
+    use super::Amount;
+    use proptest::prelude::any;
+    use proptest::{prop_assert_eq, proptest};
+
+    proptest! {
+        #[test]
+        fn should_be_none_when_divisor_is_zero(amount in any::<u128>()) {
+            prop_assert_eq!(Amount::from(amount).checked_div_floor(0_u64), None);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn should_round_down(amount in any::<u128>(), divisor in 1_u64..=u64::MAX) {
+            let quotient = Amount::from(amount).checked_div_floor(divisor).unwrap();
+
+            prop_assert_eq!(quotient.0, ethnum::u256::from(amount / u128::from(divisor)));
+        }
+    }
+
+    #[test]
+    fn should_handle_edge_cases() {
+        assert_eq!(Amount::MAX.checked_div_floor(1_u8), Some(Amount::MAX));
+        assert_eq!(
+            Amount::MAX.checked_div_floor(Amount::MAX.0),
+            Some(Amount::ONE)
+        );
+        assert_eq!(Amount::ZERO.checked_div_floor(u64::MAX), Some(Amount::ZERO));
+        assert_eq!(Amount::ONE.checked_div_floor(2_u8), Some(Amount::ZERO));
+    }
+}
+
+mod checked_mul_unit {
+    use super::Amount;
+    use crate::checked_amount::{CheckedAmountOf, Square};
+    use proptest::prelude::any;
+    use proptest::{prop_assert_eq, proptest};
+
+    type SquareAmount = CheckedAmountOf<Square<super::Unit>>;
+
+    proptest! {
+        #[test]
+        fn should_multiply_amounts(lhs in any::<u128>(), rhs in any::<u128>()) {
+            let product: Option<SquareAmount> = Amount::from(lhs).checked_mul_unit(Amount::from(rhs));
+
+            prop_assert_eq!(
+                product.map(|product| product.into_inner()),
+                Some(ethnum::u256::from(lhs) * ethnum::u256::from(rhs))
+            );
+        }
+    }
+
+    #[test]
+    fn should_be_none_on_overflow() {
+        let two_pow_128 = Amount::from(u128::MAX).checked_increment().unwrap();
+
+        assert_eq!(two_pow_128.checked_mul_unit(two_pow_128), None);
+        assert_eq!(Amount::MAX.checked_mul_unit(Amount::TWO), None);
+        assert_eq!(
+            Amount::MAX.checked_mul_unit(Amount::ONE),
+            Some(SquareAmount::MAX)
+        );
+        assert_eq!(
+            Amount::MAX.checked_mul_unit(Amount::ZERO),
+            Some(SquareAmount::ZERO)
+        );
+    }
+}
+
+mod try_from_nat {
+    use super::Amount;
+    use candid::Nat;
+    use num_bigint::BigUint;
+    use proptest::collection::vec;
+    use proptest::prelude::any;
+    use proptest::{prop_assert, prop_assert_eq, proptest};
+
+    proptest! {
+        #[test]
+        fn should_convert_any_nat_fitting_in_u256(bytes in any::<[u8; 32]>()) {
+            let nat = Nat::from(BigUint::from_bytes_be(&bytes));
+
+            let amount = Amount::try_from(nat.clone()).unwrap();
+
+            prop_assert_eq!(amount, Amount::from_be_bytes(bytes));
+            prop_assert_eq!(Nat::from(amount), nat);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn should_fail_to_convert_nat_larger_than_u256(
+            leading_byte in 1_u8..=u8::MAX,
+            bytes in vec(any::<u8>(), 32..=64)
+        ) {
+            let mut be_bytes = vec![leading_byte];
+            be_bytes.extend(bytes);
+            let nat = Nat::from(BigUint::from_bytes_be(&be_bytes));
+
+            prop_assert!(Amount::try_from(nat).is_err());
+        }
+    }
+
+    #[test]
+    fn should_convert_u256_max_but_not_the_next_nat() {
+        let max = Nat::from(Amount::MAX);
+
+        assert_eq!(Amount::try_from(max.clone()), Ok(Amount::MAX));
+        assert!(Amount::try_from(max + Nat::from(1_u8)).is_err());
+    }
+}
+
 enum Unit {}
 type Amount = CheckedAmountOf<Unit>;
@@ -11,6 +11,7 @@ use crate::{
     numeric::{BlockNumber, LogIndex},
     rpc_declarations::{FixedSizeData, Hash},
 };
+use alloy::sol_types::SolEvent;
 use evm_rpc_client::eth_types::Address;
 use thiserror::Error;
 use types::{
@@ -25,6 +26,16 @@ pub mod scraping;
 pub mod swap;
 pub mod types;
 
+/// Events emitted by the scraped contracts.
+pub trait EventSignature: SolEvent {
+    /// Keccak-256 hash of the canonical event signature, i.e. the first topic of its logs.
+    fn event_signature_hash() -> FixedSizeData {
+        FixedSizeData(Self::SIGNATURE_HASH.0)
+    }
+}
+
+impl<E: SolEvent> EventSignature for E {}
+
 /// A unique identifier of the event source: the source transaction hash and the log
 /// entry index.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
@@ -49,15 +60,20 @@ pub enum EventSourceError {
     InvalidEvent(String),
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
 pub enum ReceivedContractEvent {
-    NativeDeposit(ReceivedNativeEvent),
-    Erc20Deposit(ReceivedErc20Event),
+    #[n(0)]
+    NativeDeposit(#[n(0)] ReceivedNativeEvent),
+    #[n(1)]
+    Erc20Deposit(#[n(0)] ReceivedErc20Event),
     // new contract events
-    WrappedIcrcBurn(ReceivedBurnEvent),
-    WrappedIcrcDeployed(ReceivedWrappedIcrcDeployedEvent),
+    #[n(2)]
+    WrappedIcrcBurn(#[n(0)] ReceivedBurnEvent),
+    #[n(3)]
+    WrappedIcrcDeployed(#[n(0)] ReceivedWrappedIcrcDeployedEvent),
     // Swap contract event
-    ReceivedSwapOrder(ReceivedSwapEvent),
+    #[n(4)]
+    ReceivedSwapOrder(#[n(0)] ReceivedSwapEvent),
 }
 
 impl ReceivedContractEvent {
@@ -105,7 +121,7 @@ impl ReceivedContractEvent {
     pub fn from_address(&self) -> Address {
         match self {
             ReceivedContractEvent::NativeDeposit(evt) => evt.from_address,
-            ReceivedContractEvent::Erc20Deposit(evt) => evt.from_address,
+            ReceivedContractEvent::Erc20Deposit(evt) => evt.depositor(),
             ReceivedContractEvent::WrappedIcrcBurn(evt) => evt.from_address,
             ReceivedContractEvent::WrappedIcrcDeployed(_evt) => Address::ZERO,
             ReceivedContractEvent::ReceivedSwapOrder(evt) => evt.from_address,
@@ -121,6 +137,10 @@ pub enum ReceivedContractEventError {
         error: EventSourceError,
     },
     SameChainSwap,
+    /// The log entry was removed from the canonical chain due to a reorganisation.
+    RemovedLogEntry {
+        source: EventSource,
+    },
 }
 
 pub fn report_transaction_error(error: ReceivedContractEventError) {
@@ -143,6 +163,12 @@ pub fn report_transaction_error(error: ReceivedContractEventError) {
                 "same chain swap order detected, will not process for further steps"
             )
         }
+        ReceivedContractEventError::RemovedLogEntry { source } => {
+            log!(
+                INFO,
+                "[report_transaction_error]: log entry {source} was removed due to a chain reorganisation",
+            );
+        }
     }
 }
 
@@ -168,6 +194,32 @@ impl LedgerSubaccount {
     pub fn to_bytes(self) -> [u8; 32] {
         self.0.to_be_bytes()
     }
+
+    /// Subaccount whose lower 20 bytes are the given Ethereum address.
+    ///
+    /// Returns `None` for the zero address, which maps to the default subaccount.
+    pub fn from_eth_address(address: Address) -> Option<Self> {
+        let mut bytes = [0_u8; 32];
+        bytes[12..].copy_from_slice(address.as_ref());
+        Self::from_bytes(bytes)
+    }
+
+    /// Ethereum address stored in the lower 20 bytes of the subaccount,
+    /// or `None` if any of the upper 12 bytes is non-zero.
+    pub fn to_eth_address(&self) -> Option<Address> {
+        let bytes = self.to_icrc_subaccount();
+        if bytes[..12].iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        let mut address = [0_u8; 20];
+        address.copy_from_slice(&bytes[12..]);
+        Some(Address::new(address))
+    }
+
+    /// Same as [`Self::to_bytes`] without consuming the subaccount.
+    pub fn to_icrc_subaccount(&self) -> [u8; 32] {
+        self.0.to_be_bytes()
+    }
 }
 
 /// Decode a candid::Principal from a slice of at most 32 bytes
@@ -1,6 +1,6 @@
-use crate::contract_logs::swap::swap_logs::{ReceivedSwapEvent, RECEIVED_SWAP_EVENT_TOPIC};
+use crate::contract_logs::swap::swap_logs::{ReceivedSwapEvent, SwapExecuted};
 use crate::contract_logs::{
-    parse_principal_from_slice, EventSource, EventSourceError, LedgerSubaccount,
+    parse_principal_from_slice, EventSignature, EventSource, EventSourceError, LedgerSubaccount,
     ReceivedContractEventError,
 };
 
@@ -11,10 +11,8 @@ use candid::Principal;
 use evm_rpc_client::eth_types::Address;
 
 use super::types::{
-    ReceivedBurnEvent, ReceivedErc20Event, ReceivedNativeEvent, ReceivedWrappedIcrcDeployedEvent,
-    RECEIVED_DEPLOYED_WRAPPED_ICRC_TOKEN_EVENT_TOPIC,
-    RECEIVED_DEPOSITED_AND_BURNT_TOKENS_EVENT_TOPIC_NEW_CONTRACT,
-    RECEIVED_DEPOSITED_TOKEN_EVENT_TOPIC_OLD_CONTRACT,
+    DepositLog, ReceivedBurnEvent, ReceivedErc20Event, ReceivedNativeEvent,
+    ReceivedWrappedIcrcDeployedEvent, TokenBurn, TokenBurnWithPermit, WrappedTokenDeployed,
 };
 use super::ReceivedContractEvent;
 
@@ -50,7 +48,7 @@ impl LogParser for ReceivedEventsLogParser {
         let event_signature = entry.topics.first();
 
         match event_signature {
-            Some(&FixedSizeData(RECEIVED_DEPOSITED_TOKEN_EVENT_TOPIC_OLD_CONTRACT)) => {
+            Some(topic) if *topic == DepositLog::event_signature_hash() => {
                 // We have 4 indexed topics for all deposit events:
                 // The overall event is as follow :
                 // DepositLog(
@@ -107,10 +105,11 @@ impl LogParser for ReceivedEventsLogParser {
                         principal,
                         erc20_contract_address: token_contract_address,
                         subaccount,
+                        original_owner: None,
                     }))
                 }
             }
-            Some(&FixedSizeData(RECEIVED_DEPOSITED_AND_BURNT_TOKENS_EVENT_TOPIC_NEW_CONTRACT)) => {
+            Some(topic) if *topic == TokenBurn::event_signature_hash() => {
                 let EventSource {
                     transaction_hash,
                     log_index,
@@ -155,6 +154,7 @@ impl LogParser for ReceivedEventsLogParser {
                         principal,
                         erc20_contract_address: burnt_erc20,
                         subaccount,
+                        original_owner: None,
                     }))
                 } else if let Some(icrc_token_principal) = read_state(|s| {
                     s.find_icp_token_ledger_id_by_wrapped_erc20_address(&burnt_erc20)
@@ -179,7 +179,59 @@ impl LogParser for ReceivedEventsLogParser {
                     })
                 }
             }
-            Some(&FixedSizeData(RECEIVED_DEPLOYED_WRAPPED_ICRC_TOKEN_EVENT_TOPIC)) => {
+            Some(topic) if *topic == TokenBurnWithPermit::event_signature_hash() => {
+                let EventSource {
+                    transaction_hash,
+                    log_index,
+                } = event_source;
+
+                //event TokenBurnWithPermit(
+                //      address indexed fromAddress,
+                //      uint256 amount,
+                //      bytes32 indexed icpRecipient,
+                //      address indexed TokenAddress,
+                //      bytes32 subaccount,
+                //      address originalOwner
+                //  );
+
+                let from_address = parse_address(&entry.topics[1], event_source)?;
+
+                let [amount_bytes, subaccount_bytes, original_owner_bytes] =
+                    parse_data_into_32_byte_words(entry.data, event_source)?;
+
+                let erc20_contract_address = parse_address(&entry.topics[3], event_source)?;
+
+                let principal = parse_principal(&entry.topics[2], event_source)?;
+
+                let subaccount = LedgerSubaccount::from_bytes(subaccount_bytes);
+
+                let original_owner =
+                    parse_address(&FixedSizeData(original_owner_bytes), event_source)?;
+
+                // permits are only defined for erc20 tokens, so neither native tokens nor
+                // wrapped icrc tokens can be deposited this way.
+                if read_state(|s| s.erc20_tokens.get_alt(&erc20_contract_address).is_none()) {
+                    return Err(ReceivedContractEventError::InvalidEventSource {
+                        source: event_source,
+                        error: EventSourceError::InvalidEvent(
+                            "Deposited Erc20 token is not supported by the minter".to_string(),
+                        ),
+                    });
+                }
+
+                Ok(ReceivedContractEvent::Erc20Deposit(ReceivedErc20Event {
+                    transaction_hash,
+                    block_number,
+                    log_index,
+                    from_address,
+                    value: Erc20Value::from_be_bytes(amount_bytes),
+                    principal,
+                    erc20_contract_address,
+                    subaccount,
+                    original_owner: Some(original_owner),
+                }))
+            }
+            Some(topic) if *topic == WrappedTokenDeployed::event_signature_hash() => {
                 let EventSource {
                     transaction_hash,
                     log_index,
@@ -203,7 +255,7 @@ impl LogParser for ReceivedEventsLogParser {
                     },
                 ))
             }
-            Some(&FixedSizeData(RECEIVED_SWAP_EVENT_TOPIC)) => {
+            Some(topic) if *topic == SwapExecuted::event_signature_hash() => {
                 let EventSource {
                     transaction_hash,
                     log_index,
@@ -313,11 +365,8 @@ fn ensure_not_removed(
     event_source: EventSource,
 ) -> Result<(), ReceivedContractEventError> {
     if entry.removed {
-        return Err(ReceivedContractEventError::InvalidEventSource {
+        return Err(ReceivedContractEventError::RemovedLogEntry {
             source: event_source,
-            error: EventSourceError::InvalidEvent(
-                "this event has been removed from the chain".to_string(),
-            ),
         });
     }
     Ok(())
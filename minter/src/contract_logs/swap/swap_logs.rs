@@ -1,5 +1,4 @@
 use core::fmt;
-use hex_literal::hex;
 use minicbor::{Decode, Encode};
 
 use crate::{
@@ -9,8 +8,18 @@ use crate::{
 };
 use evm_rpc_client::eth_types::Address;
 
-pub(crate) const RECEIVED_SWAP_EVENT_TOPIC: [u8; 32] =
-    hex!("c33dada04354dd803ea44b93af35ba61d4bfa477f5f06c86b6a00cfc0c261bea");
+alloy::sol! {
+    event SwapExecuted(
+        address user,
+        bytes32 indexed recipient,
+        address indexed tokenIn,
+        address indexed tokenOut,
+        uint256 amountIn,
+        uint256 amountOut,
+        bool bridgeToMinter,
+        bytes encodedData
+    );
+}
 
 /// A swap was executed(same chain, or cross chain), and if corsschain the event should be sent to
 /// the dex canister for further execution
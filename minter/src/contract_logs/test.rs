@@ -120,7 +120,12 @@ mod parse_principal_from_slice {
 
 mod subaccount {
     use crate::contract_logs::LedgerSubaccount;
-    use proptest::{array::uniform32, prelude::any, prop_assert_eq, prop_assume, proptest};
+    use evm_rpc_client::eth_types::Address;
+    use proptest::{
+        array::{uniform20, uniform32},
+        prelude::any,
+        prop_assert_eq, prop_assume, proptest,
+    };
 
     proptest! {
         #[test]
@@ -131,5 +136,32 @@ mod subaccount {
 
             prop_assert_eq!(bytes, actual_bytes);
         }
+
+        #[test]
+        fn should_convert_from_and_to_eth_address(bytes in uniform20(any::<u8>())) {
+            prop_assume!(bytes != [0_u8; 20]);
+            let address = Address::new(bytes);
+            let subaccount = LedgerSubaccount::from_eth_address(address).unwrap();
+
+            prop_assert_eq!(&subaccount.to_icrc_subaccount()[12..], &bytes[..]);
+            prop_assert_eq!(subaccount.to_eth_address(), Some(address));
+        }
+
+        #[test]
+        fn should_not_convert_to_eth_address_when_upper_bytes_set(
+            bytes in uniform32(any::<u8>()),
+            index in 0_usize..12,
+        ) {
+            let mut bytes = bytes;
+            bytes[index] = bytes[index].max(1);
+            let subaccount = LedgerSubaccount::from_bytes(bytes).unwrap();
+
+            prop_assert_eq!(subaccount.to_eth_address(), None);
+        }
+    }
+
+    #[test]
+    fn should_map_zero_address_to_default_subaccount() {
+        assert_eq!(LedgerSubaccount::from_eth_address(Address::ZERO), None);
     }
 }
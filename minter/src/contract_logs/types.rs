@@ -7,16 +7,41 @@ use candid::Principal;
 use evm_rpc_client::eth_types::Address;
 use minicbor::{Decode, Encode};
 
-use hex_literal::hex;
-
 use super::{EventSource, LedgerSubaccount, ReceivedContractEvent};
 
-pub(crate) const RECEIVED_DEPOSITED_TOKEN_EVENT_TOPIC_OLD_CONTRACT: [u8; 32] =
-    hex!("deaddf8708b62ae1bf8ec4693b523254aa961b2da6bc5be57f3188ee784d6275");
+// Events emitted by the helper contracts. The signature hashes used to recognise their logs,
+// see `EventSignature::event_signature_hash`, are computed from these declarations at build time.
+alloy::sol! {
+    // Deposit of native or erc20 tokens in the old helper contract
+    event DepositLog(
+        address from_address,
+        address indexed token,
+        uint256 indexed amount,
+        bytes32 indexed principal,
+        bytes32 subaccount
+    );
+
+    // Deposit of native or erc20 tokens, or burn of wrapped icrc tokens in the new helper contract
+    event TokenBurn(
+        address indexed fromAddress,
+        uint256 amount,
+        bytes32 indexed icpRecipient,
+        address indexed TokenAddress,
+        bytes32 subaccount
+    );
 
-// "TokenBurn(address,uint256,bytes32,address,bytes32)": "0x37199deebd336af9013dbddaaf9a68e337707bb4ed64cb45ed12841af85e0377",
-pub(crate) const RECEIVED_DEPOSITED_AND_BURNT_TOKENS_EVENT_TOPIC_NEW_CONTRACT: [u8; 32] =
-    hex!("37199deebd336af9013dbddaaf9a68e337707bb4ed64cb45ed12841af85e0377");
+    // Deposit of erc20 tokens submitted by a relayer with the permit (EIP-2612) signature of the owner
+    event TokenBurnWithPermit(
+        address indexed fromAddress,
+        uint256 amount,
+        bytes32 indexed icpRecipient,
+        address indexed TokenAddress,
+        bytes32 subaccount,
+        address originalOwner
+    );
+
+    event WrappedTokenDeployed(bytes32 indexed baseToken, address indexed wrappedERC20);
+}
 
 // Deposited native tokens on the evm side(locked) so the wrapped token on the ICP side can be minted
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
@@ -56,6 +81,9 @@ pub struct ReceivedErc20Event {
     pub erc20_contract_address: Address,
     #[n(7)]
     pub subaccount: Option<LedgerSubaccount>,
+    // Owner who signed the permit, only set for permit deposits where `from_address` is the relayer
+    #[n(8)]
+    pub original_owner: Option<Address>,
 }
 
 // burnt wrapped ICP tokens on the evm side so the ICP tokens can be release(unlocked) on the icp
@@ -137,6 +165,7 @@ impl fmt::Debug for ReceivedErc20Event {
             .field("principal", &format_args!("{}", self.principal))
             .field("contract_address", &self.erc20_contract_address)
             .field("subaccount", &self.subaccount)
+            .field("original_owner", &self.original_owner)
             .finish()
     }
 }
@@ -176,6 +205,12 @@ impl ReceivedErc20Event {
             log_index: self.log_index,
         }
     }
+
+    /// Address that owned the deposited tokens, which for permit deposits
+    /// is not the relayer that submitted the transaction.
+    pub fn depositor(&self) -> Address {
+        self.original_owner.unwrap_or(self.from_address)
+    }
 }
 
 impl ReceivedBurnEvent {
@@ -187,10 +222,6 @@ impl ReceivedBurnEvent {
     }
 }
 
-//  "WrappedTokenDeployed(bytes32,address)": "0xe63ddf723173735772522be59b64b9c95be6eb8f14b87948f670ad6f8949ab2e"
-pub(crate) const RECEIVED_DEPLOYED_WRAPPED_ICRC_TOKEN_EVENT_TOPIC: [u8; 32] =
-    hex!("e63ddf723173735772522be59b64b9c95be6eb8f14b87948f670ad6f8949ab2e");
-
 // Fetched ReceivedWrappedIcrcDeployedEvent events to be saved into state
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
 pub struct ReceivedWrappedIcrcDeployedEvent {
@@ -1,22 +1,24 @@
 use std::collections::VecDeque;
 use std::time::Duration;
 
-use candid::Nat;
+use candid::{Nat, Principal};
+use futures::{future, stream, StreamExt};
 use ic_canister_log::log;
 use icrc_ledger_types::icrc1::account::Account;
 use scopeguard::ScopeGuard;
 
-use crate::candid_types::RequestScrapingError;
+use crate::candid_types::{BackfillScrapeError, RequestScrapingError};
 use crate::contract_logs::parser::{LogParser, ReceivedEventsLogParser};
 use crate::contract_logs::scraping::{LogScraping, ReceivedEventsLogScraping};
 use crate::contract_logs::{
-    report_transaction_error, ReceivedContractEvent, ReceivedContractEventError,
+    report_transaction_error, EventSource, ReceivedContractEvent, ReceivedContractEventError,
 };
 use crate::dex_client::types::ReceivedSwapOrderEvent;
 use crate::dex_client::DexClient;
-use crate::evm_config::EvmNetwork;
-use crate::guard::TimerGuard;
+use crate::evm_config::{ConfirmationPolicy, EvmNetwork};
+use crate::guard::{MintGuard, MintGuardError, TimerGuard};
 use crate::icrc_client::runtime::IcrcBoundedRuntime;
+use crate::instrumentation::instrument;
 use crate::logs::{DEBUG, INFO};
 use crate::numeric::{BlockNumber, BlockRangeInclusive, IcrcValue, LedgerMintIndex};
 use crate::rpc_client::providers::Provider;
@@ -32,8 +34,145 @@ use icrc_ledger_client::ICRC1Client;
 use icrc_ledger_types::icrc1::transfer::TransferArg;
 use num_traits::ToPrimitive;
 
+pub mod quote;
+
+#[cfg(test)]
+mod tests;
+
 pub(crate) const TEN_SEC: u64 = 10_000_000_000_u64; // 10 seconds
 
+/// Maximum number of deposit mints awaiting the ledgers at the same time.
+pub const MAX_CONCURRENT_MINTS: usize = 8;
+
+/// Mints the tokens for a single deposit event and records the result.
+///
+/// Returns `false` if the mint failed and should be retried later.
+async fn mint_deposit(event: ReceivedContractEvent, native_ledger_canister_id: Principal) -> bool {
+    // The event might have been dropped by a chain reorganisation while an earlier mint was
+    // awaiting the ledger, or its mint might still be in flight from a previous round.
+    let _in_flight_guard = match MintGuard::new(event.source()) {
+        Ok(guard) => guard,
+        Err(MintGuardError::NotPending) => {
+            log!(INFO, "Skipping mint for event {event:?}: no longer pending");
+            return true;
+        }
+        Err(MintGuardError::AlreadyInFlight) => {
+            log!(INFO, "Skipping mint for event {event:?}: already in flight");
+            return true;
+        }
+    };
+
+    // Ensure that even if we were to panic in the callback, after having contacted the ledger to mint the tokens,
+    // this event will not be processed again.
+    let prevent_double_minting_guard = scopeguard::guard(event.clone(), |event| {
+        mutate_state(|s| {
+            process_event(
+                s,
+                EventType::QuarantinedDeposit {
+                    event_source: event.source(),
+                },
+            )
+        });
+    });
+    let (token_symbol, ledger_canister_id, amount, recepient, subaccount) = match &event {
+        ReceivedContractEvent::NativeDeposit(event) => (
+            "Native".to_string(),
+            native_ledger_canister_id,
+            Nat::from(event.value),
+            event.principal,
+            event.subaccount.clone(),
+        ),
+        ReceivedContractEvent::Erc20Deposit(event) => {
+            if let Some(result) = read_state(|s| {
+                s.erc20_tokens
+                    .get_entry_alt(&event.erc20_contract_address)
+                    .map(|(principal, symbol)| {
+                        (
+                            symbol.to_string(),
+                            *principal,
+                            Nat::from(event.value),
+                            event.principal,
+                            event.subaccount.clone(),
+                        )
+                    })
+            }) {
+                result
+            } else {
+                panic!("Failed to mint ERC20: {event:?} Unsupported ERC20 contract address. (This should have already been filtered out by process_event)");
+            }
+        }
+        _ => panic!("BUG: Only deposit events should be in the minting list"),
+    };
+
+    let client = ICRC1Client {
+        runtime: IcrcBoundedRuntime,
+        ledger_canister_id,
+    };
+
+    // Mint tokens for the user
+    let block_index = match client
+        .transfer(TransferArg {
+            from_subaccount: None,
+            to: Account {
+                owner: recepient,
+                subaccount: subaccount.map(|subaccount| subaccount.to_bytes()),
+            },
+            fee: None,
+            created_at_time: None,
+            memo: Some((&event).into()),
+            amount: amount.clone(),
+        })
+        .await
+    {
+        Ok(Ok(block_index)) => block_index.0.to_u64().expect("nat does not fit into u64"),
+        Ok(Err(err)) => {
+            log!(INFO, "Failed to mint {token_symbol}: {event:?} {err}");
+            // minting failed, defuse guard
+            ScopeGuard::into_inner(prevent_double_minting_guard);
+            return false;
+        }
+        Err(err) => {
+            log!(
+                INFO,
+                "Failed to send a message to the ledger ({ledger_canister_id}): {err:?}"
+            );
+            // minting failed, defuse guard
+            ScopeGuard::into_inner(prevent_double_minting_guard);
+            return false;
+        }
+    };
+
+    // Record event
+    mutate_state(|s| {
+        process_event(
+            s,
+            match &event {
+                ReceivedContractEvent::NativeDeposit(event) => EventType::MintedNative {
+                    event_source: event.source(),
+                    mint_block_index: LedgerMintIndex::new(block_index),
+                },
+
+                ReceivedContractEvent::Erc20Deposit(event) => EventType::MintedErc20 {
+                    event_source: event.source(),
+                    mint_block_index: LedgerMintIndex::new(block_index),
+                    erc20_contract_address: event.erc20_contract_address,
+                    erc20_token_symbol: token_symbol.clone(),
+                },
+                _ => panic!("BUG: Only deposit events should be in the minting list"),
+            },
+        )
+    });
+    log!(
+        INFO,
+        "Minted {} {token_symbol} to {} in block {block_index} ",
+        amount,
+        recepient.to_text(),
+    );
+    // minting succeeded, defuse guard
+    ScopeGuard::into_inner(prevent_double_minting_guard);
+    true
+}
+
 async fn mint_and_release() {
     let _guard = match TimerGuard::new(TaskType::Mint) {
         Ok(guard) => guard,
@@ -48,120 +187,12 @@ async fn mint_and_release() {
         )
     });
 
-    let mut error_count = 0;
-
-    for event in events_to_mint {
-        // Ensure that even if we were to panic in the callback, after having contacted the ledger to mint the tokens,
-        // this event will not be processed again.
-        let prevent_double_minting_guard = scopeguard::guard(event.clone(), |event| {
-            mutate_state(|s| {
-                process_event(
-                    s,
-                    EventType::QuarantinedDeposit {
-                        event_source: event.source(),
-                    },
-                )
-            });
-        });
-        let (token_symbol, ledger_canister_id, amount, recepient, subaccount) = match &event {
-            ReceivedContractEvent::NativeDeposit(event) => (
-                "Native".to_string(),
-                native_ledger_canister_id,
-                Nat::from(event.value),
-                event.principal,
-                event.subaccount.clone(),
-            ),
-            ReceivedContractEvent::Erc20Deposit(event) => {
-                if let Some(result) = read_state(|s| {
-                    s.erc20_tokens
-                        .get_entry_alt(&event.erc20_contract_address)
-                        .map(|(principal, symbol)| {
-                            (
-                                symbol.to_string(),
-                                *principal,
-                                Nat::from(event.value),
-                                event.principal,
-                                event.subaccount.clone(),
-                            )
-                        })
-                }) {
-                    result
-                } else {
-                    panic!("Failed to mint ERC20: {event:?} Unsupported ERC20 contract address. (This should have already been filtered out by process_event)");
-                }
-            }
-            _ => panic!("BUG: Only deposit events should be in the minting list"),
-        };
-
-        let client = ICRC1Client {
-            runtime: IcrcBoundedRuntime,
-            ledger_canister_id,
-        };
-
-        // Mint tokens for the user
-        let block_index = match client
-            .transfer(TransferArg {
-                from_subaccount: None,
-                to: Account {
-                    owner: recepient,
-                    subaccount: subaccount.map(|subaccount| subaccount.to_bytes()),
-                },
-                fee: None,
-                created_at_time: None,
-                memo: Some((&event).into()),
-                amount: amount.clone(),
-            })
-            .await
-        {
-            Ok(Ok(block_index)) => block_index.0.to_u64().expect("nat does not fit into u64"),
-            Ok(Err(err)) => {
-                log!(INFO, "Failed to mint {token_symbol}: {event:?} {err}");
-                error_count += 1;
-                // minting failed, defuse guard
-                ScopeGuard::into_inner(prevent_double_minting_guard);
-                continue;
-            }
-            Err(err) => {
-                log!(
-                    INFO,
-                    "Failed to send a message to the ledger ({ledger_canister_id}): {err:?}"
-                );
-                error_count += 1;
-                // minting failed, defuse guard
-                ScopeGuard::into_inner(prevent_double_minting_guard);
-                continue;
-            }
-        };
-
-        // Record event
-        mutate_state(|s| {
-            process_event(
-                s,
-                match &event {
-                    ReceivedContractEvent::NativeDeposit(event) => EventType::MintedNative {
-                        event_source: event.source(),
-                        mint_block_index: LedgerMintIndex::new(block_index),
-                    },
-
-                    ReceivedContractEvent::Erc20Deposit(event) => EventType::MintedErc20 {
-                        event_source: event.source(),
-                        mint_block_index: LedgerMintIndex::new(block_index),
-                        erc20_contract_address: event.erc20_contract_address,
-                        erc20_token_symbol: token_symbol.clone(),
-                    },
-                    _ => panic!("BUG: Only deposit events should be in the minting list"),
-                },
-            )
-        });
-        log!(
-            INFO,
-            "Minted {} {token_symbol} to {} in block {block_index} ",
-            amount,
-            recepient.to_text(),
-        );
-        // minting succeeded, defuse guard
-        ScopeGuard::into_inner(prevent_double_minting_guard);
-    }
+    let mut error_count = stream::iter(events_to_mint)
+        .map(|event| mint_deposit(event, native_ledger_canister_id))
+        .buffer_unordered(MAX_CONCURRENT_MINTS)
+        .filter(|minted| future::ready(!*minted))
+        .count()
+        .await;
 
     for event in events_to_release {
         let received_burn_event = match &event {
@@ -496,10 +527,79 @@ pub async fn mint_to_appic_dex_and_swap() {
     }
 }
 
+/// Number of blocks before the last scraped block that are scraped again after an upgrade.
+pub const POST_UPGRADE_GRACE_BLOCKS: u64 = 100;
+
+/// Returns the block range `(start_block, end_block)` to scrape again after an upgrade,
+/// or `None` if nothing was scraped yet.
+pub fn upgrade_grace_scrape_range(
+    last_scraped_block_number: BlockNumber,
+) -> Option<(BlockNumber, BlockNumber)> {
+    if last_scraped_block_number == BlockNumber::ZERO {
+        return None;
+    }
+    let start_block = last_scraped_block_number
+        .checked_sub(BlockNumber::from(POST_UPGRADE_GRACE_BLOCKS))
+        .unwrap_or(BlockNumber::ZERO);
+    Some((start_block, last_scraped_block_number))
+}
+
+/// Rewinds the last scraped block by [`POST_UPGRADE_GRACE_BLOCKS`] so that the next
+/// scraping round, started right after the upgrade, covers the grace period.
+pub fn schedule_upgrade_grace_scrape() {
+    mutate_state(|s| {
+        if s.helper_contract_addresses.is_none() {
+            return;
+        }
+        if let Some((start_block, end_block)) =
+            upgrade_grace_scrape_range(s.last_scraped_block_number)
+        {
+            log!(
+                INFO,
+                "[schedule_upgrade_grace_scrape]: scraping blocks {start_block} to {end_block} again after upgrade"
+            );
+            process_event(
+                s,
+                EventType::UpgradeGraceScrape {
+                    start_block,
+                    end_block,
+                },
+            );
+        }
+    });
+}
+
+/// Returns an error if log scraping was last requested less than
+/// [`crate::LOG_SCRAPING_REQUEST_INTERVAL`] before `now_ns`.
+pub fn validate_log_scraping_request(
+    last_scraping_time: u64,
+    now_ns: u64,
+) -> Result<(), RequestScrapingError> {
+    let interval = crate::LOG_SCRAPING_REQUEST_INTERVAL.as_nanos() as u64;
+    if now_ns.saturating_sub(last_scraping_time) < interval {
+        return Err(RequestScrapingError::CalledTooManyTimes);
+    }
+    Ok(())
+}
+
 pub async fn scrape_logs() {
+    let _instrumentation = instrument("scrape_logs");
+    if read_state(State::is_draining) {
+        log!(
+            DEBUG,
+            "[scrape_logs]: draining before upgrade, skipping scraping logs"
+        );
+        return;
+    }
     let _guard = match TimerGuard::new(TaskType::ScrapLogs) {
         Ok(guard) => guard,
-        Err(_) => return,
+        Err(e) => {
+            log!(
+                DEBUG,
+                "[scrape_logs]: logs are already being scraped, skipping: {e:?}"
+            );
+            return;
+        }
     };
 
     let mut attempts = 0;
@@ -532,12 +632,39 @@ pub async fn scrape_logs() {
 
     let max_block_spread = read_state(|s| s.max_block_spread_for_logs_scraping());
     scrape_until_block(last_block_number, max_block_spread).await;
+    update_last_scraped_block_timestamp().await;
+}
+
+// Fetches the timestamp of the last scraped block, unless it is already known.
+async fn update_last_scraped_block_timestamp() {
+    let last_scraped_block_number = read_state(|s| {
+        s.last_scraped_block_timestamp()
+            .is_none()
+            .then_some(s.last_scraped_block_number)
+    });
+    let Some(block_number) = last_scraped_block_number else {
+        return;
+    };
+
+    match read_state(|s| RpcClient::from_state_one_provider(s, Provider::Alchemy))
+        .get_block_by_number(BlockSpec::Number(block_number))
+        .await
+    {
+        Ok(block) => {
+            mutate_state(|s| s.last_scraped_block_timestamp = Some((block_number, block.timestamp)))
+        }
+        Err(e) => log!(
+            INFO,
+            "Failed to get the timestamp of the last scraped block {block_number}: {e:?}"
+        ),
+    }
 }
 
 // Updates last_observed_block_number in the state.
 pub async fn update_last_observed_block_number() -> Option<BlockNumber> {
     let block_height = read_state(State::block_height);
-    let network = read_state(|state| state.evm_network);
+    let (network, confirmation_policy) =
+        read_state(|state| (state.evm_network, state.confirmation_policy));
     let now_ns = ic_cdk::api::time();
 
     // first we check if the last_observed_block_number is newly updated(it's not older than 10
@@ -559,9 +686,11 @@ pub async fn update_last_observed_block_number() -> Option<BlockNumber> {
             let block_number = latest_block.number;
             mutate_state(|s| s.last_observed_block_number = Some(block_number));
             mutate_state(|s| s.last_observed_block_time = Some(now_ns));
+            mutate_state(|s| s.last_observed_block_timestamp = Some(latest_block.timestamp));
 
             Some(apply_safe_threshold_to_latest_block_numner(
                 network,
+                confirmation_policy,
                 block_number,
             ))
         }
@@ -575,6 +704,184 @@ pub async fn update_last_observed_block_number() -> Option<BlockNumber> {
     }
 }
 
+/// Total number of unscraped blocks above which every new gap is reported in the logs.
+pub const SCRAPING_GAPS_ALERT_THRESHOLD_BLOCKS: u64 = 10;
+
+fn log_scraping_gaps_alert() {
+    let (gap_count, total_blocks) =
+        read_state(|s| (s.scraping_gaps.len(), s.scraping_gaps.total_blocks()));
+    if total_blocks > SCRAPING_GAPS_ALERT_THRESHOLD_BLOCKS {
+        log!(
+            INFO,
+            "[ALERT]: {total_blocks} blocks in {gap_count} ranges were never scraped, see get_scraping_gaps"
+        );
+    }
+}
+
+/// Records the blocks of `block_range` above the last scraped block as a scraping gap and
+/// moves the scraping cursor past them, see [`rescrape_scraping_gaps`].
+/// Only for ranges that cannot be scraped at all, transient errors must be retried instead.
+fn skip_block_range(block_range: BlockRangeInclusive) {
+    let (from_block, to_block) = block_range.into_inner();
+    mutate_state(|s| {
+        let from_block = from_block.max(
+            s.last_scraped_block_number
+                .checked_increment()
+                .unwrap_or(BlockNumber::MAX),
+        );
+        if from_block > to_block {
+            return;
+        }
+        process_event(
+            s,
+            EventType::SkippedBlockRange {
+                from_block,
+                to_block,
+            },
+        );
+        s.last_scraped_block_number = to_block;
+    });
+    log_scraping_gaps_alert();
+}
+
+/// Maximum number of scraping gaps scraped again by a single call to
+/// [`rescrape_scraping_gaps`].
+pub const MAX_RESCRAPED_GAPS_PER_ROUND: usize = 5;
+
+/// Scrapes again the oldest [`MAX_RESCRAPED_GAPS_PER_ROUND`] block ranges that were skipped
+/// before. Ranges that are scraped successfully are removed from the scraping gaps, so that
+/// the next call resumes with the remaining ones.
+pub async fn rescrape_scraping_gaps() {
+    let _guard = match TimerGuard::new(TaskType::ScrapLogs) {
+        Ok(guard) => guard,
+        Err(e) => {
+            log!(
+                DEBUG,
+                "[rescrape_scraping_gaps]: logs are already being scraped, skipping: {e:?}"
+            );
+            return;
+        }
+    };
+    let scrape = match read_state(ReceivedEventsLogScraping::next_scrape) {
+        Some(s) => s,
+        None => return,
+    };
+    let gaps: Vec<_> = read_state(|s| {
+        s.scraping_gaps
+            .iter()
+            .take(MAX_RESCRAPED_GAPS_PER_ROUND)
+            .map(|gap| BlockRangeInclusive::new(gap.from_block, gap.to_block))
+            .collect()
+    });
+    if gaps.is_empty() {
+        return;
+    }
+    let rpc_client =
+        read_state(|s| RpcClient::from_state_custom_providers(s, vec![Provider::Alchemy]));
+
+    for block_range in gaps {
+        log!(
+            INFO,
+            "[rescrape_scraping_gaps]: Scraping logs in skipped block range {block_range}",
+        );
+        if let Err(e) = scrape_block_range(
+            &rpc_client,
+            scrape.contract_addresses.clone(),
+            scrape.topics.clone(),
+            block_range.clone(),
+        )
+        .await
+        {
+            log!(
+                INFO,
+                "[rescrape_scraping_gaps]: Failed to scrape logs in range {block_range}: {e:?}",
+            );
+        }
+    }
+}
+
+/// Maximum number of blocks that a single call to `backfill_scrape` may scrape.
+pub const MAX_BACKFILL_SCRAPE_BLOCKS: u64 = 5_000;
+
+/// Returns the block range `from_block..=to_block` if it may be backfilled, i.e. if it
+/// is at most [`MAX_BACKFILL_SCRAPE_BLOCKS`] long and ends below the last scraped block.
+pub fn validate_backfill_range(
+    from_block: BlockNumber,
+    to_block: BlockNumber,
+    last_scraped_block_number: BlockNumber,
+) -> Result<BlockRangeInclusive, BackfillScrapeError> {
+    let Some(span) = to_block.checked_sub(from_block) else {
+        return Err(BackfillScrapeError::InvalidBlockRange);
+    };
+    if span >= BlockNumber::from(MAX_BACKFILL_SCRAPE_BLOCKS) {
+        return Err(BackfillScrapeError::BlockRangeTooLarge {
+            max_blocks: MAX_BACKFILL_SCRAPE_BLOCKS,
+        });
+    }
+    if to_block >= last_scraped_block_number {
+        return Err(BackfillScrapeError::BlockNotScrapedYet {
+            last_scraped_block_number: last_scraped_block_number.into(),
+        });
+    }
+    Ok(BlockRangeInclusive::new(from_block, to_block))
+}
+
+/// Scrapes again a block range that was already scraped, e.g. to import deposits that were
+/// missed, and records the events found that were not recorded yet.
+/// The last scraped block is left untouched.
+pub async fn scrape_historical_block_range(
+    block_range: BlockRangeInclusive,
+) -> Result<ScrapedEventCounts, BackfillScrapeError> {
+    if read_state(|s| s.helper_contract_addresses.is_none()) {
+        return Err(BackfillScrapeError::ScrapingNotActive);
+    }
+    let _guard = TimerGuard::new(TaskType::ScrapLogs).map_err(|_| {
+        BackfillScrapeError::TemporarilyUnavailable("logs are being scraped".to_string())
+    })?;
+    let scrape = read_state(ReceivedEventsLogScraping::next_scrape)
+        .ok_or(BackfillScrapeError::ScrapingNotActive)?;
+    let max_block_spread = read_state(|s| s.max_block_spread_for_logs_scraping());
+    let rpc_client =
+        read_state(|s| RpcClient::from_state_custom_providers(s, vec![Provider::Alchemy]));
+
+    log!(
+        INFO,
+        "[backfill_scrape]: Scraping logs in historical block range {block_range}",
+    );
+    let mut counts = ScrapedEventCounts::default();
+    for chunk in block_range.clone().into_chunks(max_block_spread) {
+        counts += scrape_block_range(
+            &rpc_client,
+            scrape.contract_addresses.clone(),
+            scrape.topics.clone(),
+            chunk.clone(),
+        )
+        .await
+        .map_err(|e| {
+            log!(
+                INFO,
+                "[backfill_scrape]: Failed to scrape logs in range {chunk}: {e:?}",
+            );
+            BackfillScrapeError::ScrapingFailed(format!("{e:?}"))
+        })?;
+    }
+
+    let (from_block, to_block) = block_range.into_inner();
+    mutate_state(|s| {
+        process_event(
+            s,
+            EventType::BackfillCompleted {
+                from_block,
+                to_block,
+                accepted_events: counts.accepted,
+                invalid_events: counts.invalid,
+                duplicate_events: counts.duplicate,
+            },
+        )
+    });
+    Ok(counts)
+}
+
 async fn scrape_until_block(last_block_number: BlockNumber, max_block_spread: u16) {
     let scrape = match read_state(ReceivedEventsLogScraping::next_scrape) {
         Some(s) => s,
@@ -609,11 +916,19 @@ async fn scrape_until_block(last_block_number: BlockNumber, max_block_spread: u1
         )
         .await
         {
-            Ok(()) => {}
+            Ok(_) => {}
+            Err(e) if is_too_many_logs(&e) => {
+                log!(
+                    INFO,
+                    "[scrape_contract_logs]: Giving up on logs in range {block_range}: {e:?}",
+                );
+                skip_block_range(block_range);
+                return;
+            }
             Err(e) => {
                 log!(
                     INFO,
-                    "[scrape_contract_logs]: Failed to scrape logs in range {block_range}: {e:?}",
+                    "[scrape_contract_logs]: Failed to scrape logs in range {block_range}, will retry at the next scraping: {e:?}",
                 );
                 return;
             }
@@ -621,14 +936,20 @@ async fn scrape_until_block(last_block_number: BlockNumber, max_block_spread: u1
     }
 }
 
+/// Maximum number of times the block range of a single scrape is split in halves because
+/// the logs it contains did not fit into a response.
+pub const MAX_BLOCK_RANGE_BISECTIONS: u32 = 32;
+
 async fn scrape_block_range(
     rpc_client: &RpcClient,
     contract_addresses: Vec<Address>,
     topics: Vec<Topic>,
     block_range: BlockRangeInclusive,
-) -> Result<(), MultiCallError<Vec<LogEntry>>> {
+) -> Result<ScrapedEventCounts, MultiCallError<Vec<LogEntry>>> {
     let mut subranges = VecDeque::new();
     subranges.push_back(block_range);
+    let mut bisections = 0;
+    let mut counts = ScrapedEventCounts::default();
 
     while !subranges.is_empty() {
         let range = subranges.pop_front().unwrap();
@@ -641,37 +962,55 @@ async fn scrape_block_range(
             topics: topics.clone(),
         };
 
-        let mut result = rpc_client
-            .get_logs(request.clone())
+        let result = rpc_client
+            .get_logs(request)
             .await
             .map(ReceivedEventsLogParser::parse_all_logs);
 
-        if result.is_err() {
-            result = rpc_client
-                .get_logs(request)
-                .await
-                .map(ReceivedEventsLogParser::parse_all_logs);
-        }
-
         match result {
             Ok((events, errors)) => {
-                register_deposit_events(events, errors);
-                mutate_state(|s| s.last_scraped_block_number = to_block);
+                counts += register_deposit_events(events, errors);
+                mutate_state(|s| {
+                    if s.scraping_gaps.overlaps(from_block, to_block) {
+                        process_event(
+                            s,
+                            EventType::RescrapedBlockRange {
+                                from_block,
+                                to_block,
+                            },
+                        );
+                    }
+                    // re-scraping a gap must not move the scraping cursor backwards
+                    s.last_scraped_block_number = s.last_scraped_block_number.max(to_block);
+                });
             }
             Err(e) => {
                 log!(INFO, "Failed to get logs in range {range}: {e:?}");
-                if e.has_http_outcall_error_matching(is_response_too_large) {
+                if is_too_many_logs(&e) {
                     if from_block == to_block {
                         mutate_state(|s| {
-                            process_event(
-                                s,
-                                EventType::SkippedBlock {
-                                    block_number: to_block,
-                                },
-                            );
+                            if !s.skipped_blocks.contains(&to_block) {
+                                process_event(
+                                    s,
+                                    EventType::SkippedBlock {
+                                        block_number: to_block,
+                                    },
+                                );
+                            }
+                        });
+                        log_scraping_gaps_alert();
+                        mutate_state(|s| {
+                            s.last_scraped_block_number = s.last_scraped_block_number.max(to_block)
                         });
-                        mutate_state(|s| s.last_scraped_block_number = to_block);
                     } else {
+                        if bisections >= MAX_BLOCK_RANGE_BISECTIONS {
+                            log!(
+                                INFO,
+                                "Too many logs received after {bisections} bisections, giving up on range {range}"
+                            );
+                            return Err(e);
+                        }
+                        bisections += 1;
                         let (left_half, right_half) = range.partition_into_halves();
                         if let Some(r) = right_half {
                             let upper_range = subranges
@@ -688,6 +1027,12 @@ async fn scrape_block_range(
                             "Too many logs received. Will retry with ranges {subranges:?}"
                         );
                     }
+                } else if e.is_rate_limited() {
+                    log!(
+                        INFO,
+                        "Rate limited by providers while getting logs in range {range}, will retry at the next scraping: {e:?}"
+                    );
+                    return Err(e);
                 } else {
                     log!(INFO, "Failed to get logs in range {range}: {e:?}",);
                     return Err(e);
@@ -695,14 +1040,46 @@ async fn scrape_block_range(
             }
         }
     }
-    Ok(())
+    Ok(counts)
+}
+
+/// Returns true if the logs of the requested block range did not fit into a response.
+/// This is the only error that does not go away by retrying the same range later.
+fn is_too_many_logs(e: &MultiCallError<Vec<LogEntry>>) -> bool {
+    e.has_http_outcall_error_matching(is_response_too_large) || e.is_result_set_too_large()
+}
+
+/// Number of events found while scraping a block range, by outcome.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScrapedEventCounts {
+    /// New events, recorded to be minted, released or otherwise processed.
+    pub accepted: u64,
+    /// Events with an invalid payload, seen for the first time.
+    pub invalid: u64,
+    /// Events that were already recorded before, valid or not.
+    pub duplicate: u64,
+}
+
+impl std::ops::AddAssign for ScrapedEventCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.accepted += other.accepted;
+        self.invalid += other.invalid;
+        self.duplicate += other.duplicate;
+    }
 }
 
 pub fn register_deposit_events(
     transaction_events: Vec<ReceivedContractEvent>,
     errors: Vec<ReceivedContractEventError>,
-) {
+) -> ScrapedEventCounts {
+    let mut counts = ScrapedEventCounts::default();
     for event in transaction_events {
+        if read_state(|s| s.is_known_event(&event)) {
+            log!(DEBUG, "Skipping already recorded event {event:?}");
+            counts.duplicate += 1;
+            continue;
+        }
+        counts.accepted += 1;
         match &event {
             ReceivedContractEvent::NativeDeposit(received_native_event) => {
                 log!(
@@ -750,32 +1127,83 @@ pub fn register_deposit_events(
         });
     }
 
-    if read_state(|s| s.is_swapping_active && s.has_events_to_mint_and_notify()) {
+    if read_state(|s| s.is_swapping_active() && s.has_events_to_mint_and_notify()) {
         ic_cdk_timers::set_timer(Duration::from_secs(0), || {
             ic_cdk::futures::spawn_017_compat(mint_to_appic_dex_and_swap());
         });
     }
 
     for error in errors {
-        if let ReceivedContractEventError::InvalidEventSource { source, error } = &error {
-            mutate_state(|s| {
-                process_event(
-                    s,
-                    EventType::InvalidEvent {
-                        event_source: *source,
-                        reason: error.to_string(),
-                    },
-                )
-            });
+        match &error {
+            ReceivedContractEventError::InvalidEventSource { source, error } => {
+                if read_state(|s| s.invalid_events.contains_key(source)) {
+                    counts.duplicate += 1;
+                } else {
+                    counts.invalid += 1;
+                }
+                mutate_state(|s| {
+                    process_event(
+                        s,
+                        EventType::InvalidEvent {
+                            event_source: *source,
+                            reason: error.to_string(),
+                        },
+                    )
+                });
+            }
+            ReceivedContractEventError::RemovedLogEntry { source } => {
+                // The ledger may already have minted a deposit whose mint is in flight,
+                // so its removal is only recorded once the mint resolved, see `MintGuard`.
+                mutate_state(|s| {
+                    if s.mints_in_flight.contains(source) {
+                        s.reorged_mints_in_flight.insert(*source);
+                    } else if let Some(payload) = removed_log_event(s, *source) {
+                        process_event(s, payload)
+                    }
+                });
+            }
+            _ => {}
         }
         report_transaction_error(error);
     }
+    counts
 }
 
+/// Returns the event recording that the log of `source` was removed by a reorganisation,
+/// or `None` if its removal was already recorded, since every scrape of the same block range
+/// reports the removed log again.
+///
+/// A removed log must never be minted, if it was already accepted but not processed yet
+/// it is dropped and the state is reverted.
+pub(crate) fn removed_log_event(state: &State, source: EventSource) -> Option<EventType> {
+    if state.is_pending_event(&source) {
+        Some(EventType::DepositReorged {
+            event_source: source,
+        })
+    } else if state.removed_log_events.contains(&source) {
+        None
+    } else {
+        Some(EventType::RemovedLog {
+            event_source: source,
+        })
+    }
+}
+
+/// Returns the highest block number considered confirmed under the given policy.
+///
+/// An explicit confirmation count takes precedence over the per-network defaults,
+/// which are only applied when the policy is a block tag.
 pub fn apply_safe_threshold_to_latest_block_numner(
     network: EvmNetwork,
+    confirmation_policy: ConfirmationPolicy,
     latest_block: BlockNumber,
 ) -> BlockNumber {
+    if let ConfirmationPolicy::Confirmations(confirmations) = confirmation_policy {
+        return latest_block
+            .checked_sub(BlockNumber::from(confirmations))
+            .unwrap_or(BlockNumber::ZERO);
+    }
+
     match network {
         EvmNetwork::BSC => {
             // Waiting for 12 blocks means the transaction is practically safe on BSC
@@ -0,0 +1,213 @@
+//! Expected outcome of a deposit, shared by the `quote_deposit` query, the ICRC-21
+//! consent messages and the minting logic so that all of them agree.
+
+use crate::candid_types::DepositQuote;
+use crate::deposit::apply_safe_threshold_to_latest_block_numner;
+use crate::erc20::ERC20TokenSymbol;
+use crate::evm_config::{ConfirmationPolicy, EvmNetwork};
+use crate::numeric::{BlockNumber, Erc20Value};
+use crate::rpc_declarations::BlockTag;
+use crate::state::State;
+use crate::SCRAPING_CONTRACT_LOGS_INTERVAL;
+use candid::{Nat, Principal};
+use evm_rpc_client::eth_types::Address;
+use std::str::FromStr;
+
+/// Number of blocks that must be mined on top of the deposit block before it is minted.
+///
+/// Returns `None` when the deposit waits for the `safe` or `finalized` block, whose
+/// distance to the latest block depends on the network's finality and not on the minter.
+pub fn blocks_before_minting(
+    evm_network: EvmNetwork,
+    confirmation_policy: ConfirmationPolicy,
+) -> Option<u64> {
+    match confirmation_policy {
+        ConfirmationPolicy::Confirmations(confirmations) => Some(u64::from(confirmations)),
+        ConfirmationPolicy::BlockTag(BlockTag::Latest) => {
+            let latest_block = BlockNumber::from(u64::MAX);
+            let confirmed_block = apply_safe_threshold_to_latest_block_numner(
+                evm_network,
+                confirmation_policy,
+                latest_block,
+            );
+            latest_block
+                .checked_sub(confirmed_block)
+                .map(|margin| margin.into_inner().as_u64())
+        }
+        ConfirmationPolicy::BlockTag(BlockTag::Safe | BlockTag::Finalized) => None,
+    }
+}
+
+/// Helper contract that deposits should be sent to, i.e. the most recently added one.
+pub fn deposit_helper_contract_address(state: &State) -> Option<Address> {
+    state
+        .helper_contract_addresses
+        .as_ref()
+        .and_then(|addresses| addresses.last())
+        .copied()
+}
+
+/// Builds the quote of depositing `amount` of `token`, the native token when `None` or
+/// the address of a supported ERC-20 token otherwise.
+pub fn quote_deposit(
+    state: &State,
+    token: Option<String>,
+    amount: Nat,
+) -> Result<DepositQuote, String> {
+    let amount = Erc20Value::try_from(amount)?;
+    let (ledger_id, token_symbol, decimals) = match &token {
+        None => (
+            state.native_ledger_id,
+            ERC20TokenSymbol::new(state.native_ledger_symbol()),
+            Some(state.native_ledger_decimals()),
+        ),
+        Some(address) => {
+            let address = Address::from_str(address)?;
+            let (ledger_id, token_symbol) = state
+                .erc20_tokens
+                .get_entry_alt(&address)
+                .ok_or_else(|| format!("ERC-20 token {address} is not supported"))?;
+            (*ledger_id, token_symbol.clone(), None)
+        }
+    };
+    Ok(build_deposit_quote(
+        state,
+        token,
+        ledger_id,
+        token_symbol,
+        decimals,
+        amount,
+    ))
+}
+
+fn build_deposit_quote(
+    state: &State,
+    token: Option<String>,
+    ledger_id: Principal,
+    token_symbol: ERC20TokenSymbol,
+    decimals: Option<u8>,
+    amount: Erc20Value,
+) -> DepositQuote {
+    DepositQuote {
+        token,
+        token_symbol: token_symbol.to_string(),
+        decimals,
+        ledger_id,
+        amount: amount.into(),
+        expected_minted_amount: amount.into(),
+        confirmation_policy: state.confirmation_policy.into(),
+        blocks_before_minting: blocks_before_minting(state.evm_network, state.confirmation_policy),
+        scraping_interval_seconds: SCRAPING_CONTRACT_LOGS_INTERVAL.as_secs(),
+        helper_contract_address: deposit_helper_contract_address(state)
+            .map(|address| address.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::candid_types::CandidConfirmationPolicy;
+    use crate::state::tests::initial_state;
+    use crate::state::NativeLedgerMetadata;
+
+    const HELPER_CONTRACT: &str = "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34";
+
+    fn state() -> State {
+        let mut state = initial_state();
+        state.evm_network = EvmNetwork::Sepolia;
+        state.confirmation_policy = ConfirmationPolicy::Confirmations(12);
+        state.helper_contract_addresses = Some(vec![Address::from_str(HELPER_CONTRACT).unwrap()]);
+        state.record_add_erc20_token(crate::state::tests::erc20::record_add_erc20_token::ic_usdc());
+        state
+    }
+
+    #[test]
+    fn should_quote_native_deposit() {
+        let state = state();
+
+        let quote = quote_deposit(&state, None, Nat::from(1_000_000_u64)).unwrap();
+
+        assert_eq!(
+            quote,
+            DepositQuote {
+                token: None,
+                token_symbol: "icMatic".to_string(),
+                decimals: Some(18),
+                ledger_id: state.native_ledger_id,
+                amount: Nat::from(1_000_000_u64),
+                expected_minted_amount: Nat::from(1_000_000_u64),
+                confirmation_policy: CandidConfirmationPolicy::Confirmations(12),
+                blocks_before_minting: Some(12),
+                scraping_interval_seconds: 300,
+                helper_contract_address: Some(HELPER_CONTRACT.to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn should_quote_erc20_deposit() {
+        let state = state();
+        let token = crate::state::tests::erc20::record_add_erc20_token::ic_usdc();
+
+        let quote = quote_deposit(
+            &state,
+            Some(token.erc20_contract_address.to_string()),
+            Nat::from(1_000_000_u64),
+        )
+        .unwrap();
+
+        assert_eq!(quote.token_symbol, "icUSDC");
+        assert_eq!(quote.ledger_id, token.erc20_ledger_id);
+        assert_eq!(quote.decimals, None);
+        assert_eq!(quote.expected_minted_amount, Nat::from(1_000_000_u64));
+    }
+
+    #[test]
+    fn should_quote_native_deposit_with_ledger_metadata() {
+        let mut state = state();
+        state.record_native_ledger_metadata(NativeLedgerMetadata {
+            symbol: "POL.icp".to_string(),
+            decimals: 8,
+        });
+
+        let quote = quote_deposit(&state, None, Nat::from(1_000_000_u64)).unwrap();
+
+        assert_eq!(quote.token_symbol, "POL.icp");
+        assert_eq!(quote.decimals, Some(8));
+    }
+
+    #[test]
+    fn should_reject_unsupported_token() {
+        let state = state();
+
+        assert!(quote_deposit(
+            &state,
+            Some("0x0000000000000000000000000000000000000001".to_string()),
+            Nat::from(1_u8)
+        )
+        .is_err());
+        assert!(quote_deposit(&state, Some("0xinvalid".to_string()), Nat::from(1_u8)).is_err());
+    }
+
+    #[test]
+    fn should_derive_blocks_before_minting_from_confirmation_policy() {
+        assert_eq!(
+            blocks_before_minting(EvmNetwork::BSC, ConfirmationPolicy::Confirmations(20)),
+            Some(20)
+        );
+        assert_eq!(
+            blocks_before_minting(
+                EvmNetwork::ArbitrumOne,
+                ConfirmationPolicy::BlockTag(BlockTag::Latest)
+            ),
+            Some(6)
+        );
+        assert_eq!(
+            blocks_before_minting(
+                EvmNetwork::Ethereum,
+                ConfirmationPolicy::BlockTag(BlockTag::Finalized)
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,115 @@
+mod validate_log_scraping_request {
+    use crate::candid_types::RequestScrapingError;
+    use crate::deposit::validate_log_scraping_request;
+
+    const NOW: u64 = 1_699_527_697_000_000_000;
+    const SECOND: u64 = 1_000_000_000;
+
+    #[test]
+    fn should_accept_first_request() {
+        assert_eq!(validate_log_scraping_request(0, NOW), Ok(()));
+    }
+
+    #[test]
+    fn should_accept_request_exactly_one_minute_later() {
+        assert_eq!(
+            validate_log_scraping_request(NOW, NOW + 60 * SECOND),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn should_reject_request_within_one_minute() {
+        for elapsed in [0, 1, 59 * SECOND, 60 * SECOND - 1] {
+            assert_eq!(
+                validate_log_scraping_request(NOW, NOW + elapsed),
+                Err(RequestScrapingError::CalledTooManyTimes)
+            );
+        }
+    }
+
+    #[test]
+    fn should_reject_request_before_last_request() {
+        assert_eq!(
+            validate_log_scraping_request(NOW, NOW - 60 * SECOND),
+            Err(RequestScrapingError::CalledTooManyTimes)
+        );
+    }
+
+    #[test]
+    fn should_not_overflow_at_end_of_time() {
+        assert_eq!(validate_log_scraping_request(0, u64::MAX), Ok(()));
+        assert_eq!(
+            validate_log_scraping_request(u64::MAX, u64::MAX),
+            Err(RequestScrapingError::CalledTooManyTimes)
+        );
+        assert_eq!(
+            validate_log_scraping_request(u64::MAX - 60 * SECOND, u64::MAX),
+            Ok(())
+        );
+    }
+}
+
+mod validate_backfill_range {
+    use crate::candid_types::BackfillScrapeError;
+    use crate::deposit::{validate_backfill_range, MAX_BACKFILL_SCRAPE_BLOCKS};
+    use crate::numeric::{BlockNumber, BlockRangeInclusive};
+    use candid::Nat;
+
+    const LAST_SCRAPED_BLOCK: u64 = 7_000_000;
+
+    fn validate(
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<BlockRangeInclusive, BackfillScrapeError> {
+        validate_backfill_range(
+            BlockNumber::from(from_block),
+            BlockNumber::from(to_block),
+            BlockNumber::from(LAST_SCRAPED_BLOCK),
+        )
+    }
+
+    #[test]
+    fn should_accept_range_below_last_scraped_block() {
+        assert_eq!(
+            validate(6_000_000, 6_000_099),
+            Ok(BlockRangeInclusive::new(
+                BlockNumber::from(6_000_000_u64),
+                BlockNumber::from(6_000_099_u64)
+            ))
+        );
+        assert!(validate(LAST_SCRAPED_BLOCK - 1, LAST_SCRAPED_BLOCK - 1).is_ok());
+    }
+
+    #[test]
+    fn should_accept_range_of_max_size() {
+        let from_block = 6_000_000;
+        assert!(validate(from_block, from_block + MAX_BACKFILL_SCRAPE_BLOCKS - 1).is_ok());
+        assert_eq!(
+            validate(from_block, from_block + MAX_BACKFILL_SCRAPE_BLOCKS),
+            Err(BackfillScrapeError::BlockRangeTooLarge {
+                max_blocks: MAX_BACKFILL_SCRAPE_BLOCKS
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_reversed_range() {
+        assert_eq!(
+            validate(6_000_001, 6_000_000),
+            Err(BackfillScrapeError::InvalidBlockRange)
+        );
+    }
+
+    #[test]
+    fn should_reject_range_reaching_last_scraped_block() {
+        for to_block in [LAST_SCRAPED_BLOCK, LAST_SCRAPED_BLOCK + 1] {
+            assert_eq!(
+                validate(LAST_SCRAPED_BLOCK - 10, to_block),
+                Err(BackfillScrapeError::BlockNotScrapedYet {
+                    last_scraped_block_number: Nat::from(LAST_SCRAPED_BLOCK)
+                })
+            );
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use crate::candid_types::AddErc20Token;
+pub mod transfer_log;
+
+use crate::candid_types::{AddErc20Token, Erc20TransferKind};
 use crate::evm_config::EvmNetwork;
 use candid::Principal;
 use evm_rpc_client::eth_types::Address;
@@ -8,6 +10,9 @@ use std::fmt::Display;
 use std::str::FromStr;
 
 pub const MAX_ERC20_TOKEN_SYMBOL_NUM_BYTES: usize = 20;
+/// Maximum length of the symbol of a newly added token, which leaves room in
+/// [`MAX_ERC20_TOKEN_SYMBOL_NUM_BYTES`] for a disambiguation suffix such as `.2`.
+pub const MAX_NEW_ERC20_TOKEN_SYMBOL_LEN: usize = 16;
 
 #[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
 pub struct ERC20Token {
@@ -19,6 +24,21 @@ pub struct ERC20Token {
     pub erc20_token_symbol: ERC20TokenSymbol,
     #[cbor(n(3), with = "crate::cbor::principal")]
     pub erc20_ledger_id: Principal,
+    /// `None` for [`Erc20TransferKind::Standard`], which is also the case of every token
+    /// added before the transfer kind could be set.
+    #[n(4)]
+    pub transfer_kind: Option<Erc20TransferKind>,
+    /// Symbol requested when the token was added, if `erc20_token_symbol` had to be
+    /// disambiguated because another token already used it, see
+    /// [`SymbolCollisionPolicy`](crate::candid_types::SymbolCollisionPolicy).
+    #[n(5)]
+    pub original_symbol: Option<ERC20TokenSymbol>,
+}
+
+impl ERC20Token {
+    pub fn is_non_standard(&self) -> bool {
+        self.transfer_kind == Some(Erc20TransferKind::NonStandard)
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Encode, Decode)]
@@ -29,6 +49,29 @@ impl ERC20TokenSymbol {
     pub fn new(symbol: String) -> Self {
         Self(symbol)
     }
+
+    /// Parses the symbol of a token to be added, which must consist of 1 to
+    /// [`MAX_NEW_ERC20_TOKEN_SYMBOL_LEN`] ASCII alphanumeric characters.
+    pub fn parse_new(token_symbol: &str) -> Result<Self, String> {
+        if token_symbol.is_empty() || token_symbol.len() > MAX_NEW_ERC20_TOKEN_SYMBOL_LEN {
+            return Err(format!(
+                "ERROR: token symbol must have between 1 and {} characters, but got {}",
+                MAX_NEW_ERC20_TOKEN_SYMBOL_LEN,
+                token_symbol.len()
+            ));
+        }
+        if !token_symbol.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!(
+                "ERROR: token symbol {token_symbol} must be ASCII alphanumeric"
+            ));
+        }
+        Ok(Self(token_symbol.to_string()))
+    }
+
+    /// Returns the symbol with the given disambiguation suffix, e.g. `USDT.2`.
+    pub fn with_suffix(&self, suffix: u32) -> Self {
+        Self(format!("{}.{suffix}", self.0))
+    }
 }
 
 impl Display for ERC20TokenSymbol {
@@ -71,8 +114,12 @@ impl TryFrom<AddErc20Token> for ERC20Token {
         Ok(Self {
             chain_id: erc20_ethereum_network,
             erc20_contract_address,
-            erc20_token_symbol: value.erc20_token_symbol.parse()?,
+            erc20_token_symbol: ERC20TokenSymbol::parse_new(&value.erc20_token_symbol)?,
             erc20_ledger_id: value.erc20_ledger_id,
+            transfer_kind: value
+                .transfer_kind
+                .filter(|kind| *kind != Erc20TransferKind::Standard),
+            original_symbol: None,
         })
     }
 }
@@ -0,0 +1,174 @@
+use crate::numeric::Erc20Value;
+use crate::rpc_declarations::LogEntry;
+use evm_rpc_client::eth_types::Address;
+use hex_literal::hex;
+
+// "Transfer(address,address,uint256)": "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+pub const TRANSFER_EVENT_TOPIC: [u8; 32] =
+    hex!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+
+/// Returns true if `logs` contain a `Transfer` event emitted by the ERC-20 contract `token`
+/// that moved exactly `amount` from `from` to `to`.
+///
+/// Non-standard tokens may not revert when a transfer fails, in which case the transaction
+/// succeeds without emitting such an event.
+pub fn contains_transfer_log(
+    logs: &[LogEntry],
+    token: &Address,
+    from: &Address,
+    to: &Address,
+    amount: Erc20Value,
+) -> bool {
+    let from_topic = <[u8; 32]>::from(from);
+    let to_topic = <[u8; 32]>::from(to);
+    let amount_data = amount.to_be_bytes();
+    logs.iter().any(|log| {
+        !log.removed
+            && &log.address == token
+            && log.topics.len() == 3
+            && log.topics[0].0 == TRANSFER_EVENT_TOPIC
+            && log.topics[1].0 == from_topic
+            && log.topics[2].0 == to_topic
+            && log.data.0 == amount_data
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc_declarations::{Data, FixedSizeData};
+    use std::str::FromStr;
+
+    const BSC_USDT: &str = "0x55d398326f99059fF775485246999027B3197955";
+    const MINTER: &str = "0x1789F79e95324A47c5Fd6693071188e82E9a3558";
+    const RECIPIENT: &str = "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34";
+
+    // Log of a transfer of 1.5 USDT (18 decimals) from MINTER to RECIPIENT, ABI-encoded as
+    // emitted by the BSC USDT contract, i.e. with both addresses indexed.
+    fn transfer_log() -> LogEntry {
+        LogEntry {
+            address: usdt(),
+            topics: vec![
+                FixedSizeData(TRANSFER_EVENT_TOPIC),
+                FixedSizeData(<[u8; 32]>::from(&minter())),
+                FixedSizeData(<[u8; 32]>::from(&recipient())),
+            ],
+            data: Data(amount().to_be_bytes().to_vec()),
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            block_hash: None,
+            log_index: None,
+            removed: false,
+        }
+    }
+
+    fn usdt() -> Address {
+        Address::from_str(BSC_USDT).unwrap()
+    }
+
+    fn minter() -> Address {
+        Address::from_str(MINTER).unwrap()
+    }
+
+    fn recipient() -> Address {
+        Address::from_str(RECIPIENT).unwrap()
+    }
+
+    fn amount() -> Erc20Value {
+        Erc20Value::new(1_500_000_000_000_000_000)
+    }
+
+    #[test]
+    fn should_encode_transfer_log_as_emitted_on_chain() {
+        let log = transfer_log();
+
+        assert_eq!(
+            log.topics[1].0,
+            hex!("0000000000000000000000001789f79e95324a47c5fd6693071188e82e9a3558")
+        );
+        assert_eq!(
+            log.data.0,
+            hex!("00000000000000000000000000000000000000000000000014d1120d7b160000")
+        );
+    }
+
+    #[test]
+    fn should_find_matching_transfer_log() {
+        assert!(contains_transfer_log(
+            &[transfer_log()],
+            &usdt(),
+            &minter(),
+            &recipient(),
+            amount()
+        ));
+    }
+
+    #[test]
+    fn should_not_find_transfer_log_when_absent() {
+        assert!(!contains_transfer_log(
+            &[],
+            &usdt(),
+            &minter(),
+            &recipient(),
+            amount()
+        ));
+    }
+
+    #[test]
+    fn should_not_find_transfer_log_with_other_fields() {
+        let logs = [transfer_log()];
+        let other_address =
+            Address::from_str("0x8AC76a51cc950d9822D68b83fE1Ad97B32Cd580d").unwrap();
+
+        assert!(!contains_transfer_log(
+            &logs,
+            &other_address,
+            &minter(),
+            &recipient(),
+            amount()
+        ));
+        assert!(!contains_transfer_log(
+            &logs,
+            &usdt(),
+            &other_address,
+            &recipient(),
+            amount()
+        ));
+        assert!(!contains_transfer_log(
+            &logs,
+            &usdt(),
+            &minter(),
+            &other_address,
+            amount()
+        ));
+        assert!(!contains_transfer_log(
+            &logs,
+            &usdt(),
+            &minter(),
+            &recipient(),
+            Erc20Value::new(1)
+        ));
+
+        let mut removed = transfer_log();
+        removed.removed = true;
+        assert!(!contains_transfer_log(
+            &[removed],
+            &usdt(),
+            &minter(),
+            &recipient(),
+            amount()
+        ));
+
+        let mut approval = transfer_log();
+        approval.topics[0].0 =
+            hex!("8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925");
+        assert!(!contains_transfer_log(
+            &[approval],
+            &usdt(),
+            &minter(),
+            &recipient(),
+            amount()
+        ));
+    }
+}
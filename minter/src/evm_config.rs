@@ -1,6 +1,14 @@
+use crate::candid_types::CandidConfirmationPolicy;
+use crate::lifecycle::GasLimitArg;
+use crate::numeric::{GasAmount, Wei, WeiPerGas};
+use crate::rpc_declarations::BlockTag;
+use crate::state::InvalidStateError;
 use candid::{CandidType, Deserialize};
+use evm_rpc_client::eth_types::Address;
+use hex_literal::hex;
 use minicbor::{Decode, Encode};
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 use strum::EnumIter;
 
@@ -61,6 +69,58 @@ impl EvmNetwork {
     }
 }
 
+impl EvmNetwork {
+    /// Address of the canonical wrapped native token contract (WETH, WBNB, ...) on this
+    /// network, whose `Withdrawal` events record the unwrapping of native tokens.
+    pub fn wrapped_native_token(&self) -> Address {
+        Address::new(match self {
+            EvmNetwork::Ethereum => hex!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            EvmNetwork::Sepolia => hex!("fff9976782d46cc05630d1f6ebab18b2324d6b14"),
+            EvmNetwork::ArbitrumOne => hex!("82af49447d8a07e3bd95bd0d56f35241523fbab1"),
+            EvmNetwork::BSC => hex!("bb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c"),
+            EvmNetwork::BSCTestnet => hex!("ae13d989dac2f0debff460ac112a837c89baa7cd"),
+            EvmNetwork::Polygon => hex!("0d500b1d8e8ef31e21c99d1db9a6444d3adf1270"),
+            EvmNetwork::Optimism | EvmNetwork::Base => {
+                hex!("4200000000000000000000000000000000000006")
+            }
+            EvmNetwork::Avalanche => hex!("b31f66aa3c1e785363f0875a1b74e27b85fd66c7"),
+            EvmNetwork::Fantom => hex!("21be370d5312f44cb42ce377bc9b8a0cef1a4c83"),
+        })
+    }
+
+    /// Highest `max_fee_per_gas` of the transactions sent by the minter on this network,
+    /// unless overridden by an upgrade.
+    pub fn default_max_fee_per_gas_cap(&self) -> WeiPerGas {
+        match self {
+            EvmNetwork::Polygon | EvmNetwork::Fantom => WeiPerGas::new(5_000_000_000_000),
+            EvmNetwork::Ethereum
+            | EvmNetwork::Sepolia
+            | EvmNetwork::ArbitrumOne
+            | EvmNetwork::BSC
+            | EvmNetwork::BSCTestnet
+            | EvmNetwork::Optimism
+            | EvmNetwork::Base
+            | EvmNetwork::Avalanche => WeiPerGas::new(500_000_000_000),
+        }
+    }
+
+    /// Highest maximum fee of a single transaction sent by the minter on this network,
+    /// unless overridden by an upgrade.
+    pub fn default_max_transaction_fee_cap(&self) -> Wei {
+        match self {
+            EvmNetwork::Polygon | EvmNetwork::Fantom => Wei::new(5_000_000_000_000_000_000),
+            EvmNetwork::Avalanche => Wei::new(500_000_000_000_000_000),
+            EvmNetwork::Ethereum
+            | EvmNetwork::Sepolia
+            | EvmNetwork::ArbitrumOne
+            | EvmNetwork::BSC
+            | EvmNetwork::BSCTestnet
+            | EvmNetwork::Optimism
+            | EvmNetwork::Base => Wei::new(50_000_000_000_000_000),
+        }
+    }
+}
+
 impl TryFrom<u64> for EvmNetwork {
     type Error = String;
 
@@ -97,3 +157,205 @@ impl Display for EvmNetwork {
         }
     }
 }
+
+/// Number of confirmations used on BSC instead of the `safe` block tag, which does not
+/// provide any additional guarantee over `latest` there.
+pub const BSC_SAFE_CONFIRMATIONS: u32 = 12;
+
+/// Upper bound on the number of confirmations, so that a typo cannot stall deposits for days.
+pub const MAX_CONFIRMATIONS: u32 = 1_000;
+
+/// Determines up to which block the minter scrapes logs, i.e. when deposits are accepted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConfirmationPolicy {
+    /// Scrape up to the block with the given tag, minus the network specific safety margin.
+    BlockTag(BlockTag),
+    /// Scrape up to the latest block minus the given number of confirmations.
+    Confirmations(u32),
+}
+
+impl Default for ConfirmationPolicy {
+    fn default() -> Self {
+        ConfirmationPolicy::BlockTag(BlockTag::default())
+    }
+}
+
+impl ConfirmationPolicy {
+    /// Returns the policy replacing this one on `network` if its block tag has no meaning
+    /// there, e.g. the `safe` tag on BSC, which is replaced by [`BSC_SAFE_CONFIRMATIONS`].
+    pub fn migrated(&self, network: EvmNetwork) -> Option<Self> {
+        match (network, self) {
+            (
+                EvmNetwork::BSC | EvmNetwork::BSCTestnet,
+                ConfirmationPolicy::BlockTag(BlockTag::Safe),
+            ) => Some(ConfirmationPolicy::Confirmations(BSC_SAFE_CONFIRMATIONS)),
+            _ => None,
+        }
+    }
+
+    /// Rejects a block tag that has no meaning on `network`, see [`Self::migrated`].
+    pub fn validate_block_tag(
+        network: EvmNetwork,
+        block_tag: BlockTag,
+    ) -> Result<(), InvalidStateError> {
+        match ConfirmationPolicy::BlockTag(block_tag).migrated(network) {
+            Some(policy) => Err(InvalidStateError::InvalidConfirmationPolicy(format!(
+                "the {block_tag:?} block tag has no meaning on {network}, use {policy:?} instead"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Tag of the block requested from the providers as the current head.
+    pub fn block_tag(&self) -> BlockTag {
+        match self {
+            ConfirmationPolicy::BlockTag(block_tag) => *block_tag,
+            ConfirmationPolicy::Confirmations(_) => BlockTag::Latest,
+        }
+    }
+}
+
+impl From<ConfirmationPolicy> for CandidConfirmationPolicy {
+    fn from(policy: ConfirmationPolicy) -> Self {
+        match policy {
+            ConfirmationPolicy::BlockTag(block_tag) => {
+                CandidConfirmationPolicy::BlockTag(block_tag.into())
+            }
+            ConfirmationPolicy::Confirmations(confirmations) => {
+                CandidConfirmationPolicy::Confirmations(confirmations)
+            }
+        }
+    }
+}
+
+impl From<CandidConfirmationPolicy> for ConfirmationPolicy {
+    fn from(policy: CandidConfirmationPolicy) -> Self {
+        match policy {
+            CandidConfirmationPolicy::BlockTag(block_tag) => {
+                ConfirmationPolicy::BlockTag(block_tag.into())
+            }
+            CandidConfirmationPolicy::Confirmations(confirmations) => {
+                ConfirmationPolicy::Confirmations(confirmations)
+            }
+        }
+    }
+}
+
+// 21000 is fixed for native tokens, however 65000 is idle for ERC20s but some ERC20 contracts have
+// more complicated logic that requires maximum of 100000 Gas.
+pub const NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT: GasAmount = GasAmount::new(21_000);
+// Smart contract wallets (e.g. Safe, Argent) run their `receive` fallback when receiving the native
+// token, which does not fit in 21000 gas.
+pub const NATIVE_WITHDRAWAL_TO_CONTRACT_TRANSACTION_GAS_LIMIT: GasAmount = GasAmount::new(50_000);
+pub const ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT: GasAmount = GasAmount::new(66_000);
+
+pub const ERC20_APPROVAL_TRANSACTION_GAS_LIMIT: GasAmount = GasAmount::new(70_000);
+
+// used for mining wrapped icrc transactions
+pub const ERC20_MINT_TRANSACTION_GAS_LIMIT: GasAmount = GasAmount::new(100_000);
+
+pub const REFUND_FAILED_SWAP_GAS_LIMIT: GasAmount = GasAmount::new(120_000);
+
+/// Bounds of the gas limits that can be set by an upgrade.
+pub const MIN_GAS_LIMIT: GasAmount = GasAmount::new(21_000);
+pub const MAX_GAS_LIMIT: GasAmount = GasAmount::new(5_000_000);
+
+/// Operations for which the minter sends transactions, each with its own gas limit.
+#[derive(
+    CandidType,
+    Clone,
+    Copy,
+    Deserialize,
+    Debug,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Encode,
+    Decode,
+    EnumIter,
+)]
+pub enum GasLimitKind {
+    #[n(0)]
+    NativeWithdrawal,
+    #[n(1)]
+    Erc20Withdrawal,
+    #[n(2)]
+    Erc20Approval,
+    #[n(3)]
+    WrappedIcrcMint,
+    #[n(4)]
+    RefundFailedSwap,
+    /// Native withdrawal to an address with deployed code.
+    #[n(5)]
+    NativeWithdrawalToContract,
+}
+
+/// Gas limits of the transactions sent by the minter: the defaults of the network,
+/// unless overridden by an upgrade.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Encode, Decode)]
+pub struct GasLimits {
+    #[n(0)]
+    overrides: BTreeMap<GasLimitKind, GasAmount>,
+}
+
+impl GasLimits {
+    /// Default gas limit of `kind` on `network`.
+    pub fn default_gas_limit(network: EvmNetwork, kind: GasLimitKind) -> GasAmount {
+        match network {
+            // Arbitrum charges the L1 data fee in L2 gas, so that the same transaction
+            // consumes more gas than on other networks, see
+            // https://docs.arbitrum.io/build-decentralized-apps/how-to-estimate-gas
+            // The L1 part varies with the L1 gas price, the limits below leave room for it
+            // on top of the execution gas of each operation.
+            EvmNetwork::ArbitrumOne => match kind {
+                GasLimitKind::NativeWithdrawal => GasAmount::new(100_000),
+                GasLimitKind::NativeWithdrawalToContract => GasAmount::new(150_000),
+                GasLimitKind::Erc20Withdrawal => GasAmount::new(200_000),
+                GasLimitKind::Erc20Approval => GasAmount::new(200_000),
+                GasLimitKind::WrappedIcrcMint => GasAmount::new(250_000),
+                GasLimitKind::RefundFailedSwap => GasAmount::new(300_000),
+            },
+            _ => match kind {
+                GasLimitKind::NativeWithdrawal => NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+                GasLimitKind::NativeWithdrawalToContract => {
+                    NATIVE_WITHDRAWAL_TO_CONTRACT_TRANSACTION_GAS_LIMIT
+                }
+                GasLimitKind::Erc20Withdrawal => ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+                GasLimitKind::Erc20Approval => ERC20_APPROVAL_TRANSACTION_GAS_LIMIT,
+                GasLimitKind::WrappedIcrcMint => ERC20_MINT_TRANSACTION_GAS_LIMIT,
+                GasLimitKind::RefundFailedSwap => REFUND_FAILED_SWAP_GAS_LIMIT,
+            },
+        }
+    }
+
+    /// Gas limit of `kind` on `network`, taking overrides into account.
+    pub fn get(&self, network: EvmNetwork, kind: GasLimitKind) -> GasAmount {
+        self.overrides
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| Self::default_gas_limit(network, kind))
+    }
+
+    pub fn overrides(&self) -> &BTreeMap<GasLimitKind, GasAmount> {
+        &self.overrides
+    }
+
+    pub fn set_override(&mut self, kind: GasLimitKind, gas_limit: GasAmount) {
+        self.overrides.insert(kind, gas_limit);
+    }
+
+    /// Parses a gas limit set by an upgrade, which must be between [`MIN_GAS_LIMIT`]
+    /// and [`MAX_GAS_LIMIT`].
+    pub fn parse_override(arg: GasLimitArg) -> Result<(GasLimitKind, GasAmount), String> {
+        let gas_limit = GasAmount::try_from(arg.gas_limit)
+            .map_err(|e| format!("invalid gas limit for {:?}: {e}", arg.kind))?;
+        if !(MIN_GAS_LIMIT..=MAX_GAS_LIMIT).contains(&gas_limit) {
+            return Err(format!(
+                "gas limit for {:?} must be between {MIN_GAS_LIMIT} and {MAX_GAS_LIMIT}, got {gas_limit}",
+                arg.kind
+            ));
+        }
+        Ok((arg.kind, gas_limit))
+    }
+}
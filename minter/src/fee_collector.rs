@@ -0,0 +1,330 @@
+use crate::candid_types::WithdrawCollectedFeesError;
+use crate::guard::TimerGuard;
+use crate::icrc_client::runtime::IcrcBoundedRuntime;
+use crate::logs::INFO;
+use crate::numeric::IcrcValue;
+use crate::state::{audit::process_event, event::EventType, mutate_state, read_state, TaskType};
+use crate::FEES_SUBACCOUNT;
+use candid::{Nat, Principal};
+use ic_canister_log::log;
+use icrc_ledger_client::ICRC1Client;
+use icrc_ledger_types::icrc1::{
+    account::Account,
+    transfer::{TransferArg, TransferError},
+};
+use minicbor::{Decode, Encode};
+use num_traits::ToPrimitive;
+
+/// Account of the minter holding the collected fees until they are withdrawn to the fee
+/// collector.
+pub fn fee_holding_account() -> Account {
+    Account {
+        owner: ic_cdk::api::canister_self(),
+        subaccount: Some(FEES_SUBACCOUNT),
+    }
+}
+
+/// Transfer of collected fees to the fee collector whose outcome is not known yet.
+///
+/// It is retried with the same arguments by the next withdrawal of the ledger, so that the
+/// ledger deduplicates it if it was executed. Recorded with
+/// [`EventType::CollectedFeesWithdrawalStarted`], so that it survives upgrades.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct PendingFeeWithdrawal {
+    #[cbor(n(0), with = "crate::cbor::account")]
+    pub to: Account,
+    /// The amount received by `to`.
+    #[n(1)]
+    pub transfer_amount: IcrcValue,
+    #[n(2)]
+    pub ledger_fee: IcrcValue,
+    #[n(3)]
+    pub created_at_time: u64,
+    /// The collected fees debited by the transfer, including the ledger transfer fee.
+    #[n(4)]
+    pub amount: IcrcValue,
+}
+
+impl PendingFeeWithdrawal {
+    pub fn transfer_arg(&self) -> TransferArg {
+        TransferArg {
+            from_subaccount: Some(FEES_SUBACCOUNT),
+            to: self.to,
+            fee: Some(self.ledger_fee.into()),
+            created_at_time: Some(self.created_at_time),
+            memo: None,
+            amount: self.transfer_amount.into(),
+        }
+    }
+}
+
+/// Transfers `amount` of the fees held in [`fee_holding_account`] on the ledger `ledger_id`
+/// to the fee collector, or all of them if `amount` is `None`. A previous withdrawal whose
+/// outcome is not known is retried instead.
+///
+/// The balance of the holding account is queried first and the collected fees according to
+/// the events are reconciled with it: fees found beyond them are accounted for, and fees
+/// missing from it, e.g. withdrawn by a transfer whose outcome could not be learned, are
+/// removed from them.
+pub async fn withdraw_collected_fees(
+    ledger_id: Principal,
+    amount: Option<Nat>,
+) -> Result<Nat, WithdrawCollectedFeesError> {
+    let _guard = TimerGuard::new(TaskType::WithdrawCollectedFees)
+        .map_err(|_| WithdrawCollectedFeesError::AlreadyProcessing)?;
+    let fee_collector =
+        read_state(|s| s.fee_collector).ok_or(WithdrawCollectedFeesError::NoFeeCollector)?;
+    let client = ICRC1Client {
+        runtime: IcrcBoundedRuntime,
+        ledger_canister_id: ledger_id,
+    };
+
+    if let Some(pending) = read_state(|s| s.pending_fee_withdrawals.get(&ledger_id).cloned()) {
+        log!(
+            INFO,
+            "[withdraw_collected_fees]: retrying the withdrawal of {} fees of ledger {ledger_id} with an unknown outcome",
+            pending.amount
+        );
+        return transfer_collected_fees(&client, pending).await;
+    }
+
+    let unavailable = |(code, message): (i32, String)| {
+        WithdrawCollectedFeesError::TemporarilyUnavailable(format!(
+            "failed to call ledger {ledger_id} with error_code: {code} and message: {message}"
+        ))
+    };
+    let ledger_fee = client.fee().await.map_err(unavailable)?;
+    let ledger_fee = IcrcValue::try_from(ledger_fee).unwrap_or(IcrcValue::MAX);
+    let balance = client
+        .balance_of(fee_holding_account())
+        .await
+        .map_err(unavailable)?;
+    let balance = IcrcValue::try_from(balance).unwrap_or(IcrcValue::MAX);
+
+    let collected = mutate_state(|s| {
+        // a fee locked by a withdrawal in flight may be part of the balance without being
+        // recorded yet, see `LedgerClient::lock`
+        if s.pending_withdrawal_principals.is_empty() {
+            let collected = s.collected_fees(&ledger_id);
+            if let Some(unaccounted) = balance
+                .checked_sub(collected)
+                .filter(|unaccounted| *unaccounted > IcrcValue::ZERO)
+            {
+                log!(
+                    INFO,
+                    "[withdraw_collected_fees]: found {unaccounted} unaccounted fees of ledger {ledger_id}"
+                );
+                process_event(
+                    s,
+                    EventType::UnaccountedFeesFound {
+                        ledger_id,
+                        amount: unaccounted,
+                    },
+                );
+            }
+            if let Some(missing) = collected
+                .checked_sub(balance)
+                .filter(|missing| *missing > IcrcValue::ZERO)
+            {
+                log!(
+                    INFO,
+                    "[withdraw_collected_fees]: {missing} collected fees of ledger {ledger_id} are missing from the holding account"
+                );
+                process_event(
+                    s,
+                    EventType::MissingFeesFound {
+                        ledger_id,
+                        amount: missing,
+                    },
+                );
+            }
+        }
+        s.collected_fees(&ledger_id)
+    });
+    let amount = match amount {
+        Some(amount) => IcrcValue::try_from(amount).map_err(|_| {
+            WithdrawCollectedFeesError::AmountExceedsCollectedFees {
+                collected: collected.into(),
+            }
+        })?,
+        None => collected,
+    };
+    let transfer_amount = validate_withdrawal(collected, amount, balance, ledger_fee)?;
+
+    let pending = PendingFeeWithdrawal {
+        to: fee_collector,
+        transfer_amount,
+        ledger_fee,
+        created_at_time: ic_cdk::api::time(),
+        amount,
+    };
+    mutate_state(|s| {
+        process_event(
+            s,
+            EventType::CollectedFeesWithdrawalStarted {
+                ledger_id,
+                withdrawal: pending.clone(),
+            },
+        )
+    });
+    transfer_collected_fees(&client, pending).await
+}
+
+async fn transfer_collected_fees(
+    client: &ICRC1Client<IcrcBoundedRuntime>,
+    pending: PendingFeeWithdrawal,
+) -> Result<Nat, WithdrawCollectedFeesError> {
+    let ledger_id = client.ledger_canister_id;
+    let block_index = match client.transfer(pending.transfer_arg()).await {
+        Ok(Ok(block_index))
+        | Ok(Err(TransferError::Duplicate {
+            duplicate_of: block_index,
+        })) => block_index,
+        // a retry rejected as too old may have been executed, the next withdrawal then finds
+        // its amount missing from the holding account
+        Ok(Err(e)) => {
+            mutate_state(|s| {
+                process_event(
+                    s,
+                    EventType::CollectedFeesWithdrawalFailed {
+                        ledger_id,
+                        reason: format!("{e:?}"),
+                    },
+                )
+            });
+            return Err(WithdrawCollectedFeesError::TemporarilyUnavailable(format!(
+                "ledger {ledger_id} rejected the transfer: {e:?}"
+            )));
+        }
+        // the transfer may have been executed, it stays pending until its outcome is known
+        Err((code, message)) => {
+            return Err(WithdrawCollectedFeesError::TemporarilyUnavailable(
+                format!(
+                    "failed to call ledger {ledger_id} with error_code: {code} and message: {message}, the transfer is retried by the next withdrawal"
+                ),
+            ));
+        }
+    };
+    log!(
+        INFO,
+        "[withdraw_collected_fees]: withdrew {} fees of ledger {ledger_id} to {} at block {block_index}",
+        pending.amount,
+        pending.to
+    );
+    mutate_state(|s| {
+        process_event(
+            s,
+            EventType::CollectedFeesWithdrawn {
+                ledger_id,
+                to: pending.to,
+                amount: pending.amount,
+                block_index: block_index
+                    .0
+                    .to_u64()
+                    .expect("BUG: block index does not fit into u64"),
+            },
+        )
+    });
+    Ok(block_index)
+}
+
+/// Returns the amount to transfer so that withdrawing `amount` of the `collected` fees,
+/// including the `ledger_fee`, debits exactly `amount` from the holding account.
+fn validate_withdrawal(
+    collected: IcrcValue,
+    amount: IcrcValue,
+    balance: IcrcValue,
+    ledger_fee: IcrcValue,
+) -> Result<IcrcValue, WithdrawCollectedFeesError> {
+    if amount > collected {
+        return Err(WithdrawCollectedFeesError::AmountExceedsCollectedFees {
+            collected: collected.into(),
+        });
+    }
+    if balance < collected {
+        return Err(WithdrawCollectedFeesError::BalanceBelowCollectedFees {
+            balance: balance.into(),
+            collected: collected.into(),
+        });
+    }
+    match amount.checked_sub(ledger_fee) {
+        Some(transfer_amount) if transfer_amount > IcrcValue::ZERO => Ok(transfer_amount),
+        _ => Err(WithdrawCollectedFeesError::AmountTooLow {
+            ledger_fee: ledger_fee.into(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEDGER_FEE: IcrcValue = IcrcValue::new(10_000);
+
+    #[test]
+    fn should_transfer_amount_minus_ledger_fee() {
+        let collected = IcrcValue::new(1_000_000);
+
+        assert_eq!(
+            validate_withdrawal(collected, collected, collected, LEDGER_FEE),
+            Ok(IcrcValue::new(990_000))
+        );
+        assert_eq!(
+            validate_withdrawal(
+                collected,
+                IcrcValue::new(500_000),
+                IcrcValue::new(2_000_000),
+                LEDGER_FEE
+            ),
+            Ok(IcrcValue::new(490_000))
+        );
+    }
+
+    #[test]
+    fn should_not_withdraw_more_than_collected() {
+        assert_eq!(
+            validate_withdrawal(
+                IcrcValue::new(1_000_000),
+                IcrcValue::new(1_000_001),
+                IcrcValue::new(2_000_000),
+                LEDGER_FEE
+            ),
+            Err(WithdrawCollectedFeesError::AmountExceedsCollectedFees {
+                collected: Nat::from(1_000_000_u64)
+            })
+        );
+    }
+
+    #[test]
+    fn should_not_withdraw_when_balance_is_below_collected_fees() {
+        assert_eq!(
+            validate_withdrawal(
+                IcrcValue::new(1_000_000),
+                IcrcValue::new(100_000),
+                IcrcValue::new(999_999),
+                LEDGER_FEE
+            ),
+            Err(WithdrawCollectedFeesError::BalanceBelowCollectedFees {
+                balance: Nat::from(999_999_u64),
+                collected: Nat::from(1_000_000_u64)
+            })
+        );
+    }
+
+    #[test]
+    fn should_not_withdraw_amount_not_covering_ledger_fee() {
+        for amount in [IcrcValue::ZERO, LEDGER_FEE] {
+            assert_eq!(
+                validate_withdrawal(
+                    IcrcValue::new(1_000_000),
+                    amount,
+                    IcrcValue::new(1_000_000),
+                    LEDGER_FEE
+                ),
+                Err(WithdrawCollectedFeesError::AmountTooLow {
+                    ledger_fee: Nat::from(10_000_u64)
+                })
+            );
+        }
+    }
+}
@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod tests;
 
+use crate::contract_logs::EventSource;
+use crate::deposit::removed_log_event;
+use crate::state::audit::process_event;
 use crate::state::{mutate_state, State, TaskType};
 
 use candid::Principal;
@@ -110,3 +113,44 @@ impl Drop for TimerGuard {
         });
     }
 }
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MintGuardError {
+    /// The event is not (or no longer) waiting to be minted.
+    NotPending,
+    AlreadyInFlight,
+}
+
+/// Marks a deposit event as being minted, so that a mint for the same event source
+/// cannot be dispatched again until this one resolved.
+#[must_use]
+#[derive(Debug, PartialEq, Eq)]
+pub struct MintGuard {
+    source: EventSource,
+}
+
+impl MintGuard {
+    pub fn new(source: EventSource) -> Result<Self, MintGuardError> {
+        mutate_state(|s| {
+            if !s.events_to_mint.contains_key(&source) {
+                return Err(MintGuardError::NotPending);
+            }
+            if !s.mints_in_flight.insert(source) {
+                return Err(MintGuardError::AlreadyInFlight);
+            }
+            Ok(Self { source })
+        })
+    }
+}
+
+impl Drop for MintGuard {
+    fn drop(&mut self) {
+        mutate_state(|s| {
+            if s.release_mint_in_flight(&self.source) {
+                if let Some(payload) = removed_log_event(s, self.source) {
+                    process_event(s, payload);
+                }
+            }
+        });
+    }
+}
@@ -81,6 +81,7 @@ mod retrieve_eth_guard {
                         created_at: None,
                         l1_fee: None,
                         withdrawal_fee: None,
+                        is_contract_destination: None,
                     }))
             })
         }
@@ -133,6 +134,149 @@ mod timer_guard {
     }
 }
 
+mod mint_guard {
+    use crate::contract_logs::types::ReceivedNativeEvent;
+    use crate::deposit::removed_log_event;
+    use crate::guard::tests::init_state;
+    use crate::guard::{MintGuard, MintGuardError};
+    use crate::numeric::{LedgerMintIndex, LogIndex};
+    use crate::state::audit::apply_state_transition;
+    use crate::state::event::EventType;
+    use crate::state::tests::received_deposit_event;
+    use crate::state::{mutate_state, read_state};
+
+    fn accept_deposit(log_index: u8) -> ReceivedNativeEvent {
+        let event = ReceivedNativeEvent {
+            log_index: LogIndex::from(log_index),
+            ..received_deposit_event()
+        };
+        mutate_state(|s| {
+            apply_state_transition(s, &EventType::AcceptedDeposit(event.clone()));
+        });
+        event
+    }
+
+    #[test]
+    fn should_refuse_event_that_is_not_pending() {
+        init_state();
+        let event = received_deposit_event();
+
+        assert_eq!(
+            MintGuard::new(event.source()),
+            Err(MintGuardError::NotPending)
+        );
+    }
+
+    #[test]
+    fn should_prevent_minting_same_event_while_in_flight() {
+        init_state();
+        let first = accept_deposit(1);
+        let second = accept_deposit(2);
+
+        let _guard = MintGuard::new(first.source()).expect("can retrieve mint guard");
+
+        assert_eq!(
+            MintGuard::new(first.source()),
+            Err(MintGuardError::AlreadyInFlight)
+        );
+        assert!(MintGuard::new(second.source()).is_ok());
+        assert_eq!(
+            read_state(|s| s.mints_in_flight.iter().copied().collect::<Vec<_>>()),
+            vec![first.source()]
+        );
+    }
+
+    #[test]
+    fn should_allow_retry_when_guard_dropped_without_mint() {
+        init_state();
+        let event = accept_deposit(1);
+
+        let guard = MintGuard::new(event.source()).expect("can retrieve mint guard");
+        drop(guard);
+
+        assert!(read_state(|s| s.mints_in_flight.is_empty()));
+        assert!(MintGuard::new(event.source()).is_ok());
+    }
+
+    #[test]
+    fn should_not_mint_again_once_minted() {
+        init_state();
+        let event = accept_deposit(1);
+
+        let guard = MintGuard::new(event.source()).expect("can retrieve mint guard");
+        mutate_state(|s| {
+            apply_state_transition(
+                s,
+                &EventType::MintedNative {
+                    event_source: event.source(),
+                    mint_block_index: LedgerMintIndex::new(1),
+                },
+            )
+        });
+        drop(guard);
+
+        assert!(read_state(|s| s.mints_in_flight.is_empty()));
+        assert_eq!(
+            MintGuard::new(event.source()),
+            Err(MintGuardError::NotPending)
+        );
+    }
+
+    #[test]
+    fn should_record_removal_of_minted_deposit_once_mint_resolved() {
+        init_state();
+        let event = accept_deposit(1);
+
+        let guard = MintGuard::new(event.source()).expect("can retrieve mint guard");
+        mutate_state(|s| s.reorged_mints_in_flight.insert(event.source()));
+        mutate_state(|s| {
+            apply_state_transition(
+                s,
+                &EventType::MintedNative {
+                    event_source: event.source(),
+                    mint_block_index: LedgerMintIndex::new(1),
+                },
+            )
+        });
+        // dropping the guard records the removal, which requires the canister time
+        std::mem::forget(guard);
+
+        assert!(mutate_state(|s| s.release_mint_in_flight(&event.source())));
+        assert_eq!(
+            read_state(|s| removed_log_event(s, event.source())),
+            Some(EventType::RemovedLog {
+                event_source: event.source()
+            })
+        );
+        assert!(!mutate_state(|s| s.release_mint_in_flight(&event.source())));
+    }
+
+    #[test]
+    fn should_revert_deposit_removed_while_mint_failed() {
+        init_state();
+        let event = accept_deposit(1);
+
+        let guard = MintGuard::new(event.source()).expect("can retrieve mint guard");
+        mutate_state(|s| s.reorged_mints_in_flight.insert(event.source()));
+        std::mem::forget(guard);
+
+        assert!(mutate_state(|s| s.release_mint_in_flight(&event.source())));
+        let payload = read_state(|s| removed_log_event(s, event.source()))
+            .expect("removal of a pending deposit should be recorded");
+        assert_eq!(
+            payload,
+            EventType::DepositReorged {
+                event_source: event.source()
+            }
+        );
+        mutate_state(|s| apply_state_transition(s, &payload));
+        assert_eq!(
+            MintGuard::new(event.source()),
+            Err(MintGuardError::NotPending)
+        );
+    }
+}
+
 fn init_state() {
     use crate::lifecycle::InitArg;
     use crate::state::State;
@@ -158,6 +302,7 @@ fn init_state() {
                     .expect("BUG: invalid principal"),
                 deposit_native_fee: Nat::from(0_u64),
                 withdrawal_native_fee: wei_from_milli_ether(1).into(),
+                fee_collector: None,
             })
             .expect("init args should be valid"),
         );
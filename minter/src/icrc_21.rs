@@ -67,10 +67,19 @@ pub enum ConsentMessage {
     },
 }
 
+/// Metadata of a consent message: [`ConsentMessageMetadata`] extended with the languages
+/// the minter can display consent messages in.
+#[derive(CandidType, Deserialize, Clone)]
+pub struct ConsentMessageResponseMetadata {
+    pub language: String,
+    pub utc_offset_minutes: Option<i16>,
+    pub supported_languages: Vec<String>,
+}
+
 #[derive(CandidType, Deserialize, Clone)]
 pub struct ConsentInfo {
     pub consent_message: ConsentMessage,
-    pub metadata: ConsentMessageMetadata,
+    pub metadata: ConsentMessageResponseMetadata,
 }
 
 #[derive(CandidType, Deserialize, Clone)]
@@ -90,3 +99,244 @@ pub enum Error {
 }
 
 pub type ConsentMessageResponse = Result<ConsentInfo, Error>;
+
+/// Formats `amount` in the smallest unit of a token with `decimals` decimals, e.g.
+/// `1_500_000` with 6 decimals as `1.5`.
+pub fn format_token_amount(amount: &Nat, decimals: u8) -> String {
+    let digits = amount.0.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits;
+    }
+    let digits = format!("{digits:0>width$}", width = decimals + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals);
+    match fraction.trim_end_matches('0') {
+        "" => integer.to_string(),
+        fraction => format!("{integer}.{fraction}"),
+    }
+}
+
+/// Languages consent messages can be displayed in, English first.
+pub const SUPPORTED_LANGUAGES: [&str; 3] = ["en", "es", "zh"];
+
+/// Returns the supported language matching the BCP 47 `language` tag, e.g. `es` for
+/// `es-MX`, or `None` if consent messages cannot be displayed in that language.
+pub fn supported_language(language: &str) -> Option<&'static str> {
+    let primary = language.split(['-', '_']).next()?.to_ascii_lowercase();
+    SUPPORTED_LANGUAGES
+        .into_iter()
+        .find(|supported| *supported == primary)
+}
+
+/// Translates the texts of consent messages.
+pub trait Localizer {
+    /// Returns the text identified by `key` in `lang`, in English if there is no
+    /// translation for `lang`, or `key` itself if the text is unknown.
+    fn translate(key: &str, lang: &str) -> String;
+}
+
+/// [`Localizer`] backed by the static translation tables below.
+pub struct ConsentMessages;
+
+impl Localizer for ConsentMessages {
+    fn translate(key: &str, lang: &str) -> String {
+        lookup(translations(lang), key)
+            .or_else(|| lookup(EN, key))
+            .unwrap_or(key)
+            .to_string()
+    }
+}
+
+impl ConsentMessages {
+    /// Translates the text identified by `key` and replaces its `{name}` placeholders
+    /// with the given `args`.
+    pub fn format(key: &str, lang: &str, args: &[(&str, &str)]) -> String {
+        args.iter()
+            .fold(Self::translate(key, lang), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), value)
+            })
+    }
+}
+
+fn translations(lang: &str) -> &'static [(&'static str, &'static str)] {
+    match lang {
+        "es" => ES,
+        "zh" => ZH,
+        _ => EN,
+    }
+}
+
+fn lookup(table: &'static [(&'static str, &'static str)], key: &str) -> Option<&'static str> {
+    table.iter().find(|(k, _)| *k == key).map(|(_, text)| *text)
+}
+
+const EN: &[(&str, &str)] = &[
+    ("field.amount", "Amount"),
+    ("field.confirmations", "Confirmations"),
+    ("field.erc20_ledger_id", "ERC20 Ledger ID"),
+    ("field.helper_contract_address", "Helper Contract Address"),
+    ("field.icrc_ledger_id", "ICRC Ledger ID"),
+    ("field.recipient", "Recipient"),
+    ("check_new_deposits.intent", "Check New Deposits"),
+    (
+        "check_new_deposits.text",
+        "Check for new deposits, minted after waiting for {confirmations}.",
+    ),
+    ("check_new_deposits.blocks", "{blocks} blocks"),
+    (
+        "check_new_deposits.safe_block",
+        "the safe or finalized block",
+    ),
+    ("withdraw_erc20.intent", "Withdraw ERC20"),
+    (
+        "withdraw_erc20.text",
+        "Withdraw {amount} from ERC20 ledger {ledger} to {recipient}.",
+    ),
+    ("withdraw_native_token.intent", "Withdraw Native Token"),
+    (
+        "withdraw_native_token.text",
+        "Withdraw {amount} to {recipient}.",
+    ),
+    ("wrap_icrc.intent", "Wrap ICRC"),
+    (
+        "wrap_icrc.text",
+        "Wrap {amount} from ICRC ledger {ledger} to {recipient}.",
+    ),
+];
+
+const ES: &[(&str, &str)] = &[
+    ("field.amount", "Cantidad"),
+    ("field.confirmations", "Confirmaciones"),
+    ("field.erc20_ledger_id", "ID del ledger ERC20"),
+    (
+        "field.helper_contract_address",
+        "Dirección del contrato auxiliar",
+    ),
+    ("field.icrc_ledger_id", "ID del ledger ICRC"),
+    ("field.recipient", "Destinatario"),
+    ("check_new_deposits.intent", "Comprobar nuevos depósitos"),
+    (
+        "check_new_deposits.text",
+        "Comprobar nuevos depósitos, acuñados tras esperar {confirmations}.",
+    ),
+    ("check_new_deposits.blocks", "{blocks} bloques"),
+    (
+        "check_new_deposits.safe_block",
+        "el bloque seguro o finalizado",
+    ),
+    ("withdraw_erc20.intent", "Retirar ERC20"),
+    (
+        "withdraw_erc20.text",
+        "Retirar {amount} del ledger ERC20 {ledger} a {recipient}.",
+    ),
+    ("withdraw_native_token.intent", "Retirar token nativo"),
+    (
+        "withdraw_native_token.text",
+        "Retirar {amount} a {recipient}.",
+    ),
+    ("wrap_icrc.intent", "Envolver ICRC"),
+    (
+        "wrap_icrc.text",
+        "Envolver {amount} del ledger ICRC {ledger} a {recipient}.",
+    ),
+];
+
+const ZH: &[(&str, &str)] = &[
+    ("field.amount", "金额"),
+    ("field.confirmations", "确认数"),
+    ("field.erc20_ledger_id", "ERC20 账本 ID"),
+    ("field.helper_contract_address", "辅助合约地址"),
+    ("field.icrc_ledger_id", "ICRC 账本 ID"),
+    ("field.recipient", "接收地址"),
+    ("check_new_deposits.intent", "检查新存款"),
+    (
+        "check_new_deposits.text",
+        "检查新存款，等待 {confirmations} 后铸造。",
+    ),
+    ("check_new_deposits.blocks", "{blocks} 个区块"),
+    ("check_new_deposits.safe_block", "安全或最终确定的区块"),
+    ("withdraw_erc20.intent", "提取 ERC20"),
+    (
+        "withdraw_erc20.text",
+        "从 ERC20 账本 {ledger} 提取 {amount} 到 {recipient}。",
+    ),
+    ("withdraw_native_token.intent", "提取原生代币"),
+    (
+        "withdraw_native_token.text",
+        "将 {amount} 提取到 {recipient}。",
+    ),
+    ("wrap_icrc.intent", "封装 ICRC"),
+    (
+        "wrap_icrc.text",
+        "从 ICRC 账本 {ledger} 封装 {amount} 到 {recipient}。",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{format_token_amount, supported_language, ConsentMessages, Localizer, EN, ES, ZH};
+    use candid::Nat;
+
+    #[test]
+    fn should_translate_withdrawal_consent_message_to_spanish() {
+        let args = [
+            ("amount", "1.5 ETH"),
+            ("recipient", "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34"),
+        ];
+        let intent = ConsentMessages::translate("withdraw_native_token.intent", "es");
+        let text = ConsentMessages::format("withdraw_native_token.text", "es", &args);
+
+        for message in [&intent, &text] {
+            assert!(message.contains("Retirar"), "{message}");
+            assert!(!message.contains("Withdraw"), "{message}");
+        }
+        assert_eq!(
+            text,
+            "Retirar 1.5 ETH a 0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34."
+        );
+    }
+
+    #[test]
+    fn should_fall_back_to_english() {
+        assert_eq!(
+            ConsentMessages::translate("withdraw_native_token.intent", "fr"),
+            "Withdraw Native Token"
+        );
+        assert_eq!(
+            ConsentMessages::translate("unknown.key", "es"),
+            "unknown.key"
+        );
+    }
+
+    #[test]
+    fn should_translate_every_english_text() {
+        for table in [ES, ZH] {
+            for (key, _) in EN {
+                assert!(table.iter().any(|(k, _)| k == key), "missing {key}");
+            }
+            assert_eq!(table.len(), EN.len());
+        }
+    }
+
+    #[test]
+    fn should_match_primary_language_subtag() {
+        assert_eq!(supported_language("en"), Some("en"));
+        assert_eq!(supported_language("es-MX"), Some("es"));
+        assert_eq!(supported_language("zh-Hans-CN"), Some("zh"));
+        assert_eq!(supported_language("ZH"), Some("zh"));
+        assert_eq!(supported_language("fr"), None);
+        assert_eq!(supported_language(""), None);
+    }
+
+    #[test]
+    fn should_format_token_amount_with_decimals() {
+        assert_eq!(
+            format_token_amount(&Nat::from(1_500_000_000_000_000_000_u64), 18),
+            "1.5"
+        );
+        assert_eq!(format_token_amount(&Nat::from(1_500_000_u64), 8), "0.015");
+        assert_eq!(format_token_amount(&Nat::from(200_000_000_u64), 8), "2");
+        assert_eq!(format_token_amount(&Nat::from(0_u8), 18), "0");
+        assert_eq!(format_token_amount(&Nat::from(42_u8), 0), "42");
+    }
+}
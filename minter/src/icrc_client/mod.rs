@@ -1,11 +1,14 @@
 use crate::{
     erc20::ERC20Token,
+    fee_collector::fee_holding_account,
     icrc_client::runtime::IcrcBoundedRuntime,
-    logs::DEBUG,
+    logs::{DEBUG, INFO},
     memo::BurnMemo,
-    numeric::{LedgerBurnIndex, LedgerLockIndex},
-    state::State,
-    FEES_SUBACCOUNT,
+    numeric::{IcrcValue, LedgerBurnIndex, LedgerLockIndex},
+    state::{
+        audit::process_event, event::EventType, mutate_state, read_state, IcrcLedgerMetadata,
+        NativeLedgerMetadata, State, NATIVE_TOKEN_DECIMALS,
+    },
 };
 use candid::{Nat, Principal};
 use ic_canister_log::log;
@@ -52,6 +55,18 @@ pub enum LedgerBurnError {
         failed_burn_amount: Nat,
         ledger: ERC20Ledger,
     },
+    /// The same burn was already executed, only possible when `created_at_time` is set.
+    Duplicate {
+        duplicate_of: LedgerBurnIndex,
+        ledger: ERC20Ledger,
+    },
+    /// `created_at_time` is older than the deduplication window of the ledger.
+    TooOld { ledger: ERC20Ledger },
+    /// `created_at_time` is ahead of the ledger time.
+    CreatedInFuture {
+        ledger_time: u64,
+        ledger: ERC20Ledger,
+    },
 }
 
 impl LedgerClient {
@@ -82,21 +97,48 @@ impl LedgerClient {
         memo: BurnMemo,
         fee: Option<A>,
     ) -> Result<LedgerBurnIndex, LedgerBurnError> {
-        let amount = amount.into();
-        match self
-            .client
-            .transfer_from(TransferFromArgs {
-                spender_subaccount: None,
-                from,
-                to: ic_cdk::api::canister_self().into(),
-                amount: amount.clone(),
-                fee: fee.map(|fee| fee.into()),
-                memo: Some(Memo::from(memo)),
-                created_at_time: None, // We don't set this field to disable transaction deduplication
-                                       // which is unnecessary in canister-to-canister calls.
-            })
+        // We don't set created_at_time to disable transaction deduplication
+        // which is unnecessary in canister-to-canister calls.
+        self.burn_from_with_created_at_time(from, amount, memo, fee, None)
             .await
-        {
+    }
+
+    /// Same as [`Self::burn_from`], but lets the ledger deduplicate the burn when
+    /// `created_at_time` is set, so that a retried call does not burn twice.
+    pub async fn burn_from_with_created_at_time<A: Into<Nat>>(
+        &self,
+        from: Account,
+        amount: A,
+        memo: BurnMemo,
+        fee: Option<A>,
+        created_at_time: Option<u64>,
+    ) -> Result<LedgerBurnIndex, LedgerBurnError> {
+        let amount = amount.into();
+        let args = TransferFromArgs {
+            spender_subaccount: None,
+            from,
+            to: ic_cdk::api::canister_self().into(),
+            amount: amount.clone(),
+            fee: fee.map(|fee| fee.into()),
+            memo: Some(Memo::from(memo)),
+            created_at_time,
+        };
+        let payload_bytes = candid::encode_one(&args)
+            .expect("BUG: failed to encode transfer_from args")
+            .len();
+        let estimated_cost =
+            IcrcBoundedRuntime::estimated_call_cost("icrc2_transfer_from", payload_bytes);
+        log!(
+            DEBUG,
+            "[burn]: calling icrc2_transfer_from on the {} ledger, estimated cost: {estimated_cost} cycles",
+            self.token_symbol
+        );
+        mutate_state(|s| {
+            s.total_ledger_call_cycles_spent = s
+                .total_ledger_call_cycles_spent
+                .saturating_add(estimated_cost)
+        });
+        match self.client.transfer_from(args).await {
             Ok(Ok(block_index)) => Ok(LedgerBurnIndex::new(
                 block_index.0.to_u64().expect("nat does not fit into u64"),
             )),
@@ -131,12 +173,33 @@ impl LedgerClient {
                             ledger: self.native_ledger(),
                         }
                     }
-                    TransferFromError::TooOld => panic!("BUG: transfer too old"),
+                    TransferFromError::TooOld => {
+                        if created_at_time.is_none() {
+                            panic!("BUG: transfer too old")
+                        }
+                        LedgerBurnError::TooOld {
+                            ledger: self.native_ledger(),
+                        }
+                    }
                     TransferFromError::CreatedInFuture { ledger_time } => {
-                        panic!("BUG: created in future, ledger time: {ledger_time}")
+                        if created_at_time.is_none() {
+                            panic!("BUG: created in future, ledger time: {ledger_time}")
+                        }
+                        LedgerBurnError::CreatedInFuture {
+                            ledger_time,
+                            ledger: self.native_ledger(),
+                        }
                     }
                     TransferFromError::Duplicate { duplicate_of } => {
-                        panic!("BUG: duplicate transfer of: {duplicate_of}")
+                        if created_at_time.is_none() {
+                            panic!("BUG: duplicate transfer of: {duplicate_of}")
+                        }
+                        LedgerBurnError::Duplicate {
+                            duplicate_of: LedgerBurnIndex::new(
+                                duplicate_of.0.to_u64().expect("nat does not fit into u64"),
+                            ),
+                            ledger: self.native_ledger(),
+                        }
                     }
                     TransferFromError::TemporarilyUnavailable => {
                         LedgerBurnError::TemporarilyUnavailable {
@@ -174,6 +237,8 @@ impl LedgerClient {
         }
     }
 
+    /// Transfers the fee `amount` from `from` to [`fee_holding_account`], where it is held
+    /// until it is withdrawn to the fee collector, and records it as collected.
     pub async fn lock<A: Into<Nat>>(
         &self,
         from: Account,
@@ -185,10 +250,7 @@ impl LedgerClient {
             .transfer_from(TransferFromArgs {
                 spender_subaccount: None,
                 from,
-                to: Account {
-                    owner: ic_cdk::api::canister_self(),
-                    subaccount: Some(FEES_SUBACCOUNT),
-                },
+                to: fee_holding_account(),
                 amount: amount.clone(),
                 fee: None,
                 memo: None,
@@ -196,9 +258,23 @@ impl LedgerClient {
             })
             .await
         {
-            Ok(Ok(block_index)) => Ok(LedgerLockIndex::new(
-                block_index.0.to_u64().expect("nat does not fit into u64"),
-            )),
+            Ok(Ok(block_index)) => {
+                let block_index = LedgerLockIndex::new(
+                    block_index.0.to_u64().expect("nat does not fit into u64"),
+                );
+                mutate_state(|s| {
+                    process_event(
+                        s,
+                        EventType::FeeCollected {
+                            ledger_id: self.client.ledger_canister_id,
+                            amount: IcrcValue::try_from(amount)
+                                .expect("BUG: locked amount does not fit into u256"),
+                            block_index,
+                        },
+                    )
+                });
+                Ok(block_index)
+            }
             Ok(Err(transfer_from_error)) => {
                 log!(
                     DEBUG,
@@ -289,4 +365,85 @@ impl LedgerClient {
     pub async fn transfer_fee(&self) -> Result<Nat, String> {
         self.client.fee().await.map_err(|err| err.1)
     }
+
+    pub async fn symbol(&self) -> Result<String, String> {
+        self.client.symbol().await.map_err(|err| err.1)
+    }
+
+    pub async fn decimals(&self) -> Result<u8, String> {
+        self.client.decimals().await.map_err(|err| err.1)
+    }
+}
+
+/// Queries the symbol and decimals of the native ledger and caches them in the state.
+///
+/// An event records whether they match the configured native symbol and
+/// [`NATIVE_TOKEN_DECIMALS`], unless the same metadata was already recorded.
+pub async fn refresh_native_ledger_metadata() {
+    let client = read_state(LedgerClient::native_ledger_from_state);
+    let (symbol, decimals) = match (client.symbol().await, client.decimals().await) {
+        (Ok(symbol), Ok(decimals)) => (symbol, decimals),
+        (Err(e), _) | (_, Err(e)) => {
+            log!(
+                INFO,
+                "[refresh_native_ledger_metadata]: failed to query the native ledger: {e}"
+            );
+            return;
+        }
+    };
+    let metadata = NativeLedgerMetadata { symbol, decimals };
+    let (is_mismatch, is_known) = read_state(|s| {
+        (
+            s.is_native_ledger_metadata_mismatch(&metadata),
+            s.native_ledger_metadata.as_ref() == Some(&metadata),
+        )
+    });
+    if is_mismatch {
+        log!(
+            INFO,
+            "[refresh_native_ledger_metadata]: WARNING: native ledger reports symbol {} with {} decimals, configured symbol is {} with {NATIVE_TOKEN_DECIMALS} decimals",
+            metadata.symbol,
+            metadata.decimals,
+            read_state(|s| s.native_symbol.clone()),
+        );
+    }
+    if is_known {
+        return;
+    }
+    let event = if is_mismatch {
+        EventType::NativeLedgerMetadataMismatch(metadata)
+    } else {
+        EventType::NativeLedgerMetadataMatched(metadata)
+    };
+    mutate_state(|s| process_event(s, event));
+}
+
+/// Queries the symbol, decimals and transfer fee of the base ledgers of the wrapped ICRC tokens
+/// whose cached metadata is missing or expired, and caches them in the state.
+pub async fn refresh_wrapped_icrc_metadata() {
+    let ledger_ids = read_state(|s| s.wrapped_icrc_ledgers_to_refresh(ic_cdk::api::time()));
+    for ledger_id in ledger_ids {
+        let client = LedgerClient::icrc_ledger(ledger_id);
+        let (symbol, decimals, transfer_fee) = match (
+            client.symbol().await,
+            client.decimals().await,
+            client.transfer_fee().await,
+        ) {
+            (Ok(symbol), Ok(decimals), Ok(transfer_fee)) => (symbol, decimals, transfer_fee),
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+                log!(
+                    INFO,
+                    "[refresh_wrapped_icrc_metadata]: failed to query the ledger {ledger_id}: {e}"
+                );
+                continue;
+            }
+        };
+        let metadata = IcrcLedgerMetadata {
+            symbol,
+            decimals,
+            transfer_fee: IcrcValue::try_from(transfer_fee).unwrap_or(IcrcValue::MAX),
+            refreshed_at: ic_cdk::api::time(),
+        };
+        mutate_state(|s| s.record_wrapped_icrc_metadata(ledger_id, metadata));
+    }
 }
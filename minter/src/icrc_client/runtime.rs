@@ -6,9 +6,25 @@ use candid::{
 use ic_cdk::call::CallErrorExt;
 pub use icrc_ledger_client::{ICRC1Client, Runtime};
 
+/// Cycles charged for sending an inter-canister call on a 13-node subnet.
+pub const INTER_CANISTER_CALL_BASE_COST: u128 = 260_000;
+/// Cycles charged per byte of an inter-canister call on a 13-node subnet.
+pub const INTER_CANISTER_CALL_COST_PER_BYTE: u128 = 1_000;
+
 /// ICRC1Client runtime that uses the ic-cdk.
 pub struct IcrcBoundedRuntime;
 
+impl IcrcBoundedRuntime {
+    /// Estimates the cycles charged to the caller for sending `method` with a
+    /// candid-encoded argument of `payload_bytes`, following the IC cost table.
+    /// The method name is transmitted along with the payload and is charged per byte as well.
+    pub fn estimated_call_cost(method: &str, payload_bytes: usize) -> u128 {
+        let transmitted_bytes = (method.len() as u128).saturating_add(payload_bytes as u128);
+        INTER_CANISTER_CALL_BASE_COST
+            .saturating_add(INTER_CANISTER_CALL_COST_PER_BYTE.saturating_mul(transmitted_bytes))
+    }
+}
+
 #[async_trait]
 impl Runtime for IcrcBoundedRuntime {
     async fn call<In, Out>(
@@ -49,3 +65,33 @@ impl Runtime for IcrcBoundedRuntime {
             .map_err(|err| (0, format!("Candid decoding failed {err}")))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::IcrcBoundedRuntime;
+
+    #[test]
+    fn should_estimate_call_cost_from_cost_table() {
+        assert_eq!(IcrcBoundedRuntime::estimated_call_cost("", 0), 260_000);
+        assert_eq!(
+            IcrcBoundedRuntime::estimated_call_cost("icrc2_transfer_from", 0),
+            260_000 + 19 * 1_000
+        );
+        assert_eq!(
+            IcrcBoundedRuntime::estimated_call_cost("icrc2_transfer_from", 150),
+            260_000 + (19 + 150) * 1_000
+        );
+        assert_eq!(
+            IcrcBoundedRuntime::estimated_call_cost("icrc1_fee", 1_024),
+            260_000 + (9 + 1_024) * 1_000
+        );
+    }
+
+    #[test]
+    fn should_not_overflow_call_cost() {
+        assert_eq!(
+            IcrcBoundedRuntime::estimated_call_cost("icrc1_fee", usize::MAX),
+            260_000 + (9 + usize::MAX as u128) * 1_000
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Per-endpoint instruction counts, to find out which endpoints and timers burn most cycles.
+//!
+//! Only update endpoints and timer-driven tasks are instrumented: queries are not charged
+//! and their state changes, including any measurement, are discarded anyway.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+thread_local! {
+    static ENDPOINT_STATS: RefCell<BTreeMap<&'static str, EndpointStats>> = RefCell::default();
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EndpointStats {
+    pub count: u64,
+    pub total_instructions: u64,
+    pub max_instructions: u64,
+}
+
+impl EndpointStats {
+    fn record(&mut self, instructions: u64) {
+        self.count = self.count.saturating_add(1);
+        self.total_instructions = self.total_instructions.saturating_add(instructions);
+        self.max_instructions = self.max_instructions.max(instructions);
+    }
+}
+
+/// Measures the instructions executed in the current call context until the returned guard
+/// is dropped, and accounts them to `endpoint`.
+///
+/// The call context instruction counter also covers the instructions executed after each
+/// `await`, so that asynchronous endpoints are measured in full.
+pub fn instrument(endpoint: &'static str) -> Instrumentation {
+    Instrumentation {
+        endpoint,
+        start: ic_cdk::api::call_context_instruction_counter(),
+    }
+}
+
+#[must_use]
+#[derive(Debug)]
+pub struct Instrumentation {
+    endpoint: &'static str,
+    start: u64,
+}
+
+impl Drop for Instrumentation {
+    fn drop(&mut self) {
+        let end = ic_cdk::api::call_context_instruction_counter();
+        record(self.endpoint, end.saturating_sub(self.start));
+    }
+}
+
+pub fn record(endpoint: &'static str, instructions: u64) {
+    ENDPOINT_STATS.with(|stats| {
+        stats
+            .borrow_mut()
+            .entry(endpoint)
+            .or_default()
+            .record(instructions)
+    });
+}
+
+pub fn endpoint_stats() -> BTreeMap<&'static str, EndpointStats> {
+    ENDPOINT_STATS.with(|stats| stats.borrow().clone())
+}
+
+pub fn reset() {
+    ENDPOINT_STATS.with(|stats| stats.borrow_mut().clear());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{endpoint_stats, record, reset, EndpointStats};
+    use maplit::btreemap;
+
+    #[test]
+    fn should_accumulate_instructions_per_endpoint() {
+        reset();
+        record("withdraw_native_token", 3_000);
+        record("scrape_logs", 10_000);
+        record("withdraw_native_token", 5_000);
+
+        assert_eq!(
+            endpoint_stats(),
+            btreemap! {
+                "scrape_logs" => EndpointStats {
+                    count: 1,
+                    total_instructions: 10_000,
+                    max_instructions: 10_000,
+                },
+                "withdraw_native_token" => EndpointStats {
+                    count: 2,
+                    total_instructions: 8_000,
+                    max_instructions: 5_000,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn should_reset_all_endpoints() {
+        record("scrape_logs", 10_000);
+
+        reset();
+
+        assert_eq!(endpoint_stats(), btreemap! {});
+    }
+}
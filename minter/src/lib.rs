@@ -8,9 +8,11 @@ pub mod deposit;
 pub mod dex_client;
 pub mod erc20;
 pub mod evm_config;
+pub mod fee_collector;
 pub mod guard;
 pub mod icrc_21;
 pub mod icrc_client;
+pub mod instrumentation;
 pub mod lifecycle;
 pub mod logs;
 pub mod lsm_client;
@@ -39,6 +41,16 @@ pub const PROCESS_TOKENS_RETRIEVE_TRANSACTIONS_INTERVAL: Duration = Duration::fr
 pub const PROCESS_REIMBURSEMENT: Duration = Duration::from_secs(60);
 pub const PROCESS_TOKENS_RETRIEVE_TRANSACTIONS_RETRY_INTERVAL: Duration = Duration::from_secs(5);
 pub const MINT_RETRY_DELAY: Duration = Duration::from_secs(30);
+pub const MANUAL_DEPOSIT_CHECK_INTERVAL: Duration = Duration::from_secs(2 * 60);
+pub const MANUAL_DEPOSIT_CHECK_GLOBAL_INTERVAL: Duration = Duration::from_secs(10);
+pub const LOG_SCRAPING_REQUEST_INTERVAL: Duration = Duration::from_secs(60);
+pub const PROVIDER_DISAGREEMENT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+pub const FEE_CAP_ALERT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+pub const NATIVE_LEDGER_METADATA_REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+pub const WRAPPED_ICRC_METADATA_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Minimum distance to the last scraped block of the events pruned by `compact_event_log`.
+pub const MIN_PRUNE_THRESHOLD_BLOCKS: u64 = 10_000;
 
 pub const APPIC_CONTROLLER_PRINCIPAL: &str =
     "tb3vi-54bcb-4oudm-fmp2s-nntjp-rmhd3-ukvnq-lawfq-vk5vy-mnlc7-pae";
@@ -1,19 +1,22 @@
 use crate::candid_types::CandidBlockTag;
 use crate::erc20::ERC20TokenSymbol;
-use crate::evm_config::EvmNetwork;
+use crate::evm_config::{ConfirmationPolicy, EvmNetwork, GasLimitKind};
 use crate::logs::INFO;
 use crate::numeric::{BlockNumber, TransactionNonce, Wei, WeiPerGas};
 use crate::rpc_declarations::BlockTag;
 use crate::state::audit::{process_event, replay_events, EventType};
 use crate::state::balances::GasTank;
 use crate::state::transactions::WithdrawalTransactions;
-use crate::state::{mutate_state, InvalidStateError, State, STATE};
-use crate::storage::total_event_count;
+use crate::state::{mutate_state, read_state, InvalidStateError, State, STATE};
+use crate::storage::{
+    archived_event_count, event_log_schema_versions, total_event_count, CBOR_SCHEMA_VERSION,
+};
 use candid::types::number::Nat;
 use candid::types::principal::Principal;
 use candid::{CandidType, Deserialize};
 use evm_rpc_client::eth_types::Address;
 use ic_canister_log::log;
+use icrc_ledger_types::icrc1::account::Account;
 use minicbor::{Decode, Encode};
 use serde::Serialize;
 
@@ -49,6 +52,27 @@ pub struct InitArg {
     pub deposit_native_fee: Nat,
     #[cbor(n(14), with = "crate::cbor::nat")]
     pub withdrawal_native_fee: Nat,
+    // account receiving the collected fees, defaults to a subaccount of the minter
+    #[cbor(n(15), with = "crate::cbor::account::option")]
+    pub fee_collector: Option<Account>,
+}
+
+/// Checks the init argument of a new installation.
+///
+/// These checks are not part of `State::try_from` since the init event already recorded
+/// in the event log must still be replayable.
+pub fn validate_init_arg(init_arg: &InitArg) -> Result<(), InvalidStateError> {
+    use std::str::FromStr;
+
+    if let Some(address) = &init_arg.helper_contract_address {
+        Address::from_str(address)
+            .map_err(|e| InvalidStateError::InvalidHelperContractAddress(format!("ERROR: {e}")))?;
+    }
+    ConfirmationPolicy::validate_block_tag(
+        init_arg.evm_network,
+        BlockTag::from(init_arg.block_height.clone()),
+    )?;
+    Ok(())
 }
 
 impl TryFrom<InitArg> for State {
@@ -70,6 +94,7 @@ impl TryFrom<InitArg> for State {
             ledger_suite_manager_id,
             deposit_native_fee,
             withdrawal_native_fee,
+            fee_collector,
         }: InitArg,
     ) -> Result<Self, Self::Error> {
         use std::str::FromStr;
@@ -86,8 +111,9 @@ impl TryFrom<InitArg> for State {
             })?;
         let native_symbol = ERC20TokenSymbol::new(native_symbol);
 
+        // the checksum of new init arguments is verified by `validate_init_arg`
         let helper_contract_addresses = match helper_contract_address {
-            Some(address_string) => match Address::from_str(&address_string) {
+            Some(address_string) => match Address::from_str_ignoring_checksum(&address_string) {
                 Ok(address) => Ok(Some(vec![address])),
                 Err(e) => Err(InvalidStateError::InvalidHelperContractAddress(format!(
                     "ERROR: {e}"
@@ -144,25 +170,42 @@ impl TryFrom<InitArg> for State {
             native_index_id,
             native_ledger_transfer_fee,
             native_minimum_withdrawal_amount,
-            block_height: BlockTag::from(block_height),
+            confirmation_policy: ConfirmationPolicy::BlockTag(BlockTag::from(block_height)),
             first_scraped_block_number,
             last_scraped_block_number,
             last_observed_block_number: None,
             last_observed_block_time: None,
+            last_observed_block_timestamp: None,
+            last_scraped_block_timestamp: None,
+            last_log_scraping_request_time: 0,
             lastest_requested_block_to_scrape: None,
             events_to_mint: Default::default(),
             minted_events: Default::default(),
             ecdsa_public_key: None,
             invalid_events: Default::default(),
+            removed_log_events: Default::default(),
             native_balance: Default::default(),
             skipped_blocks: Default::default(),
+            scraping_gaps: Default::default(),
+            last_manual_deposit_checks: Default::default(),
+            last_provider_disagreements: Default::default(),
             active_tasks: Default::default(),
+            mints_in_flight: Default::default(),
+            reorged_mints_in_flight: Default::default(),
+            contract_destinations: Default::default(),
             last_transaction_price_estimate: None,
+            last_transaction_price_estimate_block: None,
+            current_l1_fee: None,
+            total_ledger_call_cycles_spent: 0,
+            ecdsa_signing_calls: 0,
+            ecdsa_signing_cycles_spent: 0,
             ledger_suite_manager_id: Some(ledger_suite_manager_id),
             erc20_tokens: Default::default(),
             erc20_balances: Default::default(),
             evm_canister_id: Principal::from_text("sosge-5iaaa-aaaag-alcla-cai").unwrap(),
             min_max_priority_fee_per_gas,
+            gas_fee_config: None,
+            gas_limits: Default::default(),
             dex_canister_id: None,
             withdrawal_native_fee,
             events_to_release: Default::default(),
@@ -170,15 +213,32 @@ impl TryFrom<InitArg> for State {
             quarantined_releases: Default::default(),
             icrc_balances: Default::default(),
             wrapped_icrc_tokens: Default::default(),
+            wrapped_icrc_deployments: Default::default(),
+            wrapped_icrc_metadata: Default::default(),
             twin_usdc_info: None,
             swap_contract_address: None,
             swap_events_to_mint_to_appic_dex: Default::default(),
             last_native_token_usd_price_estimate: None,
             canister_signing_fee_twin_usdc_amount: None,
-            is_swapping_active: false,
+            feature_flags: Default::default(),
+            fee_collector,
+            collected_fees: Default::default(),
+            pending_fee_withdrawals: Default::default(),
+            refund_deadline_window_secs: None,
+            fee_cap_override: None,
+            last_fee_cap_alert: None,
             gas_tank: GasTank::default(),
             next_swap_ledger_burn_index: None,
             quarantined_dex_orders: Default::default(),
+            rate_limited_providers: Default::default(),
+            non_standard_erc20_tokens: Default::default(),
+            known_erc20_contracts: Default::default(),
+            token_volumes: Default::default(),
+            daily_stats: Default::default(),
+            earliest_unpruned_block: Default::default(),
+            erc20_original_symbols: Default::default(),
+            native_ledger_metadata: None,
+            onchain_approvals: Default::default(),
             swap_events_to_be_notified: Default::default(),
             notified_swap_events: Default::default(),
         };
@@ -210,21 +270,106 @@ pub struct UpgradeArg {
     pub deposit_native_fee: Option<Nat>,
     #[cbor(n(9), with = "crate::cbor::nat::option")]
     pub withdrawal_native_fee: Option<Nat>,
+    #[n(10)]
+    pub gas_fee_config: Option<GasFeeConfigArg>,
+    // replaces the block tag based policy, mutually exclusive with block_height
+    #[n(11)]
+    pub confirmations: Option<u32>,
+    // overrides the default gas limits of the network, between 21_000 and 5_000_000
+    #[n(12)]
+    pub gas_limits: Option<Vec<GasLimitArg>>,
+    // account receiving the collected fees from now on
+    #[cbor(n(13), with = "crate::cbor::account::option")]
+    pub fee_collector: Option<Account>,
+    // seconds after which a refund swap that was not executed reverts, between 10 minutes and 24 hours
+    #[n(15)]
+    pub refund_deadline_window_secs: Option<u64>,
+    // override the highest max_fee_per_gas and maximum transaction fee of the network,
+    // transactions above them are delayed until the fees drop or the caps are raised
+    #[cbor(n(16), with = "crate::cbor::nat::option")]
+    pub max_fee_per_gas_cap: Option<Nat>,
+    #[cbor(n(17), with = "crate::cbor::nat::option")]
+    pub max_transaction_fee_cap: Option<Nat>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Encode, Decode, PartialEq, Eq)]
+pub struct GasFeeConfigArg {
+    // index into the reward percentiles of the fee history: 0 => 50th, 1 => 75th, 2 => 90th
+    #[n(0)]
+    pub reward_percentile_index: u8,
+    // percentage of the base fee included in max_fee_per_gas, between 100 and 300
+    #[n(1)]
+    pub base_fee_multiplier_percent: u16,
+    #[cbor(n(2), with = "crate::cbor::nat")]
+    pub min_priority_fee: Nat,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Encode, Decode, PartialEq, Eq)]
+pub struct GasLimitArg {
+    #[n(0)]
+    pub kind: GasLimitKind,
+    #[cbor(n(1), with = "crate::cbor::nat")]
+    pub gas_limit: Nat,
 }
 
 pub fn post_upgrade(upgrade_args: Option<UpgradeArg>) {
     let start = ic_cdk::api::instruction_counter();
 
+    check_event_log_schema_versions();
     STATE.with(|cell| {
         *cell.borrow_mut() = Some(replay_events());
     });
     if let Some(args) = upgrade_args {
-        mutate_state(|s| process_event(s, EventType::Upgrade(args)))
+        if let Err(e) = read_state(|s| s.validate_upgrade_arg(&args)) {
+            ic_cdk::trap(format!("ERROR: invalid upgrade argument: {e:?}"));
+        }
+        mutate_state(|s| {
+            let previous_policy = s.confirmation_policy;
+            process_event(s, EventType::Upgrade(args));
+            if s.confirmation_policy != previous_policy {
+                log!(
+                    INFO,
+                    "[upgrade]: confirmation policy changed from {previous_policy:?} to {:?}",
+                    s.confirmation_policy
+                );
+                process_event(
+                    s,
+                    EventType::ChangedConfirmationPolicy {
+                        from: previous_policy.into(),
+                        to: s.confirmation_policy.into(),
+                    },
+                );
+            }
+        })
+    }
+    mutate_state(|s| {
+        // Minters configured before such tags were rejected still use them.
+        if let Some(policy) = s.confirmation_policy.migrated(s.evm_network) {
+            log!(
+                INFO,
+                "[upgrade]: confirmation policy {:?} has no meaning on {}, migrating it to {policy:?}",
+                s.confirmation_policy,
+                s.evm_network
+            );
+            process_event(
+                s,
+                EventType::ChangedConfirmationPolicy {
+                    from: s.confirmation_policy.into(),
+                    to: policy.into(),
+                },
+            );
+        }
+    });
+    // Trapping would also reject the upgrade fixing the violation, which is reported by
+    // `get_health` instead.
+    if let Err(e) = read_state(State::validate_invariants) {
+        log!(INFO, "ERROR: state invariants violated after upgrade: {e}");
     }
 
     let end = ic_cdk::api::instruction_counter();
 
-    let event_count = total_event_count();
+    // archived events are not replayed, the state is restored from their snapshot
+    let event_count = total_event_count() - archived_event_count();
     let instructions_consumed = end - start;
 
     log!(
@@ -234,6 +379,28 @@ pub fn post_upgrade(upgrade_args: Option<UpgradeArg>) {
     );
 }
 
+/// Refuses to replay an event log written by a newer version of the minter, and reports
+/// entries that still use an older schema.
+///
+/// The event log is append-only, so older entries are migrated on read: `decode_event`
+/// keeps a decoder for every past schema version and new entries use the latest one.
+fn check_event_log_schema_versions() {
+    let Some((oldest, newest)) = event_log_schema_versions() else {
+        return;
+    };
+    if newest > CBOR_SCHEMA_VERSION {
+        ic_cdk::trap(format!(
+            "ERROR: event log uses schema version {newest}, but this minter only supports versions up to {CBOR_SCHEMA_VERSION}"
+        ));
+    }
+    if oldest < CBOR_SCHEMA_VERSION {
+        log!(
+            INFO,
+            "[upgrade]: event log contains entries with schema version {oldest}, decoding them with the legacy decoder (current version {CBOR_SCHEMA_VERSION})"
+        );
+    }
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub enum MinterArg {
     InitArg(InitArg),
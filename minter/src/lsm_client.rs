@@ -12,7 +12,7 @@ use std::fmt::{Debug, Display, Formatter};
 use crate::icrc_client::runtime::IcrcBoundedRuntime;
 use crate::logs::INFO;
 use crate::management::Reason;
-use crate::state::{read_state, State};
+use crate::state::{read_state, State, NATIVE_TOKEN_DECIMALS};
 use crate::{logs::DEBUG, management::CallError};
 use candid::{self, CandidType, Nat, Principal};
 use ic_canister_log::log;
@@ -182,7 +182,7 @@ impl LSMClient {
             state.native_index_id,
             chain_id,
             state.native_ledger_transfer_fee.into(),
-            18_u8, // Native tokens always have 18 decimals
+            NATIVE_TOKEN_DECIMALS,
             logo,
             state.native_symbol.to_string(),
         );
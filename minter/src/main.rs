@@ -1,86 +1,110 @@
 use candid::{Nat, Principal};
 use evm_minter::candid_types::chain_data::ChainData;
-use evm_minter::candid_types::dex_orders::{DexOrderArgs, DexOrderError};
+use evm_minter::candid_types::dex_orders::{
+    DexOrderArgs, DexOrderError, DexOrderQuote, RefundableDexOrderArgs, SwapFeeQuote,
+};
 use evm_minter::candid_types::events::{
-    Event as CandidEvent, EventSource as CandidEventSource, GetEventsArg, GetEventsResult,
+    CompactionResult, Event as CandidEvent, EventSource as CandidEventSource, GetEventsArg,
+    GetEventsRawResult, GetEventsResult,
 };
 use evm_minter::candid_types::wrapped_icrc::{
-    RetrieveWrapIcrcRequest, WrapIcrcArg, WrapIcrcError, WrappedIcrcToken,
+    RetrieveWrapIcrcRequest, WrapIcrcArg, WrapIcrcError, WrappedIcrcToken, WrappedIcrcTokenInfo,
 };
 use evm_minter::contract_logs::swap::swap_logs::ReceivedSwapEvent;
 use evm_minter::contract_logs::types::{
     ReceivedBurnEvent, ReceivedErc20Event, ReceivedNativeEvent, ReceivedWrappedIcrcDeployedEvent,
 };
 use evm_minter::contract_logs::EventSource;
-use evm_minter::deposit::{apply_safe_threshold_to_latest_block_numner, scrape_logs};
+use evm_minter::deposit::{
+    apply_safe_threshold_to_latest_block_numner, quote, rescrape_scraping_gaps,
+    schedule_upgrade_grace_scrape, scrape_historical_block_range, scrape_logs,
+    validate_backfill_range, validate_log_scraping_request,
+};
 use evm_minter::rpc_declarations::parse_fee_history;
 use evm_rpc_client::address::validate_address_as_destination;
 use evm_rpc_client::address::AddressValidationError;
 
 use evm_minter::candid_types::{
-    self, ActivateSwapReqest, AddErc20Token, CandidTwinUsdcInfo, DepositStatus, GasTankBalance,
-    Icrc28TrustedOriginsResponse, IcrcBalance, NativeTokenUsdPriceEstimate, RequestScrapingError,
-    SwapStatus,
+    self, ActivateSwapError, ActivateSwapReqest, AddErc20Token, AddOrRemove, ArchiveEventsError,
+    BackfillScrapeError, BackfillSummary, CandidDailyStats, CandidFeatureFlag,
+    CandidOnchainApproval, CandidTokenVolume, CandidTwinUsdcInfo, CompactEventLogError,
+    DepositQuote, DepositStatus, DrainStatus, EndpointInstrumentation, GasTankBalance,
+    Icrc28TrustedOriginsResponse, IcrcBalance, ListKind, MinterHealth, NativeTokenUsdPriceEstimate,
+    ProviderStat, RequestScrapingError, RevokeApprovalError, ScrapingGap as CandidScrapingGap,
+    SetFeatureFlagError, SwapStatus, SwapStatusTransition, UpdateAddressListError,
+    WithdrawCollectedFeesError,
 };
 use evm_minter::candid_types::{
     withdraw_erc20::RetrieveErc20Request, withdraw_erc20::WithdrawErc20Arg,
     withdraw_erc20::WithdrawErc20Error,
 };
 use evm_minter::candid_types::{
+    withdraw_native::CancelError, withdraw_native::FinalizedWithdrawal,
     withdraw_native::WithdrawalArg, withdraw_native::WithdrawalDetail,
     withdraw_native::WithdrawalError, withdraw_native::WithdrawalSearchParameter,
     Eip1559TransactionPrice, Eip1559TransactionPriceArg, Erc20Balance, GasFeeEstimate, MinterInfo,
     RetrieveNativeRequest, RetrieveWithdrawalStatus,
 };
 use evm_minter::erc20::ERC20Token;
-use evm_minter::evm_config::EvmNetwork;
+use evm_minter::evm_config::GasLimitKind;
 use evm_minter::guard::retrieve_withdraw_guard;
 use evm_minter::icrc_21::{
-    ConsentInfo, ConsentMessage, ConsentMessageMetadata, ConsentMessageRequest,
-    ConsentMessageResponse, DeviceSpec, ErrorInfo, TextValue, Value,
+    format_token_amount, supported_language, ConsentInfo, ConsentMessage, ConsentMessageRequest,
+    ConsentMessageResponse, ConsentMessageResponseMetadata, ConsentMessages, DeviceSpec, ErrorInfo,
+    Localizer, TextValue, Value, SUPPORTED_LANGUAGES,
 };
 use evm_minter::icrc_client::runtime::IcrcBoundedRuntime;
-use evm_minter::icrc_client::{LedgerBurnError, LedgerClient};
-use evm_minter::lifecycle::MinterArg;
+use evm_minter::icrc_client::{
+    refresh_native_ledger_metadata, refresh_wrapped_icrc_metadata, LedgerBurnError, LedgerClient,
+};
+use evm_minter::instrumentation::{self, instrument};
+use evm_minter::lifecycle::{validate_init_arg, MinterArg};
 use evm_minter::logs::{DEBUG, INFO};
 use evm_minter::lsm_client::lazy_add_native_ls_to_lsm_canister;
 use evm_minter::memo::BurnMemo;
-use evm_minter::numeric::{BlockNumber, Erc20Value, LedgerBurnIndex, Wei};
-use evm_minter::rpc_client::providers::Provider;
+use evm_minter::numeric::{
+    BlockNumber, BlockTimestamp, Erc20Value, GasAmount, LedgerBurnIndex, Wei,
+};
+use evm_minter::rpc_client::providers::{provider_of, Provider};
 use evm_minter::rpc_declarations::Hash;
 use evm_minter::state::audit::{process_event, EventType};
+use evm_minter::state::daily_stats::day_index;
 use evm_minter::state::event::Event;
+use evm_minter::state::feature_flags::{validate_feature_flag_change, FeatureFlag};
 use evm_minter::state::transactions::{
-    Erc20Approve, Erc20WithdrawalRequest, ExecuteSwapRequest, NativeWithdrawalRequest, Reimbursed,
-    ReimbursementIndex, ReimbursementRequest,
+    Erc20Approve, Erc20WithdrawalRequest, ExecuteSwapRequest, NativeWithdrawalRequest,
+    PendingReimbursementRequest, Reimbursed, ReimbursementIndex,
 };
 use evm_minter::state::{
     lazy_call_ecdsa_public_key, mutate_state, read_state, transactions, State, STATE,
 };
 use evm_minter::storage::set_rpc_api_key;
 use evm_minter::swap::{
-    build_dex_swap_refund_request, build_dex_swap_request, is_quarantine_error,
+    build_dex_swap_refund_request, build_dex_swap_request, is_quarantine_error, quote_dex_order,
+    swap_fee_quote, validate_approval_revocation, validate_swap_activation, ApprovalRevocation,
+    SwapActivation,
 };
 use evm_minter::tx::gas_fees::{
-    estimate_erc20_transaction_fee, estimate_icrc_wrap_transaction_fee, estimate_transaction_fee,
-    estimate_usdc_approval_fee, lazy_refresh_gas_fee_estimate, DEFAULT_L1_BASE_GAS_FEE,
+    dex_order_fee, estimate_erc20_transaction_fee, estimate_icrc_wrap_transaction_fee,
+    estimate_transaction_fee, estimate_usdc_approval_fee, lazy_refresh_gas_fee_estimate,
 };
 use evm_minter::tx_id::SwapTxId;
 use evm_minter::withdraw::{
-    process_reimbursement, process_retrieve_tokens_requests,
-    ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT, NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+    cancel_withdrawal_request, process_reimbursement, process_retrieve_tokens_requests,
 };
 use evm_minter::{
-    state, storage, APPIC_CONTROLLER_PRINCIPAL, PROCESS_REIMBURSEMENT,
+    fee_collector, management, state, storage, APPIC_CONTROLLER_PRINCIPAL,
+    MIN_PRUNE_THRESHOLD_BLOCKS, NATIVE_LEDGER_METADATA_REFRESH_INTERVAL, PROCESS_REIMBURSEMENT,
     PROCESS_TOKENS_RETRIEVE_TRANSACTIONS_INTERVAL, RPC_HELPER_PRINCIPAL,
-    SCRAPING_CONTRACT_LOGS_INTERVAL,
+    SCRAPING_CONTRACT_LOGS_INTERVAL, WRAPPED_ICRC_METADATA_REFRESH_INTERVAL,
 };
 use evm_rpc_client::eth_types::Address;
+use evm_rpc_client::native_http::weighted::{read_latency, LatencyStats, ProviderLatency};
 use ic_canister_log::log;
 use ic_cdk::{init, post_upgrade, pre_upgrade, query, update};
 use icrc_ledger_client::ICRC1Client;
 use icrc_ledger_types::icrc1::transfer::TransferArg;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryFrom;
 use std::panic;
 use std::str::FromStr;
@@ -100,7 +124,64 @@ fn validate_caller_not_anonymous() -> candid::Principal {
     principal
 }
 
+fn validate_not_draining() -> Result<(), String> {
+    if read_state(State::is_draining) {
+        return Err("Minter is draining before an upgrade, try again later".to_string());
+    }
+    Ok(())
+}
+
+fn validate_native_decimals() -> Result<(), String> {
+    read_state(|s| s.validate_native_decimals())
+}
+
+/// Rejects destinations that are protected addresses, such as the minter address or a helper
+/// contract, unless the controller explicitly accepted them on the protected override list.
+fn validate_destination_not_protected(
+    destination: &Address,
+    token_contract: Option<&Address>,
+) -> Result<(), String> {
+    if storage::address_list_contains(ListKind::ProtectedOverride, destination) {
+        return Ok(());
+    }
+    match read_state(|s| s.protected_destination(destination, token_contract)) {
+        Some(protected) => Err(format!("Destination {destination} is {protected}")),
+        None => Ok(()),
+    }
+}
+
+// Copies the retained events of a pending archive one bounded chunk per message, so that
+// archiving a large event log never exceeds the instruction limit.
+fn schedule_archive_continuation() {
+    if storage::pending_archive().is_none() {
+        return;
+    }
+    ic_cdk_timers::set_timer(Duration::from_secs(0), || {
+        if storage::continue_archive_events(storage::MAX_COPIED_EVENTS_PER_MESSAGE) {
+            log!(
+                INFO,
+                "[schedule_archive_continuation]: {} events archived",
+                storage::archived_event_count()
+            );
+        } else {
+            schedule_archive_continuation();
+        }
+    });
+}
+
 fn setup_timers() {
+    ic_cdk_timers::set_timer(Duration::from_secs(0), || {
+        // Cache the hash of the installed Wasm module, reported by `get_minter_info`.
+        ic_cdk::futures::spawn_017_compat(async {
+            if let Err(e) = management::refresh_module_hash().await {
+                log!(
+                    INFO,
+                    "[setup_timers]: failed to fetch the Wasm module hash: {e:?}"
+                );
+            }
+        })
+    });
+
     ic_cdk_timers::set_timer(Duration::from_secs(0), || {
         // Initialize the minter's public key to make the address known.
         ic_cdk::futures::spawn_017_compat(async {
@@ -128,6 +209,21 @@ fn setup_timers() {
     ic_cdk_timers::set_timer_interval(PROCESS_REIMBURSEMENT, || {
         ic_cdk::futures::spawn_017_compat(process_reimbursement())
     });
+
+    // Ledger metadata can change with ledger upgrades, so refresh it regularly.
+    ic_cdk_timers::set_timer(Duration::from_secs(0), || {
+        ic_cdk::futures::spawn_017_compat(refresh_native_ledger_metadata())
+    });
+    ic_cdk_timers::set_timer_interval(NATIVE_LEDGER_METADATA_REFRESH_INTERVAL, || {
+        ic_cdk::futures::spawn_017_compat(refresh_native_ledger_metadata())
+    });
+    // Only the wrapped ICRC tokens without metadata or with expired metadata are queried.
+    ic_cdk_timers::set_timer(Duration::from_secs(0), || {
+        ic_cdk::futures::spawn_017_compat(refresh_wrapped_icrc_metadata())
+    });
+    ic_cdk_timers::set_timer_interval(WRAPPED_ICRC_METADATA_REFRESH_INTERVAL, || {
+        ic_cdk::futures::spawn_017_compat(refresh_wrapped_icrc_metadata())
+    });
 }
 
 #[init]
@@ -135,6 +231,9 @@ async fn init(arg: MinterArg) {
     match arg {
         MinterArg::InitArg(init_arg) => {
             log!(INFO, "[init]: initialized minter with arg: {:?}", init_arg);
+            if let Err(e) = validate_init_arg(&init_arg) {
+                ic_cdk::trap(format!("ERROR: invalid init argument: {e:?}"));
+            }
             STATE.with(|cell| {
                 storage::record_event(EventType::Init(init_arg.clone()));
                 *cell.borrow_mut() = Some(
@@ -170,6 +269,21 @@ async fn init(arg: MinterArg) {
 
 fn emit_preupgrade_events() {
     read_state(|s| {
+        let in_flight_count = s.in_flight_count();
+        if in_flight_count > 0 {
+            log!(
+                INFO,
+                "[pre_upgrade]: WARNING: upgrading with {in_flight_count} items in flight"
+            );
+            storage::record_event(EventType::UpgradedWhileNotDrained { in_flight_count });
+        }
+        // draining stops with the upgrade
+        if s.is_draining() {
+            storage::record_event(EventType::FeatureFlagChanged {
+                flag: FeatureFlag::Draining,
+                enabled: false,
+            });
+        }
         storage::record_event(EventType::SyncedToBlock {
             block_number: s.last_scraped_block_number,
         });
@@ -202,11 +316,25 @@ fn post_upgrade(minter_arg: Option<MinterArg>) {
     set_rpc_api_key(Provider::DRPC, drpc_api_key.to_string());
     set_rpc_api_key(Provider::Alchemy, alchemy_api_key.to_string());
 
+    schedule_upgrade_grace_scrape();
+    schedule_archive_continuation();
     setup_timers();
+
+    // Sends interrupted by the upgrade are reconciled before the next interval.
+    if read_state(|s| {
+        !s.withdrawal_transactions
+            .unreconciled_broadcast_attempts()
+            .is_empty()
+    }) {
+        ic_cdk_timers::set_timer(Duration::from_secs(0), || {
+            ic_cdk::futures::spawn_017_compat(process_retrieve_tokens_requests())
+        });
+    }
 }
 
 #[update]
 async fn minter_address() -> String {
+    let _instrumentation = instrument("minter_address");
     state::minter_address().await.to_string()
 }
 
@@ -224,30 +352,46 @@ async fn smart_contract_address() -> Option<Vec<String>> {
 
 /// Estimate price of EIP-1559 transaction based on the
 /// `base_fee_per_gas` included in the last Latest block.
+/// Native withdrawals to a `destination` that was found to be a contract by a withdrawal
+/// in the last hour are priced with the gas limit of contract destinations.
 #[query]
 async fn eip_1559_transaction_price(
     token: Option<Eip1559TransactionPriceArg>,
 ) -> Eip1559TransactionPrice {
-    let gas_limit = match token {
-        None => NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
-        Some(Eip1559TransactionPriceArg { erc20_ledger_id }) => {
-            match read_state(|s| s.find_erc20_token_by_ledger_id(&erc20_ledger_id)) {
-                Some(_) => ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
-                None => {
-                    if erc20_ledger_id == read_state(|s| s.native_ledger_id) {
-                        NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT
+    let gas_limit_kind = match token {
+        None => GasLimitKind::NativeWithdrawal,
+        Some(Eip1559TransactionPriceArg {
+            erc20_ledger_id,
+            destination,
+        }) => match read_state(|s| s.find_erc20_token_by_ledger_id(&erc20_ledger_id)) {
+            Some(_) => GasLimitKind::Erc20Withdrawal,
+            None => {
+                if erc20_ledger_id == read_state(|s| s.native_ledger_id) {
+                    let now = ic_cdk::api::time();
+                    let is_contract_destination = destination
+                        .and_then(|destination| Address::from_str(&destination).ok())
+                        .and_then(|destination| {
+                            read_state(|s| s.cached_is_contract_destination(&destination, now))
+                        });
+                    if is_contract_destination.unwrap_or_default() {
+                        GasLimitKind::NativeWithdrawalToContract
                     } else {
-                        ic_cdk::trap(format!(
-                            r#"ERROR: Unsupported ckERC20 token ledger {erc20_ledger_id}"#
-                        ))
+                        GasLimitKind::NativeWithdrawal
                     }
+                } else {
+                    ic_cdk::trap(format!(
+                        r#"ERROR: Unsupported ckERC20 token ledger {erc20_ledger_id}"#
+                    ))
                 }
             }
-        }
+        },
     };
+    let gas_limit = read_state(|s| s.gas_limit(gas_limit_kind));
     match read_state(|s| s.last_transaction_price_estimate.clone()) {
         Some((ts, estimate)) => {
             let mut result = Eip1559TransactionPrice::from(estimate.to_price(gas_limit));
+            result.min_priority_fee_per_gas =
+                Some(read_state(State::min_priority_fee_per_gas).into());
             result.timestamp = Some(ts);
             result
         }
@@ -261,6 +405,7 @@ async fn eip_1559_transaction_price(
 #[allow(deprecated)]
 #[query]
 async fn get_minter_info() -> MinterInfo {
+    let now = ic_cdk::api::time();
     read_state(|s| {
         let erc20_balances = Some(
             s.supported_erc20_tokens()
@@ -273,6 +418,7 @@ async fn get_minter_info() -> MinterInfo {
                 })
                 .collect(),
         );
+        // Already sorted by token symbol.
         let supported_erc20_tokens = Some(
             s.supported_erc20_tokens()
                 .map(candid_types::Erc20Token::from)
@@ -318,7 +464,7 @@ async fn get_minter_info() -> MinterInfo {
             minimum_withdrawal_amount: Some(s.native_minimum_withdrawal_amount.into()),
             deposit_native_fee: None,
             withdrawal_native_fee: s.withdrawal_native_fee.map(|fee| fee.into()),
-            block_height: Some(s.block_height.into()),
+            block_height: Some(s.block_height().into()),
             last_observed_block_number: s.last_observed_block_number.map(|n| n.into()),
             native_balance: Some(s.native_balance.native_balance().into()),
             last_gas_fee_estimate: s.last_transaction_price_estimate.as_ref().map(
@@ -338,7 +484,7 @@ async fn get_minter_info() -> MinterInfo {
             ),
             icrc_balances,
             wrapped_icrc_tokens,
-            is_swapping_active: s.is_swapping_active,
+            is_swapping_active: s.is_swapping_active(),
             dex_canister_id: s.dex_canister_id,
             swap_contract_address: s.swap_contract_address.map(|address| address.to_string()),
             twin_usdc_info: s.twin_usdc_info.clone().map(|info| CandidTwinUsdcInfo {
@@ -362,6 +508,51 @@ async fn get_minter_info() -> MinterInfo {
             next_swap_ledger_burn_index: s
                 .next_swap_ledger_burn_index
                 .map(|index| index.get().into()),
+            total_ledger_call_cycles_spent: Some(s.total_ledger_call_cycles_spent.into()),
+            confirmation_policy: Some(s.confirmation_policy.into()),
+            effective_confirmed_block_number: s.last_observed_block_number.map(|block_number| {
+                apply_safe_threshold_to_latest_block_numner(
+                    s.evm_network,
+                    s.confirmation_policy,
+                    block_number,
+                )
+                .into()
+            }),
+            pending_withdrawal_stats: Some(
+                s.withdrawal_transactions
+                    .incomplete_withdrawal_requests_iter()
+                    .collect(),
+            ),
+            oldest_pending_withdrawal_age_ns: s
+                .withdrawal_transactions
+                .oldest_incomplete_withdrawal_timestamp()
+                .map(|created_at| ic_cdk::api::time().saturating_sub(created_at)),
+            token_volumes: Some(
+                s.token_volumes
+                    .iter()
+                    .map(|(address, volume)| CandidTokenVolume::new(address, volume))
+                    .collect(),
+            ),
+            wasm_hash: management::module_hash().map(hex::encode),
+            canister_version: Some(ic_cdk::api::canister_version()),
+            onchain_approvals: Some(
+                s.onchain_approvals
+                    .iter()
+                    .map(|(key, approval)| CandidOnchainApproval::new(key, approval))
+                    .collect(),
+            ),
+            last_observed_block_timestamp: s.last_observed_block_timestamp.map(|t| t.into()),
+            last_scraped_block_timestamp: s.last_scraped_block_timestamp().map(|t| t.into()),
+            last_observed_block_lag_seconds: s.last_observed_block_lag_seconds(now),
+            last_scraped_block_lag_seconds: s.last_scraped_block_lag_seconds(now),
+            ecdsa_signing_calls: Some(s.ecdsa_signing_calls),
+            ecdsa_signing_cycles_spent: Some(s.ecdsa_signing_cycles_spent.into()),
+            estimated_remaining_signing_capacity: Some(
+                s.estimated_remaining_signing_capacity(ic_cdk::api::canister_cycle_balance()),
+            ),
+            max_fee_per_gas_cap: Some(s.fee_cap().max_fee_per_gas.into()),
+            max_transaction_fee_cap: Some(s.fee_cap().max_transaction_fee.into()),
+            withdrawal_transaction_stats: Some(s.withdrawal_transactions.stats()),
         }
     })
 }
@@ -374,6 +565,7 @@ async fn get_minter_info() -> MinterInfo {
 // Meaning that this function can only be called onces in a minute due to cycle drain attacks.
 #[update]
 async fn request_scraping_logs() -> Result<(), RequestScrapingError> {
+    let _instrumentation = instrument("request_scraping_logs");
     let caller = ic_cdk::api::msg_caller();
     let appic_controller = Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap();
 
@@ -381,15 +573,127 @@ async fn request_scraping_logs() -> Result<(), RequestScrapingError> {
         panic!("Access Denied");
     }
 
+    let now = ic_cdk::api::time();
+    read_state(|s| validate_log_scraping_request(s.last_log_scraping_request_time, now))?;
+    mutate_state(|s| s.last_log_scraping_request_time = now);
+
     ic_cdk_timers::set_timer(Duration::from_secs(0), || {
-        ic_cdk::futures::spawn_017_compat(scrape_logs())
+        ic_cdk::futures::spawn_017_compat(async {
+            rescrape_scraping_gaps().await;
+            scrape_logs().await
+        })
     });
 
     Ok(())
 }
 
+// Scrapes again the block range `from_block..=to_block` to import deposits that were missed,
+// e.g. because they were emitted before a contract was registered.
+// The range must end below the last scraped block and span at most `MAX_BACKFILL_SCRAPE_BLOCKS`
+// blocks. Events that were already recorded are skipped, so overlapping calls are harmless.
+#[update]
+async fn backfill_scrape(
+    from_block: Nat,
+    to_block: Nat,
+) -> Result<BackfillSummary, BackfillScrapeError> {
+    let _instrumentation = instrument("backfill_scrape");
+    let caller = validate_caller_not_anonymous();
+    if caller != Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap() {
+        panic!("Only appic controller can backfill block ranges");
+    }
+
+    let from_block =
+        BlockNumber::try_from(from_block).map_err(|_| BackfillScrapeError::InvalidBlockNumber)?;
+    let to_block =
+        BlockNumber::try_from(to_block).map_err(|_| BackfillScrapeError::InvalidBlockNumber)?;
+    let block_range =
+        read_state(|s| validate_backfill_range(from_block, to_block, s.last_scraped_block_number))?;
+
+    let counts = scrape_historical_block_range(block_range).await?;
+    Ok(BackfillSummary {
+        accepted_events: counts.accepted,
+        invalid_events: counts.invalid,
+        duplicate_events: counts.duplicate,
+    })
+}
+
+// Returns the block ranges whose logs were never successfully scraped,
+// e.g. because the provider response for a single block was too large.
+#[query]
+fn get_scraping_gaps() -> Vec<CandidScrapingGap> {
+    read_state(|s| {
+        s.scraping_gaps
+            .iter()
+            .map(|gap| CandidScrapingGap {
+                from_block: gap.from_block.into(),
+                to_block: gap.to_block.into(),
+                skipped_at: gap.skipped_at,
+            })
+            .collect()
+    })
+}
+
+// Returns the amount that will be minted for a deposit of `amount` and how long it takes.
+// `token` is the ERC-20 contract address, or `None` for the native token.
+#[query]
+fn quote_deposit(token: Option<String>, amount: Nat) -> DepositQuote {
+    read_state(|s| quote::quote_deposit(s, token, amount))
+        .unwrap_or_else(|e| ic_cdk::trap(format!("ERROR: {e}")))
+}
+
+// Adds or removes addresses from the withdrawal destination deny or allow list.
+// Withdrawals that were already accepted are not affected.
+#[update]
+fn update_address_list(
+    list: ListKind,
+    action: AddOrRemove,
+    addresses: Vec<String>,
+) -> Result<(), UpdateAddressListError> {
+    let _instrumentation = instrument("update_address_list");
+    let caller = validate_caller_not_anonymous();
+    if caller != Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap() {
+        panic!("Only appic controller can update address lists");
+    }
+
+    let addresses = addresses
+        .into_iter()
+        .map(|address| {
+            Address::from_str(&address).map_err(|_| UpdateAddressListError::InvalidAddress(address))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    storage::update_address_list(list, action, &addresses)?;
+
+    log!(
+        INFO,
+        "[update_address_list]: {action:?} {} addresses to the {list:?} list",
+        addresses.len()
+    );
+    mutate_state(|s| {
+        process_event(
+            s,
+            EventType::UpdatedAddressList {
+                list,
+                action,
+                addresses,
+            },
+        )
+    });
+
+    Ok(())
+}
+
+#[query]
+fn get_address_list(list: ListKind) -> Vec<String> {
+    storage::address_list_entries(list)
+        .iter()
+        .map(|address| address.to_string())
+        .collect()
+}
+
 #[update]
 fn request_block_scrape(block: Nat) {
+    let _instrumentation = instrument("request_block_scrape");
     let caller = ic_cdk::api::msg_caller();
     let rpc_helper_identity = Principal::from_text(RPC_HELPER_PRINCIPAL).unwrap();
 
@@ -407,6 +711,61 @@ fn request_block_scrape(block: Nat) {
     }
 }
 
+// Returns the ERC-20 approvals granted by the minter that were not revoked yet.
+#[query]
+fn get_onchain_approvals() -> Vec<CandidOnchainApproval> {
+    read_state(|s| {
+        s.onchain_approvals
+            .iter()
+            .map(|(key, approval)| CandidOnchainApproval::new(key, approval))
+            .collect()
+    })
+}
+
+// Returns the minted deposits and successful withdrawals per UTC day and per token ledger over the
+// last `days` days including today, optionally restricted to the token with the given ledger id.
+#[query]
+fn get_daily_stats(days: u8, token: Option<Principal>) -> Vec<CandidDailyStats> {
+    let from_day = (day_index(ic_cdk::api::time()) + 1).saturating_sub(days as u64);
+    read_state(|s| {
+        s.daily_stats
+            .iter_from(from_day, token)
+            .map(|(day, token, stats)| CandidDailyStats::new(day, *token, stats))
+            .collect()
+    })
+}
+
+// Returns the supported wrapped ICRC tokens with their deployment transaction and the cached
+// metadata of their base ledger.
+#[query]
+fn get_wrapped_icrc_tokens() -> Vec<WrappedIcrcTokenInfo> {
+    read_state(|s| {
+        s.wrapped_icrc_tokens
+            .iter()
+            .map(|(ledger_id, address, _transfer_fee)| {
+                WrappedIcrcTokenInfo::new(
+                    *ledger_id,
+                    address,
+                    s.wrapped_icrc_deployments.get(address),
+                    s.wrapped_icrc_metadata.get(ledger_id),
+                )
+            })
+            .collect()
+    })
+}
+
+// Returns the cumulative deposit, withdrawal and reimbursement volumes of an ERC-20 token,
+// or `None` if the token was never bridged.
+#[query]
+fn get_token_volume(contract_address: String) -> Option<CandidTokenVolume> {
+    let contract_address = Address::from_str(&contract_address).expect("Invalid contract address");
+    read_state(|s| {
+        s.token_volumes
+            .get(&contract_address)
+            .map(|volume| CandidTokenVolume::new(&contract_address, volume))
+    })
+}
+
 #[query]
 fn retrieve_deposit_status(tx_hash: String) -> Option<DepositStatus> {
     read_state(|s| {
@@ -446,24 +805,78 @@ fn retrieve_swap_status_by_swap_tx_id(tx_id: String) -> Option<SwapStatus> {
     })
 }
 
+#[query]
+fn retrieve_swap_status_history(tx_id: String) -> Vec<SwapStatusTransition> {
+    read_state(|s| {
+        s.withdrawal_transactions
+            .get_swap_status_history(&SwapTxId(tx_id))
+    })
+    .into_iter()
+    .map(|(timestamp, status)| SwapStatusTransition { timestamp, status })
+    .collect()
+}
+
+#[query]
+fn retrieve_swap_status_history_by_hash(tx_hash: String) -> Vec<SwapStatusTransition> {
+    let status_by_hash = read_state(|s| {
+        s.get_swap_status(Hash::from_str(&tx_hash).expect("Invalid transaction hash"))
+    });
+
+    // the history is tracked by swap_tx_id which is only known once the swap was minted to appic dex
+    match status_by_hash {
+        Some(SwapStatus::MintedToAppicDex(tx_id)) | Some(SwapStatus::NotifiedAppicDex(tx_id)) => {
+            retrieve_swap_status_history(tx_id)
+        }
+        _ => vec![],
+    }
+}
+
 #[update]
 async fn withdraw_native_token(
-    WithdrawalArg { amount, recipient }: WithdrawalArg,
+    WithdrawalArg {
+        amount,
+        recipient,
+        created_at_time,
+        allow_contract_destination,
+    }: WithdrawalArg,
 ) -> Result<RetrieveNativeRequest, WithdrawalError> {
+    let _instrumentation = instrument("withdraw_native_token");
     let caller = validate_caller_not_anonymous();
+    validate_not_draining().map_err(WithdrawalError::TemporarilyUnavailable)?;
+    validate_native_decimals().map_err(WithdrawalError::TemporarilyUnavailable)?;
     let _guard = retrieve_withdraw_guard(caller).unwrap_or_else(|e| {
         ic_cdk::trap(format!(
             "Failed retrieving guard for principal {caller}: {e:?}"
         ))
     });
 
-    let destination = validate_address_as_destination(&recipient).map_err(|e| match e {
-        AddressValidationError::Invalid { .. } | AddressValidationError::NotSupported(_) => {
-            WithdrawalError::InvalidDestination("Invalid destination entered".to_string())
-        }
-    })?;
+    let known_erc20_contracts = if allow_contract_destination.unwrap_or_default() {
+        BTreeSet::new()
+    } else {
+        read_state(|s| s.known_erc20_contracts.clone())
+    };
+    let destination = validate_address_as_destination(&recipient, &known_erc20_contracts).map_err(
+        |e| match e {
+            AddressValidationError::Invalid { .. } | AddressValidationError::NotSupported(_) => {
+                WithdrawalError::InvalidDestination("Invalid destination entered".to_string())
+            }
+            AddressValidationError::InvalidChecksum { .. }
+            | AddressValidationError::IsKnownErc20Contract(_) => {
+                WithdrawalError::InvalidDestination(e.to_string())
+            }
+        },
+    )?;
+
+    if !storage::is_destination_allowed(&destination) {
+        return Err(WithdrawalError::DestinationNotAllowed);
+    }
 
-    let amount = Wei::try_from(amount).expect("failed to convert Nat to u256");
+    validate_destination_not_protected(&destination, None)
+        .map_err(WithdrawalError::InvalidDestination)?;
+
+    let amount = Wei::try_from(amount).map_err(|_| WithdrawalError::AmountTooLarge {
+        max_withdrawal_amount: Wei::MAX.into(),
+    })?;
 
     // If withdrawal_native_fee is some, the total transaction value should be as follow
     // amount - withdrawal_native_fee
@@ -477,22 +890,20 @@ async fn withdraw_native_token(
     }
 
     // Check if l1_fee is required for this network
-    let l1_fee = match read_state(|s| s.evm_network) {
-        EvmNetwork::Base => Some(DEFAULT_L1_BASE_GAS_FEE),
-        _ => None,
-    };
+    let l1_fee = read_state(State::l1_fee);
 
     let client = read_state(LedgerClient::native_ledger_from_state);
     let now = ic_cdk::api::time();
     log!(INFO, "[withdraw]: burning {:?}", amount);
     match client
-        .burn_from(
+        .burn_from_with_created_at_time(
             caller.into(),
             amount,
             BurnMemo::Convert {
                 to_address: destination,
             },
             None,
+            created_at_time,
         )
         .await
     {
@@ -506,6 +917,8 @@ async fn withdraw_native_token(
                 created_at: Some(now),
                 l1_fee,
                 withdrawal_fee: withdrawal_native_fee,
+                // checked when the transaction is created, see `create_transactions_batch`
+                is_contract_destination: None,
             };
 
             log!(
@@ -527,12 +940,59 @@ async fn withdraw_native_token(
 
             Ok(RetrieveNativeRequest::from(withdrawal_request))
         }
+        Err(LedgerBurnError::Duplicate { duplicate_of, .. }) => {
+            // the ledger only tells that the burn was executed, not that the minter accepted
+            // a withdrawal for it
+            let is_accepted = read_state(|s| {
+                !matches!(
+                    s.withdrawal_transactions.transaction_status(&duplicate_of),
+                    RetrieveWithdrawalStatus::NotFound
+                ) && s
+                    .withdrawal_transactions
+                    .withdrawal_request(&duplicate_of)
+                    .is_some_and(|request| {
+                        matches!(request, transactions::WithdrawalRequest::Native(_))
+                            && request.from() == caller
+                    })
+            });
+            if !is_accepted {
+                log!(
+                    INFO,
+                    "[withdraw]: no withdrawal of {caller} was accepted for the duplicate burn {duplicate_of}"
+                );
+                return Err(WithdrawalError::AlreadyProcessed {
+                    duplicate_of: Nat::from(duplicate_of.get()),
+                });
+            }
+            log!(
+                INFO,
+                "[withdraw]: withdrawal already accepted with burn index {duplicate_of}"
+            );
+            Ok(RetrieveNativeRequest {
+                block_index: Nat::from(duplicate_of.get()),
+            })
+        }
         Err(e) => Err(WithdrawalError::from(e)),
     }
 }
 
+// Cancels a withdrawal of the native token issued by the caller, as long as no transaction
+// was created for it yet. The burned amount minus the ledger transfer fee is reimbursed.
+#[update]
+async fn cancel_withdrawal(ledger_burn_index: u64) -> Result<(), CancelError> {
+    let _instrumentation = instrument("cancel_withdrawal");
+    let caller = validate_caller_not_anonymous();
+    cancel_withdrawal_request(caller, LedgerBurnIndex::new(ledger_burn_index))?;
+
+    ic_cdk_timers::set_timer(Duration::from_secs(0), || {
+        ic_cdk::futures::spawn_017_compat(process_reimbursement())
+    });
+    Ok(())
+}
+
 #[update]
 async fn retrieve_withdrawal_status(block_index: u64) -> RetrieveWithdrawalStatus {
+    let _instrumentation = instrument("retrieve_withdrawal_status");
     let ledger_burn_index = LedgerBurnIndex::new(block_index);
     read_state(|s| {
         s.withdrawal_transactions
@@ -540,6 +1000,28 @@ async fn retrieve_withdrawal_status(block_index: u64) -> RetrieveWithdrawalStatu
     })
 }
 
+// Same as `retrieve_withdrawal_status` but identifies the withdrawal by the burn index on the
+// ERC-20 ledger returned in `RetrieveErc20Request.erc20_block_index`, or by the lock index on
+// the ICRC ledger for `wrap_icrc` requests.
+#[update]
+async fn retrieve_withdrawal_status_by_erc20_burn(
+    ledger_id: Principal,
+    burn_index: u64,
+) -> RetrieveWithdrawalStatus {
+    let _instrumentation = instrument("retrieve_withdrawal_status_by_erc20_burn");
+    read_state(|s| {
+        match s
+            .withdrawal_transactions
+            .native_ledger_burn_index_of(&ledger_id, LedgerBurnIndex::new(burn_index))
+        {
+            Some(ledger_burn_index) => s
+                .withdrawal_transactions
+                .transaction_status(&ledger_burn_index),
+            None => RetrieveWithdrawalStatus::NotFound,
+        }
+    })
+}
+
 #[query]
 async fn withdrawal_status(parameter: WithdrawalSearchParameter) -> Vec<WithdrawalDetail> {
     use transactions::WithdrawalRequest::*;
@@ -553,11 +1035,7 @@ async fn withdrawal_status(parameter: WithdrawalSearchParameter) -> Vec<Withdraw
                 recipient_address: request.payee().to_string(),
                 token_symbol: match request {
                     Native(_) => s.native_symbol.to_string(),
-                    Erc20(r) => s
-                        .erc20_tokens
-                        .get_alt(&r.erc20_contract_address)
-                        .unwrap()
-                        .to_string(),
+                    Erc20(r) => s.erc20_token_symbol_or_address(&r.erc20_contract_address),
                     Erc20Approve(_erc20_approve) => "USDC".to_string(),
                     Swap(_r) => "USDC".to_string(),
                 },
@@ -587,6 +1065,56 @@ async fn withdrawal_status(parameter: WithdrawalSearchParameter) -> Vec<Withdraw
     })
 }
 
+/// Returns the successful native and ERC-20 withdrawals, most recently finalized first,
+/// optionally restricted to the token with the given ledger id.
+#[query]
+fn get_finalized_transactions(
+    token: Option<Principal>,
+    start: u64,
+    length: u64,
+) -> Vec<FinalizedWithdrawal> {
+    use transactions::WithdrawalRequest::*;
+    const MAX_FINALIZED_TRANSACTIONS_PER_RESPONSE: u64 = 200;
+
+    read_state(|s| {
+        s.withdrawal_transactions
+            .finalized_withdrawals(
+                token.as_ref(),
+                start as usize,
+                length.min(MAX_FINALIZED_TRANSACTIONS_PER_RESPONSE) as usize,
+            )
+            .into_iter()
+            .filter_map(|(finalized_at, request, tx)| {
+                let (token_symbol, amount, l1_fee) = match request {
+                    Native(r) => (
+                        Some(s.native_symbol.to_string()),
+                        Nat::from(*tx.transaction_amount()),
+                        r.l1_fee,
+                    ),
+                    Erc20(r) => (
+                        s.erc20_tokens
+                            .get_alt(&r.erc20_contract_address)
+                            .map(|symbol| symbol.to_string()),
+                        r.withdrawal_amount.into(),
+                        r.l1_fee,
+                    ),
+                    Erc20Approve(_) | Swap(_) => return None,
+                };
+                Some(FinalizedWithdrawal {
+                    native_burn_index: request.native_ledger_burn_index().get(),
+                    token_symbol,
+                    destination: request.payee().to_string(),
+                    amount,
+                    transaction_hash: tx.transaction_hash().to_string(),
+                    effective_fee: tx.effective_transaction_fee().into(),
+                    l1_fee: l1_fee.map(|fee| fee.into()),
+                    finalized_at,
+                })
+            })
+            .collect()
+    })
+}
+
 #[update]
 async fn withdraw_erc20(
     WithdrawErc20Arg {
@@ -595,34 +1123,49 @@ async fn withdraw_erc20(
         recipient,
     }: WithdrawErc20Arg,
 ) -> Result<RetrieveErc20Request, WithdrawErc20Error> {
+    let _instrumentation = instrument("withdraw_erc20");
     let caller = validate_caller_not_anonymous();
+    validate_not_draining().map_err(WithdrawErc20Error::TemporarilyUnavailable)?;
+    validate_native_decimals().map_err(WithdrawErc20Error::TemporarilyUnavailable)?;
     let _guard = retrieve_withdraw_guard(caller).unwrap_or_else(|e| {
         ic_cdk::trap(format!(
             "Failed retrieving guard for principal {caller}: {e:?}"
         ))
     });
 
-    let destination = validate_address_as_destination(&recipient).map_err(|e| match e {
-        AddressValidationError::Invalid { .. } | AddressValidationError::NotSupported(_) => {
-            WithdrawErc20Error::InvalidDestination("Invalid destination entered".to_string())
-        }
-    })?;
+    let destination =
+        validate_address_as_destination(&recipient, &BTreeSet::new()).map_err(|e| match e {
+            AddressValidationError::Invalid { .. } | AddressValidationError::NotSupported(_) => {
+                WithdrawErc20Error::InvalidDestination("Invalid destination entered".to_string())
+            }
+            AddressValidationError::InvalidChecksum { .. }
+            | AddressValidationError::IsKnownErc20Contract(_) => {
+                WithdrawErc20Error::InvalidDestination(e.to_string())
+            }
+        })?;
+
+    if !storage::is_destination_allowed(&destination) {
+        return Err(WithdrawErc20Error::DestinationNotAllowed);
+    }
 
     let erc20_withdrawal_amount =
-        Erc20Value::try_from(amount).expect("ERROR: failed to convert Nat to u256");
+        Erc20Value::try_from(amount).map_err(|_| WithdrawErc20Error::AmountTooLarge {
+            max_withdrawal_amount: Erc20Value::MAX.into(),
+        })?;
 
     let erc20_token = read_state(|s| s.find_erc20_token_by_ledger_id(&erc20_ledger_id))
         .ok_or_else(|| {
-            let supported_erc20_tokens: BTreeSet<_> = read_state(|s| {
+            let supported_tokens = read_state(|s| {
                 s.supported_erc20_tokens()
                     .map(|token| token.into())
                     .collect()
             });
-            WithdrawErc20Error::TokenNotSupported {
-                supported_tokens: Vec::from_iter(supported_erc20_tokens),
-            }
+            WithdrawErc20Error::TokenNotSupported { supported_tokens }
         })?;
 
+    validate_destination_not_protected(&destination, Some(&erc20_token.erc20_contract_address))
+        .map_err(WithdrawErc20Error::InvalidDestination)?;
+
     let (withdrawal_native_fee, native_ledger, native_transfer_fee) = read_state(|s| {
         (
             s.withdrawal_native_fee,
@@ -634,12 +1177,10 @@ async fn withdraw_erc20(
     let erc20_tx_fee = estimate_erc20_transaction_fee().await.ok_or_else(|| {
         WithdrawErc20Error::TemporarilyUnavailable("Failed to retrieve current gas fee".to_string())
     })?;
+    let gas_limit = read_state(|s| s.gas_limit(GasLimitKind::Erc20Withdrawal));
 
     // Check if l1_fee is required for this network
-    let l1_fee = match read_state(|s| s.evm_network) {
-        EvmNetwork::Base => Some(DEFAULT_L1_BASE_GAS_FEE),
-        _ => None,
-    };
+    let l1_fee = read_state(State::l1_fee);
 
     let now = ic_cdk::api::time();
 
@@ -704,6 +1245,7 @@ async fn withdraw_erc20(
                         l1_fee,
                         is_wrapped_mint: Some(false),
                         withdrawal_fee: withdrawal_native_fee,
+                        gas_limit: Some(gas_limit),
                     };
                     log!(
                         INFO,
@@ -728,18 +1270,20 @@ async fn withdraw_erc20(
                         LedgerBurnError::TemporarilyUnavailable { .. } => native_burn_amount, //don't penalize user in case of an error outside of their control
                         LedgerBurnError::InsufficientFunds { .. }
                         | LedgerBurnError::AmountTooLow { .. }
-                        | LedgerBurnError::InsufficientAllowance { .. } => native_burn_amount
+                        | LedgerBurnError::InsufficientAllowance { .. }
+                        | LedgerBurnError::Duplicate { .. }
+                        | LedgerBurnError::TooOld { .. }
+                        | LedgerBurnError::CreatedInFuture { .. } => native_burn_amount
                             .checked_sub(native_transfer_fee)
                             .unwrap_or(Wei::ZERO),
                     };
 
                     if reimbursed_amount > Wei::ZERO {
-                        let reimbursement_request = ReimbursementRequest {
+                        let reimbursement_request = PendingReimbursementRequest {
                             ledger_burn_index: native_ledger_burn_index,
                             reimbursed_amount: reimbursed_amount.change_units(),
                             to: caller,
                             to_subaccount: None,
-                            transaction_hash: None,
                         };
                         mutate_state(|s| {
                             process_event(
@@ -769,22 +1313,37 @@ async fn wrap_icrc(
         amount,
         icrc_ledger_id,
         recipient,
+        max_native_fee,
     }: WrapIcrcArg,
 ) -> Result<RetrieveWrapIcrcRequest, WrapIcrcError> {
+    let _instrumentation = instrument("wrap_icrc");
     let caller = validate_caller_not_anonymous();
+    validate_not_draining().map_err(WrapIcrcError::TemporarilyUnavailable)?;
+    validate_native_decimals().map_err(WrapIcrcError::TemporarilyUnavailable)?;
     let _guard = retrieve_withdraw_guard(caller).unwrap_or_else(|e| {
         ic_cdk::trap(format!(
             "Failed retrieving guard for principal {caller}: {e:?}"
         ))
     });
 
-    let destination = validate_address_as_destination(&recipient).map_err(|e| match e {
-        AddressValidationError::Invalid { .. } | AddressValidationError::NotSupported(_) => {
-            WrapIcrcError::InvalidDestination("Invalid destination entered".to_string())
-        }
-    })?;
+    let destination =
+        validate_address_as_destination(&recipient, &BTreeSet::new()).map_err(|e| match e {
+            AddressValidationError::Invalid { .. } | AddressValidationError::NotSupported(_) => {
+                WrapIcrcError::InvalidDestination("Invalid destination entered".to_string())
+            }
+            AddressValidationError::InvalidChecksum { .. }
+            | AddressValidationError::IsKnownErc20Contract(_) => {
+                WrapIcrcError::InvalidDestination(e.to_string())
+            }
+        })?;
 
-    let lock_amount = Erc20Value::try_from(amount).expect("ERROR: failed to convert Nat to u256");
+    if !storage::is_destination_allowed(&destination) {
+        return Err(WrapIcrcError::DestinationNotAllowed);
+    }
+
+    let lock_amount = Erc20Value::try_from(amount).map_err(|_| WrapIcrcError::AmountTooLarge {
+        max_wrap_amount: Erc20Value::MAX.into(),
+    })?;
 
     let erc20_token = read_state(|s| s.find_wrapped_erc20_token_by_icrc_ledger_id(&icrc_ledger_id))
         .ok_or_else(|| {
@@ -801,6 +1360,9 @@ async fn wrap_icrc(
             }
         })?;
 
+    validate_destination_not_protected(&destination, Some(&erc20_token))
+        .map_err(WrapIcrcError::InvalidDestination)?;
+
     let (withdrawal_native_fee, native_ledger, native_transfer_fee) = read_state(|s| {
         (
             s.withdrawal_native_fee,
@@ -812,12 +1374,10 @@ async fn wrap_icrc(
     let erc20_tx_fee = estimate_icrc_wrap_transaction_fee().await.ok_or_else(|| {
         WrapIcrcError::TemporarilyUnavailable("Failed to retrieve current gas fee".to_string())
     })?;
+    let gas_limit = read_state(|s| s.gas_limit(GasLimitKind::WrappedIcrcMint));
 
     // Check if l1_fee is required for this network
-    let l1_fee = match read_state(|s| s.evm_network) {
-        EvmNetwork::Base => Some(DEFAULT_L1_BASE_GAS_FEE),
-        _ => None,
-    };
+    let l1_fee = read_state(State::l1_fee);
 
     let now = ic_cdk::api::time();
 
@@ -829,6 +1389,18 @@ async fn wrap_icrc(
         .checked_add(withdrawal_native_fee.unwrap_or(Wei::ZERO))
         .unwrap_or(Wei::MAX);
 
+    // The maximum must be checked before the native burn below: aborting after it would require
+    // reimbursing the burnt fees.
+    if let Some(maximum) = max_native_fee {
+        let native_fee = Nat::from(native_burn_amount);
+        if native_fee > maximum {
+            return Err(WrapIcrcError::NativeFeeAboveMaximum {
+                native_fee,
+                maximum,
+            });
+        }
+    }
+
     let icrc_ledger_client = LedgerClient::icrc_ledger(icrc_ledger_id);
 
     log!(INFO, "[wrap_icrc]: burning {:?} native", native_burn_amount);
@@ -873,6 +1445,7 @@ async fn wrap_icrc(
                         l1_fee,
                         is_wrapped_mint: Some(true),
                         withdrawal_fee: withdrawal_native_fee,
+                        gas_limit: Some(gas_limit),
                     };
                     log!(
                         INFO,
@@ -897,18 +1470,20 @@ async fn wrap_icrc(
                         LedgerBurnError::TemporarilyUnavailable { .. } => native_burn_amount, //don't penalize user in case of an error outside of their control
                         LedgerBurnError::InsufficientFunds { .. }
                         | LedgerBurnError::AmountTooLow { .. }
-                        | LedgerBurnError::InsufficientAllowance { .. } => native_burn_amount
+                        | LedgerBurnError::InsufficientAllowance { .. }
+                        | LedgerBurnError::Duplicate { .. }
+                        | LedgerBurnError::TooOld { .. }
+                        | LedgerBurnError::CreatedInFuture { .. } => native_burn_amount
                             .checked_sub(native_transfer_fee)
                             .unwrap_or(Wei::ZERO),
                     };
 
                     if reimbursed_amount > Wei::ZERO {
-                        let reimbursement_request = ReimbursementRequest {
+                        let reimbursement_request = PendingReimbursementRequest {
                             ledger_burn_index: native_ledger_burn_index,
                             reimbursed_amount: reimbursed_amount.change_units(),
                             to: caller,
                             to_subaccount: None,
-                            transaction_hash: None,
                         };
                         mutate_state(|s| {
                             process_event(
@@ -932,22 +1507,18 @@ async fn wrap_icrc(
 }
 
 #[update]
-async fn activate_swap_feature(
-    ActivateSwapReqest {
-        twin_usdc_ledger_id,
-        swap_contract_address,
-        twin_usdc_decimals,
-        dex_canister_id,
-        canister_signing_fee_twin_usdc_value,
-    }: ActivateSwapReqest,
-) -> Nat {
+async fn activate_swap_feature(request: ActivateSwapReqest) -> Result<Nat, ActivateSwapError> {
+    let _instrumentation = instrument("activate_swap_feature");
     let caller = validate_caller_not_anonymous();
     if caller != Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap() {
         panic!("ONLY appic controller can activate swap_feature");
     }
 
-    let erc20_token = read_state(|s| s.find_erc20_token_by_ledger_id(&twin_usdc_ledger_id))
-        .expect("could not find icUSDC tokens with provided principal");
+    let SwapActivation {
+        swap_contract_address,
+        twin_usdc_token: erc20_token,
+        canister_signing_fee_twin_usdc_value,
+    } = read_state(|s| validate_swap_activation(s, &request))?;
 
     let (withdrawal_native_fee, native_ledger) = read_state(|s| {
         (
@@ -958,20 +1529,10 @@ async fn activate_swap_feature(
 
     let tx_fee = estimate_usdc_approval_fee()
         .await
-        .expect("Failed to retrieve current gas fee");
+        .ok_or(ActivateSwapError::FeeEstimateUnavailable)?;
 
     // Check if l1_fee is required for this network
-    let l1_fee = match read_state(|s| s.evm_network) {
-        EvmNetwork::Base => Some(DEFAULT_L1_BASE_GAS_FEE),
-        _ => None,
-    };
-
-    let swap_contract_address =
-        Address::from_str(&swap_contract_address).expect("Invalid swap contract address");
-
-    let canister_signing_fee_twin_usdc_value =
-        Erc20Value::try_from(canister_signing_fee_twin_usdc_value)
-            .expect("Invalid canister signing fee twin usdc value");
+    let l1_fee = read_state(State::l1_fee);
 
     let now = ic_cdk::api::time();
 
@@ -985,70 +1546,167 @@ async fn activate_swap_feature(
 
     log!(
         INFO,
-        "[withdraw_erc20]: burning {:?} native",
+        "[activate_swap_feature]: burning {:?} native",
         native_burn_amount
     );
+
+    let native_ledger_burn_index = native_ledger
+        .burn_from(
+            caller.into(),
+            native_burn_amount,
+            BurnMemo::Erc20GasFee {
+                erc20_token_symbol: erc20_token.erc20_token_symbol.clone(),
+                erc20_withdrawal_amount: Erc20Value::ZERO,
+                to_address: Address::ZERO,
+            },
+            None,
+        )
+        .await
+        .map_err(|native_burn_error| {
+            log!(
+                INFO,
+                "[activate_swap_feature]: failed to burn native token to cover transaction fee: {native_burn_error:?}"
+            );
+            ActivateSwapError::NativeLedgerError {
+                error: native_burn_error.into(),
+            }
+        })?;
+
+    let approval_request = Erc20Approve {
+        max_transaction_fee: tx_fee,
+        swap_contract_address,
+        native_ledger_burn_index,
+        erc20_contract_address: erc20_token.erc20_contract_address,
+        from: caller,
+        from_subaccount: None,
+        created_at: now,
+        l1_fee,
+        withdrawal_fee: withdrawal_native_fee,
+        is_revocation: None,
+    };
+
+    println!("successfully burnt for maximum approval to the swap contract");
+
+    // Swapping is only activated once the fees for the approval transaction are paid,
+    // so that a failed burn does not leave the feature half-activated.
     mutate_state(|s| {
         process_event(
             s,
             EventType::SwapContractActivated {
                 swap_contract_address,
                 usdc_contract_address: erc20_token.erc20_contract_address,
-                twin_usdc_ledger_id,
-                twin_usdc_decimals,
-                dex_canister_id,
+                twin_usdc_ledger_id: request.twin_usdc_ledger_id,
+                twin_usdc_decimals: request.twin_usdc_decimals,
+                dex_canister_id: request.dex_canister_id,
                 canister_signing_fee_twin_usdc_value,
             },
         );
+        process_event(
+            s,
+            EventType::AcceptedSwapActivationRequest(approval_request),
+        );
     });
 
-    match native_ledger
+    ic_cdk_timers::set_timer(Duration::from_secs(0), || {
+        ic_cdk::futures::spawn_017_compat(process_retrieve_tokens_requests())
+    });
+
+    Ok(native_ledger_burn_index.get().into())
+}
+
+// Revokes the approval given by the ERC-20 token `token` to `spender` by sending an approve
+// transaction with a zero allowance. Swapping is deactivated right away if `spender` is the
+// active swap contract. Returns the native ledger burn index paying for the transaction.
+#[update]
+async fn revoke_approval(token: String, spender: String) -> Result<Nat, RevokeApprovalError> {
+    let _instrumentation = instrument("revoke_approval");
+    let caller = validate_caller_not_anonymous();
+    if caller != Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap() {
+        panic!("Only appic controller can revoke approvals");
+    }
+
+    let ApprovalRevocation {
+        erc20_contract_address,
+        spender,
+        erc20_token_symbol,
+    } = read_state(|s| validate_approval_revocation(s, &token, &spender))?;
+
+    let (withdrawal_native_fee, native_ledger) = read_state(|s| {
+        (
+            s.withdrawal_native_fee,
+            LedgerClient::native_ledger_from_state(s),
+        )
+    });
+
+    let tx_fee = estimate_usdc_approval_fee()
+        .await
+        .ok_or(RevokeApprovalError::FeeEstimateUnavailable)?;
+    let l1_fee = read_state(State::l1_fee);
+    let now = ic_cdk::api::time();
+
+    let native_burn_amount = tx_fee
+        .checked_add(l1_fee.unwrap_or(Wei::ZERO))
+        .expect("Bug: Tx_fee plus l1_fee should fit in u256")
+        .checked_add(withdrawal_native_fee.unwrap_or(Wei::ZERO))
+        .unwrap_or(Wei::MAX);
+
+    let native_ledger_burn_index = native_ledger
         .burn_from(
             caller.into(),
             native_burn_amount,
             BurnMemo::Erc20GasFee {
-                erc20_token_symbol: erc20_token.erc20_token_symbol.clone(),
+                erc20_token_symbol,
                 erc20_withdrawal_amount: Erc20Value::ZERO,
-                to_address: Address::ZERO,
+                to_address: spender,
             },
             None,
         )
         .await
-    {
-        Ok(native_ledger_burn_index) => {
-            let approval_request = Erc20Approve {
-                max_transaction_fee: tx_fee,
-                swap_contract_address,
-                native_ledger_burn_index,
-                erc20_contract_address: erc20_token.erc20_contract_address,
-                from: caller,
-                from_subaccount: None,
-                created_at: now,
-                l1_fee,
-                withdrawal_fee: withdrawal_native_fee,
-            };
+        .map_err(|native_burn_error| {
+            log!(
+                INFO,
+                "[revoke_approval]: failed to burn native token to cover transaction fee: {native_burn_error:?}"
+            );
+            RevokeApprovalError::NativeLedgerError {
+                error: native_burn_error.into(),
+            }
+        })?;
 
-            println!("successfully burnt for maximum approval to the swap contract");
+    let revocation_request = Erc20Approve {
+        max_transaction_fee: tx_fee,
+        erc20_contract_address,
+        swap_contract_address: spender,
+        native_ledger_burn_index,
+        from: caller,
+        from_subaccount: None,
+        created_at: now,
+        l1_fee,
+        withdrawal_fee: withdrawal_native_fee,
+        is_revocation: Some(true),
+    };
 
-            mutate_state(|s| {
-                process_event(
-                    s,
-                    EventType::AcceptedSwapActivationRequest(approval_request.clone()),
-                );
-            });
+    log!(
+        INFO,
+        "[revoke_approval]: revoking approval of {erc20_contract_address} to {spender}"
+    );
 
-            ic_cdk_timers::set_timer(Duration::from_secs(0), || {
-                ic_cdk::futures::spawn_017_compat(process_retrieve_tokens_requests())
-            });
+    mutate_state(|s| {
+        process_event(
+            s,
+            EventType::AcceptedApprovalRevocationRequest(revocation_request),
+        )
+    });
 
-            native_ledger_burn_index.get().into()
-        }
-        Err(_native_burn_error) => panic!("Failed to burn native token to cover transaction fee"),
-    }
+    ic_cdk_timers::set_timer(Duration::from_secs(0), || {
+        ic_cdk::futures::spawn_017_compat(process_retrieve_tokens_requests())
+    });
+
+    Ok(native_ledger_burn_index.get().into())
 }
 
 #[update]
 async fn add_erc20_token(erc20_token: AddErc20Token) {
+    let _instrumentation = instrument("add_erc20_token");
     let orchestrator_id = read_state(|s| s.ledger_suite_manager_id)
         .unwrap_or_else(|| ic_cdk::trap("ERROR: ERC-20 feature is not activated"));
     if orchestrator_id != ic_cdk::api::msg_caller() {
@@ -1056,31 +1714,137 @@ async fn add_erc20_token(erc20_token: AddErc20Token) {
             "ERROR: only the orchestrator {orchestrator_id} can add ERC-20 tokens"
         ));
     }
-    let erc20_token =
-        ERC20Token::try_from(erc20_token).unwrap_or_else(|e| ic_cdk::trap(format!("ERROR: {e}")));
+    let symbol_collision_policy = erc20_token.symbol_collision_policy.unwrap_or_default();
+    let erc20_token = ERC20Token::try_from(erc20_token)
+        .and_then(|token| {
+            read_state(|s| s.resolve_erc20_token_symbol_collision(token, symbol_collision_policy))
+        })
+        .unwrap_or_else(|e| ic_cdk::trap(format!("ERROR: {e}")));
     mutate_state(|s| process_event(s, EventType::AddedErc20Token(erc20_token)));
 }
 
-// Only the swap canister can call this function to make the process of swapping faster
-#[update]
-async fn check_new_deposits() {
-    let swap_canister_id = read_state(|s| s.dex_canister_id)
-        .unwrap_or_else(|| ic_cdk::trap("ERROR: swap feature not activated"));
-    if swap_canister_id != ic_cdk::api::msg_caller() {
-        ic_cdk::trap(format!(
-            "ERROR: only the swap canister id {swap_canister_id} can add request for early deposit check"
-        ));
+// Triggers an early check for new deposits instead of waiting for the next scraping interval,
+// e.g. used by the swap canister to make the process of swapping faster.
+// Each principal can call this function at most once every `MANUAL_DEPOSIT_CHECK_INTERVAL`,
+// and all principals together at most once every `MANUAL_DEPOSIT_CHECK_GLOBAL_INTERVAL`.
+// The swap canister is exempt from both limits and the call only returns once the logs
+// were scraped, for other callers the scraping is scheduled and runs after the call returned.
+#[update]
+async fn check_new_deposits() -> Result<(), RequestScrapingError> {
+    let _instrumentation = instrument("check_new_deposits");
+    let caller = validate_caller_not_anonymous();
+    if read_state(|s| s.is_dex_canister(&caller)) {
+        scrape_logs().await;
+        return Ok(());
+    }
+    let now = ic_cdk::api::time();
+    if !read_state(|s| s.can_trigger_manual_deposit_check(&caller, now)) {
+        return Err(RequestScrapingError::CalledTooManyTimes);
+    }
+    mutate_state(|s| s.record_manual_deposit_check(caller, now));
+
+    ic_cdk_timers::set_timer(Duration::from_secs(0), || {
+        ic_cdk::futures::spawn_017_compat(scrape_logs())
+    });
+
+    Ok(())
+}
+
+/// Returns the current fees in twin USDC of a swap with the given gas limit, so that the
+/// appic DEX canister can size orders above them. Uses the last gas fee estimate, which is
+/// refreshed by every withdrawal and dex order.
+#[query]
+fn get_swap_fee_quote(gas_limit: Nat) -> Result<SwapFeeQuote, DexOrderError> {
+    let gas_limit = GasAmount::try_from(gas_limit).map_err(DexOrderError::InvalidGasLimit)?;
+    read_state(|s| {
+        let unavailable = |reason: &str| DexOrderError::TemporarilyUnavailable(reason.to_string());
+        if !s.is_swapping_active() {
+            return Err(unavailable("Swapping is not active"));
+        }
+        let (twin_usdc_info, native_token_usd_price, signing_fee) = match (
+            s.twin_usdc_info.as_ref(),
+            s.last_native_token_usd_price_estimate,
+            s.canister_signing_fee_twin_usdc_amount,
+        ) {
+            (Some(info), Some(price), Some(fee)) => (info, price, fee),
+            _ => return Err(unavailable("Swapping is not configured")),
+        };
+        let (estimate_timestamp, gas_fee_estimate) = s
+            .last_transaction_price_estimate
+            .clone()
+            .ok_or_else(|| unavailable("No gas fee estimate available yet"))?;
+        let max_transaction_fee = dex_order_fee(gas_fee_estimate, gas_limit)
+            .checked_add(s.l1_fee().unwrap_or(Wei::ZERO))
+            .expect("Bug: Tx_fee plus l1_fee should fit in u256");
+        swap_fee_quote(
+            twin_usdc_info,
+            native_token_usd_price,
+            signing_fee,
+            (estimate_timestamp, max_transaction_fee),
+            s.gas_tank.native_balance,
+            ic_cdk::api::time(),
+        )
+    })
+}
+
+/// Dry run of `dex_order` allowing the appic DEX canister to pre-validate an order
+/// before committing its own state. Neither the gas tank nor the minter state is modified.
+#[update]
+async fn validate_dex_order(args: DexOrderArgs) -> Result<DexOrderQuote, DexOrderError> {
+    let _instrumentation = instrument("validate_dex_order");
+    validate_not_draining().map_err(DexOrderError::TemporarilyUnavailable)?;
+    let args = args.validate(ic_cdk::api::time() / 1_000_000_000)?;
+
+    let (
+        is_swapping_active,
+        twin_usdc_info,
+        dex_canister_id,
+        last_native_token_usd_price_estimate,
+        canister_signing_fee_twin_usdc_amount,
+        evm_network,
+    ) = read_state(|s| {
+        (
+            s.is_swapping_active(),
+            s.twin_usdc_info.clone(),
+            s.dex_canister_id,
+            s.last_native_token_usd_price_estimate,
+            s.canister_signing_fee_twin_usdc_amount,
+            s.evm_network,
+        )
+    });
+
+    if !is_swapping_active {
+        panic!("SWAPPING NOT ACTIVE");
+    }
+
+    let twin_usdc_info =
+        twin_usdc_info.expect("BUG: twin USDC info should be available if swapping is active");
+    let dex_canister_id =
+        dex_canister_id.expect("BUG: DEX canister ID should be available if swapping is active");
+    let last_native_token_usd_price_estimate = last_native_token_usd_price_estimate
+        .expect("BUG: native token USD price should be available if swapping is active");
+    let canister_signing_fee_twin_usdc_amount = canister_signing_fee_twin_usdc_amount.expect(
+        "BUG: canister signing fee twin USDC amount should be available if swapping is active",
+    );
+
+    if dex_canister_id != ic_cdk::api::msg_caller() {
+        panic!("Only appic DEX canister is authorized to call this function");
     }
-    scrape_logs().await;
+
+    quote_dex_order(
+        &args,
+        &twin_usdc_info,
+        last_native_token_usd_price_estimate.1,
+        canister_signing_fee_twin_usdc_amount,
+        evm_network,
+    )
+    .await
 }
 
 #[update]
 async fn dex_order(args: DexOrderArgs) -> Result<(), DexOrderError> {
-    log!(
-        INFO,
-        "[dex_order]: Starting dex order processing for tx_id: {:?}",
-        args.tx_id
-    );
+    let _instrumentation = instrument("dex_order");
+    validate_not_draining().map_err(DexOrderError::TemporarilyUnavailable)?;
 
     let (
         is_swapping_active,
@@ -1092,7 +1856,7 @@ async fn dex_order(args: DexOrderArgs) -> Result<(), DexOrderError> {
         evm_network,
     ) = read_state(|s| {
         (
-            s.is_swapping_active,
+            s.is_swapping_active(),
             s.twin_usdc_info.clone(),
             s.dex_canister_id,
             s.last_native_token_usd_price_estimate,
@@ -1126,28 +1890,68 @@ async fn dex_order(args: DexOrderArgs) -> Result<(), DexOrderError> {
         panic!("Only appic DEX canister is authorized to call this function");
     }
 
+    // The twin USDC of the order was already burned: it is refunded whenever the fields
+    // needed for the refund are valid, even if the order itself is invalid.
+    let refundable_args = match RefundableDexOrderArgs::try_from(args.clone()) {
+        Ok(refundable_args) => refundable_args,
+        Err(err) => {
+            log!(
+                INFO,
+                "[dex_order]: Rejecting dex order that cannot be refunded: {:?}",
+                err
+            );
+            return Err(err);
+        }
+    };
+
     log!(
         INFO,
-        "[dex_order]: Building swap request for tx_id: {:?}",
-        args.tx_id
+        "[dex_order]: Starting dex order processing for tx_id: {:?}",
+        refundable_args.tx_id()
     );
-    let swap_request_result = build_dex_swap_request(
-        &args,
-        &twin_usdc_info,
-        last_native_token_usd_price_estimate.1,
-        canister_signing_fee_twin_usdc_amount,
-        swap_contract_address,
-        evm_network,
-        dex_canister_id,
-    )
-    .await;
+
+    let (swap_request_result, quarantined_args) =
+        match args.validate(ic_cdk::api::time() / 1_000_000_000) {
+            Ok(args) => {
+                log!(
+                    INFO,
+                    "[dex_order]: Building swap request for tx_id: {:?}",
+                    args.tx_id()
+                );
+                let swap_request_result = build_dex_swap_request(
+                    &args,
+                    &twin_usdc_info,
+                    last_native_token_usd_price_estimate.1,
+                    canister_signing_fee_twin_usdc_amount,
+                    swap_contract_address,
+                    evm_network,
+                    dex_canister_id,
+                )
+                .await;
+                (swap_request_result, args.args)
+            }
+            Err(err) => {
+                log!(
+                    INFO,
+                    "[dex_order]: Invalid arguments for tx_id: {:?}: {:?}",
+                    refundable_args.tx_id(),
+                    err
+                );
+                // Only the fields needed for the refund are kept, since the others may
+                // exceed their bounds.
+                (
+                    Err(err),
+                    refundable_args.args.clone().without_swap_details(),
+                )
+            }
+        };
 
     let result = match swap_request_result {
         Ok(swap_request) => {
             log!(
                 INFO,
                 "[dex_order]: Successfully built swap request for tx_id: {:?}, with request {:?}",
-                args.tx_id,
+                refundable_args.tx_id(),
                 swap_request
             );
             mutate_state(|s| process_event(s, EventType::AcceptedSwapRequest(swap_request)));
@@ -1157,21 +1961,21 @@ async fn dex_order(args: DexOrderArgs) -> Result<(), DexOrderError> {
             log!(
                 DEBUG,
                 "[dex_order]: Quarantining dex order for tx_id: {:?} due to error: {:?}",
-                args.tx_id,
+                refundable_args.tx_id(),
                 err
             );
-            mutate_state(|s| process_event(s, EventType::QuarantinedDexOrder(args.clone())));
+            mutate_state(|s| process_event(s, EventType::QuarantinedDexOrder(quarantined_args)));
             Err(err)
         }
         Err(err) => {
             log!(
                 INFO,
                 "[dex_order]: Failed to build swap request for tx_id: {:?} for reason {:?}",
-                args.tx_id,
+                refundable_args.tx_id(),
                 err
             );
             match build_dex_swap_refund_request(
-                &args,
+                &refundable_args,
                 &twin_usdc_info,
                 last_native_token_usd_price_estimate.1,
                 canister_signing_fee_twin_usdc_amount,
@@ -1185,7 +1989,7 @@ async fn dex_order(args: DexOrderArgs) -> Result<(), DexOrderError> {
                     log!(
                         INFO,
                         "[dex_order]: Successfully built refund request for tx_id: {:?} {:?}",
-                        args.tx_id,
+                        refundable_args.tx_id(),
                         refund_swap_request
                     );
                     mutate_state(|s| {
@@ -1197,11 +2001,11 @@ async fn dex_order(args: DexOrderArgs) -> Result<(), DexOrderError> {
                     log!(
                         DEBUG,
                         "[dex_order]: Failed to build refund request for tx_id: {:?}, with refund error {:?}",
-                        args.tx_id,
+                        refundable_args.tx_id(),
                         refund_err
                     );
                     mutate_state(|s| {
-                        process_event(s, EventType::QuarantinedDexOrder(args.clone()))
+                        process_event(s, EventType::QuarantinedDexOrder(quarantined_args))
                     });
                     Err(refund_err)
                 }
@@ -1212,7 +2016,7 @@ async fn dex_order(args: DexOrderArgs) -> Result<(), DexOrderError> {
     log!(
         INFO,
         "[dex_order]: Scheduling retrieve tokens for tx_id: {:?}",
-        args.tx_id
+        refundable_args.tx_id()
     );
     ic_cdk_timers::set_timer(Duration::from_secs(0), || {
         ic_cdk::futures::spawn_017_compat(process_retrieve_tokens_requests())
@@ -1221,10 +2025,76 @@ async fn dex_order(args: DexOrderArgs) -> Result<(), DexOrderError> {
     result
 }
 
+/// Returns the instructions executed by every update endpoint and timer-driven task
+/// since the last call to `reset_instrumentation`, how far behind the chain the
+/// observed and scraped blocks are, and which timer-driven tasks are currently running.
+#[query]
+fn get_health() -> MinterHealth {
+    let now = ic_cdk::api::time();
+    read_state(|s| MinterHealth {
+        active_tasks: s
+            .active_tasks
+            .iter()
+            .map(|task| format!("{task:?}"))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect(),
+        endpoints: instrumentation::endpoint_stats()
+            .iter()
+            .map(|(endpoint, stats)| EndpointInstrumentation::new(endpoint, stats))
+            .collect(),
+        last_observed_block_timestamp: s.last_observed_block_timestamp.map(|t| t.into()),
+        last_scraped_block_timestamp: s.last_scraped_block_timestamp().map(|t| t.into()),
+        last_observed_block_lag_seconds: s.last_observed_block_lag_seconds(now),
+        last_scraped_block_lag_seconds: s.last_scraped_block_lag_seconds(now),
+        scraping_gap_count: s.scraping_gaps.len() as u64,
+        invariant_violations: s.validate_invariants().err(),
+    })
+}
+
+// Returns the latency percentiles and call counts of every provider queried since the last upgrade.
+#[query]
+fn get_provider_stats() -> Vec<ProviderStat> {
+    let now = ic_cdk::api::time();
+    let evm_network = read_state(|s| s.evm_network);
+    let mut latencies: BTreeMap<Provider, LatencyStats> = read_latency(ProviderLatency::stats)
+        .into_iter()
+        .filter_map(|(service, stats)| Some((provider_of(evm_network, &service)?, stats)))
+        .collect();
+    read_state(|s| {
+        let rate_limited_until_ns = |provider: &Provider| {
+            s.rate_limited_providers
+                .get(provider)
+                .copied()
+                .filter(|retry_after_ns| *retry_after_ns > now)
+        };
+        for provider in s.rate_limited_providers.keys() {
+            if rate_limited_until_ns(provider).is_some() {
+                latencies.entry(*provider).or_default();
+            }
+        }
+        latencies
+            .iter()
+            .map(|(provider, stats)| {
+                ProviderStat::new(provider, stats, rate_limited_until_ns(provider))
+            })
+            .collect()
+    })
+}
+
+#[update]
+fn reset_instrumentation() {
+    let caller = validate_caller_not_anonymous();
+    if caller != Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap() {
+        panic!("Only appic controller can reset the instrumentation");
+    }
+    instrumentation::reset();
+}
+
 #[query]
 fn get_events(arg: GetEventsArg) -> GetEventsResult {
     use evm_minter::candid_types::events::{
-        AccessListItem, ReimbursementIndex as CandidReimbursementIndex,
+        AccessListItem, ProviderResponseHash, ReimbursementIndex as CandidReimbursementIndex,
         TransactionReceipt as CandidTransactionReceipt,
         TransactionStatus as CandidTransactionStatus, UnsignedTransaction,
     };
@@ -1347,6 +2217,7 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                     principal,
                     erc20_contract_address,
                     subaccount,
+                    original_owner,
                 }) => EP::AcceptedErc20Deposit {
                     transaction_hash: transaction_hash.to_string(),
                     block_number: block_number.into(),
@@ -1356,6 +2227,7 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                     principal,
                     erc20_contract_address: erc20_contract_address.to_string(),
                     subaccount: subaccount.map(|s| s.to_bytes()),
+                    original_owner: original_owner.map(|address| address.to_string()),
                 },
                 EventType::InvalidDeposit {
                     event_source,
@@ -1383,6 +2255,7 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                     created_at,
                     l1_fee,
                     withdrawal_fee,
+                    is_contract_destination,
                 }) => EP::AcceptedNativeWithdrawalRequest {
                     withdrawal_amount: withdrawal_amount.into(),
                     destination: destination.to_string(),
@@ -1392,6 +2265,7 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                     created_at,
                     l1_fee: l1_fee.map(|fee| fee.into()),
                     withdrawal_fee: withdrawal_fee.map(|fee| fee.into()),
+                    is_contract_destination,
                 },
                 EventType::CreatedTransaction {
                     withdrawal_id,
@@ -1454,6 +2328,11 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                     address: token.erc20_contract_address.to_string(),
                     erc20_token_symbol: token.erc20_token_symbol.to_string(),
                     erc20_ledger_id: token.erc20_ledger_id,
+                    transfer_kind: token.transfer_kind,
+                    // the symbol was only disambiguated if the original one is recorded
+                    symbol_collision_policy: token
+                        .original_symbol
+                        .map(|_| candid_types::SymbolCollisionPolicy::Disambiguate),
                 },
                 EventType::AcceptedErc20WithdrawalRequest(Erc20WithdrawalRequest {
                     max_transaction_fee,
@@ -1469,6 +2348,7 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                     l1_fee,
                     withdrawal_fee,
                     is_wrapped_mint,
+                    gas_limit,
                 }) => EP::AcceptedErc20WithdrawalRequest {
                     max_transaction_fee: max_transaction_fee.into(),
                     withdrawal_amount: withdrawal_amount.into(),
@@ -1483,6 +2363,7 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                     l1_fee: l1_fee.map(|fee| fee.into()),
                     withdrawal_fee: withdrawal_fee.map(|fee| fee.into()),
                     is_wrapped_mint: is_wrapped_mint.unwrap_or_default(),
+                    gas_limit: gas_limit.map(|gas_limit| gas_limit.into()),
                 },
                 EventType::MintedErc20 {
                     event_source,
@@ -1495,12 +2376,11 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                     erc20_token_symbol,
                     erc20_contract_address: erc20_contract_address.to_string(),
                 },
-                EventType::FailedErc20WithdrawalRequest(ReimbursementRequest {
+                EventType::FailedErc20WithdrawalRequest(PendingReimbursementRequest {
                     ledger_burn_index,
                     reimbursed_amount,
                     to,
                     to_subaccount,
-                    transaction_hash: _,
                 }) => EP::FailedErc20WithdrawalRequest {
                     withdrawal_id: ledger_burn_index.get().into(),
                     reimbursed_amount: reimbursed_amount.into(),
@@ -1571,12 +2451,11 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                     release_block_index: release_block_index.get().into(),
                     transfer_fee: transfer_fee.into(),
                 },
-                EventType::FailedIcrcLockRequest(ReimbursementRequest {
+                EventType::FailedIcrcLockRequest(PendingReimbursementRequest {
                     ledger_burn_index,
                     reimbursed_amount,
                     to,
                     to_subaccount,
-                    transaction_hash: _,
                 }) => EP::FailedIcrcLockRequest {
                     withdrawal_id: ledger_burn_index.get().into(),
                     reimbursed_amount: reimbursed_amount.into(),
@@ -1767,12 +2646,236 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                     usdc_withdrawn: usdc_withdrawn.into(),
                     native_deposited: native_deposited.into(),
                 },
+                EventType::RemovedLog { event_source } => EP::RemovedLog {
+                    event_source: map_event_source(event_source),
+                },
+                EventType::DepositReorged { event_source } => EP::DepositReorged {
+                    event_source: map_event_source(event_source),
+                },
+                EventType::UpgradeGraceScrape {
+                    start_block,
+                    end_block,
+                } => EP::UpgradeGraceScrape {
+                    start_block: start_block.into(),
+                    end_block: end_block.into(),
+                },
+                EventType::UpdatedAddressList {
+                    list,
+                    action,
+                    addresses,
+                } => EP::UpdatedAddressList {
+                    list,
+                    action,
+                    addresses: addresses.iter().map(|a| a.to_string()).collect(),
+                },
+                EventType::RescrapedBlockRange {
+                    from_block,
+                    to_block,
+                } => EP::RescrapedBlockRange {
+                    from_block: from_block.into(),
+                    to_block: to_block.into(),
+                },
+                EventType::SkippedBlockRange {
+                    from_block,
+                    to_block,
+                } => EP::SkippedBlockRange {
+                    from_block: from_block.into(),
+                    to_block: to_block.into(),
+                },
+                EventType::ChangedConfirmationPolicy { from, to } => {
+                    EP::ChangedConfirmationPolicy { from, to }
+                }
+                EventType::ParkedTransaction {
+                    withdrawal_id,
+                    cancellation,
+                } => EP::ParkedTransaction {
+                    withdrawal_id: withdrawal_id.get().into(),
+                    cancellation: map_unsigned_transaction(cancellation),
+                },
+                EventType::ProviderDisagreement {
+                    method,
+                    block_range_or_hash,
+                    per_provider_hash,
+                    timestamp,
+                } => EP::ProviderDisagreement {
+                    method,
+                    block_range_or_hash,
+                    per_provider_hash: per_provider_hash
+                        .into_iter()
+                        .map(|(provider, hash)| ProviderResponseHash {
+                            provider,
+                            hash: hash.to_string(),
+                        })
+                        .collect(),
+                    timestamp,
+                },
+                EventType::ArchivedEvents {
+                    from_index,
+                    up_to_index,
+                    hash,
+                } => EP::ArchivedEvents {
+                    from_index,
+                    up_to_index,
+                    hash: hash.to_string(),
+                },
+                EventType::ProviderRateLimited {
+                    provider,
+                    retry_after_ns,
+                } => EP::ProviderRateLimited {
+                    provider: format!("{provider:?}"),
+                    retry_after_ns,
+                },
+                EventType::CancelledWithdrawalRequest(PendingReimbursementRequest {
+                    ledger_burn_index,
+                    reimbursed_amount,
+                    to,
+                    to_subaccount,
+                }) => EP::CancelledWithdrawalRequest {
+                    withdrawal_id: ledger_burn_index.get().into(),
+                    reimbursed_amount: reimbursed_amount.into(),
+                    to,
+                    to_subaccount: to_subaccount.map(|s| s.0),
+                },
+                EventType::EventLogCompacted {
+                    pruned_count,
+                    threshold_block,
+                } => EP::EventLogCompacted {
+                    pruned_count,
+                    threshold_block: threshold_block.into(),
+                },
+                EventType::UpgradedWhileNotDrained { in_flight_count } => {
+                    EP::UpgradedWhileNotDrained { in_flight_count }
+                }
+                EventType::NativeLedgerMetadataMismatch(metadata) => {
+                    EP::NativeLedgerMetadataMismatch {
+                        symbol: metadata.symbol,
+                        decimals: metadata.decimals,
+                    }
+                }
+                EventType::AcceptedApprovalRevocationRequest(request) => {
+                    EP::AcceptedApprovalRevocationRequest {
+                        erc20_contract_address: request.erc20_contract_address.to_string(),
+                        spender: request.swap_contract_address.to_string(),
+                        native_ledger_burn_index: request.native_ledger_burn_index.get().into(),
+                    }
+                }
+                EventType::BackfillCompleted {
+                    from_block,
+                    to_block,
+                    accepted_events,
+                    invalid_events,
+                    duplicate_events,
+                } => EP::BackfillCompleted {
+                    from_block: from_block.into(),
+                    to_block: to_block.into(),
+                    accepted_events,
+                    invalid_events,
+                    duplicate_events,
+                },
+                EventType::RealizedSwapAmountOut {
+                    withdrawal_id,
+                    amount_out,
+                } => EP::RealizedSwapAmountOut {
+                    withdrawal_id: withdrawal_id.get().into(),
+                    amount_out: amount_out.into(),
+                },
+                EventType::FeatureFlagChanged { flag, enabled } => EP::FeatureFlagChanged {
+                    flag: flag.to_string(),
+                    enabled,
+                },
+                EventType::FeeCollected {
+                    ledger_id,
+                    amount,
+                    block_index,
+                } => EP::FeeCollected {
+                    ledger_id,
+                    amount: amount.into(),
+                    block_index: block_index.get().into(),
+                },
+                EventType::CollectedFeesWithdrawn {
+                    ledger_id,
+                    to,
+                    amount,
+                    block_index,
+                } => EP::CollectedFeesWithdrawn {
+                    ledger_id,
+                    to,
+                    amount: amount.into(),
+                    block_index: block_index.into(),
+                },
+                EventType::FeeCapExceeded {
+                    withdrawal_id,
+                    max_fee_per_gas,
+                    max_transaction_fee,
+                    cap,
+                    timestamp,
+                } => EP::FeeCapExceeded {
+                    withdrawal_id: withdrawal_id.get().into(),
+                    max_fee_per_gas: max_fee_per_gas.into(),
+                    max_transaction_fee: max_transaction_fee.into(),
+                    max_fee_per_gas_cap: cap.max_fee_per_gas.into(),
+                    max_transaction_fee_cap: cap.max_transaction_fee.into(),
+                    timestamp,
+                },
+                EventType::BroadcastAttempted {
+                    withdrawal_id,
+                    transaction_hash,
+                    nonce,
+                    timestamp,
+                } => EP::BroadcastAttempted {
+                    withdrawal_id: withdrawal_id.get().into(),
+                    transaction_hash: transaction_hash.to_string(),
+                    nonce: nonce.into(),
+                    timestamp,
+                },
+                EventType::BroadcastReconciled {
+                    withdrawal_id,
+                    transaction_hash,
+                    outcome,
+                } => EP::BroadcastReconciled {
+                    withdrawal_id: withdrawal_id.get().into(),
+                    transaction_hash: transaction_hash.to_string(),
+                    outcome: outcome.to_string(),
+                },
+                EventType::QuarantinedWithdrawal { withdrawal_id } => EP::QuarantinedWithdrawal {
+                    withdrawal_id: withdrawal_id.get().into(),
+                },
+                EventType::UnaccountedFeesFound { ledger_id, amount } => EP::UnaccountedFeesFound {
+                    ledger_id,
+                    amount: amount.into(),
+                },
+                EventType::CollectedFeesWithdrawalStarted {
+                    ledger_id,
+                    withdrawal,
+                } => EP::CollectedFeesWithdrawalStarted {
+                    ledger_id,
+                    to: withdrawal.to,
+                    transfer_amount: withdrawal.transfer_amount.into(),
+                    ledger_fee: withdrawal.ledger_fee.into(),
+                    created_at_time: withdrawal.created_at_time,
+                    amount: withdrawal.amount.into(),
+                },
+                EventType::CollectedFeesWithdrawalFailed { ledger_id, reason } => {
+                    EP::CollectedFeesWithdrawalFailed { ledger_id, reason }
+                }
+                EventType::MissingFeesFound { ledger_id, amount } => EP::MissingFeesFound {
+                    ledger_id,
+                    amount: amount.into(),
+                },
+                EventType::NativeLedgerMetadataMatched(metadata) => {
+                    EP::NativeLedgerMetadataMatched {
+                        symbol: metadata.symbol,
+                        decimals: metadata.decimals,
+                    }
+                }
             },
         }
     }
 
+    // event indices are absolute, archived events are no longer available
+    let archived_event_count = storage::archived_event_count();
     let events = storage::with_event_iter(|it| {
-        it.skip(arg.start as usize)
+        it.skip(arg.start.saturating_sub(archived_event_count) as usize)
             .take(arg.length.min(MAX_EVENTS_PER_RESPONSE) as usize)
             .map(map_event)
             .collect()
@@ -1781,11 +2884,203 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
     GetEventsResult {
         events,
         total_event_count: storage::total_event_count(),
+        archived_event_count,
+        earliest_unpruned_block: read_state(|s| s.earliest_unpruned_block).into(),
+    }
+}
+
+/// Returns the CBOR-encoded events starting at `start` as stored in the stable log,
+/// without decoding them. Intended for off-chain indexers, see `storage::raw_events_chunk`
+/// for the framing of the returned bytes and `storage::decode_event` for the version prefix.
+#[query]
+fn get_events_raw(start: u64, max_bytes: u32) -> GetEventsRawResult {
+    let (events, next_start) = storage::raw_events_chunk(start, max_bytes);
+    let archive = storage::event_log_archive();
+    GetEventsRawResult {
+        events: serde_bytes::ByteBuf::from(events),
+        next_start,
+        total_event_count: storage::total_event_count(),
+        archived_event_count: archive.archived_event_count,
+        max_retained_event_count: archive.max_retained_event_count,
+    }
+}
+
+// Sets the number of retained events above which the oldest events can be archived,
+// or disables archiving if not set.
+#[update]
+fn set_max_retained_event_count(max_retained_event_count: Option<u64>) {
+    let _instrumentation = instrument("set_max_retained_event_count");
+    let caller = validate_caller_not_anonymous();
+    if caller != Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap() {
+        panic!("Only appic controller can set the maximum number of retained events");
+    }
+
+    storage::set_max_retained_event_count(max_retained_event_count);
+    log!(
+        INFO,
+        "[set_max_retained_event_count]: set to {max_retained_event_count:?}"
+    );
+}
+
+// Prunes the events before absolute index `up_to_index` from the event log. The caller must
+// have exported them via `get_events_raw` and proves it with `expected_hash`, the Keccak-256
+// hash of the concatenated chunks. The state is replayed from a snapshot after an upgrade.
+#[update]
+fn archive_events(up_to_index: u64, expected_hash: String) -> Result<(), ArchiveEventsError> {
+    let _instrumentation = instrument("archive_events");
+    let caller = validate_caller_not_anonymous();
+    if caller != Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap() {
+        panic!("Only appic controller can archive events");
+    }
+
+    let expected_hash = Hash::from_str(&expected_hash).map_err(ArchiveEventsError::InvalidHash)?;
+    let from_index = storage::archived_event_count();
+    mutate_state(|s| state::audit::archive_events(s, up_to_index, expected_hash))?;
+    schedule_archive_continuation();
+
+    log!(
+        INFO,
+        "[archive_events]: archiving events {from_index}..{up_to_index} with hash {expected_hash}"
+    );
+    Ok(())
+}
+
+// Archives the events before absolute index `up_to_index` as `archive_events` does, as long as
+// they are settled and refer to blocks more than `prune_threshold_blocks` before the last
+// scraped block. The events must have been exported first, see `archive_events`.
+#[update]
+fn compact_event_log(
+    prune_threshold_blocks: u64,
+    up_to_index: u64,
+    expected_hash: String,
+) -> Result<CompactionResult, CompactEventLogError> {
+    let _instrumentation = instrument("compact_event_log");
+    let caller = validate_caller_not_anonymous();
+    if caller != Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap() {
+        panic!("Only appic controller can compact the event log");
+    }
+    if prune_threshold_blocks < MIN_PRUNE_THRESHOLD_BLOCKS {
+        return Err(CompactEventLogError::ThresholdTooLow {
+            min_prune_threshold_blocks: MIN_PRUNE_THRESHOLD_BLOCKS,
+        });
+    }
+
+    let expected_hash = Hash::from_str(&expected_hash)
+        .map_err(|e| CompactEventLogError::ArchiveEvents(ArchiveEventsError::InvalidHash(e)))?;
+    let threshold_block = read_state(|s| {
+        s.last_scraped_block_number
+            .checked_sub(BlockNumber::from(prune_threshold_blocks))
+            .unwrap_or(BlockNumber::ZERO)
+    });
+    let pruned_count = mutate_state(|s| {
+        state::audit::compact_event_log(s, threshold_block, up_to_index, expected_hash)
+    })?;
+    schedule_archive_continuation();
+
+    log!(
+        INFO,
+        "[compact_event_log]: pruned {pruned_count} events before block {threshold_block}"
+    );
+    Ok(CompactionResult {
+        pruned_count,
+        threshold_block: threshold_block.into(),
+    })
+}
+
+// Stops accepting new withdrawals and deposits until the next upgrade, and returns the number
+// of items still in flight. Call `drain_status` to know when it is safe to stop the canister.
+#[update]
+fn prepare_upgrade() -> u64 {
+    let _instrumentation = instrument("prepare_upgrade");
+    let caller = validate_caller_not_anonymous();
+    if caller != Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap() {
+        panic!("Only appic controller can prepare an upgrade");
+    }
+
+    let in_flight_count = mutate_state(|s| {
+        if !s.is_draining() {
+            process_event(
+                s,
+                EventType::FeatureFlagChanged {
+                    flag: FeatureFlag::Draining,
+                    enabled: true,
+                },
+            );
+        }
+        s.in_flight_count()
+    });
+    log!(
+        INFO,
+        "[prepare_upgrade]: draining with {in_flight_count} items in flight"
+    );
+    in_flight_count
+}
+
+#[query]
+fn drain_status() -> DrainStatus {
+    read_state(|s| {
+        let in_flight_count = s.in_flight_count();
+        DrainStatus {
+            draining: s.is_draining(),
+            in_flight_count,
+            safe_to_stop: s.is_draining() && in_flight_count == 0,
+        }
+    })
+}
+
+// Enables or disables a feature flag, controller only. Unknown flags are rejected.
+#[update]
+fn set_feature_flag(flag: String, enabled: bool) -> Result<(), SetFeatureFlagError> {
+    let _instrumentation = instrument("set_feature_flag");
+    let caller = validate_caller_not_anonymous();
+    if caller != Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap() {
+        panic!("Only appic controller can set feature flags");
     }
+
+    let flag = read_state(|s| validate_feature_flag_change(s, &flag, enabled))?;
+    mutate_state(|s| {
+        if s.feature_flags.is_enabled(flag) != enabled {
+            process_event(s, EventType::FeatureFlagChanged { flag, enabled });
+        }
+    });
+    log!(INFO, "[set_feature_flag]: {flag} set to {enabled}");
+    Ok(())
+}
+
+// Transfers fees held by the minter on the ledger `token` to the fee collector, controller only.
+// Withdraws all of them if `amount` is not set. Returns the index of the transfer.
+// A previous withdrawal of the ledger whose outcome is unknown is retried instead.
+#[update]
+async fn withdraw_collected_fees(
+    token: Principal,
+    amount: Option<Nat>,
+) -> Result<Nat, WithdrawCollectedFeesError> {
+    let _instrumentation = instrument("withdraw_collected_fees");
+    let caller = validate_caller_not_anonymous();
+    if caller != Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap() {
+        panic!("Only appic controller can withdraw collected fees");
+    }
+
+    fee_collector::withdraw_collected_fees(token, amount).await
+}
+
+// Returns every feature flag with its current value.
+#[query]
+fn get_feature_flags() -> Vec<CandidFeatureFlag> {
+    read_state(|s| {
+        s.feature_flags
+            .iter()
+            .map(|(flag, enabled)| CandidFeatureFlag {
+                flag: flag.to_string(),
+                enabled,
+            })
+            .collect()
+    })
 }
 
 #[update]
 pub async fn update_chain_data(chain_data: ChainData) {
+    let _instrumentation = instrument("update_chain_data");
     let caller = ic_cdk::api::msg_caller();
     let rpc_helper_identity = Principal::from_text(RPC_HELPER_PRINCIPAL).unwrap();
 
@@ -1797,6 +3092,7 @@ pub async fn update_chain_data(chain_data: ChainData) {
 
     let (
         network,
+        confirmation_policy,
         last_observed_block,
         last_scraped_block,
         latest_requested_block_to_scrape,
@@ -1804,6 +3100,7 @@ pub async fn update_chain_data(chain_data: ChainData) {
     ) = read_state(|s| {
         (
             s.evm_network(),
+            s.confirmation_policy,
             s.last_observed_block_number.unwrap_or(BlockNumber::ZERO),
             s.last_scraped_block_number,
             s.lastest_requested_block_to_scrape
@@ -1814,6 +3111,7 @@ pub async fn update_chain_data(chain_data: ChainData) {
 
     let latest_block_number = apply_safe_threshold_to_latest_block_numner(
         network,
+        confirmation_policy,
         BlockNumber::try_from(chain_data.latest_block_number)
             .expect("Failed to parse block number"),
     );
@@ -1822,20 +3120,27 @@ pub async fn update_chain_data(chain_data: ChainData) {
         return;
     }
 
-    let fee_history =
-        parse_fee_history(chain_data.fee_history).expect("Failed to parse fee hisotry");
+    // Only the derived estimate is kept, the raw fee history is dropped once parsed.
+    let (estimated_transaction_fee, newest_fee_history_block) = {
+        let fee_history =
+            parse_fee_history(chain_data.fee_history).expect("Failed to parse fee hisotry");
+        (
+            estimate_transaction_fee(&fee_history).expect("Failed to estimate gas fee"),
+            fee_history.newest_block(),
+        )
+    };
 
     let native_token_usd_price = chain_data
         .native_token_usd_price
         .unwrap_or(previous_native_token_usd_price);
 
-    let estimated_transaction_fee =
-        estimate_transaction_fee(&fee_history).expect("Failed to estimate gas fee");
-
     mutate_state(|s| {
         s.last_transaction_price_estimate = Some((now, estimated_transaction_fee));
+        s.last_transaction_price_estimate_block = Some(newest_fee_history_block);
         s.last_observed_block_number = Some(latest_block_number);
         s.last_observed_block_time = Some(now);
+        s.last_observed_block_timestamp =
+            chain_data.latest_block_timestamp.map(BlockTimestamp::from);
         s.last_native_token_usd_price_estimate = Some((now, native_token_usd_price))
     });
 
@@ -1854,6 +3159,7 @@ pub async fn update_chain_data(chain_data: ChainData) {
 
 #[update]
 pub async fn charge_gas_tank(amount: Nat) {
+    let _instrumentation = instrument("charge_gas_tank");
     let caller = validate_caller_not_anonymous();
 
     let appic_controller = Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap();
@@ -1921,27 +3227,32 @@ pub async fn charge_gas_tank(amount: Nat) {
 
 #[update]
 fn icrc21_canister_call_consent_message(req: ConsentMessageRequest) -> ConsentMessageResponse {
+    let _instrumentation = instrument("icrc21_canister_call_consent_message");
+    use evm_minter::candid_types::errors::{ConsentMessageError, MinterError};
     use evm_minter::icrc_21::Error;
-    let language = req.user_preferences.metadata.language.clone();
     let _utc_offset_minutes = req.user_preferences.metadata.utc_offset_minutes; // Not used
     let device_spec = req.user_preferences.device_spec.clone();
 
-    // Support only English
-    if language != "en" {
-        return ConsentMessageResponse::Err(Error::GenericError {
-            error_code: Nat::from(1u64),
-            description: "Unsupported language".to_string(),
-        });
-    }
+    let Some(language) = supported_language(&req.user_preferences.metadata.language) else {
+        return consent_error(ConsentMessageError::UnsupportedLanguage);
+    };
+    // Texts without a translation are displayed in English
+    let t = |key: &str| ConsentMessages::translate(key, language);
+    let f = |key: &str, args: &[(&str, &str)]| ConsentMessages::format(key, language, args);
 
     // Response metadata: no utc_offset_minutes
-    let response_metadata = ConsentMessageMetadata {
-        language,
+    let response_metadata = ConsentMessageResponseMetadata {
+        language: language.to_string(),
         utc_offset_minutes: None,
+        supported_languages: SUPPORTED_LANGUAGES.map(String::from).to_vec(),
     };
 
+    fn consent_error(error: ConsentMessageError) -> ConsentMessageResponse {
+        ConsentMessageResponse::Err(error.to_generic_error().into())
+    }
+
     // Helper to create fields
-    fn create_fields(pairs: Vec<(&str, String)>) -> Vec<(String, Value)> {
+    fn create_fields<K: ToString>(pairs: Vec<(K, String)>) -> Vec<(String, Value)> {
         pairs
             .into_iter()
             .map(|(k, v)| (k.to_string(), Value::Text(TextValue { content: v })))
@@ -1967,10 +3278,7 @@ fn icrc21_canister_call_consent_message(req: ConsentMessageRequest) -> ConsentMe
                 (intent, Some(fields), text)
             }
             Err(e) => {
-                return ConsentMessageResponse::Err(Error::GenericError {
-                    error_code: Nat::from(100u64),
-                    description: format!("Failed to decode arguments: {}", e),
-                })
+                return consent_error(ConsentMessageError::ArgumentDecodingFailed(e.to_string()))
             }
         },
         "add_erc20_token" => match candid::decode_one::<AddErc20Token>(&req.arg) {
@@ -1989,10 +3297,7 @@ fn icrc21_canister_call_consent_message(req: ConsentMessageRequest) -> ConsentMe
                 (intent, Some(fields), text)
             }
             Err(e) => {
-                return ConsentMessageResponse::Err(Error::GenericError {
-                    error_code: Nat::from(100u64),
-                    description: format!("Failed to decode arguments: {}", e),
-                })
+                return consent_error(ConsentMessageError::ArgumentDecodingFailed(e.to_string()))
             }
         },
         "charge_gas_tank" => match candid::decode_one::<Nat>(&req.arg) {
@@ -2003,16 +3308,38 @@ fn icrc21_canister_call_consent_message(req: ConsentMessageRequest) -> ConsentMe
                 (intent, Some(fields), text)
             }
             Err(e) => {
-                return ConsentMessageResponse::Err(Error::GenericError {
-                    error_code: Nat::from(100u64),
-                    description: format!("Failed to decode arguments: {}", e),
-                })
+                return consent_error(ConsentMessageError::ArgumentDecodingFailed(e.to_string()))
             }
         },
         "check_new_deposits" => {
-            let intent = "Check New Deposits".to_string();
-            let fields = vec![];
-            let text = "Check for new deposits.".to_string();
+            let intent = t("check_new_deposits.intent");
+            let (blocks_before_minting, helper_contract_address) = read_state(|s| {
+                (
+                    quote::blocks_before_minting(s.evm_network, s.confirmation_policy),
+                    quote::deposit_helper_contract_address(s),
+                )
+            });
+            let confirmations = blocks_before_minting
+                .map(|blocks| {
+                    f(
+                        "check_new_deposits.blocks",
+                        &[("blocks", &blocks.to_string())],
+                    )
+                })
+                .unwrap_or_else(|| t("check_new_deposits.safe_block"));
+            let fields = create_fields(vec![
+                (t("field.confirmations"), confirmations.clone()),
+                (
+                    t("field.helper_contract_address"),
+                    helper_contract_address
+                        .map(|address| address.to_string())
+                        .unwrap_or_default(),
+                ),
+            ]);
+            let text = f(
+                "check_new_deposits.text",
+                &[("confirmations", &confirmations)],
+            );
             (intent, Some(fields), text)
         }
         "dex_order" => match candid::decode_one::<DexOrderArgs>(&req.arg) {
@@ -2038,10 +3365,7 @@ fn icrc21_canister_call_consent_message(req: ConsentMessageRequest) -> ConsentMe
                 (intent, Some(fields), text)
             }
             Err(e) => {
-                return ConsentMessageResponse::Err(Error::GenericError {
-                    error_code: Nat::from(100u64),
-                    description: format!("Failed to decode arguments: {}", e),
-                })
+                return consent_error(ConsentMessageError::ArgumentDecodingFailed(e.to_string()))
             }
         },
         "update_chain_data" => match candid::decode_one::<ChainData>(&req.arg) {
@@ -2055,86 +3379,96 @@ fn icrc21_canister_call_consent_message(req: ConsentMessageRequest) -> ConsentMe
                 (intent, Some(fields), text)
             }
             Err(e) => {
-                return ConsentMessageResponse::Err(Error::GenericError {
-                    error_code: Nat::from(100u64),
-                    description: format!("Failed to decode arguments: {}", e),
-                })
+                return consent_error(ConsentMessageError::ArgumentDecodingFailed(e.to_string()))
             }
         },
         "withdraw_erc20" => match candid::decode_one::<WithdrawErc20Arg>(&req.arg) {
             Ok(args) => {
-                let intent = "Withdraw ERC20".to_string();
+                let intent = t("withdraw_erc20.intent");
                 let fields = create_fields(vec![
-                    ("ERC20 Ledger ID", args.erc20_ledger_id.to_string()),
-                    ("Recipient", args.recipient.clone()),
-                    ("Amount", args.amount.to_string()),
+                    (t("field.erc20_ledger_id"), args.erc20_ledger_id.to_string()),
+                    (t("field.recipient"), args.recipient.clone()),
+                    (t("field.amount"), args.amount.to_string()),
                 ]);
-                let text = format!(
-                    "Withdraw {} from ERC20 ledger {} to {}.",
-                    args.amount, args.erc20_ledger_id, args.recipient
+                let text = f(
+                    "withdraw_erc20.text",
+                    &[
+                        ("amount", &args.amount.to_string()),
+                        ("ledger", &args.erc20_ledger_id.to_string()),
+                        ("recipient", &args.recipient),
+                    ],
                 );
                 (intent, Some(fields), text)
             }
             Err(e) => {
-                return ConsentMessageResponse::Err(Error::GenericError {
-                    error_code: Nat::from(100u64),
-                    description: format!("Failed to decode arguments: {}", e),
-                })
+                return consent_error(ConsentMessageError::ArgumentDecodingFailed(e.to_string()))
             }
         },
         "withdraw_native_token" => match candid::decode_one::<WithdrawalArg>(&req.arg) {
             Ok(args) => {
-                let intent = "Withdraw Native Token".to_string();
+                let intent = t("withdraw_native_token.intent");
+                let (symbol, decimals) =
+                    read_state(|s| (s.native_ledger_symbol(), s.native_ledger_decimals()));
+                let amount = format!("{} {symbol}", format_token_amount(&args.amount, decimals));
                 let fields = create_fields(vec![
-                    ("Recipient", args.recipient.clone()),
-                    ("Amount", args.amount.to_string()),
+                    (t("field.recipient"), args.recipient.clone()),
+                    (t("field.amount"), amount.clone()),
                 ]);
-                let text = format!(
-                    "Withdraw {} native tokens to {}.",
-                    args.amount, args.recipient
+                let text = f(
+                    "withdraw_native_token.text",
+                    &[("amount", &amount), ("recipient", &args.recipient)],
                 );
                 (intent, Some(fields), text)
             }
             Err(e) => {
-                return ConsentMessageResponse::Err(Error::GenericError {
-                    error_code: Nat::from(100u64),
-                    description: format!("Failed to decode arguments: {}", e),
-                })
+                return consent_error(ConsentMessageError::ArgumentDecodingFailed(e.to_string()))
             }
         },
         "wrap_icrc" => match candid::decode_one::<WrapIcrcArg>(&req.arg) {
             Ok(args) => {
-                let intent = "Wrap ICRC".to_string();
+                let intent = t("wrap_icrc.intent");
                 let fields = create_fields(vec![
-                    ("Recipient", args.recipient.clone()),
-                    ("ICRC Ledger ID", args.icrc_ledger_id.to_string()),
-                    ("Amount", args.amount.to_string()),
+                    (t("field.recipient"), args.recipient.clone()),
+                    (t("field.icrc_ledger_id"), args.icrc_ledger_id.to_string()),
+                    (t("field.amount"), args.amount.to_string()),
                 ]);
-                let text = format!(
-                    "Wrap {} from ICRC ledger {} to {}.",
-                    args.amount, args.icrc_ledger_id, args.recipient
+                let text = f(
+                    "wrap_icrc.text",
+                    &[
+                        ("amount", &args.amount.to_string()),
+                        ("ledger", &args.icrc_ledger_id.to_string()),
+                        ("recipient", &args.recipient),
+                    ],
                 );
                 (intent, Some(fields), text)
             }
             Err(e) => {
-                return ConsentMessageResponse::Err(Error::GenericError {
-                    error_code: Nat::from(100u64),
-                    description: format!("Failed to decode arguments: {}", e),
-                })
+                return consent_error(ConsentMessageError::ArgumentDecodingFailed(e.to_string()))
             }
         },
         // Query-like methods or read-only
-        "eip_1559_transaction_price"
+        "drain_status"
+        | "eip_1559_transaction_price"
+        | "get_address_list"
         | "get_events"
+        | "get_feature_flags"
+        | "get_finalized_transactions"
         | "get_minter_info"
+        | "get_onchain_approvals"
+        | "get_provider_stats"
+        | "get_scraping_gaps"
         | "icrc_28_trusted_origins"
         | "minter_address"
+        | "quote_deposit"
         | "request_scraping_logs"
         | "retrieve_deposit_status"
         | "retrieve_swap_status_by_hash"
         | "retrieve_swap_status_by_swap_tx_id"
+        | "retrieve_swap_status_history"
+        | "retrieve_swap_status_history_by_hash"
         | "retrieve_withdrawal_status"
         | "smart_contract_address"
+        | "validate_dex_order"
         | "withdrawal_status" => {
             return ConsentMessageResponse::Err(Error::UnsupportedCanisterCall(ErrorInfo {
                 description: "This is a read-only method and does not require consent.".to_string(),
@@ -2164,6 +3498,7 @@ fn icrc21_canister_call_consent_message(req: ConsentMessageRequest) -> ConsentMe
 
 #[update]
 fn icrc28_trusted_origins() -> Icrc28TrustedOriginsResponse {
+    let _instrumentation = instrument("icrc28_trusted_origins");
     let trusted_origins = vec![
         String::from("https://dduc6-3yaaa-aaaal-ai63a-cai.icp0.io"),
         String::from("https://dduc6-3yaaa-aaaal-ai63a-cai.raw.icp0.io"),
@@ -1,11 +1,19 @@
+use crate::state::mutate_state;
 use candid::{CandidType, Principal};
 use ic_cdk::call::CallFailed;
 use ic_management_canister_types::{
-    EcdsaCurve, EcdsaKeyId, SignWithEcdsaArgs, SignWithEcdsaResult,
+    CanisterInfoArgs, CanisterInfoResult, EcdsaCurve, EcdsaKeyId, SignWithEcdsaArgs,
+    SignWithEcdsaResult,
 };
 use serde::de::DeserializeOwned;
+use std::cell::RefCell;
 use std::fmt::{self};
 
+thread_local! {
+    // The installed module only changes with an upgrade, which resets this cache.
+    static MODULE_HASH: RefCell<Option<Vec<u8>>> = const { RefCell::new(None) };
+}
+
 /// Represents an error from a management canister call, such as
 /// `sign_with_ecdsa`.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -131,14 +139,15 @@ where
     }
 }
 
+/// Cycles attached to each `sign_with_ecdsa` call.
+pub const CYCLES_PER_SIGNATURE: u128 = 27_000_000_000;
+
 /// Signs a message hash using the tECDSA API.
 pub async fn sign_with_ecdsa(
     key_name: String,
     derivation_path: Vec<Vec<u8>>,
     message_hash: [u8; 32],
 ) -> Result<[u8; 64], CallError> {
-    const CYCLES_PER_SIGNATURE: u128 = 27_000_000_000;
-
     let reply: SignWithEcdsaResult = call(
         "sign_with_ecdsa",
         CYCLES_PER_SIGNATURE,
@@ -152,6 +161,9 @@ pub async fn sign_with_ecdsa(
         },
     )
     .await?;
+    // Must be read before the next await, which resets the refunded cycles.
+    let refunded_cycles = ic_cdk::api::msg_cycles_refunded();
+    mutate_state(|s| s.record_ecdsa_signing(CYCLES_PER_SIGNATURE.saturating_sub(refunded_cycles)));
 
     let signature_length = reply.signature.len();
     Ok(<[u8; 64]>::try_from(reply.signature).unwrap_or_else(|_| {
@@ -161,3 +173,31 @@ pub async fn sign_with_ecdsa(
         )
     }))
 }
+
+/// Returns the hash of the Wasm module installed on this canister, as reported by
+/// the `canister_info` management canister method.
+pub async fn canister_module_hash() -> Result<Option<Vec<u8>>, CallError> {
+    let reply: CanisterInfoResult = call(
+        "canister_info",
+        0,
+        &CanisterInfoArgs {
+            canister_id: ic_cdk::api::canister_self(),
+            num_requested_changes: None,
+        },
+    )
+    .await?;
+    Ok(reply.module_hash)
+}
+
+/// Fetches the hash of the installed Wasm module and caches it for [`module_hash`].
+pub async fn refresh_module_hash() -> Result<(), CallError> {
+    let module_hash = canister_module_hash().await?;
+    MODULE_HASH.with(|hash| *hash.borrow_mut() = module_hash);
+    Ok(())
+}
+
+/// Returns the cached hash of the installed Wasm module, `None` until
+/// [`refresh_module_hash`] succeeded.
+pub fn module_hash() -> Option<Vec<u8>> {
+    MODULE_HASH.with(|hash| hash.borrow().clone())
+}
@@ -128,7 +128,7 @@ impl From<&ReceivedContractEvent> for Memo {
                 from_address: received_native_event.from_address,
             },
             ReceivedContractEvent::Erc20Deposit(received_erc20_event) => MintMemo::Convert {
-                from_address: received_erc20_event.from_address,
+                from_address: received_erc20_event.depositor(),
             },
             ReceivedContractEvent::WrappedIcrcBurn(received_burn_event) => MintMemo::Convert {
                 from_address: received_burn_event.from_address,
@@ -146,12 +146,12 @@ impl From<&ReceivedContractEvent> for Memo {
 
 impl From<ReimbursementRequest> for MintMemo {
     fn from(reimbursement_request: ReimbursementRequest) -> Self {
-        match reimbursement_request.transaction_hash {
-            Some(_tx_hash) => MintMemo::ReimburseTransaction {
-                withdrawal_id: reimbursement_request.ledger_burn_index.get(),
+        match reimbursement_request {
+            ReimbursementRequest::Finalized(request) => MintMemo::ReimburseTransaction {
+                withdrawal_id: request.ledger_burn_index.get(),
             },
-            None => MintMemo::ReimburseWithdrawal {
-                withdrawal_id: reimbursement_request.ledger_burn_index.get(),
+            ReimbursementRequest::Pending(request) => MintMemo::ReimburseWithdrawal {
+                withdrawal_id: request.ledger_burn_index.get(),
             },
         }
     }
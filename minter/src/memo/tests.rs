@@ -5,7 +5,7 @@ use crate::memo::BurnMemo;
 use crate::memo::{Address, MintMemo};
 use crate::numeric::{BlockNumber, Erc20TokenAmount, LedgerBurnIndex, LogIndex, Wei};
 use crate::rpc_declarations::Hash;
-use crate::state::transactions::ReimbursementRequest;
+use crate::state::transactions::{FinalizedReimbursementRequest, ReimbursementRequest};
 use arbitrary::{arb_burn_memo, arb_mint_memo, arb_reimbursement_request};
 use candid::Principal;
 use icrc_ledger_types::icrc1::transfer::Memo;
@@ -40,10 +40,12 @@ proptest! {
         match mint_memo {
             MintMemo::Convert{ .. } => panic!("BUG: unexpected mint memo variant"),
             MintMemo::ReimburseTransaction{withdrawal_id} => {
-                prop_assert_eq!(withdrawal_id, reimbursement_request.ledger_burn_index.get());
+                prop_assert!(reimbursement_request.transaction_hash().is_some());
+                prop_assert_eq!(withdrawal_id, reimbursement_request.ledger_burn_index().get());
             }
             MintMemo::ReimburseWithdrawal{withdrawal_id } => {
-                prop_assert_eq!(withdrawal_id, reimbursement_request.ledger_burn_index.get());
+                prop_assert!(reimbursement_request.transaction_hash().is_none());
+                prop_assert_eq!(withdrawal_id, reimbursement_request.ledger_burn_index().get());
             }
         }
     }
@@ -84,14 +86,14 @@ fn encode_mint_reimburse_memo_is_stable() {
         "0x705f826861c802b407843e99af986cfde8749b669e5e0a5a150f4350bcaa9bc3"
             .parse()
             .unwrap();
-    let reimbursement_request = ReimbursementRequest {
+    let reimbursement_request = FinalizedReimbursementRequest {
         ledger_burn_index: LedgerBurnIndex::from(1234_u64),
         reimbursed_amount: Erc20TokenAmount::from(100_u64),
         to: Principal::anonymous(),
         to_subaccount: None,
-        transaction_hash: Some(transaction_hash),
+        transaction_hash,
     };
-    let memo: Memo = reimbursement_request.into();
+    let memo: Memo = ReimbursementRequest::from(reimbursement_request).into();
 
     assert_eq!(memo.0, [130, 1, 129, 25, 4, 210]);
 }
@@ -119,7 +121,9 @@ mod arbitrary {
     use crate::memo::{BurnMemo, MintMemo};
     use crate::numeric::LedgerBurnIndex;
     use crate::rpc_declarations::Hash;
-    use crate::state::transactions::{ReimbursementRequest, Subaccount};
+    use crate::state::transactions::{
+        PendingReimbursementRequest, ReimbursementRequest, Subaccount,
+    };
     use candid::Principal;
     use evm_rpc_client::eth_types::Address;
     use proptest::arbitrary::any;
@@ -234,12 +238,15 @@ mod arbitrary {
         )
             .prop_map(
                 |(ledger_burn_index, reimbursed_amount, to, to_subaccount, transaction_hash)| {
-                    ReimbursementRequest {
+                    let request = PendingReimbursementRequest {
                         ledger_burn_index: LedgerBurnIndex::from(ledger_burn_index),
                         reimbursed_amount,
                         to,
                         to_subaccount,
-                        transaction_hash,
+                    };
+                    match transaction_hash {
+                        Some(transaction_hash) => request.finalize(transaction_hash).into(),
+                        None => request.into(),
                     }
                 },
             )
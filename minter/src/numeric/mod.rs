@@ -53,6 +53,10 @@ pub type TransactionCount = CheckedAmountOf<TransactionCountTag>;
 pub enum BlockNumberTag {}
 pub type BlockNumber = CheckedAmountOf<BlockNumberTag>;
 
+pub enum BlockTimestampTag {}
+/// Unix timestamp of a block, in seconds.
+pub type BlockTimestamp = CheckedAmountOf<BlockTimestampTag>;
+
 pub enum GasUnit {}
 /// The number of gas units attached to a transaction for execution.
 pub type GasAmount = CheckedAmountOf<GasUnit>;
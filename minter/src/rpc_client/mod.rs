@@ -5,26 +5,28 @@ pub mod providers;
 
 use crate::{
     evm_config::EvmNetwork,
-    logs::{PrintProxySink, INFO, TRACE_HTTP},
-    numeric::{BlockNumber, GasAmount, LogIndex, TransactionCount, Wei, WeiPerGas},
-    rpc_client::providers::get_custom_providers,
+    logs::{PrintProxySink, DEBUG, INFO, TRACE_HTTP},
+    numeric::{BlockNumber, BlockTimestamp, GasAmount, LogIndex, TransactionCount, Wei, WeiPerGas},
     rpc_declarations::{
         AccessList, Block, BlockSpec, BlockTag, CallParams, Data, FeeHistory, FeeHistoryParams,
         FixedSizeData, GetLogsParam, Hash, LogEntry, Quantity, SendRawTransactionResult, Topic,
-        TransactionReceipt, TransactionStatus,
+        TransactionReceipt, TransactionReceiptWithLogs, TransactionStatus,
     },
-    state::State,
+    state::{audit::process_event, event::EventType, mutate_state, State},
 };
 use candid::Nat;
 use evm_rpc_client::eth_types::Address;
+use evm_rpc_client::native_http::eth_rpc_error::is_result_set_too_large;
+use evm_rpc_client::native_http::rate_limit::take_rate_limited;
 use evm_rpc_client::{
     evm_rpc_types::{
         self, AccessList as EvmAccessList, AccessListEntry as EvmAccessListEntry,
         Block as EvmBlock, BlockTag as EvmBlockTag, CallArgs, FeeHistory as EvmFeeHistory,
-        FeeHistoryArgs as EvmFeeHistoryArgs, GetLogsArgs as EvmGetLogsArgs,
-        GetTransactionCountArgs as EvmGetTransactionCountArgs, Hex, Hex20, Hex32, HexByte,
-        HttpOutcallError, LogEntry as EvmLogEntry, MultiRpcResult as EvmMultiRpcResult, Nat256,
-        RpcConfig as EvmRpcConfig, RpcError as EvmRpcError, RpcService as EvmRpcService,
+        FeeHistoryArgs as EvmFeeHistoryArgs, GetCodeArgs as EvmGetCodeArgs,
+        GetLogsArgs as EvmGetLogsArgs, GetTransactionCountArgs as EvmGetTransactionCountArgs, Hex,
+        Hex20, Hex32, HexByte, HttpOutcallError, LogEntry as EvmLogEntry,
+        MultiRpcResult as EvmMultiRpcResult, Nat256, PartialOnError, RpcConfig as EvmRpcConfig,
+        RpcError as EvmRpcError, RpcResult as EvmRpcResult, RpcService as EvmRpcService,
         SendRawTransactionStatus as EvmSendRawTransactionStatus,
         TransactionReceipt as EvmTransactionReceipt, TransactionRequest,
     },
@@ -32,8 +34,10 @@ use evm_rpc_client::{
 };
 use evm_rpc_client::{CallerService, EvmRpcClient, OverrideRpcConfig};
 use ic_canister_log::log;
+use ic_sha3::Keccak256;
 use num_traits::ToPrimitive;
-use providers::{get_one_provider, get_providers, Provider};
+use providers::{get_custom_providers, get_one_provider, provider_of, Provider};
+use serde::Serialize;
 use std::{collections::BTreeMap, convert::Infallible, fmt::Display};
 
 // We expect most of the calls to contain zero events.
@@ -46,6 +50,25 @@ const ETH_GET_LOGS_INITIAL_RESPONSE_SIZE_ESTIMATE: u64 = 20_000;
 // should take care of that.
 pub const HEADER_SIZE_LIMIT: u64 = 2 * 1024;
 
+// Cycles attached when polling for a new block, only a single block header is requested.
+const POLL_FOR_NEW_BLOCK_CYCLES: u128 = 30_000_000_000;
+
+// Number of providers that must agree on the logs returned by `eth_getLogs`.
+const ETH_GET_LOGS_MIN_AGREEING_PROVIDERS: u8 = 2;
+
+// Block headers and fee history are only used to estimate fees and confirmations and are refreshed
+// often, so a single failing provider should not stall them. Logs keep requiring strict consensus.
+fn tolerate_provider_errors() -> EvmRpcConfig {
+    EvmRpcConfig {
+        response_size_estimate: None,
+        response_consensus: None,
+        partial_on_error: Some(PartialOnError::default()),
+    }
+}
+
+pub const ETH_GET_LOGS_METHOD: &str = "eth_getLogs";
+pub const ETH_GET_TRANSACTION_RECEIPT_METHOD: &str = "eth_getTransactionReceipt";
+
 pub struct RpcClient {
     evm_rpc_client: Option<EvmRpcClient<PrintProxySink>>,
     chain: EvmNetwork,
@@ -59,7 +82,13 @@ impl RpcClient {
         };
         const MIN_ATTACHED_CYCLES: u128 = 30_000_000_000;
 
-        let providers = get_providers(client.chain);
+        let providers = get_custom_providers(
+            client.chain,
+            state.available_providers(
+                ic_cdk::api::time(),
+                ETH_GET_LOGS_MIN_AGREEING_PROVIDERS as usize,
+            ),
+        );
 
         client.evm_rpc_client = Some(
             EvmRpcClient::builder(CallerService::RpcHttpOutCallClient, TRACE_HTTP)
@@ -73,9 +102,12 @@ impl RpcClient {
                         ),
                         response_consensus: Some(evm_rpc_types::ConsensusStrategy::Threshold {
                             total: None,
-                            min: 2,
+                            min: ETH_GET_LOGS_MIN_AGREEING_PROVIDERS,
                         }),
+                        partial_on_error: None,
                     }),
+                    eth_get_block_by_number: Some(tolerate_provider_errors()),
+                    eth_fee_history: Some(tolerate_provider_errors()),
                     ..Default::default()
                 })
                 .build(),
@@ -104,7 +136,10 @@ impl RpcClient {
                             ETH_GET_LOGS_INITIAL_RESPONSE_SIZE_ESTIMATE + HEADER_SIZE_LIMIT,
                         ),
                         response_consensus: None,
+                        partial_on_error: None,
                     }),
+                    eth_get_block_by_number: Some(tolerate_provider_errors()),
+                    eth_fee_history: Some(tolerate_provider_errors()),
                     ..Default::default()
                 })
                 .build(),
@@ -133,7 +168,10 @@ impl RpcClient {
                             ETH_GET_LOGS_INITIAL_RESPONSE_SIZE_ESTIMATE + HEADER_SIZE_LIMIT,
                         ),
                         response_consensus: None,
+                        partial_on_error: None,
                     }),
+                    eth_get_block_by_number: Some(tolerate_provider_errors()),
+                    eth_fee_history: Some(tolerate_provider_errors()),
                     ..Default::default()
                 })
                 .build(),
@@ -148,6 +186,26 @@ impl RpcClient {
                 .eth_call(into_evm_call_args(params))
                 .await
                 .reduce();
+            record_rate_limited_providers(self.chain);
+            result.result
+        } else {
+            Err(MultiCallError::ConsistentEvmRpcCanisterError(String::from(
+                "EVM RPC canister can not be None",
+            )))
+        }
+    }
+
+    /// Returns the code deployed at `address` in the latest block, empty for externally owned accounts.
+    pub async fn get_code(&self, address: Address) -> Result<Hex, MultiCallError<Hex>> {
+        if let Some(evm_rpc_client) = &self.evm_rpc_client {
+            let result = evm_rpc_client
+                .eth_get_code(EvmGetCodeArgs {
+                    address: Hex20::from(address.into_bytes()),
+                    block: EvmBlockTag::Latest,
+                })
+                .await
+                .reduce();
+            record_rate_limited_providers(self.chain);
             result.result
         } else {
             Err(MultiCallError::ConsistentEvmRpcCanisterError(String::from(
@@ -161,7 +219,12 @@ impl RpcClient {
         params: GetLogsParam,
     ) -> Result<Vec<LogEntry>, MultiCallError<Vec<LogEntry>>> {
         if let Some(evm_rpc_client) = &self.evm_rpc_client {
-            let result = evm_rpc_client
+            let block_range = format!(
+                "{}..={}",
+                block_spec_label(&params.from_block),
+                block_spec_label(&params.to_block)
+            );
+            let multi_result = evm_rpc_client
                 .eth_get_logs(EvmGetLogsArgs {
                     from_block: Some(into_evm_block_tag(params.from_block)),
                     to_block: Some(into_evm_block_tag(params.to_block)),
@@ -172,9 +235,13 @@ impl RpcClient {
                         .collect(),
                     topics: Some(into_evm_topic(params.topics)),
                 })
-                .await
-                .reduce();
-            result.result
+                .await;
+            record_rate_limited_providers(self.chain);
+            let result = multi_result.clone().reduce().result;
+            if let Err(MultiCallError::InconsistentResults(_)) = result {
+                record_provider_disagreement(ETH_GET_LOGS_METHOD, block_range, &multi_result);
+            }
+            result
         } else {
             Err(MultiCallError::ConsistentEvmRpcCanisterError(String::from(
                 "EVM RPC canister can not be None",
@@ -191,6 +258,28 @@ impl RpcClient {
                 .eth_get_block_by_number(into_evm_block_tag(block))
                 .await
                 .reduce();
+            record_rate_limited_providers(self.chain);
+            result.result
+        } else {
+            Err(MultiCallError::ConsistentEvmRpcCanisterError(String::from(
+                "EVM RPC canister can not be None",
+            )))
+        }
+    }
+
+    /// Returns the latest block if it is newer than `last_known_block`.
+    pub async fn poll_for_new_block(
+        &self,
+        last_known_block: BlockNumber,
+    ) -> Result<Option<Block>, MultiCallError<Option<Block>>> {
+        if let Some(evm_rpc_client) = &self.evm_rpc_client {
+            let last_known_block =
+                evm_rpc_client::numeric::BlockNumber::from(Nat256::from(last_known_block));
+            let result = evm_rpc_client
+                .poll_for_new_block(last_known_block, POLL_FOR_NEW_BLOCK_CYCLES)
+                .await
+                .reduce();
+            record_rate_limited_providers(self.chain);
             result.result
         } else {
             Err(MultiCallError::ConsistentEvmRpcCanisterError(String::from(
@@ -202,13 +291,24 @@ impl RpcClient {
     pub async fn get_transaction_receipt(
         &self,
         tx_hash: Hash,
-    ) -> Result<Option<TransactionReceipt>, MultiCallError<Option<TransactionReceipt>>> {
+    ) -> Result<
+        Option<TransactionReceiptWithLogs>,
+        MultiCallError<Option<TransactionReceiptWithLogs>>,
+    > {
         if let Some(evm_rpc_client) = &self.evm_rpc_client {
-            return evm_rpc_client
+            let multi_result = evm_rpc_client
                 .eth_get_transaction_receipt(tx_hash.to_string())
-                .await
-                .reduce()
-                .into();
+                .await;
+            record_rate_limited_providers(self.chain);
+            let result = reduce_transaction_receipt_with_logs(multi_result.clone()).result;
+            if let Err(MultiCallError::InconsistentResults(_)) = result {
+                record_provider_disagreement(
+                    ETH_GET_TRANSACTION_RECEIPT_METHOD,
+                    tx_hash.to_string(),
+                    &multi_result,
+                );
+            }
+            result
         } else {
             Err(MultiCallError::ConsistentEvmRpcCanisterError(String::from(
                 "EVM RPC canister can not be None",
@@ -229,6 +329,7 @@ impl RpcClient {
                 })
                 .await
                 .reduce();
+            record_rate_limited_providers(self.chain);
             result.result
         } else {
             Err(MultiCallError::ConsistentEvmRpcCanisterError(String::from(
@@ -248,6 +349,7 @@ impl RpcClient {
                     block: EvmBlockTag::Finalized,
                 })
                 .await;
+            record_rate_limited_providers(self.chain);
             results.reduce().reduce_with_equality().result
         } else {
             Err(MultiCallError::ConsistentEvmRpcCanisterError(String::from(
@@ -267,6 +369,7 @@ impl RpcClient {
                     block: EvmBlockTag::Latest,
                 })
                 .await;
+            record_rate_limited_providers(self.chain);
             results
                 .reduce()
                 .reduce_with_min_by_key(|transaction_count| *transaction_count)
@@ -287,6 +390,7 @@ impl RpcClient {
                 .eth_send_raw_transaction(raw_signed_transaction_hex)
                 .await
                 .reduce();
+            record_rate_limited_providers(self.chain);
             result.result
         } else {
             Err(MultiCallError::ConsistentEvmRpcCanisterError(String::from(
@@ -296,11 +400,111 @@ impl RpcClient {
     }
 }
 
+/// Records an [`EventType::ProviderDisagreement`] for a consensus-critical call on which
+/// providers returned inconsistent results, at most once per method every
+/// [`crate::PROVIDER_DISAGREEMENT_INTERVAL`]. The full responses only go to the DEBUG log.
+fn record_provider_disagreement<T: Serialize + std::fmt::Debug>(
+    method: &str,
+    block_range_or_hash: String,
+    multi_result: &EvmMultiRpcResult<T>,
+) {
+    let EvmMultiRpcResult::Inconsistent(results) = multi_result else {
+        return;
+    };
+    for (provider, result) in results {
+        log!(
+            DEBUG,
+            "[{method}]: provider {} returned for {block_range_or_hash}: {result:?}",
+            provider_label(provider)
+        );
+    }
+    let now = ic_cdk::api::time();
+    mutate_state(|s| {
+        if s.can_record_provider_disagreement(method, now) {
+            process_event(
+                s,
+                provider_disagreement_event(method, block_range_or_hash, results, now),
+            );
+        }
+    });
+}
+
+/// Records an [`EventType::ProviderRateLimited`] for every provider that kept answering
+/// HTTP 429 during the last calls, so that it is skipped until its retry-after time.
+fn record_rate_limited_providers(chain: EvmNetwork) {
+    for (service, retry_after_ns) in take_rate_limited() {
+        let Some(provider) = provider_of(chain, &service) else {
+            continue;
+        };
+        mutate_state(|s| {
+            if s.should_record_rate_limited_provider(provider, retry_after_ns) {
+                process_event(
+                    s,
+                    EventType::ProviderRateLimited {
+                        provider,
+                        retry_after_ns,
+                    },
+                );
+            }
+        });
+    }
+}
+
+/// Builds the event of a provider disagreement, where each response is stored as the
+/// Keccak-256 hash of its JSON serialization to bound the size of the event.
+pub fn provider_disagreement_event<T: Serialize + std::fmt::Debug>(
+    method: &str,
+    block_range_or_hash: String,
+    results: &[(EvmRpcService, EvmRpcResult<T>)],
+    timestamp: u64,
+) -> EventType {
+    let per_provider_hash = results
+        .iter()
+        .map(|(provider, result)| {
+            let serialized = match result {
+                Ok(response) => serde_json::to_vec(response)
+                    .unwrap_or_else(|e| format!("serialization error: {e}").into_bytes()),
+                Err(error) => format!("{error:?}").into_bytes(),
+            };
+            (provider_label(provider), Hash(Keccak256::hash(serialized)))
+        })
+        .collect();
+    EventType::ProviderDisagreement {
+        method: method.to_string(),
+        block_range_or_hash,
+        per_provider_hash,
+        timestamp,
+    }
+}
+
+/// Identifies a provider without leaking the API key contained in the URL of custom providers.
+fn provider_label(provider: &EvmRpcService) -> String {
+    match provider {
+        EvmRpcService::Custom(api) => {
+            let without_scheme = api.url.split("://").last().unwrap_or(&api.url);
+            without_scheme
+                .split('/')
+                .next()
+                .unwrap_or(without_scheme)
+                .to_string()
+        }
+        other => format!("{other:?}"),
+    }
+}
+
+fn block_spec_label(block: &BlockSpec) -> String {
+    match block {
+        BlockSpec::Number(number) => number.to_string(),
+        BlockSpec::Tag(tag) => tag.to_string(),
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Ord, PartialOrd)]
 pub enum SingleCallError {
     HttpOutcallError(HttpOutcallError),
     JsonRpcError { code: i64, message: String },
     EvmRpcError(String),
+    RateLimited { retry_after_secs: u64 },
 }
 
 impl From<EvmRpcError> for SingleCallError {
@@ -313,6 +517,13 @@ impl From<EvmRpcError> for SingleCallError {
                 message: e.message,
             },
             EvmRpcError::ValidationError(e) => SingleCallError::EvmRpcError(e.to_string()),
+            EvmRpcError::ResultSetTooLarge(e) => SingleCallError::JsonRpcError {
+                code: e.code,
+                message: e.message,
+            },
+            EvmRpcError::RateLimited { retry_after_secs } => {
+                SingleCallError::RateLimited { retry_after_secs }
+            }
         }
     }
 }
@@ -322,6 +533,7 @@ pub enum MultiCallError<T> {
     ConsistentHttpOutcallError(HttpOutcallError),
     ConsistentJsonRpcError { code: i64, message: String },
     ConsistentEvmRpcCanisterError(String),
+    ConsistentRateLimited { retry_after_secs: u64 },
     InconsistentResults(Vec<(EvmRpcService, Result<T, SingleCallError>)>),
 }
 
@@ -341,11 +553,59 @@ impl<T: Clone> MultiCallError<T> {
                         Err(rpc_error) => match rpc_error {
                             SingleCallError::HttpOutcallError(error) => predicate(error),
                             SingleCallError::JsonRpcError { .. }
-                            | SingleCallError::EvmRpcError(_) => false,
+                            | SingleCallError::EvmRpcError(_)
+                            | SingleCallError::RateLimited { .. } => false,
                         },
                     })
             }
-            MultiCallError::ConsistentEvmRpcCanisterError(_) => false,
+            MultiCallError::ConsistentEvmRpcCanisterError(_)
+            | MultiCallError::ConsistentRateLimited { .. } => false,
+        }
+    }
+
+    /// Returns true if any provider answered with HTTP 429 Too Many Requests, to tell
+    /// rate limiting apart from outages. Providers called through the EVM RPC canister
+    /// report it as an HTTP outcall error with that status.
+    pub fn is_rate_limited(&self) -> bool {
+        let is_too_many_requests = |error: &HttpOutcallError| {
+            matches!(
+                error,
+                HttpOutcallError::InvalidHttpJsonRpcResponse { status: 429, .. }
+            )
+        };
+        match self {
+            MultiCallError::ConsistentRateLimited { .. } => true,
+            MultiCallError::InconsistentResults(results)
+                if results.iter().any(|(_rpcservice, rpc_result)| {
+                    matches!(rpc_result, Err(SingleCallError::RateLimited { .. }))
+                }) =>
+            {
+                true
+            }
+            _ => self.has_http_outcall_error_matching(is_too_many_requests),
+        }
+    }
+
+    /// Returns true if any provider refused to return all the requested logs, e.g. because
+    /// the block range contains too many of them. Errors received through the EVM RPC canister
+    /// are classified by their message, since they are not mapped to a dedicated variant.
+    pub fn is_result_set_too_large(&self) -> bool {
+        match self {
+            MultiCallError::ConsistentJsonRpcError { code, message } => {
+                is_result_set_too_large(*code, message)
+            }
+            MultiCallError::InconsistentResults(results) => {
+                results.iter().any(|(_rpcservice, rpc_result)| {
+                    matches!(
+                        rpc_result,
+                        Err(SingleCallError::JsonRpcError { code, message })
+                            if is_result_set_too_large(*code, message)
+                    )
+                })
+            }
+            MultiCallError::ConsistentHttpOutcallError(_)
+            | MultiCallError::ConsistentEvmRpcCanisterError(_)
+            | MultiCallError::ConsistentRateLimited { .. } => false,
         }
     }
 
@@ -430,6 +690,9 @@ impl<T: std::fmt::Debug + std::cmp::PartialEq + Clone> ReducedResult<T> {
             Err(MultiCallError::ConsistentEvmRpcCanisterError(e)) => {
                 Err(MultiCallError::<U>::ConsistentEvmRpcCanisterError(e))
             }
+            Err(MultiCallError::ConsistentRateLimited { retry_after_secs }) => {
+                Err(MultiCallError::<U>::ConsistentRateLimited { retry_after_secs })
+            }
 
             Err(MultiCallError::InconsistentResults(results)) => {
                 let mapped_inconsistent_results = results
@@ -469,6 +732,15 @@ impl<T: std::fmt::Debug + std::cmp::PartialEq + Clone> ReducedResult<T> {
                     EvmRpcError::ValidationError(e) => {
                         Err(MultiCallError::ConsistentEvmRpcCanisterError(e.to_string()))
                     }
+                    EvmRpcError::ResultSetTooLarge(e) => {
+                        Err(MultiCallError::ConsistentJsonRpcError {
+                            code: e.code,
+                            message: e.message,
+                        })
+                    }
+                    EvmRpcError::RateLimited { retry_after_secs } => {
+                        Err(MultiCallError::ConsistentRateLimited { retry_after_secs })
+                    }
                 },
             },
             EvmMultiRpcResult::Inconsistent(result) => {
@@ -677,12 +949,28 @@ impl Reduce for EvmMultiRpcResult<EvmBlock> {
                 Ok::<Block, String>(Block {
                     number: BlockNumber::from(block.number),
                     base_fee_per_gas: Wei::from(block.base_fee_per_gas.expect("BUG: must be present in blocks after the London Upgrade / EIP-1559, which pre-dates the minter")),
-
+                    timestamp: BlockTimestamp::from(block.timestamp),
                 })
             })
     }
 }
 
+impl Reduce for EvmMultiRpcResult<Option<EvmBlock>> {
+    type Item = Option<Block>;
+
+    fn reduce(self) -> ReducedResult<Self::Item> {
+        ReducedResult::from_multi_result(self)
+            .reduce_with_equality()
+            .map_reduce(&|block: Option<EvmBlock>| {
+                Ok::<Option<Block>, String>(block.map(|block| Block {
+                    number: BlockNumber::from(block.number),
+                    base_fee_per_gas: Wei::from(block.base_fee_per_gas.expect("BUG: must be present in blocks after the London Upgrade / EIP-1559, which pre-dates the minter")),
+                    timestamp: BlockTimestamp::from(block.timestamp),
+                }))
+            })
+    }
+}
+
 impl Reduce for EvmMultiRpcResult<Block> {
     type Item = Block;
 
@@ -699,26 +987,6 @@ impl Reduce for EvmMultiRpcResult<Vec<EvmLogEntry>> {
             logs.into_iter().map(map_single_log).collect()
         }
 
-        fn map_single_log(log: EvmLogEntry) -> Result<LogEntry, String> {
-            Ok(LogEntry {
-                address: Address::new(log.address.into()),
-                topics: log
-                    .topics
-                    .into_iter()
-                    .map(|t| FixedSizeData(t.into()))
-                    .collect(),
-                data: Data(log.data.into()),
-                block_number: log.block_number.map(BlockNumber::from),
-                transaction_hash: log.transaction_hash.map(|h| Hash(h.into())),
-                transaction_index: log
-                    .transaction_index
-                    .map(|i| Quantity::from_be_bytes(i.into_be_bytes())),
-                block_hash: log.block_hash.map(|h| Hash(h.into())),
-                log_index: log.log_index.map(LogIndex::from),
-                removed: log.removed,
-            })
-        }
-
         ReducedResult::from_multi_result(self)
             .reduce_with_equality()
             .map_reduce(&map_logs)
@@ -747,38 +1015,82 @@ impl Reduce for EvmMultiRpcResult<Hex> {
     }
 }
 
+fn map_single_log(log: EvmLogEntry) -> Result<LogEntry, String> {
+    Ok(LogEntry {
+        address: Address::new(log.address.into()),
+        topics: log
+            .topics
+            .into_iter()
+            .map(|t| FixedSizeData(t.into()))
+            .collect(),
+        data: Data(log.data.into()),
+        block_number: log.block_number.map(BlockNumber::from),
+        transaction_hash: log.transaction_hash.map(|h| Hash(h.into())),
+        transaction_index: log
+            .transaction_index
+            .map(|i| Quantity::from_be_bytes(i.into_be_bytes())),
+        block_hash: log.block_hash.map(|h| Hash(h.into())),
+        log_index: log.log_index.map(LogIndex::from),
+        removed: log.removed,
+    })
+}
+
+fn map_transaction_receipt(
+    evm_receipt: EvmTransactionReceipt,
+) -> Result<TransactionReceipt, String> {
+    Ok(TransactionReceipt {
+        block_hash: Hash(evm_receipt.block_hash.into()),
+        block_number: BlockNumber::from(evm_receipt.block_number),
+        effective_gas_price: WeiPerGas::from(evm_receipt.effective_gas_price),
+        gas_used: GasAmount::from(evm_receipt.gas_used),
+        status: TransactionStatus::try_from(
+            evm_receipt
+                .status
+                .and_then(|s| s.as_ref().0.to_u8())
+                .ok_or("invalid transaction status")?,
+        )?,
+        transaction_hash: Hash(evm_receipt.transaction_hash.into()),
+    })
+}
+
 impl Reduce for EvmMultiRpcResult<Option<EvmTransactionReceipt>> {
     type Item = Option<TransactionReceipt>;
 
     fn reduce(self) -> ReducedResult<Self::Item> {
-        fn map_transaction_receipt(
-            receipt: Option<EvmTransactionReceipt>,
-        ) -> Result<Option<TransactionReceipt>, String> {
-            receipt
-                .map(|evm_receipt| {
-                    Ok(TransactionReceipt {
-                        block_hash: Hash(evm_receipt.block_hash.into()),
-                        block_number: BlockNumber::from(evm_receipt.block_number),
-                        effective_gas_price: WeiPerGas::from(evm_receipt.effective_gas_price),
-                        gas_used: GasAmount::from(evm_receipt.gas_used),
-                        status: TransactionStatus::try_from(
-                            evm_receipt
-                                .status
-                                .and_then(|s| s.as_ref().0.to_u8())
-                                .ok_or("invalid transaction status")?,
-                        )?,
-                        transaction_hash: Hash(evm_receipt.transaction_hash.into()),
-                    })
-                })
-                .transpose()
-        }
-
         ReducedResult::from_multi_result(self)
             .reduce_with_equality()
-            .map_reduce(&map_transaction_receipt)
+            .map_reduce(&|receipt: Option<EvmTransactionReceipt>| {
+                receipt.map(map_transaction_receipt).transpose()
+            })
     }
 }
 
+/// Same as reducing to a [`TransactionReceipt`], but keeps the logs emitted by the transaction.
+fn reduce_transaction_receipt_with_logs(
+    results: EvmMultiRpcResult<Option<EvmTransactionReceipt>>,
+) -> ReducedResult<Option<TransactionReceiptWithLogs>> {
+    fn map_transaction_receipt_with_logs(
+        receipt: Option<EvmTransactionReceipt>,
+    ) -> Result<Option<TransactionReceiptWithLogs>, String> {
+        receipt
+            .map(|mut evm_receipt| {
+                let logs = std::mem::take(&mut evm_receipt.logs)
+                    .into_iter()
+                    .map(map_single_log)
+                    .collect::<Result<_, _>>()?;
+                Ok(TransactionReceiptWithLogs {
+                    receipt: map_transaction_receipt(evm_receipt)?,
+                    logs,
+                })
+            })
+            .transpose()
+    }
+
+    ReducedResult::from_multi_result(results)
+        .reduce_with_equality()
+        .map_reduce(&map_transaction_receipt_with_logs)
+}
+
 impl Reduce for EvmMultiRpcResult<Option<TransactionReceipt>> {
     type Item = Option<TransactionReceipt>;
 
@@ -1,9 +1,9 @@
 use crate::evm_config::EvmNetwork;
 use crate::storage::get_rpc_api_key;
-use evm_rpc_client::evm_rpc_types::{RpcApi, RpcServices};
+use evm_rpc_client::evm_rpc_types::{RpcApi, RpcService, RpcServices};
 use minicbor::{Decode, Encode};
 
-#[derive(Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Encode, Decode, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum Provider {
     #[n(0)]
     Ankr,
@@ -18,6 +18,14 @@ pub enum Provider {
 }
 
 impl Provider {
+    pub const ALL: [Provider; 5] = [
+        Provider::Ankr,
+        Provider::LlamaNodes,
+        Provider::PublicNode,
+        Provider::DRPC,
+        Provider::Alchemy,
+    ];
+
     pub fn get_url_with_api_key(&self, url: &str) -> String {
         match get_rpc_api_key(*self) {
             Some(api_key) => format!("{}{}", url, api_key),
@@ -166,19 +174,28 @@ pub fn get_custom_providers(network: EvmNetwork, providers: Vec<Provider>) -> Rp
     }
 }
 
+/// Providers queried by [`get_providers`].
+/// LlamaNodes is excluded for its large number of errors and latency.
+pub const DEFAULT_PROVIDERS: [Provider; 4] = [
+    Provider::Ankr,
+    Provider::PublicNode,
+    Provider::DRPC,
+    Provider::Alchemy,
+];
+
 pub fn get_providers(network: EvmNetwork) -> RpcServices {
-    let config = get_network_config(network);
-    let chain_id = network.chain_id();
-    let services = vec![
-        create_rpc_service(config.ankr_url, Provider::Ankr),
-        create_rpc_service(config.public_node_url, Provider::PublicNode),
-        create_rpc_service(config.drpc_url, Provider::DRPC),
-        create_rpc_service(config.alchemy_url, Provider::Alchemy),
-    ];
-    // Excluding LlamaNodes for large number of errors and latency
-    //if let Some(llama_url) = config.llama_nodes_url {
-    //    services.insert(0, create_rpc_service(llama_url, Provider::LlamaNodes));
-    //}
+    get_custom_providers(network, DEFAULT_PROVIDERS.to_vec())
+}
 
-    RpcServices::Custom { chain_id, services }
+/// Returns the provider whose URL is used by `service` on the given network, if any.
+pub fn provider_of(network: EvmNetwork, service: &RpcService) -> Option<Provider> {
+    let RpcService::Custom(api) = service else {
+        return None;
+    };
+    Provider::ALL.into_iter().find(|provider| {
+        matches!(
+            get_one_provider(network, *provider),
+            RpcServices::Custom { services, .. } if services.iter().any(|s| s.url == api.url)
+        )
+    })
 }
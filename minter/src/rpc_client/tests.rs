@@ -4,7 +4,7 @@ mod providers {
 
     use crate::{
         evm_config::EvmNetwork,
-        rpc_client::providers::{get_providers, Provider},
+        rpc_client::providers::{get_one_provider, get_providers, provider_of, Provider},
         storage::set_rpc_api_key,
     };
 
@@ -54,6 +54,38 @@ mod providers {
         }
     }
 
+    #[test]
+    fn should_map_rpc_service_back_to_provider() {
+        set_rpc_api_key(Provider::Alchemy, "Test_key_Alchemy".to_string());
+
+        for provider in Provider::ALL {
+            let RpcServices::Custom { services, .. } =
+                get_one_provider(EvmNetwork::Sepolia, provider)
+            else {
+                panic!("expected custom providers");
+            };
+            for service in services {
+                assert_eq!(
+                    provider_of(
+                        EvmNetwork::Sepolia,
+                        &evm_rpc_client::evm_rpc_types::RpcService::Custom(service)
+                    ),
+                    Some(provider)
+                );
+            }
+        }
+        assert_eq!(
+            provider_of(
+                EvmNetwork::Sepolia,
+                &evm_rpc_client::evm_rpc_types::RpcService::Custom(RpcApi {
+                    url: "https://eth-sepolia.g.alchemy.com/v2/other_key".to_string(),
+                    headers: None,
+                })
+            ),
+            None
+        );
+    }
+
     #[test]
     fn should_retrieve_ethereum_providers() {
         set_rpc_api_key(Provider::Ankr, "Test_key_Ankr".to_string());
@@ -558,7 +590,7 @@ mod multi_rpc_results {
     mod reduce_with_min_by_key {
         use super::*;
         use crate::{
-            numeric::{BlockNumber, Wei},
+            numeric::{BlockNumber, BlockTimestamp, Wei},
             rpc_client::ReducedResult,
             rpc_declarations::Block,
         };
@@ -571,6 +603,7 @@ mod multi_rpc_results {
                     Ok(Block {
                         number: BlockNumber::new(0x411cda),
                         base_fee_per_gas: Wei::new(0x10),
+                        timestamp: BlockTimestamp::new(0x6744b156),
                     }),
                 ),
                 (
@@ -578,6 +611,7 @@ mod multi_rpc_results {
                     Ok(Block {
                         number: BlockNumber::new(0x411cd9),
                         base_fee_per_gas: Wei::new(0x10),
+                        timestamp: BlockTimestamp::new(0x6744b156),
                     }),
                 ),
             ];
@@ -592,6 +626,7 @@ mod multi_rpc_results {
                 Ok(Block {
                     number: BlockNumber::new(0x411cd9),
                     base_fee_per_gas: Wei::new(0x10),
+                    timestamp: BlockTimestamp::new(0x6744b156),
                 })
             );
         }
@@ -980,6 +1015,44 @@ mod multi_rpc_results {
                 .has_http_outcall_error_matching(always_true));
         }
     }
+
+    mod is_result_set_too_large {
+        use super::*;
+        use crate::rpc_client::{MultiCallError, ReducedResult};
+        use evm_rpc_client::evm_rpc_types::JsonRpcError;
+
+        #[test]
+        fn should_match_result_set_too_large_error_of_any_provider() {
+            let error: ReducedResult<u128> =
+                ReducedResult::from_multi_result(EvmMultiRpcResult::Inconsistent(vec![
+                    (
+                        EvmRpcService::EthSepolia(EthSepoliaService::PublicNode),
+                        Err(EvmRpcError::ResultSetTooLarge(JsonRpcError {
+                            code: -32_005,
+                            message: "query returned more than 10000 results".to_string(),
+                        })),
+                    ),
+                    (EvmRpcService::EthSepolia(EthSepoliaService::Alchemy), Ok(1)),
+                ]));
+
+            assert!(error.result.err().unwrap().is_result_set_too_large());
+        }
+
+        #[test]
+        fn should_classify_json_rpc_errors_by_message() {
+            let too_large: MultiCallError<String> = MultiCallError::ConsistentJsonRpcError {
+                code: -32_602,
+                message: "Log response size exceeded.".to_string(),
+            };
+            let other: MultiCallError<String> = MultiCallError::ConsistentJsonRpcError {
+                code: -32_000,
+                message: "header not found".to_string(),
+            };
+
+            assert!(too_large.is_result_set_too_large());
+            assert!(!other.is_result_set_too_large());
+        }
+    }
 }
 
 mod get_transaction_receipt {
@@ -1432,7 +1505,7 @@ mod evm_rpc_conversion {
         //prop_map is limited to tuples of at most 11 elements, so we group the Nat and String fields
         (
             array::uniform2(option::of(arb_nat_256())),
-            array::uniform5(arb_nat_256()),
+            array::uniform4(arb_nat_256()),
             arb_hex(),
             array::uniform6(arb_hex32()),
             arb_hex256(),
@@ -1443,7 +1516,7 @@ mod evm_rpc_conversion {
             .prop_map(
                 move |(
                     [difficulty, total_difficulty],
-                    [gas_limit, gas_used, nonce, size, timestamp],
+                    [gas_limit, gas_used, nonce, size],
                     extra_data,
                     [hash, mix_hash, parent_hash, receipts_root, sha3_uncles, state_root],
                     logs_bloom,
@@ -1467,7 +1540,7 @@ mod evm_rpc_conversion {
                     sha3_uncles,
                     size,
                     state_root,
-                    timestamp,
+                    timestamp: Nat256::from(minter_block.timestamp),
                     total_difficulty,
                     transactions,
                     transactions_root,
@@ -1658,3 +1731,86 @@ mod evm_rpc_conversion {
         proptest::result::maybe_ok(arb_nat_256(), arb_evm_rpc_error())
     }
 }
+
+mod provider_disagreement {
+    use crate::rpc_client::{provider_disagreement_event, ETH_GET_LOGS_METHOD};
+    use crate::state::event::EventType;
+    use evm_rpc_client::evm_rpc_types::{
+        EthSepoliaService, Hex, Hex20, Hex32, LogEntry as EvmLogEntry, Nat256, RpcApi,
+        RpcResult as EvmRpcResult, RpcService as EvmRpcService,
+    };
+
+    fn log_entry(data: Vec<u8>) -> EvmLogEntry {
+        EvmLogEntry {
+            address: Hex20::from([1_u8; 20]),
+            topics: vec![Hex32::from([2_u8; 32])],
+            data: Hex::from(data),
+            block_number: Some(Nat256::from(150_u64)),
+            transaction_hash: Some(Hex32::from([3_u8; 32])),
+            transaction_index: Some(Nat256::from(0_u64)),
+            block_hash: Some(Hex32::from([4_u8; 32])),
+            log_index: Some(Nat256::from(0_u64)),
+            removed: false,
+        }
+    }
+
+    #[test]
+    fn should_hash_each_provider_response() {
+        let results: Vec<(EvmRpcService, EvmRpcResult<Vec<EvmLogEntry>>)> = vec![
+            (
+                EvmRpcService::EthSepolia(EthSepoliaService::Ankr),
+                Ok(vec![log_entry(vec![1, 2, 3])]),
+            ),
+            (
+                EvmRpcService::EthSepolia(EthSepoliaService::PublicNode),
+                Ok(vec![log_entry(vec![4, 5, 6])]),
+            ),
+        ];
+
+        let event = provider_disagreement_event(
+            ETH_GET_LOGS_METHOD,
+            "100..=200".to_string(),
+            &results,
+            1_700_000_000,
+        );
+
+        match event {
+            EventType::ProviderDisagreement {
+                method,
+                block_range_or_hash,
+                per_provider_hash,
+                timestamp,
+            } => {
+                assert_eq!(method, "eth_getLogs");
+                assert_eq!(block_range_or_hash, "100..=200");
+                assert_eq!(timestamp, 1_700_000_000);
+                assert_eq!(per_provider_hash.len(), 2);
+                assert_eq!(per_provider_hash[0].0, "EthSepolia(Ankr)");
+                assert_eq!(per_provider_hash[1].0, "EthSepolia(PublicNode)");
+                assert_ne!(per_provider_hash[0].1, per_provider_hash[1].1);
+            }
+            _ => panic!("unexpected event {event:?}"),
+        }
+    }
+
+    #[test]
+    fn should_not_leak_api_key_of_custom_providers() {
+        let results: Vec<(EvmRpcService, EvmRpcResult<Vec<EvmLogEntry>>)> = vec![(
+            EvmRpcService::Custom(RpcApi {
+                url: "https://rpc.ankr.com/eth_sepolia/secret-api-key".to_string(),
+                headers: None,
+            }),
+            Ok(vec![]),
+        )];
+
+        let event =
+            provider_disagreement_event(ETH_GET_LOGS_METHOD, "100..=200".to_string(), &results, 0);
+
+        match event {
+            EventType::ProviderDisagreement {
+                per_provider_hash, ..
+            } => assert_eq!(per_provider_hash[0].0, "rpc.ankr.com"),
+            _ => panic!("unexpected event {event:?}"),
+        }
+    }
+}
@@ -1,7 +1,11 @@
+//! Requests and responses of the EVM JSON-RPC methods used by the minter.
+//!
+//! [`parse_fee_history`] is fuzzed, see `minter/FUZZING.md`.
+
 use crate::candid_types::CandidBlockTag;
 use crate::numeric::TransactionNonce;
 use crate::numeric::WeiPerBlobGas;
-use crate::numeric::{BlockNumber, GasAmount, LogIndex, Wei, WeiPerGas};
+use crate::numeric::{BlockNumber, BlockTimestamp, GasAmount, LogIndex, Wei, WeiPerGas};
 use evm_rpc_client::eth_types::{serde_data, Address};
 use evm_rpc_client::evm_rpc_types::SendRawTransactionStatus as EvmSendRawTransactionStatus;
 use minicbor::{Decode, Encode};
@@ -118,6 +122,20 @@ impl UpperHex for FixedSizeData {
     }
 }
 
+/// A 32-byte Ethereum hash, e.g. of a transaction or a block.
+///
+/// Hashes can be deduplicated in a `HashSet`:
+/// ```
+/// use evm_minter::rpc_declarations::Hash;
+/// use std::collections::HashSet;
+///
+/// let tx_hash = Hash::from([0x11; 32]);
+/// let tx_hashes: HashSet<Hash> = [tx_hash, tx_hash, Hash::from([0x22; 32])].into_iter().collect();
+///
+/// assert_eq!(tx_hashes.len(), 2);
+/// assert!(tx_hashes.contains(&tx_hash));
+/// assert!(!tx_hashes.contains(&Hash::from([0x33; 32])));
+/// ```
 #[derive(
     Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Decode, Deserialize, Encode, Serialize,
 )]
@@ -167,6 +185,18 @@ impl std::str::FromStr for Hash {
     }
 }
 
+impl From<[u8; 32]> for Hash {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl AsRef<[u8]> for Hash {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 /// Block tags.
 /// See <https://ethereum.org/en/developers/docs/apis/json-rpc/#default-block>
 #[derive(Debug, Default, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -416,6 +446,8 @@ pub struct Block {
     pub number: BlockNumber,
     /// Base fee value of this block
     pub base_fee_per_gas: Wei,
+    /// Unix timestamp in seconds at which the block was collated.
+    pub timestamp: BlockTimestamp,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Decode, Deserialize, Encode, Serialize)]
@@ -454,6 +486,15 @@ impl TransactionReceipt {
     }
 }
 
+/// A [`TransactionReceipt`] together with the logs emitted by the transaction.
+///
+/// The logs are only needed to check the outcome of some withdrawals and are not persisted.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TransactionReceiptWithLogs {
+    pub receipt: TransactionReceipt,
+    pub logs: Vec<LogEntry>,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Encode, Decode)]
 #[serde(try_from = "ethnum::u256", into = "ethnum::u256")]
 pub enum TransactionStatus {
@@ -552,6 +593,16 @@ pub struct FeeHistory {
     pub reward: Vec<Vec<WeiPerGas>>,
 }
 
+impl FeeHistory {
+    /// Highest block of the returned range.
+    pub fn newest_block(&self) -> BlockNumber {
+        let block_count = self.reward.len().saturating_sub(1) as u64;
+        self.oldest_block
+            .checked_add(BlockNumber::from(block_count))
+            .unwrap_or(BlockNumber::MAX)
+    }
+}
+
 pub fn parse_fee_history(fee_history: String) -> Option<FeeHistory> {
     let fee_history_parsed = serde_json::from_str::<FeeHistory>(&fee_history).ok()?;
 
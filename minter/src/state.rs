@@ -3,14 +3,27 @@ pub mod tests;
 
 pub mod audit;
 pub mod balances;
+pub mod daily_stats;
 pub mod event;
+pub mod feature_flags;
+pub mod scraping_gaps;
+pub mod snapshot;
 pub mod transactions;
 
 use crate::{
-    candid_types::{dex_orders::DexOrderArgs, SwapStatus},
+    candid_types::{
+        dex_orders::{
+            DexOrderArgs, DEFAULT_REFUND_DEADLINE_WINDOW_SECS, MAX_DEADLINE_HORIZON_SECS,
+            MIN_REFUND_DEADLINE_WINDOW_SECS,
+        },
+        Erc20TransferKind, SwapStatus, SymbolCollisionPolicy,
+    },
     numeric::Erc20Value,
+    rpc_client::providers::{Provider, DEFAULT_PROVIDERS},
     state::{
         balances::GasTank,
+        daily_stats::DailyStats,
+        scraping_gaps::ScrapingGaps,
         transactions::{data::TransactionCallData, ExecuteSwapRequest},
     },
     tx_id::SwapTxId,
@@ -25,27 +38,36 @@ use std::{
 
 use crate::{
     candid_types::DepositStatus,
+    checked_amount::CheckedAmountOf,
     contract_logs::{EventSource, ReceivedContractEvent},
     erc20::{ERC20Token, ERC20TokenSymbol},
-    evm_config::EvmNetwork,
+    evm_config::{ConfirmationPolicy, EvmNetwork, GasLimitKind, GasLimits, MAX_CONFIRMATIONS},
+    fee_collector::PendingFeeWithdrawal,
     lifecycle::UpgradeArg,
-    logs::DEBUG,
+    logs::{DEBUG, INFO},
+    management::CYCLES_PER_SIGNATURE,
     map::DedupMultiKeyMap,
     numeric::{
-        BlockNumber, IcrcValue, LedgerBurnIndex, LedgerMintIndex, LedgerReleaseIndex,
-        TransactionNonce, Wei, WeiPerGas,
+        BlockNumber, BlockTimestamp, GasAmount, IcrcValue, LedgerBurnIndex, LedgerMintIndex,
+        LedgerReleaseIndex, TransactionNonce, Wei, WeiPerGas,
     },
     rpc_declarations::{BlockTag, Hash, TransactionReceipt, TransactionStatus},
     state::transactions::NativeWithdrawalRequest,
-    tx::gas_fees::GasFeeEstimate,
+    tx::gas_fees::{l1_fee_estimate, FeeCap, GasFeeConfig, GasFeeEstimate},
+    tx::gas_usd::MaxFeeUsd,
 };
-use balances::{Erc20Balances, IcrcBalances, NativeBalance};
-use candid::Principal;
+use balances::{Erc20Balances, IcrcBalances, NativeBalance, TokenVolume};
+use candid::{Nat, Principal};
+use feature_flags::{FeatureFlag, FeatureFlags};
 use ic_canister_log::log;
+use icrc_ledger_types::icrc1::account::Account;
 use libsecp256k1::{PublicKey, PublicKeyFormat};
+use minicbor::{Decode, Encode};
 use serde_bytes::ByteBuf;
 use strum_macros::EnumIter;
-use transactions::{Erc20WithdrawalRequest, WithdrawalRequest, WithdrawalTransactions};
+use transactions::{
+    Erc20Approve, Erc20WithdrawalRequest, WithdrawalRequest, WithdrawalTransactions,
+};
 
 use ic_cdk::management_canister::EcdsaPublicKeyResult;
 
@@ -55,17 +77,36 @@ thread_local! {
 
 pub const MAIN_DERIVATION_PATH: Vec<ByteBuf> = vec![];
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+/// Maximum number of helper contracts whose logs are scraped.
+/// Each helper contract adds to the cost of the `eth_getLogs` outcalls.
+pub const MAX_HELPER_CONTRACT_ADDRESSES: usize = 10;
+
+/// How long the presence of code at a withdrawal destination is cached, in nanoseconds.
+pub const CONTRACT_DESTINATION_CACHE_TTL_NANOS: u64 = 60 * 60 * 1_000_000_000;
+
+/// Maximum number of withdrawal destinations whose check is cached.
+pub const MAX_CACHED_CONTRACT_DESTINATIONS: usize = 1_000;
+
+/// How long the metadata of the base ledger of a wrapped ICRC token is cached, in nanoseconds.
+pub const WRAPPED_ICRC_METADATA_TTL_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Cycles kept out of the estimated signing capacity, so that the minter does not
+/// run into its freezing threshold.
+pub const SIGNING_CYCLES_RESERVE: u128 = 1_000_000_000_000;
+
+#[derive(Debug, Eq, PartialEq, Clone, Encode, Decode)]
 pub enum InvalidEventReason {
     /// Deposit or release is invalid and was never minted or released.
     /// This is most likely due to a user error (e.g., user's IC principal cannot be decoded)
     /// or there is a critical issue in the logs returned from the JSON-RPC providers.
-    InvalidEvent(String),
+    #[n(0)]
+    InvalidEvent(#[n(0)] String),
 
     /// Deposit is valid but it's unknown whether it was minted or not,
     /// most likely because there was an unexpected panic in the callback.
     /// The deposit is quarantined to avoid any double minting and
     /// will not be further processed without manual intervention.
+    #[n(1)]
     QuarantinedDeposit,
 }
 
@@ -92,49 +133,149 @@ pub enum InvalidStateError {
     InvalidLastScrapedBlockNumber(String),
     InvalidMinimumMaximumPriorityFeePerGas(String),
     InvalidFeeInput(String),
+    InvalidGasFeeConfig(String),
+    InvalidConfirmationPolicy(String),
+    InvalidGasLimit(String),
+    InvalidRefundDeadlineWindow(String),
+    InvalidFeeCap(String),
+}
+
+/// Addresses that withdrawals must not send funds to, since the funds would be
+/// credited back to the minter, trigger a deposit, or be lost.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ProtectedDestination {
+    ZeroAddress,
+    MinterAddress,
+    HelperContract,
+    SwapContract,
+    TokenContract,
+}
+
+impl Display for ProtectedDestination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtectedDestination::ZeroAddress => write!(f, "the zero address"),
+            ProtectedDestination::MinterAddress => write!(f, "the minter address"),
+            ProtectedDestination::HelperContract => write!(f, "a helper contract"),
+            ProtectedDestination::SwapContract => write!(f, "the swap contract"),
+            ProtectedDestination::TokenContract => write!(f, "the token contract"),
+        }
+    }
 }
 
 // events for minted(wrapped) erc20 tokens
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
 pub struct MintedEvent {
+    #[n(0)]
     pub event: ReceivedContractEvent,
+    #[cbor(n(1), with = "crate::cbor::id")]
     pub mint_block_index: LedgerMintIndex,
+    #[n(2)]
     pub token_symbol: String,
+    #[n(3)]
     pub erc20_contract_address: Option<Address>,
 }
 
 // events for minted(wrapped) erc20 tokens to appic dex
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
 pub struct MintedToDex {
+    #[n(0)]
     pub event: ReceivedContractEvent,
+    #[cbor(n(1), with = "crate::cbor::id")]
     pub mint_block_index: LedgerMintIndex,
+    #[cbor(n(2), with = "crate::cbor::principal")]
     pub minted_token: Principal,
+    #[n(3)]
     pub erc20_contract_address: Option<Address>,
+    #[n(4)]
     pub tx_id: SwapTxId,
 }
 
 // events for minted(wrapped) erc20 tokens to appic dex
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
 pub struct NotifiedToAppiDex {
+    #[n(0)]
     pub event: ReceivedContractEvent,
+    #[n(1)]
     pub tx_id: SwapTxId,
 }
 
 // events for unlocked(unwrapped) icp tokens
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
 pub struct ReleasedEvent {
+    #[n(0)]
     pub event: ReceivedContractEvent,
+    #[cbor(n(1), with = "crate::cbor::id")]
     pub transfer_block_index: LedgerReleaseIndex,
+    #[n(2)]
     pub transfer_fee: IcrcValue,
+    #[cbor(n(3), with = "crate::cbor::principal")]
     pub icrc_ledger: Principal,
+    #[n(4)]
     pub erc20_contract_address: Address,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
 pub struct TwinUSDCInfo {
+    #[n(0)]
     pub address: Address,
+    #[cbor(n(1), with = "crate::cbor::principal")]
     pub ledger_id: Principal,
+    #[n(2)]
+    pub decimals: u8,
+}
+
+impl TwinUSDCInfo {
+    /// Value of `native_amount` in twin USDC, converted with the decimals of the token.
+    pub fn usdc_from_native_wei(
+        &self,
+        native_amount: Wei,
+        native_price_usd: f64,
+    ) -> Result<Erc20Value, String> {
+        MaxFeeUsd::twin_usdc_from_native_wei(native_amount, native_price_usd, self.decimals)
+    }
+}
+
+/// Number of decimals the native token has on the EVM side, amounts on the native ledger
+/// are only equal to amounts in wei if the ledger uses the same number of decimals.
+pub const NATIVE_TOKEN_DECIMALS: u8 = 18;
+
+/// Symbol and decimals as reported by the native ledger.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct NativeLedgerMetadata {
+    #[n(0)]
+    pub symbol: String,
+    #[n(1)]
+    pub decimals: u8,
+}
+
+/// Transaction of the deployer contract that deployed a wrapped ICRC token.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct WrappedIcrcDeployment {
+    #[n(0)]
+    pub transaction_hash: Hash,
+    #[n(1)]
+    pub block_number: BlockNumber,
+}
+
+/// Symbol, decimals and transfer fee as reported by the base ledger of a wrapped ICRC token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IcrcLedgerMetadata {
+    pub symbol: String,
     pub decimals: u8,
+    pub transfer_fee: IcrcValue,
+    /// The IC time at which the ledger was queried.
+    pub refreshed_at: u64,
+}
+
+/// ERC-20 approval granted by a finalized `approve` transaction of the minter.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct OnchainApproval {
+    /// The IC time at which the approval request was accepted.
+    #[n(0)]
+    pub granted_at: u64,
+    #[n(1)]
+    pub transaction_hash: Hash,
 }
 
 impl MintedEvent {
@@ -159,15 +300,22 @@ pub struct State {
     pub native_ledger_transfer_fee: Wei,
     pub native_minimum_withdrawal_amount: Wei,
 
-    pub block_height: BlockTag,
+    pub confirmation_policy: ConfirmationPolicy,
     pub first_scraped_block_number: BlockNumber,
     pub last_scraped_block_number: BlockNumber,
     pub last_observed_block_number: Option<BlockNumber>,
     pub last_observed_block_time: Option<u64>,
+    // timestamp reported by the chain for the last observed block
+    pub last_observed_block_timestamp: Option<BlockTimestamp>,
+    // timestamp of the last scraped block, only refreshed once per scraping round
+    pub last_scraped_block_timestamp: Option<(BlockNumber, BlockTimestamp)>,
 
     // latest requested block to scrape for getting deposit or swap events
     pub lastest_requested_block_to_scrape: Option<BlockNumber>,
 
+    // last time `request_scraping_logs` was successfully called, 0 if never
+    pub last_log_scraping_request_time: u64,
+
     // after icp-evm bridge update we have both events to mint and events to release locked
     // icp tokens in case the wrapped ones on the evm side are already burnt
     pub events_to_mint: BTreeMap<EventSource, ReceivedContractEvent>,
@@ -177,6 +325,11 @@ pub struct State {
     pub released_events: BTreeMap<EventSource, ReleasedEvent>,
     pub invalid_events: BTreeMap<EventSource, InvalidEventReason>,
 
+    // Log entries reported as removed by a chain reorganisation, so that the removal of
+    // each entry is only recorded once.
+    // Computed based on audit events.
+    pub removed_log_events: BTreeSet<EventSource>,
+
     // received release event was correct, but there was a problem with releasing,
     // e.g. canister out of cycles or unknown transfer fee.
     pub quarantined_releases: BTreeMap<EventSource, ReceivedContractEvent>,
@@ -184,6 +337,10 @@ pub struct State {
     pub withdrawal_transactions: WithdrawalTransactions,
     pub skipped_blocks: BTreeSet<BlockNumber>,
 
+    // Block ranges whose logs were never successfully scraped.
+    // Computed based on audit events.
+    pub scraping_gaps: ScrapingGaps,
+
     // Current balance of Native held by the minter.
     // Computed based on audit events.
     pub native_balance: NativeBalance,
@@ -197,11 +354,42 @@ pub struct State {
     // /// Per-principal lock for pending withdrawals
     pub pending_withdrawal_principals: BTreeSet<Principal>,
 
+    // Last time each principal triggered a manual deposit check.
+    // Kept in memory only, so that the rate limit is reset by upgrades. Entries older than
+    // `MANUAL_DEPOSIT_CHECK_INTERVAL` are pruned.
+    pub last_manual_deposit_checks: BTreeMap<Principal, u64>,
+
+    // Last time a provider disagreement was recorded for each RPC method.
+    // Computed based on audit events.
+    pub last_provider_disagreements: BTreeMap<String, u64>,
+
     /// Locks preventing concurrent execution timer tasks
     pub active_tasks: HashSet<TaskType>,
 
+    /// Deposit events whose mint was dispatched to the ledger but has not resolved yet
+    pub mints_in_flight: BTreeSet<EventSource>,
+
+    /// Deposit events whose log was removed while their mint was in flight. The removal is
+    /// recorded once the mint resolved, see `MintGuard`.
+    pub reorged_mints_in_flight: BTreeSet<EventSource>,
+
+    /// Whether code was deployed at each recently checked withdrawal destination, with the
+    /// IC time of the check. Not recorded as an event, so that the upgrade resets it.
+    pub contract_destinations: BTreeMap<Address, (bool, u64)>,
+
     // Transaction price estimate
     pub last_transaction_price_estimate: Option<(u64, GasFeeEstimate)>,
+    // Newest block covered by the fee history used for the transaction price estimate
+    pub last_transaction_price_estimate_block: Option<BlockNumber>,
+    // L1 fee of a sample transaction fetched from the gas price oracle, only used on Base
+    pub current_l1_fee: Option<Wei>,
+    // Estimated cycles spent on ledger burn calls since the last upgrade, see
+    // `IcrcBoundedRuntime::estimated_call_cost`. Not recorded as an event, so that the upgrade resets it.
+    pub total_ledger_call_cycles_spent: u128,
+    // Number of `sign_with_ecdsa` calls and the cycles they cost, i.e. the cycles attached to them
+    // minus the refunded ones, since the last upgrade
+    pub ecdsa_signing_calls: u64,
+    pub ecdsa_signing_cycles_spent: u128,
     pub last_native_token_usd_price_estimate: Option<(u64, f64)>,
 
     /// fees charged for withdraw and mint_wrapped icp tokens operations in order to cover signing cost,
@@ -224,8 +412,22 @@ pub struct State {
     /// - value: IcrcValue token transfer fee
     pub wrapped_icrc_tokens: DedupMultiKeyMap<Principal, Address, Option<IcrcValue>>,
 
+    // Deployment transaction of each wrapped ICRC token, keyed by the wrapped ERC-20 address.
+    // Computed based on audit events.
+    pub wrapped_icrc_deployments: BTreeMap<Address, WrappedIcrcDeployment>,
+
+    /// Metadata of the base ledger of each wrapped ICRC token, keyed by ledger ID.
+    /// Not recorded as an event, so that the upgrade resets it.
+    pub wrapped_icrc_metadata: BTreeMap<Principal, IcrcLedgerMetadata>,
+
     pub min_max_priority_fee_per_gas: WeiPerGas,
 
+    // Operator settings for gas fee estimation, defaults are used if not set
+    pub gas_fee_config: Option<GasFeeConfig>,
+
+    // Gas limits of the transactions sent by the minter, overridable by upgrades
+    pub gas_limits: GasLimits,
+
     // Appic swapper canister_id
     pub dex_canister_id: Option<Principal>,
 
@@ -246,8 +448,30 @@ pub struct State {
     // canister_fee in twin usdc amount for covering signing cost
     pub canister_signing_fee_twin_usdc_amount: Option<Erc20Value>,
 
-    // is the maximum approval given to the swap contract
-    pub is_swapping_active: bool,
+    // Switches of the minter behavior, e.g. whether swapping is active or the minter is
+    // draining before an upgrade
+    pub feature_flags: FeatureFlags,
+
+    // Account to which the fees held in `FEES_SUBACCOUNT` of the minter are withdrawn,
+    // see `withdraw_collected_fees`
+    pub fee_collector: Option<Account>,
+
+    // Fees held in `FEES_SUBACCOUNT` of the minter by ledger, according to the events
+    pub collected_fees: BTreeMap<Principal, IcrcValue>,
+
+    // Withdrawals of collected fees by ledger whose outcome is not known yet.
+    // Computed based on audit events.
+    pub pending_fee_withdrawals: BTreeMap<Principal, PendingFeeWithdrawal>,
+
+    // Replaces `DEFAULT_REFUND_DEADLINE_WINDOW_SECS`, see `refund_deadline`
+    pub refund_deadline_window_secs: Option<u64>,
+
+    // Replaces the fee caps of the network, see `fee_cap`
+    pub fee_cap_override: Option<FeeCap>,
+
+    // IC time of the last alert about a transaction exceeding the fee cap.
+    // Computed based on audit events.
+    pub last_fee_cap_alert: Option<u64>,
 
     // gas tank
     pub gas_tank: GasTank,
@@ -259,6 +483,46 @@ pub struct State {
     // Swap requests that failed to process
     // key = swap_tx_id
     pub quarantined_dex_orders: BTreeMap<String, DexOrderArgs>,
+
+    // IC time before which each provider that kept answering HTTP 429 should not be queried.
+    // Computed based on audit events.
+    pub rate_limited_providers: BTreeMap<Provider, u64>,
+
+    // Contract addresses of the supported ERC-20 tokens whose `transfer` does not revert on
+    // failure, so that withdrawals are only finalized as successful if a `Transfer` log is found.
+    // Computed based on audit events.
+    pub non_standard_erc20_tokens: BTreeSet<Address>,
+
+    // Contract addresses of the supported ERC-20 tokens, to which native tokens are not withdrawn
+    // unless explicitly allowed since most of them cannot receive them.
+    // Computed from `erc20_tokens`.
+    pub known_erc20_contracts: BTreeSet<Address>,
+
+    // Cumulative deposit, withdrawal and reimbursement volumes of each ERC-20 token.
+    // Computed based on audit events.
+    pub token_volumes: BTreeMap<Address, TokenVolume>,
+
+    // Deposits and withdrawals per UTC day and per token ledger over the last days.
+    // Computed based on audit events.
+    pub daily_stats: DailyStats,
+
+    // Events that only refer to blocks before this one may have been pruned from the event log
+    // by `compact_event_log`, zero if the event log was never compacted.
+    // Computed based on audit events.
+    pub earliest_unpruned_block: BlockNumber,
+
+    // Symbols requested for the ERC-20 tokens whose symbol in `erc20_tokens` was disambiguated
+    // because another token already used it, keyed by contract address.
+    // Computed based on audit events.
+    pub erc20_original_symbols: BTreeMap<Address, ERC20TokenSymbol>,
+
+    // Symbol and decimals reported by the native ledger, `None` until the ledger was queried.
+    // Refreshed daily and updated by audit events when it differs from the configuration.
+    pub native_ledger_metadata: Option<NativeLedgerMetadata>,
+
+    // Outstanding ERC-20 approvals granted by the minter, keyed by (token address, spender).
+    // Computed based on audit events.
+    pub onchain_approvals: BTreeMap<(Address, Address), OnchainApproval>,
 }
 
 impl State {
@@ -271,6 +535,37 @@ impl State {
         Some(ecdsa_public_key_to_address(&pubkey))
     }
 
+    /// Returns which protected address `destination` is, if any.
+    ///
+    /// `token_contract` is the contract of the token being withdrawn, if it is not the native token.
+    pub fn protected_destination(
+        &self,
+        destination: &Address,
+        token_contract: Option<&Address>,
+    ) -> Option<ProtectedDestination> {
+        if destination == &Address::ZERO {
+            return Some(ProtectedDestination::ZeroAddress);
+        }
+        if self.minter_address().as_ref() == Some(destination) {
+            return Some(ProtectedDestination::MinterAddress);
+        }
+        if self
+            .helper_contract_addresses
+            .as_deref()
+            .unwrap_or_default()
+            .contains(destination)
+        {
+            return Some(ProtectedDestination::HelperContract);
+        }
+        if self.swap_contract_address.as_ref() == Some(destination) {
+            return Some(ProtectedDestination::SwapContract);
+        }
+        if token_contract == Some(destination) {
+            return Some(ProtectedDestination::TokenContract);
+        }
+        None
+    }
+
     pub fn validate_config(&self) -> Result<(), InvalidStateError> {
         if self.ecdsa_key_name.trim().is_empty() {
             return Err(InvalidStateError::InvalidEcdsaKeyName(
@@ -307,15 +602,299 @@ impl State {
         Ok(())
     }
 
+    /// Checks that the given upgrade argument can be applied on top of the current state.
+    ///
+    /// These checks are not part of [`State::upgrade`] since upgrade events already
+    /// recorded in the event log must still be replayable.
+    pub fn validate_upgrade_arg(&self, upgrade_arg: &UpgradeArg) -> Result<(), InvalidStateError> {
+        use std::str::FromStr;
+
+        if let Some(addr) = &upgrade_arg.helper_contract_address {
+            let contract_address = Address::from_str(addr).map_err(|e| {
+                InvalidStateError::InvalidHelperContractAddress(format!("Invalid address: {e}"))
+            })?;
+            self.validate_new_helper_contract_address(&contract_address)?;
+        }
+        if let Some(config) = &upgrade_arg.gas_fee_config {
+            GasFeeConfig::try_from(config.clone())
+                .map_err(InvalidStateError::InvalidGasFeeConfig)?;
+        }
+        for gas_limit in upgrade_arg.gas_limits.iter().flatten() {
+            GasLimits::parse_override(gas_limit.clone())
+                .map_err(InvalidStateError::InvalidGasLimit)?;
+        }
+        if let Some(cap) = &upgrade_arg.max_fee_per_gas_cap {
+            parse_fee_cap::<WeiPerGas>(cap.clone())?;
+        }
+        if let Some(cap) = &upgrade_arg.max_transaction_fee_cap {
+            parse_fee_cap::<Wei>(cap.clone())?;
+        }
+        if let Some(window) = upgrade_arg.refund_deadline_window_secs {
+            if !(MIN_REFUND_DEADLINE_WINDOW_SECS..=MAX_DEADLINE_HORIZON_SECS).contains(&window) {
+                return Err(InvalidStateError::InvalidRefundDeadlineWindow(format!(
+                    "refund_deadline_window_secs must be between {MIN_REFUND_DEADLINE_WINDOW_SECS} and {MAX_DEADLINE_HORIZON_SECS}, got {window}"
+                )));
+            }
+        }
+        if let Some(block_height) = &upgrade_arg.block_height {
+            ConfirmationPolicy::validate_block_tag(self.evm_network, block_height.clone().into())?;
+        }
+        if let Some(confirmations) = upgrade_arg.confirmations {
+            if upgrade_arg.block_height.is_some() {
+                return Err(InvalidStateError::InvalidConfirmationPolicy(
+                    "block_height and confirmations are mutually exclusive".to_string(),
+                ));
+            }
+            if confirmations > MAX_CONFIRMATIONS {
+                return Err(InvalidStateError::InvalidConfirmationPolicy(format!(
+                    "confirmations must be at most {MAX_CONFIRMATIONS}, got {confirmations}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that `address` can be added to the list of helper contracts scraped for logs.
+    ///
+    /// Addresses are compared on their bytes so the check is case-insensitive on the hex.
+    pub fn validate_new_helper_contract_address(
+        &self,
+        address: &Address,
+    ) -> Result<(), InvalidStateError> {
+        let current = self
+            .helper_contract_addresses
+            .as_deref()
+            .unwrap_or_default();
+        let mut errors = vec![];
+        if current.contains(address) {
+            errors.push(format!("{address} is already a helper contract"));
+        }
+        if self.swap_contract_address.as_ref() == Some(address) {
+            errors.push(format!("{address} is the swap contract address"));
+        }
+        if self.minter_address().as_ref() == Some(address) {
+            errors.push(format!("{address} is the minter address"));
+        }
+        if current.len() >= MAX_HELPER_CONTRACT_ADDRESSES {
+            errors.push(format!(
+                "cannot have more than {MAX_HELPER_CONTRACT_ADDRESSES} helper contracts, got {current:?}"
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidStateError::InvalidHelperContractAddress(
+                errors.join("; "),
+            ))
+        }
+    }
+
+    /// Checks invariants that must hold for any reachable state.
+    ///
+    /// Returns a description of every violated invariant.
+    pub fn validate_invariants(&self) -> Result<(), String> {
+        let mut errors = vec![];
+
+        let helpers = self
+            .helper_contract_addresses
+            .as_deref()
+            .unwrap_or_default();
+        let mut seen = BTreeSet::new();
+        let duplicates: BTreeSet<_> = helpers.iter().filter(|a| !seen.insert(*a)).collect();
+        if !duplicates.is_empty() {
+            errors.push(format!("duplicate helper contracts: {duplicates:?}"));
+        }
+        if helpers.len() > MAX_HELPER_CONTRACT_ADDRESSES {
+            errors.push(format!(
+                "{} helper contracts exceed the maximum of {MAX_HELPER_CONTRACT_ADDRESSES}",
+                helpers.len()
+            ));
+        }
+        if let Some(swap_contract) = self.swap_contract_address {
+            if helpers.contains(&swap_contract) {
+                errors.push(format!(
+                    "swap contract {swap_contract} is also a helper contract"
+                ));
+            }
+        }
+        if let Some(minter_address) = self.minter_address() {
+            if helpers.contains(&minter_address) {
+                errors.push(format!(
+                    "minter address {minter_address} is also a helper contract"
+                ));
+            }
+        }
+
+        if let Some(last_observed) = self.last_observed_block_number {
+            if self.last_scraped_block_number > last_observed {
+                errors.push(format!(
+                    "last scraped block {} is ahead of last observed block {last_observed}",
+                    self.last_scraped_block_number
+                ));
+            }
+        }
+
+        // Balances are unsigned, so a deficit shows up as pending deposits that are
+        // not covered by the tracked balance of the deposited token.
+        let mut pending_erc20: BTreeMap<Address, Erc20Value> = BTreeMap::new();
+        let pending_events = self
+            .events_to_mint
+            .values()
+            .chain(self.swap_events_to_mint_to_appic_dex.values());
+        for event in pending_events {
+            let (token, amount) = match event {
+                ReceivedContractEvent::Erc20Deposit(event) => {
+                    (event.erc20_contract_address, event.value)
+                }
+                ReceivedContractEvent::ReceivedSwapOrder(event) => {
+                    (event.token_out, event.amount_out)
+                }
+                _ => continue,
+            };
+            let pending = pending_erc20.entry(token).or_insert(Erc20Value::ZERO);
+            match pending.checked_add(amount) {
+                Some(sum) => *pending = sum,
+                None => errors.push(format!("pending deposits of {token} overflow")),
+            }
+        }
+        for (token, pending) in pending_erc20 {
+            let balance = self.erc20_balances.balance_of(&token);
+            if balance < pending {
+                errors.push(format!(
+                    "balance {balance} of {token} is below pending deposits {pending}"
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    /// Timestamp of `last_scraped_block_number`, if it was fetched since the block was scraped.
+    pub fn last_scraped_block_timestamp(&self) -> Option<BlockTimestamp> {
+        self.last_scraped_block_timestamp
+            .filter(|(block_number, _)| *block_number == self.last_scraped_block_number)
+            .map(|(_, timestamp)| timestamp)
+    }
+
+    pub fn last_observed_block_lag_seconds(&self, now_ns: u64) -> Option<u64> {
+        self.last_observed_block_timestamp
+            .map(|timestamp| block_lag_seconds(timestamp, now_ns))
+    }
+
+    pub fn last_scraped_block_lag_seconds(&self, now_ns: u64) -> Option<u64> {
+        self.last_scraped_block_timestamp()
+            .map(|timestamp| block_lag_seconds(timestamp, now_ns))
+    }
+
+    /// Lowest `max_priority_fee_per_gas` of the transactions sent by the minter: the minimum of
+    /// the gas fee config if set, otherwise `min_max_priority_fee_per_gas`.
+    pub fn min_priority_fee_per_gas(&self) -> WeiPerGas {
+        self.gas_fee_config
+            .as_ref()
+            .map(|config| config.min_priority_fee)
+            .unwrap_or(self.min_max_priority_fee_per_gas)
+    }
+
+    /// Highest `max_fee_per_gas` and maximum transaction fee of the transactions sent by the minter.
+    pub fn fee_cap(&self) -> FeeCap {
+        self.fee_cap_override
+            .unwrap_or_else(|| FeeCap::for_network(self.evm_network))
+    }
+
+    /// Returns true if no alert about the fee cap was recorded
+    /// within the last [`crate::FEE_CAP_ALERT_INTERVAL`].
+    pub fn can_record_fee_cap_alert(&self, now: u64) -> bool {
+        let interval = crate::FEE_CAP_ALERT_INTERVAL.as_nanos() as u64;
+        self.last_fee_cap_alert.map_or(true, |last_alert| {
+            now.saturating_sub(last_alert) >= interval
+        })
+    }
+
+    pub fn record_fee_cap_alert(&mut self, timestamp: u64) {
+        self.last_fee_cap_alert = Some(timestamp);
+    }
+
+    /// Deadline, in seconds since the UNIX epoch, of a refund swap created at `now_ns`.
+    pub fn refund_deadline(&self, now_ns: u64) -> Erc20Value {
+        let window = self
+            .refund_deadline_window_secs
+            .unwrap_or(DEFAULT_REFUND_DEADLINE_WINDOW_SECS);
+        Erc20Value::from((now_ns / 1_000_000_000).saturating_add(window))
+    }
+
+    /// Fees held in `FEES_SUBACCOUNT` of the minter on the ledger `ledger_id`.
+    pub fn collected_fees(&self, ledger_id: &Principal) -> IcrcValue {
+        self.collected_fees
+            .get(ledger_id)
+            .copied()
+            .unwrap_or(IcrcValue::ZERO)
+    }
+
+    pub fn record_fee_collected(&mut self, ledger_id: Principal, amount: IcrcValue) {
+        let collected = self.collected_fees(&ledger_id);
+        let new_collected = collected.checked_add(amount).unwrap_or_else(|| {
+            panic!("BUG: overflow when adding {amount} to the collected fees {collected}")
+        });
+        self.collected_fees.insert(ledger_id, new_collected);
+    }
+
+    pub fn record_collected_fees_withdrawn(&mut self, ledger_id: Principal, amount: IcrcValue) {
+        let collected = self.collected_fees(&ledger_id);
+        let new_collected = collected.checked_sub(amount).unwrap_or_else(|| {
+            panic!("BUG: withdrew {amount} but only {collected} fees were collected")
+        });
+        if new_collected == IcrcValue::ZERO {
+            self.collected_fees.remove(&ledger_id);
+        } else {
+            self.collected_fees.insert(ledger_id, new_collected);
+        }
+    }
+
+    pub fn record_ecdsa_signing(&mut self, cycles: u128) {
+        self.ecdsa_signing_calls = self.ecdsa_signing_calls.saturating_add(1);
+        self.ecdsa_signing_cycles_spent = self.ecdsa_signing_cycles_spent.saturating_add(cycles);
+    }
+
+    /// Average cost of the signing calls since the last upgrade, or the cycles attached to
+    /// a call if none was made yet.
+    pub fn cost_per_signing(&self) -> u128 {
+        if self.ecdsa_signing_calls == 0 {
+            return CYCLES_PER_SIGNATURE;
+        }
+        (self.ecdsa_signing_cycles_spent / self.ecdsa_signing_calls as u128).max(1)
+    }
+
+    /// Number of signing calls that `cycles_balance` can still pay for,
+    /// keeping [`SIGNING_CYCLES_RESERVE`] cycles.
+    pub fn estimated_remaining_signing_capacity(&self, cycles_balance: u128) -> u64 {
+        let capacity =
+            cycles_balance.saturating_sub(SIGNING_CYCLES_RESERVE) / self.cost_per_signing();
+        u64::try_from(capacity).unwrap_or(u64::MAX)
+    }
+
     // Returns the block height
-    pub const fn block_height(&self) -> BlockTag {
-        self.block_height
+    pub fn block_height(&self) -> BlockTag {
+        self.confirmation_policy.block_tag()
     }
 
     pub const fn evm_network(&self) -> EvmNetwork {
         self.evm_network
     }
 
+    /// Gas limit of the transactions sent for `kind` on the minter's network.
+    pub fn gas_limit(&self, kind: GasLimitKind) -> GasAmount {
+        self.gas_limits.get(self.evm_network, kind)
+    }
+
+    /// L1 fee added to transactions on networks that charge one, see [`l1_fee_estimate`].
+    pub fn l1_fee(&self) -> Option<Wei> {
+        l1_fee_estimate(self.evm_network, self.current_l1_fee)
+    }
+
     pub fn max_block_spread_for_logs_scraping(&self) -> u16 {
         // Limit set by the EVM-RPC canister itself, see
         // https://github.com/internet-computer-protocol/evm-rpc-canister/blob/3cce151d4c1338d83e6741afa354ccf11dff41e8/src/candid_rpc.rs#L192
@@ -428,9 +1007,16 @@ impl State {
                         None,
                     )
                     .expect("Bug: duplicate wrapped icp token should've been detected before");
+                self.wrapped_icrc_deployments.insert(
+                    wrapped_icrc_deployed.deployed_wrapped_erc20,
+                    WrappedIcrcDeployment {
+                        transaction_hash: wrapped_icrc_deployed.transaction_hash,
+                        block_number: wrapped_icrc_deployed.block_number,
+                    },
+                );
             }
             ReceivedContractEvent::ReceivedSwapOrder(received_swap_event) => {
-                assert!(self.is_swapping_active, "BUG: There should be no swap event fetched if swap feature is not yet activated");
+                assert!(self.is_swapping_active(), "BUG: There should be no swap event fetched if swap feature is not yet activated");
                 assert!(self.dex_canister_id.is_some(), "BUG: Swap events can not be minted to appic dex if appic dex canister id is not represented");
                 assert!(received_swap_event.bridged_to_minter, "BUG: Events with bridged_to_minter=false should have already been filtered out");
                 assert!(
@@ -446,6 +1032,107 @@ impl State {
         };
     }
 
+    /// Returns true if the maximum approval was given to the swap contract and was not revoked,
+    /// unless swapping was disabled by the controller.
+    pub fn is_swapping_active(&self) -> bool {
+        self.feature_flags.is_enabled(FeatureFlag::SwapActive)
+    }
+
+    /// Returns true if `prepare_upgrade` was called since the last upgrade: new withdrawals
+    /// and deposits are not accepted until the canister is upgraded.
+    pub fn is_draining(&self) -> bool {
+        self.feature_flags.is_enabled(FeatureFlag::Draining)
+    }
+
+    /// Returns the number of items that are still being processed: timer tasks and requests
+    /// holding a guard, e.g. while awaiting an inter-canister call, mints in flight, and
+    /// withdrawals whose transaction is not finalized yet.
+    pub fn in_flight_count(&self) -> u64 {
+        (self.active_tasks.len()
+            + self.pending_withdrawal_principals.len()
+            + self.mints_in_flight.len()
+            + self.withdrawal_transactions.unfinalized_withdrawals_count()) as u64
+    }
+
+    /// Clears the in-flight mark of a deposit. Returns true if its log was removed while the
+    /// mint was in flight, in which case the removal must be recorded now.
+    pub fn release_mint_in_flight(&mut self, source: &EventSource) -> bool {
+        self.mints_in_flight.remove(source);
+        self.reorged_mints_in_flight.remove(source)
+    }
+
+    /// Returns true if the event was accepted but not yet minted, released or sent to the dex.
+    pub fn is_pending_event(&self, source: &EventSource) -> bool {
+        self.events_to_mint.contains_key(source)
+            || self.events_to_release.contains_key(source)
+            || self.swap_events_to_mint_to_appic_dex.contains_key(source)
+    }
+
+    /// Returns true if the event was already recorded, e.g. when scraping the same
+    /// block range again after an upgrade.
+    pub fn is_known_event(&self, event: &ReceivedContractEvent) -> bool {
+        let source = event.source();
+        if let ReceivedContractEvent::WrappedIcrcDeployed(wrapped_icrc_deployed) = event {
+            if self
+                .wrapped_icrc_tokens
+                .contains_alt(&wrapped_icrc_deployed.deployed_wrapped_erc20)
+            {
+                return true;
+            }
+        }
+        self.is_pending_event(&source)
+            || self.minted_events.contains_key(&source)
+            || self.released_events.contains_key(&source)
+            || self.invalid_events.contains_key(&source)
+            || self.quarantined_releases.contains_key(&source)
+            || self.swap_events_to_be_notified.contains_key(&source)
+            || self.notified_swap_events.contains_key(&source)
+    }
+
+    /// Drops an accepted but not yet processed event whose log entry was removed
+    /// by a chain reorganisation and reverts the balance changes made upon acceptance.
+    ///
+    /// Reorged data must not trap the scraping, so unexpected events are only logged.
+    fn record_reorged_deposit(&mut self, source: EventSource) {
+        self.removed_log_events.insert(source);
+        if let Some(event) = self.events_to_mint.remove(&source) {
+            match event {
+                ReceivedContractEvent::NativeDeposit(event) => {
+                    self.native_balance.eth_balance_sub(event.value)
+                }
+                ReceivedContractEvent::Erc20Deposit(event) => self
+                    .erc20_balances
+                    .erc20_sub(event.erc20_contract_address, event.value),
+                event => log!(
+                    INFO,
+                    "[record_reorged_deposit]: dropped unexpected event {event:?} from the minting list"
+                ),
+            }
+        } else if let Some(event) = self.swap_events_to_mint_to_appic_dex.remove(&source) {
+            match event {
+                ReceivedContractEvent::ReceivedSwapOrder(event) => self
+                    .erc20_balances
+                    .erc20_sub(event.token_out, event.amount_out),
+                event => log!(
+                    INFO,
+                    "[record_reorged_deposit]: dropped unexpected event {event:?} from the swap minting list"
+                ),
+            }
+        } else if self.events_to_release.remove(&source).is_none() {
+            // the ICRC balance of a wrapped ICRC burn only changes once it is released,
+            // so dropping a pending release leaves nothing else to revert
+            log!(
+                INFO,
+                "[record_reorged_deposit]: event {source:?} is unknown or already processed"
+            );
+        }
+    }
+
+    /// Records that the log entry of `source` was removed by a chain reorganisation.
+    fn record_removed_log(&mut self, source: EventSource) {
+        self.removed_log_events.insert(source);
+    }
+
     pub fn record_skipped_block(&mut self, block_number: BlockNumber) {
         assert!(
             self.skipped_blocks.insert(block_number),
@@ -491,6 +1178,12 @@ impl State {
             Some(event) => event,
             None => panic!("attempted to mint Twin tokens for an unknown event {source:?}"),
         };
+        if let ReceivedContractEvent::Erc20Deposit(deposit) = &event {
+            self.token_volumes
+                .entry(deposit.erc20_contract_address)
+                .or_default()
+                .deposited_add(deposit.value);
+        }
         assert_eq!(
             self.minted_events.insert(
                 source,
@@ -680,6 +1373,54 @@ impl State {
         None
     }
 
+    /// Returns the current status of the swap with the given id, including the statuses
+    /// before the swap order reached the appic dex.
+    pub fn get_swap_status_by_tx_id(&self, tx_id: &SwapTxId) -> Option<SwapStatus> {
+        if let Some(status) = self
+            .withdrawal_transactions
+            .get_swap_status_by_tx_id(tx_id.clone())
+        {
+            return Some(status);
+        }
+
+        if self
+            .notified_swap_events
+            .values()
+            .any(|notified_event| notified_event.tx_id == *tx_id)
+        {
+            return Some(SwapStatus::NotifiedAppicDex(tx_id.0.clone()));
+        }
+
+        if self
+            .swap_events_to_be_notified
+            .values()
+            .any(|minted_order| minted_order.tx_id == *tx_id)
+        {
+            return Some(SwapStatus::MintedToAppicDex(tx_id.0.clone()));
+        }
+
+        None
+    }
+
+    /// Records the current status of the swap in its status history if it changed.
+    /// `event_status` is the status following from the event itself, which applies
+    /// as long as the swap has no withdrawal request.
+    pub fn record_swap_status_transition(
+        &mut self,
+        tx_id: &SwapTxId,
+        event_status: Option<SwapStatus>,
+        timestamp: u64,
+    ) {
+        if let Some(status) = self
+            .withdrawal_transactions
+            .get_swap_status_by_tx_id(tx_id.clone())
+            .or(event_status)
+        {
+            self.withdrawal_transactions
+                .record_swap_status(&tx_id.0, timestamp, status);
+        }
+    }
+
     pub fn record_native_withdrawal_request(&mut self, request: NativeWithdrawalRequest) {
         self.withdrawal_transactions
             .record_withdrawal_request(request);
@@ -702,6 +1443,10 @@ impl State {
                 "BUG: unsupported ERC-20 token {}",
                 request.erc20_contract_address
             );
+            self.token_volumes
+                .entry(request.erc20_contract_address)
+                .or_default()
+                .withdrawn_add(request.withdrawal_amount);
         }
 
         self.withdrawal_transactions
@@ -709,6 +1454,14 @@ impl State {
     }
 
     pub fn record_swap_request(&mut self, request: ExecuteSwapRequest) {
+        assert!(
+            self.next_swap_ledger_burn_index
+                .is_some_and(|next| request.native_ledger_burn_index < next),
+            "BUG: swap request {} uses swap index {} that was not allocated, next swap index is {:?}",
+            request.swap_tx_id,
+            request.native_ledger_burn_index,
+            self.next_swap_ledger_burn_index
+        );
         self.withdrawal_transactions
             .remove_failed_swap_request_by_swap_tx_id(&request.swap_tx_id);
 
@@ -729,17 +1482,81 @@ impl State {
             .expect("BUG: missing withdrawal request")
             .clone();
 
+        self.withdrawal_transactions
+            .record_finalized_transaction(*withdrawal_id, receipt.clone());
+        let is_cancelled = self
+            .withdrawal_transactions
+            .is_cancelled_withdrawal(withdrawal_id);
+
         match withdrawal_request {
             WithdrawalRequest::Native(_) | WithdrawalRequest::Erc20(_) => {}
-            WithdrawalRequest::Erc20Approve(_) => {
-                self.is_swapping_active = true;
+            WithdrawalRequest::Erc20Approve(ref request) => {
+                if !is_cancelled {
+                    self.record_finalized_approval(request, receipt);
+                }
             }
             WithdrawalRequest::Swap(_) => {}
         }
 
+        self.update_balance_upon_withdrawal(
+            withdrawal_id,
+            receipt,
+            withdrawal_request,
+            is_cancelled,
+        );
+    }
+
+    fn record_finalized_approval(&mut self, request: &Erc20Approve, receipt: &TransactionReceipt) {
+        let key = (
+            request.erc20_contract_address,
+            request.swap_contract_address,
+        );
+        if request.is_revocation() {
+            if receipt.status == TransactionStatus::Success {
+                self.onchain_approvals.remove(&key);
+            }
+            return;
+        }
+        self.feature_flags.set(FeatureFlag::SwapActive, true);
+        if receipt.status == TransactionStatus::Success {
+            self.onchain_approvals.insert(
+                key,
+                OnchainApproval {
+                    granted_at: request.created_at,
+                    transaction_hash: receipt.transaction_hash,
+                },
+            );
+        }
+    }
+
+    /// Returns true if a revocation of the approval given by `erc20_contract_address` to
+    /// `spender` was accepted and its transaction is not finalized yet.
+    pub fn is_approval_revocation_pending(
+        &self,
+        erc20_contract_address: &Address,
+        spender: &Address,
+    ) -> bool {
         self.withdrawal_transactions
-            .record_finalized_transaction(*withdrawal_id, receipt.clone());
-        self.update_balance_upon_withdrawal(withdrawal_id, receipt, withdrawal_request);
+            .incomplete_withdrawal_requests_iter()
+            .any(|request| {
+                matches!(
+                    request,
+                    WithdrawalRequest::Erc20Approve(approve)
+                        if approve.is_revocation()
+                            && &approve.erc20_contract_address == erc20_contract_address
+                            && &approve.swap_contract_address == spender
+                )
+            })
+    }
+
+    /// Records a request to revoke an approval. Swapping is deactivated right away if the
+    /// approval of the active swap contract is revoked, without waiting for the transaction.
+    pub fn record_approval_revocation_request(&mut self, request: Erc20Approve) {
+        if self.swap_contract_address == Some(request.swap_contract_address) {
+            self.feature_flags.set(FeatureFlag::SwapActive, false);
+        }
+        self.withdrawal_transactions
+            .record_withdrawal_request(request);
     }
 
     fn update_balance_upon_deposit(&mut self, event: &ReceivedContractEvent) {
@@ -786,6 +1603,7 @@ impl State {
         withdrawal_id: &LedgerBurnIndex,
         receipt: &TransactionReceipt,
         withdrawal_request: WithdrawalRequest,
+        is_cancelled: bool,
     ) {
         let tx = self
             .withdrawal_transactions
@@ -804,6 +1622,9 @@ impl State {
 
         // charged_tx_fee is only the fee paid to cover transaction fee excluding any other fee
         let (charged_tx_fee, is_wrapped_mint) = match withdrawal_request {
+            // the cancellation fee is deducted from the reimbursed amount,
+            // see `WithdrawalTransactions::record_finalized_transaction`
+            WithdrawalRequest::Native(_) if is_cancelled => (tx_fee, false),
             WithdrawalRequest::Native(req) => {
                 let total_charged_fees = req
                     .withdrawal_amount
@@ -826,9 +1647,20 @@ impl State {
             WithdrawalRequest::Swap(req) => (req.max_transaction_fee, false),
         };
 
-        let unspent_tx_fee = charged_tx_fee.checked_sub(tx_fee).expect(
-            "BUG: charged transaction fee MUST always be at least the effective transaction fee",
-        );
+        let (unspent_tx_fee, cancellation_cost) = match charged_tx_fee.checked_sub(tx_fee) {
+            Some(unspent_tx_fee) => (unspent_tx_fee, Wei::ZERO),
+            // a cancellation may cost more than the fee charged for the cancelled transaction,
+            // in which case the difference is paid by the minter out of the gas tank
+            None if is_cancelled => (
+                Wei::ZERO,
+                tx_fee
+                    .checked_sub(charged_tx_fee)
+                    .expect("BUG: effective transaction fee is higher than charged fee"),
+            ),
+            None => panic!(
+                "BUG: charged transaction fee MUST always be at least the effective transaction fee"
+            ),
+        };
 
         // we dont add the withdrawal_fee to debited amount, since its already added to total_collected_operation_native_fee
         let debited_amount = match receipt.status {
@@ -860,6 +1692,11 @@ impl State {
         // to the gas tank to be used later
         self.gas_tank.native_balance_add(withdrawal_fee);
 
+        // the part of a cancellation not covered by the cancelled withdrawal is taken from the gas tank,
+        // as far as the gas tank can cover it
+        self.gas_tank
+            .native_balance_sub(cancellation_cost.min(self.gas_tank.native_balance));
+
         // update erc20 balances only if request is erc20 and tx is not a wrapped_mint for icrc
         // tokens
         if receipt.status == TransactionStatus::Success
@@ -906,6 +1743,8 @@ impl State {
                 erc20_ledger_id: *erc20_ledger_id,
                 chain_id: self.evm_network,
                 erc20_token_symbol: symbol.clone(),
+                transfer_kind: self.erc20_transfer_kind(erc20_address),
+                original_symbol: self.erc20_original_symbols.get(erc20_address).cloned(),
             })
     }
 
@@ -933,15 +1772,96 @@ impl State {
             .map(|(ledger_id, address, _transfer_fee)| (*ledger_id, *address))
     }
 
-    pub fn supported_erc20_tokens(&self) -> impl Iterator<Item = ERC20Token> + '_ {
+    /// Supported ERC-20 tokens, sorted lexicographically by `erc20_token_symbol`.
+    pub fn supported_erc20_tokens(&self) -> impl Iterator<Item = ERC20Token> {
         self.erc20_tokens
             .iter()
-            .map(|(ledger_id, erc20_address, symbol)| ERC20Token {
-                erc20_contract_address: *erc20_address,
-                erc20_ledger_id: *ledger_id,
-                chain_id: self.evm_network,
-                erc20_token_symbol: symbol.clone(),
+            .map(|(ledger_id, erc20_address, symbol)| {
+                (
+                    symbol.clone(),
+                    ERC20Token {
+                        erc20_contract_address: *erc20_address,
+                        erc20_ledger_id: *ledger_id,
+                        chain_id: self.evm_network,
+                        erc20_token_symbol: symbol.clone(),
+                        transfer_kind: self.erc20_transfer_kind(erc20_address),
+                        original_symbol: self.erc20_original_symbols.get(erc20_address).cloned(),
+                    },
+                )
             })
+            .collect::<BTreeMap<_, _>>()
+            .into_values()
+    }
+
+    fn erc20_transfer_kind(&self, erc20_address: &Address) -> Option<Erc20TransferKind> {
+        self.non_standard_erc20_tokens
+            .contains(erc20_address)
+            .then_some(Erc20TransferKind::NonStandard)
+    }
+
+    pub fn is_non_standard_erc20_token(&self, erc20_address: &Address) -> bool {
+        self.non_standard_erc20_tokens.contains(erc20_address)
+    }
+
+    /// Returns the symbol of the supported ERC-20 token at `erc20_address`, or the address
+    /// itself if the token is unknown.
+    pub fn erc20_token_symbol_or_address(&self, erc20_address: &Address) -> String {
+        self.erc20_tokens
+            .get_alt(erc20_address)
+            .map(|symbol| symbol.to_string())
+            .unwrap_or_else(|| erc20_address.to_string())
+    }
+
+    /// Applies `policy` if the symbol of `erc20_token` is already used by a token with
+    /// a different contract address.
+    pub fn resolve_erc20_token_symbol_collision(
+        &self,
+        erc20_token: ERC20Token,
+        policy: SymbolCollisionPolicy,
+    ) -> Result<ERC20Token, String> {
+        let is_used = |symbol: &ERC20TokenSymbol| {
+            self.erc20_tokens
+                .iter()
+                .any(|(_ledger_id, erc20_address, used_symbol)| {
+                    used_symbol == symbol && erc20_address != &erc20_token.erc20_contract_address
+                })
+        };
+        let symbol = erc20_token.erc20_token_symbol.clone();
+        if !is_used(&symbol) {
+            return Ok(erc20_token);
+        }
+        match policy {
+            SymbolCollisionPolicy::Reject => Err(format!(
+                "ERROR: ERC20 token symbol {symbol} is already used by another token"
+            )),
+            SymbolCollisionPolicy::Disambiguate => {
+                let display_symbol = (2..)
+                    .map(|suffix| symbol.with_suffix(suffix))
+                    .find(|candidate| !is_used(candidate))
+                    .expect("BUG: ran out of symbol suffixes");
+                Ok(ERC20Token {
+                    erc20_token_symbol: display_symbol,
+                    original_symbol: Some(symbol),
+                    ..erc20_token
+                })
+            }
+        }
+    }
+
+    /// Records a reimbursed ERC-20 withdrawal in the volume of the token of `erc20_ledger_id`.
+    pub fn record_erc20_reimbursed_volume(
+        &mut self,
+        erc20_ledger_id: &Principal,
+        reimbursed_amount: Erc20Value,
+    ) {
+        if let Some((erc20_contract_address, _symbol)) =
+            self.erc20_tokens.get_entry(erc20_ledger_id)
+        {
+            self.token_volumes
+                .entry(*erc20_contract_address)
+                .or_default()
+                .reimbursed_add(reimbursed_amount);
+        }
     }
 
     pub fn record_add_erc20_token(&mut self, erc20_token: ERC20Token) {
@@ -961,6 +1881,16 @@ impl State {
             erc20_token.erc20_token_symbol,
             erc20_with_same_symbol
         );
+        if erc20_token.is_non_standard() {
+            self.non_standard_erc20_tokens
+                .insert(erc20_token.erc20_contract_address);
+        }
+        self.known_erc20_contracts
+            .insert(erc20_token.erc20_contract_address);
+        if let Some(original_symbol) = erc20_token.original_symbol {
+            self.erc20_original_symbols
+                .insert(erc20_token.erc20_contract_address, original_symbol);
+        }
         assert_eq!(
             self.erc20_tokens.try_insert(
                 erc20_token.erc20_ledger_id,
@@ -1003,10 +1933,23 @@ impl State {
         self.gas_tank.native_balance_sub(gas_amount);
         self.gas_tank.usdc_balance_add(usdc_amount);
 
-        // increment the next swap ledger burn index after releasing gas
+        // every release of gas is consumed by exactly one swap operation
+        self.allocate_swap_index();
+    }
+
+    /// Returns the ledger burn index of the next swap operation and advances the counter,
+    /// so that no two swap operations can be recorded with the same index.
+    pub fn allocate_swap_index(&mut self) -> LedgerBurnIndex {
+        let swap_index = self
+            .next_swap_ledger_burn_index
+            .expect("BUG: next_swap_ledger_burn_index should be available if swapping is active");
         self.next_swap_ledger_burn_index = Some(LedgerBurnIndex::new(
-            self.next_swap_ledger_burn_index.unwrap().get() + 1,
+            swap_index
+                .get()
+                .checked_add(1)
+                .expect("BUG: swap ledger burn index overflow"),
         ));
+        swap_index
     }
 
     pub fn record_quarantined_dex_order(&mut self, swap_request: DexOrderArgs) {
@@ -1044,10 +1987,11 @@ impl State {
             self.last_scraped_block_number,
             other.last_scraped_block_number
         );
-        ensure_eq!(self.block_height, other.block_height);
+        ensure_eq!(self.confirmation_policy, other.confirmation_policy);
         ensure_eq!(self.events_to_mint, other.events_to_mint);
         ensure_eq!(self.minted_events, other.minted_events);
         ensure_eq!(self.invalid_events, other.invalid_events);
+        ensure_eq!(self.removed_log_events, other.removed_log_events);
 
         ensure_eq!(self.erc20_tokens, other.erc20_tokens);
 
@@ -1055,6 +1999,205 @@ impl State {
             .is_equivalent_to(&other.withdrawal_transactions)
     }
 
+    /// Returns true if `caller` did not trigger a manual deposit check
+    /// within the last [`crate::MANUAL_DEPOSIT_CHECK_INTERVAL`] and no principal triggered
+    /// one within the last [`crate::MANUAL_DEPOSIT_CHECK_GLOBAL_INTERVAL`].
+    /// The dex canister is not rate limited.
+    pub fn can_trigger_manual_deposit_check(&self, caller: &Principal, now: u64) -> bool {
+        if self.is_dex_canister(caller) {
+            return true;
+        }
+        let interval = crate::MANUAL_DEPOSIT_CHECK_INTERVAL.as_nanos() as u64;
+        let global_interval = crate::MANUAL_DEPOSIT_CHECK_GLOBAL_INTERVAL.as_nanos() as u64;
+        let caller_allowed = match self.last_manual_deposit_checks.get(caller) {
+            Some(last_check) => now.saturating_sub(*last_check) >= interval,
+            None => true,
+        };
+        let globally_allowed = self
+            .last_manual_deposit_checks
+            .values()
+            .max()
+            .map_or(true, |last_check| {
+                now.saturating_sub(*last_check) >= global_interval
+            });
+        caller_allowed && globally_allowed
+    }
+
+    /// Records a manual deposit check by `caller`, unless it is the dex canister, whose
+    /// checks do not count against the limits of other callers.
+    pub fn record_manual_deposit_check(&mut self, caller: Principal, timestamp: u64) {
+        if self.is_dex_canister(&caller) {
+            return;
+        }
+        let interval = crate::MANUAL_DEPOSIT_CHECK_INTERVAL.as_nanos() as u64;
+        self.last_manual_deposit_checks
+            .retain(|_, last_check| timestamp.saturating_sub(*last_check) < interval);
+        self.last_manual_deposit_checks.insert(caller, timestamp);
+    }
+
+    pub fn is_dex_canister(&self, principal: &Principal) -> bool {
+        self.dex_canister_id.as_ref() == Some(principal)
+    }
+
+    /// Returns true if no provider disagreement was recorded for `method`
+    /// within the last [`crate::PROVIDER_DISAGREEMENT_INTERVAL`].
+    pub fn can_record_provider_disagreement(&self, method: &str, now: u64) -> bool {
+        let interval = crate::PROVIDER_DISAGREEMENT_INTERVAL.as_nanos() as u64;
+        match self.last_provider_disagreements.get(method) {
+            Some(last_record) => now.saturating_sub(*last_record) >= interval,
+            None => true,
+        }
+    }
+
+    pub fn record_provider_disagreement(&mut self, method: String, timestamp: u64) {
+        self.last_provider_disagreements.insert(method, timestamp);
+    }
+
+    pub fn record_event_log_compaction(&mut self, threshold_block: BlockNumber) {
+        self.earliest_unpruned_block = self.earliest_unpruned_block.max(threshold_block);
+    }
+
+    /// Returns the native token symbol reported by the native ledger, or the configured
+    /// symbol if the ledger was not queried yet.
+    pub fn native_ledger_symbol(&self) -> String {
+        match &self.native_ledger_metadata {
+            Some(metadata) => metadata.symbol.clone(),
+            None => self.native_symbol.to_string(),
+        }
+    }
+
+    /// Returns the decimals reported by the native ledger, or [`NATIVE_TOKEN_DECIMALS`]
+    /// if the ledger was not queried yet.
+    pub fn native_ledger_decimals(&self) -> u8 {
+        self.native_ledger_metadata
+            .as_ref()
+            .map(|metadata| metadata.decimals)
+            .unwrap_or(NATIVE_TOKEN_DECIMALS)
+    }
+
+    /// Returns true if `metadata` differs from the configured symbol or decimals.
+    pub fn is_native_ledger_metadata_mismatch(&self, metadata: &NativeLedgerMetadata) -> bool {
+        metadata.symbol != self.native_symbol.to_string()
+            || metadata.decimals != NATIVE_TOKEN_DECIMALS
+    }
+
+    /// Native ledger amounts are converted to wei one to one, which would silently mis-scale
+    /// withdrawals if the ledger used a different number of decimals.
+    pub fn validate_native_decimals(&self) -> Result<(), String> {
+        let decimals = self.native_ledger_decimals();
+        if decimals != NATIVE_TOKEN_DECIMALS {
+            return Err(format!(
+                "Native ledger reports {decimals} decimals instead of {NATIVE_TOKEN_DECIMALS}, withdrawals are disabled"
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn record_native_ledger_metadata(&mut self, metadata: NativeLedgerMetadata) {
+        self.native_ledger_metadata = Some(metadata);
+    }
+
+    /// Returns whether code is deployed at `destination`, if it was checked less than
+    /// [`CONTRACT_DESTINATION_CACHE_TTL_NANOS`] before `now`.
+    pub fn cached_is_contract_destination(&self, destination: &Address, now: u64) -> Option<bool> {
+        self.contract_destinations
+            .get(destination)
+            .filter(|(_, checked_at)| {
+                now.saturating_sub(*checked_at) < CONTRACT_DESTINATION_CACHE_TTL_NANOS
+            })
+            .map(|(is_contract, _)| *is_contract)
+    }
+
+    /// Returns the ledger IDs of the wrapped ICRC tokens whose metadata was not queried yet
+    /// or more than [`WRAPPED_ICRC_METADATA_TTL_NANOS`] before `now`.
+    pub fn wrapped_icrc_ledgers_to_refresh(&self, now: u64) -> Vec<Principal> {
+        self.wrapped_icrc_tokens
+            .iter()
+            .map(|(ledger_id, _address, _transfer_fee)| *ledger_id)
+            .filter(|ledger_id| {
+                self.wrapped_icrc_metadata
+                    .get(ledger_id)
+                    .map_or(true, |metadata| {
+                        now.saturating_sub(metadata.refreshed_at) >= WRAPPED_ICRC_METADATA_TTL_NANOS
+                    })
+            })
+            .collect()
+    }
+
+    pub fn record_wrapped_icrc_metadata(
+        &mut self,
+        ledger_id: Principal,
+        metadata: IcrcLedgerMetadata,
+    ) {
+        self.wrapped_icrc_metadata.insert(ledger_id, metadata);
+    }
+
+    /// Caches whether code is deployed at `destination` and drops the expired entries,
+    /// as well as the oldest one if the cache holds [`MAX_CACHED_CONTRACT_DESTINATIONS`].
+    pub fn record_is_contract_destination(
+        &mut self,
+        destination: Address,
+        is_contract: bool,
+        now: u64,
+    ) {
+        self.contract_destinations.retain(|_, (_, checked_at)| {
+            now.saturating_sub(*checked_at) < CONTRACT_DESTINATION_CACHE_TTL_NANOS
+        });
+        if self.contract_destinations.len() >= MAX_CACHED_CONTRACT_DESTINATIONS
+            && !self.contract_destinations.contains_key(&destination)
+        {
+            if let Some(oldest) = self
+                .contract_destinations
+                .iter()
+                .min_by_key(|(_, (_, checked_at))| *checked_at)
+                .map(|(address, _)| *address)
+            {
+                self.contract_destinations.remove(&oldest);
+            }
+        }
+        self.contract_destinations
+            .insert(destination, (is_contract, now));
+    }
+
+    pub fn record_rate_limited_provider(&mut self, provider: Provider, retry_after_ns: u64) {
+        self.rate_limited_providers.insert(provider, retry_after_ns);
+    }
+
+    /// Returns true if `provider` was not rate limited past `retry_after_ns` already.
+    pub fn should_record_rate_limited_provider(
+        &self,
+        provider: Provider,
+        retry_after_ns: u64,
+    ) -> bool {
+        self.rate_limited_providers
+            .get(&provider)
+            .map_or(true, |known| *known < retry_after_ns)
+    }
+
+    /// Default providers that are not rate limited at `now`.
+    /// Falls back to all default providers if skipping the rate limited ones would leave
+    /// fewer than `min_providers`.
+    pub fn available_providers(&self, now: u64, min_providers: usize) -> Vec<Provider> {
+        let available: Vec<Provider> = DEFAULT_PROVIDERS
+            .into_iter()
+            .filter(|provider| {
+                self.rate_limited_providers
+                    .get(provider)
+                    .map_or(true, |retry_after_ns| *retry_after_ns <= now)
+            })
+            .collect();
+        if available.len() < min_providers {
+            log!(
+                INFO,
+                "Only {} providers are not rate limited, querying all providers: {:?}",
+                available.len(),
+                self.rate_limited_providers
+            );
+            return DEFAULT_PROVIDERS.to_vec();
+        }
+        available
+    }
+
     fn upgrade(&mut self, upgrade_args: UpgradeArg) -> Result<(), InvalidStateError> {
         use std::str::FromStr;
 
@@ -1070,6 +2213,13 @@ impl State {
             // deposit native fee is deprecated
             deposit_native_fee: _,
             withdrawal_native_fee,
+            gas_fee_config,
+            confirmations,
+            gas_limits,
+            fee_collector,
+            refund_deadline_window_secs,
+            max_fee_per_gas_cap,
+            max_transaction_fee_cap,
         } = upgrade_args;
         if let Some(nonce) = next_transaction_nonce {
             let nonce = TransactionNonce::try_from(nonce)
@@ -1098,13 +2248,51 @@ impl State {
             self.min_max_priority_fee_per_gas = min_max_priority_fee_per_gas;
         }
 
+        if let Some(config) = gas_fee_config {
+            let gas_fee_config =
+                GasFeeConfig::try_from(config).map_err(InvalidStateError::InvalidGasFeeConfig)?;
+            self.gas_fee_config = Some(gas_fee_config);
+        }
+
+        for gas_limit in gas_limits.into_iter().flatten() {
+            let (kind, gas_limit) =
+                GasLimits::parse_override(gas_limit).map_err(InvalidStateError::InvalidGasLimit)?;
+            self.gas_limits.set_override(kind, gas_limit);
+        }
+
+        if let Some(fee_collector) = fee_collector {
+            self.fee_collector = Some(fee_collector);
+        }
+
+        if let Some(window) = refund_deadline_window_secs {
+            self.refund_deadline_window_secs = Some(window);
+        }
+
+        if max_fee_per_gas_cap.is_some() || max_transaction_fee_cap.is_some() {
+            let mut fee_cap = self.fee_cap();
+            if let Some(cap) = max_fee_per_gas_cap {
+                fee_cap.max_fee_per_gas = parse_fee_cap(cap)?;
+            }
+            if let Some(cap) = max_transaction_fee_cap {
+                fee_cap.max_transaction_fee = parse_fee_cap(cap)?;
+            }
+            self.fee_cap_override = Some(fee_cap);
+        }
+
+        // the checksum of new upgrade arguments is verified by `validate_upgrade_arg`
         if let Some(addr) = helper_contract_address {
-            let contract_address = Address::from_str(&addr).map_err(|e| -> InvalidStateError {
-                InvalidStateError::InvalidHelperContractAddress(format!("Invalid address: {e}"))
-            })?;
-            self.helper_contract_addresses
-                .get_or_insert_with(std::vec::Vec::new)
-                .push(contract_address);
+            let contract_address =
+                Address::from_str_ignoring_checksum(&addr).map_err(|e| -> InvalidStateError {
+                    InvalidStateError::InvalidHelperContractAddress(format!("Invalid address: {e}"))
+                })?;
+            let helper_contract_addresses = self
+                .helper_contract_addresses
+                .get_or_insert_with(std::vec::Vec::new);
+            // Duplicates recorded before upgrade arguments were validated are dropped
+            // so that the same logs are not scraped twice.
+            if !helper_contract_addresses.contains(&contract_address) {
+                helper_contract_addresses.push(contract_address);
+            }
         }
 
         if let Some(block_number) = last_scraped_block_number {
@@ -1113,7 +2301,10 @@ impl State {
             })?;
         }
         if let Some(block_height) = block_height {
-            self.block_height = block_height.into();
+            self.confirmation_policy = ConfirmationPolicy::BlockTag(block_height.into());
+        }
+        if let Some(confirmations) = confirmations {
+            self.confirmation_policy = ConfirmationPolicy::Confirmations(confirmations);
         }
 
         if let Some(evm_id) = evm_rpc_id {
@@ -1139,6 +2330,31 @@ impl State {
     }
 }
 
+/// Parses a fee cap of the upgrade arguments, which must fit in a u256 and be non-zero
+/// since a zero cap would block all transactions.
+fn parse_fee_cap<Unit>(cap: Nat) -> Result<CheckedAmountOf<Unit>, InvalidStateError> {
+    let cap = CheckedAmountOf::<Unit>::try_from(cap)
+        .map_err(|e| InvalidStateError::InvalidFeeCap(format!("ERROR: {e}")))?;
+    if cap == CheckedAmountOf::ZERO {
+        return Err(InvalidStateError::InvalidFeeCap(
+            "fee caps must be non-zero".to_string(),
+        ));
+    }
+    Ok(cap)
+}
+
+/// Seconds elapsed between the production of a block and `now_ns`.
+///
+/// Block timestamps are in seconds while the IC time is in nanoseconds. A block whose
+/// timestamp is ahead of the IC time, e.g. due to clock drift, has no lag.
+pub fn block_lag_seconds(block_timestamp: BlockTimestamp, now_ns: u64) -> u64 {
+    const NANOS_PER_SEC: u64 = 1_000_000_000;
+    BlockTimestamp::from(now_ns / NANOS_PER_SEC)
+        .checked_sub(block_timestamp)
+        .map(|lag| u64::try_from(lag.into_inner()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
+
 pub fn read_state<R>(f: impl FnOnce(&State) -> R) -> R {
     STATE.with(|s| f(s.borrow().as_ref().expect("BUG: state is not initialized")))
 }
@@ -1166,6 +2382,7 @@ pub enum TaskType {
     RefreshGasFeeEstimate,
     Reimbursement,
     MintErc20,
+    WithdrawCollectedFees,
 }
 
 pub async fn lazy_call_ecdsa_public_key() -> PublicKey {
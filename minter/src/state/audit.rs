@@ -1,11 +1,16 @@
 pub use super::event::{Event, EventType};
 use super::{
+    snapshot::StateSnapshot,
     transactions::{Reimbursed, ReimbursementIndex},
     State,
 };
 use crate::{
-    contract_logs::ReceivedContractEvent,
-    storage::{record_event, with_event_iter},
+    candid_types::{ArchiveEventsError, CompactEventLogError, SwapStatus},
+    contract_logs::{EventSource, ReceivedContractEvent},
+    numeric::BlockNumber,
+    rpc_declarations::Hash,
+    storage::{self, record_event, with_event_iter},
+    tx_id::SwapTxId,
 };
 
 /// Updates the state to reflect the given state transition.
@@ -124,6 +129,10 @@ pub fn apply_state_transition(state: &mut State, payload: &EventType) {
             erc20_ledger_id,
             reimbursed,
         } => {
+            state.record_erc20_reimbursed_volume(
+                erc20_ledger_id,
+                reimbursed.reimbursed_amount.change_units(),
+            );
             state
                 .withdrawal_transactions
                 .record_finalized_reimbursement(
@@ -141,7 +150,7 @@ pub fn apply_state_transition(state: &mut State, payload: &EventType) {
                 ReimbursementIndex::Native {
                     ledger_burn_index: native_reimbursement_request.ledger_burn_index,
                 },
-                native_reimbursement_request.clone(),
+                native_reimbursement_request.clone().into(),
             )
         }
         EventType::QuarantinedDeposit { event_source } => {
@@ -193,7 +202,7 @@ pub fn apply_state_transition(state: &mut State, payload: &EventType) {
                 ReimbursementIndex::Native {
                     ledger_burn_index: native_reimbursement_request.ledger_burn_index,
                 },
-                native_reimbursement_request.clone(),
+                native_reimbursement_request.clone().into(),
             )
         }
         EventType::ReimbursedIcrcWrap {
@@ -280,41 +289,543 @@ pub fn apply_state_transition(state: &mut State, payload: &EventType) {
         } => {
             state.update_gas_tank_balance(*usdc_withdrawn, *native_deposited);
         }
+        EventType::RemovedLog { event_source } => {
+            state.record_removed_log(*event_source);
+        }
+        EventType::DepositReorged { event_source } => {
+            state.record_reorged_deposit(*event_source);
+        }
+        EventType::UpgradeGraceScrape {
+            start_block,
+            end_block: _,
+        } => {
+            state.last_scraped_block_number = *start_block;
+        }
+        EventType::UpdatedAddressList { .. } => {
+            // The address lists are kept in stable memory, see `storage::update_address_list`.
+        }
+        EventType::RescrapedBlockRange { .. } | EventType::SkippedBlockRange { .. } => {
+            // The scraping gaps are updated by `record_scraping_gaps`.
+        }
+        EventType::ChangedConfirmationPolicy { from: _, to } => {
+            state.confirmation_policy = (*to).into();
+        }
+        EventType::ParkedTransaction {
+            withdrawal_id,
+            cancellation,
+        } => {
+            state
+                .withdrawal_transactions
+                .record_parked_transaction(*withdrawal_id, cancellation.clone());
+        }
+        EventType::ProviderDisagreement {
+            method, timestamp, ..
+        } => {
+            state.record_provider_disagreement(method.clone(), *timestamp);
+        }
+        EventType::ArchivedEvents { .. } => {}
+        EventType::EventLogCompacted {
+            threshold_block, ..
+        } => {
+            state.record_event_log_compaction(*threshold_block);
+        }
+        EventType::UpgradedWhileNotDrained { .. } => {}
+        EventType::BackfillCompleted { .. } => {
+            // The backfilled events are recorded by their own events.
+        }
+        EventType::RealizedSwapAmountOut {
+            withdrawal_id,
+            amount_out,
+        } => {
+            state
+                .withdrawal_transactions
+                .record_realized_swap_amount_out(*withdrawal_id, *amount_out);
+        }
+        EventType::NativeLedgerMetadataMismatch(metadata)
+        | EventType::NativeLedgerMetadataMatched(metadata) => {
+            state.record_native_ledger_metadata(metadata.clone());
+        }
+        EventType::AcceptedApprovalRevocationRequest(request) => {
+            state.record_approval_revocation_request(request.clone());
+        }
+        EventType::FeatureFlagChanged { flag, enabled } => {
+            state.feature_flags.set(*flag, *enabled);
+        }
+        EventType::FeeCollected {
+            ledger_id, amount, ..
+        } => {
+            if *ledger_id == state.native_ledger_id {
+                state
+                    .native_balance
+                    .total_collected_operation_native_fee_add(amount.change_units());
+            }
+            state.record_fee_collected(*ledger_id, *amount);
+        }
+        EventType::UnaccountedFeesFound { ledger_id, amount } => {
+            state.record_fee_collected(*ledger_id, *amount);
+        }
+        EventType::CollectedFeesWithdrawalStarted {
+            ledger_id,
+            withdrawal,
+        } => {
+            state
+                .pending_fee_withdrawals
+                .insert(*ledger_id, withdrawal.clone());
+        }
+        EventType::CollectedFeesWithdrawn {
+            ledger_id, amount, ..
+        } => {
+            state.pending_fee_withdrawals.remove(ledger_id);
+            state.record_collected_fees_withdrawn(*ledger_id, *amount);
+        }
+        EventType::CollectedFeesWithdrawalFailed { ledger_id, .. } => {
+            state.pending_fee_withdrawals.remove(ledger_id);
+        }
+        EventType::MissingFeesFound { ledger_id, amount } => {
+            state.record_collected_fees_withdrawn(*ledger_id, *amount);
+        }
+        EventType::ProviderRateLimited {
+            provider,
+            retry_after_ns,
+        } => {
+            state.record_rate_limited_provider(*provider, *retry_after_ns);
+        }
+        EventType::CancelledWithdrawalRequest(reimbursement_request) => {
+            state
+                .withdrawal_transactions
+                .record_cancelled_withdrawal_request(reimbursement_request.clone());
+        }
+        EventType::FeeCapExceeded { timestamp, .. } => {
+            state.record_fee_cap_alert(*timestamp);
+        }
+        EventType::BroadcastAttempted {
+            withdrawal_id,
+            transaction_hash,
+            nonce,
+            timestamp,
+        } => {
+            state.withdrawal_transactions.record_broadcast_attempt(
+                *withdrawal_id,
+                *transaction_hash,
+                *nonce,
+                *timestamp,
+            );
+        }
+        EventType::BroadcastReconciled {
+            withdrawal_id,
+            transaction_hash,
+            outcome,
+        } => {
+            state.withdrawal_transactions.record_broadcast_outcome(
+                *withdrawal_id,
+                transaction_hash,
+                *outcome,
+            );
+        }
+        EventType::QuarantinedWithdrawal { .. } => {
+            // The withdrawal was finalized by its own event.
+        }
+    }
+}
+
+/// Keeps track of the block ranges that were never successfully scraped.
+/// Gaps are opened by skipped blocks or block ranges and closed once the blocks are scraped again.
+pub fn record_scraping_gaps(state: &mut State, payload: &EventType, timestamp: u64) {
+    match payload {
+        EventType::SkippedBlock { block_number } => {
+            state
+                .scraping_gaps
+                .insert(*block_number, *block_number, timestamp);
+        }
+        EventType::SkippedBlockRange {
+            from_block,
+            to_block,
+        } => {
+            state
+                .scraping_gaps
+                .insert(*from_block, *to_block, timestamp);
+        }
+        EventType::RescrapedBlockRange {
+            from_block,
+            to_block,
+        } => {
+            state.scraping_gaps.clear(*from_block, *to_block);
+        }
+        _ => {}
+    }
+}
+
+/// Appends the new status of the swaps affected by the given event to their status history.
+/// Must be called after the event was applied to the state.
+///
+/// Runs for every replayed event, so the status is only looked up by swap_tx_id or burn index
+/// and never by scanning the processed withdrawals or the swap events notified to the dex.
+pub fn record_swap_status_transitions(state: &mut State, payload: &EventType, timestamp: u64) {
+    let (tx_id, event_status) = match payload {
+        EventType::AcceptedSwapRequest(request) | EventType::QuarantinedSwapRequest(request) => {
+            (SwapTxId(request.swap_tx_id.clone()), None)
+        }
+        EventType::MintedToAppicDex { tx_id, .. } => (
+            tx_id.clone(),
+            Some(SwapStatus::MintedToAppicDex(tx_id.0.clone())),
+        ),
+        EventType::NotifiedSwapEventOrderToAppicDex { tx_id, .. } => (
+            tx_id.clone(),
+            Some(SwapStatus::NotifiedAppicDex(tx_id.0.clone())),
+        ),
+        EventType::CreatedTransaction { withdrawal_id, .. }
+        | EventType::SignedTransaction { withdrawal_id, .. }
+        | EventType::ReplacedTransaction { withdrawal_id, .. }
+        | EventType::ParkedTransaction { withdrawal_id, .. }
+        | EventType::FinalizedTransaction { withdrawal_id, .. } => {
+            match state.withdrawal_transactions.find_swap_tx_id(withdrawal_id) {
+                Some(tx_id) => (SwapTxId(tx_id), None),
+                None => return,
+            }
+        }
+        _ => return,
+    };
+    state.record_swap_status_transition(&tx_id, event_status, timestamp);
+}
+
+/// Indexes successfully finalized withdrawals by the time of the event finalizing them.
+/// Must be called after the event was applied to the state.
+pub fn record_finalized_withdrawals(state: &mut State, payload: &EventType, timestamp: u64) {
+    if let EventType::FinalizedTransaction { withdrawal_id, .. } = payload {
+        let native_ledger_id = state.native_ledger_id;
+        state.withdrawal_transactions.record_finalized_withdrawal(
+            *withdrawal_id,
+            native_ledger_id,
+            timestamp,
+        );
+    }
+}
+
+/// Counts minted deposits and successful withdrawals in the statistics of the day of the event.
+/// Must be called after the event was applied to the state.
+pub fn record_daily_stats(state: &mut State, payload: &EventType, timestamp: u64) {
+    match payload {
+        EventType::MintedNative { event_source, .. }
+        | EventType::MintedErc20 { event_source, .. } => {
+            let deposit = match state
+                .minted_events
+                .get(event_source)
+                .map(|minted| &minted.event)
+            {
+                Some(ReceivedContractEvent::NativeDeposit(deposit)) => {
+                    Some((state.native_ledger_id, deposit.value.change_units()))
+                }
+                Some(ReceivedContractEvent::Erc20Deposit(deposit)) => state
+                    .erc20_tokens
+                    .get_entry_alt(&deposit.erc20_contract_address)
+                    .map(|(ledger_id, _symbol)| (*ledger_id, deposit.value)),
+                _ => None,
+            };
+            if let Some((ledger_id, amount)) = deposit {
+                state
+                    .daily_stats
+                    .record_deposit(timestamp, ledger_id, amount);
+            }
+        }
+        EventType::FinalizedTransaction { withdrawal_id, .. } => {
+            if let Some((ledger_id, amount)) = state
+                .withdrawal_transactions
+                .successful_withdrawal(*withdrawal_id, state.native_ledger_id)
+            {
+                state
+                    .daily_stats
+                    .record_withdrawal(timestamp, ledger_id, amount);
+            }
+        }
+        _ => {}
     }
 }
 
 /// Records the given event payload in the event log and updates the state to reflect the change.
 pub fn process_event(state: &mut State, payload: EventType) {
     apply_state_transition(state, &payload);
+    // same timestamp as the one recorded with the event, so that replaying yields the same history
+    record_swap_status_transitions(state, &payload, ic_cdk::api::time());
+    record_scraping_gaps(state, &payload, ic_cdk::api::time());
+    record_finalized_withdrawals(state, &payload, ic_cdk::api::time());
+    record_daily_stats(state, &payload, ic_cdk::api::time());
     record_event(payload);
+    #[cfg(debug_assertions)]
+    if let Err(e) = state.validate_invariants() {
+        panic!("BUG: state invariants violated: {e}");
+    }
 }
 
-/// Recomputes the minter state from the event log.
+/// Recomputes the minter state from the event log, starting from the state snapshot
+/// of the archived events if the oldest events were archived.
 ///
 /// # Panics
 ///
 /// This function panics if:
 ///   * The event log is empty.
-///   * The first event in the log is not an Init event.
+///   * There is no snapshot and the first event in the log is not an Init event.
 ///   * One of the events in the log invalidates the minter's state invariants.
 pub fn replay_events() -> State {
-    with_event_iter(|iter| replay_events_internal(iter))
+    let snapshot = stored_snapshot();
+    with_event_iter(|iter| replay_events_from(snapshot, iter))
 }
 
-fn replay_events_internal<T: IntoIterator<Item = Event>>(events: T) -> State {
+fn stored_snapshot() -> Option<State> {
+    storage::state_snapshot().map(|bytes| {
+        StateSnapshot::decode_from_slice(&bytes)
+            .unwrap_or_else(|e| panic!("failed to decode state snapshot: {e}"))
+            .into()
+    })
+}
+
+/// Applies the events on top of `snapshot`, or on top of the state initialized by the
+/// first event if there is no snapshot.
+pub fn replay_events_from<T: IntoIterator<Item = Event>>(
+    snapshot: Option<State>,
+    events: T,
+) -> State {
     let mut events_iter = events.into_iter();
-    let mut state = match events_iter
-        .next()
-        .expect("the event log should not be empty")
-    {
-        Event {
-            payload: EventType::Init(init_arg),
-            ..
-        } => State::try_from(init_arg).expect("state initialization should succeed"),
-        other => panic!("the first event must be an Init event, got: {other:?}"),
+    let mut state = match snapshot {
+        Some(state) => state,
+        None => match events_iter
+            .next()
+            .expect("the event log should not be empty")
+        {
+            Event {
+                payload: EventType::Init(init_arg),
+                ..
+            } => State::try_from(init_arg).expect("state initialization should succeed"),
+            other => panic!("the first event must be an Init event, got: {other:?}"),
+        },
     };
     for event in events_iter {
         apply_state_transition(&mut state, &event.payload);
+        record_swap_status_transitions(&mut state, &event.payload, event.timestamp);
+        record_scraping_gaps(&mut state, &event.payload, event.timestamp);
+        record_finalized_withdrawals(&mut state, &event.payload, event.timestamp);
+        record_daily_stats(&mut state, &event.payload, event.timestamp);
     }
     state
 }
+
+/// Archives the events before absolute index `up_to_index`, see `storage::archive_events`.
+/// The retained events are copied in bounded chunks, the archive only takes effect once
+/// `storage::continue_archive_events` reports it completed.
+///
+/// The snapshot replacing the archived events is computed by replaying them rather than
+/// taken from the current state, so that replaying the retained events on top of it yields
+/// the same state as replaying the full log.
+pub fn archive_events(
+    state: &mut State,
+    up_to_index: u64,
+    expected_hash: Hash,
+) -> Result<(), ArchiveEventsError> {
+    let from_index = archive_events_without_recording(up_to_index, &expected_hash)?;
+    process_event(
+        state,
+        EventType::ArchivedEvents {
+            from_index,
+            up_to_index,
+            hash: expected_hash,
+        },
+    );
+    Ok(())
+}
+
+/// Archives the events as [`archive_events`] does without recording any event.
+/// Returns the absolute index of the first archived event.
+// public because it's used in tests since process_event
+// requires canister infrastructure to retrieve time
+pub fn archive_events_without_recording(
+    up_to_index: u64,
+    expected_hash: &Hash,
+) -> Result<u64, ArchiveEventsError> {
+    storage::validate_archive_events(up_to_index, expected_hash)?;
+    let from_index = storage::archived_event_count();
+    replace_events_by_snapshot(up_to_index);
+    Ok(from_index)
+}
+
+/// Starts pruning the retained events before absolute index `up_to_index` and storing the
+/// state obtained by replaying them as the new snapshot, then copies a first chunk of the
+/// retained events.
+fn replace_events_by_snapshot(up_to_index: u64) {
+    let from_index = storage::archived_event_count();
+    let snapshot = with_event_iter(|iter| {
+        replay_events_from(
+            stored_snapshot(),
+            iter.take((up_to_index - from_index) as usize),
+        )
+    });
+    storage::archive_events(up_to_index, StateSnapshot::from(&snapshot).encode_to_vec());
+    storage::continue_archive_events(storage::MAX_COPIED_EVENTS_PER_MESSAGE);
+}
+
+/// Returns the number of oldest retained events that can be pruned by [`compact_event_log`],
+/// i.e. the length of the longest prefix of settled events, see [`is_settled_event`].
+pub fn compactable_event_count(state: &State, threshold_block: BlockNumber) -> u64 {
+    with_event_iter(|iter| {
+        iter.take_while(|event| is_settled_event(state, &event.payload, threshold_block))
+            .count() as u64
+    })
+}
+
+/// Checks that the events before absolute index `up_to_index` are settled and only refer to
+/// blocks before `threshold_block`. Returns the number of events to prune.
+pub fn validate_compact_event_log(
+    state: &State,
+    threshold_block: BlockNumber,
+    up_to_index: u64,
+) -> Result<u64, CompactEventLogError> {
+    let archived_event_count = storage::archived_event_count();
+    let compactable_up_to_index =
+        archived_event_count + compactable_event_count(state, threshold_block);
+    if up_to_index > compactable_up_to_index {
+        return Err(CompactEventLogError::UnsettledEvents {
+            compactable_up_to_index,
+        });
+    }
+    Ok(up_to_index.saturating_sub(archived_event_count))
+}
+
+/// Archives the events before absolute index `up_to_index` as long as they are settled and
+/// only refer to blocks before `threshold_block`. Returns the number of pruned events.
+///
+/// As for [`archive_events`], the pruned events must have been exported first and
+/// `expected_hash` must match their hash.
+pub fn compact_event_log(
+    state: &mut State,
+    threshold_block: BlockNumber,
+    up_to_index: u64,
+    expected_hash: Hash,
+) -> Result<u64, CompactEventLogError> {
+    let pruned_count = validate_compact_event_log(state, threshold_block, up_to_index)?;
+    archive_events(state, up_to_index, expected_hash)?;
+    process_event(
+        state,
+        EventType::EventLogCompacted {
+            pruned_count,
+            threshold_block,
+        },
+    );
+    Ok(pruned_count)
+}
+
+/// Returns false if the event refers to a block at or after `threshold_block`, to a deposit
+/// that is not minted or released yet, to a withdrawal that is not settled yet,
+/// see [`WithdrawalTransactions::is_settled_withdrawal`], or to a quarantined item awaiting
+/// manual intervention.
+///
+/// Every event kind is classified explicitly so that new events are not pruned by default.
+///
+/// [`WithdrawalTransactions::is_settled_withdrawal`]: super::transactions::WithdrawalTransactions::is_settled_withdrawal
+fn is_settled_event(state: &State, payload: &EventType, threshold_block: BlockNumber) -> bool {
+    let is_settled_deposit = |source: EventSource, block_number: BlockNumber| {
+        block_number < threshold_block && !state.is_pending_event(&source)
+    };
+    let is_settled_withdrawal = |burn_index| {
+        state
+            .withdrawal_transactions
+            .is_settled_withdrawal(burn_index, threshold_block)
+    };
+    match payload {
+        EventType::AcceptedDeposit(event) => is_settled_deposit(event.source(), event.block_number),
+        EventType::AcceptedErc20Deposit(event) => {
+            is_settled_deposit(event.source(), event.block_number)
+        }
+        EventType::AcceptedWrappedIcrcBurn(event) => {
+            is_settled_deposit(event.source(), event.block_number)
+        }
+        EventType::ReceivedSwapOrder(event) => {
+            is_settled_deposit(event.source(), event.block_number)
+        }
+        EventType::InvalidDeposit { event_source, .. }
+        | EventType::InvalidEvent { event_source, .. }
+        | EventType::MintedNative { event_source, .. }
+        | EventType::MintedErc20 { event_source, .. }
+        | EventType::ReleasedIcrcToken { event_source, .. }
+        | EventType::MintedToAppicDex { event_source, .. }
+        | EventType::NotifiedSwapEventOrderToAppicDex { event_source, .. }
+        | EventType::RemovedLog { event_source }
+        | EventType::DepositReorged { event_source } => !state.is_pending_event(event_source),
+        EventType::DeployedWrappedIcrcToken(event) => event.block_number < threshold_block,
+        EventType::SyncedToBlock { block_number } | EventType::SkippedBlock { block_number } => {
+            *block_number < threshold_block
+        }
+        EventType::UpgradeGraceScrape { end_block, .. } => *end_block < threshold_block,
+        EventType::RescrapedBlockRange { to_block, .. }
+        | EventType::SkippedBlockRange { to_block, .. }
+        | EventType::BackfillCompleted { to_block, .. } => *to_block < threshold_block,
+        EventType::AcceptedNativeWithdrawalRequest(request) => {
+            is_settled_withdrawal(&request.ledger_burn_index)
+        }
+        EventType::AcceptedErc20WithdrawalRequest(request) => {
+            is_settled_withdrawal(&request.native_ledger_burn_index)
+        }
+        EventType::AcceptedSwapActivationRequest(request)
+        | EventType::AcceptedApprovalRevocationRequest(request) => {
+            is_settled_withdrawal(&request.native_ledger_burn_index)
+        }
+        EventType::AcceptedSwapRequest(request) => {
+            is_settled_withdrawal(&request.native_ledger_burn_index)
+        }
+        EventType::CreatedTransaction { withdrawal_id, .. }
+        | EventType::SignedTransaction { withdrawal_id, .. }
+        | EventType::ReplacedTransaction { withdrawal_id, .. }
+        | EventType::FinalizedTransaction { withdrawal_id, .. }
+        | EventType::ParkedTransaction { withdrawal_id, .. }
+        | EventType::RealizedSwapAmountOut { withdrawal_id, .. }
+        | EventType::FeeCapExceeded { withdrawal_id, .. }
+        | EventType::BroadcastAttempted { withdrawal_id, .. }
+        | EventType::BroadcastReconciled { withdrawal_id, .. } => {
+            is_settled_withdrawal(withdrawal_id)
+        }
+        EventType::ReimbursedNativeWithdrawal(reimbursed) => {
+            is_settled_withdrawal(&reimbursed.burn_in_block)
+        }
+        EventType::ReimbursedErc20Withdrawal {
+            native_ledger_burn_index,
+            ..
+        }
+        | EventType::ReimbursedIcrcWrap {
+            native_ledger_burn_index,
+            ..
+        } => is_settled_withdrawal(native_ledger_burn_index),
+        EventType::FailedErc20WithdrawalRequest(request)
+        | EventType::FailedIcrcLockRequest(request)
+        | EventType::CancelledWithdrawalRequest(request) => {
+            is_settled_withdrawal(&request.ledger_burn_index)
+        }
+        EventType::QuarantinedDeposit { .. }
+        | EventType::QuarantinedRelease { .. }
+        | EventType::QuarantinedReimbursement { .. }
+        | EventType::QuarantinedDexOrder(_)
+        | EventType::QuarantinedSwapRequest(_)
+        | EventType::QuarantinedWithdrawal { .. } => false,
+        // configuration and accounting events, fully captured by the snapshot
+        EventType::Init(_)
+        | EventType::Upgrade(_)
+        | EventType::AddedErc20Token(_)
+        | EventType::SwapContractActivated { .. }
+        | EventType::UpdatedAddressList { .. }
+        | EventType::ChangedConfirmationPolicy { .. }
+        | EventType::FeatureFlagChanged { .. }
+        | EventType::NativeLedgerMetadataMismatch(_)
+        | EventType::NativeLedgerMetadataMatched(_)
+        | EventType::UpgradedWhileNotDrained { .. }
+        | EventType::ArchivedEvents { .. }
+        | EventType::EventLogCompacted { .. }
+        | EventType::ProviderDisagreement { .. }
+        | EventType::ProviderRateLimited { .. }
+        | EventType::ReleasedGasFromGasTankWithUsdc { .. }
+        | EventType::GasTankUpdate { .. }
+        | EventType::FeeCollected { .. }
+        | EventType::CollectedFeesWithdrawn { .. }
+        | EventType::UnaccountedFeesFound { .. }
+        | EventType::CollectedFeesWithdrawalFailed { .. }
+        | EventType::MissingFeesFound { .. } => true,
+        EventType::CollectedFeesWithdrawalStarted { ledger_id, .. } => {
+            !state.pending_fee_withdrawals.contains_key(ledger_id)
+        }
+    }
+}
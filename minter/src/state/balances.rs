@@ -7,9 +7,10 @@ use crate::{
 };
 use candid::Principal;
 use evm_rpc_client::eth_types::Address;
+use minicbor::{Decode, Encode};
 use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
 pub struct NativeBalance {
     /// Amount of ETH controlled by the minter's address via tECDSA.
     /// Note that invalid deposits are not accounted for and so this value
@@ -17,15 +18,20 @@ pub struct NativeBalance {
     /// or retrieved by the JSON-RPC call `eth_getBalance`.
     /// Also, some transactions may have gone directly to the minter's address
     /// without going via the helper smart contract.
+    #[n(0)]
     pub native_balance: Wei,
     /// Total amount of fees across all finalized transactions icNative -> Native. conversion of twin native token to token on the home chain.
+    #[n(1)]
     pub total_effective_tx_fees: Wei,
     /// Total amount of fees that were charged to the user during the withdrawal
     /// but not consumed by the finalized transaction icNative -> Native. conversion of twin native token to token on the home chain.
+    #[n(2)]
     pub total_unspent_tx_fees: Wei,
 
     // fee collected to cover signing cost, for withdraw and lock(mint on evm) operations.
-    // after each operation withdrawal_native_fee should be added to total collected fee
+    // native fees are added to total collected fee when they are locked into
+    // `FEES_SUBACCOUNT`, see `EventType::FeeCollected`
+    #[n(3)]
     pub total_collected_operation_native_fee: Wei,
 }
 
@@ -71,6 +77,18 @@ impl NativeBalance {
             })
     }
 
+    pub fn total_collected_operation_native_fee_add(&mut self, value: Wei) {
+        self.total_collected_operation_native_fee = self
+            .total_collected_operation_native_fee
+            .checked_add(value)
+            .unwrap_or_else(|| {
+                panic!(
+                    "BUG: overflow when adding {} to {}",
+                    value, self.total_collected_operation_native_fee
+                )
+            })
+    }
+
     pub fn native_balance(&self) -> Wei {
         self.native_balance
     }
@@ -84,8 +102,9 @@ impl NativeBalance {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode)]
 pub struct Erc20Balances {
+    #[n(0)]
     pub balance_by_erc20_contract: BTreeMap<Address, Erc20Value>,
 }
 
@@ -128,8 +147,9 @@ impl Erc20Balances {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode)]
 pub struct IcrcBalances {
+    #[cbor(n(0), with = "crate::cbor::principal::btree_map")]
     pub balance_by_icrc_ledger: BTreeMap<Principal, IcrcValue>,
 }
 
@@ -172,11 +192,60 @@ impl IcrcBalances {
     }
 }
 
+/// Cumulative volumes of an ERC-20 token bridged through the minter.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct TokenVolume {
+    /// Total amount of minted deposits.
+    #[n(0)]
+    pub deposited: Erc20Value,
+    /// Total amount of accepted withdrawal requests, including the reimbursed ones.
+    #[n(1)]
+    pub withdrawn: Erc20Value,
+    /// Total amount reimbursed for failed or cancelled withdrawals.
+    #[n(2)]
+    pub reimbursed: Erc20Value,
+}
+
+impl TokenVolume {
+    pub fn deposited_add(&mut self, value: Erc20Value) {
+        self.deposited = self
+            .deposited
+            .checked_add(value)
+            .unwrap_or_else(|| panic!("BUG: overflow when adding {} to {}", value, self.deposited))
+    }
+
+    pub fn withdrawn_add(&mut self, value: Erc20Value) {
+        self.withdrawn = self
+            .withdrawn
+            .checked_add(value)
+            .unwrap_or_else(|| panic!("BUG: overflow when adding {} to {}", value, self.withdrawn))
+    }
+
+    pub fn reimbursed_add(&mut self, value: Erc20Value) {
+        self.reimbursed = self
+            .reimbursed
+            .checked_add(value)
+            .unwrap_or_else(|| panic!("BUG: overflow when adding {} to {}", value, self.reimbursed))
+    }
+}
+
+impl Default for TokenVolume {
+    fn default() -> Self {
+        Self {
+            deposited: Erc20Value::ZERO,
+            withdrawn: Erc20Value::ZERO,
+            reimbursed: Erc20Value::ZERO,
+        }
+    }
+}
+
 //  tank for collecting un-used transaction fees to be used for next swap-bridge transactions sent
 //  by the appic dex(User paying usdc to cover fees)
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
 pub struct GasTank {
+    #[n(0)]
     pub usdc_balance: Erc20Value,
+    #[n(1)]
     pub native_balance: Wei,
 }
 
@@ -237,14 +306,7 @@ pub fn release_gas_from_tank_with_usdc(
     gas_amount: Wei,
     swap_tx_id: String,
 ) -> Result<LedgerBurnIndex, ReleaseGasFromTankError> {
-    let (native_tank_balance, next_swap_ledger_burn_index) = read_state(|s| {
-        (
-            s.gas_tank.native_balance,
-            s.next_swap_ledger_burn_index.expect(
-                "Bug: next_swap_ledger_burn_index should be available if swapping is active",
-            ),
-        )
-    });
+    let native_tank_balance = read_state(|s| s.gas_tank.native_balance);
 
     if native_tank_balance < gas_amount {
         return Err(ReleaseGasFromTankError {
@@ -253,7 +315,12 @@ pub fn release_gas_from_tank_with_usdc(
         });
     }
 
-    mutate_state(|s| {
+    // The swap index is allocated by applying the event, reading it within the same
+    // state mutation guarantees that concurrent swap requests get distinct indices.
+    Ok(mutate_state(|s| {
+        let swap_index = s
+            .next_swap_ledger_burn_index
+            .expect("Bug: next_swap_ledger_burn_index should be available if swapping is active");
         process_event(
             s,
             EventType::ReleasedGasFromGasTankWithUsdc {
@@ -261,8 +328,7 @@ pub fn release_gas_from_tank_with_usdc(
                 gas_amount,
                 swap_tx_id,
             },
-        )
-    });
-
-    Ok(next_swap_ledger_burn_index)
+        );
+        swap_index
+    }))
 }
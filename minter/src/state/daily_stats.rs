@@ -0,0 +1,119 @@
+use crate::numeric::Erc20Value;
+use candid::Principal;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeMap;
+
+pub const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Number of days, including the most recent one, for which statistics are retained.
+pub const MAX_RETAINED_DAYS: u64 = 90;
+
+/// Index of the UTC day containing the IC time `timestamp`, i.e. the number of days since
+/// the Unix epoch.
+pub fn day_index(timestamp: u64) -> u64 {
+    timestamp / NANOS_PER_DAY
+}
+
+/// Deposits and withdrawals of a token during one day.
+/// Volumes are in the smallest unit of the token.
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
+pub struct TokenDailyStats {
+    #[n(0)]
+    pub deposit_count: u64,
+    #[n(1)]
+    pub deposit_volume: Erc20Value,
+    #[n(2)]
+    pub withdrawal_count: u64,
+    #[n(3)]
+    pub withdrawal_volume: Erc20Value,
+}
+
+impl Default for TokenDailyStats {
+    fn default() -> Self {
+        Self {
+            deposit_count: 0,
+            deposit_volume: Erc20Value::ZERO,
+            withdrawal_count: 0,
+            withdrawal_volume: Erc20Value::ZERO,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Encode, Decode)]
+struct DayStats {
+    #[cbor(n(0), with = "crate::cbor::principal::btree_map")]
+    tokens: BTreeMap<Principal, TokenDailyStats>,
+}
+
+/// Statistics of the minted deposits and successful withdrawals per day and per token ledger,
+/// for the last [`MAX_RETAINED_DAYS`] days. Older days are evicted when a newer day is recorded.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Encode, Decode)]
+pub struct DailyStats {
+    #[n(0)]
+    days: BTreeMap<u64, DayStats>,
+}
+
+impl DailyStats {
+    pub fn record_deposit(&mut self, timestamp: u64, ledger_id: Principal, amount: Erc20Value) {
+        if let Some(stats) = self.stats_mut(timestamp, ledger_id) {
+            stats.deposit_count += 1;
+            stats.deposit_volume = add_volume(stats.deposit_volume, amount);
+        }
+    }
+
+    pub fn record_withdrawal(&mut self, timestamp: u64, ledger_id: Principal, amount: Erc20Value) {
+        if let Some(stats) = self.stats_mut(timestamp, ledger_id) {
+            stats.withdrawal_count += 1;
+            stats.withdrawal_volume = add_volume(stats.withdrawal_volume, amount);
+        }
+    }
+
+    /// Returns the statistics of the days from `from_day` onwards, oldest first,
+    /// optionally restricted to the token with the given ledger id.
+    pub fn iter_from(
+        &self,
+        from_day: u64,
+        ledger_id: Option<Principal>,
+    ) -> impl Iterator<Item = (u64, &Principal, &TokenDailyStats)> {
+        self.days
+            .range(from_day..)
+            .flat_map(move |(day, day_stats)| {
+                day_stats
+                    .tokens
+                    .iter()
+                    .filter(move |(token, _)| {
+                        ledger_id.map_or(true, |ledger_id| **token == ledger_id)
+                    })
+                    .map(move |(token, stats)| (*day, token, stats))
+            })
+    }
+
+    /// Returns the statistics of the token on the day of `timestamp` after evicting the days
+    /// outside of the retained window, or `None` if that day is itself outside of the window.
+    fn stats_mut(&mut self, timestamp: u64, ledger_id: Principal) -> Option<&mut TokenDailyStats> {
+        let day = day_index(timestamp);
+        let newest_day = self
+            .days
+            .last_key_value()
+            .map_or(day, |(newest_day, _)| day.max(*newest_day));
+        let oldest_retained_day = newest_day.saturating_sub(MAX_RETAINED_DAYS - 1);
+        if day < oldest_retained_day {
+            return None;
+        }
+        self.days = self.days.split_off(&oldest_retained_day);
+        Some(
+            self.days
+                .entry(day)
+                .or_default()
+                .tokens
+                .entry(ledger_id)
+                .or_default(),
+        )
+    }
+}
+
+fn add_volume(volume: Erc20Value, amount: Erc20Value) -> Erc20Value {
+    volume
+        .checked_add(amount)
+        .unwrap_or_else(|| panic!("BUG: overflow when adding {amount} to {volume}"))
+}
@@ -1,5 +1,5 @@
 use crate::{
-    candid_types::dex_orders::DexOrderArgs,
+    candid_types::{dex_orders::DexOrderArgs, AddOrRemove, CandidConfirmationPolicy, ListKind},
     contract_logs::{
         swap::swap_logs::ReceivedSwapEvent,
         types::{
@@ -9,24 +9,29 @@ use crate::{
         EventSource, ReceivedContractEvent,
     },
     erc20::ERC20Token,
+    fee_collector::PendingFeeWithdrawal,
     lifecycle::{InitArg, UpgradeArg},
     numeric::{
-        BlockNumber, Erc20Value, IcrcValue, LedgerBurnIndex, LedgerMintIndex, LedgerReleaseIndex,
-        Wei,
+        BlockNumber, Erc20Value, IcrcValue, LedgerBurnIndex, LedgerLockIndex, LedgerMintIndex,
+        LedgerReleaseIndex, TransactionNonce, Wei, WeiPerGas,
     },
-    rpc_declarations::TransactionReceipt,
+    rpc_client::providers::Provider,
+    rpc_declarations::{Hash, TransactionReceipt},
     state::transactions::{Erc20Approve, ExecuteSwapRequest},
-    tx::{Eip1559TransactionRequest, SignedEip1559TransactionRequest},
+    tx::{gas_fees::FeeCap, Eip1559TransactionRequest, SignedEip1559TransactionRequest},
     tx_id::SwapTxId,
 };
 use candid::Principal;
 use evm_rpc_client::eth_types::Address;
+use icrc_ledger_types::icrc1::account::Account;
 use minicbor::{Decode, Encode};
 
+use super::feature_flags::FeatureFlag;
 use super::transactions::{
-    Erc20WithdrawalRequest, NativeWithdrawalRequest, Reimbursed, ReimbursementIndex,
-    ReimbursementRequest,
+    BroadcastOutcome, Erc20WithdrawalRequest, NativeWithdrawalRequest, PendingReimbursementRequest,
+    Reimbursed, ReimbursementIndex,
 };
+use super::NativeLedgerMetadata;
 
 /// The event describing the  minter state transition.
 #[derive(Clone, Debug, Encode, Decode, PartialEq, Eq)]
@@ -147,7 +152,7 @@ pub enum EventType {
     },
     /// The minter could not burn the given amount of ckERC20 tokens.
     #[n(20)]
-    FailedErc20WithdrawalRequest(#[n(0)] ReimbursementRequest),
+    FailedErc20WithdrawalRequest(#[n(0)] PendingReimbursementRequest),
     /// The minter unexpectedly panic while processing a deposit.
     // /// The deposit is quarantined to prevent any double minting and
     // /// will not be processed without further manual intervention.
@@ -214,7 +219,7 @@ pub enum EventType {
         transfer_fee: IcrcValue,
     },
     #[n(29)]
-    FailedIcrcLockRequest(#[n(0)] ReimbursementRequest),
+    FailedIcrcLockRequest(#[n(0)] PendingReimbursementRequest),
     #[n(30)]
     ReimbursedIcrcWrap {
         #[cbor(n(0), with = "crate::cbor::id")]
@@ -289,6 +294,301 @@ pub enum EventType {
         #[n(1)]
         native_deposited: Wei,
     },
+    /// The minter received a log entry flagged as `removed` due to a chain reorganisation.
+    #[n(41)]
+    RemovedLog {
+        /// The unique identifier of the removed log entry on the EVM network.
+        #[n(0)]
+        event_source: EventSource,
+    },
+    /// A previously accepted event was removed from the chain by a reorganisation
+    /// before it was processed, and has been dropped from the pending queues.
+    #[n(42)]
+    DepositReorged {
+        /// The unique identifier of the reorged event on the EVM network.
+        #[n(0)]
+        event_source: EventSource,
+    },
+    /// After an upgrade, the blocks after `start_block` up to `end_block` are scraped again
+    /// in case logs were missed around the upgrade.
+    #[n(43)]
+    UpgradeGraceScrape {
+        /// The block from which scraping resumes, i.e. the new last scraped block.
+        #[n(0)]
+        start_block: BlockNumber,
+        /// The last scraped block before the upgrade.
+        #[n(1)]
+        end_block: BlockNumber,
+    },
+    /// The controller updated one of the withdrawal destination address lists.
+    #[n(44)]
+    UpdatedAddressList {
+        #[n(0)]
+        list: ListKind,
+        #[n(1)]
+        action: AddOrRemove,
+        #[n(2)]
+        addresses: Vec<Address>,
+    },
+    /// The minter successfully scraped again a block range that contained scraping gaps.
+    #[n(45)]
+    RescrapedBlockRange {
+        #[n(0)]
+        from_block: BlockNumber,
+        #[n(1)]
+        to_block: BlockNumber,
+    },
+    /// The confirmation policy was changed by an upgrade.
+    /// The policy itself is applied by the [`EventType::Upgrade`] preceding this event.
+    #[n(46)]
+    ChangedConfirmationPolicy {
+        #[n(0)]
+        from: CandidConfirmationPolicy,
+        #[n(1)]
+        to: CandidConfirmationPolicy,
+    },
+    /// The minter gave up on the transaction of a withdrawal after too many replacements
+    /// and replaced it by a cancellation transaction to free its nonce.
+    #[n(47)]
+    ParkedTransaction {
+        /// The withdrawal identifier.
+        #[cbor(n(0), with = "crate::cbor::id")]
+        withdrawal_id: LedgerBurnIndex,
+        /// The cancellation transaction, with the same nonce as the parked transaction.
+        #[n(1)]
+        cancellation: Eip1559TransactionRequest,
+    },
+    /// Providers returned inconsistent results for a consensus-critical RPC call.
+    #[n(49)]
+    ProviderDisagreement {
+        /// The JSON-RPC method, e.g. `eth_getLogs`.
+        #[n(0)]
+        method: String,
+        /// The queried block range for `eth_getLogs` or transaction hash for `eth_getTransactionReceipt`.
+        #[n(1)]
+        block_range_or_hash: String,
+        /// Keccak-256 hash of the serialized response returned by each provider.
+        #[n(2)]
+        per_provider_hash: Vec<(String, Hash)>,
+        /// The time at which the disagreement was observed, used for rate limiting.
+        #[n(3)]
+        timestamp: u64,
+    },
+    /// The controller archived the events with absolute indices `from_index..up_to_index`,
+    /// which were pruned from the event log and replaced by a state snapshot.
+    #[n(50)]
+    ArchivedEvents {
+        #[n(0)]
+        from_index: u64,
+        #[n(1)]
+        up_to_index: u64,
+        /// Keccak-256 hash of the archived events, see `storage::raw_events_hash`.
+        #[n(2)]
+        hash: Hash,
+    },
+    /// A provider kept answering HTTP 429 Too Many Requests and is skipped until `retry_after_ns`.
+    #[n(51)]
+    ProviderRateLimited {
+        #[n(0)]
+        provider: Provider,
+        /// The IC time before which the provider should not be queried.
+        #[n(1)]
+        retry_after_ns: u64,
+    },
+    /// The user cancelled a native withdrawal request before any transaction was created for it.
+    /// The burned amount minus the ledger transfer fee is reimbursed.
+    #[n(52)]
+    CancelledWithdrawalRequest(#[n(0)] PendingReimbursementRequest),
+    /// The controller compacted the event log: the `pruned_count` oldest retained events were
+    /// settled and only referred to blocks before `threshold_block`, so they were pruned
+    /// and replaced by a state snapshot.
+    #[n(53)]
+    EventLogCompacted {
+        #[n(0)]
+        pruned_count: u64,
+        #[n(1)]
+        threshold_block: BlockNumber,
+    },
+    /// The canister was upgraded while items were still in flight, e.g. without calling
+    /// `prepare_upgrade` first or before `drain_status` reported that it was safe to stop.
+    #[n(54)]
+    UpgradedWhileNotDrained {
+        #[n(0)]
+        in_flight_count: u64,
+    },
+    /// The native ledger reported a symbol or number of decimals that differs from the
+    /// configured native symbol or from 18 decimals.
+    #[n(55)]
+    NativeLedgerMetadataMismatch(#[n(0)] NativeLedgerMetadata),
+    /// The minter accepted a request to revoke the approval given to a spender by
+    /// setting its allowance to zero.
+    #[n(56)]
+    AcceptedApprovalRevocationRequest(#[n(0)] Erc20Approve),
+    /// The controller scraped again the historical block range `from_block..=to_block`,
+    /// which is below the last scraped block, without moving the last scraped block.
+    #[n(57)]
+    BackfillCompleted {
+        #[n(0)]
+        from_block: BlockNumber,
+        #[n(1)]
+        to_block: BlockNumber,
+        /// Number of new events that were recorded.
+        #[n(2)]
+        accepted_events: u64,
+        /// Number of new events with an invalid payload.
+        #[n(3)]
+        invalid_events: u64,
+        /// Number of events that were already recorded before the backfill.
+        #[n(4)]
+        duplicate_events: u64,
+    },
+    /// The amount of output tokens delivered to the recipient of a successful swap,
+    /// as parsed from the logs of its transaction receipt.
+    /// Recorded right before the [`EventType::FinalizedTransaction`] of the swap.
+    #[n(58)]
+    RealizedSwapAmountOut {
+        /// The withdrawal identifier of the swap.
+        #[cbor(n(0), with = "crate::cbor::id")]
+        withdrawal_id: LedgerBurnIndex,
+        #[n(1)]
+        amount_out: Erc20Value,
+    },
+    /// A feature flag was enabled or disabled, either by the controller or by the minter
+    /// itself, e.g. when draining before an upgrade.
+    #[n(59)]
+    FeatureFlagChanged {
+        #[n(0)]
+        flag: FeatureFlag,
+        #[n(1)]
+        enabled: bool,
+    },
+    /// Fees were transferred to `FEES_SUBACCOUNT` of the minter, where they are held until
+    /// they are withdrawn to the fee collector.
+    #[n(60)]
+    FeeCollected {
+        #[cbor(n(0), with = "crate::cbor::principal")]
+        ledger_id: Principal,
+        #[n(1)]
+        amount: IcrcValue,
+        #[cbor(n(2), with = "crate::cbor::id")]
+        block_index: LedgerLockIndex,
+    },
+    /// The controller withdrew fees held in `FEES_SUBACCOUNT` of the minter to the
+    /// fee collector. `amount` includes the ledger transfer fee.
+    #[n(61)]
+    CollectedFeesWithdrawn {
+        #[cbor(n(0), with = "crate::cbor::principal")]
+        ledger_id: Principal,
+        #[cbor(n(1), with = "crate::cbor::account")]
+        to: Account,
+        #[n(2)]
+        amount: IcrcValue,
+        /// The index of the transfer on the ledger.
+        #[n(3)]
+        block_index: u64,
+    },
+    /// The fees of the transaction of a withdrawal exceeded the fee cap, so that the transaction
+    /// was not created or not signed. Recorded at most once per [`crate::FEE_CAP_ALERT_INTERVAL`].
+    #[n(62)]
+    FeeCapExceeded {
+        #[cbor(n(0), with = "crate::cbor::id")]
+        withdrawal_id: LedgerBurnIndex,
+        #[n(1)]
+        max_fee_per_gas: WeiPerGas,
+        #[n(2)]
+        max_transaction_fee: Wei,
+        #[n(3)]
+        cap: FeeCap,
+        /// The time of the alert, used for rate limiting.
+        #[n(4)]
+        timestamp: u64,
+    },
+    /// The minter is about to send a signed transaction of a withdrawal for the first time.
+    /// Recorded before awaiting `eth_sendRawTransaction`, so that a send interrupted by an
+    /// upgrade or a trap is reconciled afterwards.
+    #[n(63)]
+    BroadcastAttempted {
+        #[cbor(n(0), with = "crate::cbor::id")]
+        withdrawal_id: LedgerBurnIndex,
+        #[n(1)]
+        transaction_hash: Hash,
+        #[n(2)]
+        nonce: TransactionNonce,
+        #[n(3)]
+        timestamp: u64,
+    },
+    /// The outcome of a [`EventType::BroadcastAttempted`] became known.
+    #[n(64)]
+    BroadcastReconciled {
+        #[cbor(n(0), with = "crate::cbor::id")]
+        withdrawal_id: LedgerBurnIndex,
+        #[n(1)]
+        transaction_hash: Hash,
+        #[n(2)]
+        outcome: BroadcastOutcome,
+    },
+    /// The transaction of an ERC-20 withdrawal of a non-standard token succeeded without a
+    /// `Transfer` event of the withdrawn amount from the minter to the destination.
+    /// The withdrawal stays finalized as successful, as recorded on chain, and is left for
+    /// manual review instead of being reimbursed.
+    /// Recorded right after the [`EventType::FinalizedTransaction`] of the withdrawal.
+    #[n(65)]
+    QuarantinedWithdrawal {
+        #[cbor(n(0), with = "crate::cbor::id")]
+        withdrawal_id: LedgerBurnIndex,
+    },
+    /// `FEES_SUBACCOUNT` of the minter holds more than the fees collected according to the
+    /// events, e.g. fees transferred before they were accounted for. The difference is
+    /// added to the collected fees.
+    #[n(67)]
+    UnaccountedFeesFound {
+        #[cbor(n(0), with = "crate::cbor::principal")]
+        ledger_id: Principal,
+        #[n(1)]
+        amount: IcrcValue,
+    },
+    /// The native ledger reported the configured native symbol and 18 decimals.
+    #[n(68)]
+    NativeLedgerMetadataMatched(#[n(0)] NativeLedgerMetadata),
+    /// The minter gave up on a block range whose logs did not fit into a response,
+    /// even after bisecting it, and moved past it.
+    /// The range stays a scraping gap until it is scraped again.
+    #[n(69)]
+    SkippedBlockRange {
+        #[n(0)]
+        from_block: BlockNumber,
+        #[n(1)]
+        to_block: BlockNumber,
+    },
+    /// The minter is about to transfer collected fees of `ledger_id` to the fee collector.
+    /// Recorded before awaiting the transfer, so that it is retried with the same arguments
+    /// after an upgrade. Its outcome is recorded with [`EventType::CollectedFeesWithdrawn`]
+    /// or [`EventType::CollectedFeesWithdrawalFailed`].
+    #[n(70)]
+    CollectedFeesWithdrawalStarted {
+        #[cbor(n(0), with = "crate::cbor::principal")]
+        ledger_id: Principal,
+        #[n(1)]
+        withdrawal: PendingFeeWithdrawal,
+    },
+    /// The ledger rejected the transfer of a [`EventType::CollectedFeesWithdrawalStarted`].
+    #[n(71)]
+    CollectedFeesWithdrawalFailed {
+        #[cbor(n(0), with = "crate::cbor::principal")]
+        ledger_id: Principal,
+        #[n(1)]
+        reason: String,
+    },
+    /// `FEES_SUBACCOUNT` of the minter holds less than the fees collected according to the
+    /// events, e.g. after a transfer to the fee collector that was executed but rejected as
+    /// too old when retried. The difference is removed from the collected fees.
+    #[n(72)]
+    MissingFeesFound {
+        #[cbor(n(0), with = "crate::cbor::principal")]
+        ledger_id: Principal,
+        #[n(1)]
+        amount: IcrcValue,
+    },
 }
 
 impl ReceivedContractEvent {
@@ -0,0 +1,101 @@
+use crate::candid_types::SetFeatureFlagError;
+use crate::state::State;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Named switches of the minter behavior, changed by `FeatureFlagChanged` events.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Encode, Decode)]
+pub enum FeatureFlag {
+    /// Swap orders are minted to the DEX canister and swap requests are accepted.
+    /// Enabled when the approval of the swap contract is finalized, and disabled when it
+    /// is revoked.
+    #[n(0)]
+    SwapActive,
+    /// New withdrawals and deposits are rejected, see `prepare_upgrade`.
+    /// Disabled when the minter is upgraded.
+    #[n(1)]
+    Draining,
+}
+
+impl FeatureFlag {
+    pub const ALL: [FeatureFlag; 2] = [FeatureFlag::SwapActive, FeatureFlag::Draining];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FeatureFlag::SwapActive => "swap_active",
+            FeatureFlag::Draining => "draining",
+        }
+    }
+}
+
+impl fmt::Display for FeatureFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownFeatureFlag(pub String);
+
+impl FromStr for FeatureFlag {
+    type Err = UnknownFeatureFlag;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|flag| flag.name() == name)
+            .ok_or_else(|| UnknownFeatureFlag(name.to_string()))
+    }
+}
+
+/// Values of the feature flags. Flags that were never changed are disabled.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Encode, Decode)]
+pub struct FeatureFlags {
+    #[n(0)]
+    flags: BTreeMap<FeatureFlag, bool>,
+}
+
+impl FeatureFlags {
+    pub fn is_enabled(&self, flag: FeatureFlag) -> bool {
+        self.flags.get(&flag).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, flag: FeatureFlag, enabled: bool) {
+        self.flags.insert(flag, enabled);
+    }
+
+    /// Returns every known flag with its value.
+    pub fn iter(&self) -> impl Iterator<Item = (FeatureFlag, bool)> + '_ {
+        FeatureFlag::ALL
+            .into_iter()
+            .map(|flag| (flag, self.is_enabled(flag)))
+    }
+}
+
+/// Validates that the flag named `flag` can be set to `enabled` by the controller.
+pub fn validate_feature_flag_change(
+    state: &State,
+    flag: &str,
+    enabled: bool,
+) -> Result<FeatureFlag, SetFeatureFlagError> {
+    let flag = FeatureFlag::from_str(flag).map_err(|UnknownFeatureFlag(flag)| {
+        SetFeatureFlagError::UnknownFlag {
+            flag,
+            known_flags: FeatureFlag::ALL
+                .iter()
+                .map(|flag| flag.name().to_string())
+                .collect(),
+        }
+    })?;
+    if flag == FeatureFlag::SwapActive
+        && enabled
+        && (state.twin_usdc_info.is_none()
+            || state.dex_canister_id.is_none()
+            || state.swap_contract_address.is_none())
+    {
+        return Err(SetFeatureFlagError::SwapFeatureNotActivated);
+    }
+    Ok(flag)
+}
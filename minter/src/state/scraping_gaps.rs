@@ -0,0 +1,125 @@
+use crate::numeric::BlockNumber;
+use minicbor::{Decode, Encode};
+
+/// Inclusive range of blocks whose logs were never successfully scraped.
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
+pub struct ScrapingGap {
+    #[n(0)]
+    pub from_block: BlockNumber,
+    #[n(1)]
+    pub to_block: BlockNumber,
+    /// Timestamp of the event that first skipped a block of this range.
+    #[n(2)]
+    pub skipped_at: u64,
+}
+
+impl ScrapingGap {
+    pub fn block_count(&self) -> u64 {
+        let count = self
+            .to_block
+            .checked_sub(self.from_block)
+            .and_then(|diff| diff.checked_increment())
+            .expect("BUG: gap ranges are non-empty");
+        u64::try_from(count.into_inner()).unwrap_or(u64::MAX)
+    }
+}
+
+/// Sorted set of disjoint, non-adjacent block ranges that were not scraped.
+/// Adjacent or overlapping ranges are merged on insertion.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Encode, Decode)]
+pub struct ScrapingGaps {
+    #[n(0)]
+    gaps: Vec<ScrapingGap>,
+}
+
+impl ScrapingGaps {
+    pub fn insert(&mut self, from_block: BlockNumber, to_block: BlockNumber, skipped_at: u64) {
+        assert!(
+            from_block <= to_block,
+            "BUG: invalid gap {from_block}..={to_block}"
+        );
+        let mut merged = ScrapingGap {
+            from_block,
+            to_block,
+            skipped_at,
+        };
+        let mut gaps = Vec::with_capacity(self.gaps.len() + 1);
+        for gap in self.gaps.drain(..) {
+            if touches(&gap, &merged) {
+                merged = ScrapingGap {
+                    from_block: gap.from_block.min(merged.from_block),
+                    to_block: gap.to_block.max(merged.to_block),
+                    skipped_at: gap.skipped_at.min(merged.skipped_at),
+                };
+            } else {
+                gaps.push(gap);
+            }
+        }
+        let index = gaps.partition_point(|gap| gap.from_block < merged.from_block);
+        gaps.insert(index, merged);
+        self.gaps = gaps;
+    }
+
+    /// Removes the blocks `from_block..=to_block` from the gaps, splitting ranges if needed.
+    /// Returns whether any gap was affected.
+    pub fn clear(&mut self, from_block: BlockNumber, to_block: BlockNumber) -> bool {
+        let mut cleared = false;
+        let mut gaps = Vec::with_capacity(self.gaps.len() + 1);
+        for gap in self.gaps.drain(..) {
+            if gap.to_block < from_block || to_block < gap.from_block {
+                gaps.push(gap);
+                continue;
+            }
+            cleared = true;
+            if gap.from_block < from_block {
+                gaps.push(ScrapingGap {
+                    to_block: from_block
+                        .checked_decrement()
+                        .expect("BUG: from_block is above another block"),
+                    ..gap.clone()
+                });
+            }
+            if to_block < gap.to_block {
+                gaps.push(ScrapingGap {
+                    from_block: to_block
+                        .checked_increment()
+                        .expect("BUG: to_block is below another block"),
+                    ..gap
+                });
+            }
+        }
+        self.gaps = gaps;
+        cleared
+    }
+
+    pub fn overlaps(&self, from_block: BlockNumber, to_block: BlockNumber) -> bool {
+        self.gaps
+            .iter()
+            .any(|gap| gap.from_block <= to_block && from_block <= gap.to_block)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ScrapingGap> {
+        self.gaps.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.gaps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gaps.is_empty()
+    }
+
+    /// Total number of blocks in all gaps.
+    pub fn total_blocks(&self) -> u64 {
+        self.gaps
+            .iter()
+            .fold(0_u64, |total, gap| total.saturating_add(gap.block_count()))
+    }
+}
+
+fn touches(a: &ScrapingGap, b: &ScrapingGap) -> bool {
+    let a_end = a.to_block.checked_increment().unwrap_or(BlockNumber::MAX);
+    let b_end = b.to_block.checked_increment().unwrap_or(BlockNumber::MAX);
+    a.from_block <= b_end && b.from_block <= a_end
+}
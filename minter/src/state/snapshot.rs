@@ -0,0 +1,576 @@
+use super::{
+    balances::{Erc20Balances, GasTank, IcrcBalances, NativeBalance, TokenVolume},
+    daily_stats::DailyStats,
+    feature_flags::FeatureFlags,
+    scraping_gaps::ScrapingGaps,
+    transactions::{
+        BroadcastAttempt, ExecuteSwapRequest, Reimbursed, ReimbursedError, ReimbursementIndex,
+        ReimbursementRequest, WithdrawalRequest, WithdrawalTransactions,
+    },
+    InvalidEventReason, MintedEvent, MintedToDex, NativeLedgerMetadata, NotifiedToAppiDex,
+    OnchainApproval, ReleasedEvent, State, TwinUSDCInfo, WrappedIcrcDeployment,
+};
+use crate::{
+    candid_types::{dex_orders::DexOrderArgs, CandidConfirmationPolicy, SwapStatus},
+    cbor::principal::CborPrincipal,
+    contract_logs::{EventSource, ReceivedContractEvent},
+    erc20::ERC20TokenSymbol,
+    evm_config::{EvmNetwork, GasLimits},
+    fee_collector::PendingFeeWithdrawal,
+    map::{DedupMultiKeyMap, MultiKeyMap},
+    numeric::{
+        BlockNumber, Erc20Value, IcrcValue, LedgerBurnIndex, TransactionNonce, Wei, WeiPerGas,
+    },
+    rpc_client::providers::Provider,
+    tx::{
+        gas_fees::{FeeCap, GasFeeConfig},
+        FinalizedEip1559Transaction, SignedTransactionRequest, TransactionRequest,
+    },
+};
+use candid::Principal;
+use evm_rpc_client::eth_types::Address;
+use icrc_ledger_types::icrc1::account::Account;
+use minicbor::{Decode, Encode};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Replayable part of the minter [`State`], written to stable memory when the oldest events
+/// are archived, see `storage::archive_events`. The state is then recomputed by applying the
+/// retained events on top of the snapshot instead of replaying the log from the `Init` event.
+///
+/// Fields that are not derived from events, such as caches (`ecdsa_public_key`, price
+/// estimates) and locks (`active_tasks`, `pending_withdrawal_principals`), are not part of
+/// the snapshot and are reset to their initial value, as after replaying the full log.
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct StateSnapshot {
+    #[n(0)]
+    pub evm_network: EvmNetwork,
+    #[n(1)]
+    pub ecdsa_key_name: String,
+    #[cbor(n(2), with = "crate::cbor::principal")]
+    pub native_ledger_id: Principal,
+    #[cbor(n(3), with = "crate::cbor::principal")]
+    pub native_index_id: Principal,
+    #[n(4)]
+    pub native_symbol: ERC20TokenSymbol,
+    #[n(5)]
+    pub helper_contract_addresses: Option<Vec<Address>>,
+    #[cbor(n(6), with = "crate::cbor::principal")]
+    pub evm_canister_id: Principal,
+    #[n(7)]
+    pub native_ledger_transfer_fee: Wei,
+    #[n(8)]
+    pub native_minimum_withdrawal_amount: Wei,
+    #[n(9)]
+    pub confirmation_policy: CandidConfirmationPolicy,
+    #[n(10)]
+    pub first_scraped_block_number: BlockNumber,
+    #[n(11)]
+    pub last_scraped_block_number: BlockNumber,
+    #[n(12)]
+    pub events_to_mint: Vec<(EventSource, ReceivedContractEvent)>,
+    #[n(13)]
+    pub events_to_release: Vec<(EventSource, ReceivedContractEvent)>,
+    #[n(14)]
+    pub minted_events: Vec<(EventSource, MintedEvent)>,
+    #[n(15)]
+    pub released_events: Vec<(EventSource, ReleasedEvent)>,
+    #[n(16)]
+    pub invalid_events: Vec<(EventSource, InvalidEventReason)>,
+    #[n(17)]
+    pub quarantined_releases: Vec<(EventSource, ReceivedContractEvent)>,
+    #[n(18)]
+    pub withdrawal_transactions: WithdrawalTransactionsSnapshot,
+    #[n(19)]
+    pub skipped_blocks: BTreeSet<BlockNumber>,
+    #[n(20)]
+    pub scraping_gaps: ScrapingGaps,
+    #[n(21)]
+    pub native_balance: NativeBalance,
+    #[n(22)]
+    pub erc20_balances: Erc20Balances,
+    #[n(23)]
+    pub icrc_balances: IcrcBalances,
+    #[n(25)]
+    pub last_provider_disagreements: BTreeMap<String, u64>,
+    #[n(26)]
+    pub withdrawal_native_fee: Option<Wei>,
+    #[cbor(n(27), with = "crate::cbor::principal::option")]
+    pub ledger_suite_manager_id: Option<Principal>,
+    #[n(28)]
+    pub erc20_tokens: Vec<(CborPrincipal, Address, ERC20TokenSymbol)>,
+    #[n(29)]
+    pub wrapped_icrc_tokens: Vec<(CborPrincipal, Address, Option<IcrcValue>)>,
+    #[n(30)]
+    pub min_max_priority_fee_per_gas: WeiPerGas,
+    #[n(31)]
+    pub gas_fee_config: Option<GasFeeConfig>,
+    #[cbor(n(32), with = "crate::cbor::principal::option")]
+    pub dex_canister_id: Option<Principal>,
+    #[n(33)]
+    pub swap_events_to_mint_to_appic_dex: Vec<(EventSource, ReceivedContractEvent)>,
+    #[n(34)]
+    pub swap_events_to_be_notified: Vec<(EventSource, MintedToDex)>,
+    #[n(35)]
+    pub notified_swap_events: Vec<(EventSource, NotifiedToAppiDex)>,
+    #[n(36)]
+    pub twin_usdc_info: Option<TwinUSDCInfo>,
+    #[n(37)]
+    pub swap_contract_address: Option<Address>,
+    #[n(38)]
+    pub canister_signing_fee_twin_usdc_amount: Option<Erc20Value>,
+    // n(39) was `is_swapping_active`, now `FeatureFlag::SwapActive` in `feature_flags`
+    #[n(40)]
+    pub gas_tank: GasTank,
+    #[n(41)]
+    pub next_swap_ledger_burn_index: Option<u64>,
+    #[n(42)]
+    pub quarantined_dex_orders: BTreeMap<String, DexOrderArgs>,
+    #[n(43)]
+    pub rate_limited_providers: BTreeMap<Provider, u64>,
+    #[n(44)]
+    pub non_standard_erc20_tokens: BTreeSet<Address>,
+    #[n(45)]
+    pub token_volumes: BTreeMap<Address, TokenVolume>,
+    #[n(46)]
+    pub earliest_unpruned_block: BlockNumber,
+    #[n(47)]
+    pub erc20_original_symbols: BTreeMap<Address, ERC20TokenSymbol>,
+    #[n(48)]
+    pub native_ledger_metadata: Option<NativeLedgerMetadata>,
+    #[n(49)]
+    pub onchain_approvals: BTreeMap<(Address, Address), OnchainApproval>,
+    #[n(50)]
+    pub gas_limits: GasLimits,
+    #[n(51)]
+    pub feature_flags: FeatureFlags,
+    #[cbor(n(52), with = "crate::cbor::account::option")]
+    pub fee_collector: Option<Account>,
+    #[cbor(n(53), with = "crate::cbor::principal::btree_map")]
+    pub collected_fees: BTreeMap<Principal, IcrcValue>,
+    #[n(55)]
+    pub daily_stats: DailyStats,
+    #[n(56)]
+    pub refund_deadline_window_secs: Option<u64>,
+    #[n(57)]
+    pub wrapped_icrc_deployments: BTreeMap<Address, WrappedIcrcDeployment>,
+    #[n(58)]
+    pub fee_cap_override: Option<FeeCap>,
+    #[n(59)]
+    pub last_fee_cap_alert: Option<u64>,
+    #[n(60)]
+    pub removed_log_events: BTreeSet<EventSource>,
+    #[cbor(n(61), with = "crate::cbor::principal::btree_map")]
+    pub pending_fee_withdrawals: BTreeMap<Principal, PendingFeeWithdrawal>,
+}
+
+/// Snapshot of [`WithdrawalTransactions`], where maps keyed by ledger burn indices
+/// are flattened into lists of entries.
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct WithdrawalTransactionsSnapshot {
+    #[n(0)]
+    pub pending_withdrawal_requests: Vec<WithdrawalRequest>,
+    #[n(1)]
+    pub processed_withdrawal_requests: Vec<(u64, WithdrawalRequest)>,
+    #[n(2)]
+    pub created_tx: Vec<(TransactionNonce, u64, TransactionRequest)>,
+    #[n(3)]
+    pub sent_tx: Vec<(TransactionNonce, u64, Vec<SignedTransactionRequest>)>,
+    #[n(4)]
+    pub finalized_tx: Vec<(TransactionNonce, u64, FinalizedEip1559Transaction)>,
+    #[n(5)]
+    pub next_nonce: TransactionNonce,
+    #[n(6)]
+    pub maybe_reimburse: Vec<u64>,
+    #[n(7)]
+    pub reimbursement_requests: Vec<(ReimbursementIndex, ReimbursementRequest)>,
+    #[n(8)]
+    pub reimbursed: Vec<(ReimbursementIndex, Option<Reimbursed>)>,
+    #[n(9)]
+    pub failed_swap_requests: BTreeMap<String, ExecuteSwapRequest>,
+    #[n(10)]
+    pub quarantined_swap_requests: BTreeMap<String, ExecuteSwapRequest>,
+    #[n(11)]
+    pub swap_status_history: BTreeMap<String, Vec<(u64, SwapStatus)>>,
+    #[n(12)]
+    pub parked: Vec<(u64, Address)>,
+    #[n(13)]
+    pub erc20_burn_indexes: Vec<(CborPrincipal, u64, u64)>,
+    #[n(14)]
+    pub finalized_withdrawals: Vec<(CborPrincipal, u64, u64)>,
+    #[n(15)]
+    pub realized_swap_amounts_out: Vec<(u64, Erc20Value)>,
+    #[n(16)]
+    pub broadcast_attempts: Vec<(u64, BroadcastAttempt)>,
+}
+
+impl StateSnapshot {
+    pub fn encode_to_vec(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        minicbor::encode(self, &mut buf).expect("state snapshot encoding should always succeed");
+        buf
+    }
+
+    pub fn decode_from_slice(bytes: &[u8]) -> Result<Self, minicbor::decode::Error> {
+        minicbor::decode(bytes)
+    }
+}
+
+fn entries<K: Clone, V: Clone>(map: &BTreeMap<K, V>) -> Vec<(K, V)> {
+    map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+}
+
+fn multi_key_entries<V: Clone>(
+    map: &MultiKeyMap<TransactionNonce, LedgerBurnIndex, V>,
+) -> Vec<(TransactionNonce, u64, V)> {
+    map.iter()
+        .map(|(nonce, index, value)| (*nonce, index.get(), value.clone()))
+        .collect()
+}
+
+fn multi_key_map<V>(
+    entries: Vec<(TransactionNonce, u64, V)>,
+) -> MultiKeyMap<TransactionNonce, LedgerBurnIndex, V> {
+    let mut map = MultiKeyMap::new();
+    for (nonce, index, value) in entries {
+        if map
+            .try_insert(nonce, LedgerBurnIndex::new(index), value)
+            .is_err()
+        {
+            panic!(
+                "BUG: duplicate transaction with nonce {nonce} or burn index {index} in snapshot"
+            );
+        }
+    }
+    map
+}
+
+fn dedup_entries<V: Clone>(
+    map: &DedupMultiKeyMap<Principal, Address, V>,
+) -> Vec<(CborPrincipal, Address, V)> {
+    map.iter()
+        .map(|(ledger_id, address, value)| (CborPrincipal(*ledger_id), *address, value.clone()))
+        .collect()
+}
+
+fn dedup_map<V>(
+    entries: Vec<(CborPrincipal, Address, V)>,
+) -> DedupMultiKeyMap<Principal, Address, V> {
+    let mut map = DedupMultiKeyMap::new();
+    for (ledger_id, address, value) in entries {
+        if map.try_insert(ledger_id.0, address, value).is_err() {
+            panic!(
+                "BUG: duplicate token {} or {address} in snapshot",
+                ledger_id.0
+            );
+        }
+    }
+    map
+}
+
+impl From<&WithdrawalTransactions> for WithdrawalTransactionsSnapshot {
+    fn from(transactions: &WithdrawalTransactions) -> Self {
+        Self {
+            pending_withdrawal_requests: transactions
+                .pending_withdrawal_requests
+                .iter()
+                .cloned()
+                .collect(),
+            processed_withdrawal_requests: transactions
+                .processed_withdrawal_requests
+                .iter()
+                .map(|(index, request)| (index.get(), request.clone()))
+                .collect(),
+            created_tx: multi_key_entries(&transactions.created_tx),
+            sent_tx: multi_key_entries(&transactions.sent_tx),
+            finalized_tx: multi_key_entries(&transactions.finalized_tx),
+            next_nonce: transactions.next_nonce,
+            maybe_reimburse: transactions
+                .maybe_reimburse
+                .iter()
+                .map(|index| index.get())
+                .collect(),
+            reimbursement_requests: entries(&transactions.reimbursement_requests),
+            reimbursed: transactions
+                .reimbursed
+                .iter()
+                .map(|(index, result)| {
+                    let reimbursed = match result {
+                        Ok(reimbursed) => Some(reimbursed.clone()),
+                        Err(ReimbursedError::Quarantined) => None,
+                    };
+                    (index.clone(), reimbursed)
+                })
+                .collect(),
+            failed_swap_requests: transactions.failed_swap_requests.clone(),
+            quarantined_swap_requests: transactions.quarantined_swap_requests.clone(),
+            swap_status_history: transactions.swap_status_history.clone(),
+            parked: transactions
+                .parked
+                .iter()
+                .map(|(index, destination)| (index.get(), *destination))
+                .collect(),
+            realized_swap_amounts_out: transactions
+                .realized_swap_amounts_out
+                .iter()
+                .map(|(index, amount_out)| (index.get(), *amount_out))
+                .collect(),
+            broadcast_attempts: transactions
+                .broadcast_attempts
+                .iter()
+                .map(|(index, attempt)| (index.get(), attempt.clone()))
+                .collect(),
+            erc20_burn_indexes: transactions
+                .erc20_burn_indexes
+                .iter()
+                .map(|((ledger_id, erc20_index), native_index)| {
+                    (
+                        CborPrincipal(*ledger_id),
+                        erc20_index.get(),
+                        native_index.get(),
+                    )
+                })
+                .collect(),
+            finalized_withdrawals: transactions
+                .finalized_withdrawals
+                .iter()
+                .flat_map(|(ledger_id, entries)| {
+                    entries.iter().map(|(timestamp, native_index)| {
+                        (CborPrincipal(*ledger_id), *timestamp, native_index.get())
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<WithdrawalTransactionsSnapshot> for WithdrawalTransactions {
+    fn from(snapshot: WithdrawalTransactionsSnapshot) -> Self {
+        let mut transactions = Self {
+            pending_withdrawal_requests: snapshot.pending_withdrawal_requests.into(),
+            processed_withdrawal_requests: snapshot
+                .processed_withdrawal_requests
+                .into_iter()
+                .map(|(index, request)| (LedgerBurnIndex::new(index), request))
+                .collect(),
+            created_tx: multi_key_map(snapshot.created_tx),
+            sent_tx: multi_key_map(snapshot.sent_tx),
+            finalized_tx: multi_key_map(snapshot.finalized_tx),
+            next_nonce: snapshot.next_nonce,
+            maybe_reimburse: snapshot
+                .maybe_reimburse
+                .into_iter()
+                .map(LedgerBurnIndex::new)
+                .collect(),
+            reimbursement_requests: snapshot.reimbursement_requests.into_iter().collect(),
+            reimbursed: snapshot
+                .reimbursed
+                .into_iter()
+                .map(|(index, reimbursed)| (index, reimbursed.ok_or(ReimbursedError::Quarantined)))
+                .collect(),
+            failed_swap_requests: snapshot.failed_swap_requests,
+            quarantined_swap_requests: snapshot.quarantined_swap_requests,
+            swap_status_history: snapshot.swap_status_history,
+            swap_burn_indexes: Default::default(),
+            parked: snapshot
+                .parked
+                .into_iter()
+                .map(|(index, destination)| (LedgerBurnIndex::new(index), destination))
+                .collect(),
+            realized_swap_amounts_out: snapshot
+                .realized_swap_amounts_out
+                .into_iter()
+                .map(|(index, amount_out)| (LedgerBurnIndex::new(index), amount_out))
+                .collect(),
+            broadcast_attempts: snapshot
+                .broadcast_attempts
+                .into_iter()
+                .map(|(index, attempt)| (LedgerBurnIndex::new(index), attempt))
+                .collect(),
+            erc20_burn_indexes: snapshot
+                .erc20_burn_indexes
+                .into_iter()
+                .map(|(ledger_id, erc20_index, native_index)| {
+                    (
+                        (ledger_id.0, LedgerBurnIndex::new(erc20_index)),
+                        LedgerBurnIndex::new(native_index),
+                    )
+                })
+                .collect(),
+            finalized_withdrawals: snapshot.finalized_withdrawals.into_iter().fold(
+                BTreeMap::new(),
+                |mut map: BTreeMap<_, BTreeSet<_>>, (ledger_id, timestamp, native_index)| {
+                    map.entry(ledger_id.0)
+                        .or_default()
+                        .insert((timestamp, LedgerBurnIndex::new(native_index)));
+                    map
+                },
+            ),
+        };
+        // derived from the withdrawal requests, hence not part of the snapshot
+        transactions.reindex_swap_requests();
+        transactions
+    }
+}
+
+impl From<&State> for StateSnapshot {
+    fn from(state: &State) -> Self {
+        Self {
+            evm_network: state.evm_network,
+            ecdsa_key_name: state.ecdsa_key_name.clone(),
+            native_ledger_id: state.native_ledger_id,
+            native_index_id: state.native_index_id,
+            native_symbol: state.native_symbol.clone(),
+            helper_contract_addresses: state.helper_contract_addresses.clone(),
+            evm_canister_id: state.evm_canister_id,
+            native_ledger_transfer_fee: state.native_ledger_transfer_fee,
+            native_minimum_withdrawal_amount: state.native_minimum_withdrawal_amount,
+            confirmation_policy: state.confirmation_policy.into(),
+            first_scraped_block_number: state.first_scraped_block_number,
+            last_scraped_block_number: state.last_scraped_block_number,
+            events_to_mint: entries(&state.events_to_mint),
+            events_to_release: entries(&state.events_to_release),
+            minted_events: entries(&state.minted_events),
+            released_events: entries(&state.released_events),
+            invalid_events: entries(&state.invalid_events),
+            removed_log_events: state.removed_log_events.clone(),
+            quarantined_releases: entries(&state.quarantined_releases),
+            withdrawal_transactions: (&state.withdrawal_transactions).into(),
+            skipped_blocks: state.skipped_blocks.clone(),
+            scraping_gaps: state.scraping_gaps.clone(),
+            native_balance: state.native_balance.clone(),
+            erc20_balances: state.erc20_balances.clone(),
+            icrc_balances: state.icrc_balances.clone(),
+            last_provider_disagreements: state.last_provider_disagreements.clone(),
+            withdrawal_native_fee: state.withdrawal_native_fee,
+            ledger_suite_manager_id: state.ledger_suite_manager_id,
+            erc20_tokens: dedup_entries(&state.erc20_tokens),
+            wrapped_icrc_tokens: dedup_entries(&state.wrapped_icrc_tokens),
+            wrapped_icrc_deployments: state.wrapped_icrc_deployments.clone(),
+            min_max_priority_fee_per_gas: state.min_max_priority_fee_per_gas,
+            gas_fee_config: state.gas_fee_config.clone(),
+            dex_canister_id: state.dex_canister_id,
+            swap_events_to_mint_to_appic_dex: entries(&state.swap_events_to_mint_to_appic_dex),
+            swap_events_to_be_notified: entries(&state.swap_events_to_be_notified),
+            notified_swap_events: entries(&state.notified_swap_events),
+            twin_usdc_info: state.twin_usdc_info.clone(),
+            swap_contract_address: state.swap_contract_address,
+            canister_signing_fee_twin_usdc_amount: state.canister_signing_fee_twin_usdc_amount,
+            gas_tank: state.gas_tank.clone(),
+            next_swap_ledger_burn_index: state.next_swap_ledger_burn_index.map(|index| index.get()),
+            quarantined_dex_orders: state.quarantined_dex_orders.clone(),
+            rate_limited_providers: state.rate_limited_providers.clone(),
+            non_standard_erc20_tokens: state.non_standard_erc20_tokens.clone(),
+            token_volumes: state.token_volumes.clone(),
+            daily_stats: state.daily_stats.clone(),
+            earliest_unpruned_block: state.earliest_unpruned_block,
+            erc20_original_symbols: state.erc20_original_symbols.clone(),
+            native_ledger_metadata: state.native_ledger_metadata.clone(),
+            onchain_approvals: state.onchain_approvals.clone(),
+            gas_limits: state.gas_limits.clone(),
+            feature_flags: state.feature_flags.clone(),
+            fee_collector: state.fee_collector,
+            collected_fees: state.collected_fees.clone(),
+            pending_fee_withdrawals: state.pending_fee_withdrawals.clone(),
+            refund_deadline_window_secs: state.refund_deadline_window_secs,
+            fee_cap_override: state.fee_cap_override,
+            last_fee_cap_alert: state.last_fee_cap_alert,
+        }
+    }
+}
+
+impl From<StateSnapshot> for State {
+    fn from(snapshot: StateSnapshot) -> Self {
+        Self {
+            evm_network: snapshot.evm_network,
+            ecdsa_key_name: snapshot.ecdsa_key_name,
+            native_ledger_id: snapshot.native_ledger_id,
+            native_index_id: snapshot.native_index_id,
+            native_symbol: snapshot.native_symbol,
+            helper_contract_addresses: snapshot.helper_contract_addresses,
+            evm_canister_id: snapshot.evm_canister_id,
+            ecdsa_public_key: None,
+            native_ledger_transfer_fee: snapshot.native_ledger_transfer_fee,
+            native_minimum_withdrawal_amount: snapshot.native_minimum_withdrawal_amount,
+            confirmation_policy: snapshot.confirmation_policy.into(),
+            first_scraped_block_number: snapshot.first_scraped_block_number,
+            last_scraped_block_number: snapshot.last_scraped_block_number,
+            last_observed_block_number: None,
+            last_observed_block_time: None,
+            last_observed_block_timestamp: None,
+            last_scraped_block_timestamp: None,
+            last_log_scraping_request_time: 0,
+            lastest_requested_block_to_scrape: None,
+            events_to_mint: snapshot.events_to_mint.into_iter().collect(),
+            events_to_release: snapshot.events_to_release.into_iter().collect(),
+            minted_events: snapshot.minted_events.into_iter().collect(),
+            released_events: snapshot.released_events.into_iter().collect(),
+            invalid_events: snapshot.invalid_events.into_iter().collect(),
+            removed_log_events: snapshot.removed_log_events,
+            quarantined_releases: snapshot.quarantined_releases.into_iter().collect(),
+            withdrawal_transactions: snapshot.withdrawal_transactions.into(),
+            skipped_blocks: snapshot.skipped_blocks,
+            scraping_gaps: snapshot.scraping_gaps,
+            native_balance: snapshot.native_balance,
+            erc20_balances: snapshot.erc20_balances,
+            icrc_balances: snapshot.icrc_balances,
+            pending_withdrawal_principals: Default::default(),
+            last_manual_deposit_checks: Default::default(),
+            last_provider_disagreements: snapshot.last_provider_disagreements,
+            active_tasks: Default::default(),
+            mints_in_flight: Default::default(),
+            reorged_mints_in_flight: Default::default(),
+            contract_destinations: Default::default(),
+            last_transaction_price_estimate: None,
+            last_transaction_price_estimate_block: None,
+            current_l1_fee: None,
+            total_ledger_call_cycles_spent: 0,
+            ecdsa_signing_calls: 0,
+            ecdsa_signing_cycles_spent: 0,
+            last_native_token_usd_price_estimate: None,
+            withdrawal_native_fee: snapshot.withdrawal_native_fee,
+            ledger_suite_manager_id: snapshot.ledger_suite_manager_id,
+            known_erc20_contracts: snapshot
+                .erc20_tokens
+                .iter()
+                .map(|(_ledger_id, address, _symbol)| *address)
+                .collect(),
+            erc20_tokens: dedup_map(snapshot.erc20_tokens),
+            wrapped_icrc_tokens: dedup_map(snapshot.wrapped_icrc_tokens),
+            wrapped_icrc_deployments: snapshot.wrapped_icrc_deployments,
+            wrapped_icrc_metadata: Default::default(),
+            min_max_priority_fee_per_gas: snapshot.min_max_priority_fee_per_gas,
+            gas_fee_config: snapshot.gas_fee_config,
+            gas_limits: snapshot.gas_limits,
+            dex_canister_id: snapshot.dex_canister_id,
+            swap_events_to_mint_to_appic_dex: snapshot
+                .swap_events_to_mint_to_appic_dex
+                .into_iter()
+                .collect(),
+            swap_events_to_be_notified: snapshot.swap_events_to_be_notified.into_iter().collect(),
+            notified_swap_events: snapshot.notified_swap_events.into_iter().collect(),
+            twin_usdc_info: snapshot.twin_usdc_info,
+            swap_contract_address: snapshot.swap_contract_address,
+            canister_signing_fee_twin_usdc_amount: snapshot.canister_signing_fee_twin_usdc_amount,
+            feature_flags: snapshot.feature_flags,
+            fee_collector: snapshot.fee_collector,
+            collected_fees: snapshot.collected_fees,
+            pending_fee_withdrawals: snapshot.pending_fee_withdrawals,
+            refund_deadline_window_secs: snapshot.refund_deadline_window_secs,
+            fee_cap_override: snapshot.fee_cap_override,
+            last_fee_cap_alert: snapshot.last_fee_cap_alert,
+            gas_tank: snapshot.gas_tank,
+            next_swap_ledger_burn_index: snapshot
+                .next_swap_ledger_burn_index
+                .map(LedgerBurnIndex::new),
+            quarantined_dex_orders: snapshot.quarantined_dex_orders,
+            rate_limited_providers: snapshot.rate_limited_providers,
+            non_standard_erc20_tokens: snapshot.non_standard_erc20_tokens,
+            token_volumes: snapshot.token_volumes,
+            daily_stats: snapshot.daily_stats,
+            earliest_unpruned_block: snapshot.earliest_unpruned_block,
+            erc20_original_symbols: snapshot.erc20_original_symbols,
+            native_ledger_metadata: snapshot.native_ledger_metadata,
+            onchain_approvals: snapshot.onchain_approvals,
+        }
+    }
+}
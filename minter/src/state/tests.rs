@@ -1,24 +1,29 @@
-use crate::candid_types::CandidBlockTag;
+use crate::candid_types::{AddOrRemove, CandidBlockTag, CandidConfirmationPolicy, ListKind};
 use crate::contract_logs::types::{ReceivedErc20Event, ReceivedNativeEvent};
 use crate::contract_logs::{EventSource, LedgerSubaccount};
 use crate::erc20::ERC20TokenSymbol;
-use crate::evm_config::EvmNetwork;
+use crate::evm_config::{ConfirmationPolicy, EvmNetwork, GasLimitKind};
 use crate::lifecycle::InitArg;
-use crate::lifecycle::UpgradeArg;
+use crate::lifecycle::{GasFeeConfigArg, GasLimitArg, UpgradeArg};
 use crate::map::DedupMultiKeyMap;
 use crate::numeric::{
     wei_from_milli_ether, BlockNumber, Erc20TokenAmount, Erc20Value, GasAmount, LedgerBurnIndex,
     LedgerMintIndex, LogIndex, TransactionNonce, Wei, WeiPerGas,
 };
+use crate::rpc_client::providers::Provider;
 use crate::rpc_declarations::BlockTag;
 use crate::rpc_declarations::{TransactionReceipt, TransactionStatus};
 use crate::state::audit::apply_state_transition;
 use crate::state::balances::GasTank;
 use crate::state::event::{Event, EventType};
-use crate::state::transactions::{Erc20WithdrawalRequest, ReimbursementIndex};
-use crate::state::{Erc20Balances, State};
+use crate::state::feature_flags::FeatureFlag;
+use crate::state::transactions::{
+    BroadcastOutcome, Erc20Approve, Erc20WithdrawalRequest, PendingReimbursementRequest,
+    ReimbursementIndex,
+};
+use crate::state::{Erc20Balances, NativeLedgerMetadata, State};
 use crate::test_fixtures::arb::{arb_address, arb_checked_amount_of, arb_hash};
-use crate::tx::gas_fees::GasFeeEstimate;
+use crate::tx::gas_fees::{FeeCap, GasFeeEstimate, DEFAULT_BASE_FEE_MULTIPLIER_PERCENT};
 use crate::tx::{
     AccessList, AccessListItem, Eip1559Signature, Eip1559TransactionRequest, ResubmissionStrategy,
     SignedEip1559TransactionRequest, StorageKey,
@@ -27,13 +32,18 @@ use candid::{Nat, Principal};
 use ethnum::u256;
 use evm_rpc_client::eth_types::Address;
 use ic_management_canister_types::EcdsaPublicKeyResult;
+use icrc_ledger_types::icrc1::account::Account;
 use proptest::array::uniform32;
 use proptest::collection::vec as pvec;
 use proptest::prelude::*;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 pub fn initial_state() -> State {
-    State::try_from(InitArg {
+    State::try_from(initial_init_arg()).expect("init args should be valid")
+}
+
+fn initial_init_arg() -> InitArg {
+    InitArg {
         evm_network: Default::default(),
         ecdsa_key_name: "test_key_1".to_string(),
         helper_contract_address: None,
@@ -52,8 +62,8 @@ pub fn initial_state() -> State {
             .expect("BUG: invalid principal"),
         deposit_native_fee: wei_from_milli_ether(1).into(),
         withdrawal_native_fee: 5_000_000_u128.into(),
-    })
-    .expect("init args should be valid")
+        fee_collector: None,
+    }
 }
 
 mod mint_transaction {
@@ -217,6 +227,91 @@ mod mint_transaction {
         assert!(state.invalid_events.contains_key(&event.source()));
     }
 
+    #[test]
+    fn should_drop_reorged_deposit_and_revert_balance() {
+        use crate::state::audit::{apply_state_transition, EventType};
+
+        let mut state = initial_state();
+        let balance_before = state.native_balance.clone();
+        let event = received_deposit_event();
+        apply_state_transition(&mut state, &EventType::AcceptedDeposit(event.clone()));
+        assert!(state.is_pending_event(&event.source()));
+
+        apply_state_transition(
+            &mut state,
+            &EventType::DepositReorged {
+                event_source: event.source(),
+            },
+        );
+
+        assert!(!state.is_pending_event(&event.source()));
+        assert!(state.events_to_mint.is_empty());
+        assert!(state.minted_events.is_empty());
+        assert_eq!(state.native_balance, balance_before);
+    }
+
+    #[test]
+    #[should_panic = "unknown event"]
+    fn should_not_mint_reorged_deposit() {
+        use crate::state::audit::{apply_state_transition, EventType};
+
+        let mut state = initial_state();
+        let event = received_deposit_event();
+        apply_state_transition(&mut state, &EventType::AcceptedDeposit(event.clone()));
+        apply_state_transition(
+            &mut state,
+            &EventType::DepositReorged {
+                event_source: event.source(),
+            },
+        );
+
+        state.record_successful_mint(event.source(), "icETH", LedgerMintIndex::new(1), None);
+    }
+
+    #[test]
+    fn should_only_record_removal_on_removed_log() {
+        use crate::deposit::removed_log_event;
+        use crate::state::audit::{apply_state_transition, EventType};
+
+        let mut state = initial_state();
+        let state_before = state.clone();
+        let source = received_deposit_event().source();
+        let payload = removed_log_event(&state, source).expect("removal should be recorded");
+        apply_state_transition(&mut state, &payload);
+
+        assert_eq!(
+            state,
+            State {
+                removed_log_events: BTreeSet::from([source]),
+                ..state_before
+            }
+        );
+        assert_eq!(removed_log_event(&state, source), None);
+    }
+
+    #[test]
+    fn should_not_panic_on_reorg_of_unknown_event() {
+        use crate::state::audit::{apply_state_transition, EventType};
+
+        let mut state = initial_state();
+        let state_before = state.clone();
+        let source = received_deposit_event().source();
+        apply_state_transition(
+            &mut state,
+            &EventType::DepositReorged {
+                event_source: source,
+            },
+        );
+
+        assert_eq!(
+            state,
+            State {
+                removed_log_events: BTreeSet::from([source]),
+                ..state_before
+            }
+        );
+    }
+
     #[test]
     fn should_have_readable_eth_debug_representation() {
         let expected = "ReceivedNativeEvent { \
@@ -241,13 +336,14 @@ mod mint_transaction {
           value: 5_000_000, \
           principal: hkroy-sm7vs-yyjs7-ekppe-qqnwx-hm4zf-n7ybs-titsi-k6e3k-ucuiu-uqe, \
           contract_address: 0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238, \
-          subaccount: None \
+          subaccount: None, \
+          original_owner: None \
         }";
         assert_eq!(format!("{:?}", received_erc20_event()), expected);
     }
 }
 
-fn received_deposit_event() -> ReceivedNativeEvent {
+pub fn received_deposit_event() -> ReceivedNativeEvent {
     ReceivedNativeEvent {
         transaction_hash: "0xf1ac37d920fa57d9caeebc7136fea591191250309ffca95ae0e8a7739de89cc2"
             .parse()
@@ -285,19 +381,21 @@ fn received_erc20_event() -> ReceivedErc20Event {
             .parse()
             .unwrap(),
         subaccount: None,
+        original_owner: None,
     }
 }
 
 mod upgrade {
-    use crate::evm_config::EvmNetwork;
-    use crate::lifecycle::UpgradeArg;
-    use crate::numeric::{TransactionNonce, Wei};
+    use crate::evm_config::{ConfirmationPolicy, EvmNetwork};
+    use crate::lifecycle::{validate_init_arg, InitArg, UpgradeArg};
+    use crate::numeric::{BlockNumber, TransactionNonce, Wei};
     use crate::rpc_declarations::BlockTag;
-    use crate::state::tests::initial_state;
-    use crate::state::InvalidStateError;
+    use crate::state::tests::{initial_init_arg, initial_state};
+    use crate::state::{InvalidStateError, State, MAX_HELPER_CONTRACT_ADDRESSES};
     use assert_matches::assert_matches;
     use candid::Nat;
     use evm_rpc_client::eth_types::Address;
+    use ic_management_canister_types::EcdsaPublicKeyResult;
     use num_bigint::BigUint;
     use std::str::FromStr;
 
@@ -394,12 +492,203 @@ mod upgrade {
             )
             .unwrap()])
         );
-        assert_eq!(state.block_height, BlockTag::Safe);
+        assert_eq!(
+            state.confirmation_policy,
+            ConfirmationPolicy::BlockTag(BlockTag::Safe)
+        );
+    }
+
+    const HELPER_CONTRACT: &str = "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34";
+
+    fn state_with_helper_contract() -> crate::state::State {
+        let mut state = initial_state();
+        state
+            .upgrade(UpgradeArg {
+                helper_contract_address: Some(HELPER_CONTRACT.to_string()),
+                ..Default::default()
+            })
+            .expect("valid upgrade args");
+        state
+    }
+
+    fn helper_upgrade_arg(address: &str) -> UpgradeArg {
+        UpgradeArg {
+            helper_contract_address: Some(address.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_reject_duplicate_helper_contract_address() {
+        let state = state_with_helper_contract();
+
+        for address in [
+            HELPER_CONTRACT.to_string(),
+            HELPER_CONTRACT.to_lowercase(),
+            format!("0x{}", HELPER_CONTRACT[2..].to_uppercase()),
+        ] {
+            assert_matches!(
+                state.validate_upgrade_arg(&helper_upgrade_arg(&address)),
+                Err(InvalidStateError::InvalidHelperContractAddress(e)) if e.contains("already a helper contract")
+            );
+        }
+    }
+
+    #[test]
+    fn should_reject_swap_contract_as_helper_contract() {
+        let mut state = initial_state();
+        let swap_contract = "0x1c7d4b196cb0c7b01d743fbc6116a902379c7238";
+        state.swap_contract_address = Some(Address::from_str(swap_contract).unwrap());
+
+        assert_matches!(
+            state.validate_upgrade_arg(&helper_upgrade_arg(swap_contract)),
+            Err(InvalidStateError::InvalidHelperContractAddress(e)) if e.contains("swap contract")
+        );
+    }
+
+    #[test]
+    fn should_reject_minter_address_as_helper_contract() {
+        let mut state = initial_state();
+        // Compressed public key of the secret key 1.
+        state.ecdsa_public_key = Some(EcdsaPublicKeyResult {
+            public_key: hex::decode(
+                "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            )
+            .unwrap(),
+            chain_code: vec![2; 32],
+        });
+        let minter_address = "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf";
+        assert_eq!(
+            state.minter_address(),
+            Some(Address::from_str(minter_address).unwrap())
+        );
+
+        assert_matches!(
+            state.validate_upgrade_arg(&helper_upgrade_arg(minter_address)),
+            Err(InvalidStateError::InvalidHelperContractAddress(e)) if e.contains("minter address")
+        );
+    }
+
+    #[test]
+    fn should_reject_too_many_helper_contracts() {
+        let mut state = initial_state();
+        let addresses: Vec<_> = (1..=MAX_HELPER_CONTRACT_ADDRESSES as u8)
+            .map(|i| Address::new([i; 20]))
+            .collect();
+        state.helper_contract_addresses = Some(addresses);
+
+        assert_matches!(
+            state.validate_upgrade_arg(&helper_upgrade_arg(HELPER_CONTRACT)),
+            Err(InvalidStateError::InvalidHelperContractAddress(e)) if e.contains("cannot have more than")
+        );
+    }
+
+    #[test]
+    fn should_accept_new_helper_contract_address() {
+        let state = state_with_helper_contract();
+
+        assert_eq!(
+            state.validate_upgrade_arg(&helper_upgrade_arg(
+                "0x1c7d4b196cb0c7b01d743fbc6116a902379c7238"
+            )),
+            Ok(())
+        );
+        assert_eq!(state.validate_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn should_not_duplicate_helper_contract_when_replaying_upgrade() {
+        let mut state = state_with_helper_contract();
+
+        state
+            .upgrade(helper_upgrade_arg(&HELPER_CONTRACT.to_lowercase()))
+            .expect("valid upgrade args");
+
+        assert_eq!(
+            state.helper_contract_addresses,
+            Some(vec![Address::from_str(HELPER_CONTRACT).unwrap()])
+        );
+        assert_eq!(state.validate_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn should_replay_helper_contract_address_with_invalid_checksum() {
+        // flipped the case of the first letter of HELPER_CONTRACT
+        let miscased = "0xB44B5e756A894775FC32EDdf3314Bb1B1944dC34";
+        assert!(Address::from_str(miscased).is_err());
+
+        let state = initial_state();
+        assert_matches!(
+            state.validate_upgrade_arg(&helper_upgrade_arg(miscased)),
+            Err(InvalidStateError::InvalidHelperContractAddress(e)) if e.contains("checksum")
+        );
+        let mut state = initial_state();
+        state
+            .upgrade(helper_upgrade_arg(miscased))
+            .expect("recorded upgrade args should be replayable");
+        assert_eq!(
+            state.helper_contract_addresses,
+            Some(vec![Address::from_str(HELPER_CONTRACT).unwrap()])
+        );
+
+        let init_arg = InitArg {
+            helper_contract_address: Some(miscased.to_string()),
+            ..initial_init_arg()
+        };
+        assert_matches!(
+            validate_init_arg(&init_arg),
+            Err(InvalidStateError::InvalidHelperContractAddress(e)) if e.contains("checksum")
+        );
+        let state = State::try_from(init_arg).expect("recorded init args should be replayable");
+        assert_eq!(
+            state.helper_contract_addresses,
+            Some(vec![Address::from_str(HELPER_CONTRACT).unwrap()])
+        );
+    }
+
+    #[test]
+    fn should_detect_invariant_violations() {
+        let address = Address::from_str(HELPER_CONTRACT).unwrap();
+
+        let mut state = initial_state();
+        state.helper_contract_addresses = Some(vec![address, address]);
+        assert_matches!(state.validate_invariants(), Err(e) if e.contains("duplicate helper contracts"));
+
+        let mut state = initial_state();
+        state.helper_contract_addresses = Some(vec![address]);
+        state.swap_contract_address = Some(address);
+        assert_matches!(state.validate_invariants(), Err(e) if e.contains("is also a helper contract"));
+
+        let mut state = initial_state();
+        state.last_scraped_block_number = BlockNumber::new(11);
+        state.last_observed_block_number = Some(BlockNumber::new(10));
+        assert_matches!(state.validate_invariants(), Err(e) if e.contains("ahead of last observed block"));
+    }
+
+    #[test]
+    fn should_detect_pending_deposits_not_covered_by_balance() {
+        use crate::contract_logs::ReceivedContractEvent;
+        use crate::state::audit::apply_state_transition;
+        use crate::state::tests::{initial_erc20_state, received_erc20_event};
+
+        let mut state = initial_erc20_state();
+        let deposit = received_erc20_event();
+        apply_state_transition(
+            &mut state,
+            &ReceivedContractEvent::from(deposit.clone()).into_event_type(),
+        );
+        assert_eq!(state.validate_invariants(), Ok(()));
+
+        state
+            .erc20_balances
+            .erc20_sub(deposit.erc20_contract_address, deposit.value);
+        assert_matches!(state.validate_invariants(), Err(e) if e.contains("below pending deposits"));
     }
 }
 
-mod erc20 {
+pub mod erc20 {
     pub mod record_add_erc20_token {
+        use crate::candid_types::Erc20TransferKind;
         use crate::erc20::ERC20Token;
         use crate::evm_config::EvmNetwork;
         use crate::state::tests::initial_state;
@@ -431,10 +720,36 @@ mod erc20 {
                     erc20_contract_address: ckerc20.erc20_contract_address,
                     erc20_token_symbol: ckerc20.erc20_token_symbol,
                     erc20_ledger_id: ckerc20.erc20_ledger_id,
+                    transfer_kind: None,
+                    original_symbol: None,
                 }]
             );
         }
 
+        #[test]
+        fn should_record_non_standard_erc20_token() {
+            let mut state = initial_state();
+            state.evm_network = EvmNetwork::Sepolia;
+            let usdt = ERC20Token {
+                transfer_kind: Some(Erc20TransferKind::NonStandard),
+                ..ic_usdt()
+            };
+
+            state.record_add_erc20_token(ic_usdc());
+            state.record_add_erc20_token(usdt.clone());
+
+            assert!(state.is_non_standard_erc20_token(&usdt.erc20_contract_address));
+            assert!(!state.is_non_standard_erc20_token(&ic_usdc().erc20_contract_address));
+            assert_eq!(
+                state.find_erc20_token_by_ledger_id(&usdt.erc20_ledger_id),
+                Some(usdt.clone())
+            );
+            assert_eq!(
+                state.supported_erc20_tokens().collect::<Vec<_>>(),
+                vec![usdt, ic_usdc()]
+            );
+        }
+
         #[test]
         fn should_panic_when_duplicate_ledger_id() {
             let mut state = initial_state();
@@ -494,6 +809,8 @@ mod erc20 {
                     .unwrap(),
                 erc20_token_symbol: "icUSDC".parse().unwrap(),
                 erc20_ledger_id: "mxzaz-hqaaa-aaaar-qaada-cai".parse().unwrap(),
+                transfer_kind: None,
+                original_symbol: None,
             }
         }
 
@@ -505,78 +822,321 @@ mod erc20 {
                     .unwrap(),
                 erc20_token_symbol: "ckUSDT".parse().unwrap(),
                 erc20_ledger_id: "nbsys-saaaa-aaaar-qaaga-cai".parse().unwrap(),
+                transfer_kind: None,
+                original_symbol: None,
             }
         }
     }
-}
-
-fn arb_principal() -> impl Strategy<Value = Principal> {
-    pvec(any::<u8>(), 0..=29).prop_map(|bytes| Principal::from_slice(&bytes))
-}
 
-fn arb_ledger_subaccount() -> impl Strategy<Value = Option<LedgerSubaccount>> {
-    uniform32(any::<u8>()).prop_map(LedgerSubaccount::from_bytes)
-}
+    mod supported_erc20_tokens {
+        use crate::candid_types;
+        use crate::erc20::ERC20Token;
+        use crate::evm_config::EvmNetwork;
+        use crate::state::tests::erc20::record_add_erc20_token::{ic_usdc, ic_usdt};
+        use crate::state::tests::initial_state;
 
-fn arb_u256() -> impl Strategy<Value = u256> {
-    uniform32(any::<u8>()).prop_map(u256::from_be_bytes)
-}
+        fn ic_wbtc() -> ERC20Token {
+            ERC20Token {
+                chain_id: EvmNetwork::Sepolia,
+                erc20_contract_address: "0x2260fac5e5542a773aa44fbcfedf7c193bc2c599"
+                    .parse()
+                    .unwrap(),
+                erc20_token_symbol: "icWBTC".parse().unwrap(),
+                erc20_ledger_id: "ss2fx-dyaaa-aaaar-qacoq-cai".parse().unwrap(),
+                transfer_kind: None,
+                original_symbol: None,
+            }
+        }
 
-fn arb_event_source() -> impl Strategy<Value = EventSource> {
-    (arb_hash(), arb_checked_amount_of()).prop_map(|(transaction_hash, log_index)| EventSource {
-        transaction_hash,
-        log_index,
-    })
-}
+        #[test]
+        fn should_return_tokens_sorted_by_symbol() {
+            let mut state = initial_state();
+            state.evm_network = EvmNetwork::Sepolia;
+            // inserted in reverse alphabetical order
+            for token in [ic_wbtc(), ic_usdc(), ic_usdt()] {
+                state.record_add_erc20_token(token);
+            }
 
-fn arb_block_tag() -> impl Strategy<Value = CandidBlockTag> {
-    prop_oneof![
-        Just(CandidBlockTag::Safe),
-        Just(CandidBlockTag::Latest),
-        Just(CandidBlockTag::Finalized),
-    ]
-}
+            assert_eq!(
+                state.supported_erc20_tokens().collect::<Vec<_>>(),
+                vec![ic_usdt(), ic_usdc(), ic_wbtc()]
+            );
 
-fn arb_nat() -> impl Strategy<Value = Nat> {
-    any::<u128>().prop_map(Nat::from)
-}
+            // as returned by `get_minter_info`
+            let symbols: Vec<_> = state
+                .supported_erc20_tokens()
+                .map(candid_types::Erc20Token::from)
+                .map(|token| token.erc20_token_symbol)
+                .collect();
+            assert_eq!(symbols, vec!["ckUSDT", "icUSDC", "icWBTC"]);
+        }
+    }
 
-fn arb_storage_key() -> impl Strategy<Value = StorageKey> {
-    uniform32(any::<u8>()).prop_map(StorageKey)
-}
+    mod erc20_token_symbol {
+        use crate::candid_types::{AddErc20Token, SymbolCollisionPolicy};
+        use crate::erc20::{ERC20Token, ERC20TokenSymbol};
+        use crate::evm_config::EvmNetwork;
+        use crate::state::audit::{apply_state_transition, EventType};
+        use crate::state::tests::erc20::record_add_erc20_token::{ic_usdc, ic_usdt};
+        use crate::state::tests::initial_state;
+        use candid::Nat;
 
-fn arb_access_list_item() -> impl Strategy<Value = AccessListItem> {
-    (arb_address(), pvec(arb_storage_key(), 0..100)).prop_map(|(address, storage_keys)| {
-        AccessListItem {
-            address,
-            storage_keys,
+        fn add_erc20_token(erc20_token_symbol: &str) -> AddErc20Token {
+            AddErc20Token {
+                chain_id: Nat::from(EvmNetwork::Sepolia.chain_id()),
+                address: "0xdac17f958d2ee523a2206206994597c13d831ec7".to_string(),
+                erc20_token_symbol: erc20_token_symbol.to_string(),
+                erc20_ledger_id: "nbsys-saaaa-aaaar-qaaga-cai".parse().unwrap(),
+                transfer_kind: None,
+                symbol_collision_policy: None,
+            }
         }
-    })
-}
 
-fn arb_access_list() -> impl Strategy<Value = AccessList> {
-    pvec(arb_access_list_item(), 0..100).prop_map(AccessList)
-}
+        #[test]
+        fn should_accept_alphanumeric_symbols() {
+            for symbol in [
+                "U",
+                "icUSDT",
+                "icSepoliaUSDC",
+                "icUSDCbase",
+                "A234567890123456",
+            ] {
+                let token = ERC20Token::try_from(add_erc20_token(symbol)).unwrap();
+                assert_eq!(
+                    token.erc20_token_symbol,
+                    ERC20TokenSymbol::new(symbol.to_string())
+                );
+                assert_eq!(token.original_symbol, None);
+            }
+        }
 
-prop_compose! {
-    fn arb_init_arg()(
-        contract_address in proptest::option::of(arb_address()),
-        block_height in arb_block_tag(),
-        native_minimum_withdrawal_amount in arb_nat(),
-        next_transaction_nonce in arb_nat(),
-        native_ledger_id in arb_principal(),
-        native_index_id in arb_principal(),
-        ecdsa_key_name in "[a-z_]*",
-        last_scraped_block_number in arb_nat(),
-        min_max_priority_fee_per_gas in arb_nat(),
-        native_ledger_transfer_fee in arb_nat(),
-        native_symbol in "[a-z_]*",
-        ledger_suite_manager_id in arb_principal(),
-        deposit_native_fee in arb_nat(),
-        withdrawal_native_fee in arb_nat()
+        #[test]
+        fn should_reject_invalid_symbols() {
+            for symbol in [
+                "",
+                "A2345678901234567",
+                "ic USDT",
+                "icUSDT!",
+                "icUSDT_base",
+                "icUSDC.base",
+                "icUSDT.",
+                ".icUSDT",
+                "ic..USDT",
+                "icUSDTé",
+            ] {
+                assert!(
+                    ERC20Token::try_from(add_erc20_token(symbol)).is_err(),
+                    "symbol {symbol:?} should be rejected"
+                );
+            }
+        }
+
+        #[test]
+        fn should_reject_symbol_collision_by_default() {
+            let mut state = initial_state();
+            state.evm_network = EvmNetwork::Sepolia;
+            state.record_add_erc20_token(ic_usdc());
+            let usdt_as_usdc = ERC20Token {
+                erc20_token_symbol: ic_usdc().erc20_token_symbol,
+                ..ic_usdt()
+            };
+
+            let result = state.resolve_erc20_token_symbol_collision(
+                usdt_as_usdc,
+                SymbolCollisionPolicy::default(),
+            );
+
+            assert!(result.unwrap_err().contains("already used"));
+        }
+
+        #[test]
+        fn should_not_consider_same_token_as_collision() {
+            let mut state = initial_state();
+            state.evm_network = EvmNetwork::Sepolia;
+            state.record_add_erc20_token(ic_usdc());
+
+            assert_eq!(
+                state
+                    .resolve_erc20_token_symbol_collision(ic_usdc(), SymbolCollisionPolicy::Reject),
+                Ok(ic_usdc())
+            );
+            assert_eq!(
+                state
+                    .resolve_erc20_token_symbol_collision(ic_usdt(), SymbolCollisionPolicy::Reject),
+                Ok(ic_usdt())
+            );
+        }
+
+        #[test]
+        fn should_disambiguate_symbol_collision() {
+            let mut state = initial_state();
+            state.evm_network = EvmNetwork::Sepolia;
+            state.record_add_erc20_token(ic_usdc());
+            let usdc_symbol = ic_usdc().erc20_token_symbol;
+            let usdt_as_usdc = ERC20Token {
+                erc20_token_symbol: usdc_symbol.clone(),
+                ..ic_usdt()
+            };
+
+            let usdt = state
+                .resolve_erc20_token_symbol_collision(
+                    usdt_as_usdc,
+                    SymbolCollisionPolicy::Disambiguate,
+                )
+                .unwrap();
+            assert_eq!(usdt.erc20_token_symbol, "icUSDC.2".parse().unwrap());
+            assert_eq!(usdt.original_symbol, Some(usdc_symbol.clone()));
+            state.record_add_erc20_token(usdt.clone());
+
+            assert_eq!(
+                state.find_erc20_token_by_ledger_id(&usdt.erc20_ledger_id),
+                Some(usdt.clone())
+            );
+            assert_eq!(
+                state.erc20_token_symbol_or_address(&usdt.erc20_contract_address),
+                "icUSDC.2"
+            );
+
+            let third = ERC20Token {
+                erc20_contract_address: "0x2260fac5e5542a773aa44fbcfedf7c193bc2c599"
+                    .parse()
+                    .unwrap(),
+                erc20_ledger_id: "ss2fx-dyaaa-aaaar-qacoq-cai".parse().unwrap(),
+                erc20_token_symbol: usdc_symbol,
+                ..ic_usdt()
+            };
+            assert_eq!(
+                state
+                    .resolve_erc20_token_symbol_collision(
+                        third,
+                        SymbolCollisionPolicy::Disambiguate
+                    )
+                    .unwrap()
+                    .erc20_token_symbol,
+                "icUSDC.3".parse().unwrap()
+            );
+        }
+
+        #[test]
+        fn should_add_disambiguated_token_with_colliding_symbol() {
+            let mut state = initial_state();
+            state.evm_network = EvmNetwork::Sepolia;
+            apply_state_transition(&mut state, &EventType::AddedErc20Token(ic_usdc()));
+            let usdt_as_usdc = ERC20Token {
+                erc20_token_symbol: ic_usdc().erc20_token_symbol,
+                ..ic_usdt()
+            };
+
+            let usdt = state
+                .resolve_erc20_token_symbol_collision(
+                    usdt_as_usdc,
+                    SymbolCollisionPolicy::Disambiguate,
+                )
+                .unwrap();
+            apply_state_transition(&mut state, &EventType::AddedErc20Token(usdt.clone()));
+
+            assert_eq!(
+                state.supported_erc20_tokens().collect::<Vec<_>>(),
+                vec![ic_usdc(), usdt]
+            );
+        }
+
+        #[test]
+        fn should_render_unknown_token_as_address() {
+            let mut state = initial_state();
+            state.evm_network = EvmNetwork::Sepolia;
+            state.record_add_erc20_token(ic_usdc());
+            let usdt_address = ic_usdt().erc20_contract_address;
+
+            assert_eq!(
+                state.erc20_token_symbol_or_address(&ic_usdc().erc20_contract_address),
+                "icUSDC"
+            );
+            // e.g. a withdrawal request for a token that is not supported anymore
+            assert_eq!(
+                state.erc20_token_symbol_or_address(&usdt_address),
+                usdt_address.to_string()
+            );
+        }
+    }
+}
+
+fn arb_principal() -> impl Strategy<Value = Principal> {
+    pvec(any::<u8>(), 0..=29).prop_map(|bytes| Principal::from_slice(&bytes))
+}
+
+fn arb_account() -> impl Strategy<Value = Account> {
+    (
+        arb_principal(),
+        proptest::option::of(uniform32(any::<u8>())),
+    )
+        .prop_map(|(owner, subaccount)| Account { owner, subaccount })
+}
+
+fn arb_ledger_subaccount() -> impl Strategy<Value = Option<LedgerSubaccount>> {
+    uniform32(any::<u8>()).prop_map(LedgerSubaccount::from_bytes)
+}
+
+fn arb_u256() -> impl Strategy<Value = u256> {
+    uniform32(any::<u8>()).prop_map(u256::from_be_bytes)
+}
+
+fn arb_event_source() -> impl Strategy<Value = EventSource> {
+    (arb_hash(), arb_checked_amount_of()).prop_map(|(transaction_hash, log_index)| EventSource {
+        transaction_hash,
+        log_index,
+    })
+}
+
+fn arb_block_tag() -> impl Strategy<Value = CandidBlockTag> {
+    prop_oneof![
+        Just(CandidBlockTag::Safe),
+        Just(CandidBlockTag::Latest),
+        Just(CandidBlockTag::Finalized),
+    ]
+}
+
+fn arb_nat() -> impl Strategy<Value = Nat> {
+    any::<u128>().prop_map(Nat::from)
+}
+
+fn arb_storage_key() -> impl Strategy<Value = StorageKey> {
+    uniform32(any::<u8>()).prop_map(StorageKey)
+}
+
+fn arb_access_list_item() -> impl Strategy<Value = AccessListItem> {
+    (arb_address(), pvec(arb_storage_key(), 0..100)).prop_map(|(address, storage_keys)| {
+        AccessListItem {
+            address,
+            storage_keys,
+        }
+    })
+}
+
+fn arb_access_list() -> impl Strategy<Value = AccessList> {
+    pvec(arb_access_list_item(), 0..100).prop_map(AccessList)
+}
+
+prop_compose! {
+    fn arb_init_arg()(
+        contract_address in proptest::option::of(arb_address()),
+        block_height in arb_block_tag(),
+        native_minimum_withdrawal_amount in arb_nat(),
+        next_transaction_nonce in arb_nat(),
+        native_ledger_id in arb_principal(),
+        native_index_id in arb_principal(),
+        ecdsa_key_name in "[a-z_]*",
+        last_scraped_block_number in arb_nat(),
+        min_max_priority_fee_per_gas in arb_nat(),
+        native_ledger_transfer_fee in arb_nat(),
+        native_symbol in "[a-z_]*",
+        ledger_suite_manager_id in arb_principal(),
+        deposit_native_fee in arb_nat(),
+        withdrawal_native_fee in arb_nat(),
+        fee_collector in proptest::option::of(arb_account())
 
     ) -> InitArg {
-        InitArg {evm_network:EvmNetwork::Sepolia,ecdsa_key_name,helper_contract_address:contract_address.map(|addr|addr.to_string()),native_ledger_id,native_index_id,block_height,native_minimum_withdrawal_amount,next_transaction_nonce,last_scraped_block_number,min_max_priority_fee_per_gas,native_ledger_transfer_fee,native_symbol,ledger_suite_manager_id, deposit_native_fee, withdrawal_native_fee }
+        InitArg {evm_network:EvmNetwork::Sepolia,ecdsa_key_name,helper_contract_address:contract_address.map(|addr|addr.to_string()),native_ledger_id,native_index_id,block_height,native_minimum_withdrawal_amount,next_transaction_nonce,last_scraped_block_number,min_max_priority_fee_per_gas,native_ledger_transfer_fee,native_symbol,ledger_suite_manager_id, deposit_native_fee, withdrawal_native_fee, fee_collector }
     }
 }
 
@@ -591,9 +1151,42 @@ prop_compose! {
         native_ledger_transfer_fee in proptest::option::of(arb_nat()),
         min_max_priority_fee_per_gas in proptest::option::of(arb_nat()),
         deposit_native_fee in proptest::option::of(arb_nat()),
-        withdrawal_native_fee in proptest::option::of(arb_nat())
+        withdrawal_native_fee in proptest::option::of(arb_nat()),
+        gas_fee_config in proptest::option::of(arb_gas_fee_config_arg()),
+        confirmations in proptest::option::of(any::<u32>()),
+        gas_limits in proptest::option::of(pvec(arb_gas_limit_arg(), 0..5)),
+        fee_collector in proptest::option::of(arb_account()),
+        refund_deadline_window_secs in proptest::option::of(any::<u64>()),
+        max_fee_per_gas_cap in proptest::option::of(arb_nat()),
+        max_transaction_fee_cap in proptest::option::of(arb_nat())
     ) -> UpgradeArg {
-        UpgradeArg {helper_contract_address:contract_address.map(|addr|addr.to_string()),block_height,native_minimum_withdrawal_amount,next_transaction_nonce,last_scraped_block_number,evm_rpc_id,native_ledger_transfer_fee,min_max_priority_fee_per_gas, deposit_native_fee, withdrawal_native_fee }
+        UpgradeArg {helper_contract_address:contract_address.map(|addr|addr.to_string()),block_height,native_minimum_withdrawal_amount,next_transaction_nonce,last_scraped_block_number,evm_rpc_id,native_ledger_transfer_fee,min_max_priority_fee_per_gas, deposit_native_fee, withdrawal_native_fee, gas_fee_config, confirmations, gas_limits, fee_collector, refund_deadline_window_secs, max_fee_per_gas_cap, max_transaction_fee_cap }
+    }
+}
+
+prop_compose! {
+    fn arb_gas_limit_arg()(
+        kind in prop_oneof![
+            Just(GasLimitKind::NativeWithdrawal),
+            Just(GasLimitKind::Erc20Withdrawal),
+            Just(GasLimitKind::Erc20Approval),
+            Just(GasLimitKind::WrappedIcrcMint),
+            Just(GasLimitKind::RefundFailedSwap),
+            Just(GasLimitKind::NativeWithdrawalToContract),
+        ],
+        gas_limit in arb_nat()
+    ) -> GasLimitArg {
+        GasLimitArg { kind, gas_limit }
+    }
+}
+
+prop_compose! {
+    fn arb_gas_fee_config_arg()(
+        reward_percentile_index in any::<u8>(),
+        base_fee_multiplier_percent in any::<u16>(),
+        min_priority_fee in arb_nat()
+    ) -> GasFeeConfigArg {
+        GasFeeConfigArg { reward_percentile_index, base_fee_multiplier_percent, min_priority_fee }
     }
 }
 
@@ -628,7 +1221,8 @@ prop_compose! {
         value in arb_checked_amount_of(),
         principal in arb_principal(),
         erc20_contract_address in arb_address(),
-        subaccount in arb_ledger_subaccount()
+        subaccount in arb_ledger_subaccount(),
+        original_owner in proptest::option::of(arb_address())
     ) -> ReceivedErc20Event {
         ReceivedErc20Event {
             transaction_hash,
@@ -638,7 +1232,8 @@ prop_compose! {
             value,
             principal,
             erc20_contract_address,
-            subaccount
+            subaccount,
+            original_owner
         }
     }
 }
@@ -714,6 +1309,13 @@ prop_compose! {
     }
 }
 
+fn arb_confirmation_policy() -> impl Strategy<Value = CandidConfirmationPolicy> {
+    prop_oneof![
+        arb_block_tag().prop_map(CandidConfirmationPolicy::BlockTag),
+        any::<u32>().prop_map(CandidConfirmationPolicy::Confirmations),
+    ]
+}
+
 fn arb_event_type() -> impl Strategy<Value = EventType> {
     prop_oneof![
         arb_init_arg().prop_map(EventType::Init),
@@ -731,6 +1333,41 @@ fn arb_event_type() -> impl Strategy<Value = EventType> {
             }
         }),
         arb_checked_amount_of().prop_map(|block_number| EventType::SyncedToBlock { block_number }),
+        (any::<bool>(), any::<bool>(), pvec(arb_address(), 0..10)).prop_map(
+            |(deny, add, addresses)| EventType::UpdatedAddressList {
+                list: if deny {
+                    ListKind::Deny
+                } else {
+                    ListKind::Allow
+                },
+                action: if add {
+                    AddOrRemove::Add
+                } else {
+                    AddOrRemove::Remove
+                },
+                addresses,
+            }
+        ),
+        (arb_checked_amount_of(), arb_checked_amount_of()).prop_map(|(start_block, end_block)| {
+            EventType::UpgradeGraceScrape {
+                start_block,
+                end_block,
+            }
+        }),
+        (arb_checked_amount_of(), arb_checked_amount_of()).prop_map(|(from_block, to_block)| {
+            EventType::RescrapedBlockRange {
+                from_block,
+                to_block,
+            }
+        }),
+        (arb_checked_amount_of(), arb_checked_amount_of()).prop_map(|(from_block, to_block)| {
+            EventType::SkippedBlockRange {
+                from_block,
+                to_block,
+            }
+        }),
+        (arb_confirmation_policy(), arb_confirmation_policy())
+            .prop_map(|(from, to)| EventType::ChangedConfirmationPolicy { from, to }),
         (any::<u64>(), arb_unsigned_tx()).prop_map(|(withdrawal_id, transaction)| {
             EventType::CreatedTransaction {
                 withdrawal_id: withdrawal_id.into(),
@@ -749,12 +1386,203 @@ fn arb_event_type() -> impl Strategy<Value = EventType> {
                 transaction,
             }
         }),
+        (any::<u64>(), arb_unsigned_tx()).prop_map(|(withdrawal_id, cancellation)| {
+            EventType::ParkedTransaction {
+                withdrawal_id: withdrawal_id.into(),
+                cancellation,
+            }
+        }),
+        (".*", ".*", pvec((".*", arb_hash()), 0..5), any::<u64>()).prop_map(
+            |(method, block_range_or_hash, per_provider_hash, timestamp)| {
+                EventType::ProviderDisagreement {
+                    method,
+                    block_range_or_hash,
+                    per_provider_hash,
+                    timestamp,
+                }
+            }
+        ),
         (any::<u64>(), arb_tx_receipt()).prop_map(|(withdrawal_id, transaction_receipt)| {
             EventType::FinalizedTransaction {
                 withdrawal_id: withdrawal_id.into(),
                 transaction_receipt,
             }
         }),
+        (
+            proptest::sample::select(Provider::ALL.to_vec()),
+            any::<u64>()
+        )
+            .prop_map(
+                |(provider, retry_after_ns)| EventType::ProviderRateLimited {
+                    provider,
+                    retry_after_ns,
+                }
+            ),
+        (any::<u64>(), any::<u64>(), arb_hash()).prop_map(|(from_index, up_to_index, hash)| {
+            EventType::ArchivedEvents {
+                from_index,
+                up_to_index,
+                hash,
+            }
+        }),
+        (any::<u64>(), arb_checked_amount_of()).prop_map(|(pruned_count, threshold_block)| {
+            EventType::EventLogCompacted {
+                pruned_count,
+                threshold_block,
+            }
+        }),
+        any::<u64>()
+            .prop_map(|in_flight_count| EventType::UpgradedWhileNotDrained { in_flight_count }),
+        ("[a-zA-Z.]{1,16}", any::<u8>()).prop_map(|(symbol, decimals)| {
+            EventType::NativeLedgerMetadataMismatch(NativeLedgerMetadata { symbol, decimals })
+        }),
+        ("[a-zA-Z.]{1,16}", any::<u8>()).prop_map(|(symbol, decimals)| {
+            EventType::NativeLedgerMetadataMatched(NativeLedgerMetadata { symbol, decimals })
+        }),
+        (
+            arb_address(),
+            arb_address(),
+            arb_checked_amount_of(),
+            any::<u64>(),
+            arb_principal(),
+            any::<u64>(),
+        )
+            .prop_map(
+                |(
+                    erc20_contract_address,
+                    swap_contract_address,
+                    max_transaction_fee,
+                    burn_index,
+                    from,
+                    created_at,
+                )| {
+                    EventType::AcceptedApprovalRevocationRequest(Erc20Approve {
+                        max_transaction_fee,
+                        erc20_contract_address,
+                        swap_contract_address,
+                        native_ledger_burn_index: LedgerBurnIndex::new(burn_index),
+                        from,
+                        from_subaccount: None,
+                        created_at,
+                        l1_fee: None,
+                        withdrawal_fee: None,
+                        is_revocation: Some(true),
+                    })
+                }
+            ),
+        (
+            arb_checked_amount_of(),
+            arb_checked_amount_of(),
+            any::<u64>(),
+            any::<u64>(),
+            any::<u64>(),
+        )
+            .prop_map(
+                |(from_block, to_block, accepted_events, invalid_events, duplicate_events)| {
+                    EventType::BackfillCompleted {
+                        from_block,
+                        to_block,
+                        accepted_events,
+                        invalid_events,
+                        duplicate_events,
+                    }
+                }
+            ),
+        (any::<u64>(), arb_checked_amount_of()).prop_map(|(withdrawal_id, amount_out)| {
+            EventType::RealizedSwapAmountOut {
+                withdrawal_id: withdrawal_id.into(),
+                amount_out,
+            }
+        }),
+        (
+            proptest::sample::select(FeatureFlag::ALL.to_vec()),
+            any::<bool>()
+        )
+            .prop_map(|(flag, enabled)| EventType::FeatureFlagChanged { flag, enabled }),
+        (arb_principal(), arb_checked_amount_of(), any::<u64>()).prop_map(
+            |(ledger_id, amount, block_index)| EventType::FeeCollected {
+                ledger_id,
+                amount,
+                block_index: block_index.into(),
+            }
+        ),
+        (
+            arb_principal(),
+            arb_account(),
+            arb_checked_amount_of(),
+            any::<u64>()
+        )
+            .prop_map(|(ledger_id, to, amount, block_index)| {
+                EventType::CollectedFeesWithdrawn {
+                    ledger_id,
+                    to,
+                    amount,
+                    block_index,
+                }
+            }),
+        (
+            any::<u64>(),
+            arb_checked_amount_of(),
+            arb_checked_amount_of(),
+            arb_checked_amount_of(),
+            arb_checked_amount_of(),
+            any::<u64>()
+        )
+            .prop_map(
+                |(
+                    withdrawal_id,
+                    max_fee_per_gas,
+                    max_transaction_fee,
+                    max_fee_per_gas_cap,
+                    max_transaction_fee_cap,
+                    timestamp,
+                )| EventType::FeeCapExceeded {
+                    withdrawal_id: withdrawal_id.into(),
+                    max_fee_per_gas,
+                    max_transaction_fee,
+                    cap: FeeCap {
+                        max_fee_per_gas: max_fee_per_gas_cap,
+                        max_transaction_fee: max_transaction_fee_cap,
+                    },
+                    timestamp,
+                }
+            ),
+        (
+            any::<u64>(),
+            arb_hash(),
+            arb_checked_amount_of(),
+            any::<u64>()
+        )
+            .prop_map(|(withdrawal_id, transaction_hash, nonce, timestamp)| {
+                EventType::BroadcastAttempted {
+                    withdrawal_id: withdrawal_id.into(),
+                    transaction_hash,
+                    nonce,
+                    timestamp,
+                }
+            }),
+        (
+            any::<u64>(),
+            arb_hash(),
+            prop_oneof![
+                Just(BroadcastOutcome::Sent),
+                Just(BroadcastOutcome::Mined),
+                Just(BroadcastOutcome::NotMined),
+            ]
+        )
+            .prop_map(|(withdrawal_id, transaction_hash, outcome)| {
+                EventType::BroadcastReconciled {
+                    withdrawal_id: withdrawal_id.into(),
+                    transaction_hash,
+                    outcome,
+                }
+            }),
+        any::<u64>().prop_map(|withdrawal_id| EventType::QuarantinedWithdrawal {
+            withdrawal_id: withdrawal_id.into(),
+        }),
+        (arb_principal(), arb_checked_amount_of()).prop_map(|(ledger_id, amount)| {
+            EventType::UnaccountedFeesFound { ledger_id, amount }
+        }),
     ]
 }
 
@@ -776,7 +1604,8 @@ pub fn state_equivalence() {
     use crate::map::MultiKeyMap;
     use crate::rpc_declarations::{TransactionReceipt, TransactionStatus};
     use crate::state::transactions::{
-        NativeWithdrawalRequest, Reimbursed, ReimbursementRequest, WithdrawalTransactions,
+        FinalizedReimbursementRequest, NativeWithdrawalRequest, Reimbursed, ReimbursementRequest,
+        WithdrawalTransactions,
     };
     use crate::state::{InvalidEventReason, MintedEvent};
     use crate::tx::{
@@ -817,6 +1646,7 @@ pub fn state_equivalence() {
         created_at: Some(1699527697000000000),
         l1_fee: Some(Wei::new(1_000_000_000_000)),
         withdrawal_fee: None,
+        is_contract_destination: None,
     };
     let withdrawal_request2 = NativeWithdrawalRequest {
         ledger_burn_index: LedgerBurnIndex::new(20),
@@ -840,7 +1670,8 @@ pub fn state_equivalence() {
                 from_subaccount: None,
                 created_at: Some(1699527697000000000),
                 l1_fee:Some(Wei::new(4_000_000_000_000)),
-                withdrawal_fee:None
+                withdrawal_fee:None,
+                is_contract_destination: None,
             }.into(),
            withdrawal_request1.ledger_burn_index  => withdrawal_request1.clone().into(),
         },
@@ -936,15 +1767,15 @@ pub fn state_equivalence() {
         next_nonce: TransactionNonce::new(3),
         maybe_reimburse: btreeset! { LedgerBurnIndex::new(4) },
         reimbursement_requests: btreemap! {
-            ReimbursementIndex::Native { ledger_burn_index: LedgerBurnIndex::new(3) } => ReimbursementRequest {
-                transaction_hash: Some("0x06afc3c693dc2ba2c19b5c287c4dddce040d766bea5fd13c8a7268b04aa94f2d"
+            ReimbursementIndex::Native { ledger_burn_index: LedgerBurnIndex::new(3) } => ReimbursementRequest::Finalized(FinalizedReimbursementRequest {
+                transaction_hash: "0x06afc3c693dc2ba2c19b5c287c4dddce040d766bea5fd13c8a7268b04aa94f2d"
                 .parse()
-                .unwrap()),
+                .unwrap(),
                 ledger_burn_index: LedgerBurnIndex::new(3),
                 reimbursed_amount: Erc20TokenAmount::new(100_000_000_000),
                 to: "ezu3d-2mifu-k3bh4-oqhrj-mbrql-5p67r-pp6pr-dbfra-unkx5-sxdtv-rae".parse().unwrap(),
                 to_subaccount: None,
-            }
+            })
         },
         reimbursed: btreemap! {
            ReimbursementIndex::Native { ledger_burn_index: LedgerBurnIndex::new(6) } =>
@@ -953,6 +1784,13 @@ pub fn state_equivalence() {
         },
         failed_swap_requests: Default::default(),
         quarantined_swap_requests: Default::default(),
+        swap_status_history: Default::default(),
+        swap_burn_indexes: Default::default(),
+        parked: Default::default(),
+        realized_swap_amounts_out: Default::default(),
+        erc20_burn_indexes: Default::default(),
+        finalized_withdrawals: Default::default(),
+        broadcast_attempts: Default::default(),
     };
     let mut erc20_tokens = DedupMultiKeyMap::default();
     erc20_tokens
@@ -977,7 +1815,7 @@ pub fn state_equivalence() {
             chain_code: vec![2; 32],
         }),
         native_minimum_withdrawal_amount: Wei::new(1_000_000_000_000_000),
-        block_height: BlockTag::Finalized,
+        confirmation_policy: ConfirmationPolicy::BlockTag(BlockTag::Finalized),
         first_scraped_block_number: BlockNumber::new(1_000_001),
         last_scraped_block_number: BlockNumber::new(1_000_000),
         last_observed_block_number: Some(BlockNumber::new(2_000_000)),
@@ -1011,36 +1849,70 @@ pub fn state_equivalence() {
         invalid_events: btreemap! {
             source("0x05c6ec45699c9a6a4b1a4ea2058b0cee852ea2f19b18fb8313c04bf8156efde4", 11) => InvalidEventReason::InvalidEvent("failed to decode principal from bytes 0x00333c125dc9f41abaf2b8b85d49fdc7ff75b2a4000000000000000000000000".to_string()),
         },
+        removed_log_events: Default::default(),
         withdrawal_transactions: withdrawal_transactions.clone(),
         pending_withdrawal_principals: Default::default(),
         active_tasks: Default::default(),
+        mints_in_flight: Default::default(),
+        reorged_mints_in_flight: Default::default(),
+        contract_destinations: Default::default(),
         native_balance: Default::default(),
         erc20_balances: Default::default(),
         skipped_blocks: Default::default(),
+        scraping_gaps: Default::default(),
+        last_manual_deposit_checks: Default::default(),
+        last_provider_disagreements: Default::default(),
         last_transaction_price_estimate: None,
+        last_transaction_price_estimate_block: None,
+        current_l1_fee: None,
+        total_ledger_call_cycles_spent: 0,
+        ecdsa_signing_calls: 0,
+        ecdsa_signing_cycles_spent: 0,
         evm_canister_id: "sosge-5iaaa-aaaag-alcla-cai".parse().unwrap(),
         erc20_tokens,
         native_symbol: ERC20TokenSymbol::new("icSepoliaETH".to_string()),
         native_ledger_transfer_fee: Wei::new(2_000_000_000_000_000),
         min_max_priority_fee_per_gas: WeiPerGas::new(1000),
+        gas_fee_config: None,
+        gas_limits: Default::default(),
         ledger_suite_manager_id: None,
         dex_canister_id: None,
         last_observed_block_time: None,
+        last_observed_block_timestamp: None,
+        last_scraped_block_timestamp: None,
+        last_log_scraping_request_time: 0,
         withdrawal_native_fee: None,
         events_to_release: Default::default(),
         released_events: Default::default(),
         quarantined_releases: Default::default(),
         icrc_balances: Default::default(),
         wrapped_icrc_tokens: Default::default(),
+        wrapped_icrc_deployments: Default::default(),
+        wrapped_icrc_metadata: Default::default(),
         twin_usdc_info: None,
         swap_contract_address: None,
-        is_swapping_active: false,
+        feature_flags: Default::default(),
+        fee_collector: None,
+        collected_fees: Default::default(),
+        pending_fee_withdrawals: Default::default(),
+        refund_deadline_window_secs: None,
+        fee_cap_override: None,
+        last_fee_cap_alert: None,
         swap_events_to_mint_to_appic_dex: Default::default(),
         last_native_token_usd_price_estimate: None,
         canister_signing_fee_twin_usdc_amount: None,
         gas_tank: GasTank::default(),
         next_swap_ledger_burn_index: None,
         quarantined_dex_orders: Default::default(),
+        rate_limited_providers: Default::default(),
+        non_standard_erc20_tokens: Default::default(),
+        known_erc20_contracts: Default::default(),
+        token_volumes: Default::default(),
+        daily_stats: Default::default(),
+        earliest_unpruned_block: Default::default(),
+        erc20_original_symbols: Default::default(),
+        native_ledger_metadata: None,
+        onchain_approvals: Default::default(),
         swap_events_to_be_notified: Default::default(),
         notified_swap_events: Default::default(),
         lastest_requested_block_to_scrape: None,
@@ -1095,7 +1967,7 @@ pub fn state_equivalence() {
     assert_ne!(
         Ok(()),
         state.is_equivalent_to(&State {
-            block_height: BlockTag::Latest,
+            confirmation_policy: ConfirmationPolicy::BlockTag(BlockTag::Latest),
             ..state.clone()
         }),
         "changing essential fields should break equivalence",
@@ -1249,6 +2121,7 @@ pub fn state_equivalence() {
                 GasFeeEstimate {
                     base_fee_per_gas: WeiPerGas::new(10_000_000),
                     max_priority_fee_per_gas: WeiPerGas::new(1_000_000),
+                    base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
                 }
             )),
             ..state.clone()
@@ -1380,12 +2253,14 @@ mod native_balance {
             created_at: Some(1699527697000000000),
             l1_fee: Some(l1_fee),
             withdrawal_fee: Some(withdrawal_native_fee),
+            is_contract_destination: None,
         };
 
         let withdrawal_flow = WithdrawalFlow {
             tx_fee: GasFeeEstimate {
                 base_fee_per_gas: WeiPerGas::from(0xbc9998d1_u64),
                 max_priority_fee_per_gas: WeiPerGas::from(1_500_000_000_u64),
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
             },
             gas_limit: GasAmount::from(21_000_u32),
             effective_gas_price: WeiPerGas::from(0x1176e9eb9_u64),
@@ -1489,6 +2364,7 @@ mod native_balance {
         let tx_fee = GasFeeEstimate {
             base_fee_per_gas: WeiPerGas::from(0x4ce9a_u64),
             max_priority_fee_per_gas: WeiPerGas::from(1_500_000_000_u64),
+            base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
         };
 
         let l1_fee = withdrawal_request.l1_fee.unwrap();
@@ -1579,60 +2455,192 @@ mod native_balance {
         );
     }
 
-    #[derive(Clone)]
-    struct WithdrawalFlow {
-        withdrawal_request: WithdrawalRequest,
-        nonce: TransactionNonce,
-        tx_fee: GasFeeEstimate,
-        gas_limit: GasAmount,
-        effective_gas_price: WeiPerGas,
-        effective_gas_used: GasAmount,
-        tx_status: TransactionStatus,
-    }
-
-    impl WithdrawalFlow {
-        fn for_request<T: Into<WithdrawalRequest>>(withdrawal_request: T) -> Self {
-            Self {
-                withdrawal_request: withdrawal_request.into(),
-                nonce: TransactionNonce::ZERO,
-                tx_fee: GasFeeEstimate {
-                    base_fee_per_gas: WeiPerGas::ONE,
-                    max_priority_fee_per_gas: WeiPerGas::ONE,
-                },
-                gas_limit: GasAmount::from(21_000_u32),
-                effective_gas_price: WeiPerGas::ONE,
-                effective_gas_used: GasAmount::from(21_000_u32),
-                tx_status: TransactionStatus::Success,
-            }
-        }
+    #[test]
+    fn should_pay_cancellation_exceeding_charged_fee_from_gas_tank() {
+        use crate::tx::cancellation::create_cancellation_transaction;
 
-        fn apply(self, state: &mut State) -> TransactionReceipt {
-            let accepted_withdrawal_request_event = match &self.withdrawal_request {
-                WithdrawalRequest::Native(eth_request) => {
-                    EventType::AcceptedNativeWithdrawalRequest(eth_request.clone())
-                }
-                WithdrawalRequest::Erc20(erc20_request) => {
-                    EventType::AcceptedErc20WithdrawalRequest(erc20_request.clone())
-                }
-                WithdrawalRequest::Erc20Approve(erc20_approve) => {
-                    EventType::AcceptedSwapActivationRequest(erc20_approve.clone())
-                }
-                WithdrawalRequest::Swap(swap_request) => {
-                    EventType::AcceptedSwapRequest(swap_request.clone())
-                }
-            };
-            apply_state_transition(state, &accepted_withdrawal_request_event);
+        let mut state = initial_erc20_state();
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedDeposit(received_deposit_event()),
+        );
+        state
+            .gas_tank
+            .native_balance_add(Wei::new(1_000_000_000_000_000));
+        let gas_tank_before_withdrawal = state.gas_tank.native_balance;
+        let native_balance_before_withdrawal = state.native_balance.native_balance;
 
-            let transaction = create_transaction(
-                &self.withdrawal_request,
-                self.nonce,
-                self.tx_fee,
-                self.gas_limit,
-                EvmNetwork::Sepolia,
-            )
-            .expect("BUG: failed to create transaction");
-            apply_state_transition(
-                state,
+        let withdrawal_request = Erc20WithdrawalRequest {
+            max_transaction_fee: Wei::new(21_000_000_000_000),
+            ..erc20_withdrawal_request()
+        };
+        let withdrawal_id = withdrawal_request.native_ledger_burn_index;
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedErc20WithdrawalRequest(withdrawal_request.clone()),
+        );
+        let transaction = create_transaction(
+            &withdrawal_request.clone().into(),
+            TransactionNonce::ZERO,
+            GasFeeEstimate {
+                base_fee_per_gas: WeiPerGas::ONE,
+                max_priority_fee_per_gas: WeiPerGas::ONE,
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+            },
+            GasAmount::from(66_000_u32),
+            EvmNetwork::Sepolia,
+        )
+        .unwrap();
+        apply_state_transition(
+            &mut state,
+            &EventType::CreatedTransaction {
+                withdrawal_id,
+                transaction: transaction.clone(),
+            },
+        );
+        let dummy_signature = Eip1559Signature {
+            signature_y_parity: false,
+            r: Default::default(),
+            s: Default::default(),
+        };
+        apply_state_transition(
+            &mut state,
+            &EventType::SignedTransaction {
+                withdrawal_id,
+                transaction: SignedEip1559TransactionRequest::from((
+                    transaction.clone(),
+                    dummy_signature.clone(),
+                )),
+            },
+        );
+
+        let cancellation = create_cancellation_transaction(
+            &transaction,
+            "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34"
+                .parse()
+                .unwrap(),
+            GasFeeEstimate {
+                base_fee_per_gas: WeiPerGas::ONE,
+                max_priority_fee_per_gas: WeiPerGas::from(2_000_000_000_u64),
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+            },
+            state.gas_limit(GasLimitKind::NativeWithdrawal),
+        );
+        apply_state_transition(
+            &mut state,
+            &EventType::ParkedTransaction {
+                withdrawal_id,
+                cancellation: cancellation.clone(),
+            },
+        );
+        let signed_cancellation =
+            SignedEip1559TransactionRequest::from((cancellation, dummy_signature));
+        apply_state_transition(
+            &mut state,
+            &EventType::SignedTransaction {
+                withdrawal_id,
+                transaction: signed_cancellation.clone(),
+            },
+        );
+        let receipt = TransactionReceipt {
+            block_hash: "0xce67a85c9fb8bc50213815c32814c159fd75160acf7cb8631e8e7b7cf7f1d472"
+                .parse()
+                .unwrap(),
+            block_number: BlockNumber::new(4190269),
+            effective_gas_price: WeiPerGas::from(2_000_000_000_u64),
+            gas_used: GasAmount::from(21_000_u32),
+            status: TransactionStatus::Success,
+            transaction_hash: signed_cancellation.hash(),
+        };
+        apply_state_transition(
+            &mut state,
+            &EventType::FinalizedTransaction {
+                withdrawal_id,
+                transaction_receipt: receipt.clone(),
+            },
+        );
+
+        let cancellation_fee = receipt.effective_transaction_fee();
+        let cancellation_cost = cancellation_fee
+            .checked_sub(withdrawal_request.max_transaction_fee)
+            .unwrap();
+        assert_eq!(cancellation_cost, Wei::new(21_000_000_000_000));
+        assert_eq!(
+            state.gas_tank.native_balance,
+            gas_tank_before_withdrawal
+                .checked_add(withdrawal_request.withdrawal_fee.unwrap())
+                .unwrap()
+                .checked_sub(cancellation_cost)
+                .unwrap()
+        );
+        assert_eq!(
+            state.native_balance.native_balance,
+            native_balance_before_withdrawal
+                .checked_sub(cancellation_fee)
+                .unwrap()
+                .checked_sub(withdrawal_request.l1_fee.unwrap())
+                .unwrap()
+        );
+    }
+
+    #[derive(Clone)]
+    pub(super) struct WithdrawalFlow {
+        withdrawal_request: WithdrawalRequest,
+        pub(super) nonce: TransactionNonce,
+        tx_fee: GasFeeEstimate,
+        gas_limit: GasAmount,
+        effective_gas_price: WeiPerGas,
+        effective_gas_used: GasAmount,
+        pub(super) tx_status: TransactionStatus,
+    }
+
+    impl WithdrawalFlow {
+        pub(super) fn for_request<T: Into<WithdrawalRequest>>(withdrawal_request: T) -> Self {
+            Self {
+                withdrawal_request: withdrawal_request.into(),
+                nonce: TransactionNonce::ZERO,
+                tx_fee: GasFeeEstimate {
+                    base_fee_per_gas: WeiPerGas::ONE,
+                    max_priority_fee_per_gas: WeiPerGas::ONE,
+                    base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+                },
+                gas_limit: GasAmount::from(21_000_u32),
+                effective_gas_price: WeiPerGas::ONE,
+                effective_gas_used: GasAmount::from(21_000_u32),
+                tx_status: TransactionStatus::Success,
+            }
+        }
+
+        pub(super) fn apply(self, state: &mut State) -> TransactionReceipt {
+            let accepted_withdrawal_request_event = match &self.withdrawal_request {
+                WithdrawalRequest::Native(eth_request) => {
+                    EventType::AcceptedNativeWithdrawalRequest(eth_request.clone())
+                }
+                WithdrawalRequest::Erc20(erc20_request) => {
+                    EventType::AcceptedErc20WithdrawalRequest(erc20_request.clone())
+                }
+                WithdrawalRequest::Erc20Approve(erc20_approve) if erc20_approve.is_revocation() => {
+                    EventType::AcceptedApprovalRevocationRequest(erc20_approve.clone())
+                }
+                WithdrawalRequest::Erc20Approve(erc20_approve) => {
+                    EventType::AcceptedSwapActivationRequest(erc20_approve.clone())
+                }
+                WithdrawalRequest::Swap(swap_request) => {
+                    EventType::AcceptedSwapRequest(swap_request.clone())
+                }
+            };
+            apply_state_transition(state, &accepted_withdrawal_request_event);
+
+            let transaction = create_transaction(
+                &self.withdrawal_request,
+                self.nonce,
+                self.tx_fee,
+                self.gas_limit,
+                EvmNetwork::Sepolia,
+            )
+            .expect("BUG: failed to create transaction");
+            apply_state_transition(
+                state,
                 &EventType::CreatedTransaction {
                     withdrawal_id: self.withdrawal_request.native_ledger_burn_index(),
                     transaction: transaction.clone(),
@@ -1692,123 +2700,1321 @@ mod native_balance {
                     .unwrap(),
                 erc20_token_symbol: "ckSepoliaUSDC".parse().unwrap(),
                 erc20_ledger_id: Principal::from_text("3sgad-taaaa-aaaar-qaedq-cai").unwrap(),
+                transfer_kind: None,
+                original_symbol: None,
             }),
         );
     }
 }
 
-mod erc20_balance {
-    use crate::contract_logs::types::ReceivedErc20Event;
+mod swap_status_history {
+    use crate::candid_types::{RetrieveWithdrawalStatus, SwapStatus, TxFinalizedStatus};
+    use crate::contract_logs::swap::swap_logs::ReceivedSwapEvent;
     use crate::contract_logs::ReceivedContractEvent;
-    use crate::state::audit::EventType::AcceptedErc20WithdrawalRequest;
-    use crate::state::audit::{apply_state_transition, EventType};
-    use crate::state::tests::{
-        checked_sub, erc20_withdrawal_request, initial_erc20_state, received_deposit_event,
-        received_erc20_event,
+    use crate::evm_config::EvmNetwork;
+    use crate::numeric::{
+        BlockNumber, Erc20Value, GasAmount, LedgerBurnIndex, LedgerMintIndex, LogIndex,
+        TransactionNonce, Wei, WeiPerGas,
     };
-    use crate::state::transactions::Erc20WithdrawalRequest;
-    use crate::test_fixtures::expect_panic_with_message;
+    use crate::rpc_declarations::{Data, FixedSizeData, TransactionReceipt, TransactionStatus};
+    use crate::state::audit::{apply_state_transition, record_swap_status_transitions, EventType};
+    use crate::state::feature_flags::FeatureFlag;
+    use crate::state::tests::{initial_erc20_state, received_deposit_event};
+    use crate::state::transactions::{create_transaction, ExecuteSwapRequest, WithdrawalRequest};
+    use crate::state::State;
+    use crate::tx::gas_fees::{GasFeeEstimate, DEFAULT_BASE_FEE_MULTIPLIER_PERCENT};
+    use crate::tx::{Eip1559Signature, SignedEip1559TransactionRequest};
+    use crate::tx_id::SwapTxId;
+    use assert_matches::assert_matches;
+    use candid::{Nat, Principal};
     use evm_rpc_client::eth_types::Address;
-    use maplit::btreemap;
 
-    #[test]
-    fn should_panic_when_deposited_erc20_not_supported() {
-        let mut state = initial_erc20_state();
-        let unsupported_erc20_address: Address = "0x6b175474e89094c44da98b954eedeac495271d0f"
+    const SWAP_TX_ID: &str = "56-1-1741348218000000000";
+
+    fn usdc_address() -> Address {
+        "0x1c7d4b196cb0c7b01d743fbc6116a902379c7238"
             .parse()
-            .unwrap();
-        assert!(!state.erc20_tokens.contains_alt(&unsupported_erc20_address));
+            .unwrap()
+    }
 
-        let deposit_event = ReceivedErc20Event {
-            erc20_contract_address: unsupported_erc20_address,
-            ..received_erc20_event()
-        };
-        expect_panic_with_message(
-            || {
-                apply_state_transition(
-                    &mut state,
-                    &ReceivedContractEvent::from(deposit_event.clone()).into_event_type(),
-                )
-            },
-            "BUG: unsupported ERC-20",
-        );
+    fn swap_contract_address() -> Address {
+        "0x733a1BEeF5A02990aAD285d7ED93fc1b622EeF1d"
+            .parse()
+            .unwrap()
     }
 
-    #[test]
-    fn should_panic_when_withdrawn_erc20_not_supported() {
+    fn twin_usdc_ledger_id() -> Principal {
+        Principal::from_text("3sgad-taaaa-aaaar-qaedq-cai").unwrap()
+    }
+
+    fn received_swap_event() -> ReceivedSwapEvent {
+        ReceivedSwapEvent {
+            transaction_hash: "0x8b5a6e1e2bd1ab4d3c0a3d2b1ff4e3c1c4a2b7e6d5f4e3d2c1b0a9f8e7d6c5b4"
+                .parse()
+                .unwrap(),
+            block_number: BlockNumber::new(5_539_950),
+            log_index: LogIndex::from(3_u8),
+            from_address: "0xdd2851Cdd40aE6536831558DD46db62fAc7A844d"
+                .parse()
+                .unwrap(),
+            recipient: FixedSizeData([1; 32]),
+            token_in: "0x6b175474e89094c44da98b954eedeac495271d0f"
+                .parse()
+                .unwrap(),
+            token_out: usdc_address(),
+            amount_in: Erc20Value::from(2_000_000_u64),
+            amount_out: Erc20Value::from(1_000_000_u64),
+            bridged_to_minter: true,
+            encoded_swap_data: Data(vec![1, 2, 3]),
+        }
+    }
+
+    pub(super) fn refund_swap_request() -> ExecuteSwapRequest {
+        ExecuteSwapRequest {
+            max_transaction_fee: Wei::new(1_000_000_000_000_000),
+            erc20_token_in: usdc_address(),
+            erc20_amount_in: Erc20Value::from(1_000_000_u64),
+            min_amount_out: Erc20Value::ZERO,
+            recipient: "0xdd2851Cdd40aE6536831558DD46db62fAc7A844d"
+                .parse()
+                .unwrap(),
+            deadline: Erc20Value::from(1_741_351_818_u64),
+            commands: vec![],
+            commands_data: vec![],
+            swap_contract: swap_contract_address(),
+            gas_estimate: GasAmount::from(500_000_u32),
+            native_ledger_burn_index: LedgerBurnIndex::new(10_000_000_000_000_000_000_u64),
+            erc20_ledger_id: twin_usdc_ledger_id(),
+            erc20_ledger_burn_index: LedgerBurnIndex::new(7),
+            from: Principal::from_text(
+                "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae",
+            )
+            .unwrap(),
+            from_subaccount: None,
+            created_at: 1_741_348_218_000_000_000,
+            l1_fee: None,
+            withdrawal_fee: None,
+            swap_tx_id: SWAP_TX_ID.to_string(),
+            is_refund: true,
+        }
+    }
+
+    pub(super) fn swap_enabled_state() -> State {
         let mut state = initial_erc20_state();
-        let unsupported_erc20_address: Address = "0x6b175474e89094c44da98b954eedeac495271d0f"
-            .parse()
-            .unwrap();
-        assert!(!state.erc20_tokens.contains_alt(&unsupported_erc20_address));
         apply_state_transition(
             &mut state,
-            &EventType::AcceptedErc20Deposit(received_erc20_event()),
+            &EventType::SwapContractActivated {
+                swap_contract_address: swap_contract_address(),
+                usdc_contract_address: usdc_address(),
+                twin_usdc_ledger_id: twin_usdc_ledger_id(),
+                twin_usdc_decimals: 6,
+                canister_signing_fee_twin_usdc_value: Erc20Value::from(100_000_u64),
+                dex_canister_id: Principal::from_text("sosge-5iaaa-aaaag-alcla-cai").unwrap(),
+            },
         );
+        state.feature_flags.set(FeatureFlag::SwapActive, true);
         apply_state_transition(
             &mut state,
             &EventType::AcceptedDeposit(received_deposit_event()),
         );
-        let erc20_withdrawal = Erc20WithdrawalRequest {
-            erc20_contract_address: unsupported_erc20_address,
-            ..erc20_withdrawal_request()
+        state
+    }
+
+    /// Created, signed and successfully finalized transaction of the swap `request`.
+    fn executed_swap_events(request: &ExecuteSwapRequest) -> [EventType; 3] {
+        let withdrawal_id = request.native_ledger_burn_index;
+        let transaction = create_transaction(
+            &WithdrawalRequest::Swap(request.clone()),
+            TransactionNonce::ZERO,
+            GasFeeEstimate {
+                base_fee_per_gas: WeiPerGas::ONE,
+                max_priority_fee_per_gas: WeiPerGas::ONE,
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+            },
+            request.gas_estimate,
+            EvmNetwork::Sepolia,
+        )
+        .expect("BUG: failed to create transaction");
+        let signed_tx = SignedEip1559TransactionRequest::from((
+            transaction.clone(),
+            Eip1559Signature {
+                signature_y_parity: false,
+                r: Default::default(),
+                s: Default::default(),
+            },
+        ));
+        let receipt = TransactionReceipt {
+            block_hash: "0xce67a85c9fb8bc50213815c32814c159fd75160acf7cb8631e8e7b7cf7f1d472"
+                .parse()
+                .unwrap(),
+            block_number: BlockNumber::new(5_540_000),
+            effective_gas_price: WeiPerGas::ONE,
+            gas_used: GasAmount::from(21_000_u32),
+            status: TransactionStatus::Success,
+            transaction_hash: signed_tx.hash(),
         };
-        expect_panic_with_message(
-            || {
-                apply_state_transition(
-                    &mut state,
-                    &AcceptedErc20WithdrawalRequest(erc20_withdrawal.clone()),
-                )
+
+        [
+            EventType::CreatedTransaction {
+                withdrawal_id,
+                transaction,
             },
-            "BUG: unsupported ERC-20",
-        );
+            EventType::SignedTransaction {
+                withdrawal_id,
+                transaction: signed_tx,
+            },
+            EventType::FinalizedTransaction {
+                withdrawal_id,
+                transaction_receipt: receipt,
+            },
+        ]
     }
 
     #[test]
-    fn should_add_deposit_to_erc20_balance() {
-        let mut state = initial_erc20_state();
-        let balance_before = state.erc20_balances.clone();
-
-        let deposit_event = received_erc20_event();
-        apply_state_transition(
-            &mut state,
-            &ReceivedContractEvent::from(deposit_event.clone()).into_event_type(),
+    fn should_record_refund_flow_transitions() {
+        let mut state = swap_enabled_state();
+        let swap_event = received_swap_event();
+        let event_source = ReceivedContractEvent::from(swap_event.clone()).source();
+        let tx_id = SwapTxId(SWAP_TX_ID.to_string());
+        let request = refund_swap_request();
+        assert_eq!(
+            state.allocate_swap_index(),
+            request.native_ledger_burn_index
         );
-        let balance_after = state.erc20_balances.clone();
+        let [created, signed, finalized] = executed_swap_events(&request);
+
+        let events = [
+            ReceivedContractEvent::from(swap_event).into_event_type(),
+            EventType::MintedToAppicDex {
+                event_source,
+                mint_block_index: LedgerMintIndex::new(12),
+                minted_token: twin_usdc_ledger_id(),
+                erc20_contract_address: usdc_address(),
+                tx_id: tx_id.clone(),
+            },
+            EventType::NotifiedSwapEventOrderToAppicDex {
+                event_source,
+                tx_id: tx_id.clone(),
+            },
+            EventType::AcceptedSwapRequest(request),
+            created,
+            signed,
+            finalized,
+        ];
+        for (timestamp, event) in (1_u64..).zip(events.iter()) {
+            apply_state_transition(&mut state, event);
+            record_swap_status_transitions(&mut state, event, timestamp);
+        }
 
+        let history = state
+            .withdrawal_transactions
+            .get_swap_status_history(&tx_id);
+        let (timestamps, statuses): (Vec<_>, Vec<_>) = history.into_iter().unzip();
+
+        assert_eq!(timestamps, vec![2, 3, 4, 5, 6, 7]);
+        assert!(timestamps.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(statuses.len(), 6);
         assert_eq!(
-            checked_sub(balance_after, balance_before),
-            btreemap! {
-                deposit_event.erc20_contract_address => deposit_event.value
-            }
+            statuses[0],
+            SwapStatus::MintedToAppicDex(SWAP_TX_ID.to_string())
+        );
+        assert_eq!(
+            statuses[1],
+            SwapStatus::NotifiedAppicDex(SWAP_TX_ID.to_string())
+        );
+        assert_matches!(&statuses[2], SwapStatus::PendingRefundSwap(details) if details.tx_id == SWAP_TX_ID);
+        assert_matches!(&statuses[3], SwapStatus::RefundSwapTxCreated(details) if details.is_refund);
+        assert_matches!(&statuses[4], SwapStatus::RefundSwapTxSent(_));
+        assert_matches!(
+            &statuses[5],
+            SwapStatus::RefundSwapTxFinalized(TxFinalizedStatus::Success { .. })
         );
     }
+
     #[test]
-    fn should_ignore_rejected_deposit() {
-        let mut state = initial_erc20_state();
-        let balance_before = state.erc20_balances.clone();
+    fn should_record_swap_statuses_of_non_refund_request() {
+        let mut state = swap_enabled_state();
+        let tx_id = SwapTxId(SWAP_TX_ID.to_string());
+        let request = ExecuteSwapRequest {
+            is_refund: false,
+            ..refund_swap_request()
+        };
+        assert_eq!(
+            state.allocate_swap_index(),
+            request.native_ledger_burn_index
+        );
+        let [created, signed, finalized] = executed_swap_events(&request);
+
+        let events = [
+            EventType::AcceptedSwapRequest(request),
+            created,
+            signed,
+            finalized,
+        ];
+        for (timestamp, event) in (1_u64..).zip(events.iter()) {
+            apply_state_transition(&mut state, event);
+            record_swap_status_transitions(&mut state, event, timestamp);
+        }
 
-        let deposit_event = received_erc20_event();
-        apply_state_transition(
-            &mut state,
-            &EventType::InvalidEvent {
-                event_source: deposit_event.source(),
-                reason: "invalid principal".to_string(),
-            },
+        let statuses: Vec<_> = state
+            .withdrawal_transactions
+            .get_swap_status_history(&tx_id)
+            .into_iter()
+            .map(|(_, status)| status)
+            .collect();
+        assert_eq!(statuses.len(), 4);
+        assert_matches!(&statuses[0], SwapStatus::PendingSwap(details) if !details.is_refund);
+        assert_matches!(&statuses[1], SwapStatus::SwapTxCreated(details) if !details.is_refund);
+        assert_matches!(&statuses[2], SwapStatus::SwapTxSent(_));
+        assert_matches!(
+            &statuses[3],
+            SwapStatus::SwapTxFinalized(TxFinalizedStatus::Success { .. })
         );
-        let balance_after = state.erc20_balances.clone();
+    }
 
-        assert_eq!(balance_after, balance_before);
+    #[test]
+    fn should_record_realized_amount_out_of_finalized_swap() {
+        let mut state = swap_enabled_state();
+        let tx_id = SwapTxId(SWAP_TX_ID.to_string());
+        let request = refund_swap_request();
+        let withdrawal_id = request.native_ledger_burn_index;
+        assert_eq!(state.allocate_swap_index(), withdrawal_id);
+        let [created, signed, finalized] = executed_swap_events(&request);
+
+        let events = [
+            EventType::AcceptedSwapRequest(request),
+            created,
+            signed,
+            EventType::RealizedSwapAmountOut {
+                withdrawal_id,
+                amount_out: Erc20Value::from(990_000_u64),
+            },
+            finalized,
+        ];
+        for (timestamp, event) in (1_u64..).zip(events.iter()) {
+            apply_state_transition(&mut state, event);
+            record_swap_status_transitions(&mut state, event, timestamp);
+        }
+
+        let expected_amount_out = Some(Nat::from(990_000_u64));
+        assert_matches!(
+            state.withdrawal_transactions.transaction_status(&withdrawal_id),
+            RetrieveWithdrawalStatus::TxFinalized(TxFinalizedStatus::Success { realized_amount_out, .. })
+                if realized_amount_out == expected_amount_out
+        );
+        let history = state
+            .withdrawal_transactions
+            .get_swap_status_history(&tx_id);
+        // the amount out is recorded before the transaction is finalized and shows up in the same transition
+        assert_eq!(history.len(), 4);
+        assert_matches!(
+            &history[3],
+            (5, SwapStatus::RefundSwapTxFinalized(TxFinalizedStatus::Success { realized_amount_out, .. }))
+                if *realized_amount_out == expected_amount_out
+        );
     }
-}
-fn initial_erc20_state() -> State {
-    let mut state = initial_state();
-    add_erc20_token(&mut state);
-    state
-}
 
-fn add_erc20_token(state: &mut State) {
-    use crate::state::ERC20Token;
+    #[test]
+    fn should_find_status_of_processed_swap_after_restoring_snapshot() {
+        use crate::state::snapshot::StateSnapshot;
+
+        let mut state = swap_enabled_state();
+        let tx_id = SwapTxId(SWAP_TX_ID.to_string());
+        let request = refund_swap_request();
+        assert_eq!(
+            state.allocate_swap_index(),
+            request.native_ledger_burn_index
+        );
+        let [created, signed, _finalized] = executed_swap_events(&request);
+        for event in [EventType::AcceptedSwapRequest(request), created, signed] {
+            apply_state_transition(&mut state, &event);
+        }
+        assert_matches!(
+            state.get_swap_status_by_tx_id(&tx_id),
+            Some(SwapStatus::RefundSwapTxSent(_))
+        );
+
+        let restored = State::from(StateSnapshot::from(&state));
+
+        assert_eq!(
+            restored.get_swap_status_by_tx_id(&tx_id),
+            state.get_swap_status_by_tx_id(&tx_id)
+        );
+        assert_eq!(
+            restored
+                .withdrawal_transactions
+                .is_equivalent_to(&state.withdrawal_transactions),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn should_keep_refund_deadline_of_created_transaction() {
+        let mut state = swap_enabled_state();
+        let request = refund_swap_request();
+        let withdrawal_id = request.native_ledger_burn_index;
+        assert_eq!(state.allocate_swap_index(), withdrawal_id);
+        let deadline = Erc20Value::from(1_741_360_000_u64);
+        let [created, signed, finalized] = executed_swap_events(&ExecuteSwapRequest {
+            deadline,
+            ..request.clone()
+        });
+        let failed = match finalized {
+            EventType::FinalizedTransaction {
+                withdrawal_id,
+                mut transaction_receipt,
+            } => {
+                transaction_receipt.status = TransactionStatus::Failure;
+                EventType::FinalizedTransaction {
+                    withdrawal_id,
+                    transaction_receipt,
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        for event in [EventType::AcceptedSwapRequest(request), created, signed] {
+            apply_state_transition(&mut state, &event);
+        }
+        assert_matches!(
+            state
+                .withdrawal_transactions
+                .get_processed_withdrawal_request(&withdrawal_id),
+            Some(WithdrawalRequest::Swap(processed)) if processed.deadline == deadline
+        );
+
+        apply_state_transition(&mut state, &failed);
+        assert_matches!(
+            state.withdrawal_transactions.failed_swap_requests().as_slice(),
+            [(tx_id, failed_refund)] if tx_id == SWAP_TX_ID && failed_refund.deadline == deadline
+        );
+    }
+
+    #[test]
+    fn should_not_record_unchanged_status() {
+        let mut state = swap_enabled_state();
+        let tx_id = SwapTxId(SWAP_TX_ID.to_string());
+
+        state.record_swap_status_transition(&tx_id, None, 1);
+        assert!(state
+            .withdrawal_transactions
+            .get_swap_status_history(&tx_id)
+            .is_empty());
+
+        let request = EventType::AcceptedSwapRequest(refund_swap_request());
+        state.allocate_swap_index();
+        apply_state_transition(&mut state, &request);
+        record_swap_status_transitions(&mut state, &request, 2);
+        state.record_swap_status_transition(&tx_id, None, 3);
+
+        assert_eq!(
+            state
+                .withdrawal_transactions
+                .get_swap_status_history(&tx_id)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn should_bound_history_length() {
+        use crate::state::transactions::MAX_SWAP_STATUS_HISTORY_LENGTH;
+
+        let mut state = swap_enabled_state();
+        let tx_id = SwapTxId(SWAP_TX_ID.to_string());
+        for timestamp in 0..(MAX_SWAP_STATUS_HISTORY_LENGTH as u64 + 5) {
+            let status = if timestamp % 2 == 0 {
+                SwapStatus::AcceptedSwap
+            } else {
+                SwapStatus::QuarantinedSwap
+            };
+            state
+                .withdrawal_transactions
+                .record_swap_status(SWAP_TX_ID, timestamp, status);
+        }
+
+        let history = state
+            .withdrawal_transactions
+            .get_swap_status_history(&tx_id);
+        assert_eq!(history.len(), MAX_SWAP_STATUS_HISTORY_LENGTH);
+        assert_eq!(history.first().map(|(t, _)| *t), Some(5));
+    }
+}
+
+mod upgrade_grace_scrape {
+    use crate::contract_logs::ReceivedContractEvent;
+    use crate::deposit::{upgrade_grace_scrape_range, POST_UPGRADE_GRACE_BLOCKS};
+    use crate::numeric::BlockNumber;
+    use crate::state::audit::apply_state_transition;
+    use crate::state::event::EventType;
+    use crate::state::tests::{initial_state, received_deposit_event};
+
+    #[test]
+    fn should_rewind_last_scraped_block_after_upgrade() {
+        let mut state = initial_state();
+        state.last_scraped_block_number = BlockNumber::from(1_000_u64);
+
+        let (start_block, end_block) = upgrade_grace_scrape_range(state.last_scraped_block_number)
+            .expect("a grace range should exist");
+        assert_eq!(
+            start_block,
+            BlockNumber::from(1_000_u64 - POST_UPGRADE_GRACE_BLOCKS)
+        );
+        assert_eq!(end_block, BlockNumber::from(1_000_u64));
+
+        apply_state_transition(
+            &mut state,
+            &EventType::UpgradeGraceScrape {
+                start_block,
+                end_block,
+            },
+        );
+        assert_eq!(state.last_scraped_block_number, start_block);
+    }
+
+    #[test]
+    fn should_saturate_grace_range_at_genesis() {
+        assert_eq!(upgrade_grace_scrape_range(BlockNumber::ZERO), None);
+        assert_eq!(
+            upgrade_grace_scrape_range(BlockNumber::from(42_u64)),
+            Some((BlockNumber::ZERO, BlockNumber::from(42_u64)))
+        );
+    }
+
+    #[test]
+    fn should_recognize_events_scraped_again_during_grace_period() {
+        let mut state = initial_state();
+        let event = ReceivedContractEvent::NativeDeposit(received_deposit_event());
+        assert!(!state.is_known_event(&event));
+
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedDeposit(received_deposit_event()),
+        );
+        assert!(state.is_known_event(&event));
+
+        let balance_before = state.native_balance.clone();
+        state.last_scraped_block_number = BlockNumber::from(1_000_u64);
+        apply_state_transition(
+            &mut state,
+            &EventType::UpgradeGraceScrape {
+                start_block: BlockNumber::from(900_u64),
+                end_block: BlockNumber::from(1_000_u64),
+            },
+        );
+        assert!(state.is_known_event(&event));
+        assert_eq!(state.native_balance, balance_before);
+    }
+}
+
+mod scraping_gaps {
+    use crate::numeric::BlockNumber;
+    use crate::state::audit::{apply_state_transition, record_scraping_gaps};
+    use crate::state::event::EventType;
+    use crate::state::scraping_gaps::{ScrapingGap, ScrapingGaps};
+    use crate::state::tests::initial_state;
+
+    fn gap(from_block: u64, to_block: u64, skipped_at: u64) -> ScrapingGap {
+        ScrapingGap {
+            from_block: BlockNumber::from(from_block),
+            to_block: BlockNumber::from(to_block),
+            skipped_at,
+        }
+    }
+
+    fn gaps_from(ranges: &[(u64, u64, u64)]) -> ScrapingGaps {
+        let mut gaps = ScrapingGaps::default();
+        for (from_block, to_block, skipped_at) in ranges {
+            gaps.insert(
+                BlockNumber::from(*from_block),
+                BlockNumber::from(*to_block),
+                *skipped_at,
+            );
+        }
+        gaps
+    }
+
+    #[test]
+    fn should_keep_disjoint_gaps_sorted() {
+        let gaps = gaps_from(&[(20, 25, 2), (5, 5, 1), (10, 12, 3)]);
+
+        assert_eq!(
+            gaps.iter().cloned().collect::<Vec<_>>(),
+            vec![gap(5, 5, 1), gap(10, 12, 3), gap(20, 25, 2)]
+        );
+        assert_eq!(gaps.total_blocks(), 1 + 3 + 6);
+    }
+
+    #[test]
+    fn should_merge_adjacent_and_overlapping_gaps() {
+        let gaps = gaps_from(&[(10, 10, 3), (11, 11, 1), (9, 9, 2)]);
+        assert_eq!(
+            gaps.iter().cloned().collect::<Vec<_>>(),
+            vec![gap(9, 11, 1)]
+        );
+
+        let gaps = gaps_from(&[(1, 3, 5), (10, 12, 4), (2, 11, 6)]);
+        assert_eq!(
+            gaps.iter().cloned().collect::<Vec<_>>(),
+            vec![gap(1, 12, 4)]
+        );
+    }
+
+    #[test]
+    fn should_clear_gaps_and_split_partially_rescraped_ranges() {
+        let mut gaps = gaps_from(&[(1, 3, 1), (10, 20, 2), (30, 30, 3)]);
+
+        assert!(gaps.clear(BlockNumber::from(12_u64), BlockNumber::from(15_u64)));
+        assert_eq!(
+            gaps.iter().cloned().collect::<Vec<_>>(),
+            vec![gap(1, 3, 1), gap(10, 11, 2), gap(16, 20, 2), gap(30, 30, 3)]
+        );
+
+        assert!(gaps.clear(BlockNumber::from(0_u64), BlockNumber::from(10_u64)));
+        assert!(gaps.clear(BlockNumber::from(16_u64), BlockNumber::from(30_u64)));
+        assert_eq!(
+            gaps.iter().cloned().collect::<Vec<_>>(),
+            vec![gap(11, 11, 2)]
+        );
+
+        assert!(!gaps.clear(BlockNumber::from(12_u64), BlockNumber::from(100_u64)));
+        assert!(gaps.clear(BlockNumber::from(11_u64), BlockNumber::from(11_u64)));
+        assert!(gaps.is_empty());
+    }
+
+    #[test]
+    fn should_rebuild_gaps_from_events() {
+        let mut state = initial_state();
+        let events = [
+            (
+                EventType::SkippedBlock {
+                    block_number: BlockNumber::from(7_u64),
+                },
+                1,
+            ),
+            (
+                EventType::SkippedBlock {
+                    block_number: BlockNumber::from(8_u64),
+                },
+                2,
+            ),
+            (
+                EventType::SkippedBlock {
+                    block_number: BlockNumber::from(50_u64),
+                },
+                3,
+            ),
+            (
+                EventType::RescrapedBlockRange {
+                    from_block: BlockNumber::from(40_u64),
+                    to_block: BlockNumber::from(60_u64),
+                },
+                4,
+            ),
+            (
+                EventType::SkippedBlockRange {
+                    from_block: BlockNumber::from(9_u64),
+                    to_block: BlockNumber::from(20_u64),
+                },
+                5,
+            ),
+            (
+                EventType::SkippedBlockRange {
+                    from_block: BlockNumber::from(70_u64),
+                    to_block: BlockNumber::from(80_u64),
+                },
+                6,
+            ),
+        ];
+        for (event, timestamp) in events {
+            apply_state_transition(&mut state, &event);
+            record_scraping_gaps(&mut state, &event, timestamp);
+        }
+
+        assert_eq!(
+            state.scraping_gaps.iter().cloned().collect::<Vec<_>>(),
+            vec![gap(7, 20, 1), gap(70, 80, 6)]
+        );
+        assert_eq!(state.skipped_blocks.len(), 3);
+    }
+}
+
+mod confirmation_policy {
+    use crate::candid_types::{CandidBlockTag, CandidConfirmationPolicy};
+    use crate::deposit::apply_safe_threshold_to_latest_block_numner;
+    use crate::evm_config::{ConfirmationPolicy, EvmNetwork, MAX_CONFIRMATIONS};
+    use crate::lifecycle::UpgradeArg;
+    use crate::numeric::BlockNumber;
+    use crate::rpc_declarations::BlockTag;
+    use crate::state::audit::apply_state_transition;
+    use crate::state::event::EventType;
+    use crate::state::tests::initial_state;
+    use crate::state::InvalidStateError;
+
+    #[test]
+    fn should_reject_safe_block_tag_on_bsc() {
+        let mut state = initial_state();
+        state.evm_network = EvmNetwork::BSC;
+
+        assert!(matches!(
+            state.validate_upgrade_arg(&UpgradeArg {
+                block_height: Some(CandidBlockTag::Safe),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidConfirmationPolicy(_))
+        ));
+        assert_eq!(
+            state.validate_upgrade_arg(&UpgradeArg {
+                block_height: Some(CandidBlockTag::Finalized),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn should_migrate_safe_block_tag_to_confirmations_on_bsc() {
+        let safe = ConfirmationPolicy::BlockTag(BlockTag::Safe);
+        let migrated = ConfirmationPolicy::Confirmations(12);
+        assert_eq!(safe.migrated(EvmNetwork::BSC), Some(migrated));
+        assert_eq!(safe.migrated(EvmNetwork::BSCTestnet), Some(migrated));
+        assert_eq!(safe.migrated(EvmNetwork::Ethereum), None);
+        assert_eq!(
+            ConfirmationPolicy::BlockTag(BlockTag::Finalized).migrated(EvmNetwork::BSC),
+            None
+        );
+
+        // a minter configured with the safe tag replays it, the migration is recorded on upgrade
+        let mut state = initial_state();
+        state.evm_network = EvmNetwork::BSC;
+        state
+            .upgrade(UpgradeArg {
+                block_height: Some(CandidBlockTag::Safe),
+                ..Default::default()
+            })
+            .expect("upgrade events are replayed without validation");
+        assert_eq!(state.confirmation_policy, safe);
+
+        apply_state_transition(
+            &mut state,
+            &EventType::ChangedConfirmationPolicy {
+                from: safe.into(),
+                to: migrated.into(),
+            },
+        );
+        assert_eq!(state.confirmation_policy, migrated);
+        assert_eq!(state.block_height(), BlockTag::Latest);
+        assert_eq!(state.confirmation_policy.migrated(EvmNetwork::BSC), None);
+    }
+
+    #[test]
+    fn should_keep_safe_block_tag_on_ethereum() {
+        let mut state = initial_state();
+        state.evm_network = EvmNetwork::Ethereum;
+
+        state
+            .upgrade(UpgradeArg {
+                block_height: Some(CandidBlockTag::Safe),
+                ..Default::default()
+            })
+            .expect("valid upgrade args");
+
+        assert_eq!(
+            state.confirmation_policy,
+            ConfirmationPolicy::BlockTag(BlockTag::Safe)
+        );
+    }
+
+    #[test]
+    fn should_set_confirmations_on_upgrade() {
+        let mut state = initial_state();
+
+        state
+            .upgrade(UpgradeArg {
+                confirmations: Some(20),
+                ..Default::default()
+            })
+            .expect("valid upgrade args");
+
+        assert_eq!(
+            state.confirmation_policy,
+            ConfirmationPolicy::Confirmations(20)
+        );
+    }
+
+    #[test]
+    fn should_reject_block_height_together_with_confirmations() {
+        let state = initial_state();
+
+        assert!(matches!(
+            state.validate_upgrade_arg(&UpgradeArg {
+                block_height: Some(CandidBlockTag::Finalized),
+                confirmations: Some(12),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidConfirmationPolicy(_))
+        ));
+    }
+
+    #[test]
+    fn should_reject_too_many_confirmations() {
+        let state = initial_state();
+
+        assert!(matches!(
+            state.validate_upgrade_arg(&UpgradeArg {
+                confirmations: Some(MAX_CONFIRMATIONS + 1),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidConfirmationPolicy(_))
+        ));
+        assert_eq!(
+            state.validate_upgrade_arg(&UpgradeArg {
+                confirmations: Some(MAX_CONFIRMATIONS),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn should_delay_deposit_until_enough_confirmations() {
+        let deposit_block = BlockNumber::from(1_000_u64);
+        let policy = ConfirmationPolicy::Confirmations(12);
+
+        let confirmed_up_to = |latest_block: u64| {
+            apply_safe_threshold_to_latest_block_numner(
+                EvmNetwork::BSC,
+                policy,
+                BlockNumber::from(latest_block),
+            )
+        };
+
+        assert!(confirmed_up_to(1_011) < deposit_block);
+        assert!(confirmed_up_to(1_012) >= deposit_block);
+        assert_eq!(confirmed_up_to(5), BlockNumber::ZERO);
+    }
+
+    #[test]
+    fn should_apply_changed_confirmation_policy() {
+        let mut state = initial_state();
+
+        apply_state_transition(
+            &mut state,
+            &EventType::ChangedConfirmationPolicy {
+                from: state.confirmation_policy.into(),
+                to: CandidConfirmationPolicy::Confirmations(30),
+            },
+        );
+
+        assert_eq!(
+            state.confirmation_policy,
+            ConfirmationPolicy::Confirmations(30)
+        );
+    }
+}
+
+mod gas_limits {
+    use crate::evm_config::{
+        EvmNetwork, GasLimitKind, GasLimits, ERC20_APPROVAL_TRANSACTION_GAS_LIMIT,
+        ERC20_MINT_TRANSACTION_GAS_LIMIT, ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT, MAX_GAS_LIMIT,
+        MIN_GAS_LIMIT, NATIVE_WITHDRAWAL_TO_CONTRACT_TRANSACTION_GAS_LIMIT,
+        NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT, REFUND_FAILED_SWAP_GAS_LIMIT,
+    };
+    use crate::lifecycle::{GasLimitArg, UpgradeArg};
+    use crate::numeric::GasAmount;
+    use crate::state::tests::initial_state;
+    use crate::state::InvalidStateError;
+    use candid::Nat;
+
+    fn gas_limit_arg(kind: GasLimitKind, gas_limit: u64) -> GasLimitArg {
+        GasLimitArg {
+            kind,
+            gas_limit: Nat::from(gas_limit),
+        }
+    }
+
+    #[test]
+    fn should_resolve_default_gas_limit_per_network() {
+        for network in [
+            EvmNetwork::Ethereum,
+            EvmNetwork::Sepolia,
+            EvmNetwork::BSC,
+            EvmNetwork::Base,
+        ] {
+            let gas_limits = GasLimits::default();
+            assert_eq!(
+                gas_limits.get(network, GasLimitKind::NativeWithdrawal),
+                NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT
+            );
+            assert_eq!(
+                gas_limits.get(network, GasLimitKind::Erc20Withdrawal),
+                ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT
+            );
+            assert_eq!(
+                gas_limits.get(network, GasLimitKind::Erc20Approval),
+                ERC20_APPROVAL_TRANSACTION_GAS_LIMIT
+            );
+            assert_eq!(
+                gas_limits.get(network, GasLimitKind::WrappedIcrcMint),
+                ERC20_MINT_TRANSACTION_GAS_LIMIT
+            );
+            assert_eq!(
+                gas_limits.get(network, GasLimitKind::RefundFailedSwap),
+                REFUND_FAILED_SWAP_GAS_LIMIT
+            );
+            assert_eq!(
+                gas_limits.get(network, GasLimitKind::NativeWithdrawalToContract),
+                NATIVE_WITHDRAWAL_TO_CONTRACT_TRANSACTION_GAS_LIMIT
+            );
+        }
+    }
+
+    #[test]
+    fn should_resolve_higher_gas_limits_on_arbitrum() {
+        let gas_limits = GasLimits::default();
+
+        for (kind, expected) in [
+            (GasLimitKind::NativeWithdrawal, 100_000_u64),
+            (GasLimitKind::Erc20Withdrawal, 200_000),
+            (GasLimitKind::Erc20Approval, 200_000),
+            (GasLimitKind::WrappedIcrcMint, 250_000),
+            (GasLimitKind::RefundFailedSwap, 300_000),
+            (GasLimitKind::NativeWithdrawalToContract, 150_000),
+        ] {
+            assert_eq!(
+                gas_limits.get(EvmNetwork::ArbitrumOne, kind),
+                GasAmount::from(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn should_override_gas_limit_on_upgrade() {
+        let mut state = initial_state();
+        assert_eq!(
+            state.gas_limit(GasLimitKind::Erc20Withdrawal),
+            ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT
+        );
+
+        state
+            .upgrade(UpgradeArg {
+                gas_limits: Some(vec![gas_limit_arg(GasLimitKind::Erc20Withdrawal, 90_000)]),
+                ..Default::default()
+            })
+            .expect("valid upgrade args");
+
+        assert_eq!(
+            state.gas_limit(GasLimitKind::Erc20Withdrawal),
+            GasAmount::from(90_000_u64)
+        );
+        assert_eq!(
+            state.gas_limit(GasLimitKind::NativeWithdrawal),
+            NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT
+        );
+    }
+
+    #[test]
+    fn should_keep_gas_limit_of_accepted_erc20_withdrawal_after_override() {
+        use crate::state::tests::erc20_withdrawal_request;
+        use crate::state::transactions::{Erc20WithdrawalRequest, WithdrawalRequest};
+        use crate::withdraw::estimate_gas_limit;
+
+        let mut state = initial_state();
+        let accepted: WithdrawalRequest = Erc20WithdrawalRequest {
+            gas_limit: Some(ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT),
+            ..erc20_withdrawal_request()
+        }
+        .into();
+        let accepted_before_gas_limit_was_recorded: WithdrawalRequest =
+            erc20_withdrawal_request().into();
+
+        state
+            .upgrade(UpgradeArg {
+                gas_limits: Some(vec![gas_limit_arg(GasLimitKind::Erc20Withdrawal, 90_000)]),
+                ..Default::default()
+            })
+            .expect("valid upgrade args");
+
+        for request in [accepted, accepted_before_gas_limit_was_recorded] {
+            assert_eq!(
+                estimate_gas_limit(&request, state.evm_network, &state.gas_limits),
+                ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT
+            );
+        }
+        let new_request: WithdrawalRequest = Erc20WithdrawalRequest {
+            gas_limit: Some(state.gas_limit(GasLimitKind::Erc20Withdrawal)),
+            ..erc20_withdrawal_request()
+        }
+        .into();
+        assert_eq!(
+            estimate_gas_limit(&new_request, state.evm_network, &state.gas_limits),
+            GasAmount::from(90_000_u64)
+        );
+    }
+
+    #[test]
+    fn should_reject_gas_limit_out_of_bounds() {
+        let state = initial_state();
+        let min = MIN_GAS_LIMIT.into_inner().as_u64();
+        let max = MAX_GAS_LIMIT.into_inner().as_u64();
+
+        for gas_limit in [0, min - 1, max + 1] {
+            assert!(matches!(
+                state.validate_upgrade_arg(&UpgradeArg {
+                    gas_limits: Some(vec![gas_limit_arg(
+                        GasLimitKind::NativeWithdrawal,
+                        gas_limit
+                    )]),
+                    ..Default::default()
+                }),
+                Err(InvalidStateError::InvalidGasLimit(_))
+            ));
+        }
+        for gas_limit in [min, max] {
+            assert_eq!(
+                state.validate_upgrade_arg(&UpgradeArg {
+                    gas_limits: Some(vec![gas_limit_arg(
+                        GasLimitKind::NativeWithdrawal,
+                        gas_limit
+                    )]),
+                    ..Default::default()
+                }),
+                Ok(())
+            );
+        }
+    }
+}
+
+mod refund_deadline {
+    use crate::candid_types::dex_orders::DEFAULT_REFUND_DEADLINE_WINDOW_SECS;
+    use crate::lifecycle::UpgradeArg;
+    use crate::numeric::Erc20Value;
+    use crate::state::tests::initial_state;
+    use crate::state::InvalidStateError;
+    use assert_matches::assert_matches;
+
+    const NOW_NS: u64 = 1_899_990_000_123_456_789;
+
+    #[test]
+    fn should_expire_refunds_after_window() {
+        let mut state = initial_state();
+        assert_eq!(
+            state.refund_deadline(NOW_NS),
+            Erc20Value::from(1_899_990_000 + DEFAULT_REFUND_DEADLINE_WINDOW_SECS)
+        );
+
+        state
+            .upgrade(UpgradeArg {
+                refund_deadline_window_secs: Some(30 * 60),
+                ..Default::default()
+            })
+            .expect("valid upgrade args");
+        assert_eq!(
+            state.refund_deadline(NOW_NS),
+            Erc20Value::from(1_899_990_000 + 30 * 60_u64)
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_refund_deadline_window() {
+        let state = initial_state();
+        for window in [0, 60, 24 * 60 * 60 + 1, u64::MAX] {
+            assert_matches!(
+                state.validate_upgrade_arg(&UpgradeArg {
+                    refund_deadline_window_secs: Some(window),
+                    ..Default::default()
+                }),
+                Err(InvalidStateError::InvalidRefundDeadlineWindow(_))
+            );
+        }
+    }
+}
+
+mod reimbursement_request_migration {
+    use crate::numeric::{Erc20TokenAmount, LedgerBurnIndex};
+    use crate::rpc_declarations::Hash;
+    use crate::state::event::{Event, EventType};
+    use crate::state::transactions::{
+        LegacyReimbursementRequest, PendingReimbursementRequest, ReimbursementRequest,
+    };
+
+    fn pending_request() -> PendingReimbursementRequest {
+        PendingReimbursementRequest {
+            ledger_burn_index: LedgerBurnIndex::new(7),
+            reimbursed_amount: Erc20TokenAmount::new(1_000_000),
+            to: "ezu3d-2mifu-k3bh4-oqhrj-mbrql-5p67r-pp6pr-dbfra-unkx5-sxdtv-rae"
+                .parse()
+                .unwrap(),
+            to_subaccount: None,
+        }
+    }
+
+    fn legacy_request(transaction_hash: Option<Hash>) -> LegacyReimbursementRequest {
+        let request = pending_request();
+        LegacyReimbursementRequest {
+            ledger_burn_index: request.ledger_burn_index,
+            reimbursed_amount: request.reimbursed_amount,
+            to: request.to,
+            to_subaccount: request.to_subaccount,
+            transaction_hash,
+        }
+    }
+
+    fn transaction_hash() -> Hash {
+        "0x06afc3c693dc2ba2c19b5c287c4dddce040d766bea5fd13c8a7268b04aa94f2d"
+            .parse()
+            .unwrap()
+    }
+
+    // Encodes `event` as it would have been recorded with the legacy reimbursement layout.
+    fn encode_with_legacy_request(event: &Event, legacy: &LegacyReimbursementRequest) -> Vec<u8> {
+        let encoded_event = minicbor::to_vec(event).unwrap();
+        let encoded_request = minicbor::to_vec(pending_request()).unwrap();
+        assert!(encoded_event.ends_with(&encoded_request));
+        let mut legacy_event =
+            encoded_event[..encoded_event.len() - encoded_request.len()].to_vec();
+        legacy_event.extend(minicbor::to_vec(legacy).unwrap());
+        legacy_event
+    }
+
+    #[test]
+    fn should_decode_legacy_failed_withdrawal_events() {
+        for payload in [
+            EventType::FailedErc20WithdrawalRequest(pending_request()),
+            EventType::FailedIcrcLockRequest(pending_request()),
+        ] {
+            let event = Event {
+                timestamp: 1_700_000_000_000_000_000,
+                payload,
+            };
+            for transaction_hash in [None, Some(transaction_hash())] {
+                let legacy_bytes =
+                    encode_with_legacy_request(&event, &legacy_request(transaction_hash));
+                let decoded: Event = minicbor::decode(&legacy_bytes).unwrap();
+                assert_eq!(decoded, event);
+            }
+        }
+    }
+
+    #[test]
+    fn should_encode_pending_request_as_legacy_request_without_hash() {
+        assert_eq!(
+            minicbor::to_vec(pending_request()).unwrap(),
+            minicbor::to_vec(legacy_request(None)).unwrap()
+        );
+    }
+
+    #[test]
+    fn should_promote_pending_request_to_finalized() {
+        let pending = pending_request();
+        let finalized = pending.clone().finalize(transaction_hash());
+
+        let request: ReimbursementRequest = finalized.into();
+        assert_eq!(request.ledger_burn_index(), pending.ledger_burn_index);
+        assert_eq!(request.reimbursed_amount(), pending.reimbursed_amount);
+        assert_eq!(request.to(), pending.to);
+        assert_eq!(request.transaction_hash(), Some(transaction_hash()));
+        assert_eq!(ReimbursementRequest::from(pending).transaction_hash(), None);
+    }
+}
+
+mod erc20_balance {
+    use crate::contract_logs::types::ReceivedErc20Event;
+    use crate::contract_logs::ReceivedContractEvent;
+    use crate::state::audit::EventType::AcceptedErc20WithdrawalRequest;
+    use crate::state::audit::{apply_state_transition, EventType};
+    use crate::state::tests::{
+        checked_sub, erc20_withdrawal_request, initial_erc20_state, received_deposit_event,
+        received_erc20_event,
+    };
+    use crate::state::transactions::Erc20WithdrawalRequest;
+    use crate::test_fixtures::expect_panic_with_message;
+    use evm_rpc_client::eth_types::Address;
+    use maplit::btreemap;
+
+    #[test]
+    fn should_panic_when_deposited_erc20_not_supported() {
+        let mut state = initial_erc20_state();
+        let unsupported_erc20_address: Address = "0x6b175474e89094c44da98b954eedeac495271d0f"
+            .parse()
+            .unwrap();
+        assert!(!state.erc20_tokens.contains_alt(&unsupported_erc20_address));
+
+        let deposit_event = ReceivedErc20Event {
+            erc20_contract_address: unsupported_erc20_address,
+            ..received_erc20_event()
+        };
+        expect_panic_with_message(
+            || {
+                apply_state_transition(
+                    &mut state,
+                    &ReceivedContractEvent::from(deposit_event.clone()).into_event_type(),
+                )
+            },
+            "BUG: unsupported ERC-20",
+        );
+    }
+
+    #[test]
+    fn should_panic_when_withdrawn_erc20_not_supported() {
+        let mut state = initial_erc20_state();
+        let unsupported_erc20_address: Address = "0x6b175474e89094c44da98b954eedeac495271d0f"
+            .parse()
+            .unwrap();
+        assert!(!state.erc20_tokens.contains_alt(&unsupported_erc20_address));
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedErc20Deposit(received_erc20_event()),
+        );
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedDeposit(received_deposit_event()),
+        );
+        let erc20_withdrawal = Erc20WithdrawalRequest {
+            erc20_contract_address: unsupported_erc20_address,
+            ..erc20_withdrawal_request()
+        };
+        expect_panic_with_message(
+            || {
+                apply_state_transition(
+                    &mut state,
+                    &AcceptedErc20WithdrawalRequest(erc20_withdrawal.clone()),
+                )
+            },
+            "BUG: unsupported ERC-20",
+        );
+    }
+
+    #[test]
+    fn should_add_deposit_to_erc20_balance() {
+        let mut state = initial_erc20_state();
+        let balance_before = state.erc20_balances.clone();
+
+        let deposit_event = received_erc20_event();
+        apply_state_transition(
+            &mut state,
+            &ReceivedContractEvent::from(deposit_event.clone()).into_event_type(),
+        );
+        let balance_after = state.erc20_balances.clone();
+
+        assert_eq!(
+            checked_sub(balance_after, balance_before),
+            btreemap! {
+                deposit_event.erc20_contract_address => deposit_event.value
+            }
+        );
+    }
+    #[test]
+    fn should_ignore_rejected_deposit() {
+        let mut state = initial_erc20_state();
+        let balance_before = state.erc20_balances.clone();
+
+        let deposit_event = received_erc20_event();
+        apply_state_transition(
+            &mut state,
+            &EventType::InvalidEvent {
+                event_source: deposit_event.source(),
+                reason: "invalid principal".to_string(),
+            },
+        );
+        let balance_after = state.erc20_balances.clone();
+
+        assert_eq!(balance_after, balance_before);
+    }
+}
+mod token_volume {
+    use crate::contract_logs::ReceivedContractEvent;
+    use crate::numeric::{Erc20Value, LedgerMintIndex};
+    use crate::rpc_declarations::TransactionStatus;
+    use crate::state::audit::{apply_state_transition, EventType};
+    use crate::state::balances::TokenVolume;
+    use crate::state::tests::native_balance::WithdrawalFlow;
+    use crate::state::tests::{
+        erc20_withdrawal_request, initial_erc20_state, received_deposit_event, received_erc20_event,
+    };
+    use crate::state::transactions::Reimbursed;
+
+    #[test]
+    fn should_update_volumes_through_deposit_withdrawal_and_reimbursement() {
+        let mut state = initial_erc20_state();
+        let deposit = received_erc20_event();
+        let token = deposit.erc20_contract_address;
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedDeposit(received_deposit_event()),
+        );
+        apply_state_transition(
+            &mut state,
+            &ReceivedContractEvent::from(deposit.clone()).into_event_type(),
+        );
+        assert_eq!(state.token_volumes.get(&token), None);
+
+        apply_state_transition(
+            &mut state,
+            &EventType::MintedErc20 {
+                event_source: deposit.source(),
+                mint_block_index: LedgerMintIndex::new(1),
+                erc20_token_symbol: "ckSepoliaUSDC".to_string(),
+                erc20_contract_address: token,
+            },
+        );
+        assert_eq!(
+            state.token_volumes.get(&token),
+            Some(&TokenVolume {
+                deposited: deposit.value,
+                ..TokenVolume::default()
+            })
+        );
+
+        let withdrawal_request = erc20_withdrawal_request();
+        let withdrawal_amount = withdrawal_request.withdrawal_amount;
+        WithdrawalFlow {
+            tx_status: TransactionStatus::Failure,
+            ..WithdrawalFlow::for_request(withdrawal_request.clone())
+        }
+        .apply(&mut state);
+        assert_eq!(
+            state.token_volumes.get(&token),
+            Some(&TokenVolume {
+                deposited: deposit.value,
+                withdrawn: withdrawal_amount,
+                reimbursed: Erc20Value::ZERO,
+            })
+        );
+
+        apply_state_transition(
+            &mut state,
+            &EventType::ReimbursedErc20Withdrawal {
+                native_ledger_burn_index: withdrawal_request.native_ledger_burn_index,
+                erc20_ledger_id: withdrawal_request.erc20_ledger_id,
+                reimbursed: Reimbursed {
+                    reimbursed_in_block: LedgerMintIndex::new(2),
+                    burn_in_block: withdrawal_request.erc20_ledger_burn_index,
+                    reimbursed_amount: withdrawal_amount.change_units(),
+                    transaction_hash: None,
+                    transfer_fee: None,
+                },
+            },
+        );
+        assert_eq!(
+            state.token_volumes.get(&token),
+            Some(&TokenVolume {
+                deposited: deposit.value,
+                withdrawn: withdrawal_amount,
+                reimbursed: withdrawal_amount,
+            })
+        );
+    }
+}
+
+fn initial_erc20_state() -> State {
+    let mut state = initial_state();
+    add_erc20_token(&mut state);
+    state
+}
+
+fn add_erc20_token(state: &mut State) {
+    use crate::state::ERC20Token;
     apply_state_transition(
         state,
         &EventType::AddedErc20Token(ERC20Token {
@@ -1816,58 +4022,2045 @@ fn add_erc20_token(state: &mut State) {
             erc20_contract_address: "0x1c7d4b196cb0c7b01d743fbc6116a902379c7238"
                 .parse()
                 .unwrap(),
-            erc20_token_symbol: "ckSepoliaUSDC".parse().unwrap(),
-            erc20_ledger_id: Principal::from_text("3sgad-taaaa-aaaar-qaedq-cai").unwrap(),
-        }),
-    );
-}
+            erc20_token_symbol: "ckSepoliaUSDC".parse().unwrap(),
+            erc20_ledger_id: Principal::from_text("3sgad-taaaa-aaaar-qaedq-cai").unwrap(),
+            transfer_kind: None,
+            original_symbol: None,
+        }),
+    );
+}
+
+fn erc20_withdrawal_request() -> Erc20WithdrawalRequest {
+    Erc20WithdrawalRequest {
+        max_transaction_fee: Wei::from(30_000_000_000_000_000_u64),
+        withdrawal_amount: Erc20Value::from(4_996_000_u64),
+        destination: "0xdd2851Cdd40aE6536831558DD46db62fAc7A844d"
+            .parse()
+            .unwrap(),
+        native_ledger_burn_index: LedgerBurnIndex::new(5),
+        erc20_contract_address: "0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238"
+            .parse()
+            .unwrap(),
+        erc20_ledger_id: Principal::from_text("3sgad-taaaa-aaaar-qaedq-cai").unwrap(),
+        erc20_ledger_burn_index: LedgerBurnIndex::new(2),
+        from: Principal::from_text(
+            "hkroy-sm7vs-yyjs7-ekppe-qqnwx-hm4zf-n7ybs-titsi-k6e3k-ucuiu-uqe",
+        )
+        .unwrap(),
+        from_subaccount: None,
+        created_at: 1_711_138_972_460_345_032,
+        l1_fee: Some(Wei::new(10_000_000)),
+        is_wrapped_mint: Some(false),
+        gas_limit: None,
+        withdrawal_fee: Some(Wei::new(5_000_000)),
+    }
+}
+
+fn checked_sub(lhs: Erc20Balances, rhs: Erc20Balances) -> BTreeMap<Address, Erc20Value> {
+    assert!(rhs
+                .balance_by_erc20_contract
+                .keys()
+                .all(|rhs_erc20_contract| {
+                    lhs.balance_by_erc20_contract
+                        .contains_key(rhs_erc20_contract)
+                }), "BUG: Cannot subtract rhs {rhs:?} to lhs {lhs:?} since some ERC-20 contracts are missing in the lhs");
+    let mut result = lhs.balance_by_erc20_contract.clone();
+    for (erc20_contract, rhs_value) in rhs.balance_by_erc20_contract.into_iter() {
+        match lhs.balance_by_erc20_contract.get(&erc20_contract).unwrap() {
+            lhs_value if lhs_value == &rhs_value => {
+                result.remove(&erc20_contract);
+            }
+            lhs_value if lhs_value > &rhs_value => {
+                result.insert(erc20_contract, lhs_value.checked_sub(rhs_value).unwrap());
+            }
+            lhs_value => panic!(
+                "BUG: Cannot subtract rhs {rhs_value:?} to lhs {lhs_value:?} since it would underflow"
+            ),
+        }
+    }
+    result
+}
+
+mod manual_deposit_check {
+    use crate::state::tests::initial_state;
+    use crate::{MANUAL_DEPOSIT_CHECK_GLOBAL_INTERVAL, MANUAL_DEPOSIT_CHECK_INTERVAL};
+    use candid::Principal;
+
+    const NOW: u64 = 1_700_000_000_000_000_000;
+
+    fn dex_canister() -> Principal {
+        Principal::from_text("nbepk-iyaaa-aaaad-qhlma-cai").unwrap()
+    }
+
+    fn other_user() -> Principal {
+        Principal::from_text("matbl-u2myk-jsllo-b5aw6-bxboq-7oon2-h6wmo-awsxf-pcebc-4wpgx-4qe")
+            .unwrap()
+    }
+
+    fn user() -> Principal {
+        Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
+            .unwrap()
+    }
+
+    fn interval() -> u64 {
+        MANUAL_DEPOSIT_CHECK_INTERVAL.as_nanos() as u64
+    }
+
+    fn global_interval() -> u64 {
+        MANUAL_DEPOSIT_CHECK_GLOBAL_INTERVAL.as_nanos() as u64
+    }
+
+    #[test]
+    fn should_rate_limit_each_caller() {
+        let mut state = initial_state();
+        assert!(state.can_trigger_manual_deposit_check(&other_user(), NOW));
+        assert!(state.can_trigger_manual_deposit_check(&user(), NOW));
+
+        state.record_manual_deposit_check(other_user(), NOW);
+
+        assert!(!state.can_trigger_manual_deposit_check(&other_user(), NOW + interval() - 1));
+        assert!(state.can_trigger_manual_deposit_check(&other_user(), NOW + interval()));
+        assert!(state.can_trigger_manual_deposit_check(&user(), NOW + global_interval()));
+    }
+
+    #[test]
+    fn should_rate_limit_all_callers_together() {
+        let mut state = initial_state();
+        state.record_manual_deposit_check(other_user(), NOW);
+
+        assert!(!state.can_trigger_manual_deposit_check(&user(), NOW));
+        assert!(!state.can_trigger_manual_deposit_check(&user(), NOW + global_interval() - 1));
+        assert!(state.can_trigger_manual_deposit_check(&user(), NOW + global_interval()));
+    }
+
+    #[test]
+    fn should_prune_expired_checks() {
+        let mut state = initial_state();
+        state.record_manual_deposit_check(other_user(), NOW);
+        state.record_manual_deposit_check(user(), NOW + 1);
+
+        state.record_manual_deposit_check(user(), NOW + interval() + 1);
+
+        assert_eq!(
+            state
+                .last_manual_deposit_checks
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![(user(), NOW + interval() + 1)]
+        );
+    }
+
+    #[test]
+    fn should_not_rate_limit_dex_canister() {
+        let mut state = initial_state();
+        state.dex_canister_id = Some(dex_canister());
+        state.record_manual_deposit_check(user(), NOW);
+
+        assert!(state.can_trigger_manual_deposit_check(&dex_canister(), NOW));
+        state.record_manual_deposit_check(dex_canister(), NOW);
+        assert!(state.can_trigger_manual_deposit_check(&dex_canister(), NOW + 1));
+
+        // the checks of the dex canister do not count against other callers
+        assert!(state.can_trigger_manual_deposit_check(&other_user(), NOW + global_interval()));
+        assert_eq!(
+            state
+                .last_manual_deposit_checks
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![(user(), NOW)]
+        );
+    }
+}
+
+mod provider_disagreement {
+    use crate::rpc_client::{ETH_GET_LOGS_METHOD, ETH_GET_TRANSACTION_RECEIPT_METHOD};
+    use crate::state::audit::apply_state_transition;
+    use crate::state::event::EventType;
+    use crate::state::tests::initial_state;
+    use crate::PROVIDER_DISAGREEMENT_INTERVAL;
+
+    const NOW: u64 = 1_700_000_000_000_000_000;
+
+    #[test]
+    fn should_record_at_most_one_disagreement_per_method_per_interval() {
+        let mut state = initial_state();
+        let interval = PROVIDER_DISAGREEMENT_INTERVAL.as_nanos() as u64;
+        assert!(state.can_record_provider_disagreement(ETH_GET_LOGS_METHOD, NOW));
+
+        apply_state_transition(
+            &mut state,
+            &EventType::ProviderDisagreement {
+                method: ETH_GET_LOGS_METHOD.to_string(),
+                block_range_or_hash: "100..=200".to_string(),
+                per_provider_hash: vec![],
+                timestamp: NOW,
+            },
+        );
+
+        assert!(!state.can_record_provider_disagreement(ETH_GET_LOGS_METHOD, NOW + interval - 1));
+        assert!(state.can_record_provider_disagreement(ETH_GET_LOGS_METHOD, NOW + interval));
+        assert!(state.can_record_provider_disagreement(ETH_GET_TRANSACTION_RECEIPT_METHOD, NOW));
+    }
+}
+
+mod rate_limited_providers {
+    use crate::rpc_client::providers::{Provider, DEFAULT_PROVIDERS};
+    use crate::state::audit::apply_state_transition;
+    use crate::state::event::EventType;
+    use crate::state::tests::initial_state;
+
+    const NOW: u64 = 1_700_000_000_000_000_000;
+
+    #[test]
+    fn should_skip_rate_limited_provider_until_retry_after() {
+        let mut state = initial_state();
+        assert_eq!(
+            state.available_providers(NOW, 2),
+            DEFAULT_PROVIDERS.to_vec()
+        );
+
+        apply_state_transition(
+            &mut state,
+            &EventType::ProviderRateLimited {
+                provider: Provider::Ankr,
+                retry_after_ns: NOW + 1,
+            },
+        );
+
+        assert_eq!(
+            state.available_providers(NOW, 2),
+            vec![Provider::PublicNode, Provider::DRPC, Provider::Alchemy]
+        );
+        assert_eq!(
+            state.available_providers(NOW + 1, 2),
+            DEFAULT_PROVIDERS.to_vec()
+        );
+        assert!(!state.should_record_rate_limited_provider(Provider::Ankr, NOW + 1));
+        assert!(state.should_record_rate_limited_provider(Provider::Ankr, NOW + 2));
+    }
+
+    #[test]
+    fn should_query_all_providers_when_too_many_are_rate_limited() {
+        let mut state = initial_state();
+        for provider in [Provider::Ankr, Provider::PublicNode, Provider::DRPC] {
+            apply_state_transition(
+                &mut state,
+                &EventType::ProviderRateLimited {
+                    provider,
+                    retry_after_ns: NOW + 1,
+                },
+            );
+        }
+
+        assert_eq!(state.available_providers(NOW, 1), vec![Provider::Alchemy]);
+        assert_eq!(
+            state.available_providers(NOW, 2),
+            DEFAULT_PROVIDERS.to_vec()
+        );
+    }
+}
+
+mod snapshot {
+    use super::*;
+    use crate::state::audit::replay_events_from;
+    use crate::state::snapshot::StateSnapshot;
+    use crate::state::transactions::{
+        create_transaction, NativeWithdrawalRequest, WithdrawalRequest,
+    };
+    use crate::state::ERC20Token;
+
+    fn events() -> Vec<Event> {
+        let erc20_deposit = received_erc20_event();
+        let withdrawal_request = NativeWithdrawalRequest {
+            withdrawal_amount: Wei::new(5_000_000_000_000_000),
+            destination: "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34"
+                .parse()
+                .unwrap(),
+            ledger_burn_index: LedgerBurnIndex::new(0),
+            from: "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae"
+                .parse()
+                .unwrap(),
+            from_subaccount: None,
+            created_at: Some(1_699_527_697_000_000_000),
+            l1_fee: None,
+            withdrawal_fee: None,
+            is_contract_destination: None,
+        };
+        let withdrawal_id = withdrawal_request.ledger_burn_index;
+        let transaction = create_transaction(
+            &WithdrawalRequest::Native(withdrawal_request.clone()),
+            TransactionNonce::ZERO,
+            GasFeeEstimate {
+                base_fee_per_gas: WeiPerGas::ONE,
+                max_priority_fee_per_gas: WeiPerGas::ONE,
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+            },
+            GasAmount::from(21_000_u32),
+            EvmNetwork::Sepolia,
+        )
+        .expect("BUG: failed to create transaction");
+        let signed_tx = SignedEip1559TransactionRequest::from((
+            transaction.clone(),
+            Eip1559Signature {
+                signature_y_parity: false,
+                r: Default::default(),
+                s: Default::default(),
+            },
+        ));
+        let failed_receipt = TransactionReceipt {
+            block_hash: "0xce67a85c9fb8bc50213815c32814c159fd75160acf7cb8631e8e7b7cf7f1d472"
+                .parse()
+                .unwrap(),
+            block_number: BlockNumber::new(4_190_269),
+            effective_gas_price: WeiPerGas::ONE,
+            gas_used: GasAmount::from(21_000_u32),
+            status: TransactionStatus::Failure,
+            transaction_hash: signed_tx.hash(),
+        };
+
+        let payloads = vec![
+            EventType::Init(initial_init_arg()),
+            EventType::AddedErc20Token(ERC20Token {
+                chain_id: Default::default(),
+                erc20_contract_address: erc20_deposit.erc20_contract_address,
+                erc20_token_symbol: "ckSepoliaUSDC".parse().unwrap(),
+                erc20_ledger_id: Principal::from_text("3sgad-taaaa-aaaar-qaedq-cai").unwrap(),
+                transfer_kind: None,
+                original_symbol: None,
+            }),
+            EventType::AcceptedDeposit(received_deposit_event()),
+            EventType::AcceptedErc20Deposit(erc20_deposit.clone()),
+            EventType::MintedNative {
+                event_source: received_deposit_event().source(),
+                mint_block_index: LedgerMintIndex::new(1),
+            },
+            EventType::MintedErc20 {
+                event_source: erc20_deposit.source(),
+                mint_block_index: LedgerMintIndex::new(2),
+                erc20_token_symbol: "ckSepoliaUSDC".to_string(),
+                erc20_contract_address: erc20_deposit.erc20_contract_address,
+            },
+            EventType::SkippedBlock {
+                block_number: BlockNumber::new(5_539_000),
+            },
+            EventType::SyncedToBlock {
+                block_number: BlockNumber::new(5_540_000),
+            },
+            EventType::AcceptedNativeWithdrawalRequest(withdrawal_request),
+            EventType::CreatedTransaction {
+                withdrawal_id,
+                transaction,
+            },
+            EventType::SignedTransaction {
+                withdrawal_id,
+                transaction: signed_tx,
+            },
+            EventType::FinalizedTransaction {
+                withdrawal_id,
+                transaction_receipt: failed_receipt,
+            },
+            EventType::AcceptedErc20WithdrawalRequest(erc20_withdrawal_request()),
+            EventType::ProviderDisagreement {
+                method: "eth_getLogs".to_string(),
+                block_range_or_hash: "5540000-5540100".to_string(),
+                per_provider_hash: vec![],
+                timestamp: 15,
+            },
+        ];
+        (1_u64..)
+            .zip(payloads)
+            .map(|(timestamp, payload)| Event { timestamp, payload })
+            .collect()
+    }
+
+    fn encode_decode(state: &State) -> State {
+        let bytes = StateSnapshot::from(state).encode_to_vec();
+        StateSnapshot::decode_from_slice(&bytes)
+            .expect("state snapshot should decode")
+            .into()
+    }
+
+    #[test]
+    fn should_replay_from_snapshot_as_from_init() {
+        let events = events();
+        let full_replay = replay_events_from(None, events.clone());
+
+        for archived in 1..=events.len() {
+            let archived_state = replay_events_from(None, events[..archived].to_vec());
+            let replay_from_snapshot = replay_events_from(
+                Some(encode_decode(&archived_state)),
+                events[archived..].to_vec(),
+            );
+
+            assert_eq!(
+                replay_from_snapshot, full_replay,
+                "replay diverged when archiving {archived} events"
+            );
+        }
+    }
+
+    #[test]
+    fn should_round_trip_replayed_state() {
+        let state = replay_events_from(None, events());
+        assert!(!state
+            .withdrawal_transactions
+            .reimbursement_requests
+            .is_empty());
+        assert!(!state.scraping_gaps.is_empty());
+
+        assert_eq!(encode_decode(&state), state);
+    }
+
+    #[test]
+    fn should_restore_known_erc20_contracts() {
+        let state = replay_events_from(None, events());
+        assert_eq!(
+            state.known_erc20_contracts,
+            BTreeSet::from([received_erc20_event().erc20_contract_address])
+        );
+
+        assert_eq!(
+            encode_decode(&state).known_erc20_contracts,
+            state.known_erc20_contracts
+        );
+    }
+
+    #[test]
+    fn should_reset_transient_fields() {
+        let mut state = replay_events_from(None, events());
+        let replayed = state.clone();
+        state.last_observed_block_number = Some(BlockNumber::new(5_540_100));
+        state.current_l1_fee = Some(Wei::ONE);
+        state
+            .pending_withdrawal_principals
+            .insert(Principal::management_canister());
+
+        assert_eq!(encode_decode(&state), replayed);
+    }
+}
+
+mod replay {
+    use crate::test_fixtures::replay::{
+        assert_deterministic_replay, event_sequence, missing_event_types, replay,
+    };
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn should_cover_every_event_type() {
+        assert_eq!(missing_event_types(&event_sequence()), BTreeSet::new());
+    }
+
+    #[test]
+    fn should_replay_deterministically() {
+        assert_deterministic_replay(&event_sequence());
+    }
+
+    #[test]
+    fn should_reach_every_terminal_state() {
+        let state = replay(&event_sequence());
+        let transactions = &state.withdrawal_transactions;
+
+        assert!(!state.minted_events.is_empty());
+        assert!(!state.released_events.is_empty());
+        assert!(!state.quarantined_releases.is_empty());
+        assert!(!state.invalid_events.is_empty());
+        assert!(!state.notified_swap_events.is_empty());
+        assert!(!state.quarantined_dex_orders.is_empty());
+        assert!(state.is_swapping_active());
+        assert!(!transactions.parked.is_empty());
+        assert!(!transactions.reimbursement_requests.is_empty());
+        assert_eq!(transactions.reimbursed.len(), 5);
+        assert!(!transactions.quarantined_swap_requests.is_empty());
+        assert!(!transactions.pending_withdrawal_requests.is_empty());
+    }
+}
+
+mod swap_index {
+    use crate::numeric::{Erc20Value, LedgerBurnIndex, Wei};
+    use crate::state::audit::{apply_state_transition, EventType};
+    use crate::state::tests::swap_status_history::{refund_swap_request, swap_enabled_state};
+    use crate::test_fixtures::expect_panic_with_message;
+
+    #[test]
+    fn should_allocate_distinct_swap_indices() {
+        let mut state = swap_enabled_state();
+        let first = state.allocate_swap_index();
+        let second = state.allocate_swap_index();
+
+        assert_eq!(first, LedgerBurnIndex::new(10_000_000_000_000_000_000_u64));
+        assert_eq!(second, LedgerBurnIndex::new(10_000_000_000_000_000_001_u64));
+        assert_eq!(
+            state.next_swap_ledger_burn_index,
+            Some(LedgerBurnIndex::new(10_000_000_000_000_000_002_u64))
+        );
+    }
+
+    #[test]
+    fn should_allocate_swap_index_when_releasing_gas_from_tank() {
+        let mut state = swap_enabled_state();
+        state.gas_tank.native_balance_add(Wei::new(1_000_000));
+
+        apply_state_transition(
+            &mut state,
+            &EventType::ReleasedGasFromGasTankWithUsdc {
+                usdc_amount: Erc20Value::from(1_000_u64),
+                gas_amount: Wei::new(1_000),
+                swap_tx_id: "56-1-1741348218000000000".to_string(),
+            },
+        );
+
+        assert_eq!(
+            state.next_swap_ledger_burn_index,
+            Some(LedgerBurnIndex::new(10_000_000_000_000_000_001_u64))
+        );
+    }
+
+    #[test]
+    fn should_panic_when_swap_request_uses_unallocated_index() {
+        let mut state = swap_enabled_state();
+
+        expect_panic_with_message(
+            || {
+                apply_state_transition(
+                    &mut state,
+                    &EventType::AcceptedSwapRequest(refund_swap_request()),
+                )
+            },
+            "that was not allocated",
+        );
+    }
+}
+
+mod protected_destination {
+    use crate::state::tests::initial_state;
+    use crate::state::{ProtectedDestination, State};
+    use evm_rpc_client::eth_types::Address;
+    use ic_management_canister_types::EcdsaPublicKeyResult;
+    use std::str::FromStr;
+
+    const HELPER_CONTRACT: &str = "0xE1788E4834c896F1932188645cc36c54d1b80AC1";
+    const SWAP_CONTRACT: &str = "0x1c7d4b196cb0c7b01d743fbc6116a902379c7238";
+    const TOKEN_CONTRACT: &str = "0xdAC17F958D2ee523a2206206994597C13D831ec7";
+    // Address of the secret key 1.
+    const MINTER_ADDRESS: &str = "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf";
+
+    #[test]
+    fn should_reject_zero_address() {
+        assert_eq!(
+            protected_state().protected_destination(&Address::ZERO, None),
+            Some(ProtectedDestination::ZeroAddress)
+        );
+    }
+
+    #[test]
+    fn should_reject_minter_address() {
+        assert_eq!(
+            protected_state().protected_destination(&address(MINTER_ADDRESS), None),
+            Some(ProtectedDestination::MinterAddress)
+        );
+    }
+
+    #[test]
+    fn should_reject_every_helper_contract() {
+        let mut state = protected_state();
+        let new_helper_contract = Address::new([0x42; 20]);
+        assert_eq!(
+            state.protected_destination(&new_helper_contract, None),
+            None
+        );
+
+        state
+            .helper_contract_addresses
+            .get_or_insert_with(Vec::new)
+            .push(new_helper_contract);
+
+        for helper_contract in [address(HELPER_CONTRACT), new_helper_contract] {
+            assert_eq!(
+                state.protected_destination(&helper_contract, None),
+                Some(ProtectedDestination::HelperContract)
+            );
+        }
+    }
+
+    #[test]
+    fn should_reject_swap_contract() {
+        assert_eq!(
+            protected_state().protected_destination(&address(SWAP_CONTRACT), None),
+            Some(ProtectedDestination::SwapContract)
+        );
+    }
+
+    #[test]
+    fn should_reject_contract_of_withdrawn_token_only() {
+        let state = protected_state();
+        let token_contract = address(TOKEN_CONTRACT);
+
+        assert_eq!(
+            state.protected_destination(&token_contract, Some(&token_contract)),
+            Some(ProtectedDestination::TokenContract)
+        );
+        assert_eq!(state.protected_destination(&token_contract, None), None);
+        assert_eq!(
+            state.protected_destination(&token_contract, Some(&address(SWAP_CONTRACT))),
+            None
+        );
+    }
+
+    #[test]
+    fn should_accept_normal_address() {
+        assert_eq!(
+            protected_state().protected_destination(
+                &address("0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34"),
+                Some(&address(TOKEN_CONTRACT))
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn should_describe_protected_destination() {
+        assert_eq!(
+            ProtectedDestination::HelperContract.to_string(),
+            "a helper contract"
+        );
+        assert_eq!(
+            ProtectedDestination::MinterAddress.to_string(),
+            "the minter address"
+        );
+    }
+
+    fn protected_state() -> State {
+        let mut state = initial_state();
+        // Compressed public key of the secret key 1.
+        state.ecdsa_public_key = Some(EcdsaPublicKeyResult {
+            public_key: hex::decode(
+                "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            )
+            .unwrap(),
+            chain_code: vec![2; 32],
+        });
+        state.helper_contract_addresses = Some(vec![address(HELPER_CONTRACT)]);
+        state.swap_contract_address = Some(address(SWAP_CONTRACT));
+        state
+    }
+
+    fn address(s: &str) -> Address {
+        Address::from_str(s).unwrap()
+    }
+}
+
+mod finalized_withdrawals {
+    use crate::numeric::{LedgerBurnIndex, TransactionNonce, Wei};
+    use crate::rpc_declarations::TransactionStatus;
+    use crate::state::audit::{apply_state_transition, record_finalized_withdrawals, EventType};
+    use crate::state::snapshot::StateSnapshot;
+    use crate::state::tests::native_balance::WithdrawalFlow;
+    use crate::state::tests::{initial_state, received_deposit_event};
+    use crate::state::transactions::NativeWithdrawalRequest;
+    use crate::state::State;
+    use candid::Principal;
+
+    #[test]
+    fn should_list_successful_withdrawals_most_recent_first() {
+        let state = state_with_withdrawals();
+        let native_ledger_id = state.native_ledger_id;
+
+        assert_eq!(finalized(&state, None, 0, 10), vec![(30, 2), (10, 0)]);
+        assert_eq!(finalized(&state, None, 1, 10), vec![(10, 0)]);
+        assert_eq!(finalized(&state, None, 0, 1), vec![(30, 2)]);
+        assert_eq!(
+            finalized(&state, Some(&native_ledger_id), 0, 10),
+            vec![(30, 2), (10, 0)]
+        );
+        assert_eq!(
+            finalized(&state, Some(&Principal::management_canister()), 0, 10),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn should_keep_finalization_timestamps_in_snapshot() {
+        let state = state_with_withdrawals();
+
+        let restored = State::from(StateSnapshot::from(&state));
+
+        assert_eq!(
+            restored.withdrawal_transactions.finalized_withdrawals,
+            state.withdrawal_transactions.finalized_withdrawals
+        );
+    }
+
+    fn state_with_withdrawals() -> State {
+        let mut state = initial_state();
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedDeposit(received_deposit_event()),
+        );
+        for (burn_index, status, timestamp) in [
+            (0, TransactionStatus::Success, 10),
+            (1, TransactionStatus::Failure, 20),
+            (2, TransactionStatus::Success, 30),
+        ] {
+            let flow = WithdrawalFlow {
+                nonce: TransactionNonce::new(burn_index),
+                tx_status: status,
+                ..WithdrawalFlow::for_request(withdrawal_request(burn_index))
+            };
+            let transaction_receipt = flow.apply(&mut state);
+            record_finalized_withdrawals(
+                &mut state,
+                &EventType::FinalizedTransaction {
+                    withdrawal_id: LedgerBurnIndex::new(burn_index),
+                    transaction_receipt,
+                },
+                timestamp,
+            );
+        }
+        state
+    }
+
+    fn withdrawal_request(ledger_burn_index: u64) -> NativeWithdrawalRequest {
+        NativeWithdrawalRequest {
+            withdrawal_amount: Wei::new(1_000_000_000_000_000),
+            destination: "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34"
+                .parse()
+                .unwrap(),
+            ledger_burn_index: LedgerBurnIndex::new(ledger_burn_index),
+            from: "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae"
+                .parse()
+                .unwrap(),
+            from_subaccount: None,
+            created_at: Some(1699527697000000000),
+            l1_fee: None,
+            withdrawal_fee: None,
+            is_contract_destination: None,
+        }
+    }
+
+    fn finalized(
+        state: &State,
+        ledger_id: Option<&Principal>,
+        start: usize,
+        length: usize,
+    ) -> Vec<(u64, u64)> {
+        state
+            .withdrawal_transactions
+            .finalized_withdrawals(ledger_id, start, length)
+            .into_iter()
+            .map(|(timestamp, request, _tx)| (timestamp, request.native_ledger_burn_index().get()))
+            .collect()
+    }
+}
+
+mod native_ledger_metadata {
+    use crate::state::audit::apply_state_transition;
+    use crate::state::event::EventType;
+    use crate::state::tests::initial_state;
+    use crate::state::NativeLedgerMetadata;
+
+    #[test]
+    fn should_accept_withdrawals_until_ledger_is_queried() {
+        let state = initial_state();
+
+        assert_eq!(state.native_ledger_metadata, None);
+        assert_eq!(state.native_ledger_symbol(), "icMatic");
+        assert_eq!(state.native_ledger_decimals(), 18);
+        assert_eq!(state.validate_native_decimals(), Ok(()));
+    }
+
+    #[test]
+    fn should_refuse_withdrawals_when_ledger_reports_8_decimals() {
+        let mut state = initial_state();
+        let metadata = NativeLedgerMetadata {
+            symbol: "icMatic".to_string(),
+            decimals: 8,
+        };
+        assert!(state.is_native_ledger_metadata_mismatch(&metadata));
+
+        apply_state_transition(
+            &mut state,
+            &EventType::NativeLedgerMetadataMismatch(metadata),
+        );
+
+        assert_eq!(state.native_ledger_decimals(), 8);
+        assert_eq!(
+            state.validate_native_decimals(),
+            Err(
+                "Native ledger reports 8 decimals instead of 18, withdrawals are disabled"
+                    .to_string()
+            )
+        );
+
+        apply_state_transition(
+            &mut state,
+            &EventType::NativeLedgerMetadataMatched(NativeLedgerMetadata {
+                symbol: "icMatic".to_string(),
+                decimals: 18,
+            }),
+        );
+        assert_eq!(state.native_ledger_decimals(), 18);
+        assert_eq!(state.validate_native_decimals(), Ok(()));
+    }
+
+    #[test]
+    fn should_use_ledger_symbol_when_it_differs_from_configuration() {
+        let mut state = initial_state();
+        let metadata = NativeLedgerMetadata {
+            symbol: "POL.icp".to_string(),
+            decimals: 18,
+        };
+        assert!(state.is_native_ledger_metadata_mismatch(&metadata));
+
+        apply_state_transition(
+            &mut state,
+            &EventType::NativeLedgerMetadataMismatch(metadata),
+        );
+
+        assert_eq!(state.native_ledger_symbol(), "POL.icp");
+        assert_eq!(state.native_symbol.to_string(), "icMatic");
+        assert_eq!(state.validate_native_decimals(), Ok(()));
+    }
+}
+
+mod onchain_approvals {
+    use crate::candid_types::RevokeApprovalError;
+    use crate::numeric::{LedgerBurnIndex, TransactionNonce, Wei};
+    use crate::state::audit::{apply_state_transition, EventType};
+    use crate::state::feature_flags::FeatureFlag;
+    use crate::state::tests::native_balance::WithdrawalFlow;
+    use crate::state::tests::swap_status_history::swap_enabled_state;
+    use crate::state::transactions::Erc20Approve;
+    use crate::state::OnchainApproval;
+    use crate::swap::validate_approval_revocation;
+    use candid::Principal;
+    use evm_rpc_client::eth_types::Address;
+
+    const USDC: &str = "0x1c7d4b196cb0c7b01d743fbc6116a902379c7238";
+    const SWAP_CONTRACT: &str = "0x733a1BEeF5A02990aAD285d7ED93fc1b622EeF1d";
+    const OTHER_SPENDER: &str = "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34";
+
+    #[test]
+    fn should_record_approval_once_finalized() {
+        let mut state = swap_enabled_state();
+        state.feature_flags.set(FeatureFlag::SwapActive, false);
+        let request = approve(SWAP_CONTRACT, 1, false);
+
+        let receipt = WithdrawalFlow::for_request(request.clone()).apply(&mut state);
+
+        assert!(state.is_swapping_active());
+        assert_eq!(
+            state
+                .onchain_approvals
+                .get(&(address(USDC), address(SWAP_CONTRACT))),
+            Some(&OnchainApproval {
+                granted_at: request.created_at,
+                transaction_hash: receipt.transaction_hash,
+            })
+        );
+    }
+
+    #[test]
+    fn should_deactivate_swapping_once_revocation_of_swap_contract_accepted() {
+        let mut state = swap_enabled_state();
+        WithdrawalFlow::for_request(approve(SWAP_CONTRACT, 1, false)).apply(&mut state);
+        assert_eq!(
+            validate_approval_revocation(&state, USDC, SWAP_CONTRACT).map(|r| r.spender),
+            Ok(address(SWAP_CONTRACT))
+        );
+
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedApprovalRevocationRequest(approve(SWAP_CONTRACT, 2, true)),
+        );
+
+        assert!(!state.is_swapping_active());
+        assert!(state
+            .onchain_approvals
+            .contains_key(&(address(USDC), address(SWAP_CONTRACT))));
+        assert_eq!(
+            validate_approval_revocation(&state, USDC, SWAP_CONTRACT).map(|r| r.spender),
+            Err(RevokeApprovalError::RevocationPending)
+        );
+    }
+
+    #[test]
+    fn should_clear_approval_once_revocation_finalized() {
+        let mut state = swap_enabled_state();
+        WithdrawalFlow::for_request(approve(SWAP_CONTRACT, 1, false)).apply(&mut state);
+        WithdrawalFlow {
+            nonce: TransactionNonce::new(1),
+            ..WithdrawalFlow::for_request(approve(OTHER_SPENDER, 2, false))
+        }
+        .apply(&mut state);
+        assert_eq!(state.onchain_approvals.len(), 2);
+
+        WithdrawalFlow {
+            nonce: TransactionNonce::new(2),
+            ..WithdrawalFlow::for_request(approve(OTHER_SPENDER, 3, true))
+        }
+        .apply(&mut state);
+
+        // revoking the approval of a spender that is not the swap contract keeps swapping active
+        assert!(state.is_swapping_active());
+        assert_eq!(
+            state.onchain_approvals.keys().collect::<Vec<_>>(),
+            vec![&(address(USDC), address(SWAP_CONTRACT))]
+        );
+        assert_eq!(
+            validate_approval_revocation(&state, USDC, OTHER_SPENDER).map(|r| r.spender),
+            Err(RevokeApprovalError::ApprovalNotFound)
+        );
+    }
+
+    #[test]
+    fn should_reject_revocation_of_invalid_addresses() {
+        let state = swap_enabled_state();
+
+        assert!(matches!(
+            validate_approval_revocation(&state, "0xinvalid", SWAP_CONTRACT).map(|r| r.spender),
+            Err(RevokeApprovalError::InvalidAddress(_))
+        ));
+        assert_eq!(
+            validate_approval_revocation(&state, USDC, SWAP_CONTRACT).map(|r| r.spender),
+            Err(RevokeApprovalError::ApprovalNotFound)
+        );
+    }
+
+    fn approve(spender: &str, burn_index: u64, is_revocation: bool) -> Erc20Approve {
+        Erc20Approve {
+            max_transaction_fee: Wei::new(1_000_000_000_000_000),
+            erc20_contract_address: address(USDC),
+            swap_contract_address: address(spender),
+            native_ledger_burn_index: LedgerBurnIndex::new(burn_index),
+            from: Principal::from_text("sosge-5iaaa-aaaag-alcla-cai").unwrap(),
+            from_subaccount: None,
+            created_at: 1_699_527_697_000_000_000 + burn_index,
+            l1_fee: None,
+            withdrawal_fee: None,
+            is_revocation: Some(is_revocation),
+        }
+    }
+
+    fn address(address: &str) -> Address {
+        address.parse().unwrap()
+    }
+}
+
+mod block_timestamps {
+    use crate::numeric::{BlockNumber, BlockTimestamp};
+    use crate::state::tests::initial_state;
+
+    const NANOS_PER_SEC: u64 = 1_000_000_000;
+    const BLOCK_TIMESTAMP: BlockTimestamp = BlockTimestamp::new(1_732_555_094);
+
+    #[test]
+    fn should_report_lag_of_observed_block() {
+        let mut state = initial_state();
+        let now_ns = (1_732_555_094 + 90) * NANOS_PER_SEC;
+        assert_eq!(state.last_observed_block_lag_seconds(now_ns), None);
+
+        state.last_observed_block_timestamp = Some(BLOCK_TIMESTAMP);
+
+        assert_eq!(state.last_observed_block_lag_seconds(now_ns), Some(90));
+    }
+
+    #[test]
+    fn should_forget_timestamp_of_previously_scraped_block() {
+        let mut state = initial_state();
+        let now_ns = (1_732_555_094 + 14 * 60) * NANOS_PER_SEC;
+        state.last_scraped_block_timestamp =
+            Some((state.last_scraped_block_number, BLOCK_TIMESTAMP));
+
+        assert_eq!(state.last_scraped_block_timestamp(), Some(BLOCK_TIMESTAMP));
+        assert_eq!(state.last_scraped_block_lag_seconds(now_ns), Some(14 * 60));
+
+        state.last_scraped_block_number = state
+            .last_scraped_block_number
+            .checked_add(BlockNumber::ONE)
+            .unwrap();
+
+        assert_eq!(state.last_scraped_block_timestamp(), None);
+        assert_eq!(state.last_scraped_block_lag_seconds(now_ns), None);
+    }
+}
+
+mod contract_destinations {
+    use crate::state::tests::initial_state;
+    use crate::state::{CONTRACT_DESTINATION_CACHE_TTL_NANOS, MAX_CACHED_CONTRACT_DESTINATIONS};
+    use evm_rpc_client::eth_types::Address;
+    use std::str::FromStr;
+
+    const NOW: u64 = 1_700_000_000_000_000_000;
+
+    #[test]
+    fn should_cache_contract_destination_until_expiry() {
+        let mut state = initial_state();
+        let contract = Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
+        let eoa = Address::from_str("0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34").unwrap();
+        assert_eq!(state.cached_is_contract_destination(&contract, NOW), None);
+
+        state.record_is_contract_destination(contract, true, NOW);
+        state.record_is_contract_destination(eoa, false, NOW);
+
+        let before_expiry = NOW + CONTRACT_DESTINATION_CACHE_TTL_NANOS - 1;
+        assert_eq!(
+            state.cached_is_contract_destination(&contract, before_expiry),
+            Some(true)
+        );
+        assert_eq!(
+            state.cached_is_contract_destination(&eoa, before_expiry),
+            Some(false)
+        );
+
+        let after_expiry = NOW + CONTRACT_DESTINATION_CACHE_TTL_NANOS;
+        assert_eq!(
+            state.cached_is_contract_destination(&contract, after_expiry),
+            None
+        );
+
+        state.record_is_contract_destination(eoa, false, after_expiry);
+        assert!(!state.contract_destinations.contains_key(&contract));
+        assert_eq!(
+            state.cached_is_contract_destination(&eoa, after_expiry),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn should_evict_oldest_destination_when_cache_is_full() {
+        let mut state = initial_state();
+        let address = |i: usize| {
+            let mut bytes = [0_u8; 20];
+            bytes[..2].copy_from_slice(&(i as u16).to_be_bytes());
+            Address::new(bytes)
+        };
+        for i in 0..MAX_CACHED_CONTRACT_DESTINATIONS {
+            state.record_is_contract_destination(address(i), false, NOW + i as u64);
+        }
+
+        state.record_is_contract_destination(address(0), true, NOW + 1_000_000);
+        assert_eq!(
+            state.contract_destinations.len(),
+            MAX_CACHED_CONTRACT_DESTINATIONS
+        );
+
+        let new_destination = Address::new([0xff; 20]);
+        state.record_is_contract_destination(new_destination, true, NOW + 1_000_001);
+        assert_eq!(
+            state.contract_destinations.len(),
+            MAX_CACHED_CONTRACT_DESTINATIONS
+        );
+        assert!(!state.contract_destinations.contains_key(&address(1)));
+        assert_eq!(
+            state.cached_is_contract_destination(&address(0), NOW + 1_000_001),
+            Some(true)
+        );
+    }
+}
+
+mod ecdsa_signing {
+    use crate::management::CYCLES_PER_SIGNATURE;
+    use crate::state::tests::initial_state;
+    use crate::state::SIGNING_CYCLES_RESERVE;
+
+    #[test]
+    fn should_count_signing_calls_and_cycles() {
+        let mut state = initial_state();
+        assert_eq!(state.ecdsa_signing_calls, 0);
+        assert_eq!(state.ecdsa_signing_cycles_spent, 0);
+
+        for _ in 0..3 {
+            state.record_ecdsa_signing(CYCLES_PER_SIGNATURE);
+        }
+
+        assert_eq!(state.ecdsa_signing_calls, 3);
+        assert_eq!(state.ecdsa_signing_cycles_spent, 3 * CYCLES_PER_SIGNATURE);
+    }
+
+    #[test]
+    fn should_estimate_remaining_signing_capacity() {
+        let mut state = initial_state();
+        assert_eq!(state.estimated_remaining_signing_capacity(0), 0);
+        assert_eq!(
+            state.estimated_remaining_signing_capacity(SIGNING_CYCLES_RESERVE),
+            0
+        );
+        assert_eq!(
+            state.estimated_remaining_signing_capacity(
+                SIGNING_CYCLES_RESERVE + 10 * CYCLES_PER_SIGNATURE + CYCLES_PER_SIGNATURE - 1
+            ),
+            10
+        );
+
+        // the average cost of the calls made since the upgrade is used
+        state.record_ecdsa_signing(20_000_000_000);
+        state.record_ecdsa_signing(30_000_000_000);
+        assert_eq!(state.cost_per_signing(), 25_000_000_000);
+        assert_eq!(
+            state.estimated_remaining_signing_capacity(SIGNING_CYCLES_RESERVE + 100_000_000_000),
+            4
+        );
+        assert_eq!(
+            state.estimated_remaining_signing_capacity(u128::MAX),
+            u64::MAX
+        );
+    }
+}
+
+mod collected_fees {
+    use crate::fee_collector::PendingFeeWithdrawal;
+    use crate::lifecycle::UpgradeArg;
+    use crate::numeric::{IcrcValue, Wei};
+    use crate::state::audit::{apply_state_transition, replay_events_from};
+    use crate::state::event::{Event, EventType};
+    use crate::state::snapshot::StateSnapshot;
+    use crate::state::tests::{initial_init_arg, initial_state};
+    use crate::state::State;
+    use candid::Principal;
+    use icrc_ledger_types::icrc1::account::Account;
+
+    fn ledger_id() -> Principal {
+        Principal::from_text("xevnm-gaaaa-aaaar-qafnq-cai").unwrap()
+    }
+
+    fn treasury() -> Account {
+        Account {
+            owner: Principal::from_text(
+                "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae",
+            )
+            .unwrap(),
+            subaccount: Some([1; 32]),
+        }
+    }
+
+    #[test]
+    fn should_keep_fee_collector_across_upgrades() {
+        let mut state = initial_state();
+        assert_eq!(state.fee_collector, None);
+
+        apply_state_transition(
+            &mut state,
+            &EventType::Upgrade(UpgradeArg {
+                fee_collector: Some(treasury()),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(state.fee_collector, Some(treasury()));
+
+        // upgrades without a fee collector keep the configured one
+        apply_state_transition(&mut state, &EventType::Upgrade(UpgradeArg::default()));
+        assert_eq!(state.fee_collector, Some(treasury()));
+        // the withdrawal fee is still burned with the withdrawal and funds the gas tank
+        assert_eq!(state.withdrawal_native_fee, Some(Wei::new(5_000_000)));
+    }
+
+    #[test]
+    fn should_add_collected_native_fees_to_operation_fees() {
+        let mut state = initial_state();
+        let native_ledger_id = state.native_ledger_id;
+
+        for (ledger_id, block_index) in [(native_ledger_id, 1), (ledger_id(), 2)] {
+            apply_state_transition(
+                &mut state,
+                &EventType::FeeCollected {
+                    ledger_id,
+                    amount: IcrcValue::new(5_000_000),
+                    block_index: block_index.into(),
+                },
+            );
+        }
+
+        assert_eq!(
+            state.native_balance.total_collected_operation_native_fee,
+            Wei::new(5_000_000)
+        );
+        assert_eq!(
+            state.collected_fees(&native_ledger_id),
+            IcrcValue::new(5_000_000)
+        );
+        assert_eq!(
+            state.collected_fees(&ledger_id()),
+            IcrcValue::new(5_000_000)
+        );
+    }
+
+    #[test]
+    fn should_account_unaccounted_fees() {
+        let mut state = initial_state();
+        apply_state_transition(
+            &mut state,
+            &EventType::FeeCollected {
+                ledger_id: ledger_id(),
+                amount: IcrcValue::new(1_000_000),
+                block_index: 1.into(),
+            },
+        );
+
+        apply_state_transition(
+            &mut state,
+            &EventType::UnaccountedFeesFound {
+                ledger_id: ledger_id(),
+                amount: IcrcValue::new(250_000),
+            },
+        );
+
+        assert_eq!(
+            state.collected_fees(&ledger_id()),
+            IcrcValue::new(1_250_000)
+        );
+        assert_eq!(
+            state.native_balance.total_collected_operation_native_fee,
+            Wei::ZERO
+        );
+    }
+
+    #[test]
+    fn should_account_collected_and_withdrawn_fees() {
+        let mut state = initial_state();
+        assert_eq!(state.collected_fees(&ledger_id()), IcrcValue::ZERO);
+
+        for (amount, block_index) in [(1_000_000, 1), (500_000, 2)] {
+            apply_state_transition(
+                &mut state,
+                &EventType::FeeCollected {
+                    ledger_id: ledger_id(),
+                    amount: IcrcValue::new(amount),
+                    block_index: block_index.into(),
+                },
+            );
+        }
+        assert_eq!(
+            state.collected_fees(&ledger_id()),
+            IcrcValue::new(1_500_000)
+        );
+
+        apply_state_transition(
+            &mut state,
+            &EventType::CollectedFeesWithdrawn {
+                ledger_id: ledger_id(),
+                to: treasury(),
+                amount: IcrcValue::new(1_000_000),
+                block_index: 3,
+            },
+        );
+        assert_eq!(state.collected_fees(&ledger_id()), IcrcValue::new(500_000));
+
+        apply_state_transition(
+            &mut state,
+            &EventType::CollectedFeesWithdrawn {
+                ledger_id: ledger_id(),
+                to: treasury(),
+                amount: IcrcValue::new(500_000),
+                block_index: 4,
+            },
+        );
+        assert!(state.collected_fees.is_empty());
+    }
+
+    fn pending_withdrawal() -> PendingFeeWithdrawal {
+        PendingFeeWithdrawal {
+            to: treasury(),
+            transfer_amount: IcrcValue::new(990_000),
+            ledger_fee: IcrcValue::new(10_000),
+            created_at_time: 1_699_527_697_000_000_000,
+            amount: IcrcValue::new(1_000_000),
+        }
+    }
+
+    /// Returns the state after an upgrade, replaying `events` from the `Init` event and from
+    /// a snapshot archiving them.
+    fn upgrade(events: &[EventType]) -> State {
+        let events = events.iter().cloned().map(|payload| Event {
+            timestamp: 0,
+            payload,
+        });
+        let replayed = replay_events_from(None, events);
+        let bytes = StateSnapshot::from(&replayed).encode_to_vec();
+        let restored: State = StateSnapshot::decode_from_slice(&bytes)
+            .expect("state snapshot should decode")
+            .into();
+        assert_eq!(restored, replayed);
+        replayed
+    }
+
+    #[test]
+    fn should_keep_pending_withdrawal_across_upgrades() {
+        let mut events = vec![
+            EventType::Init(initial_init_arg()),
+            EventType::FeeCollected {
+                ledger_id: ledger_id(),
+                amount: IcrcValue::new(1_500_000),
+                block_index: 1.into(),
+            },
+            EventType::CollectedFeesWithdrawalStarted {
+                ledger_id: ledger_id(),
+                withdrawal: pending_withdrawal(),
+            },
+        ];
+
+        // the transfer was executed, but the minter was upgraded before its outcome was recorded
+        let state = upgrade(&events);
+        assert_eq!(
+            state.pending_fee_withdrawals.get(&ledger_id()),
+            Some(&pending_withdrawal())
+        );
+        assert_eq!(
+            state.collected_fees(&ledger_id()),
+            IcrcValue::new(1_500_000)
+        );
+
+        // the retry with the same arguments is deduplicated by the ledger
+        events.push(EventType::CollectedFeesWithdrawn {
+            ledger_id: ledger_id(),
+            to: treasury(),
+            amount: IcrcValue::new(1_000_000),
+            block_index: 2,
+        });
+        let state = upgrade(&events);
+        assert!(state.pending_fee_withdrawals.is_empty());
+        assert_eq!(state.collected_fees(&ledger_id()), IcrcValue::new(500_000));
+    }
+
+    #[test]
+    fn should_reconcile_withdrawal_rejected_as_too_old() {
+        let mut state = initial_state();
+        apply_state_transition(
+            &mut state,
+            &EventType::FeeCollected {
+                ledger_id: ledger_id(),
+                amount: IcrcValue::new(1_500_000),
+                block_index: 1.into(),
+            },
+        );
+        apply_state_transition(
+            &mut state,
+            &EventType::CollectedFeesWithdrawalStarted {
+                ledger_id: ledger_id(),
+                withdrawal: pending_withdrawal(),
+            },
+        );
+
+        apply_state_transition(
+            &mut state,
+            &EventType::CollectedFeesWithdrawalFailed {
+                ledger_id: ledger_id(),
+                reason: "TooOld".to_string(),
+            },
+        );
+        assert!(state.pending_fee_withdrawals.is_empty());
+        assert_eq!(
+            state.collected_fees(&ledger_id()),
+            IcrcValue::new(1_500_000)
+        );
+
+        // the transfer was executed, so that the holding account only holds 500_000
+        apply_state_transition(
+            &mut state,
+            &EventType::MissingFeesFound {
+                ledger_id: ledger_id(),
+                amount: IcrcValue::new(1_000_000),
+            },
+        );
+        assert_eq!(state.collected_fees(&ledger_id()), IcrcValue::new(500_000));
+    }
+}
+
+mod feature_flags {
+    use crate::candid_types::SetFeatureFlagError;
+    use crate::state::audit::{apply_state_transition, EventType};
+    use crate::state::feature_flags::{validate_feature_flag_change, FeatureFlag};
+    use crate::state::tests::initial_state;
+    use crate::state::tests::swap_status_history::swap_enabled_state;
+
+    #[test]
+    fn should_toggle_feature_flag_with_event() {
+        let mut state = initial_state();
+        assert!(!state.is_draining());
+
+        apply_state_transition(
+            &mut state,
+            &EventType::FeatureFlagChanged {
+                flag: FeatureFlag::Draining,
+                enabled: true,
+            },
+        );
+        assert!(state.is_draining());
+        assert!(!state.is_swapping_active());
+
+        apply_state_transition(
+            &mut state,
+            &EventType::FeatureFlagChanged {
+                flag: FeatureFlag::Draining,
+                enabled: false,
+            },
+        );
+        assert!(!state.is_draining());
+    }
+
+    #[test]
+    fn should_reject_unknown_flag() {
+        let state = initial_state();
+
+        assert_eq!(
+            validate_feature_flag_change(&state, "Draining", true),
+            Err(SetFeatureFlagError::UnknownFlag {
+                flag: "Draining".to_string(),
+                known_flags: vec!["swap_active".to_string(), "draining".to_string()],
+            })
+        );
+        for flag in FeatureFlag::ALL {
+            assert_eq!(flag.name().parse::<FeatureFlag>(), Ok(flag));
+        }
+    }
+
+    #[test]
+    fn should_only_enable_swapping_once_swap_feature_activated() {
+        assert_eq!(
+            validate_feature_flag_change(&initial_state(), "swap_active", true),
+            Err(SetFeatureFlagError::SwapFeatureNotActivated)
+        );
+        assert_eq!(
+            validate_feature_flag_change(&initial_state(), "swap_active", false),
+            Ok(FeatureFlag::SwapActive)
+        );
+        assert_eq!(
+            validate_feature_flag_change(&swap_enabled_state(), "swap_active", true),
+            Ok(FeatureFlag::SwapActive)
+        );
+    }
+}
+
+mod daily_stats {
+    use super::*;
+    use crate::state::audit::replay_events_from;
+    use crate::state::daily_stats::{DailyStats, NANOS_PER_DAY};
+    use crate::state::ERC20Token;
+
+    const DAY_1: u64 = 19_700 * NANOS_PER_DAY;
+    const DAY_2: u64 = DAY_1 + NANOS_PER_DAY;
+
+    #[test]
+    fn should_record_deposits_on_different_days_in_different_buckets() {
+        let native_ledger_id = initial_state().native_ledger_id;
+        let erc20_ledger_id = Principal::from_text("3sgad-taaaa-aaaar-qaedq-cai").unwrap();
+        let first_deposit = received_deposit_event();
+        let second_deposit = ReceivedNativeEvent {
+            log_index: LogIndex::from(30_u8),
+            value: Wei::new(3_000_000_000_000_000),
+            ..received_deposit_event()
+        };
+        let erc20_deposit = received_erc20_event();
+        let events = [
+            (0, EventType::Init(initial_init_arg())),
+            (
+                0,
+                EventType::AddedErc20Token(ERC20Token {
+                    chain_id: Default::default(),
+                    erc20_contract_address: erc20_deposit.erc20_contract_address,
+                    erc20_token_symbol: "ckSepoliaUSDC".parse().unwrap(),
+                    erc20_ledger_id,
+                    transfer_kind: None,
+                    original_symbol: None,
+                }),
+            ),
+            (DAY_1, EventType::AcceptedDeposit(first_deposit.clone())),
+            (DAY_1, EventType::AcceptedDeposit(second_deposit.clone())),
+            (
+                DAY_1,
+                EventType::AcceptedErc20Deposit(erc20_deposit.clone()),
+            ),
+            (
+                DAY_1 + 1,
+                EventType::MintedNative {
+                    event_source: first_deposit.source(),
+                    mint_block_index: LedgerMintIndex::new(1),
+                },
+            ),
+            (
+                DAY_1 + 2,
+                EventType::MintedErc20 {
+                    event_source: erc20_deposit.source(),
+                    mint_block_index: LedgerMintIndex::new(2),
+                    erc20_token_symbol: "ckSepoliaUSDC".to_string(),
+                    erc20_contract_address: erc20_deposit.erc20_contract_address,
+                },
+            ),
+            (
+                DAY_2 + 1,
+                EventType::MintedNative {
+                    event_source: second_deposit.source(),
+                    mint_block_index: LedgerMintIndex::new(3),
+                },
+            ),
+        ]
+        .into_iter()
+        .map(|(timestamp, payload)| Event { timestamp, payload });
+
+        let state = replay_events_from(None, events);
+
+        let day_1 = DAY_1 / NANOS_PER_DAY;
+        assert_eq!(
+            buckets(&state.daily_stats, None),
+            vec![
+                (day_1, erc20_ledger_id, 1, erc20_deposit.value),
+                (
+                    day_1,
+                    native_ledger_id,
+                    1,
+                    first_deposit.value.change_units()
+                ),
+                (
+                    day_1 + 1,
+                    native_ledger_id,
+                    1,
+                    second_deposit.value.change_units()
+                ),
+            ]
+        );
+        assert_eq!(buckets(&state.daily_stats, Some(native_ledger_id)).len(), 2);
+    }
+
+    #[test]
+    fn should_evict_days_outside_of_retained_window() {
+        let ledger_id = Principal::management_canister();
+        let mut stats = DailyStats::default();
+        stats.record_deposit(DAY_1, ledger_id, Erc20Value::ONE);
+        stats.record_deposit(DAY_1 + 89 * NANOS_PER_DAY, ledger_id, Erc20Value::ONE);
+        assert_eq!(buckets(&stats, None).len(), 2);
+
+        stats.record_withdrawal(DAY_1 + 90 * NANOS_PER_DAY, ledger_id, Erc20Value::ONE);
+        assert_eq!(
+            buckets(&stats, None)
+                .into_iter()
+                .map(|(day, ..)| day)
+                .collect::<Vec<_>>(),
+            vec![DAY_1 / NANOS_PER_DAY + 89, DAY_1 / NANOS_PER_DAY + 90]
+        );
+
+        stats.record_deposit(DAY_1, ledger_id, Erc20Value::ONE);
+        assert_eq!(buckets(&stats, None).len(), 2);
+    }
+
+    fn buckets(
+        stats: &DailyStats,
+        ledger_id: Option<Principal>,
+    ) -> Vec<(u64, Principal, u64, Erc20Value)> {
+        stats
+            .iter_from(0, ledger_id)
+            .map(|(day, ledger_id, stats)| {
+                (day, *ledger_id, stats.deposit_count, stats.deposit_volume)
+            })
+            .collect()
+    }
+}
+
+mod wrapped_icrc_tokens {
+    use super::*;
+    use crate::contract_logs::types::{ReceivedBurnEvent, ReceivedWrappedIcrcDeployedEvent};
+    use crate::contract_logs::ReceivedContractEvent;
+    use crate::deposit::removed_log_event;
+    use crate::numeric::IcrcValue;
+    use crate::state::audit::replay_events_from;
+    use crate::state::snapshot::StateSnapshot;
+    use crate::state::{
+        IcrcLedgerMetadata, WrappedIcrcDeployment, WRAPPED_ICRC_METADATA_TTL_NANOS,
+    };
+
+    const ICP_LEDGER_ID: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai";
+    const WRAPPED_ICP_ADDRESS: &str = "0x8ac4dcd9a1fd6e5efd8f9a5d3c6c8d0b2f3ae1c5";
+
+    fn deployed_event() -> ReceivedWrappedIcrcDeployedEvent {
+        ReceivedWrappedIcrcDeployedEvent {
+            transaction_hash: "0x705f826861c802b407843e99af986cfde8749b669e5e0a5a150f4350bcaa9bc3"
+                .parse()
+                .unwrap(),
+            block_number: BlockNumber::new(5_539_910),
+            log_index: LogIndex::from(1_u8),
+            base_token: Principal::from_text(ICP_LEDGER_ID).unwrap(),
+            deployed_wrapped_erc20: WRAPPED_ICP_ADDRESS.parse().unwrap(),
+        }
+    }
 
-fn erc20_withdrawal_request() -> Erc20WithdrawalRequest {
-    Erc20WithdrawalRequest {
-        max_transaction_fee: Wei::from(30_000_000_000_000_000_u64),
-        withdrawal_amount: Erc20Value::from(4_996_000_u64),
-        destination: "0xdd2851Cdd40aE6536831558DD46db62fAc7A844d"
-            .parse()
-            .unwrap(),
-        native_ledger_burn_index: LedgerBurnIndex::new(5),
-        erc20_contract_address: "0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238"
-            .parse()
+    #[test]
+    fn should_keep_deployment_transaction_across_replay_and_snapshot() {
+        let deployed = deployed_event();
+        let events = [
+            EventType::Init(initial_init_arg()),
+            EventType::DeployedWrappedIcrcToken(deployed.clone()),
+        ]
+        .into_iter()
+        .map(|payload| Event {
+            timestamp: 0,
+            payload,
+        });
+
+        let state = replay_events_from(None, events);
+
+        let expected = BTreeMap::from([(
+            deployed.deployed_wrapped_erc20,
+            WrappedIcrcDeployment {
+                transaction_hash: deployed.transaction_hash,
+                block_number: deployed.block_number,
+            },
+        )]);
+        assert_eq!(state.wrapped_icrc_deployments, expected);
+
+        let snapshot =
+            StateSnapshot::decode_from_slice(&StateSnapshot::from(&state).encode_to_vec())
+                .expect("state snapshot should decode");
+        let restored = State::from(snapshot);
+        assert_eq!(restored.wrapped_icrc_deployments, expected);
+    }
+
+    #[test]
+    fn should_replay_removed_release_log() {
+        let deployed = deployed_event();
+        let burn = ReceivedBurnEvent {
+            transaction_hash: "0x1c1a6a8fbb8e1d2c3b4a59687766554433221100ffeeddccbbaa998877665544"
+                .parse()
+                .unwrap(),
+            block_number: BlockNumber::new(5_539_920),
+            log_index: LogIndex::from(4_u8),
+            from_address: "0xdd2851Cdd40aE6536831558DD46db62fAc7A844d"
+                .parse()
+                .unwrap(),
+            value: IcrcValue::new(30_000_000),
+            principal: Principal::from_text(
+                "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae",
+            )
             .unwrap(),
-        erc20_ledger_id: Principal::from_text("3sgad-taaaa-aaaar-qaedq-cai").unwrap(),
-        erc20_ledger_burn_index: LedgerBurnIndex::new(2),
-        from: Principal::from_text(
-            "hkroy-sm7vs-yyjs7-ekppe-qqnwx-hm4zf-n7ybs-titsi-k6e3k-ucuiu-uqe",
-        )
-        .unwrap(),
-        from_subaccount: None,
-        created_at: 1_711_138_972_460_345_032,
-        l1_fee: Some(Wei::new(10_000_000)),
-        is_wrapped_mint: Some(false),
-        withdrawal_fee: Some(Wei::new(5_000_000)),
+            wrapped_erc20_contract_address: deployed.deployed_wrapped_erc20,
+            icrc_token_principal: deployed.base_token,
+            subaccount: None,
+        };
+        let source = ReceivedContractEvent::from(burn.clone()).source();
+        let to_events = |payloads: Vec<EventType>| {
+            payloads.into_iter().map(|payload| Event {
+                timestamp: 0,
+                payload,
+            })
+        };
+        let deployed_state = replay_events_from(
+            None,
+            to_events(vec![
+                EventType::Init(initial_init_arg()),
+                EventType::DeployedWrappedIcrcToken(deployed.clone()),
+            ]),
+        );
+
+        let state = replay_events_from(
+            None,
+            to_events(vec![
+                EventType::Init(initial_init_arg()),
+                EventType::DeployedWrappedIcrcToken(deployed),
+                EventType::AcceptedWrappedIcrcBurn(burn),
+                EventType::DepositReorged {
+                    event_source: source,
+                },
+            ]),
+        );
+
+        assert!(!state.has_events_to_release());
+        assert_eq!(state.icrc_balances, deployed_state.icrc_balances);
+        assert_eq!(state.removed_log_events, BTreeSet::from([source]));
+        // scraping the same block range again must not record the removal again
+        assert_eq!(removed_log_event(&state, source), None);
+    }
+
+    #[test]
+    fn should_cache_metadata_until_expired() {
+        let deployed = deployed_event();
+        let mut state = initial_state();
+        state.record_contract_events(&deployed.clone().into());
+        assert_eq!(
+            state.wrapped_icrc_ledgers_to_refresh(0),
+            vec![deployed.base_token]
+        );
+
+        state.record_wrapped_icrc_metadata(
+            deployed.base_token,
+            IcrcLedgerMetadata {
+                symbol: "ICP".to_string(),
+                decimals: 8,
+                transfer_fee: IcrcValue::new(10_000),
+                refreshed_at: 10,
+            },
+        );
+
+        assert!(state
+            .wrapped_icrc_ledgers_to_refresh(10 + WRAPPED_ICRC_METADATA_TTL_NANOS - 1)
+            .is_empty());
+        assert_eq!(
+            state.wrapped_icrc_ledgers_to_refresh(10 + WRAPPED_ICRC_METADATA_TTL_NANOS),
+            vec![deployed.base_token]
+        );
     }
 }
 
-fn checked_sub(lhs: Erc20Balances, rhs: Erc20Balances) -> BTreeMap<Address, Erc20Value> {
-    assert!(rhs
-                .balance_by_erc20_contract
-                .keys()
-                .all(|rhs_erc20_contract| {
-                    lhs.balance_by_erc20_contract
-                        .contains_key(rhs_erc20_contract)
-                }), "BUG: Cannot subtract rhs {rhs:?} to lhs {lhs:?} since some ERC-20 contracts are missing in the lhs");
-    let mut result = lhs.balance_by_erc20_contract.clone();
-    for (erc20_contract, rhs_value) in rhs.balance_by_erc20_contract.into_iter() {
-        match lhs.balance_by_erc20_contract.get(&erc20_contract).unwrap() {
-            lhs_value if lhs_value == &rhs_value => {
-                result.remove(&erc20_contract);
-            }
-            lhs_value if lhs_value > &rhs_value => {
-                result.insert(erc20_contract, lhs_value.checked_sub(rhs_value).unwrap());
+mod fee_cap {
+    use super::*;
+    use crate::candid_types::RetrieveWithdrawalStatus;
+    use crate::state::transactions::{
+        create_transaction, NativeWithdrawalRequest, WithdrawalRequest,
+    };
+    use crate::state::InvalidStateError;
+    use crate::FEE_CAP_ALERT_INTERVAL;
+
+    const GWEI: u128 = 1_000_000_000;
+
+    fn withdrawal_request() -> NativeWithdrawalRequest {
+        NativeWithdrawalRequest {
+            withdrawal_amount: Wei::new(1_000_000_000_000_000_000),
+            destination: "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34"
+                .parse()
+                .unwrap(),
+            ledger_burn_index: LedgerBurnIndex::new(7),
+            from: "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae"
+                .parse()
+                .unwrap(),
+            from_subaccount: None,
+            created_at: Some(1_699_527_697_000_000_000),
+            l1_fee: None,
+            withdrawal_fee: None,
+            is_contract_destination: None,
+        }
+    }
+
+    // Doubled by the base fee multiplier to 600 gwei, above the default cap of 500 gwei.
+    fn spiked_gas_fee_estimate() -> GasFeeEstimate {
+        GasFeeEstimate {
+            base_fee_per_gas: WeiPerGas::new(300 * GWEI),
+            max_priority_fee_per_gas: WeiPerGas::new(GWEI),
+            base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+        }
+    }
+
+    #[test]
+    fn should_keep_withdrawal_pending_until_cap_is_raised() {
+        let mut state = initial_state();
+        let request = withdrawal_request();
+        let withdrawal_id = request.ledger_burn_index;
+        let gas_limit = GasAmount::from(21_000_u32);
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedNativeWithdrawalRequest(request.clone()),
+        );
+
+        let exceeded = spiked_gas_fee_estimate()
+            .to_capped_price(gas_limit, &state.fee_cap())
+            .expect_err("estimate should exceed the default cap");
+        assert_eq!(exceeded.max_fee_per_gas, WeiPerGas::new(601 * GWEI));
+        assert_eq!(exceeded.cap, FeeCap::for_network(EvmNetwork::Sepolia));
+        state
+            .withdrawal_transactions
+            .reschedule_withdrawal_request(request.clone());
+        assert_eq!(
+            state
+                .withdrawal_transactions
+                .transaction_status(&withdrawal_id),
+            RetrieveWithdrawalStatus::Pending
+        );
+
+        state
+            .upgrade(UpgradeArg {
+                max_fee_per_gas_cap: Some(Nat::from(1_000 * GWEI)),
+                ..Default::default()
+            })
+            .expect("valid upgrade args");
+        assert_eq!(
+            state.fee_cap(),
+            FeeCap {
+                max_fee_per_gas: WeiPerGas::new(1_000 * GWEI),
+                ..FeeCap::for_network(EvmNetwork::Sepolia)
             }
-            lhs_value => panic!(
-                "BUG: Cannot subtract rhs {rhs_value:?} to lhs {lhs_value:?} since it would underflow"
-            ),
+        );
+
+        let price = spiked_gas_fee_estimate()
+            .to_capped_price(gas_limit, &state.fee_cap())
+            .expect("estimate should be within the raised cap");
+        assert_eq!(price.max_fee_per_gas, WeiPerGas::new(601 * GWEI));
+        let transaction = create_transaction(
+            &WithdrawalRequest::Native(request),
+            TransactionNonce::ZERO,
+            spiked_gas_fee_estimate(),
+            gas_limit,
+            EvmNetwork::Sepolia,
+        )
+        .expect("transaction should be created");
+        apply_state_transition(
+            &mut state,
+            &EventType::CreatedTransaction {
+                withdrawal_id,
+                transaction,
+            },
+        );
+        assert_eq!(
+            state
+                .withdrawal_transactions
+                .transaction_status(&withdrawal_id),
+            RetrieveWithdrawalStatus::TxCreated
+        );
+    }
+
+    #[test]
+    fn should_reject_zero_caps() {
+        let state = initial_state();
+
+        assert!(matches!(
+            state.validate_upgrade_arg(&UpgradeArg {
+                max_fee_per_gas_cap: Some(Nat::from(0_u8)),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidFeeCap(_))
+        ));
+        assert!(matches!(
+            state.validate_upgrade_arg(&UpgradeArg {
+                max_transaction_fee_cap: Some(Nat::from(0_u8)),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidFeeCap(_))
+        ));
+    }
+
+    #[test]
+    fn should_rate_limit_alerts() {
+        let mut state = initial_state();
+        let interval = FEE_CAP_ALERT_INTERVAL.as_nanos() as u64;
+        assert!(state.can_record_fee_cap_alert(0));
+
+        apply_state_transition(
+            &mut state,
+            &EventType::FeeCapExceeded {
+                withdrawal_id: LedgerBurnIndex::new(7),
+                max_fee_per_gas: WeiPerGas::new(601 * GWEI),
+                max_transaction_fee: Wei::new(601 * GWEI * 21_000),
+                cap: state.fee_cap(),
+                timestamp: 10,
+            },
+        );
+
+        assert_eq!(state.last_fee_cap_alert, Some(10));
+        assert!(!state.can_record_fee_cap_alert(10 + interval - 1));
+        assert!(state.can_record_fee_cap_alert(10 + interval));
+    }
+}
+
+mod broadcast_attempts {
+    use super::*;
+    use crate::numeric::TransactionCount;
+    use crate::rpc_declarations::Hash;
+    use crate::state::snapshot::StateSnapshot;
+    use crate::state::transactions::{
+        create_transaction, NativeWithdrawalRequest, WithdrawalRequest,
+    };
+
+    fn withdrawal_request() -> NativeWithdrawalRequest {
+        NativeWithdrawalRequest {
+            withdrawal_amount: Wei::new(1_000_000_000_000_000),
+            destination: "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34"
+                .parse()
+                .unwrap(),
+            ledger_burn_index: LedgerBurnIndex::new(7),
+            from: "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae"
+                .parse()
+                .unwrap(),
+            from_subaccount: None,
+            created_at: Some(1_699_527_697_000_000_000),
+            l1_fee: None,
+            withdrawal_fee: None,
+            is_contract_destination: None,
         }
     }
-    result
+
+    fn state_with_sent_transaction() -> (State, SignedEip1559TransactionRequest) {
+        let mut state = initial_state();
+        let request = withdrawal_request();
+        let withdrawal_id = request.ledger_burn_index;
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedNativeWithdrawalRequest(request.clone()),
+        );
+        let transaction = create_transaction(
+            &WithdrawalRequest::Native(request),
+            TransactionNonce::ZERO,
+            GasFeeEstimate {
+                base_fee_per_gas: WeiPerGas::new(1_000_000_000),
+                max_priority_fee_per_gas: WeiPerGas::new(1_000_000_000),
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+            },
+            GasAmount::from(21_000_u32),
+            EvmNetwork::Sepolia,
+        )
+        .expect("transaction should be created");
+        apply_state_transition(
+            &mut state,
+            &EventType::CreatedTransaction {
+                withdrawal_id,
+                transaction: transaction.clone(),
+            },
+        );
+        let signed_tx = SignedEip1559TransactionRequest::from((
+            transaction,
+            Eip1559Signature {
+                signature_y_parity: false,
+                r: Default::default(),
+                s: Default::default(),
+            },
+        ));
+        apply_state_transition(
+            &mut state,
+            &EventType::SignedTransaction {
+                withdrawal_id,
+                transaction: signed_tx.clone(),
+            },
+        );
+        (state, signed_tx)
+    }
+
+    fn broadcast_attempted(signed_tx: &SignedEip1559TransactionRequest) -> EventType {
+        EventType::BroadcastAttempted {
+            withdrawal_id: LedgerBurnIndex::new(7),
+            transaction_hash: signed_tx.hash(),
+            nonce: signed_tx.nonce(),
+            timestamp: 1_699_527_698_000_000_000,
+        }
+    }
+
+    #[test]
+    fn should_keep_attempt_unreconciled_until_outcome_is_recorded() {
+        let (mut state, signed_tx) = state_with_sent_transaction();
+        let withdrawal_id = LedgerBurnIndex::new(7);
+        apply_state_transition(&mut state, &broadcast_attempted(&signed_tx));
+
+        // an upgrade while awaiting `eth_sendRawTransaction` leaves the attempt without outcome
+        let mut state = State::from(
+            StateSnapshot::decode_from_slice(&StateSnapshot::from(&state).encode_to_vec())
+                .expect("snapshot should decode"),
+        );
+        let unreconciled = state
+            .withdrawal_transactions
+            .unreconciled_broadcast_attempts();
+        assert_eq!(unreconciled.len(), 1);
+        let (id, attempt, tx) = &unreconciled[0];
+        assert_eq!(id, &withdrawal_id);
+        assert_eq!(attempt.nonce, TransactionNonce::ZERO);
+        assert_eq!(attempt.outcome, None);
+        assert_eq!(tx, &signed_tx);
+
+        // outcome of another transaction of the same withdrawal
+        apply_state_transition(
+            &mut state,
+            &EventType::BroadcastReconciled {
+                withdrawal_id,
+                transaction_hash: Hash([1; 32]),
+                outcome: BroadcastOutcome::Mined,
+            },
+        );
+        assert_eq!(
+            state
+                .withdrawal_transactions
+                .unreconciled_broadcast_attempts()
+                .len(),
+            1
+        );
+
+        apply_state_transition(
+            &mut state,
+            &EventType::BroadcastReconciled {
+                withdrawal_id,
+                transaction_hash: signed_tx.hash(),
+                outcome: BroadcastOutcome::Mined,
+            },
+        );
+        assert_eq!(
+            state
+                .withdrawal_transactions
+                .unreconciled_broadcast_attempts(),
+            vec![]
+        );
+        assert_eq!(
+            state
+                .withdrawal_transactions
+                .get_broadcast_attempt(&withdrawal_id)
+                .and_then(|attempt| attempt.outcome),
+            Some(BroadcastOutcome::Mined)
+        );
+    }
+
+    #[test]
+    fn should_only_hold_back_withdrawal_with_unreconciled_attempt() {
+        let (mut state, signed_tx) = state_with_sent_transaction();
+        let withdrawal_id = LedgerBurnIndex::new(7);
+        let latest_transaction_count = TransactionCount::ZERO;
+        let to_send = |state: &State| {
+            state
+                .withdrawal_transactions
+                .transactions_to_send_batch(latest_transaction_count, 10)
+        };
+        assert_eq!(to_send(&state), vec![(withdrawal_id, signed_tx.clone())]);
+
+        apply_state_transition(&mut state, &broadcast_attempted(&signed_tx));
+        assert!(state
+            .withdrawal_transactions
+            .has_unreconciled_broadcast_attempt(&withdrawal_id));
+        assert_eq!(to_send(&state), vec![]);
+        let gas_fee_estimate = GasFeeEstimate {
+            base_fee_per_gas: WeiPerGas::new(10_000_000_000),
+            max_priority_fee_per_gas: WeiPerGas::new(10_000_000_000),
+            base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+        };
+        assert_eq!(
+            state
+                .withdrawal_transactions
+                .create_resubmit_transactions(latest_transaction_count, gas_fee_estimate.clone()),
+            vec![]
+        );
+        assert_eq!(
+            state.withdrawal_transactions.transaction_to_park(
+                latest_transaction_count,
+                &gas_fee_estimate,
+                0
+            ),
+            None
+        );
+
+        apply_state_transition(
+            &mut state,
+            &EventType::BroadcastReconciled {
+                withdrawal_id,
+                transaction_hash: signed_tx.hash(),
+                outcome: BroadcastOutcome::NotMined,
+            },
+        );
+        assert_eq!(to_send(&state), vec![(withdrawal_id, signed_tx)]);
+    }
+
+    #[test]
+    fn should_remove_attempt_once_transaction_is_finalized() {
+        let (mut state, signed_tx) = state_with_sent_transaction();
+        let withdrawal_id = LedgerBurnIndex::new(7);
+        apply_state_transition(&mut state, &broadcast_attempted(&signed_tx));
+
+        apply_state_transition(
+            &mut state,
+            &EventType::FinalizedTransaction {
+                withdrawal_id,
+                transaction_receipt: TransactionReceipt {
+                    block_hash: Hash([2; 32]),
+                    block_number: BlockNumber::new(4_190_269),
+                    effective_gas_price: WeiPerGas::new(1_000_000_000),
+                    gas_used: GasAmount::from(21_000_u32),
+                    status: TransactionStatus::Success,
+                    transaction_hash: signed_tx.hash(),
+                },
+            },
+        );
+
+        assert_eq!(
+            state
+                .withdrawal_transactions
+                .get_broadcast_attempt(&withdrawal_id),
+            None
+        );
+    }
 }
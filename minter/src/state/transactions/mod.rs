@@ -7,14 +7,16 @@ use crate::candid_types::withdraw_native::SwapDetails;
 use crate::candid_types::SwapStatus;
 use crate::candid_types::{
     withdraw_native::WithdrawalStatus, RetrieveWithdrawalStatus, Transaction, TxFinalizedStatus,
+    WithdrawalTransactionStats,
 };
 use crate::evm_config::EvmNetwork;
 use crate::logs::INFO;
 use crate::map::MultiKeyMap;
-use crate::numeric::{Erc20TokenAmount, Erc20Value, LedgerBurnIndex, Wei};
+use crate::numeric::{BlockNumber, Erc20TokenAmount, Erc20Value, LedgerBurnIndex, Wei};
 use crate::numeric::{GasAmount, LedgerMintIndex, TransactionCount, TransactionNonce};
 use crate::rpc_declarations::{Data, Hash, TransactionReceipt, TransactionStatus};
 use crate::state::transactions::data::{Command, TransactionCallData};
+use crate::tx::cancellation::is_cancellation_transaction;
 use crate::tx::gas_fees::GasFeeEstimate;
 use crate::tx::{
     Eip1559TransactionRequest, FinalizedEip1559Transaction, ResubmissionStrategy,
@@ -30,6 +32,9 @@ use std::cmp::min;
 use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::{fmt, panic};
 
+/// Maximum number of status transitions kept per swap.
+pub const MAX_SWAP_STATUS_HISTORY_LENGTH: usize = 20;
+
 #[derive(Clone, Eq, PartialEq, Encode, Decode)]
 #[cbor(transparent)]
 pub struct Subaccount(#[cbor(n(0), with = "minicbor::bytes")] pub [u8; 32]);
@@ -69,6 +74,14 @@ pub struct NativeWithdrawalRequest {
     /// Fee taken for covering the signing, rpc calls, and other incfraustructure costs
     #[n(7)]
     pub withdrawal_fee: Option<Wei>,
+
+    /// Whether code was deployed at the destination when the request was accepted,
+    /// in which case the transaction uses the higher gas limit of
+    /// [`GasLimitKind::NativeWithdrawalToContract`](crate::evm_config::GasLimitKind).
+    /// Only set for older requests, the destination of newer ones is checked when their
+    /// transaction is created.
+    #[n(8)]
+    pub is_contract_destination: Option<bool>,
 }
 
 /// ERC-20(both unlocking erc20 tokens, and minting wrappped icrc tokens) withdrawal request issued by the user.
@@ -117,6 +130,11 @@ pub struct Erc20WithdrawalRequest {
     /// locked on the icp side     
     #[n(12)]
     pub is_wrapped_mint: Option<bool>,
+
+    /// Gas limit with which `max_transaction_fee` was charged, `None` for requests accepted
+    /// before it was recorded, which were charged with the default gas limit of the network.
+    #[n(13)]
+    pub gas_limit: Option<GasAmount>,
 }
 
 /// ERC-20(both unlocking erc20 tokens, and minting wrappped icrc tokens) withdrawal request issued by the user.
@@ -149,6 +167,25 @@ pub struct Erc20Approve {
     /// Fee taken for covering the signing, rpc calls, and other incfraustructure costs
     #[n(8)]
     pub withdrawal_fee: Option<Wei>,
+    /// Set when the transaction revokes the approval given to `swap_contract_address`
+    /// by approving a zero allowance, instead of granting an unlimited one.
+    #[n(9)]
+    pub is_revocation: Option<bool>,
+}
+
+impl Erc20Approve {
+    pub fn is_revocation(&self) -> bool {
+        self.is_revocation.unwrap_or_default()
+    }
+
+    /// Allowance set by the approval transaction.
+    pub fn approved_value(&self) -> Erc20Value {
+        if self.is_revocation() {
+            Erc20Value::ZERO
+        } else {
+            Erc20Value::MAX
+        }
+    }
 }
 
 ///  Defines a struct for an ExecuteSwapRequest
@@ -242,6 +279,7 @@ impl fmt::Debug for NativeWithdrawalRequest {
             created_at,
             l1_fee,
             withdrawal_fee,
+            is_contract_destination,
         } = self;
         f.debug_struct("NativeWithdrawalRequest")
             .field("withdrawal_amount", withdrawal_amount)
@@ -252,6 +290,7 @@ impl fmt::Debug for NativeWithdrawalRequest {
             .field("created_at", created_at)
             .field("l1_fee", l1_fee)
             .field("withdrawal_fee", withdrawal_fee)
+            .field("is_contract_destination", is_contract_destination)
             .finish()
     }
 }
@@ -272,6 +311,7 @@ impl fmt::Debug for Erc20WithdrawalRequest {
             l1_fee,
             withdrawal_fee,
             is_wrapped_mint,
+            gas_limit,
         } = self;
         f.debug_struct("Erc20WithdrawalRequest")
             .field("max_transaction_fee", max_transaction_fee)
@@ -287,6 +327,7 @@ impl fmt::Debug for Erc20WithdrawalRequest {
             .field("l1_fee", l1_fee)
             .field("withdrawal_fee", withdrawal_fee)
             .field("is_wrapped_mint", is_wrapped_mint)
+            .field("gas_limit", gas_limit)
             .finish()
     }
 }
@@ -303,6 +344,7 @@ impl fmt::Debug for Erc20Approve {
             l1_fee,
             withdrawal_fee,
             swap_contract_address,
+            is_revocation,
         } = self;
         f.debug_struct("Erc20Approve")
             .field("max_transaction_fee", max_transaction_fee)
@@ -314,10 +356,20 @@ impl fmt::Debug for Erc20Approve {
             .field("l1_fee", l1_fee)
             .field("withdrawal_fee", withdrawal_fee)
             .field("swap_contract_address", swap_contract_address)
+            .field("is_revocation", is_revocation)
             .finish()
     }
 }
 
+impl ExecuteSwapRequest {
+    /// Returns true if the request transfers the input tokens of a failed swap or dex order
+    /// back to the recipient without executing any command. The deadline of such a refund is
+    /// replaced by [`crate::state::State::refund_deadline`] when its transaction is created.
+    pub fn is_plain_refund(&self) -> bool {
+        self.is_refund && self.commands.is_empty()
+    }
+}
+
 impl fmt::Debug for ExecuteSwapRequest {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         let ExecuteSwapRequest {
@@ -374,12 +426,29 @@ pub enum WithdrawalSearchParameter {
     BySenderAccount(Account),
 }
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug, Encode, Decode)]
 pub enum WithdrawalRequest {
-    Native(NativeWithdrawalRequest),
-    Erc20(Erc20WithdrawalRequest),
-    Erc20Approve(Erc20Approve),
-    Swap(ExecuteSwapRequest),
+    #[n(0)]
+    Native(#[n(0)] NativeWithdrawalRequest),
+    #[n(1)]
+    Erc20(#[n(0)] Erc20WithdrawalRequest),
+    #[n(2)]
+    Erc20Approve(#[n(0)] Erc20Approve),
+    #[n(3)]
+    Swap(#[n(0)] ExecuteSwapRequest),
+}
+
+/// Queue in which a pending withdrawal request waits for its transaction to be created.
+///
+/// Requests are processed in FIFO order within a lane and lanes take turns, so that a
+/// burst of requests of one kind, or a token whose transfers keep getting stuck, does not
+/// delay the requests of the other lanes.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum WithdrawalLane {
+    Native,
+    Erc20 { ledger_id: Principal },
+    Erc20Approve,
+    Swap,
 }
 
 impl WithdrawalRequest {
@@ -392,6 +461,17 @@ impl WithdrawalRequest {
         }
     }
 
+    pub fn lane(&self) -> WithdrawalLane {
+        match self {
+            WithdrawalRequest::Native(_) => WithdrawalLane::Native,
+            WithdrawalRequest::Erc20(request) => WithdrawalLane::Erc20 {
+                ledger_id: request.erc20_ledger_id,
+            },
+            WithdrawalRequest::Erc20Approve(_) => WithdrawalLane::Erc20Approve,
+            WithdrawalRequest::Swap(_) => WithdrawalLane::Swap,
+        }
+    }
+
     pub fn created_at(&self) -> Option<u64> {
         match self {
             WithdrawalRequest::Native(request) => request.created_at,
@@ -603,8 +683,76 @@ impl ReimbursementIndex {
     }
 }
 
+/// Reimbursement of a withdrawal that failed before any EVM transaction was sent,
+/// e.g. when the ERC-20 burn failed after the native fee was burned.
+#[derive(Debug, Clone, Eq, PartialEq, Encode)]
+pub struct PendingReimbursementRequest {
+    /// Burn index on the ledger that should be reimbursed.
+    #[cbor(n(0), with = "crate::cbor::id")]
+    pub ledger_burn_index: LedgerBurnIndex,
+    /// The amount that should be reimbursed in the smallest denomination.
+    #[n(1)]
+    pub reimbursed_amount: Erc20TokenAmount,
+    #[cbor(n(2), with = "crate::cbor::principal")]
+    pub to: Principal,
+    #[n(3)]
+    pub to_subaccount: Option<Subaccount>,
+}
+
+impl PendingReimbursementRequest {
+    /// Promotes the request once the EVM transaction it reimburses is finalized.
+    pub fn finalize(self, transaction_hash: Hash) -> FinalizedReimbursementRequest {
+        FinalizedReimbursementRequest {
+            ledger_burn_index: self.ledger_burn_index,
+            reimbursed_amount: self.reimbursed_amount,
+            to: self.to,
+            to_subaccount: self.to_subaccount,
+            transaction_hash,
+        }
+    }
+}
+
+/// Layout of the reimbursement requests recorded in events before pending and finalized
+/// requests were split. Events recorded with this layout are migrated when replayed
+/// in `post_upgrade`.
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
+pub(crate) struct LegacyReimbursementRequest {
+    #[cbor(n(0), with = "crate::cbor::id")]
+    pub ledger_burn_index: LedgerBurnIndex,
+    #[n(1)]
+    pub reimbursed_amount: Erc20TokenAmount,
+    #[cbor(n(2), with = "crate::cbor::principal")]
+    pub to: Principal,
+    #[n(3)]
+    pub to_subaccount: Option<Subaccount>,
+    #[n(4)]
+    pub transaction_hash: Option<Hash>,
+}
+
+impl From<LegacyReimbursementRequest> for PendingReimbursementRequest {
+    fn from(legacy: LegacyReimbursementRequest) -> Self {
+        // Only failed burns were recorded as events and no EVM transaction was ever sent for them,
+        // so the transaction hash carries no information.
+        Self {
+            ledger_burn_index: legacy.ledger_burn_index,
+            reimbursed_amount: legacy.reimbursed_amount,
+            to: legacy.to,
+            to_subaccount: legacy.to_subaccount,
+        }
+    }
+}
+
+// Decoding accepts both the current and the legacy layout, the latter being a superset.
+impl<'b, C> minicbor::Decode<'b, C> for PendingReimbursementRequest {
+    fn decode(d: &mut minicbor::Decoder<'b>, ctx: &mut C) -> Result<Self, minicbor::decode::Error> {
+        d.decode_with(ctx)
+            .map(|legacy: LegacyReimbursementRequest| legacy.into())
+    }
+}
+
+/// Reimbursement of a withdrawal whose EVM transaction was finalized but failed.
 #[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
-pub struct ReimbursementRequest {
+pub struct FinalizedReimbursementRequest {
     /// Burn index on the ledger that should be reimbursed.
     #[cbor(n(0), with = "crate::cbor::id")]
     pub ledger_burn_index: LedgerBurnIndex,
@@ -615,11 +763,68 @@ pub struct ReimbursementRequest {
     pub to: Principal,
     #[n(3)]
     pub to_subaccount: Option<Subaccount>,
-    /// Transaction hash of the failed ETH transaction.
+    /// Transaction hash of the failed EVM transaction.
     /// We use this hash to link the mint reimbursement transaction
-    /// on the ledger with the failed ETH transaction.
+    /// on the ledger with the failed EVM transaction.
     #[n(4)]
-    pub transaction_hash: Option<Hash>,
+    pub transaction_hash: Hash,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
+pub enum ReimbursementRequest {
+    #[n(0)]
+    Pending(#[n(0)] PendingReimbursementRequest),
+    #[n(1)]
+    Finalized(#[n(0)] FinalizedReimbursementRequest),
+}
+
+impl ReimbursementRequest {
+    pub fn ledger_burn_index(&self) -> LedgerBurnIndex {
+        match self {
+            ReimbursementRequest::Pending(request) => request.ledger_burn_index,
+            ReimbursementRequest::Finalized(request) => request.ledger_burn_index,
+        }
+    }
+
+    pub fn reimbursed_amount(&self) -> Erc20TokenAmount {
+        match self {
+            ReimbursementRequest::Pending(request) => request.reimbursed_amount,
+            ReimbursementRequest::Finalized(request) => request.reimbursed_amount,
+        }
+    }
+
+    pub fn to(&self) -> Principal {
+        match self {
+            ReimbursementRequest::Pending(request) => request.to,
+            ReimbursementRequest::Finalized(request) => request.to,
+        }
+    }
+
+    pub fn to_subaccount(&self) -> Option<&Subaccount> {
+        match self {
+            ReimbursementRequest::Pending(request) => request.to_subaccount.as_ref(),
+            ReimbursementRequest::Finalized(request) => request.to_subaccount.as_ref(),
+        }
+    }
+
+    pub fn transaction_hash(&self) -> Option<Hash> {
+        match self {
+            ReimbursementRequest::Pending(_) => None,
+            ReimbursementRequest::Finalized(request) => Some(request.transaction_hash),
+        }
+    }
+}
+
+impl From<PendingReimbursementRequest> for ReimbursementRequest {
+    fn from(request: PendingReimbursementRequest) -> Self {
+        ReimbursementRequest::Pending(request)
+    }
+}
+
+impl From<FinalizedReimbursementRequest> for ReimbursementRequest {
+    fn from(request: FinalizedReimbursementRequest) -> Self {
+        ReimbursementRequest::Finalized(request)
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
@@ -642,18 +847,20 @@ pub struct Reimbursed {
 
 pub type ReimbursedResult = Result<Reimbursed, ReimbursedError>;
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
 pub enum ReimbursedError {
     /// Whether reimbursement was (minted, released) or not is unknown,
     /// most likely because there was an unexpected panic in the callback.
     /// The reimbursement request is quarantined to avoid any double (minting, releaseing) and
     /// will not be further processed without manual intervention.
+    #[n(0)]
     Quarantined,
 }
 
 /// State machine holding EVM transactions issued by the minter.
 /// Overall the transaction lifecycle is as follows:
-/// 1. The user's withdrawal request is enqueued and processed in a FIFO order.
+/// 1. The user's withdrawal request is enqueued and processed in a FIFO order within its
+///    [`WithdrawalLane`].
 /// 2. A transaction is created by either consuming a withdrawal request
 ///    (the first time a transaction is created for that nonce and burn index)
 ///    or re-submitting an already sent transaction for that nonce and burn index.
@@ -670,6 +877,14 @@ pub enum ReimbursedError {
 ///    users with USDC equivalent
 ///
 /// 8. If the swap refund fails for any reason(low usdc amount or etc) it will be quarantined.
+///
+/// 9. If the transaction with the lowest pending nonce was replaced too many times without being
+///    mined, it is parked: its nonce is freed by a cancellation transaction and, once the
+///    cancellation is finalized, the withdrawal is reimbursed like a failed transaction.
+///
+/// 10. Before a signed transaction is sent for the first time, a [`BroadcastAttempt`] is
+///     recorded. An attempt without outcome was interrupted, e.g. by an upgrade or a trap,
+///     and is reconciled before any other transaction is sent.
 #[derive(Clone, Debug, Eq, PartialEq)]
 
 pub struct WithdrawalTransactions {
@@ -692,6 +907,71 @@ pub struct WithdrawalTransactions {
     // Key = swap_tx_id
     pub(in crate::state) failed_swap_requests: BTreeMap<String, ExecuteSwapRequest>,
     pub(in crate::state) quarantined_swap_requests: BTreeMap<String, ExecuteSwapRequest>,
+
+    // Key = swap_tx_id, value = status transitions with the timestamp of the event causing them
+    pub(in crate::state) swap_status_history: BTreeMap<String, Vec<(u64, SwapStatus)>>,
+
+    // Key = swap_tx_id, value = burn index on the native ledger of its latest swap request
+    pub(in crate::state) swap_burn_indexes: BTreeMap<String, LedgerBurnIndex>,
+
+    // Key = burn index of a parked withdrawal, value = destination of its cancellation transaction
+    pub(in crate::state) parked: BTreeMap<LedgerBurnIndex, Address>,
+
+    // Key = burn index of a successful swap, value = amount of output tokens received by its recipient
+    pub(in crate::state) realized_swap_amounts_out: BTreeMap<LedgerBurnIndex, Erc20Value>,
+
+    // Key = (ERC-20 or ICRC ledger id, burn or lock index on that ledger),
+    // value = burn index on the native ledger identifying the withdrawal
+    pub(in crate::state) erc20_burn_indexes:
+        BTreeMap<(Principal, LedgerBurnIndex), LedgerBurnIndex>,
+
+    // Key = ledger id of the withdrawn token, value = (finalization timestamp, burn index on
+    // the native ledger) of the successful withdrawals of that token
+    pub(in crate::state) finalized_withdrawals:
+        BTreeMap<Principal, BTreeSet<(u64, LedgerBurnIndex)>>,
+
+    // Key = burn index of a withdrawal with a sent transaction, value = last attempt to send
+    // one of its transactions
+    pub(in crate::state) broadcast_attempts: BTreeMap<LedgerBurnIndex, BroadcastAttempt>,
+}
+
+/// Sending of a signed transaction, recorded before awaiting `eth_sendRawTransaction`.
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
+pub struct BroadcastAttempt {
+    /// Hash of the signed transaction, i.e. the Keccak-256 hash of the raw transaction.
+    #[n(0)]
+    pub transaction_hash: Hash,
+    #[n(1)]
+    pub nonce: TransactionNonce,
+    #[n(2)]
+    pub timestamp: u64,
+    /// `None` until the attempt is known to have reached the network.
+    #[n(3)]
+    pub outcome: Option<BroadcastOutcome>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Encode, Decode)]
+#[cbor(index_only)]
+pub enum BroadcastOutcome {
+    /// The provider answered `eth_sendRawTransaction`.
+    #[n(0)]
+    Sent,
+    /// A receipt was found, or the transaction count of the minter is past the nonce.
+    #[n(1)]
+    Mined,
+    /// The transaction was not mined and was sent again.
+    #[n(2)]
+    NotMined,
+}
+
+impl fmt::Display for BroadcastOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BroadcastOutcome::Sent => "Sent",
+            BroadcastOutcome::Mined => "Mined",
+            BroadcastOutcome::NotMined => "NotMined",
+        })
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -727,6 +1007,13 @@ impl WithdrawalTransactions {
             reimbursed: Default::default(),
             failed_swap_requests: Default::default(),
             quarantined_swap_requests: Default::default(),
+            swap_status_history: Default::default(),
+            swap_burn_indexes: Default::default(),
+            parked: Default::default(),
+            realized_swap_amounts_out: Default::default(),
+            erc20_burn_indexes: Default::default(),
+            finalized_withdrawals: Default::default(),
+            broadcast_attempts: Default::default(),
         }
     }
 
@@ -770,6 +1057,27 @@ impl WithdrawalTransactions {
             })
     }
 
+    /// Returns true if the withdrawal was either reimbursed, or its transaction succeeded
+    /// in a block before `threshold_block`, so that it will not change anymore.
+    pub fn is_settled_withdrawal(
+        &self,
+        burn_index: &LedgerBurnIndex,
+        threshold_block: BlockNumber,
+    ) -> bool {
+        let is_reimbursed = matches!(
+            self.find_reimbursed_transaction_by_native_token_ledger_burn_index(burn_index),
+            Some(Ok(_))
+        );
+        match self.finalized_tx.get_alt(burn_index) {
+            Some(tx) => {
+                tx.block_number() < &threshold_block
+                    && (tx.transaction_status() == &TransactionStatus::Success || is_reimbursed)
+            }
+            // withdrawals cancelled before any transaction was created
+            None => is_reimbursed,
+        }
+    }
+
     pub fn record_withdrawal_request<R: Into<WithdrawalRequest>>(&mut self, request: R) {
         let request: WithdrawalRequest = request.into();
         let burn_index = request.native_ledger_burn_index();
@@ -783,9 +1091,36 @@ impl WithdrawalTransactions {
         {
             panic!("BUG: duplicate Native ledger burn index {burn_index}");
         }
+        if let WithdrawalRequest::Erc20(erc20_request) = &request {
+            self.erc20_burn_indexes.insert(
+                (
+                    erc20_request.erc20_ledger_id,
+                    erc20_request.erc20_ledger_burn_index,
+                ),
+                burn_index,
+            );
+        }
+        if let WithdrawalRequest::Swap(swap_request) = &request {
+            index_swap_request(&mut self.swap_burn_indexes, swap_request);
+        }
         self.pending_withdrawal_requests.push_back(request);
     }
 
+    /// Rebuilds the index of the swap requests by swap_tx_id from the withdrawal requests.
+    pub(in crate::state) fn reindex_swap_requests(&mut self) {
+        let mut swap_burn_indexes = BTreeMap::new();
+        for request in self
+            .pending_withdrawal_requests
+            .iter()
+            .chain(self.processed_withdrawal_requests.values())
+        {
+            if let WithdrawalRequest::Swap(swap_request) = request {
+                index_swap_request(&mut swap_burn_indexes, swap_request);
+            }
+        }
+        self.swap_burn_indexes = swap_burn_indexes;
+    }
+
     /// Move an existing withdrawal request to the back of the queue.
     pub fn reschedule_withdrawal_request<R: Into<WithdrawalRequest>>(&mut self, request: R) {
         let request = request.into();
@@ -802,6 +1137,48 @@ impl WithdrawalTransactions {
         self.record_withdrawal_request(request);
     }
 
+    /// Returns the pending withdrawal request with the given burn index, i.e. a request for
+    /// which no transaction was created yet.
+    pub fn pending_withdrawal_request(
+        &self,
+        burn_index: &LedgerBurnIndex,
+    ) -> Option<&WithdrawalRequest> {
+        self.pending_withdrawal_requests
+            .iter()
+            .find(|r| &r.native_ledger_burn_index() == burn_index)
+    }
+
+    /// Returns the withdrawal request with the given burn index, whether it is still pending
+    /// or was already processed.
+    pub fn withdrawal_request(&self, burn_index: &LedgerBurnIndex) -> Option<&WithdrawalRequest> {
+        self.pending_withdrawal_request(burn_index)
+            .or_else(|| self.processed_withdrawal_requests.get(burn_index))
+    }
+
+    /// Removes a pending native withdrawal request from the queue and schedules the
+    /// reimbursement of the burned tokens.
+    pub fn record_cancelled_withdrawal_request(
+        &mut self,
+        reimbursement: PendingReimbursementRequest,
+    ) {
+        let burn_index = reimbursement.ledger_burn_index;
+        let request = self
+            .pending_withdrawal_request(&burn_index)
+            .cloned()
+            .unwrap_or_else(|| panic!("BUG: pending withdrawal request {burn_index} not found"));
+        assert!(
+            matches!(request, WithdrawalRequest::Native(_)),
+            "BUG: only native withdrawal requests can be cancelled"
+        );
+        self.remove_withdrawal_request(&request);
+        self.record_reimbursement_request(
+            ReimbursementIndex::Native {
+                ledger_burn_index: burn_index,
+            },
+            reimbursement.into(),
+        );
+    }
+
     pub fn record_created_transaction(
         &mut self,
         withdrawal_id: LedgerBurnIndex,
@@ -859,6 +1236,18 @@ impl WithdrawalTransactions {
             .checked_increment()
             .expect("Transaction nonce overflow");
         self.remove_withdrawal_request(&withdrawal_request);
+        let withdrawal_request = match withdrawal_request {
+            // The deadline of a refund is only known once its transaction is created.
+            WithdrawalRequest::Swap(mut request) if request.is_plain_refund() => {
+                if let Ok(TransactionCallData::ExecuteSwap { deadline, .. }) =
+                    TransactionCallData::decode(&transaction.data)
+                {
+                    request.deadline = deadline;
+                }
+                WithdrawalRequest::Swap(request)
+            }
+            request => request,
+        };
         let transaction_request = TransactionRequest {
             transaction,
             resubmission: match &withdrawal_request {
@@ -946,6 +1335,14 @@ impl WithdrawalTransactions {
             .filter(|(nonce, _burn_index, _signed_tx)| *nonce >= &first_pending_tx_nonce)
         {
             let last_signed_tx = signed_tx.last().expect("BUG: empty sent transactions list");
+            if self.has_unreconciled_broadcast_attempt(burn_index) {
+                continue;
+            }
+            if self.is_awaiting_cancellation_signature(burn_index, last_signed_tx.as_ref()) {
+                // The parked transaction must not be resubmitted,
+                // its cancellation is created but not yet signed.
+                continue;
+            }
             match last_signed_tx.resubmit(current_gas_fee.clone()) {
                 Ok(Some(new_tx)) => {
                     transactions_to_resubmit.push(Ok((*burn_index, new_tx)));
@@ -989,6 +1386,113 @@ impl WithdrawalTransactions {
         );
     }
 
+    /// Returns the sent transaction with the lowest pending nonce if it is not parked yet and either
+    /// * it was already replaced at least `max_replacements` times, or
+    /// * it cannot be resubmitted with the current gas fee because its fee is capped,
+    ///   see [`ResubmitTransactionError::InsufficientTransactionFee`].
+    ///
+    /// Only that transaction is considered since the transactions with higher nonces
+    /// cannot be mined before it anyway and are resubmitted for that reason alone.
+    pub fn transaction_to_park(
+        &self,
+        latest_transaction_count: TransactionCount,
+        current_gas_fee: &GasFeeEstimate,
+        max_replacements: usize,
+    ) -> Option<(LedgerBurnIndex, Eip1559TransactionRequest)> {
+        let first_pending_tx_nonce: TransactionNonce = latest_transaction_count.change_units();
+        let (burn_index, sent_txs) = self.sent_tx.get_entry(&first_pending_tx_nonce)?;
+        if self.parked.contains_key(burn_index)
+            || self.has_unreconciled_broadcast_attempt(burn_index)
+        {
+            return None;
+        }
+        let last_sent_tx = sent_txs.last().expect("BUG: empty sent transactions list");
+        let is_fee_capped = last_sent_tx.resubmit(current_gas_fee.clone()).is_err();
+        if sent_txs.len() <= max_replacements && !is_fee_capped {
+            return None;
+        }
+        Some((*burn_index, last_sent_tx.as_ref().transaction().clone()))
+    }
+
+    /// Replaces the last sent transaction of the given withdrawal by a cancellation
+    /// transaction with the same nonce, see [`crate::tx::cancellation`].
+    pub fn record_parked_transaction(
+        &mut self,
+        ledger_burn_index: LedgerBurnIndex,
+        cancellation: Eip1559TransactionRequest,
+    ) {
+        let nonce = cancellation.nonce;
+        let (sent_burn_index, last_sent_tx) =
+            Self::expect_last_sent_tx_entry(&self.sent_tx, &nonce);
+        assert_eq!(
+            sent_burn_index, &ledger_burn_index,
+            "BUG: cancellation nonce {nonce} does not belong to withdrawal {ledger_burn_index}"
+        );
+        let allowed_max_transaction_fee = last_sent_tx
+            .resubmission
+            .allowed_max_transaction_fee()
+            .max(cancellation.transaction_price().max_transaction_fee());
+        let cancellation_destination = cancellation.destination;
+        Self::cleanup_failed_resubmitted_transactions(&mut self.created_tx, &nonce);
+        assert_eq!(
+            self.created_tx.try_insert(
+                nonce,
+                ledger_burn_index,
+                TransactionRequest {
+                    transaction: cancellation,
+                    resubmission: ResubmissionStrategy::GuaranteeEthAmount {
+                        allowed_max_transaction_fee,
+                    },
+                },
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            self.parked
+                .insert(ledger_burn_index, cancellation_destination),
+            None,
+            "BUG: withdrawal {ledger_burn_index} is already parked"
+        );
+    }
+
+    /// Returns true if the finalized transaction of the given withdrawal is the cancellation
+    /// of a parked transaction.
+    pub fn is_cancelled_withdrawal(&self, ledger_burn_index: &LedgerBurnIndex) -> bool {
+        match (
+            self.parked.get(ledger_burn_index),
+            self.finalized_tx.get_alt(ledger_burn_index),
+        ) {
+            (Some(cancellation_destination), Some(finalized_tx)) => {
+                is_cancellation_transaction(finalized_tx.transaction(), cancellation_destination)
+            }
+            _ => false,
+        }
+    }
+
+    fn is_awaiting_cancellation_signature(
+        &self,
+        ledger_burn_index: &LedgerBurnIndex,
+        last_sent_tx: &SignedEip1559TransactionRequest,
+    ) -> bool {
+        self.parked
+            .get(ledger_burn_index)
+            .is_some_and(|cancellation_destination| {
+                !is_cancellation_transaction(last_sent_tx.transaction(), cancellation_destination)
+            })
+    }
+
+    /// Lanes with a parked withdrawal whose cancellation is not finalized yet.
+    fn lanes_with_parked_withdrawals(&self) -> BTreeSet<WithdrawalLane> {
+        self.parked
+            .keys()
+            .filter(|burn_index| {
+                self.created_tx.contains_alt(burn_index) || self.sent_tx.contains_alt(burn_index)
+            })
+            .filter_map(|burn_index| self.processed_withdrawal_requests.get(burn_index))
+            .map(WithdrawalRequest::lane)
+            .collect()
+    }
+
     pub fn sent_transactions_to_finalize(
         &self,
         finalized_transaction_count: &TransactionCount,
@@ -1033,6 +1537,7 @@ impl WithdrawalTransactions {
         {
             self.sent_tx.remove_entry(&nonce);
             Self::cleanup_failed_resubmitted_transactions(&mut self.created_tx, &nonce);
+            self.broadcast_attempts.remove(&ledger_burn_index);
         }
         assert_eq!(
             self.finalized_tx
@@ -1049,50 +1554,106 @@ impl WithdrawalTransactions {
             .get(&ledger_burn_index)
             .expect("failed to find entry from processed_withdrawal_requests with block index: {ledger_burn_index}");
         let index = ReimbursementIndex::from(request);
+        let is_cancelled = self.is_cancelled_withdrawal(&ledger_burn_index);
 
         match &request {
+            WithdrawalRequest::Native(request) if is_cancelled => {
+                // The user pays for the cancellation, like for a failed transaction.
+                let reimbursed_amount = request
+                    .withdrawal_amount
+                    .checked_sub(finalized_tx.effective_transaction_fee())
+                    .and_then(|amount| amount.checked_sub(request.l1_fee.unwrap_or(Wei::ZERO)))
+                    .and_then(|amount| {
+                        amount.checked_sub(request.withdrawal_fee.unwrap_or(Wei::ZERO))
+                    })
+                    .unwrap_or(Wei::ZERO);
+                self.record_reimbursement_request(
+                    index,
+                    PendingReimbursementRequest {
+                        ledger_burn_index,
+                        to: request.from,
+                        to_subaccount: request.from_subaccount.clone(),
+                        reimbursed_amount: reimbursed_amount.change_units(),
+                    }
+                    .finalize(receipt.transaction_hash)
+                    .into(),
+                );
+            }
             WithdrawalRequest::Native(request) => {
                 if receipt.status == TransactionStatus::Failure {
                     self.record_reimbursement_request(
                         index,
-                        ReimbursementRequest {
+                        PendingReimbursementRequest {
                             ledger_burn_index,
                             to: request.from,
                             to_subaccount: request.from_subaccount.clone(),
                             reimbursed_amount: finalized_tx.transaction_amount().change_units(),
-                            transaction_hash: Some(receipt.transaction_hash),
-                        },
+                        }
+                        .finalize(receipt.transaction_hash)
+                        .into(),
                     );
                 }
             }
             WithdrawalRequest::Erc20(request) => {
-                if receipt.status == TransactionStatus::Failure {
+                if receipt.status == TransactionStatus::Failure || is_cancelled {
                     self.record_reimbursement_request(
                         index,
-                        ReimbursementRequest {
+                        PendingReimbursementRequest {
                             ledger_burn_index: request.erc20_ledger_burn_index,
                             reimbursed_amount: request.withdrawal_amount.change_units(),
                             to: request.from,
                             to_subaccount: request.from_subaccount.clone(),
-                            transaction_hash: Some(receipt.transaction_hash),
-                        },
+                        }
+                        .finalize(receipt.transaction_hash)
+                        .into(),
                     );
                 }
             }
             WithdrawalRequest::Erc20Approve(_request) => {
-                if receipt.status == TransactionStatus::Failure {
+                if receipt.status == TransactionStatus::Failure || is_cancelled {
                     log!(INFO,"ERC20 approval failed, and there is no reimbursment for failed erc20 transactions");
                 }
             }
 
             WithdrawalRequest::Swap(request) => {
-                if receipt.status == TransactionStatus::Failure {
+                if receipt.status == TransactionStatus::Failure || is_cancelled {
                     self.record_failed_swap_request(request.clone());
                 }
             }
         }
     }
 
+    pub fn record_realized_swap_amount_out(
+        &mut self,
+        ledger_burn_index: LedgerBurnIndex,
+        amount_out: Erc20Value,
+    ) {
+        assert!(
+            matches!(
+                self.processed_withdrawal_requests.get(&ledger_burn_index),
+                Some(WithdrawalRequest::Swap(_))
+            ),
+            "BUG: no processed swap request with burn index {ledger_burn_index}"
+        );
+        assert_eq!(
+            self.realized_swap_amounts_out
+                .insert(ledger_burn_index, amount_out),
+            None,
+            "BUG: realized amount out of swap with burn index {ledger_burn_index} was already recorded"
+        );
+    }
+
+    /// Returns the amount of output tokens received by the recipient of the swap with the given
+    /// burn index, if it was parsed from the receipt of the swap transaction.
+    pub fn realized_swap_amount_out(
+        &self,
+        ledger_burn_index: &LedgerBurnIndex,
+    ) -> Option<Erc20Value> {
+        self.realized_swap_amounts_out
+            .get(ledger_burn_index)
+            .copied()
+    }
+
     pub fn record_failed_swap_request(&mut self, request: ExecuteSwapRequest) {
         self.failed_swap_requests
             .insert(request.swap_tx_id.clone(), request);
@@ -1152,8 +1713,8 @@ impl WithdrawalTransactions {
                 Ok(Reimbursed {
                     burn_in_block,
                     reimbursed_in_block,
-                    reimbursed_amount: reimbursement_request.reimbursed_amount,
-                    transaction_hash: reimbursement_request.transaction_hash,
+                    reimbursed_amount: reimbursement_request.reimbursed_amount(),
+                    transaction_hash: reimbursement_request.transaction_hash(),
                     transfer_fee,
                 }),
             ),
@@ -1200,6 +1761,18 @@ impl WithdrawalTransactions {
         pending.chain(processed).collect()
     }
 
+    /// Resolves the burn index on an ERC-20 ledger (or the lock index on an ICRC ledger for
+    /// wrapping requests) into the native ledger burn index identifying the withdrawal.
+    pub fn native_ledger_burn_index_of(
+        &self,
+        ledger_id: &Principal,
+        ledger_burn_index: LedgerBurnIndex,
+    ) -> Option<LedgerBurnIndex> {
+        self.erc20_burn_indexes
+            .get(&(*ledger_id, ledger_burn_index))
+            .copied()
+    }
+
     pub fn transaction_status(&self, burn_index: &LedgerBurnIndex) -> RetrieveWithdrawalStatus {
         if self
             .pending_withdrawal_requests
@@ -1254,6 +1827,7 @@ impl WithdrawalTransactions {
                 RetrieveWithdrawalStatus::TxFinalized(TxFinalizedStatus::Success {
                     transaction_hash: tx.transaction_hash().to_string(),
                     effective_transaction_fee: Some(tx.effective_transaction_fee().into()),
+                    realized_amount_out: self.realized_swap_amount_out(burn_index).map(Into::into),
                 }),
                 Some(tx.as_ref()),
             );
@@ -1275,10 +1849,38 @@ impl WithdrawalTransactions {
                 .saturating_sub(unique_pending_transaction_nonces.len()),
             requested_batch_size,
         );
-        self.withdrawal_requests_iter()
-            .take(actual_batch_size)
-            .cloned()
-            .collect()
+        let blocked_lanes = self.lanes_with_parked_withdrawals();
+        let mut lanes: BTreeMap<WithdrawalLane, VecDeque<&WithdrawalRequest>> = BTreeMap::new();
+        // Lanes take turns in the order in which their oldest request was received.
+        let mut lanes_order = vec![];
+        for request in self.withdrawal_requests_iter() {
+            let lane = request.lane();
+            if blocked_lanes.contains(&lane) {
+                continue;
+            }
+            let queue = lanes.entry(lane).or_insert_with(|| {
+                lanes_order.push(lane);
+                VecDeque::new()
+            });
+            queue.push_back(request);
+        }
+        let mut batch = Vec::with_capacity(actual_batch_size);
+        while batch.len() < actual_batch_size && !lanes.is_empty() {
+            for lane in &lanes_order {
+                if batch.len() >= actual_batch_size {
+                    break;
+                }
+                if let Some(queue) = lanes.get_mut(lane) {
+                    match queue.pop_front() {
+                        Some(request) => batch.push(request.clone()),
+                        None => {
+                            lanes.remove(lane);
+                        }
+                    }
+                }
+            }
+        }
+        batch
     }
 
     pub fn withdrawal_requests_iter(&self) -> impl Iterator<Item = &WithdrawalRequest> {
@@ -1329,18 +1931,95 @@ impl WithdrawalTransactions {
         &self,
         latest_transaction_count: TransactionCount,
         batch_size: usize,
-    ) -> Vec<SignedEip1559TransactionRequest> {
+    ) -> Vec<(LedgerBurnIndex, SignedEip1559TransactionRequest)> {
         let first_pending_tx_nonce: TransactionNonce = latest_transaction_count.change_units();
         self.sent_tx
             .iter()
+            .filter(|(_nonce, ledger_burn_index, _txs)| {
+                !self.has_unreconciled_broadcast_attempt(ledger_burn_index)
+            })
             .filter_map(move |(nonce, ledger_burn_index, txs)| {
                 txs.last()
                     .map(|tx| (nonce, ledger_burn_index, tx))
                     .filter(|(nonce, _ledger_burn_index, _tx)| *nonce >= &first_pending_tx_nonce)
             })
             .take(batch_size)
-            .map(|(_nonce, _index, tx)| tx.as_ref())
-            .cloned()
+            .map(|(_nonce, index, tx)| (*index, tx.as_ref().clone()))
+            .collect()
+    }
+
+    /// Records that `transaction_hash` of the withdrawal is about to be sent, replacing
+    /// the attempt of any previous transaction of that withdrawal.
+    pub fn record_broadcast_attempt(
+        &mut self,
+        withdrawal_id: LedgerBurnIndex,
+        transaction_hash: Hash,
+        nonce: TransactionNonce,
+        timestamp: u64,
+    ) {
+        self.broadcast_attempts.insert(
+            withdrawal_id,
+            BroadcastAttempt {
+                transaction_hash,
+                nonce,
+                timestamp,
+                outcome: None,
+            },
+        );
+    }
+
+    /// Records the outcome of the last attempt of the withdrawal, unless it was meanwhile
+    /// replaced by the attempt of another transaction.
+    pub fn record_broadcast_outcome(
+        &mut self,
+        withdrawal_id: LedgerBurnIndex,
+        transaction_hash: &Hash,
+        outcome: BroadcastOutcome,
+    ) {
+        if let Some(attempt) = self
+            .broadcast_attempts
+            .get_mut(&withdrawal_id)
+            .filter(|attempt| &attempt.transaction_hash == transaction_hash)
+        {
+            attempt.outcome = Some(outcome);
+        }
+    }
+
+    pub fn get_broadcast_attempt(
+        &self,
+        withdrawal_id: &LedgerBurnIndex,
+    ) -> Option<&BroadcastAttempt> {
+        self.broadcast_attempts.get(withdrawal_id)
+    }
+
+    /// Returns true if a transaction of the withdrawal may have been sent without a known
+    /// outcome. It must then neither be sent again nor replaced until the attempt is
+    /// reconciled, since it may have landed.
+    pub fn has_unreconciled_broadcast_attempt(&self, withdrawal_id: &LedgerBurnIndex) -> bool {
+        self.broadcast_attempts
+            .get(withdrawal_id)
+            .is_some_and(|attempt| attempt.outcome.is_none())
+    }
+
+    /// Attempts without outcome together with the signed transaction they were sending.
+    pub fn unreconciled_broadcast_attempts(
+        &self,
+    ) -> Vec<(
+        LedgerBurnIndex,
+        BroadcastAttempt,
+        SignedEip1559TransactionRequest,
+    )> {
+        self.broadcast_attempts
+            .iter()
+            .filter(|(_withdrawal_id, attempt)| attempt.outcome.is_none())
+            .filter_map(|(withdrawal_id, attempt)| {
+                self.sent_tx
+                    .get_alt(withdrawal_id)?
+                    .iter()
+                    .map(|tx| tx.as_ref())
+                    .find(|tx| tx.hash() == attempt.transaction_hash)
+                    .map(|tx| (*withdrawal_id, attempt.clone(), tx.clone()))
+            })
             .collect()
     }
 
@@ -1392,6 +2071,13 @@ impl WithdrawalTransactions {
         self.sent_tx.is_empty()
     }
 
+    /// Returns the number of accepted withdrawals whose transaction is not finalized yet.
+    pub fn unfinalized_withdrawals_count(&self) -> usize {
+        self.pending_withdrawal_requests.len()
+            + self.created_tx.keys().count()
+            + self.sent_tx.keys().count()
+    }
+
     pub fn has_pending_requests(&self) -> bool {
         !self.pending_withdrawal_requests.is_empty()
             || !self.created_tx.is_empty()
@@ -1458,17 +2144,164 @@ impl WithdrawalTransactions {
         ensure_eq!(self.maybe_reimburse, other.maybe_reimburse);
         ensure_eq!(self.reimbursement_requests, other.reimbursement_requests);
         ensure_eq!(self.reimbursed, other.reimbursed);
+        ensure_eq!(self.parked, other.parked);
+        ensure_eq!(self.erc20_burn_indexes, other.erc20_burn_indexes);
+        ensure_eq!(self.swap_burn_indexes, other.swap_burn_indexes);
+        ensure_eq!(self.broadcast_attempts, other.broadcast_attempts);
 
         Ok(())
     }
 
-    pub fn oldest_incomplete_withdrawal_timestamp(&self) -> Option<u64> {
+    /// Withdrawal requests whose transaction is not finalized yet, whether or not
+    /// a transaction was already created for them.
+    pub fn incomplete_withdrawal_requests_iter(&self) -> impl Iterator<Item = &WithdrawalRequest> {
         self.withdrawal_requests_iter()
             .chain(self.maybe_reimburse_requests_iter())
+    }
+
+    /// Number of withdrawals in each state, read from the sizes of the collections holding them.
+    pub fn stats(&self) -> WithdrawalTransactionStats {
+        fn count(len: usize) -> u32 {
+            u32::try_from(len).unwrap_or(u32::MAX)
+        }
+        WithdrawalTransactionStats {
+            pending: count(self.pending_withdrawal_requests.len()),
+            created: count(self.created_tx.len()),
+            sent: count(self.sent_tx.len()),
+            finalized: count(self.finalized_tx.len()),
+            reimbursed: count(self.reimbursed.len()),
+        }
+    }
+
+    pub fn oldest_incomplete_withdrawal_timestamp(&self) -> Option<u64> {
+        self.incomplete_withdrawal_requests_iter()
             .flat_map(|req| req.created_at().into_iter())
             .min()
     }
 
+    /// Appends `status` to the history of the swap unless it is already the latest recorded status.
+    /// Only the last [`MAX_SWAP_STATUS_HISTORY_LENGTH`] transitions are kept.
+    pub fn record_swap_status(&mut self, swap_tx_id: &str, timestamp: u64, status: SwapStatus) {
+        let history = self
+            .swap_status_history
+            .entry(swap_tx_id.to_string())
+            .or_default();
+        if history.last().map(|(_, last_status)| last_status) == Some(&status) {
+            return;
+        }
+        history.push((timestamp, status));
+        if history.len() > MAX_SWAP_STATUS_HISTORY_LENGTH {
+            history.remove(0);
+        }
+    }
+
+    /// Indexes the withdrawal with the given burn index under the ledger of the withdrawn token,
+    /// together with the time at which its transaction was finalized.
+    /// Only successful native and ERC-20 withdrawals are indexed, cancelled withdrawals,
+    /// approvals and swaps are not.
+    pub fn record_finalized_withdrawal(
+        &mut self,
+        burn_index: LedgerBurnIndex,
+        native_ledger_id: Principal,
+        timestamp: u64,
+    ) {
+        if let Some((ledger_id, _amount)) = self.successful_withdrawal(burn_index, native_ledger_id)
+        {
+            self.finalized_withdrawals
+                .entry(ledger_id)
+                .or_default()
+                .insert((timestamp, burn_index));
+        }
+    }
+
+    /// Returns the ledger id of the withdrawn token and the withdrawn amount if the withdrawal
+    /// with the given burn index is a successfully finalized native or ERC-20 withdrawal.
+    pub fn successful_withdrawal(
+        &self,
+        burn_index: LedgerBurnIndex,
+        native_ledger_id: Principal,
+    ) -> Option<(Principal, Erc20Value)> {
+        let is_successful = self
+            .finalized_tx
+            .get_alt(&burn_index)
+            .is_some_and(|tx| tx.transaction_status() == &TransactionStatus::Success);
+        if !is_successful || self.is_cancelled_withdrawal(&burn_index) {
+            return None;
+        }
+        match self.processed_withdrawal_requests.get(&burn_index)? {
+            WithdrawalRequest::Native(request) => {
+                Some((native_ledger_id, request.withdrawal_amount.change_units()))
+            }
+            WithdrawalRequest::Erc20(request) => {
+                Some((request.erc20_ledger_id, request.withdrawal_amount))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the finalization timestamp, request and transaction of the successful withdrawals
+    /// of the token with the given ledger id, or of all tokens if `None`.
+    /// Withdrawals are ordered from the most recently finalized and the first `start` are skipped.
+    pub fn finalized_withdrawals(
+        &self,
+        ledger_id: Option<&Principal>,
+        start: usize,
+        length: usize,
+    ) -> Vec<(u64, &WithdrawalRequest, &FinalizedEip1559Transaction)> {
+        let entries: Vec<&(u64, LedgerBurnIndex)> = match ledger_id {
+            Some(ledger_id) => self
+                .finalized_withdrawals
+                .get(ledger_id)
+                .into_iter()
+                .flat_map(|entries| entries.iter().rev())
+                .skip(start)
+                .take(length)
+                .collect(),
+            None => {
+                let mut entries: Vec<_> = self.finalized_withdrawals.values().flatten().collect();
+                entries.sort_unstable_by(|a, b| b.cmp(a));
+                entries.into_iter().skip(start).take(length).collect()
+            }
+        };
+        entries
+            .into_iter()
+            .map(|(timestamp, burn_index)| {
+                let request = self
+                    .processed_withdrawal_requests
+                    .get(burn_index)
+                    .expect("BUG: missing request of finalized withdrawal");
+                let tx = self
+                    .finalized_tx
+                    .get_alt(burn_index)
+                    .expect("BUG: missing transaction of finalized withdrawal");
+                (*timestamp, request, tx)
+            })
+            .collect()
+    }
+
+    pub fn get_swap_status_history(&self, tx_id: &SwapTxId) -> Vec<(u64, SwapStatus)> {
+        self.swap_status_history
+            .get(&tx_id.0)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the swap_tx_id of the swap request with the given burn index, if any.
+    pub fn find_swap_tx_id(&self, burn_index: &LedgerBurnIndex) -> Option<String> {
+        self.processed_withdrawal_requests
+            .get(burn_index)
+            .into_iter()
+            .chain(self.pending_withdrawal_requests.iter())
+            .find_map(|request| match request {
+                WithdrawalRequest::Swap(swap_request)
+                    if swap_request.native_ledger_burn_index == *burn_index =>
+                {
+                    Some(swap_request.swap_tx_id.clone())
+                }
+                _ => None,
+            })
+    }
+
     pub fn get_swap_status_by_tx_id(&self, tx_id: SwapTxId) -> Option<SwapStatus> {
         // if the swap is quarantined
         if self.quarantined_swap_requests.contains_key(&tx_id.0) {
@@ -1513,7 +2346,7 @@ impl WithdrawalTransactions {
                 deadline: swap_request.deadline.into(),
                 is_refund: swap_request.is_refund,
             };
-            if !swap_request.is_refund {
+            if swap_request.is_refund {
                 return Some(SwapStatus::PendingRefundSwap(swap_detials));
             } else {
                 return Some(SwapStatus::PendingSwap(swap_detials));
@@ -1522,19 +2355,13 @@ impl WithdrawalTransactions {
 
         // if there is a processed request
         if let Some(latest_processed_swap_request) = self
-            .processed_withdrawal_requests
-            .iter()
-            .filter_map(|(_burn_index, request)| match request {
-                WithdrawalRequest::Swap(swap_request) => {
-                    if swap_request.swap_tx_id == tx_id.0 {
-                        Some(swap_request)
-                    } else {
-                        None
-                    }
-                }
+            .swap_burn_indexes
+            .get(&tx_id.0)
+            .and_then(|burn_index| self.processed_withdrawal_requests.get(burn_index))
+            .and_then(|request| match request {
+                WithdrawalRequest::Swap(swap_request) => Some(swap_request),
                 _ => None,
             })
-            .next_back()
         {
             let burn_index = latest_processed_swap_request.native_ledger_burn_index;
             let swap_detials = SwapDetails {
@@ -1566,20 +2393,17 @@ impl WithdrawalTransactions {
 
             if let Some(tx) = self.finalized_tx.get_alt(&burn_index) {
                 if tx.transaction_status() == &TransactionStatus::Success {
+                    let status = TxFinalizedStatus::Success {
+                        transaction_hash: tx.transaction_hash().to_string(),
+                        effective_transaction_fee: Some(tx.effective_transaction_fee().into()),
+                        realized_amount_out: self
+                            .realized_swap_amount_out(&burn_index)
+                            .map(Into::into),
+                    };
                     if latest_processed_swap_request.is_refund {
-                        return Some(SwapStatus::RefundSwapTxFinalized(
-                            TxFinalizedStatus::Success {
-                                transaction_hash: tx.transaction_hash().to_string(),
-                                effective_transaction_fee: Some(
-                                    tx.effective_transaction_fee().into(),
-                                ),
-                            },
-                        ));
+                        return Some(SwapStatus::RefundSwapTxFinalized(status));
                     } else {
-                        return Some(SwapStatus::SwapTxFinalized(TxFinalizedStatus::Success {
-                            transaction_hash: tx.transaction_hash().to_string(),
-                            effective_transaction_fee: Some(tx.effective_transaction_fee().into()),
-                        }));
+                        return Some(SwapStatus::SwapTxFinalized(status));
                     }
                 } else {
                     return None;
@@ -1591,6 +2415,16 @@ impl WithdrawalTransactions {
     }
 }
 
+fn index_swap_request(
+    swap_burn_indexes: &mut BTreeMap<String, LedgerBurnIndex>,
+    request: &ExecuteSwapRequest,
+) {
+    let latest = swap_burn_indexes
+        .entry(request.swap_tx_id.clone())
+        .or_insert(request.native_ledger_burn_index);
+    *latest = (*latest).max(request.native_ledger_burn_index);
+}
+
 /// Creates an EIP-1559 transaction for the given withdrawal request.
 /// The transaction fees are paid by the beneficiary,
 /// meaning that the fees will be deducted from the withdrawal amount.
@@ -1709,7 +2543,7 @@ pub fn create_transaction(
                 amount: Wei::ZERO,
                 data: TransactionCallData::Erc20Approve {
                     spender: request.swap_contract_address,
-                    value: Erc20Value::MAX,
+                    value: request.approved_value(),
                 }
                 .encode(),
                 access_list: Default::default(),
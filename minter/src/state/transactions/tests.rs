@@ -1,5 +1,5 @@
 use crate::checked_amount::CheckedAmountOf;
-use crate::evm_config::EvmNetwork;
+use crate::evm_config::{EvmNetwork, GasLimits};
 use crate::numeric::{
     BlockNumber, Erc20Value, GasAmount, LedgerBurnIndex, TransactionNonce, Wei, WeiPerGas,
 };
@@ -9,7 +9,7 @@ use crate::state::transactions::{
     create_transaction, Erc20WithdrawalRequest, NativeWithdrawalRequest, Subaccount,
     WithdrawalRequest, WithdrawalTransactions,
 };
-use crate::tx::gas_fees::GasFeeEstimate;
+use crate::tx::gas_fees::{GasFeeEstimate, DEFAULT_BASE_FEE_MULTIPLIER_PERCENT};
 use crate::tx::{
     AccessList, Eip1559Signature, Eip1559TransactionRequest, SignedEip1559TransactionRequest,
 };
@@ -297,6 +297,10 @@ mod withdrawal_transactions {
 
     mod record_created_transaction {
         use crate::evm_config::EvmNetwork;
+        use crate::evm_config::{
+            GasLimits, ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+            NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+        };
         use crate::numeric::{LedgerBurnIndex, TransactionNonce, Wei};
         use crate::state::transactions::tests::{
             create_and_record_transaction, create_and_record_twin_withdrawal_requests,
@@ -307,10 +311,7 @@ mod withdrawal_transactions {
         use crate::state::transactions::{create_transaction, WithdrawalTransactions};
         use crate::test_fixtures::expect_panic_with_message;
         use crate::tx::Eip1559TransactionRequest;
-        use crate::withdraw::{
-            estimate_gas_limit, ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
-            NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
-        };
+        use crate::withdraw::estimate_gas_limit;
         use evm_rpc_client::eth_types::Address;
         use ic_crypto_test_utils_reproducible_rng::reproducible_rng;
         use proptest::prelude::any;
@@ -325,7 +326,11 @@ mod withdrawal_transactions {
                 &withdrawal_request.clone(),
                 TransactionNonce::ZERO,
                 gas_fee_estimate(),
-                estimate_gas_limit(&withdrawal_request),
+                estimate_gas_limit(
+                    &withdrawal_request,
+                    EvmNetwork::Sepolia,
+                    &GasLimits::default(),
+                ),
                 EvmNetwork::Sepolia,
             )
             .unwrap();
@@ -346,7 +351,11 @@ mod withdrawal_transactions {
                 &withdrawal_request.clone().into(),
                 TransactionNonce::ZERO,
                 gas_fee_estimate(),
-                estimate_gas_limit(&withdrawal_request.clone().into()),
+                estimate_gas_limit(
+                    &withdrawal_request.clone().into(),
+                    EvmNetwork::Sepolia,
+                    &GasLimits::default(),
+                ),
                 EvmNetwork::Sepolia,
             )
             .unwrap();
@@ -397,7 +406,11 @@ mod withdrawal_transactions {
                 &withdrawal_request.clone().into(),
                 TransactionNonce::ZERO,
                 gas_fee_estimate(),
-                estimate_gas_limit(&withdrawal_request.clone().into()),
+                estimate_gas_limit(
+                    &withdrawal_request.clone().into(),
+                    EvmNetwork::Sepolia,
+                    &GasLimits::default(),
+                ),
                 EvmNetwork::Sepolia,
             )
             .unwrap();
@@ -701,7 +714,10 @@ mod withdrawal_transactions {
         use crate::state::transactions::{
             ResubmitTransactionError, WithdrawalRequest, WithdrawalTransactions,
         };
-        use crate::tx::{gas_fees::GasFeeEstimate, Eip1559TransactionRequest};
+        use crate::tx::{
+            gas_fees::{GasFeeEstimate, DEFAULT_BASE_FEE_MULTIPLIER_PERCENT},
+            Eip1559TransactionRequest,
+        };
         use ic_crypto_test_utils_reproducible_rng::reproducible_rng;
 
         #[test]
@@ -754,6 +770,7 @@ mod withdrawal_transactions {
             let initial_price = GasFeeEstimate {
                 base_fee_per_gas: WeiPerGas::from(10_u8),
                 max_priority_fee_per_gas: WeiPerGas::from(2_u8),
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
             };
             for withdrawal_request in withdrawal_requests {
                 let created_tx = create_and_record_transaction(
@@ -775,12 +792,14 @@ mod withdrawal_transactions {
                 (0_u8..=21).map(|base_fee_per_gas_next_block| GasFeeEstimate {
                     base_fee_per_gas: WeiPerGas::from(base_fee_per_gas_next_block),
                     max_priority_fee_per_gas: WeiPerGas::from(1_u8),
+                    base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
                 }),
             );
             updated_prices.extend(
                 (0_u8..=22).map(|base_fee_per_gas_next_block| GasFeeEstimate {
                     base_fee_per_gas: WeiPerGas::from(base_fee_per_gas_next_block),
                     max_priority_fee_per_gas: WeiPerGas::from(0_u8),
+                    base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
                 }),
             );
 
@@ -796,6 +815,7 @@ mod withdrawal_transactions {
             let price_at_tx_creation = GasFeeEstimate {
                 base_fee_per_gas: WeiPerGas::from(10_u8),
                 max_priority_fee_per_gas: WeiPerGas::from(2_u8),
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
             };
             let max_fee_per_gas_at_tx_creation = 2 * 10 + 2;
             let tests = vec![
@@ -845,6 +865,7 @@ mod withdrawal_transactions {
                             .max_priority_fee_per_gas
                             .checked_mul(2_u8)
                             .unwrap(),
+                        base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
                     },
                     resubmitted_tx_max_fee_per_gas: WeiPerGas::from(25_u8), //(2 * 10 + 1) + 4
                     resubmitted_tx_max_priority_fee_per_gas: WeiPerGas::from(4_u8), //max_priority_fee_per_gas at tx_resubmission because higher than 10% bump of max_priority_fee_per_gas at tx_creation
@@ -914,6 +935,7 @@ mod withdrawal_transactions {
             let initial_price = GasFeeEstimate {
                 base_fee_per_gas: WeiPerGas::from(10_u8),
                 max_priority_fee_per_gas: WeiPerGas::from(2_u8),
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
             };
             for withdrawal_request in &withdrawal_requests {
                 let created_tx = create_and_record_transaction(
@@ -965,6 +987,7 @@ mod withdrawal_transactions {
             let initial_price = GasFeeEstimate {
                 base_fee_per_gas: WeiPerGas::from(10_u8),
                 max_priority_fee_per_gas: WeiPerGas::from(2_u8),
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
             };
             for withdrawal_request in &withdrawal_requests {
                 let created_tx = create_and_record_transaction(
@@ -1019,6 +1042,7 @@ mod withdrawal_transactions {
             let too_high_price = GasFeeEstimate {
                 base_fee_per_gas: DEFAULT_ERC20_MAX_FEE_PER_GAS,
                 max_priority_fee_per_gas: WeiPerGas::ONE,
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
             };
             let resubmitted_txs = transactions.create_resubmit_transactions(
                 TransactionCount::from(30_u8),
@@ -1057,7 +1081,10 @@ mod withdrawal_transactions {
             equal_ignoring_fee_and_amount, WithdrawalRequest, WithdrawalTransactions,
         };
         use crate::test_fixtures::expect_panic_with_message;
-        use crate::tx::{gas_fees::GasFeeEstimate, Eip1559TransactionRequest};
+        use crate::tx::{
+            gas_fees::{GasFeeEstimate, DEFAULT_BASE_FEE_MULTIPLIER_PERCENT},
+            Eip1559TransactionRequest,
+        };
         use ic_crypto_test_utils_reproducible_rng::reproducible_rng;
         use proptest::{prop_assume, proptest};
         use std::iter;
@@ -1202,6 +1229,7 @@ mod withdrawal_transactions {
                 let initial_price = GasFeeEstimate {
                     base_fee_per_gas: WeiPerGas::from(10_u8),
                     max_priority_fee_per_gas: WeiPerGas::from(2_u8),
+                    base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
                 };
                 let resubmit_price_1 = GasFeeEstimate {
                     max_priority_fee_per_gas: WeiPerGas::from(3_u8),
@@ -1358,12 +1386,24 @@ mod withdrawal_transactions {
 
             assert_eq!(
                 transactions.transactions_to_send_batch(TransactionCount::ZERO, usize::MAX),
-                vec![last_first_tx, second_tx.clone()]
+                vec![
+                    (
+                        first_withdrawal_request.native_ledger_burn_index(),
+                        last_first_tx
+                    ),
+                    (
+                        second_withdrawal_request.native_ledger_burn_index(),
+                        second_tx.clone()
+                    )
+                ]
             );
 
             assert_eq!(
                 transactions.transactions_to_send_batch(TransactionCount::ONE, usize::MAX),
-                vec![second_tx]
+                vec![(
+                    second_withdrawal_request.native_ledger_burn_index(),
+                    second_tx
+                )]
             );
 
             assert_transactions_to_send_iter_is_empty(&transactions, TransactionCount::TWO);
@@ -1501,11 +1541,14 @@ mod withdrawal_transactions {
             native_withdrawal_request_with_index, transaction_receipt,
         };
         use crate::state::transactions::{
-            Erc20WithdrawalRequest, ReimbursementIndex, ReimbursementRequest, TransactionStatus,
-            WithdrawalRequest, WithdrawalTransactions,
+            Erc20WithdrawalRequest, FinalizedReimbursementRequest, ReimbursementIndex,
+            ReimbursementRequest, TransactionStatus, WithdrawalRequest, WithdrawalTransactions,
         };
         use crate::test_fixtures::expect_panic_with_message;
-        use crate::tx::{gas_fees::GasFeeEstimate, SignedEip1559TransactionRequest};
+        use crate::tx::{
+            gas_fees::{GasFeeEstimate, DEFAULT_BASE_FEE_MULTIPLIER_PERCENT},
+            SignedEip1559TransactionRequest,
+        };
         use ic_crypto_test_utils_reproducible_rng::reproducible_rng;
         use maplit::{btreemap, btreeset};
 
@@ -1632,6 +1675,7 @@ mod withdrawal_transactions {
                 GasFeeEstimate {
                     base_fee_per_gas: WeiPerGas::from(250_000_000_000_u128),
                     max_priority_fee_per_gas: WeiPerGas::ZERO,
+                    base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
                 },
             );
             let signed_tx = create_and_record_signed_transaction(&mut transactions, created_tx);
@@ -1686,13 +1730,13 @@ mod withdrawal_transactions {
                         native_ledger_burn_index,
                         ledger_id: withdrawal_request.erc20_ledger_id,
                         erc20_ledger_burn_index } =>
-                    ReimbursementRequest {
+                    ReimbursementRequest::Finalized(FinalizedReimbursementRequest {
                         ledger_burn_index: native_ledger_burn_index,
                         reimbursed_amount: expected_erc20_reimbursed_amount.change_units(),
                         to: withdrawal_request.from,
                         to_subaccount: withdrawal_request.from_subaccount,
-                        transaction_hash: Some(receipt.transaction_hash),
-                    }
+                        transaction_hash: receipt.transaction_hash,
+                    })
                 }
             );
         }
@@ -1734,8 +1778,8 @@ mod withdrawal_transactions {
             let effective_fee_paid = finalized_transaction.effective_transaction_fee();
             assert_eq!(
                 reimbursement_request,
-                &ReimbursementRequest {
-                    transaction_hash: Some(receipt.transaction_hash),
+                &ReimbursementRequest::Finalized(FinalizedReimbursementRequest {
+                    transaction_hash: receipt.transaction_hash,
                     ledger_burn_index: native_ledger_burn_index,
                     to: withdrawal_request.from,
                     to_subaccount: withdrawal_request.from_subaccount,
@@ -1744,7 +1788,7 @@ mod withdrawal_transactions {
                         .checked_sub(effective_fee_paid)
                         .unwrap()
                         .change_units()
-                }
+                })
             );
         }
 
@@ -1924,6 +1968,7 @@ mod withdrawal_transactions {
             let success = TxFinalizedStatus::Success {
                 transaction_hash: receipt.transaction_hash.to_string(),
                 effective_transaction_fee: Some(receipt.effective_transaction_fee().into()),
+                realized_amount_out: None,
             };
             assert_eq!(
                 transactions.transaction_status(&native_ledger_burn_index),
@@ -2066,6 +2111,422 @@ mod withdrawal_transactions {
         }
     }
 
+    mod withdrawal_requests_batch {
+        use crate::numeric::{LedgerBurnIndex, TransactionNonce};
+        use crate::state::transactions::tests::{
+            create_and_record_native_withdrawal_requests, create_and_record_transaction,
+            erc20_withdrawal_request_with_index, gas_fee_estimate,
+            native_withdrawal_request_with_index,
+        };
+        use crate::state::transactions::{WithdrawalRequest, WithdrawalTransactions};
+        use crate::withdraw::MAX_BATCH_SIZE;
+
+        #[test]
+        fn should_take_turns_between_lanes() {
+            let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+            let erc20_requests: Vec<WithdrawalRequest> = (1..=3_u64)
+                .map(|i| {
+                    erc20_withdrawal_request_with_index(
+                        LedgerBurnIndex::new(i),
+                        LedgerBurnIndex::new(i),
+                    )
+                    .into()
+                })
+                .collect();
+            let native_request: WithdrawalRequest =
+                native_withdrawal_request_with_index(LedgerBurnIndex::new(4)).into();
+            for request in erc20_requests.iter().chain([&native_request]) {
+                transactions.record_withdrawal_request(request.clone());
+            }
+
+            assert_eq!(
+                transactions.withdrawal_requests_batch(2),
+                vec![erc20_requests[0].clone(), native_request.clone()]
+            );
+            assert_eq!(
+                transactions.withdrawal_requests_batch(5),
+                vec![
+                    erc20_requests[0].clone(),
+                    native_request,
+                    erc20_requests[1].clone(),
+                    erc20_requests[2].clone(),
+                ]
+            );
+        }
+
+        #[test]
+        fn should_create_transactions_for_all_pending_requests_in_two_batches() {
+            let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+            let requests: [WithdrawalRequest; 15] =
+                create_and_record_native_withdrawal_requests(&mut transactions);
+
+            let mut batches = 0;
+            while !transactions.pending_withdrawal_requests.is_empty() {
+                let batch = transactions.withdrawal_requests_batch(MAX_BATCH_SIZE);
+                assert!(!batch.is_empty() && batch.len() <= MAX_BATCH_SIZE);
+                for request in batch {
+                    create_and_record_transaction(&mut transactions, request, gas_fee_estimate());
+                }
+                batches += 1;
+            }
+
+            assert_eq!(batches, 2);
+            for request in requests {
+                assert!(transactions
+                    .created_tx
+                    .get_alt(&request.native_ledger_burn_index())
+                    .is_some());
+            }
+            assert_eq!(
+                transactions
+                    .transactions_to_sign_batch(MAX_BATCH_SIZE)
+                    .len(),
+                MAX_BATCH_SIZE
+            );
+        }
+    }
+
+    mod native_ledger_burn_index_of {
+        use crate::numeric::{LedgerBurnIndex, TransactionNonce};
+        use crate::state::transactions::tests::{
+            erc20_withdrawal_request_with_index, native_withdrawal_request_with_index,
+            DEFAULT_ERC20_LEDGER_ID,
+        };
+        use crate::state::transactions::{Erc20WithdrawalRequest, WithdrawalTransactions};
+        use candid::Principal;
+
+        #[test]
+        fn should_resolve_erc20_burn_index_and_icrc_lock_index() {
+            let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+            let erc20_ledger_id = Principal::from_text(DEFAULT_ERC20_LEDGER_ID).unwrap();
+            let icrc_ledger_id = Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap();
+            transactions.record_withdrawal_request(native_withdrawal_request_with_index(
+                LedgerBurnIndex::new(1),
+            ));
+            transactions.record_withdrawal_request(erc20_withdrawal_request_with_index(
+                LedgerBurnIndex::new(2),
+                LedgerBurnIndex::new(7),
+            ));
+            transactions.record_withdrawal_request(Erc20WithdrawalRequest {
+                erc20_ledger_id: icrc_ledger_id,
+                is_wrapped_mint: Some(true),
+                gas_limit: None,
+                ..erc20_withdrawal_request_with_index(
+                    LedgerBurnIndex::new(3),
+                    LedgerBurnIndex::new(7),
+                )
+            });
+
+            assert_eq!(
+                transactions.native_ledger_burn_index_of(&erc20_ledger_id, LedgerBurnIndex::new(7)),
+                Some(LedgerBurnIndex::new(2))
+            );
+            assert_eq!(
+                transactions.native_ledger_burn_index_of(&icrc_ledger_id, LedgerBurnIndex::new(7)),
+                Some(LedgerBurnIndex::new(3))
+            );
+            assert_eq!(
+                transactions.native_ledger_burn_index_of(&erc20_ledger_id, LedgerBurnIndex::new(1)),
+                None
+            );
+        }
+    }
+
+    mod park_transaction {
+        use crate::evm_config::NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT;
+        use crate::numeric::{LedgerBurnIndex, TransactionCount, TransactionNonce, Wei};
+        use crate::state::transactions::tests::{
+            create_and_record_signed_transaction, create_and_record_transaction,
+            erc20_withdrawal_request_with_index, gas_fee_estimate,
+            native_withdrawal_request_with_index, resubmit_transaction_with_bumped_price,
+            sign_transaction, transaction_receipt,
+        };
+        use crate::state::transactions::{
+            ReimbursementIndex, TransactionStatus, WithdrawalRequest, WithdrawalTransactions,
+        };
+        use crate::tx::cancellation::create_cancellation_transaction;
+        use crate::tx::gas_fees::GasFeeEstimate;
+        use crate::tx::SignedEip1559TransactionRequest;
+        use evm_rpc_client::eth_types::Address;
+        use std::str::FromStr;
+
+        const MAX_REPLACEMENTS: usize = 3;
+
+        fn minter_address() -> Address {
+            Address::from_str("0x1789F79e95324A47c5Fd6693071188e82E9a3558").unwrap()
+        }
+
+        /// Sends the transaction of the given request and replaces it `MAX_REPLACEMENTS` times.
+        fn send_stuck_transaction(
+            transactions: &mut WithdrawalTransactions,
+            request: WithdrawalRequest,
+        ) -> SignedEip1559TransactionRequest {
+            transactions.record_withdrawal_request(request.clone());
+            let created_tx =
+                create_and_record_transaction(transactions, request, gas_fee_estimate());
+            let mut last_sent_tx = create_and_record_signed_transaction(transactions, created_tx);
+            for _ in 0..MAX_REPLACEMENTS {
+                last_sent_tx = resubmit_transaction_with_bumped_price(
+                    transactions,
+                    last_sent_tx.transaction().clone(),
+                );
+            }
+            last_sent_tx
+        }
+
+        fn park_and_sign_cancellation(
+            transactions: &mut WithdrawalTransactions,
+        ) -> SignedEip1559TransactionRequest {
+            let (withdrawal_id, stuck_tx) = transactions
+                .transaction_to_park(
+                    TransactionCount::ZERO,
+                    &gas_fee_estimate(),
+                    MAX_REPLACEMENTS,
+                )
+                .expect("BUG: expected a transaction to park");
+            let cancellation = create_cancellation_transaction(
+                &stuck_tx,
+                minter_address(),
+                gas_fee_estimate(),
+                NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+            );
+            transactions.record_parked_transaction(withdrawal_id, cancellation.clone());
+            create_and_record_signed_transaction(transactions, cancellation)
+        }
+
+        #[test]
+        fn should_only_park_head_transaction_after_max_replacements() {
+            let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+            let request: WithdrawalRequest = erc20_withdrawal_request_with_index(
+                LedgerBurnIndex::new(1),
+                LedgerBurnIndex::new(1),
+            )
+            .into();
+            transactions.record_withdrawal_request(request.clone());
+            let created_tx =
+                create_and_record_transaction(&mut transactions, request, gas_fee_estimate());
+            let mut last_sent_tx =
+                create_and_record_signed_transaction(&mut transactions, created_tx);
+            for _ in 0..MAX_REPLACEMENTS {
+                assert_eq!(
+                    transactions.transaction_to_park(
+                        TransactionCount::ZERO,
+                        &gas_fee_estimate(),
+                        MAX_REPLACEMENTS
+                    ),
+                    None
+                );
+                last_sent_tx = resubmit_transaction_with_bumped_price(
+                    &mut transactions,
+                    last_sent_tx.transaction().clone(),
+                );
+            }
+
+            assert_eq!(
+                transactions.transaction_to_park(
+                    TransactionCount::ZERO,
+                    &gas_fee_estimate(),
+                    MAX_REPLACEMENTS
+                ),
+                Some((LedgerBurnIndex::new(1), last_sent_tx.transaction().clone()))
+            );
+            assert_eq!(
+                transactions.transaction_to_park(
+                    TransactionCount::ONE,
+                    &gas_fee_estimate(),
+                    MAX_REPLACEMENTS
+                ),
+                None
+            );
+        }
+
+        #[test]
+        fn should_park_head_transaction_when_its_fee_is_capped() {
+            let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+            let request: WithdrawalRequest = erc20_withdrawal_request_with_index(
+                LedgerBurnIndex::new(1),
+                LedgerBurnIndex::new(1),
+            )
+            .into();
+            transactions.record_withdrawal_request(request.clone());
+            let created_tx =
+                create_and_record_transaction(&mut transactions, request, gas_fee_estimate());
+            let sent_tx = create_and_record_signed_transaction(&mut transactions, created_tx);
+            let higher_gas_fee = GasFeeEstimate {
+                max_priority_fee_per_gas: gas_fee_estimate()
+                    .max_priority_fee_per_gas
+                    .checked_mul(100_u8)
+                    .unwrap(),
+                ..gas_fee_estimate()
+            };
+            assert!(transactions
+                .create_resubmit_transactions(TransactionCount::ZERO, higher_gas_fee.clone())
+                .iter()
+                .all(|result| result.is_err()));
+
+            assert_eq!(
+                transactions.transaction_to_park(
+                    TransactionCount::ZERO,
+                    &higher_gas_fee,
+                    MAX_REPLACEMENTS
+                ),
+                Some((LedgerBurnIndex::new(1), sent_tx.transaction().clone()))
+            );
+        }
+
+        #[test]
+        fn should_not_resubmit_parked_transaction_before_cancellation_is_signed() {
+            let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+            let request: WithdrawalRequest = erc20_withdrawal_request_with_index(
+                LedgerBurnIndex::new(1),
+                LedgerBurnIndex::new(1),
+            )
+            .into();
+            let stuck_tx = send_stuck_transaction(&mut transactions, request);
+            let cancellation = create_cancellation_transaction(
+                stuck_tx.transaction(),
+                minter_address(),
+                gas_fee_estimate(),
+                NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+            );
+
+            transactions.record_parked_transaction(LedgerBurnIndex::new(1), cancellation.clone());
+
+            assert_eq!(
+                transactions
+                    .create_resubmit_transactions(TransactionCount::ZERO, gas_fee_estimate()),
+                vec![]
+            );
+            assert_eq!(
+                transactions.transactions_to_sign_batch(5),
+                vec![(LedgerBurnIndex::new(1), cancellation)]
+            );
+            assert_eq!(
+                transactions.transaction_to_park(
+                    TransactionCount::ZERO,
+                    &gas_fee_estimate(),
+                    MAX_REPLACEMENTS
+                ),
+                None
+            );
+        }
+
+        #[test]
+        fn should_reimburse_cancelled_erc20_withdrawal_and_process_native_withdrawal() {
+            let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+            let erc20_request: WithdrawalRequest = erc20_withdrawal_request_with_index(
+                LedgerBurnIndex::new(1),
+                LedgerBurnIndex::new(1),
+            )
+            .into();
+            let next_erc20_request: WithdrawalRequest = erc20_withdrawal_request_with_index(
+                LedgerBurnIndex::new(2),
+                LedgerBurnIndex::new(2),
+            )
+            .into();
+            let native_request: WithdrawalRequest =
+                native_withdrawal_request_with_index(LedgerBurnIndex::new(3)).into();
+            send_stuck_transaction(&mut transactions, erc20_request.clone());
+            transactions.record_withdrawal_request(next_erc20_request.clone());
+            transactions.record_withdrawal_request(native_request.clone());
+
+            let signed_cancellation = park_and_sign_cancellation(&mut transactions);
+
+            // the lane of the parked withdrawal waits until the cancellation is finalized
+            assert_eq!(
+                transactions.withdrawal_requests_batch(5),
+                vec![native_request.clone()]
+            );
+
+            transactions.record_finalized_transaction(
+                LedgerBurnIndex::new(1),
+                transaction_receipt(&signed_cancellation, TransactionStatus::Success),
+            );
+
+            assert!(transactions.is_cancelled_withdrawal(&LedgerBurnIndex::new(1)));
+            let reimbursement_index = ReimbursementIndex::from(&erc20_request);
+            let reimbursement = transactions
+                .reimbursement_requests
+                .get(&reimbursement_index)
+                .expect("missing reimbursement request");
+            assert_eq!(
+                reimbursement.reimbursed_amount(),
+                match &erc20_request {
+                    WithdrawalRequest::Erc20(request) => request.withdrawal_amount.change_units(),
+                    _ => unreachable!(),
+                }
+            );
+            assert_eq!(
+                transactions.withdrawal_requests_batch(5),
+                vec![next_erc20_request, native_request.clone()]
+            );
+
+            let native_tx = create_and_record_transaction(
+                &mut transactions,
+                native_request,
+                gas_fee_estimate(),
+            );
+            assert_eq!(native_tx.nonce, TransactionNonce::ONE);
+            let signed_native_tx = sign_transaction(native_tx);
+            transactions.record_signed_transaction(signed_native_tx.clone());
+            transactions.record_finalized_transaction(
+                LedgerBurnIndex::new(3),
+                transaction_receipt(&signed_native_tx, TransactionStatus::Success),
+            );
+
+            assert!(!transactions.is_cancelled_withdrawal(&LedgerBurnIndex::new(3)));
+            assert_eq!(transactions.reimbursement_requests.len(), 1);
+        }
+
+        #[test]
+        fn should_deduct_cancellation_fee_from_reimbursed_native_withdrawal() {
+            let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+            let request: WithdrawalRequest =
+                native_withdrawal_request_with_index(LedgerBurnIndex::new(1)).into();
+            send_stuck_transaction(&mut transactions, request.clone());
+            let signed_cancellation = park_and_sign_cancellation(&mut transactions);
+            let receipt = transaction_receipt(&signed_cancellation, TransactionStatus::Success);
+
+            transactions.record_finalized_transaction(LedgerBurnIndex::new(1), receipt.clone());
+
+            let withdrawal_amount = match &request {
+                WithdrawalRequest::Native(request) => request.withdrawal_amount,
+                _ => unreachable!(),
+            };
+            let reimbursement = transactions
+                .reimbursement_requests
+                .get(&ReimbursementIndex::from(&request))
+                .expect("missing reimbursement request");
+            assert_eq!(
+                reimbursement.reimbursed_amount(),
+                withdrawal_amount
+                    .checked_sub(receipt.effective_transaction_fee())
+                    .unwrap_or(Wei::ZERO)
+                    .change_units()
+            );
+        }
+
+        #[test]
+        fn should_not_reimburse_when_parked_transaction_is_mined() {
+            let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+            let request: WithdrawalRequest = erc20_withdrawal_request_with_index(
+                LedgerBurnIndex::new(1),
+                LedgerBurnIndex::new(1),
+            )
+            .into();
+            let stuck_tx = send_stuck_transaction(&mut transactions, request);
+            park_and_sign_cancellation(&mut transactions);
+
+            transactions.record_finalized_transaction(
+                LedgerBurnIndex::new(1),
+                transaction_receipt(&stuck_tx, TransactionStatus::Success),
+            );
+
+            assert!(!transactions.is_cancelled_withdrawal(&LedgerBurnIndex::new(1)));
+            assert!(transactions.reimbursement_requests.is_empty());
+        }
+    }
+
     pub fn withdrawal_flow<T: Into<WithdrawalRequest>>(
         transactions: &mut WithdrawalTransactions,
         withdrawal_request: T,
@@ -2235,6 +2696,159 @@ mod oldest_incomplete_withdrawal_timestamp {
     }
 }
 
+mod withdrawal_transaction_stats {
+    use super::*;
+    use crate::candid_types::WithdrawalTransactionStats;
+    use crate::numeric::LedgerMintIndex;
+    use crate::state::transactions::tests::withdrawal_transactions::withdrawal_flow;
+    use crate::state::transactions::ReimbursementIndex;
+
+    #[test]
+    fn should_be_empty_without_requests() {
+        let transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+
+        assert_eq!(transactions.stats(), WithdrawalTransactionStats::default());
+    }
+
+    #[test]
+    fn should_count_one_withdrawal_in_each_state() {
+        let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+        let reimbursed_request = native_withdrawal_request_with_index(LedgerBurnIndex::new(1));
+        withdrawal_flow(
+            &mut transactions,
+            reimbursed_request.clone(),
+            TransactionStatus::Failure,
+        );
+        transactions.record_finalized_reimbursement(
+            ReimbursementIndex::Native {
+                ledger_burn_index: reimbursed_request.ledger_burn_index,
+            },
+            LedgerMintIndex::new(16),
+            None,
+        );
+        let sent_request = native_withdrawal_request_with_index(LedgerBurnIndex::new(2));
+        transactions.record_withdrawal_request(sent_request.clone());
+        let created_tx =
+            create_and_record_transaction(&mut transactions, sent_request, gas_fee_estimate());
+        create_and_record_signed_transaction(&mut transactions, created_tx);
+        let created_request = native_withdrawal_request_with_index(LedgerBurnIndex::new(3));
+        transactions.record_withdrawal_request(created_request.clone());
+        create_and_record_transaction(&mut transactions, created_request, gas_fee_estimate());
+        transactions.record_withdrawal_request(native_withdrawal_request_with_index(
+            LedgerBurnIndex::new(4),
+        ));
+
+        assert_eq!(
+            transactions.stats(),
+            WithdrawalTransactionStats {
+                pending: 1,
+                created: 1,
+                sent: 1,
+                finalized: 1,
+                reimbursed: 1,
+            }
+        );
+    }
+}
+
+mod pending_withdrawal_stats {
+    use super::*;
+    use crate::candid_types::PendingWithdrawalStats;
+    use crate::state::transactions::ExecuteSwapRequest;
+
+    fn pending_withdrawal_stats(transactions: &WithdrawalTransactions) -> PendingWithdrawalStats {
+        transactions.incomplete_withdrawal_requests_iter().collect()
+    }
+
+    fn swap_request_with_index(native_ledger_burn_index: LedgerBurnIndex) -> ExecuteSwapRequest {
+        let erc20_request =
+            erc20_withdrawal_request_with_index(native_ledger_burn_index, LedgerBurnIndex::new(1));
+        ExecuteSwapRequest {
+            max_transaction_fee: erc20_request.max_transaction_fee,
+            erc20_token_in: erc20_request.erc20_contract_address,
+            erc20_amount_in: erc20_request.withdrawal_amount,
+            min_amount_out: Erc20Value::ZERO,
+            recipient: erc20_request.destination,
+            deadline: Erc20Value::MAX,
+            commands: vec![],
+            commands_data: vec![],
+            swap_contract: erc20_request.destination,
+            gas_estimate: GasAmount::new(500_000),
+            native_ledger_burn_index,
+            erc20_ledger_id: erc20_request.erc20_ledger_id,
+            erc20_ledger_burn_index: erc20_request.erc20_ledger_burn_index,
+            from: erc20_request.from,
+            from_subaccount: None,
+            created_at: DEFAULT_CREATED_AT,
+            l1_fee: None,
+            withdrawal_fee: None,
+            swap_tx_id: "0xswap".to_string(),
+            is_refund: false,
+        }
+    }
+
+    #[test]
+    fn should_be_empty_without_requests() {
+        let transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+
+        let stats = pending_withdrawal_stats(&transactions);
+
+        assert_eq!(stats, PendingWithdrawalStats::default());
+        assert_eq!(stats.total(), 0);
+    }
+
+    #[test]
+    fn should_count_each_withdrawal_type() {
+        let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+        let native_request = native_withdrawal_request_with_index(LedgerBurnIndex::new(1));
+        transactions.record_withdrawal_request(native_request.clone());
+        transactions.record_withdrawal_request(erc20_withdrawal_request_with_index(
+            LedgerBurnIndex::new(2),
+            LedgerBurnIndex::new(2),
+        ));
+        transactions.record_withdrawal_request(Erc20WithdrawalRequest {
+            is_wrapped_mint: Some(true),
+            gas_limit: None,
+            ..erc20_withdrawal_request_with_index(LedgerBurnIndex::new(3), LedgerBurnIndex::new(3))
+        });
+        transactions.record_withdrawal_request(swap_request_with_index(LedgerBurnIndex::new(4)));
+        // requests with a transaction that is not finalized yet are still pending
+        create_and_record_transaction(&mut transactions, native_request, gas_fee_estimate());
+
+        let stats = pending_withdrawal_stats(&transactions);
+
+        assert_eq!(
+            stats,
+            PendingWithdrawalStats {
+                native: 1,
+                erc20: 1,
+                icrc_wrap: 1,
+                swap: 1,
+            }
+        );
+        assert_eq!(stats.total(), 4);
+    }
+
+    #[test]
+    fn should_ignore_finalized_requests() {
+        let mut transactions = WithdrawalTransactions::new(TransactionNonce::ZERO);
+        let withdrawal_request = native_withdrawal_request_with_index(LedgerBurnIndex::new(1));
+        transactions.record_withdrawal_request(withdrawal_request.clone());
+        let created_tx = create_and_record_transaction(
+            &mut transactions,
+            withdrawal_request,
+            gas_fee_estimate(),
+        );
+        let signed_tx = create_and_record_signed_transaction(&mut transactions, created_tx);
+        transactions.record_finalized_transaction(
+            LedgerBurnIndex::new(1),
+            transaction_receipt(&signed_tx, TransactionStatus::Success),
+        );
+
+        assert_eq!(pending_withdrawal_stats(&transactions).total(), 0);
+    }
+}
+
 mod native_withdrawal_request {
     use crate::numeric::LedgerBurnIndex;
     use crate::state::transactions::tests::native_withdrawal_request_with_index;
@@ -2242,7 +2856,7 @@ mod native_withdrawal_request {
     #[test]
     fn should_have_readable_debug_representation() {
         let request = native_withdrawal_request_with_index(LedgerBurnIndex::new(131));
-        let expected_debug = "NativeWithdrawalRequest { withdrawal_amount: 1_100_000_000_000_000, destination: 0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34, ledger_burn_index: 131, from: k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae, from_subaccount: Some(1111111111111111111111111111111111111111111111111111111111111111), created_at: Some(1699527697000000000), l1_fee: None, withdrawal_fee: None }";
+        let expected_debug = "NativeWithdrawalRequest { withdrawal_amount: 1_100_000_000_000_000, destination: 0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34, ledger_burn_index: 131, from: k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae, from_subaccount: Some(1111111111111111111111111111111111111111111111111111111111111111), created_at: Some(1699527697000000000), l1_fee: None, withdrawal_fee: None, is_contract_destination: None }";
         assert_eq!(format!("{request:?}"), expected_debug);
     }
 }
@@ -2255,13 +2869,17 @@ mod erc_20_withdrawal_request {
     fn should_have_readable_debug_representation() {
         let request =
             erc20_withdrawal_request_with_index(LedgerBurnIndex::new(131), LedgerBurnIndex::new(2));
-        let expected_debug = "Erc20WithdrawalRequest { max_transaction_fee: 30_000_000_000_000_000, withdrawal_amount: 1_100_000_000_000_000, erc20_contract_address: 0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48, destination: 0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34, native_ledger_burn_index: 131, erc20_ledger_id: sa4so-piaaa-aaaar-qacnq-cai, erc20_ledger_burn_index: 2, from: k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae, from_subaccount: Some(1111111111111111111111111111111111111111111111111111111111111111), created_at: 1699527697000000000, l1_fee: None, withdrawal_fee: None, is_wrapped_mint: Some(false) }";
+        let expected_debug = "Erc20WithdrawalRequest { max_transaction_fee: 30_000_000_000_000_000, withdrawal_amount: 1_100_000_000_000_000, erc20_contract_address: 0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48, destination: 0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34, native_ledger_burn_index: 131, erc20_ledger_id: sa4so-piaaa-aaaar-qacnq-cai, erc20_ledger_burn_index: 2, from: k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae, from_subaccount: Some(1111111111111111111111111111111111111111111111111111111111111111), created_at: 1699527697000000000, l1_fee: None, withdrawal_fee: None, is_wrapped_mint: Some(false), gas_limit: None }";
         assert_eq!(format!("{request:?}"), expected_debug);
     }
 }
 
 mod create_transaction {
     use crate::evm_config::EvmNetwork;
+    use crate::evm_config::{
+        GasLimits, NATIVE_WITHDRAWAL_TO_CONTRACT_TRANSACTION_GAS_LIMIT,
+        NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+    };
     use crate::numeric::{
         Erc20Value, GasAmount, LedgerBurnIndex, TransactionNonce, Wei, WeiPerGas,
     };
@@ -2271,11 +2889,11 @@ mod create_transaction {
     };
     use crate::state::transactions::{
         create_transaction, CreateTransactionError, Erc20WithdrawalRequest,
-        NativeWithdrawalRequest, TransactionCallData,
+        NativeWithdrawalRequest, TransactionCallData, WithdrawalRequest,
     };
-    use crate::tx::gas_fees::GasFeeEstimate;
+    use crate::tx::gas_fees::{GasFeeEstimate, DEFAULT_BASE_FEE_MULTIPLIER_PERCENT};
     use crate::tx::{AccessList, Eip1559TransactionRequest};
-    use crate::withdraw::NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT;
+    use crate::withdraw::estimate_gas_limit;
     use proptest::collection::vec as pvec;
     use proptest::prelude::any;
     use proptest::{prop_assert, prop_assert_eq, proptest};
@@ -2374,6 +2992,54 @@ mod create_transaction {
         }
     }
 
+    #[test]
+    fn should_use_higher_gas_limit_for_native_withdrawal_to_contract() {
+        let gas_fee = gas_fee_estimate();
+        let withdrawal_amount = Wei::from(1_000_000_000_000_000_u64);
+
+        for (is_contract_destination, expected_gas_limit) in [
+            (None, NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT),
+            (Some(false), NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT),
+            (
+                Some(true),
+                NATIVE_WITHDRAWAL_TO_CONTRACT_TRANSACTION_GAS_LIMIT,
+            ),
+        ] {
+            let withdrawal_request: WithdrawalRequest = NativeWithdrawalRequest {
+                withdrawal_amount,
+                is_contract_destination,
+                ..native_withdrawal_request_with_index(LedgerBurnIndex::new(15))
+            }
+            .into();
+            let gas_limit = estimate_gas_limit(
+                &withdrawal_request,
+                EvmNetwork::Sepolia,
+                &GasLimits::default(),
+            );
+            assert_eq!(gas_limit, expected_gas_limit);
+
+            let transaction = create_transaction(
+                &withdrawal_request,
+                TransactionNonce::TWO,
+                gas_fee.clone(),
+                gas_limit,
+                EvmNetwork::Sepolia,
+            )
+            .unwrap();
+
+            // The withdrawn amount pays for the higher gas limit
+            let max_transaction_fee = gas_fee
+                .clone()
+                .to_price(expected_gas_limit)
+                .max_transaction_fee();
+            assert_eq!(transaction.gas_limit, expected_gas_limit);
+            assert_eq!(
+                transaction.amount,
+                withdrawal_amount.checked_sub(max_transaction_fee).unwrap()
+            );
+        }
+    }
+
     proptest! {
         #[test]
         fn should_create_erc20_withdrawal_transaction(max_transaction_fee in 4_652_229_101_896_296_u128..=u128::MAX) {
@@ -2381,7 +3047,8 @@ mod create_transaction {
             // https://etherscan.io/tx/0x295846b122356aebb2d426ffdc2510c2f5eecbf5fc359fb24bba8a7ab5fa8616
             let gas_fee = GasFeeEstimate {
                 base_fee_per_gas: WeiPerGas::from(0xb9790a88a_u64),
-                max_priority_fee_per_gas:  WeiPerGas::from(0x2faf080_u64)
+                max_priority_fee_per_gas:  WeiPerGas::from(0x2faf080_u64),
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
             };
             let gas_limit = GasAmount::from(0x16ca4_u64);
             let min_tx_fee = gas_fee.min_max_fee_per_gas().transaction_cost(gas_limit).unwrap();
@@ -2451,6 +3118,7 @@ mod create_transaction {
 
 mod withdrawal_flow {
     use super::arbitrary::{arb_checked_amount_of, arb_gas_fee_estimate, arb_withdrawal_request};
+    use crate::evm_config::GasLimits;
     use crate::numeric::TransactionNonce;
     use crate::state::transactions::tests::sign_transaction;
     use crate::state::transactions::{create_transaction, EvmNetwork, WithdrawalTransactions};
@@ -2481,7 +3149,7 @@ mod withdrawal_flow {
                     &request,
                     nonce,
                     gas_fee_estimate.clone(),
-                    estimate_gas_limit(&request),
+                    estimate_gas_limit(&request, EvmNetwork::Sepolia, &GasLimits::default()),
                     EvmNetwork::Sepolia,
                 ){
                     wrapped_txs.borrow_mut().record_created_transaction(request.native_ledger_burn_index(), created_tx);
@@ -2585,6 +3253,7 @@ pub mod arbitrary {
                         created_at,
                         l1_fee: None,
                         withdrawal_fee: None,
+                        is_contract_destination: None,
                     }
                 },
             )
@@ -2629,6 +3298,7 @@ pub mod arbitrary {
                         created_at,
                         l1_fee: None,
                         is_wrapped_mint: Some(false),
+                        gas_limit: None,
                         withdrawal_fee: None,
                     }
                 },
@@ -2648,6 +3318,7 @@ pub mod arbitrary {
             |(base_fee_per_gas_next_block, max_priority_fee_per_gas)| GasFeeEstimate {
                 base_fee_per_gas: WeiPerGas::new(base_fee_per_gas_next_block),
                 max_priority_fee_per_gas: WeiPerGas::new(max_priority_fee_per_gas),
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
             },
         )
     }
@@ -2732,6 +3403,7 @@ fn native_withdrawal_request_with_index(
         created_at: Some(DEFAULT_CREATED_AT),
         l1_fee: None,
         withdrawal_fee: None,
+        is_contract_destination: None,
     }
 }
 
@@ -2753,6 +3425,7 @@ fn erc20_withdrawal_request_with_index(
         created_at: DEFAULT_CREATED_AT,
         l1_fee: None,
         is_wrapped_mint: Some(false),
+        gas_limit: None,
         withdrawal_fee: None,
     }
 }
@@ -2787,6 +3460,7 @@ fn gas_fee_estimate() -> GasFeeEstimate {
     GasFeeEstimate {
         base_fee_per_gas: WeiPerGas::from(25_u8),
         max_priority_fee_per_gas: WeiPerGas::new(0x59682f00),
+        base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
     }
 }
 
@@ -2879,7 +3553,11 @@ fn create_and_record_transaction<R: Into<WithdrawalRequest>>(
         &withdrawal_request,
         transactions.next_transaction_nonce(),
         gas_fee_estimate,
-        estimate_gas_limit(&withdrawal_request),
+        estimate_gas_limit(
+            &withdrawal_request,
+            EvmNetwork::Sepolia,
+            &GasLimits::default(),
+        ),
         EvmNetwork::Sepolia,
     )
     .expect("failed to create transaction");
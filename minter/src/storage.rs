@@ -1,39 +1,218 @@
 #[cfg(test)]
 mod tests;
+use crate::candid_types::{AddOrRemove, ArchiveEventsError, ListKind, UpdateAddressListError};
 use crate::rpc_client::providers::Provider;
+use crate::rpc_declarations::Hash;
 use crate::state::event::{Event, EventType};
+use evm_rpc_client::eth_types::Address;
+use ic_sha3::Keccak256;
 use ic_stable_structures::{
     log::Log as StableLog,
     memory_manager::{MemoryId, MemoryManager, VirtualMemory},
     storable::{Bound, Storable},
-    DefaultMemoryImpl, StableBTreeMap,
+    Cell as StableCell, DefaultMemoryImpl, StableBTreeMap,
 };
 use minicbor;
+use minicbor::{Decode, Encode};
 use std::borrow::Cow;
 use std::cell::RefCell;
+use std::collections::BTreeSet;
 
 const LOG_INDEX_MEMORY_ID: MemoryId = MemoryId::new(0);
 const LOG_DATA_MEMORY_ID: MemoryId = MemoryId::new(1);
+const DENY_LIST_MEMORY_ID: MemoryId = MemoryId::new(3);
+const ALLOW_LIST_MEMORY_ID: MemoryId = MemoryId::new(4);
+const EVENT_LOG_ARCHIVE_MEMORY_ID: MemoryId = MemoryId::new(5);
+const STATE_SNAPSHOT_MEMORY_ID: MemoryId = MemoryId::new(6);
+const PROTECTED_OVERRIDE_LIST_MEMORY_ID: MemoryId = MemoryId::new(7);
+const SECONDARY_LOG_INDEX_MEMORY_ID: MemoryId = MemoryId::new(8);
+const SECONDARY_LOG_DATA_MEMORY_ID: MemoryId = MemoryId::new(9);
+const PENDING_STATE_SNAPSHOT_MEMORY_ID: MemoryId = MemoryId::new(10);
+
+/// Maximum number of addresses in each of the destination deny and allow lists.
+pub const MAX_ADDRESS_LIST_ENTRIES: u64 = 10_000;
+
+/// Maximum number of events archived by a single `archive_events` call, which bounds the
+/// number of events replayed to compute the snapshot replacing them.
+pub const MAX_ARCHIVED_EVENTS_PER_CALL: u64 = 100_000;
+
+/// Maximum number of retained events copied into the inactive log memories per message
+/// while archiving, see [`continue_archive_events`].
+pub const MAX_COPIED_EVENTS_PER_MESSAGE: u64 = 10_000;
+
+/// Upper bound on the size of a raw events chunk, kept below the 2 MiB response limit.
+pub const MAX_RAW_EVENTS_CHUNK_BYTES: u32 = 1_900_000;
+
+/// Size of the length prefix preceding every entry of a raw events chunk.
+pub const RAW_EVENT_LENGTH_PREFIX_BYTES: usize = 4;
+
+/// Version of the event encoding, stored as the first byte of every event log entry.
+///
+/// Entries written before the version byte was introduced (version 0) are plain CBOR
+/// arrays, whose first byte is a major type 4 header and therefore never collides with
+/// a small version number.
+pub const CBOR_SCHEMA_VERSION: u8 = 1;
+
+/// Schema version of entries written without a version byte.
+pub const LEGACY_CBOR_SCHEMA_VERSION: u8 = 0;
+
+/// CBOR major type 4 (array), used to recognize legacy entries.
+const CBOR_ARRAY_MAJOR_TYPE: u8 = 0x80;
+const CBOR_MAJOR_TYPE_MASK: u8 = 0xe0;
 
 type VMem = VirtualMemory<DefaultMemoryImpl>;
 type EventLog = StableLog<Event, VMem, VMem>;
 type RpcApiKey = StableBTreeMap<Provider, String, VMem>;
+type AddressList = StableBTreeMap<[u8; 20], (), VMem>;
+type RawEventLog = StableLog<RawEvent, VMem, VMem>;
 
-impl Storable for Event {
+/// Bookkeeping of the events pruned from the front of the event log, see [`archive_events`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Encode, Decode)]
+pub struct EventLogArchive {
+    /// Number of archived events, which is also the absolute index of the first event
+    /// retained in the stable log.
+    #[n(0)]
+    pub archived_event_count: u64,
+    /// Events can only be archived while the log retains more than this many events.
+    /// Archiving is disabled if not set.
+    #[n(1)]
+    pub max_retained_event_count: Option<u64>,
+    /// Memories holding the event log, the primary ones if not set.
+    #[n(2)]
+    pub active_log_memories: Option<EventLogMemories>,
+    /// Archiving started but the retained events are not all copied yet.
+    #[n(3)]
+    pub pending_archive: Option<PendingArchive>,
+}
+
+impl EventLogArchive {
+    pub fn active_log_memories(&self) -> EventLogMemories {
+        self.active_log_memories.unwrap_or_default()
+    }
+}
+
+/// Pair of memories storing the index and the data of the event log.
+///
+/// The stable log cannot be truncated at the front, so archiving copies the retained events
+/// into the inactive pair and then swaps both pairs. The memories of the archived events are
+/// reset and reused by the next archiving, so the event log never takes more than twice the
+/// memory of its retained events.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Encode, Decode)]
+#[cbor(index_only)]
+pub enum EventLogMemories {
+    #[default]
+    #[n(0)]
+    Primary,
+    #[n(1)]
+    Secondary,
+}
+
+impl EventLogMemories {
+    fn other(self) -> Self {
+        match self {
+            EventLogMemories::Primary => EventLogMemories::Secondary,
+            EventLogMemories::Secondary => EventLogMemories::Primary,
+        }
+    }
+
+    fn memories(self) -> (VMem, VMem) {
+        let (index_id, data_id) = match self {
+            EventLogMemories::Primary => (LOG_INDEX_MEMORY_ID, LOG_DATA_MEMORY_ID),
+            EventLogMemories::Secondary => {
+                (SECONDARY_LOG_INDEX_MEMORY_ID, SECONDARY_LOG_DATA_MEMORY_ID)
+            }
+        };
+        MEMORY_MANAGER.with(|m| {
+            let m = m.borrow();
+            (m.get(index_id), m.get(data_id))
+        })
+    }
+}
+
+/// Archiving of the events before `up_to_index` whose retained events are being copied
+/// into the inactive log memories.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct PendingArchive {
+    #[n(0)]
+    pub up_to_index: u64,
+    /// Absolute index of the next retained event to copy.
+    #[n(1)]
+    pub next_index: u64,
+}
+
+impl Storable for EventLogArchive {
     fn to_bytes(&self) -> Cow<[u8]> {
         let mut buf = vec![];
+        minicbor::encode(self, &mut buf).expect("event log archive encoding should always succeed");
+        Cow::Owned(buf)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        minicbor::decode(bytes.as_ref()).unwrap_or_else(|e| {
+            panic!(
+                "failed to decode event log archive bytes {}: {e}",
+                hex::encode(bytes)
+            )
+        })
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Event log entry copied as is, so that retained entries keep their exact bytes
+/// (and schema version) when the log is rewritten.
+struct RawEvent(Vec<u8>);
+
+impl Storable for RawEvent {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        RawEvent(bytes.into_owned())
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for Event {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut buf = vec![CBOR_SCHEMA_VERSION];
         minicbor::encode(self, &mut buf).expect("event encoding should always succeed");
         Cow::Owned(buf)
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        minicbor::decode(bytes.as_ref())
+        decode_event(bytes.as_ref())
             .unwrap_or_else(|e| panic!("failed to decode event bytes {}: {e}", hex::encode(bytes)))
     }
 
     const BOUND: Bound = Bound::Unbounded;
 }
 
+/// Returns the schema version of an encoded event log entry.
+pub fn event_schema_version(bytes: &[u8]) -> Option<u8> {
+    match bytes.first() {
+        Some(&first) if first & CBOR_MAJOR_TYPE_MASK == CBOR_ARRAY_MAJOR_TYPE => {
+            Some(LEGACY_CBOR_SCHEMA_VERSION)
+        }
+        Some(&version) => Some(version),
+        None => None,
+    }
+}
+
+/// Decodes an event log entry of any supported schema version.
+pub fn decode_event(bytes: &[u8]) -> Result<Event, minicbor::decode::Error> {
+    match event_schema_version(bytes) {
+        Some(LEGACY_CBOR_SCHEMA_VERSION) => minicbor::decode(bytes),
+        Some(CBOR_SCHEMA_VERSION) => minicbor::decode(&bytes[1..]),
+        Some(version) => Err(minicbor::decode::Error::message(format!(
+            "unsupported event schema version {version}, latest known is {CBOR_SCHEMA_VERSION}"
+        ))),
+        None => Err(minicbor::decode::Error::message("empty event entry")),
+    }
+}
+
 impl Storable for Provider {
     const BOUND: Bound = Bound::Unbounded;
 
@@ -55,15 +234,12 @@ thread_local! {
     );
 
     /// The log of the ETH state modifications.
-    static EVENTS: RefCell<EventLog> = MEMORY_MANAGER
-        .with(|m|
-              RefCell::new(
-                  StableLog::init(
-                      m.borrow().get(LOG_INDEX_MEMORY_ID),
-                      m.borrow().get(LOG_DATA_MEMORY_ID)
-                  ).expect("failed to initialize stable log")
-              )
-        );
+    static EVENTS: RefCell<EventLog> = {
+        let (index_memory, data_memory) = event_log_archive().active_log_memories().memories();
+        RefCell::new(
+            StableLog::init(index_memory, data_memory).expect("failed to initialize stable log")
+        )
+    };
 
     // the rpc api key saved on stable storage
     static RPC_API_KEYS:RefCell<RpcApiKey>=RefCell::new(
@@ -71,6 +247,108 @@ thread_local! {
             MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(2))))
     );
 
+    // withdrawal destinations that are always refused
+    static DENY_LIST: RefCell<AddressList> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(DENY_LIST_MEMORY_ID)))
+    );
+
+    // if non-empty, the only withdrawal destinations that are accepted
+    static ALLOW_LIST: RefCell<AddressList> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(ALLOW_LIST_MEMORY_ID)))
+    );
+
+    // protected withdrawal destinations that are nevertheless accepted
+    static PROTECTED_OVERRIDE_LIST: RefCell<AddressList> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(PROTECTED_OVERRIDE_LIST_MEMORY_ID)))
+    );
+
+    // events pruned from the front of the event log
+    static EVENT_LOG_ARCHIVE: RefCell<StableCell<EventLogArchive, VMem>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(EVENT_LOG_ARCHIVE_MEMORY_ID)),
+            EventLogArchive::default(),
+        ).expect("failed to initialize event log archive")
+    );
+
+    // CBOR-encoded `StateSnapshot` of the state after the archived events, empty if none
+    static STATE_SNAPSHOT: RefCell<StableCell<Vec<u8>, VMem>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(STATE_SNAPSHOT_MEMORY_ID)),
+            vec![],
+        ).expect("failed to initialize state snapshot")
+    );
+
+    // snapshot replacing the events of the pending archive, stored once its retained events are copied
+    static PENDING_STATE_SNAPSHOT: RefCell<StableCell<Vec<u8>, VMem>> = RefCell::new(
+        StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(PENDING_STATE_SNAPSHOT_MEMORY_ID)),
+            vec![],
+        ).expect("failed to initialize pending state snapshot")
+    );
+}
+
+fn with_address_list<R>(list: ListKind, f: impl FnOnce(&mut AddressList) -> R) -> R {
+    match list {
+        ListKind::Deny => DENY_LIST.with(|l| f(&mut l.borrow_mut())),
+        ListKind::Allow => ALLOW_LIST.with(|l| f(&mut l.borrow_mut())),
+        ListKind::ProtectedOverride => PROTECTED_OVERRIDE_LIST.with(|l| f(&mut l.borrow_mut())),
+    }
+}
+
+pub fn address_list_contains(list: ListKind, address: &Address) -> bool {
+    with_address_list(list, |l| l.contains_key(&address.into_bytes()))
+}
+
+pub fn address_list_len(list: ListKind) -> u64 {
+    with_address_list(list, |l| l.len())
+}
+
+pub fn address_list_entries(list: ListKind) -> Vec<Address> {
+    with_address_list(list, |l| {
+        l.iter().map(|(bytes, ())| Address::new(bytes)).collect()
+    })
+}
+
+/// Adds or removes the given addresses. Additions are rejected as a whole if the list
+/// would exceed [`MAX_ADDRESS_LIST_ENTRIES`].
+pub fn update_address_list(
+    list: ListKind,
+    action: AddOrRemove,
+    addresses: &[Address],
+) -> Result<(), UpdateAddressListError> {
+    with_address_list(list, |l| match action {
+        AddOrRemove::Add => {
+            let new_entries: BTreeSet<[u8; 20]> = addresses
+                .iter()
+                .map(|address| address.into_bytes())
+                .filter(|bytes| !l.contains_key(bytes))
+                .collect();
+            if l.len() + new_entries.len() as u64 > MAX_ADDRESS_LIST_ENTRIES {
+                return Err(UpdateAddressListError::ListFull {
+                    max_entries: MAX_ADDRESS_LIST_ENTRIES,
+                });
+            }
+            for bytes in new_entries {
+                l.insert(bytes, ());
+            }
+            Ok(())
+        }
+        AddOrRemove::Remove => {
+            for address in addresses {
+                l.remove(&address.into_bytes());
+            }
+            Ok(())
+        }
+    })
+}
+
+/// Returns false if the destination is on the deny list, or if an allow list is
+/// configured and the destination is not on it.
+pub fn is_destination_allowed(destination: &Address) -> bool {
+    if address_list_contains(ListKind::Deny, destination) {
+        return false;
+    }
+    address_list_len(ListKind::Allow) == 0 || address_list_contains(ListKind::Allow, destination)
 }
 
 pub fn set_rpc_api_key(rpc_provider: Provider, key: String) -> Option<String> {
@@ -92,14 +370,321 @@ pub fn record_event(payload: EventType) {
         .expect("recording an event should succeed");
 }
 
-/// Returns the total number of events in the audit log.
+/// Returns the total number of events in the audit log, including archived ones.
+/// Event indices are absolute: archiving events does not shift the index of later events.
 pub fn total_event_count() -> u64 {
-    EVENTS.with(|events| events.borrow().len())
+    archived_event_count() + EVENTS.with(|events| events.borrow().len())
+}
+
+/// Returns the number of events pruned from the event log, which is also the absolute index
+/// of the first retained event.
+pub fn archived_event_count() -> u64 {
+    event_log_archive().archived_event_count
+}
+
+pub fn event_log_archive() -> EventLogArchive {
+    EVENT_LOG_ARCHIVE.with(|archive| archive.borrow().get().clone())
+}
+
+/// Sets the number of events above which the oldest events can be archived,
+/// or disables archiving if `None`.
+pub fn set_max_retained_event_count(max_retained_event_count: Option<u64>) {
+    update_event_log_archive(|archive| {
+        archive.max_retained_event_count = max_retained_event_count;
+    });
+}
+
+fn update_event_log_archive(f: impl FnOnce(&mut EventLogArchive)) {
+    EVENT_LOG_ARCHIVE.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let mut archive = cell.get().clone();
+        f(&mut archive);
+        cell.set(archive)
+            .expect("failed to update event log archive");
+    });
+}
+
+/// Returns the encoded state snapshot written when events were last archived, if any.
+pub fn state_snapshot() -> Option<Vec<u8>> {
+    STATE_SNAPSHOT.with(|snapshot| {
+        let snapshot = snapshot.borrow();
+        (!snapshot.get().is_empty()).then(|| snapshot.get().clone())
+    })
+}
+
+/// Returns the lowest and highest schema versions of the entries in the event log,
+/// or `None` if the log is empty.
+///
+/// Entries are appended in order and the version only ever increases, so it is enough
+/// to look at the first and last entries.
+pub fn event_log_schema_versions() -> Option<(u8, u8)> {
+    EVENTS.with(|events| {
+        let events = events.borrow();
+        let len = events.len();
+        if len == 0 {
+            return None;
+        }
+        let mut entry = vec![];
+        let mut version_at = |index: u64| {
+            events
+                .read_entry(index, &mut entry)
+                .expect("BUG: event index within log bounds should exist");
+            event_schema_version(&entry).expect("BUG: event log entry should not be empty")
+        };
+        let oldest = version_at(0);
+        let newest = version_at(len - 1);
+        Some((oldest, newest))
+    })
 }
 
+/// Iterates over the retained events, i.e. starting at absolute index [`archived_event_count`].
 pub fn with_event_iter<F, R>(f: F) -> R
 where
     F: for<'a> FnOnce(Box<dyn Iterator<Item = Event> + 'a>) -> R,
 {
     EVENTS.with(|events| f(Box::new(events.borrow().iter())))
 }
+
+/// Returns the CBOR-encoded events starting at absolute index `start` exactly as they are
+/// stored in the stable log, together with the index of the first event that was not included.
+/// Each event is prefixed with its schema version byte, see [`decode_event`].
+///
+/// The chunk is a concatenation of length-prefixed entries:
+///
+/// ```text
+/// | len_0: u32 (big-endian) | event_0: len_0 bytes | len_1: u32 (big-endian) | event_1 | ...
+/// ```
+///
+/// Entries are added as long as the chunk stays within `max_bytes`, which is itself
+/// capped at [`MAX_RAW_EVENTS_CHUNK_BYTES`]. The first entry is always included if `start`
+/// is within the log so that callers cannot get stuck on an entry larger than `max_bytes`.
+/// If `start` is past the end of the log, the chunk is empty and the returned index is
+/// the total number of events. Archived events are no longer available: if `start` precedes
+/// the first retained event, the chunk starts at the first retained event.
+pub fn raw_events_chunk(start: u64, max_bytes: u32) -> (Vec<u8>, u64) {
+    let max_bytes = max_bytes.min(MAX_RAW_EVENTS_CHUNK_BYTES) as usize;
+    let offset = archived_event_count();
+    EVENTS.with(|events| {
+        let events = events.borrow();
+        let len = offset + events.len();
+        let mut chunk = vec![];
+        let mut entry = vec![];
+        let mut next = start.clamp(offset, len);
+        while next < len {
+            events
+                .read_entry(next - offset, &mut entry)
+                .expect("BUG: event index within log bounds should exist");
+            let framed_len = RAW_EVENT_LENGTH_PREFIX_BYTES + entry.len();
+            if !chunk.is_empty() && chunk.len() + framed_len > max_bytes {
+                break;
+            }
+            let entry_len =
+                u32::try_from(entry.len()).expect("BUG: event entry does not fit into u32");
+            chunk.extend_from_slice(&entry_len.to_be_bytes());
+            chunk.extend_from_slice(&entry);
+            next += 1;
+        }
+        (chunk, next)
+    })
+}
+
+/// Returns the Keccak-256 hash of the retained events before absolute index `up_to_index`,
+/// framed as in [`raw_events_chunk`]. This is the hash of the concatenation of the chunks
+/// returned by `get_events_raw` from the first retained event up to `up_to_index`.
+pub fn raw_events_hash(up_to_index: u64) -> Hash {
+    let offset = archived_event_count();
+    EVENTS.with(|events| {
+        let events = events.borrow();
+        let up_to_index = up_to_index.clamp(offset, offset + events.len());
+        let mut hasher = Keccak256::new();
+        let mut entry = vec![];
+        for index in offset..up_to_index {
+            events
+                .read_entry(index - offset, &mut entry)
+                .expect("BUG: event index within log bounds should exist");
+            let entry_len =
+                u32::try_from(entry.len()).expect("BUG: event entry does not fit into u32");
+            hasher.write(&entry_len.to_be_bytes());
+            hasher.write(&entry);
+        }
+        Hash(hasher.finish())
+    })
+}
+
+/// Checks that the events before absolute index `up_to_index` can be archived
+/// with the given hash, see [`raw_events_hash`].
+pub fn validate_archive_events(
+    up_to_index: u64,
+    expected_hash: &Hash,
+) -> Result<(), ArchiveEventsError> {
+    let archive = event_log_archive();
+    let total_event_count = total_event_count();
+    let max_retained_event_count = archive
+        .max_retained_event_count
+        .ok_or(ArchiveEventsError::ArchivingDisabled)?;
+    if let Some(pending) = archive.pending_archive {
+        return Err(ArchiveEventsError::ArchiveInProgress {
+            up_to_index: pending.up_to_index,
+        });
+    }
+    let retained_event_count = total_event_count - archive.archived_event_count;
+    if retained_event_count <= max_retained_event_count {
+        return Err(ArchiveEventsError::BelowThreshold {
+            retained_event_count,
+            max_retained_event_count,
+        });
+    }
+    if up_to_index <= archive.archived_event_count || up_to_index > total_event_count {
+        return Err(ArchiveEventsError::InvalidRange {
+            archived_event_count: archive.archived_event_count,
+            total_event_count,
+        });
+    }
+    if up_to_index - archive.archived_event_count > MAX_ARCHIVED_EVENTS_PER_CALL {
+        return Err(ArchiveEventsError::TooManyEvents {
+            max_archived_event_count: MAX_ARCHIVED_EVENTS_PER_CALL,
+        });
+    }
+    if &raw_events_hash(up_to_index) != expected_hash {
+        return Err(ArchiveEventsError::HashMismatch);
+    }
+    Ok(())
+}
+
+/// Starts pruning the events before absolute index `up_to_index` from the event log, and
+/// storing `snapshot`, the encoded state resulting from applying them, in their place.
+///
+/// The stable log cannot be truncated at the front, so the retained events are copied byte
+/// for byte into the inactive log memories by [`continue_archive_events`], a bounded number
+/// per message. Until then, the event log and the snapshot are left as they are. The caller
+/// must have validated the range with [`validate_archive_events`].
+pub fn archive_events(up_to_index: u64, snapshot: Vec<u8>) {
+    let archive = event_log_archive();
+    let offset = archive.archived_event_count;
+    let total_event_count = total_event_count();
+    assert!(
+        archive.pending_archive.is_none(),
+        "BUG: cannot archive events while another archive is in progress"
+    );
+    assert!(
+        offset < up_to_index && up_to_index <= total_event_count,
+        "BUG: cannot archive events up to {up_to_index}, retained events are {offset}..{total_event_count}"
+    );
+    let (index_memory, data_memory) = archive.active_log_memories().other().memories();
+    // resets the memories left over by the previous archive
+    let _: RawEventLog = StableLog::new(index_memory, data_memory);
+    PENDING_STATE_SNAPSHOT.with(|cell| {
+        cell.borrow_mut()
+            .set(snapshot)
+            .expect("failed to store pending state snapshot");
+    });
+    update_event_log_archive(|archive| {
+        archive.pending_archive = Some(PendingArchive {
+            up_to_index,
+            next_index: up_to_index,
+        })
+    });
+}
+
+/// Returns the archive whose retained events are still being copied, if any.
+pub fn pending_archive() -> Option<PendingArchive> {
+    event_log_archive().pending_archive
+}
+
+/// Copies at most `max_copied_events` retained events of the pending archive into the
+/// inactive log memories. Once all of them are copied, including the events appended since
+/// archiving started, both log memories are swapped and the snapshot of the archived events
+/// is stored. Returns true if there is no pending archive anymore.
+pub fn continue_archive_events(max_copied_events: u64) -> bool {
+    let archive = event_log_archive();
+    let Some(pending) = archive.pending_archive else {
+        return true;
+    };
+    let offset = archive.archived_event_count;
+    let inactive = archive.active_log_memories().other();
+    let (index_memory, data_memory) = inactive.memories();
+    let copy: RawEventLog = StableLog::init(index_memory.clone(), data_memory.clone())
+        .expect("failed to initialize the inactive event log");
+    let next_index = EVENTS.with(|events| {
+        let events = events.borrow();
+        let end = (offset + events.len()).min(pending.next_index.saturating_add(max_copied_events));
+        let mut entry = vec![];
+        for index in pending.next_index..end {
+            events
+                .read_entry(index - offset, &mut entry)
+                .expect("BUG: event index within log bounds should exist");
+            copy.append(&RawEvent(std::mem::take(&mut entry)))
+                .expect("copying a retained event should succeed");
+        }
+        end
+    });
+
+    if next_index < total_event_count() {
+        update_event_log_archive(|archive| {
+            archive.pending_archive = Some(PendingArchive {
+                next_index,
+                ..pending
+            })
+        });
+        return false;
+    }
+
+    EVENTS.with(|events| {
+        *events.borrow_mut() =
+            StableLog::init(index_memory, data_memory).expect("failed to re-initialize stable log");
+    });
+    let snapshot = PENDING_STATE_SNAPSHOT.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let snapshot = cell.get().clone();
+        cell.set(vec![])
+            .expect("failed to clear pending state snapshot");
+        snapshot
+    });
+    STATE_SNAPSHOT.with(|cell| {
+        cell.borrow_mut()
+            .set(snapshot)
+            .expect("failed to store state snapshot");
+    });
+    update_event_log_archive(|archive| {
+        archive.archived_event_count = pending.up_to_index;
+        archive.active_log_memories = Some(inactive);
+        archive.pending_archive = None;
+    });
+    true
+}
+
+#[cfg(any(test, feature = "export"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RawEventsDecodeError {
+    TruncatedLengthPrefix { offset: usize },
+    TruncatedEntry { offset: usize, expected: usize },
+    InvalidEntry { offset: usize, reason: String },
+}
+
+/// Decodes a chunk returned by [`raw_events_chunk`] back into events.
+#[cfg(any(test, feature = "export"))]
+pub fn decode_raw_events(chunk: &[u8]) -> Result<Vec<Event>, RawEventsDecodeError> {
+    let mut events = vec![];
+    let mut offset = 0;
+    while offset < chunk.len() {
+        let prefix_end = offset + RAW_EVENT_LENGTH_PREFIX_BYTES;
+        let prefix: [u8; RAW_EVENT_LENGTH_PREFIX_BYTES] = chunk
+            .get(offset..prefix_end)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(RawEventsDecodeError::TruncatedLengthPrefix { offset })?;
+        let entry_len = u32::from_be_bytes(prefix) as usize;
+        let entry = chunk.get(prefix_end..prefix_end + entry_len).ok_or(
+            RawEventsDecodeError::TruncatedEntry {
+                offset,
+                expected: entry_len,
+            },
+        )?;
+        let event = decode_event(entry).map_err(|e| RawEventsDecodeError::InvalidEntry {
+            offset,
+            reason: e.to_string(),
+        })?;
+        events.push(event);
+        offset = prefix_end + entry_len;
+    }
+    Ok(events)
+}
@@ -29,3 +29,540 @@ mod api_key {
         );
     }
 }
+
+mod raw_events {
+    use crate::numeric::BlockNumber;
+    use crate::state::event::{Event, EventType};
+    use crate::storage::{
+        decode_raw_events, raw_events_chunk, total_event_count, with_event_iter,
+        RawEventsDecodeError, EVENTS, RAW_EVENT_LENGTH_PREFIX_BYTES,
+    };
+
+    fn append_events(count: u64) {
+        EVENTS.with(|events| {
+            let events = events.borrow();
+            for i in 0..count {
+                events
+                    .append(&Event {
+                        timestamp: i,
+                        payload: EventType::SyncedToBlock {
+                            block_number: BlockNumber::from(i),
+                        },
+                    })
+                    .unwrap();
+            }
+        });
+    }
+
+    fn events_range(start: u64, length: u64) -> Vec<Event> {
+        with_event_iter(|it| it.skip(start as usize).take(length as usize).collect())
+    }
+
+    #[test]
+    fn should_decode_raw_chunk_into_same_events() {
+        append_events(50);
+
+        let (chunk, next_start) = raw_events_chunk(10, u32::MAX);
+
+        assert_eq!(next_start, 50);
+        assert_eq!(decode_raw_events(&chunk), Ok(events_range(10, 40)));
+    }
+
+    #[test]
+    fn should_page_through_log_respecting_max_bytes() {
+        append_events(30);
+        let max_bytes = 64;
+
+        let mut start = 0;
+        let mut decoded = vec![];
+        while start < total_event_count() {
+            let (chunk, next_start) = raw_events_chunk(start, max_bytes);
+            assert!(chunk.len() <= max_bytes as usize);
+            assert!(next_start > start);
+            let events = decode_raw_events(&chunk).unwrap();
+            assert_eq!(events, events_range(start, next_start - start));
+            decoded.extend(events);
+            start = next_start;
+        }
+
+        assert_eq!(decoded, events_range(0, 30));
+    }
+
+    #[test]
+    fn should_return_at_least_one_event_when_max_bytes_too_small() {
+        append_events(3);
+
+        let (chunk, next_start) = raw_events_chunk(1, 0);
+
+        assert_eq!(next_start, 2);
+        assert_eq!(decode_raw_events(&chunk), Ok(events_range(1, 1)));
+    }
+
+    #[test]
+    fn should_return_empty_chunk_at_log_tail() {
+        append_events(5);
+
+        assert_eq!(raw_events_chunk(5, u32::MAX), (vec![], 5));
+        assert_eq!(raw_events_chunk(100, u32::MAX), (vec![], 5));
+    }
+
+    #[test]
+    fn should_reject_truncated_chunk() {
+        append_events(2);
+        let (chunk, _) = raw_events_chunk(0, u32::MAX);
+
+        assert_eq!(
+            decode_raw_events(&chunk[..RAW_EVENT_LENGTH_PREFIX_BYTES - 1]),
+            Err(RawEventsDecodeError::TruncatedLengthPrefix { offset: 0 })
+        );
+        assert!(matches!(
+            decode_raw_events(&chunk[..chunk.len() - 1]),
+            Err(RawEventsDecodeError::TruncatedEntry { .. })
+        ));
+    }
+}
+
+mod event_schema_version {
+    use crate::numeric::BlockNumber;
+    use crate::state::event::{Event, EventType};
+    use crate::storage::{
+        decode_event, event_log_schema_versions, event_schema_version, with_event_iter,
+        CBOR_SCHEMA_VERSION, EVENTS, LEGACY_CBOR_SCHEMA_VERSION,
+    };
+    use ic_stable_structures::Storable;
+
+    fn event() -> Event {
+        Event {
+            timestamp: 1_700_000_000,
+            payload: EventType::SyncedToBlock {
+                block_number: BlockNumber::from(42_u64),
+            },
+        }
+    }
+
+    fn legacy_bytes(event: &Event) -> Vec<u8> {
+        let mut buf = vec![];
+        minicbor::encode(event, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn should_decode_legacy_entry() {
+        let bytes = legacy_bytes(&event());
+
+        assert_eq!(
+            event_schema_version(&bytes),
+            Some(LEGACY_CBOR_SCHEMA_VERSION)
+        );
+        assert_eq!(decode_event(&bytes).unwrap(), event());
+        assert_eq!(Event::from_bytes(bytes.into()), event());
+    }
+
+    #[test]
+    fn should_prefix_new_entries_with_current_version() {
+        let bytes = event().to_bytes().into_owned();
+
+        assert_eq!(bytes[0], CBOR_SCHEMA_VERSION);
+        assert_eq!(&bytes[1..], legacy_bytes(&event()).as_slice());
+        assert_eq!(decode_event(&bytes).unwrap(), event());
+    }
+
+    #[test]
+    fn should_reject_unknown_version_and_empty_entry() {
+        let mut bytes = legacy_bytes(&event());
+        bytes.insert(0, CBOR_SCHEMA_VERSION + 1);
+
+        assert!(decode_event(&bytes).is_err());
+        assert!(decode_event(&[]).is_err());
+    }
+
+    #[test]
+    fn should_report_versions_of_event_log() {
+        assert_eq!(event_log_schema_versions(), None);
+
+        EVENTS.with(|events| {
+            let events = events.borrow();
+            events.append(&event()).unwrap();
+            events.append(&event()).unwrap();
+        });
+
+        assert_eq!(
+            event_log_schema_versions(),
+            Some((CBOR_SCHEMA_VERSION, CBOR_SCHEMA_VERSION))
+        );
+        let decoded: Vec<Event> = with_event_iter(|it| it.collect());
+        assert_eq!(decoded, vec![event(), event()]);
+    }
+}
+
+mod address_list {
+    use crate::candid_types::{AddOrRemove, ListKind, UpdateAddressListError};
+    use crate::numeric::{LedgerBurnIndex, Wei};
+    use crate::state::audit::apply_state_transition;
+    use crate::state::event::EventType;
+    use crate::state::tests::initial_state;
+    use crate::state::transactions::{NativeWithdrawalRequest, WithdrawalRequest};
+    use crate::storage::{
+        address_list_len, is_destination_allowed, update_address_list, MAX_ADDRESS_LIST_ENTRIES,
+    };
+    use evm_rpc_client::eth_types::Address;
+
+    fn address(byte: u8) -> Address {
+        Address::new([byte; 20])
+    }
+
+    #[test]
+    fn should_refuse_denied_destination() {
+        update_address_list(ListKind::Deny, AddOrRemove::Add, &[address(1)]).unwrap();
+
+        assert!(!is_destination_allowed(&address(1)));
+        assert!(is_destination_allowed(&address(2)));
+
+        update_address_list(ListKind::Deny, AddOrRemove::Remove, &[address(1)]).unwrap();
+        assert!(is_destination_allowed(&address(1)));
+    }
+
+    #[test]
+    fn should_only_accept_listed_destinations_in_allow_list_mode() {
+        update_address_list(ListKind::Allow, AddOrRemove::Add, &[address(1), address(2)]).unwrap();
+
+        assert!(is_destination_allowed(&address(1)));
+        assert!(is_destination_allowed(&address(2)));
+        assert!(!is_destination_allowed(&address(3)));
+
+        // the deny list always applies
+        update_address_list(ListKind::Deny, AddOrRemove::Add, &[address(2)]).unwrap();
+        assert!(!is_destination_allowed(&address(2)));
+
+        update_address_list(
+            ListKind::Allow,
+            AddOrRemove::Remove,
+            &[address(1), address(2)],
+        )
+        .unwrap();
+        assert_eq!(address_list_len(ListKind::Allow), 0);
+        assert!(is_destination_allowed(&address(3)));
+    }
+
+    #[test]
+    fn should_reject_additions_beyond_max_entries() {
+        let addresses: Vec<Address> = (0..MAX_ADDRESS_LIST_ENTRIES)
+            .map(|i| {
+                let mut bytes = [0_u8; 20];
+                bytes[12..].copy_from_slice(&i.to_be_bytes());
+                Address::new(bytes)
+            })
+            .collect();
+        update_address_list(ListKind::Deny, AddOrRemove::Add, &addresses).unwrap();
+        assert_eq!(address_list_len(ListKind::Deny), MAX_ADDRESS_LIST_ENTRIES);
+
+        assert_eq!(
+            update_address_list(ListKind::Deny, AddOrRemove::Add, &[address(0xff)]),
+            Err(UpdateAddressListError::ListFull {
+                max_entries: MAX_ADDRESS_LIST_ENTRIES
+            })
+        );
+        // already listed addresses do not count towards the limit
+        assert_eq!(
+            update_address_list(ListKind::Deny, AddOrRemove::Add, &addresses[..10]),
+            Ok(())
+        );
+        assert_eq!(address_list_len(ListKind::Deny), MAX_ADDRESS_LIST_ENTRIES);
+    }
+
+    #[test]
+    fn should_not_affect_already_queued_withdrawals() {
+        let mut state = initial_state();
+        let request = NativeWithdrawalRequest {
+            withdrawal_amount: Wei::new(10_000_000_000_000_000),
+            destination: address(1),
+            ledger_burn_index: LedgerBurnIndex::new(7),
+            from: "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae"
+                .parse()
+                .unwrap(),
+            from_subaccount: None,
+            created_at: Some(1699527697000000000),
+            l1_fee: None,
+            withdrawal_fee: None,
+            is_contract_destination: None,
+        };
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedNativeWithdrawalRequest(request.clone()),
+        );
+
+        update_address_list(ListKind::Deny, AddOrRemove::Add, &[address(1)]).unwrap();
+        apply_state_transition(
+            &mut state,
+            &EventType::UpdatedAddressList {
+                list: ListKind::Deny,
+                action: AddOrRemove::Add,
+                addresses: vec![address(1)],
+            },
+        );
+
+        assert!(!is_destination_allowed(&address(1)));
+        assert_eq!(
+            state.withdrawal_transactions.withdrawal_requests_batch(5),
+            vec![WithdrawalRequest::from(request)]
+        );
+    }
+}
+
+mod archive {
+    use crate::candid_types::ArchiveEventsError;
+    use crate::numeric::BlockNumber;
+    use crate::state::event::{Event, EventType};
+    use crate::storage::{
+        archive_events, archived_event_count, continue_archive_events, pending_archive,
+        raw_events_chunk, raw_events_hash, set_max_retained_event_count, state_snapshot,
+        total_event_count, validate_archive_events, PendingArchive, EVENTS,
+    };
+    use evm_rpc_client::eth_types::Hash;
+    use ic_sha3::Keccak256;
+
+    fn append_events(count: u64) {
+        EVENTS.with(|events| {
+            let events = events.borrow();
+            for i in 0..count {
+                events
+                    .append(&Event {
+                        timestamp: i,
+                        payload: EventType::SyncedToBlock {
+                            block_number: BlockNumber::from(i),
+                        },
+                    })
+                    .unwrap();
+            }
+        });
+    }
+
+    fn chunk_hash(start: u64, up_to_index: u64) -> Hash {
+        let (chunk, next_start) = raw_events_chunk(start, u32::MAX);
+        let mut hasher = Keccak256::new();
+        let mut rest = chunk.as_slice();
+        for _ in start..up_to_index.min(next_start) {
+            let (prefix, entry) = rest.split_at(RAW_EVENT_LENGTH_PREFIX_BYTES);
+            let entry_len = u32::from_be_bytes(prefix.try_into().unwrap()) as usize;
+            hasher.write(prefix);
+            hasher.write(&entry[..entry_len]);
+            rest = &entry[entry_len..];
+        }
+        Hash(hasher.finish())
+    }
+
+    #[test]
+    fn should_hash_raw_entries_as_returned_by_chunks() {
+        append_events(20);
+
+        assert_eq!(raw_events_hash(12), chunk_hash(0, 12));
+        assert_eq!(raw_events_hash(20), chunk_hash(0, 20));
+    }
+
+    #[test]
+    fn should_reject_invalid_archive_requests() {
+        append_events(20);
+        let hash = raw_events_hash(10);
+
+        assert_eq!(
+            validate_archive_events(10, &hash),
+            Err(ArchiveEventsError::ArchivingDisabled)
+        );
+
+        set_max_retained_event_count(Some(20));
+        assert_eq!(
+            validate_archive_events(10, &hash),
+            Err(ArchiveEventsError::BelowThreshold {
+                retained_event_count: 20,
+                max_retained_event_count: 20,
+            })
+        );
+
+        set_max_retained_event_count(Some(5));
+        for up_to_index in [0, 21] {
+            assert_eq!(
+                validate_archive_events(up_to_index, &hash),
+                Err(ArchiveEventsError::InvalidRange {
+                    archived_event_count: 0,
+                    total_event_count: 20,
+                })
+            );
+        }
+        assert_eq!(
+            validate_archive_events(11, &hash),
+            Err(ArchiveEventsError::HashMismatch)
+        );
+        assert_eq!(validate_archive_events(10, &hash), Ok(()));
+    }
+
+    #[test]
+    fn should_keep_absolute_indices_of_retained_events() {
+        append_events(20);
+        set_max_retained_event_count(Some(5));
+        let (retained_chunk, _) = raw_events_chunk(12, u32::MAX);
+        let snapshot = vec![1, 2, 3];
+
+        archive_events(12, snapshot.clone());
+        assert!(continue_archive_events(u64::MAX));
+
+        assert_eq!(total_event_count(), 20);
+        assert_eq!(archived_event_count(), 12);
+        assert_eq!(state_snapshot(), Some(snapshot));
+        assert_eq!(raw_events_chunk(0, u32::MAX), (retained_chunk.clone(), 20));
+        assert_eq!(raw_events_chunk(12, u32::MAX), (retained_chunk, 20));
+
+        append_events(3);
+        assert_eq!(total_event_count(), 23);
+        assert_eq!(raw_events_hash(18), chunk_hash(12, 18));
+        assert_eq!(
+            validate_archive_events(12, &raw_events_hash(12)),
+            Err(ArchiveEventsError::InvalidRange {
+                archived_event_count: 12,
+                total_event_count: 23,
+            })
+        );
+        assert_eq!(validate_archive_events(18, &raw_events_hash(18)), Ok(()));
+    }
+
+    #[test]
+    fn should_archive_large_log_over_several_calls() {
+        append_events(50);
+        set_max_retained_event_count(Some(5));
+        let snapshot = vec![1, 2, 3];
+
+        archive_events(30, snapshot.clone());
+        assert_eq!(
+            pending_archive(),
+            Some(PendingArchive {
+                up_to_index: 30,
+                next_index: 30,
+            })
+        );
+        assert_eq!(
+            validate_archive_events(40, &raw_events_hash(40)),
+            Err(ArchiveEventsError::ArchiveInProgress { up_to_index: 30 })
+        );
+
+        assert!(!continue_archive_events(7));
+        // events appended while archiving are copied as well
+        append_events(5);
+        assert_eq!(archived_event_count(), 0);
+        assert_eq!(state_snapshot(), None);
+        let (retained_chunk, _) = raw_events_chunk(30, u32::MAX);
+
+        let mut calls = 1;
+        while !continue_archive_events(7) {
+            calls += 1;
+        }
+        // the 18 remaining events are copied 7 at a time
+        assert_eq!(calls, 3);
+        assert_eq!(pending_archive(), None);
+        assert_eq!(total_event_count(), 55);
+        assert_eq!(archived_event_count(), 30);
+        assert_eq!(state_snapshot(), Some(snapshot));
+        assert_eq!(raw_events_chunk(30, u32::MAX), (retained_chunk, 55));
+
+        // the next archive reuses the memories of the archived events
+        let (retained_chunk, _) = raw_events_chunk(45, u32::MAX);
+        archive_events(45, vec![4, 5]);
+        while !continue_archive_events(3) {}
+        assert_eq!(archived_event_count(), 45);
+        assert_eq!(state_snapshot(), Some(vec![4, 5]));
+        assert_eq!(raw_events_chunk(45, u32::MAX), (retained_chunk, 55));
+    }
+}
+
+mod compaction {
+    use crate::candid_types::{ArchiveEventsError, CompactEventLogError};
+    use crate::numeric::BlockNumber;
+    use crate::state::audit::{
+        archive_events_without_recording, compactable_event_count, replay_events,
+        validate_compact_event_log,
+    };
+    use crate::storage::{
+        archived_event_count, raw_events_hash, set_max_retained_event_count, total_event_count,
+        with_event_iter, EVENTS,
+    };
+    use crate::test_fixtures::replay::{canonical_state, event_sequence, replay};
+
+    fn append_event_sequence() {
+        EVENTS.with(|events| {
+            let events = events.borrow();
+            for event in event_sequence() {
+                events.append(&event).unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn should_only_prune_settled_events_before_threshold() {
+        append_event_sequence();
+        let state = replay(&event_sequence());
+
+        let setup_event_count = compactable_event_count(&state, BlockNumber::ZERO);
+        let compactable = compactable_event_count(&state, BlockNumber::MAX);
+        assert!(setup_event_count > 0);
+        assert!(compactable > setup_event_count);
+        assert!(compactable <= total_event_count());
+    }
+
+    #[test]
+    fn should_reject_compacting_unsettled_events() {
+        append_event_sequence();
+        let state = replay(&event_sequence());
+        let compactable = compactable_event_count(&state, BlockNumber::ZERO);
+
+        assert_eq!(
+            validate_compact_event_log(&state, BlockNumber::ZERO, compactable + 1),
+            Err(CompactEventLogError::UnsettledEvents {
+                compactable_up_to_index: compactable,
+            })
+        );
+        assert_eq!(
+            validate_compact_event_log(&state, BlockNumber::ZERO, compactable),
+            Ok(compactable)
+        );
+    }
+
+    #[test]
+    fn should_require_export_proof_to_compact() {
+        append_event_sequence();
+        let state = replay(&event_sequence());
+        set_max_retained_event_count(Some(1));
+        let up_to_index = compactable_event_count(&state, BlockNumber::MAX);
+
+        assert_eq!(
+            archive_events_without_recording(up_to_index, &raw_events_hash(up_to_index - 1)),
+            Err(ArchiveEventsError::HashMismatch)
+        );
+        assert_eq!(archived_event_count(), 0);
+    }
+
+    #[test]
+    fn should_replay_same_state_after_compaction() {
+        append_event_sequence();
+        let state = replay(&event_sequence());
+        set_max_retained_event_count(Some(1));
+        let retained_before = with_event_iter(|iter| iter.count() as u64);
+        let up_to_index = compactable_event_count(&state, BlockNumber::MAX);
+
+        let pruned_count =
+            validate_compact_event_log(&state, BlockNumber::MAX, up_to_index).unwrap();
+        assert_eq!(
+            archive_events_without_recording(up_to_index, &raw_events_hash(up_to_index)),
+            Ok(0)
+        );
+
+        assert!(pruned_count > 0);
+        assert_eq!(archived_event_count(), pruned_count);
+        assert_eq!(
+            with_event_iter(|iter| iter.count() as u64),
+            retained_before - pruned_count
+        );
+        assert_eq!(canonical_state(&replay_events()), canonical_state(&state));
+        assert_eq!(
+            compactable_event_count(&replay_events(), BlockNumber::MAX),
+            0
+        );
+    }
+}
@@ -1,3 +1,7 @@
+//! Hex encoding of the commands data of dex orders.
+//!
+//! [`decode_commands_data`] is fuzzed, see `minter/FUZZING.md`.
+
 use crate::rpc_declarations::Data;
 
 // New function to decode commands_data from Vec<String> (hex strings) to Vec<Data>
@@ -1,35 +1,180 @@
 use crate::candid_types::dex_orders::DexOrderError;
-use crate::evm_config::EvmNetwork;
+use crate::candid_types::{ActivateSwapError, ActivateSwapReqest, RevokeApprovalError};
+use crate::erc20::{ERC20Token, ERC20TokenSymbol};
+use crate::evm_config::{EvmNetwork, GasLimitKind};
 use crate::logs::DEBUG;
 use crate::rpc_declarations::Data;
 use crate::state::balances::{release_gas_from_tank_with_usdc, ReleaseGasFromTankError};
 use crate::state::transactions::data::Command;
 use crate::state::transactions::ExecuteSwapRequest;
 use crate::state::TwinUSDCInfo;
-use crate::swap::command_data::decode_commands_data;
-use crate::tx::gas_fees::{estimate_dex_order_fee, DEFAULT_L1_BASE_GAS_FEE};
-use crate::tx::gas_usd::MaxFeeUsd;
-use crate::withdraw::{REFUND_FAILED_SWAP_GAS_LIMIT, UNLIMITED_DEADLINE};
+use crate::state::{read_state, State};
+use crate::storage::is_destination_allowed;
+use crate::tx::gas_fees::{estimate_dex_order_fee, l1_fee_estimate};
 use crate::{
-    candid_types::dex_orders::DexOrderArgs,
-    numeric::{Erc20Value, Wei},
+    candid_types::dex_orders::{
+        DexOrderQuote, RefundableDexOrderArgs, SwapFeeQuote, ValidatedDexOrderArgs,
+    },
+    numeric::{Erc20Value, GasAmount, LedgerBurnIndex, Wei},
 };
 use candid::Principal;
 use evm_rpc_client::eth_types::Address;
 use ic_canister_log::log;
 
 pub mod command_data;
+pub mod receipt_logs;
 
-pub async fn build_dex_swap_request(
-    args: &DexOrderArgs,
-    twin_usdc_info: &TwinUSDCInfo,
-    gas_usd_price: f64,
-    actual_signing_fee: Erc20Value,
-    swap_contract: Address,
-    evm_network: EvmNetwork,
-    from: Principal,
-) -> Result<ExecuteSwapRequest, DexOrderError> {
-    let gas_limit = args.gas_limit().map_err(DexOrderError::InvalidGasLimit)?;
+#[cfg(test)]
+mod tests;
+
+/// Fees and amounts of a dex order, computed without mutating the state.
+/// Both the dry-run quote and the actual swap request are derived from it,
+/// so that they cannot diverge.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DexOrderPlan {
+    pub gas_limit: GasAmount,
+    pub max_transaction_fee: Wei,
+    pub max_gas_fee_twin_usdc: Erc20Value,
+    pub l1_fee: Option<Wei>,
+    pub recipient: Address,
+    pub deadline: Erc20Value,
+    pub amount_in_minus_fees: Erc20Value,
+    pub min_amount_out: Erc20Value,
+    pub all_twin_usdc_fees: Erc20Value,
+    pub commands: Vec<Command>,
+    pub commands_data: Vec<Data>,
+}
+
+impl DexOrderPlan {
+    pub fn quote(&self, gas_tank_native_balance: Wei) -> DexOrderQuote {
+        DexOrderQuote {
+            max_transaction_fee: self.max_transaction_fee.into(),
+            max_gas_fee_twin_usdc: self.max_gas_fee_twin_usdc.into(),
+            amount_in_minus_fees: self.amount_in_minus_fees.into(),
+            gas_tank_has_capacity: gas_tank_native_balance >= self.max_transaction_fee,
+        }
+    }
+
+    pub fn into_swap_request(
+        self,
+        args: &ValidatedDexOrderArgs,
+        twin_usdc_info: &TwinUSDCInfo,
+        swap_contract: Address,
+        native_ledger_burn_index: LedgerBurnIndex,
+        from: Principal,
+        created_at: u64,
+    ) -> ExecuteSwapRequest {
+        ExecuteSwapRequest {
+            max_transaction_fee: self.max_transaction_fee,
+            erc20_token_in: twin_usdc_info.address,
+            erc20_amount_in: self.amount_in_minus_fees,
+            min_amount_out: self.min_amount_out,
+            recipient: self.recipient,
+            deadline: self.deadline,
+            commands: self.commands,
+            commands_data: self.commands_data,
+            swap_contract,
+            gas_estimate: self.gas_limit,
+            native_ledger_burn_index,
+            erc20_ledger_id: twin_usdc_info.ledger_id,
+            erc20_ledger_burn_index: args.erc20_ledger_burn_index,
+            from,
+            from_subaccount: None,
+            created_at,
+            l1_fee: self.l1_fee,
+            withdrawal_fee: None,
+            swap_tx_id: args.tx_id(),
+            is_refund: false,
+        }
+    }
+}
+
+/// Arguments of `activate_swap_feature` once validated against the state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwapActivation {
+    pub swap_contract_address: Address,
+    pub twin_usdc_token: ERC20Token,
+    pub canister_signing_fee_twin_usdc_value: Erc20Value,
+}
+
+/// Validates a swap activation request without mutating the state, so that
+/// nothing is burnt or recorded when the request is invalid.
+pub fn validate_swap_activation(
+    state: &State,
+    request: &ActivateSwapReqest,
+) -> Result<SwapActivation, ActivateSwapError> {
+    use std::str::FromStr;
+
+    let swap_contract_address = Address::from_str(&request.swap_contract_address).map_err(|e| {
+        ActivateSwapError::InvalidSwapContractAddress(format!("Invalid address: {e}"))
+    })?;
+
+    let canister_signing_fee_twin_usdc_value =
+        Erc20Value::try_from(request.canister_signing_fee_twin_usdc_value.clone())
+            .map_err(ActivateSwapError::InvalidFeeValue)?;
+
+    let twin_usdc_token = state
+        .find_erc20_token_by_ledger_id(&request.twin_usdc_ledger_id)
+        .ok_or(ActivateSwapError::TokenNotFound {
+            twin_usdc_ledger_id: request.twin_usdc_ledger_id,
+        })?;
+
+    Ok(SwapActivation {
+        swap_contract_address,
+        twin_usdc_token,
+        canister_signing_fee_twin_usdc_value,
+    })
+}
+
+/// Approval to revoke, with the symbol of the approved token for the burn memo.
+pub struct ApprovalRevocation {
+    pub erc20_contract_address: Address,
+    pub spender: Address,
+    pub erc20_token_symbol: ERC20TokenSymbol,
+}
+
+/// Validates a request to revoke the approval given by `token` to `spender` without
+/// mutating the state, so that nothing is burnt when there is no such approval.
+pub fn validate_approval_revocation(
+    state: &State,
+    token: &str,
+    spender: &str,
+) -> Result<ApprovalRevocation, RevokeApprovalError> {
+    use std::str::FromStr;
+
+    let parse_address = |address: &str| {
+        Address::from_str(address)
+            .map_err(|e| RevokeApprovalError::InvalidAddress(format!("Invalid address: {e}")))
+    };
+    let erc20_contract_address = parse_address(token)?;
+    let spender = parse_address(spender)?;
+
+    if !state
+        .onchain_approvals
+        .contains_key(&(erc20_contract_address, spender))
+    {
+        return Err(RevokeApprovalError::ApprovalNotFound);
+    }
+    if state.is_approval_revocation_pending(&erc20_contract_address, &spender) {
+        return Err(RevokeApprovalError::RevocationPending);
+    }
+    let erc20_token_symbol = state
+        .erc20_tokens
+        .get_alt(&erc20_contract_address)
+        .cloned()
+        .ok_or(RevokeApprovalError::ApprovalNotFound)?;
+
+    Ok(ApprovalRevocation {
+        erc20_contract_address,
+        spender,
+        erc20_token_symbol,
+    })
+}
+
+async fn estimate_dex_order_tx_fee(
+    args: &ValidatedDexOrderArgs,
+) -> Result<(GasAmount, Wei), DexOrderError> {
+    let gas_limit = args.gas_limit;
 
     let erc20_tx_fee =
         estimate_dex_order_fee(gas_limit)
@@ -37,17 +182,33 @@ pub async fn build_dex_swap_request(
             .ok_or(DexOrderError::TemporarilyUnavailable(
                 "Failed to retrieve current gas fee".to_string(),
             ))?;
+    Ok((gas_limit, erc20_tx_fee))
+}
 
+/// Validates a dex order and computes its fees given the current transaction fee estimate.
+pub fn plan_dex_order(
+    args: &ValidatedDexOrderArgs,
+    twin_usdc_info: &TwinUSDCInfo,
+    gas_usd_price: f64,
+    actual_signing_fee: Erc20Value,
+    gas_limit: GasAmount,
+    erc20_tx_fee: Wei,
+    l1_fee: Option<Wei>,
+) -> Result<DexOrderPlan, DexOrderError> {
     // if the max transaction fee is specified in the request use that, else use the estimated tx
     // fee, in the refund transactions the max fee is not represented thats why we should calculate
     // it.
-    let max_transaction_fee =
-        match args.max_gas_fee_amount(actual_signing_fee, twin_usdc_info.decimals, gas_usd_price) {
-            Some(max_transaction_fee) => max_transaction_fee,
-            None => erc20_tx_fee,
-        };
+    let max_transaction_fee = match args.args.max_gas_fee_amount(
+        actual_signing_fee,
+        twin_usdc_info.decimals,
+        gas_usd_price,
+    ) {
+        Some(max_transaction_fee) => max_transaction_fee,
+        None => erc20_tx_fee,
+    };
 
     let max_gas_fee_twin_usdc = match args
+        .args
         .max_gas_fee_twin_usdc_amount(twin_usdc_info.decimals, actual_signing_fee)
     {
         Some(max_gas_fee_twin_usdc) => {
@@ -59,23 +220,14 @@ pub async fn build_dex_swap_request(
             max_gas_fee_twin_usdc
         }
         None => {
-            let converted_fee = MaxFeeUsd::twin_usdc_from_native_wei(
-                max_transaction_fee,
-                gas_usd_price,
-                twin_usdc_info.decimals,
-            )
-            .map_err(DexOrderError::TemporarilyUnavailable)?;
+            let converted_fee =
+                max_gas_fee_twin_usdc(twin_usdc_info, gas_usd_price, max_transaction_fee)?;
             log!(DEBUG, "[dex_order]: Calculated max gas fee in twin USDC: {:?} from max_transaction_fee: {:?}", 
             converted_fee, max_transaction_fee);
             converted_fee
         }
     };
 
-    let l1_fee = match evm_network {
-        EvmNetwork::Base => Some(DEFAULT_L1_BASE_GAS_FEE),
-        _ => None,
-    };
-
     let total_required_fee = erc20_tx_fee
         .checked_add(l1_fee.unwrap_or(Wei::ZERO))
         .unwrap_or(Wei::MAX);
@@ -90,86 +242,156 @@ pub async fn build_dex_swap_request(
         return Err(DexOrderError::MaxUsdFeeTooLow);
     }
 
-    let recipient = args.recipient().map_err(DexOrderError::InvalidRecipient)?;
-    let deadline = args.deadline().map_err(DexOrderError::InvalidDeadline)?;
-    let now = ic_cdk::api::time();
-    let erc20_ledger_burn_index = args.erc20_ledger_burn_index();
+    if !is_destination_allowed(&args.recipient) {
+        return Err(DexOrderError::DestinationNotAllowed);
+    }
 
-    let (erc20_amount_in, min_amount_out, all_twin_usdc_fees, commands, commands_data) =
-        prepare_order_details(args, max_gas_fee_twin_usdc, actual_signing_fee)?;
+    let all_twin_usdc_fees = all_twin_usdc_fees(max_gas_fee_twin_usdc, actual_signing_fee);
 
-    let native_ledger_burn_index =
-        release_gas_from_tank_with_usdc(all_twin_usdc_fees, max_transaction_fee, args.tx_id())
-            .map_err(
-                |ReleaseGasFromTankError {
-                     requested,
-                     available,
-                 }| DexOrderError::NotEnoughGasInGasTank {
-                    requested: requested.into(),
-                    available: available.into(),
-                },
-            )?;
+    let amount_in_minus_fees = args
+        .amount_in
+        .checked_sub(all_twin_usdc_fees)
+        .ok_or(DexOrderError::UsdcAmountInTooLow)?;
 
-    Ok(ExecuteSwapRequest {
+    Ok(DexOrderPlan {
+        gas_limit,
         max_transaction_fee,
-        erc20_token_in: twin_usdc_info.address,
-        erc20_amount_in,
-        min_amount_out,
-        recipient,
-        deadline,
-        commands,
-        commands_data,
-        swap_contract,
-        gas_estimate: gas_limit,
-        native_ledger_burn_index,
-        erc20_ledger_id: twin_usdc_info.ledger_id,
-        erc20_ledger_burn_index,
-        from,
-        from_subaccount: None,
-        created_at: now,
+        max_gas_fee_twin_usdc,
         l1_fee,
-        withdrawal_fee: None,
-        swap_tx_id: args.tx_id(),
-        is_refund: false,
+        recipient: args.recipient,
+        deadline: args.deadline,
+        amount_in_minus_fees,
+        min_amount_out: args.min_amount_out,
+        all_twin_usdc_fees,
+        commands: args.commands.clone(),
+        commands_data: args.commands_data.clone(),
     })
 }
 
-fn prepare_order_details(
-    args: &DexOrderArgs,
-    max_gas_fee_twin_usdc: Erc20Value,
-    signing_fee: Erc20Value,
-) -> Result<(Erc20Value, Erc20Value, Erc20Value, Vec<Command>, Vec<Data>), DexOrderError> {
-    let amount_in =
-        Erc20Value::try_from(args.amount_in.clone()).map_err(|_| DexOrderError::InvalidAmount)?;
-    let min_amount_out = Erc20Value::try_from(args.min_amount_out.clone())
-        .map_err(|_| DexOrderError::InvalidAmount)?;
+fn max_gas_fee_twin_usdc(
+    twin_usdc_info: &TwinUSDCInfo,
+    gas_usd_price: f64,
+    max_transaction_fee: Wei,
+) -> Result<Erc20Value, DexOrderError> {
+    twin_usdc_info
+        .usdc_from_native_wei(max_transaction_fee, gas_usd_price)
+        .map_err(DexOrderError::TemporarilyUnavailable)
+}
 
-    let all_twin_usdc_fees = max_gas_fee_twin_usdc
+fn all_twin_usdc_fees(max_gas_fee_twin_usdc: Erc20Value, signing_fee: Erc20Value) -> Erc20Value {
+    max_gas_fee_twin_usdc
         .checked_add(signing_fee)
-        .unwrap_or(Erc20Value::MAX);
+        .unwrap_or(Erc20Value::MAX)
+}
 
-    let amount_in_minus_fees = amount_in
-        .checked_sub(all_twin_usdc_fees)
-        .ok_or(DexOrderError::UsdcAmountInTooLow)?;
+/// Maximum age of the gas fee estimate a swap fee quote is computed from.
+pub const MAX_SWAP_FEE_QUOTE_ESTIMATE_AGE_NS: u64 = 5 * 60 * 1_000_000_000;
 
-    let commands_data =
-        decode_commands_data(&args.commands_data).map_err(DexOrderError::InvalidCommandData)?;
-    let commands = args
-        .commands
-        .iter()
-        .map(|&command| Command::from_u8(command).map_err(DexOrderError::InvalidCommand))
-        .collect::<Result<Vec<Command>, DexOrderError>>()?;
-    Ok((
-        amount_in_minus_fees,
-        min_amount_out,
-        all_twin_usdc_fees,
-        commands,
-        commands_data,
+/// Fees of a swap whose transaction costs at most `max_transaction_fee`, converted to twin
+/// USDC the same way as by [`plan_dex_order`] for orders without a specified max gas fee.
+///
+/// Fails if the gas fee estimate `max_transaction_fee` was computed from is older than
+/// [`MAX_SWAP_FEE_QUOTE_ESTIMATE_AGE_NS`].
+pub fn swap_fee_quote(
+    twin_usdc_info: &TwinUSDCInfo,
+    (price_timestamp, gas_usd_price): (u64, f64),
+    signing_fee: Erc20Value,
+    (estimate_timestamp, max_transaction_fee): (u64, Wei),
+    gas_tank_native_balance: Wei,
+    now_ns: u64,
+) -> Result<SwapFeeQuote, DexOrderError> {
+    if now_ns > estimate_timestamp.saturating_add(MAX_SWAP_FEE_QUOTE_ESTIMATE_AGE_NS) {
+        return Err(DexOrderError::TemporarilyUnavailable(
+            "Gas fee estimate is stale".to_string(),
+        ));
+    }
+    let max_gas_fee_twin_usdc =
+        max_gas_fee_twin_usdc(twin_usdc_info, gas_usd_price, max_transaction_fee)?;
+    Ok(SwapFeeQuote {
+        max_transaction_fee: max_transaction_fee.into(),
+        max_gas_fee_twin_usdc: max_gas_fee_twin_usdc.into(),
+        signing_fee_twin_usdc: signing_fee.into(),
+        total_fee_twin_usdc: all_twin_usdc_fees(max_gas_fee_twin_usdc, signing_fee).into(),
+        native_token_usd_price_timestamp: price_timestamp,
+        gas_fee_estimate_timestamp: estimate_timestamp,
+        gas_tank_has_capacity: gas_tank_native_balance >= max_transaction_fee,
+    })
+}
+
+/// Dry run of [`build_dex_swap_request`]: runs the same validation and fee computation
+/// but neither releases gas from the gas tank nor mutates the state.
+pub async fn quote_dex_order(
+    args: &ValidatedDexOrderArgs,
+    twin_usdc_info: &TwinUSDCInfo,
+    gas_usd_price: f64,
+    actual_signing_fee: Erc20Value,
+    evm_network: EvmNetwork,
+) -> Result<DexOrderQuote, DexOrderError> {
+    let (gas_limit, erc20_tx_fee) = estimate_dex_order_tx_fee(args).await?;
+    let plan = plan_dex_order(
+        args,
+        twin_usdc_info,
+        gas_usd_price,
+        actual_signing_fee,
+        gas_limit,
+        erc20_tx_fee,
+        l1_fee_estimate(evm_network, read_state(|s| s.current_l1_fee)),
+    )?;
+    let gas_tank_native_balance = read_state(|s| s.gas_tank.native_balance);
+    Ok(plan.quote(gas_tank_native_balance))
+}
+
+pub async fn build_dex_swap_request(
+    args: &ValidatedDexOrderArgs,
+    twin_usdc_info: &TwinUSDCInfo,
+    gas_usd_price: f64,
+    actual_signing_fee: Erc20Value,
+    swap_contract: Address,
+    evm_network: EvmNetwork,
+    from: Principal,
+) -> Result<ExecuteSwapRequest, DexOrderError> {
+    let (gas_limit, erc20_tx_fee) = estimate_dex_order_tx_fee(args).await?;
+    let plan = plan_dex_order(
+        args,
+        twin_usdc_info,
+        gas_usd_price,
+        actual_signing_fee,
+        gas_limit,
+        erc20_tx_fee,
+        l1_fee_estimate(evm_network, read_state(|s| s.current_l1_fee)),
+    )?;
+    let now = ic_cdk::api::time();
+
+    let native_ledger_burn_index = release_gas_from_tank_with_usdc(
+        plan.all_twin_usdc_fees,
+        plan.max_transaction_fee,
+        args.tx_id(),
+    )
+    .map_err(
+        |ReleaseGasFromTankError {
+             requested,
+             available,
+         }| DexOrderError::NotEnoughGasInGasTank {
+            requested: requested.into(),
+            available: available.into(),
+        },
+    )?;
+
+    Ok(plan.into_swap_request(
+        args,
+        twin_usdc_info,
+        swap_contract,
+        native_ledger_burn_index,
+        from,
+        now,
     ))
 }
 
+/// Builds the refund of a dex order that cannot be executed: the twin USDC amount minus fees is
+/// transferred back to the recipient as is, without executing any command of the original
+/// order, since the swap contract holds no wrapped native token to unwrap.
 pub async fn build_dex_swap_refund_request(
-    args: &DexOrderArgs,
+    args: &RefundableDexOrderArgs,
     twin_usdc_info: &TwinUSDCInfo,
     gas_usd_price: f64,
     signing_fee: Erc20Value,
@@ -177,39 +399,26 @@ pub async fn build_dex_swap_refund_request(
     from: Principal,
     swap_contract: Address,
 ) -> Result<ExecuteSwapRequest, DexOrderError> {
-    let amount = args.amount_in.clone();
-    let original_amount =
-        Erc20Value::try_from(amount).expect("BUG: amount should be valid at this point");
-    let recipient = args
-        .recipient()
-        .expect("BUG: recipient should be valid at this point");
-
-    let erc20_tx_fee = estimate_dex_order_fee(REFUND_FAILED_SWAP_GAS_LIMIT)
-        .await
-        .ok_or(DexOrderError::TemporarilyUnavailable(
-            "Failed to retrieve current gas fee".to_string(),
-        ))?;
-
-    let l1_fee = match evm_network {
-        EvmNetwork::Base => Some(DEFAULT_L1_BASE_GAS_FEE),
-        _ => None,
-    };
+    let refund_gas_limit = read_state(|s| s.gas_limit(GasLimitKind::RefundFailedSwap));
+    let erc20_tx_fee = estimate_dex_order_fee(refund_gas_limit).await.ok_or(
+        DexOrderError::TemporarilyUnavailable("Failed to retrieve current gas fee".to_string()),
+    )?;
+
+    let l1_fee = l1_fee_estimate(evm_network, read_state(|s| s.current_l1_fee));
     let fee_to_be_deducted = erc20_tx_fee
         .checked_add(l1_fee.unwrap_or(Wei::ZERO))
         .expect("Bug: Tx_fee plus l1_fee should fit in u256");
 
-    let max_gas_fee_twin_usdc = MaxFeeUsd::twin_usdc_from_native_wei(
-        fee_to_be_deducted,
-        gas_usd_price,
-        twin_usdc_info.decimals,
-    )
-    .map_err(|_| DexOrderError::UsdcAmountInTooLow)?;
+    let max_gas_fee_twin_usdc = twin_usdc_info
+        .usdc_from_native_wei(fee_to_be_deducted, gas_usd_price)
+        .map_err(|_| DexOrderError::UsdcAmountInTooLow)?;
 
     let all_twin_usdc_fees = max_gas_fee_twin_usdc
         .checked_add(signing_fee)
         .unwrap_or(Erc20Value::MAX);
 
-    let amount_in = original_amount
+    let amount_in = args
+        .amount_in
         .checked_sub(all_twin_usdc_fees)
         .ok_or(DexOrderError::UsdcAmountInTooLow)?;
 
@@ -232,15 +441,15 @@ pub async fn build_dex_swap_refund_request(
         erc20_token_in: twin_usdc_info.address,
         erc20_amount_in: amount_in,
         min_amount_out: amount_in,
-        recipient,
-        deadline: UNLIMITED_DEADLINE,
+        recipient: args.recipient,
+        deadline: read_state(|s| s.refund_deadline(now)),
         commands: vec![],
         commands_data: vec![],
         swap_contract,
-        gas_estimate: REFUND_FAILED_SWAP_GAS_LIMIT,
+        gas_estimate: refund_gas_limit,
         native_ledger_burn_index,
         erc20_ledger_id: twin_usdc_info.ledger_id,
-        erc20_ledger_burn_index: args.erc20_ledger_burn_index(),
+        erc20_ledger_burn_index: args.erc20_ledger_burn_index,
         from,
         from_subaccount: None,
         created_at: now,
@@ -258,5 +467,6 @@ pub fn is_quarantine_error(err: &DexOrderError) -> bool {
             | DexOrderError::InvalidAmount
             | DexOrderError::InvalidMaxUsdFeeAmount(_)
             | DexOrderError::InvalidRecipient(_)
+            | DexOrderError::DestinationNotAllowed
     )
 }
@@ -0,0 +1,388 @@
+use crate::contract_logs::swap::swap_logs::SwapExecuted;
+use crate::contract_logs::EventSignature;
+use crate::erc20::transfer_log::TRANSFER_EVENT_TOPIC;
+use crate::numeric::Erc20Value;
+use crate::rpc_declarations::LogEntry;
+use crate::state::transactions::data::Command;
+use crate::state::transactions::ExecuteSwapRequest;
+use evm_rpc_client::eth_types::Address;
+
+alloy::sol! {
+    event Withdrawal(address indexed src, uint256 wad);
+}
+
+/// Returns the amount of output tokens received by the recipient of the swap `request`,
+/// parsed from the `logs` of its successful transaction.
+///
+/// Swaps to the native token end with [`Command::UnwrapEth`] and their output is the amount
+/// unwrapped by the `Withdrawal` events of the `wrapped_native_token` contract. Other swaps
+/// deliver ERC-20 tokens and their output is the amount moved by `Transfer` events of the
+/// output token to the recipient. The output token of a refund is its input token, the one
+/// of a swap is the `tokenOut` of the `SwapExecuted` event of the swap contract.
+///
+/// Returns `None` if no such event was found.
+pub fn realized_amount_out(
+    request: &ExecuteSwapRequest,
+    wrapped_native_token: &Address,
+    logs: &[LogEntry],
+) -> Option<Erc20Value> {
+    let logs: Vec<&LogEntry> = logs.iter().filter(|log| !log.removed).collect();
+    let amounts: Vec<Erc20Value> = if request.commands.last() == Some(&Command::UnwrapEth) {
+        logs.iter()
+            .filter_map(|log| unwrapped_amount(log, wrapped_native_token))
+            .collect()
+    } else {
+        let token_out = if request.commands.is_empty() {
+            request.erc20_token_in
+        } else {
+            swap_token_out(&logs, &request.swap_contract)?
+        };
+        logs.iter()
+            .filter_map(|log| transferred_amount(log, &token_out, &request.recipient))
+            .collect()
+    };
+    if amounts.is_empty() {
+        return None;
+    }
+    amounts
+        .into_iter()
+        .try_fold(Erc20Value::ZERO, |total, amount| total.checked_add(amount))
+}
+
+fn swap_token_out(logs: &[&LogEntry], swap_contract: &Address) -> Option<Address> {
+    logs.iter()
+        .find(|log| {
+            log.address == *swap_contract
+                && log.topics.len() == 4
+                && log.topics[0] == SwapExecuted::event_signature_hash()
+        })
+        .and_then(|log| Address::try_from(&log.topics[3].0).ok())
+}
+
+fn unwrapped_amount(log: &LogEntry, wrapped_native_token: &Address) -> Option<Erc20Value> {
+    if log.address == *wrapped_native_token
+        && log.topics.len() == 2
+        && log.topics[0] == Withdrawal::event_signature_hash()
+    {
+        single_word(log)
+    } else {
+        None
+    }
+}
+
+fn transferred_amount(log: &LogEntry, token: &Address, to: &Address) -> Option<Erc20Value> {
+    if log.address == *token
+        && log.topics.len() == 3
+        && log.topics[0].0 == TRANSFER_EVENT_TOPIC
+        && log.topics[2].0 == <[u8; 32]>::from(to)
+    {
+        single_word(log)
+    } else {
+        None
+    }
+}
+
+fn single_word(log: &LogEntry) -> Option<Erc20Value> {
+    <[u8; 32]>::try_from(log.data.0.as_slice())
+        .ok()
+        .map(Erc20Value::from_be_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::numeric::{GasAmount, LedgerBurnIndex, Wei};
+    use crate::rpc_declarations::{Data, FixedSizeData};
+    use candid::Principal;
+    use std::str::FromStr;
+
+    const RECIPIENT: &str = "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34";
+    const SWAP_CONTRACT: &str = "0x1789F79e95324A47c5Fd6693071188e82E9a3558";
+    const USDC: &str = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913";
+    const WETH: &str = "0x4200000000000000000000000000000000000006";
+    const POOL: &str = "0xd0b53D9277642d899DF5C87A3966A349A798F224";
+
+    const AMOUNT_IN: u128 = 5_000_000;
+    const REALIZED_AMOUNT_OUT: u128 = 1_300_000_000_000_000;
+
+    fn address(address: &str) -> Address {
+        Address::from_str(address).unwrap()
+    }
+
+    fn log(address: &str, topics: Vec<[u8; 32]>, data: Vec<u8>) -> LogEntry {
+        LogEntry {
+            address: self::address(address),
+            topics: topics.into_iter().map(FixedSizeData).collect(),
+            data: Data(data),
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            block_hash: None,
+            log_index: None,
+            removed: false,
+        }
+    }
+
+    fn topic(address: &str) -> [u8; 32] {
+        <[u8; 32]>::from(&self::address(address))
+    }
+
+    fn transfer(token: &str, from: &str, to: &str, value: u128) -> LogEntry {
+        log(
+            token,
+            vec![TRANSFER_EVENT_TOPIC, topic(from), topic(to)],
+            Erc20Value::new(value).to_be_bytes().to_vec(),
+        )
+    }
+
+    fn withdrawal(wrapped_native_token: &str, src: &str, wad: u128) -> LogEntry {
+        log(
+            wrapped_native_token,
+            vec![Withdrawal::event_signature_hash().0, topic(src)],
+            Erc20Value::new(wad).to_be_bytes().to_vec(),
+        )
+    }
+
+    // The non-indexed fields of the event are not parsed and left out of its data.
+    fn swap_executed(swap_contract: &str, token_in: &str, token_out: &str) -> LogEntry {
+        log(
+            swap_contract,
+            vec![
+                SwapExecuted::event_signature_hash().0,
+                topic(RECIPIENT),
+                topic(token_in),
+                topic(token_out),
+            ],
+            vec![],
+        )
+    }
+
+    // Logs of a USDC -> WETH swap through a Uniswap V3 pool on Base, where the swap contract
+    // unwraps the received WETH and sends ETH to RECIPIENT. They are not captured from a real
+    // transaction but built from the ABI encoding of the events:
+    // 1. Transfer of 5 USDC from the swap contract to the pool,
+    // 2. Transfer of 0.0013 WETH from the pool to the swap contract,
+    // 3. Withdrawal of 0.0013 WETH by the swap contract,
+    // 4. SwapExecuted event of the swap contract.
+    fn usdc_to_eth_logs() -> Vec<LogEntry> {
+        vec![
+            transfer(USDC, SWAP_CONTRACT, POOL, AMOUNT_IN),
+            transfer(WETH, POOL, SWAP_CONTRACT, REALIZED_AMOUNT_OUT),
+            withdrawal(WETH, SWAP_CONTRACT, REALIZED_AMOUNT_OUT),
+            swap_executed(SWAP_CONTRACT, USDC, WETH),
+        ]
+    }
+
+    // Same swap, where the pool sends the WETH directly to RECIPIENT.
+    fn usdc_to_weth_logs() -> Vec<LogEntry> {
+        vec![
+            transfer(USDC, SWAP_CONTRACT, POOL, AMOUNT_IN),
+            transfer(WETH, POOL, RECIPIENT, REALIZED_AMOUNT_OUT),
+            swap_executed(SWAP_CONTRACT, USDC, WETH),
+        ]
+    }
+
+    fn wrapped_native_token() -> Address {
+        address(WETH)
+    }
+
+    fn recipient() -> Address {
+        address(RECIPIENT)
+    }
+
+    fn swap_request(commands: Vec<Command>) -> ExecuteSwapRequest {
+        ExecuteSwapRequest {
+            max_transaction_fee: Wei::new(1_000_000_000_000_000),
+            erc20_token_in: address(USDC),
+            erc20_amount_in: Erc20Value::new(AMOUNT_IN),
+            min_amount_out: Erc20Value::new(1_290_000_000_000_000),
+            recipient: recipient(),
+            deadline: Erc20Value::new(1_741_351_818),
+            commands,
+            commands_data: vec![],
+            swap_contract: address(SWAP_CONTRACT),
+            gas_estimate: GasAmount::new(500_000),
+            native_ledger_burn_index: LedgerBurnIndex::new(10_000_000_000_000_000_000),
+            erc20_ledger_id: Principal::from_text("xevnm-gaaaa-aaaar-qafnq-cai").unwrap(),
+            erc20_ledger_burn_index: LedgerBurnIndex::new(7),
+            from: Principal::from_text(
+                "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae",
+            )
+            .unwrap(),
+            from_subaccount: None,
+            created_at: 1_741_348_218_000_000_000,
+            l1_fee: None,
+            withdrawal_fee: None,
+            swap_tx_id: "8453-1-1741348218000000000".to_string(),
+            is_refund: false,
+        }
+    }
+
+    #[test]
+    fn should_match_withdrawal_event_signature() {
+        assert_eq!(
+            Withdrawal::event_signature_hash().to_string(),
+            "0x7fcf532c15f0a6db0bd6d0e038bea71d30d808c7d98cb3bf7268a95bf5081b65"
+        );
+    }
+
+    #[test]
+    fn should_parse_unwrapped_amount_of_swap_to_native_token() {
+        assert_eq!(
+            realized_amount_out(
+                &swap_request(vec![Command::V3Single, Command::UnwrapEth]),
+                &wrapped_native_token(),
+                &usdc_to_eth_logs()
+            ),
+            Some(Erc20Value::new(REALIZED_AMOUNT_OUT))
+        );
+    }
+
+    #[test]
+    fn should_parse_transferred_amount_of_swap_to_erc20_token() {
+        assert_eq!(
+            realized_amount_out(
+                &swap_request(vec![Command::V3Single]),
+                &wrapped_native_token(),
+                &usdc_to_weth_logs()
+            ),
+            Some(Erc20Value::new(REALIZED_AMOUNT_OUT))
+        );
+    }
+
+    #[test]
+    fn should_parse_transferred_amount_of_refund() {
+        let logs = vec![transfer(USDC, SWAP_CONTRACT, RECIPIENT, AMOUNT_IN)];
+
+        assert_eq!(
+            realized_amount_out(&swap_request(vec![]), &wrapped_native_token(), &logs),
+            Some(Erc20Value::new(AMOUNT_IN))
+        );
+    }
+
+    #[test]
+    fn should_sum_transfers_to_recipient() {
+        let mut logs = usdc_to_weth_logs();
+        logs.push(logs[1].clone());
+
+        assert_eq!(
+            realized_amount_out(
+                &swap_request(vec![Command::V3Multi]),
+                &wrapped_native_token(),
+                &logs
+            ),
+            Some(Erc20Value::new(2 * REALIZED_AMOUNT_OUT))
+        );
+    }
+
+    #[test]
+    fn should_ignore_withdrawal_events_of_other_contracts() {
+        let mut logs = usdc_to_eth_logs();
+        logs.push(withdrawal(POOL, SWAP_CONTRACT, REALIZED_AMOUNT_OUT));
+
+        assert_eq!(
+            realized_amount_out(
+                &swap_request(vec![Command::V3Single, Command::UnwrapEth]),
+                &wrapped_native_token(),
+                &logs
+            ),
+            Some(Erc20Value::new(REALIZED_AMOUNT_OUT))
+        );
+        assert_eq!(
+            realized_amount_out(
+                &swap_request(vec![Command::V3Single, Command::UnwrapEth]),
+                &address(USDC),
+                &usdc_to_eth_logs()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn should_ignore_transfers_of_other_tokens() {
+        let mut logs = usdc_to_weth_logs();
+        logs.push(transfer(USDC, SWAP_CONTRACT, RECIPIENT, AMOUNT_IN));
+        logs.push(transfer(POOL, POOL, RECIPIENT, REALIZED_AMOUNT_OUT));
+
+        assert_eq!(
+            realized_amount_out(
+                &swap_request(vec![Command::V3Single]),
+                &wrapped_native_token(),
+                &logs
+            ),
+            Some(Erc20Value::new(REALIZED_AMOUNT_OUT))
+        );
+    }
+
+    #[test]
+    fn should_only_trust_swap_executed_event_of_swap_contract() {
+        let mut logs = usdc_to_weth_logs();
+        logs[2] = swap_executed(POOL, USDC, WETH);
+
+        assert_eq!(
+            realized_amount_out(
+                &swap_request(vec![Command::V3Single]),
+                &wrapped_native_token(),
+                &logs
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn should_not_find_amount_out_without_matching_logs() {
+        // ETH is sent to the recipient, there is no transfer of ERC-20 tokens to it
+        assert_eq!(
+            realized_amount_out(
+                &swap_request(vec![Command::V3Single]),
+                &wrapped_native_token(),
+                &usdc_to_eth_logs()
+            ),
+            None
+        );
+        assert_eq!(
+            realized_amount_out(
+                &swap_request(vec![Command::V3Single, Command::UnwrapEth]),
+                &wrapped_native_token(),
+                &usdc_to_weth_logs()
+            ),
+            None
+        );
+        assert_eq!(
+            realized_amount_out(
+                &swap_request(vec![Command::V3Single]),
+                &wrapped_native_token(),
+                &[]
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn should_ignore_removed_logs() {
+        let logs: Vec<LogEntry> = usdc_to_eth_logs()
+            .into_iter()
+            .map(|mut log| {
+                log.removed = true;
+                log
+            })
+            .collect();
+
+        assert_eq!(
+            realized_amount_out(
+                &swap_request(vec![Command::V3Single, Command::UnwrapEth]),
+                &wrapped_native_token(),
+                &logs
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn should_know_wrapped_native_token_of_base() {
+        assert_eq!(
+            crate::evm_config::EvmNetwork::Base.wrapped_native_token(),
+            wrapped_native_token()
+        );
+    }
+}
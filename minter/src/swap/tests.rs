@@ -0,0 +1,780 @@
+use crate::candid_types::dex_orders::{
+    DexOrderArgs, DexOrderError, DexOrderQuote, RefundableDexOrderArgs, SwapFeeQuote,
+    ValidatedDexOrderArgs,
+};
+use crate::candid_types::{ActivateSwapError, ActivateSwapReqest};
+use crate::evm_config::EvmNetwork;
+use crate::numeric::{Erc20Value, GasAmount, LedgerBurnIndex, Wei};
+use crate::state::TwinUSDCInfo;
+use crate::swap::{
+    plan_dex_order, swap_fee_quote, validate_swap_activation, DexOrderPlan, SwapActivation,
+    MAX_SWAP_FEE_QUOTE_ESTIMATE_AGE_NS,
+};
+use crate::tx::gas_fees::l1_fee_estimate;
+use candid::{Nat, Principal};
+use evm_rpc_client::eth_types::Address;
+use std::str::FromStr;
+
+const GAS_USD_PRICE: f64 = 2_000.0;
+const ERC20_TX_FEE: Wei = Wei::new(100_000_000_000_000);
+const SIGNING_FEE: Erc20Value = Erc20Value::new(10_000);
+const GAS_LIMIT: GasAmount = GasAmount::new(500_000);
+
+fn twin_usdc_info() -> TwinUSDCInfo {
+    TwinUSDCInfo {
+        address: Address::from_str("0x833589fcd6edb6e08f4c7c32d4f71b54bda02913").unwrap(),
+        ledger_id: Principal::from_text("mxzaz-hqaaa-aaaar-qaada-cai").unwrap(),
+        decimals: 6,
+    }
+}
+
+fn dex_order_args() -> DexOrderArgs {
+    DexOrderArgs {
+        tx_id: "0xABCDEF".to_string(),
+        amount_in: Nat::from(10_000_000_u64),
+        min_amount_out: Nat::from(9_000_000_u64),
+        commands: vec![],
+        commands_data: vec!["0x01".to_string()],
+        max_gas_fee_usd: None,
+        signing_fee: None,
+        gas_limit: Nat::from(500_000_u64),
+        deadline: Nat::from(1_900_000_000_u64),
+        recipient: "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".to_string(),
+        erc20_ledger_burn_index: Nat::from(42_u64),
+        is_refund: false,
+    }
+}
+
+fn plan(args: &DexOrderArgs, evm_network: EvmNetwork) -> Result<DexOrderPlan, DexOrderError> {
+    plan_dex_order(
+        &ValidatedDexOrderArgs::try_from(args.clone()).unwrap(),
+        &twin_usdc_info(),
+        GAS_USD_PRICE,
+        SIGNING_FEE,
+        GAS_LIMIT,
+        ERC20_TX_FEE,
+        l1_fee_estimate(evm_network, None),
+    )
+}
+
+fn assert_quote_matches_swap_request(args: &DexOrderArgs, evm_network: EvmNetwork) {
+    let plan = plan(args, evm_network).unwrap();
+    let quote = plan.quote(Wei::MAX);
+    let released_twin_usdc = plan.all_twin_usdc_fees;
+
+    let swap_request = plan.into_swap_request(
+        &ValidatedDexOrderArgs::try_from(args.clone()).unwrap(),
+        &twin_usdc_info(),
+        Address::from_str("0x1c7d4b196cb0c7b01d743fbc6116a902379c7238").unwrap(),
+        LedgerBurnIndex::new(7),
+        Principal::anonymous(),
+        0,
+    );
+
+    assert_eq!(
+        quote.max_transaction_fee,
+        Nat::from(swap_request.max_transaction_fee)
+    );
+    assert_eq!(
+        quote.amount_in_minus_fees,
+        Nat::from(swap_request.erc20_amount_in)
+    );
+    assert_eq!(
+        quote.max_gas_fee_twin_usdc,
+        Nat::from(released_twin_usdc.checked_sub(SIGNING_FEE).unwrap())
+    );
+    assert_eq!(
+        Erc20Value::try_from(args.amount_in.clone()).unwrap(),
+        swap_request
+            .erc20_amount_in
+            .checked_add(released_twin_usdc)
+            .unwrap()
+    );
+    assert!(quote.gas_tank_has_capacity);
+}
+
+#[test]
+fn should_quote_amounts_consumed_by_swap_request() {
+    assert_quote_matches_swap_request(&dex_order_args(), EvmNetwork::Ethereum);
+    assert_quote_matches_swap_request(
+        &DexOrderArgs {
+            max_gas_fee_usd: Some("1".to_string()),
+            signing_fee: Some("0.02".to_string()),
+            ..dex_order_args()
+        },
+        EvmNetwork::Base,
+    );
+}
+
+#[test]
+fn should_compute_fees_from_estimated_transaction_fee() {
+    let quote = plan(&dex_order_args(), EvmNetwork::Ethereum)
+        .unwrap()
+        .quote(ERC20_TX_FEE);
+
+    // 0.0001 native token at 2000 USD is 0.2 USD, i.e. 200_000 twin USDC units.
+    assert_eq!(
+        quote,
+        DexOrderQuote {
+            max_transaction_fee: Nat::from(ERC20_TX_FEE),
+            max_gas_fee_twin_usdc: Nat::from(200_000_u64),
+            amount_in_minus_fees: Nat::from(10_000_000_u64 - 200_000 - 10_000),
+            gas_tank_has_capacity: true,
+        }
+    );
+}
+
+#[test]
+fn should_report_gas_tank_without_capacity() {
+    let plan = plan(&dex_order_args(), EvmNetwork::Ethereum).unwrap();
+
+    assert!(
+        !plan
+            .quote(ERC20_TX_FEE.checked_sub(Wei::ONE).unwrap())
+            .gas_tank_has_capacity
+    );
+    assert!(plan.quote(ERC20_TX_FEE).gas_tank_has_capacity);
+}
+
+#[test]
+fn should_quote_swap_fees_consumed_by_swap_request() {
+    let args = dex_order_args();
+    let quote = swap_fee_quote(
+        &twin_usdc_info(),
+        (1_700_000_000_000_000_000, GAS_USD_PRICE),
+        SIGNING_FEE,
+        (1_700_000_000_000_000_001, ERC20_TX_FEE),
+        ERC20_TX_FEE,
+        1_700_000_000_000_000_002,
+    )
+    .unwrap();
+    let plan = plan(&args, EvmNetwork::Ethereum).unwrap();
+    let released_twin_usdc = plan.all_twin_usdc_fees;
+    let max_gas_fee_twin_usdc = plan.max_gas_fee_twin_usdc;
+
+    let swap_request = plan.into_swap_request(
+        &ValidatedDexOrderArgs::try_from(args.clone()).unwrap(),
+        &twin_usdc_info(),
+        Address::from_str("0x1c7d4b196cb0c7b01d743fbc6116a902379c7238").unwrap(),
+        LedgerBurnIndex::new(7),
+        Principal::anonymous(),
+        0,
+    );
+
+    assert_eq!(
+        quote,
+        SwapFeeQuote {
+            max_transaction_fee: Nat::from(swap_request.max_transaction_fee),
+            max_gas_fee_twin_usdc: Nat::from(max_gas_fee_twin_usdc),
+            signing_fee_twin_usdc: Nat::from(SIGNING_FEE),
+            total_fee_twin_usdc: Nat::from(released_twin_usdc),
+            native_token_usd_price_timestamp: 1_700_000_000_000_000_000,
+            gas_fee_estimate_timestamp: 1_700_000_000_000_000_001,
+            gas_tank_has_capacity: true,
+        }
+    );
+    assert_eq!(
+        Erc20Value::try_from(args.amount_in).unwrap(),
+        swap_request
+            .erc20_amount_in
+            .checked_add(released_twin_usdc)
+            .unwrap()
+    );
+}
+
+#[test]
+fn should_not_quote_swap_fees_from_stale_estimate() {
+    let quote = |now_ns| {
+        swap_fee_quote(
+            &twin_usdc_info(),
+            (0, GAS_USD_PRICE),
+            SIGNING_FEE,
+            (1_000, ERC20_TX_FEE),
+            ERC20_TX_FEE,
+            now_ns,
+        )
+    };
+
+    assert!(quote(1_000 + MAX_SWAP_FEE_QUOTE_ESTIMATE_AGE_NS).is_ok());
+    assert_eq!(
+        quote(1_001 + MAX_SWAP_FEE_QUOTE_ESTIMATE_AGE_NS),
+        Err(DexOrderError::TemporarilyUnavailable(
+            "Gas fee estimate is stale".to_string()
+        ))
+    );
+}
+
+#[test]
+fn should_reject_order_below_quoted_total_fee() {
+    let quote = swap_fee_quote(
+        &twin_usdc_info(),
+        (0, GAS_USD_PRICE),
+        SIGNING_FEE,
+        (0, ERC20_TX_FEE),
+        ERC20_TX_FEE.checked_sub(Wei::ONE).unwrap(),
+        0,
+    )
+    .unwrap();
+    assert!(!quote.gas_tank_has_capacity);
+
+    let total_fee = quote.total_fee_twin_usdc;
+    assert_eq!(
+        plan(
+            &DexOrderArgs {
+                amount_in: total_fee.clone() - Nat::from(1_u8),
+                min_amount_out: Nat::from(0_u8),
+                ..dex_order_args()
+            },
+            EvmNetwork::Ethereum
+        ),
+        Err(DexOrderError::UsdcAmountInTooLow)
+    );
+    assert_eq!(
+        plan(
+            &DexOrderArgs {
+                amount_in: total_fee,
+                min_amount_out: Nat::from(0_u8),
+                ..dex_order_args()
+            },
+            EvmNetwork::Ethereum
+        )
+        .unwrap()
+        .amount_in_minus_fees,
+        Erc20Value::ZERO
+    );
+}
+
+#[test]
+fn should_reject_max_fee_below_required_fee() {
+    let args = DexOrderArgs {
+        max_gas_fee_usd: Some("0.01".to_string()),
+        ..dex_order_args()
+    };
+    assert_eq!(
+        plan(&args, EvmNetwork::Ethereum),
+        Err(DexOrderError::MaxUsdFeeTooLow)
+    );
+
+    // the L1 fee must also be covered on Base
+    assert_eq!(
+        plan(&dex_order_args(), EvmNetwork::Base),
+        Err(DexOrderError::MaxUsdFeeTooLow)
+    );
+}
+
+#[test]
+fn should_reject_amount_in_below_fees() {
+    let args = DexOrderArgs {
+        amount_in: Nat::from(200_000_u64),
+        ..dex_order_args()
+    };
+    assert_eq!(
+        plan(&args, EvmNetwork::Ethereum),
+        Err(DexOrderError::UsdcAmountInTooLow)
+    );
+}
+
+fn activate_swap_request(twin_usdc_ledger_id: Principal) -> ActivateSwapReqest {
+    ActivateSwapReqest {
+        twin_usdc_ledger_id,
+        swap_contract_address: "0xa72ab997CCd4C55a7aDc049df8057D577f5322a8".to_string(),
+        twin_usdc_decimals: 6,
+        dex_canister_id: Principal::from_text("nbepk-iyaaa-aaaad-qhlma-cai").unwrap(),
+        canister_signing_fee_twin_usdc_value: Nat::from(50_000_u32),
+    }
+}
+
+fn state_with_twin_usdc() -> (crate::state::State, crate::erc20::ERC20Token) {
+    let mut state = crate::state::tests::initial_state();
+    state.evm_network = EvmNetwork::Sepolia;
+    let token = crate::state::tests::erc20::record_add_erc20_token::ic_usdc();
+    state.record_add_erc20_token(token.clone());
+    (state, token)
+}
+
+#[test]
+fn should_validate_swap_activation() {
+    let (state, token) = state_with_twin_usdc();
+
+    assert_eq!(
+        validate_swap_activation(&state, &activate_swap_request(token.erc20_ledger_id)),
+        Ok(SwapActivation {
+            swap_contract_address: Address::from_str("0xa72ab997CCd4C55a7aDc049df8057D577f5322a8")
+                .unwrap(),
+            twin_usdc_token: token,
+            canister_signing_fee_twin_usdc_value: Erc20Value::new(50_000),
+        })
+    );
+}
+
+#[test]
+fn should_reject_swap_activation_for_unknown_token() {
+    let (state, _token) = state_with_twin_usdc();
+    let unknown_ledger_id = Principal::from_text("2chl6-4hpzw-vqaaa-aaaaa-c").unwrap();
+
+    assert_eq!(
+        validate_swap_activation(&state, &activate_swap_request(unknown_ledger_id)),
+        Err(ActivateSwapError::TokenNotFound {
+            twin_usdc_ledger_id: unknown_ledger_id
+        })
+    );
+}
+
+#[test]
+fn should_reject_swap_activation_with_invalid_contract_address() {
+    let (state, token) = state_with_twin_usdc();
+    let request = ActivateSwapReqest {
+        swap_contract_address: "0xinvalid".to_string(),
+        ..activate_swap_request(token.erc20_ledger_id)
+    };
+
+    assert!(matches!(
+        validate_swap_activation(&state, &request),
+        Err(ActivateSwapError::InvalidSwapContractAddress(_))
+    ));
+}
+
+#[test]
+fn should_reject_swap_activation_with_invalid_fee_value() {
+    let (state, token) = state_with_twin_usdc();
+    let request = ActivateSwapReqest {
+        canister_signing_fee_twin_usdc_value: Nat(num_bigint::BigUint::from_bytes_be(
+            &ethnum::u256::MAX.to_be_bytes(),
+        ) + 1_u8),
+        ..activate_swap_request(token.erc20_ledger_id)
+    };
+
+    assert!(matches!(
+        validate_swap_activation(&state, &request),
+        Err(ActivateSwapError::InvalidFeeValue(_))
+    ));
+}
+
+mod validated_dex_order_args {
+    use super::*;
+    use crate::candid_types::dex_orders::{
+        deadline_secs, MAX_COMMANDS_DATA_TOTAL_BYTES, MAX_COMMAND_DATA_BYTES,
+        MAX_DEADLINE_HORIZON_SECS, MAX_DEADLINE_SECS, MAX_DEX_ORDER_COMMANDS, MAX_FEE_USD_LENGTH,
+        MAX_TX_ID_LENGTH,
+    };
+    use assert_matches::assert_matches;
+    use proptest::collection::vec;
+    use proptest::prelude::{any, prop_oneof, Just, Strategy};
+    use proptest::{prop_assert, proptest};
+
+    const NOW_SECS: u64 = 1_899_990_000;
+
+    fn validation_error(args: DexOrderArgs) -> DexOrderError {
+        ValidatedDexOrderArgs::try_from(args).expect_err("expected invalid arguments")
+    }
+
+    fn hex_of_len(bytes: usize) -> String {
+        format!("0x{}", "ab".repeat(bytes))
+    }
+
+    #[test]
+    fn should_validate_all_fields() {
+        let args = dex_order_args();
+
+        let validated = ValidatedDexOrderArgs::try_from(args.clone()).unwrap();
+
+        assert_eq!(validated.tx_id(), "0xabcdef");
+        assert_eq!(validated.amount_in, Erc20Value::new(10_000_000));
+        assert_eq!(validated.min_amount_out, Erc20Value::new(9_000_000));
+        assert_eq!(validated.gas_limit, GAS_LIMIT);
+        assert_eq!(validated.deadline, Erc20Value::new(1_900_000_000));
+        assert_eq!(
+            validated.recipient,
+            Address::from_str("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").unwrap()
+        );
+        assert_eq!(validated.erc20_ledger_burn_index, LedgerBurnIndex::new(42));
+        assert_eq!(validated.args, args.clone());
+        assert_eq!(args.validate(NOW_SECS), Ok(validated));
+    }
+
+    #[test]
+    fn should_reject_invalid_recipient() {
+        for recipient in [
+            "",
+            "0x",
+            "5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAe",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAedd",
+            "0xZaAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        ] {
+            assert_matches!(
+                validation_error(DexOrderArgs {
+                    recipient: recipient.to_string(),
+                    ..dex_order_args()
+                }),
+                DexOrderError::InvalidRecipient(_)
+            );
+        }
+    }
+
+    #[test]
+    fn should_reject_values_not_fitting_in_u256() {
+        let too_large = Nat::from(Erc20Value::MAX) + 1_u8;
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                amount_in: too_large.clone(),
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidAmount
+        );
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                min_amount_out: too_large.clone(),
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidMinAmountIn
+        );
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                gas_limit: too_large.clone(),
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidGasLimit(_)
+        );
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                deadline: too_large,
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidDeadline(_)
+        );
+    }
+
+    #[test]
+    fn should_reject_burn_index_not_fitting_in_u64() {
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                erc20_ledger_burn_index: Nat::from(u64::MAX) + 1_u8,
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidArgs(reason) if reason.starts_with("invalid erc20_ledger_burn_index")
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_commands() {
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                commands: vec![u8::MAX],
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidCommand(_)
+        );
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                commands_data: vec!["0xZZ".to_string()],
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidCommandData(_)
+        );
+    }
+
+    #[test]
+    fn should_accept_fields_at_their_bounds() {
+        let args = DexOrderArgs {
+            tx_id: "a".repeat(MAX_TX_ID_LENGTH),
+            commands: vec![0; MAX_DEX_ORDER_COMMANDS],
+            commands_data: vec![
+                hex_of_len(MAX_COMMAND_DATA_BYTES),
+                hex_of_len(MAX_COMMANDS_DATA_TOTAL_BYTES - MAX_COMMAND_DATA_BYTES),
+            ],
+            max_gas_fee_usd: Some("1".repeat(MAX_FEE_USD_LENGTH)),
+            ..dex_order_args()
+        };
+
+        assert!(ValidatedDexOrderArgs::try_from(args).is_ok());
+    }
+
+    #[test]
+    fn should_reject_fields_exceeding_their_bounds() {
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                tx_id: "a".repeat(MAX_TX_ID_LENGTH + 1),
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidArgs(reason) if reason.starts_with("invalid tx_id")
+        );
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                commands: vec![0; MAX_DEX_ORDER_COMMANDS + 1],
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidCommand(_)
+        );
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                commands_data: vec!["0x".to_string(); MAX_DEX_ORDER_COMMANDS + 1],
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidCommandData(_)
+        );
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                commands_data: vec![hex_of_len(MAX_COMMAND_DATA_BYTES + 1)],
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidCommandData(_)
+        );
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                commands_data: vec![
+                    hex_of_len(MAX_COMMAND_DATA_BYTES),
+                    hex_of_len(MAX_COMMANDS_DATA_TOTAL_BYTES - MAX_COMMAND_DATA_BYTES + 1),
+                ],
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidCommandData(_)
+        );
+        assert_matches!(
+            validation_error(DexOrderArgs {
+                signing_fee: Some("1".repeat(MAX_FEE_USD_LENGTH + 1)),
+                ..dex_order_args()
+            }),
+            DexOrderError::InvalidMaxUsdFeeAmount(_)
+        );
+    }
+
+    #[test]
+    fn should_refund_orders_failing_checks_after_the_burn() {
+        let too_large = Nat::from(Erc20Value::MAX) + 1_u8;
+        for args in [
+            DexOrderArgs {
+                commands: vec![u8::MAX],
+                ..dex_order_args()
+            },
+            DexOrderArgs {
+                commands_data: vec!["0xZZ".to_string()],
+                ..dex_order_args()
+            },
+            DexOrderArgs {
+                commands_data: vec![hex_of_len(MAX_COMMAND_DATA_BYTES + 1)],
+                ..dex_order_args()
+            },
+            DexOrderArgs {
+                gas_limit: too_large.clone(),
+                ..dex_order_args()
+            },
+            DexOrderArgs {
+                deadline: too_large,
+                ..dex_order_args()
+            },
+            DexOrderArgs {
+                deadline: Nat::from(NOW_SECS),
+                ..dex_order_args()
+            },
+        ] {
+            assert!(args.clone().validate(NOW_SECS).is_err());
+
+            let refundable =
+                RefundableDexOrderArgs::try_from(args.clone().without_swap_details()).unwrap();
+
+            assert_eq!(refundable.tx_id(), "0xabcdef");
+            assert_eq!(refundable.amount_in, Erc20Value::new(10_000_000));
+            assert_eq!(
+                refundable.recipient,
+                Address::from_str("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").unwrap()
+            );
+            assert_eq!(refundable.erc20_ledger_burn_index, LedgerBurnIndex::new(42));
+            assert_eq!(RefundableDexOrderArgs::try_from(args).map(|_| ()), Ok(()));
+        }
+    }
+
+    #[test]
+    fn should_not_refund_orders_without_refund_fields() {
+        for args in [
+            DexOrderArgs {
+                tx_id: "a".repeat(MAX_TX_ID_LENGTH + 1),
+                ..dex_order_args()
+            },
+            DexOrderArgs {
+                amount_in: Nat::from(Erc20Value::MAX) + 1_u8,
+                ..dex_order_args()
+            },
+            DexOrderArgs {
+                recipient: "0x".to_string(),
+                ..dex_order_args()
+            },
+            DexOrderArgs {
+                erc20_ledger_burn_index: Nat::from(u64::MAX) + 1_u8,
+                ..dex_order_args()
+            },
+        ] {
+            assert_eq!(
+                RefundableDexOrderArgs::try_from(args.clone()).map(|_| ()),
+                ValidatedDexOrderArgs::try_from(args).map(|_| ())
+            );
+        }
+    }
+
+    #[test]
+    fn should_check_deadline_horizon() {
+        let with_deadline = |deadline: u64| DexOrderArgs {
+            deadline: Nat::from(deadline),
+            ..dex_order_args()
+        };
+
+        assert!(with_deadline(NOW_SECS + 1).validate(NOW_SECS).is_ok());
+        assert!(with_deadline(NOW_SECS + MAX_DEADLINE_HORIZON_SECS)
+            .validate(NOW_SECS)
+            .is_ok());
+        assert_eq!(
+            with_deadline(NOW_SECS - 60).validate(NOW_SECS),
+            Err(DexOrderError::DeadlineExpired {
+                deadline: NOW_SECS - 60,
+                now_secs: NOW_SECS
+            })
+        );
+        assert_matches!(
+            with_deadline(NOW_SECS).validate(NOW_SECS),
+            Err(DexOrderError::DeadlineExpired { .. })
+        );
+        assert_eq!(
+            with_deadline(NOW_SECS + MAX_DEADLINE_HORIZON_SECS + 1).validate(NOW_SECS),
+            Err(DexOrderError::DeadlineTooFar {
+                deadline: NOW_SECS + MAX_DEADLINE_HORIZON_SECS + 1,
+                max_deadline: NOW_SECS + MAX_DEADLINE_HORIZON_SECS
+            })
+        );
+        // Quarantined orders are refunded regardless of their deadline.
+        assert!(ValidatedDexOrderArgs::try_from(with_deadline(NOW_SECS)).is_ok());
+    }
+
+    #[test]
+    fn should_reject_deadlines_not_in_seconds() {
+        assert_eq!(
+            deadline_secs(&Nat::from(1_900_000_000_u64)),
+            Ok(1_900_000_000)
+        );
+        assert_eq!(
+            deadline_secs(&Nat::from(MAX_DEADLINE_SECS)),
+            Ok(MAX_DEADLINE_SECS)
+        );
+        for deadline in [
+            // milliseconds
+            Nat::from(1_900_000_000_000_u64),
+            // nanoseconds
+            Nat::from(1_900_000_000_000_000_000_u64),
+            Nat::from(u64::MAX) + 1_u8,
+        ] {
+            assert_eq!(
+                deadline_secs(&deadline),
+                Err(DexOrderError::DeadlineNotInSeconds {
+                    deadline: deadline.clone()
+                })
+            );
+        }
+
+        let nanos_deadline = DexOrderArgs {
+            deadline: Nat::from((NOW_SECS + 60) * 1_000_000_000),
+            ..dex_order_args()
+        };
+        assert_matches!(
+            nanos_deadline.validate(NOW_SECS),
+            Err(DexOrderError::DeadlineNotInSeconds { .. })
+        );
+    }
+
+    fn arb_nat() -> impl Strategy<Value = Nat> {
+        vec(any::<u8>(), 0..40)
+            .prop_map(|bytes| Nat::from(num_bigint::BigUint::from_bytes_be(&bytes)))
+    }
+
+    fn arb_commands_data_item() -> impl Strategy<Value = String> {
+        prop_oneof![
+            (0..2 * MAX_COMMAND_DATA_BYTES).prop_map(hex_of_len),
+            ".{0,64}",
+            Just("0x".to_string()),
+        ]
+    }
+
+    fn arb_dex_order_args() -> impl Strategy<Value = DexOrderArgs> {
+        (
+            (
+                ".{0,200}",
+                arb_nat(),
+                arb_nat(),
+                vec(any::<u8>(), 0..2 * MAX_DEX_ORDER_COMMANDS),
+                vec(arb_commands_data_item(), 0..2 * MAX_DEX_ORDER_COMMANDS),
+            ),
+            (
+                proptest::option::of(".{0,100}"),
+                proptest::option::of(".{0,100}"),
+                arb_nat(),
+                arb_nat(),
+                ".{0,50}",
+                arb_nat(),
+                any::<bool>(),
+            ),
+        )
+            .prop_map(
+                |(
+                    (tx_id, amount_in, min_amount_out, commands, commands_data),
+                    (
+                        max_gas_fee_usd,
+                        signing_fee,
+                        gas_limit,
+                        deadline,
+                        recipient,
+                        erc20_ledger_burn_index,
+                        is_refund,
+                    ),
+                )| DexOrderArgs {
+                    tx_id,
+                    amount_in,
+                    min_amount_out,
+                    commands,
+                    commands_data,
+                    max_gas_fee_usd,
+                    signing_fee,
+                    gas_limit,
+                    deadline,
+                    recipient,
+                    erc20_ledger_burn_index,
+                    is_refund,
+                },
+            )
+    }
+
+    fn exceeds_bounds(args: &DexOrderArgs) -> bool {
+        let data_bytes: Vec<usize> = args
+            .commands_data
+            .iter()
+            .map(|item| item.len().saturating_sub(2) / 2)
+            .collect();
+        args.tx_id.len() > MAX_TX_ID_LENGTH
+            || args.commands.len() > MAX_DEX_ORDER_COMMANDS
+            || args.commands_data.len() > MAX_DEX_ORDER_COMMANDS
+            || data_bytes
+                .iter()
+                .any(|bytes| *bytes > MAX_COMMAND_DATA_BYTES)
+            || data_bytes.iter().sum::<usize>() > MAX_COMMANDS_DATA_TOTAL_BYTES
+            || [&args.max_gas_fee_usd, &args.signing_fee]
+                .into_iter()
+                .flatten()
+                .any(|fee| fee.len() > MAX_FEE_USD_LENGTH)
+    }
+
+    proptest! {
+        #[test]
+        fn should_never_panic_and_reject_oversized_args(
+            args in arb_dex_order_args(),
+            now_secs in any::<u64>(),
+        ) {
+            let oversized = exceeds_bounds(&args);
+            let result = args.clone().validate(now_secs);
+            prop_assert!(!oversized || result.is_err());
+            if let Ok(validated) = result {
+                prop_assert!(validated.commands.len() <= MAX_DEX_ORDER_COMMANDS);
+                prop_assert!(
+                    validated.commands_data.iter().map(|data| data.0.len()).sum::<usize>()
+                        <= MAX_COMMANDS_DATA_TOTAL_BYTES
+                );
+            }
+        }
+    }
+}
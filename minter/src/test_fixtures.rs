@@ -1,3 +1,5 @@
+pub mod replay;
+
 pub fn expect_panic_with_message<F: FnOnce() -> R, R: std::fmt::Debug>(
     f: F,
     expected_message: &str,
@@ -110,12 +112,16 @@ pub mod arb {
     }
 
     pub fn arb_block() -> impl Strategy<Value = Block> {
-        (arb_checked_amount_of(), arb_checked_amount_of()).prop_map(|(number, base_fee_per_gas)| {
-            Block {
+        (
+            arb_checked_amount_of(),
+            arb_checked_amount_of(),
+            arb_checked_amount_of(),
+        )
+            .prop_map(|(number, base_fee_per_gas, timestamp)| Block {
                 number,
                 base_fee_per_gas,
-            }
-        })
+                timestamp,
+            })
     }
 
     pub fn arb_log_entry() -> impl Strategy<Value = LogEntry> {
@@ -215,6 +221,8 @@ pub mod arb {
             arb_evm_http_outcall_error().prop_map(EvmRpcError::HttpOutcallError),
             arb_evm_json_rpc_error().prop_map(EvmRpcError::JsonRpcError),
             arb_evm_validation_error().prop_map(EvmRpcError::ValidationError),
+            arb_evm_json_rpc_error().prop_map(EvmRpcError::ResultSetTooLarge),
+            any::<u64>().prop_map(|retry_after_secs| EvmRpcError::RateLimited { retry_after_secs }),
         ]
     }
 
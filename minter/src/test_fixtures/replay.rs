@@ -0,0 +1,1010 @@
+//! Harness asserting that replaying the event log is deterministic, which upgrades and
+//! event archiving rely on.
+
+use crate::candid_types::dex_orders::DexOrderArgs;
+use crate::candid_types::{AddOrRemove, CandidBlockTag, CandidConfirmationPolicy, ListKind};
+use crate::contract_logs::swap::swap_logs::ReceivedSwapEvent;
+use crate::contract_logs::types::{
+    ReceivedBurnEvent, ReceivedErc20Event, ReceivedNativeEvent, ReceivedWrappedIcrcDeployedEvent,
+};
+use crate::contract_logs::{EventSource, ReceivedContractEvent};
+use crate::erc20::ERC20Token;
+use crate::evm_config::{
+    EvmNetwork, GasLimitKind, ERC20_APPROVAL_TRANSACTION_GAS_LIMIT,
+    ERC20_MINT_TRANSACTION_GAS_LIMIT, ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+    NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+};
+use crate::fee_collector::PendingFeeWithdrawal;
+use crate::lifecycle::{GasLimitArg, InitArg, UpgradeArg};
+use crate::numeric::{
+    BlockNumber, Erc20TokenAmount, Erc20Value, GasAmount, IcrcValue, LedgerBurnIndex,
+    LedgerLockIndex, LedgerMintIndex, LedgerReleaseIndex, LogIndex, TransactionNonce, Wei,
+    WeiPerGas,
+};
+use crate::rpc_client::providers::Provider;
+use crate::rpc_declarations::{Data, FixedSizeData, Hash, TransactionReceipt, TransactionStatus};
+use crate::state::audit::replay_events_from;
+use crate::state::event::{Event, EventType};
+use crate::state::feature_flags::FeatureFlag;
+use crate::state::snapshot::StateSnapshot;
+use crate::state::transactions::{
+    create_transaction, BroadcastOutcome, Erc20Approve, Erc20WithdrawalRequest, ExecuteSwapRequest,
+    NativeWithdrawalRequest, PendingReimbursementRequest, Reimbursed, ReimbursementIndex,
+    WithdrawalRequest,
+};
+use crate::state::{NativeLedgerMetadata, State};
+use crate::tx::cancellation::create_cancellation_transaction;
+use crate::tx::gas_fees::{FeeCap, GasFeeEstimate, DEFAULT_BASE_FEE_MULTIPLIER_PERCENT};
+use crate::tx::{Eip1559Signature, Eip1559TransactionRequest, SignedEip1559TransactionRequest};
+use crate::tx_id::SwapTxId;
+use candid::{Nat, Principal};
+use evm_rpc_client::eth_types::Address;
+use icrc_ledger_types::icrc1::account::Account;
+use std::collections::BTreeSet;
+
+/// Declares [`EVENT_TYPES`] and [`event_type_name`] from the same list of variants,
+/// so that the exhaustive match stops compiling when a variant is added to [`EventType`]
+/// until it is listed here, and [`event_sequence`] is then checked to cover it.
+macro_rules! event_types {
+    ($($variant:ident),* $(,)?) => {
+        /// Names of all [`EventType`] variants.
+        pub const EVENT_TYPES: &[&str] = &[$(stringify!($variant)),*];
+
+        /// Returns the name of the variant of `event_type`.
+        pub fn event_type_name(event_type: &EventType) -> &'static str {
+            match event_type {
+                $(EventType::$variant { .. } => stringify!($variant),)*
+            }
+        }
+    };
+}
+
+event_types!(
+    Init,
+    Upgrade,
+    AcceptedDeposit,
+    InvalidDeposit,
+    MintedNative,
+    SyncedToBlock,
+    AcceptedNativeWithdrawalRequest,
+    CreatedTransaction,
+    SignedTransaction,
+    ReplacedTransaction,
+    FinalizedTransaction,
+    ReimbursedNativeWithdrawal,
+    AddedErc20Token,
+    AcceptedErc20Deposit,
+    AcceptedErc20WithdrawalRequest,
+    MintedErc20,
+    ReimbursedErc20Withdrawal,
+    FailedErc20WithdrawalRequest,
+    QuarantinedDeposit,
+    QuarantinedReimbursement,
+    SkippedBlock,
+    AcceptedWrappedIcrcBurn,
+    InvalidEvent,
+    DeployedWrappedIcrcToken,
+    QuarantinedRelease,
+    ReleasedIcrcToken,
+    FailedIcrcLockRequest,
+    ReimbursedIcrcWrap,
+    AcceptedSwapActivationRequest,
+    SwapContractActivated,
+    ReceivedSwapOrder,
+    MintedToAppicDex,
+    NotifiedSwapEventOrderToAppicDex,
+    ReleasedGasFromGasTankWithUsdc,
+    AcceptedSwapRequest,
+    QuarantinedDexOrder,
+    QuarantinedSwapRequest,
+    GasTankUpdate,
+    RemovedLog,
+    DepositReorged,
+    UpgradeGraceScrape,
+    UpdatedAddressList,
+    RescrapedBlockRange,
+    ChangedConfirmationPolicy,
+    ParkedTransaction,
+    ProviderDisagreement,
+    ArchivedEvents,
+    ProviderRateLimited,
+    CancelledWithdrawalRequest,
+    EventLogCompacted,
+    UpgradedWhileNotDrained,
+    NativeLedgerMetadataMismatch,
+    NativeLedgerMetadataMatched,
+    AcceptedApprovalRevocationRequest,
+    BackfillCompleted,
+    RealizedSwapAmountOut,
+    FeatureFlagChanged,
+    FeeCollected,
+    CollectedFeesWithdrawn,
+    FeeCapExceeded,
+    BroadcastAttempted,
+    BroadcastReconciled,
+    QuarantinedWithdrawal,
+    UnaccountedFeesFound,
+    SkippedBlockRange,
+    CollectedFeesWithdrawalStarted,
+    CollectedFeesWithdrawalFailed,
+    MissingFeesFound,
+);
+
+/// Returns the names of the [`EVENT_TYPES`] that do not occur in `events`.
+pub fn missing_event_types(events: &[Event]) -> BTreeSet<&'static str> {
+    let covered: BTreeSet<_> = events
+        .iter()
+        .map(|event| event_type_name(&event.payload))
+        .collect();
+    EVENT_TYPES
+        .iter()
+        .copied()
+        .filter(|name| !covered.contains(name))
+        .collect()
+}
+
+/// Replays `events` on top of the state initialized by the first event.
+pub fn replay(events: &[Event]) -> State {
+    replay_events_from(None, events.to_vec())
+}
+
+/// Canonical serialization of `state` used to compare replayed states.
+///
+/// This is the encoding of the state snapshot, which stores every map in key order
+/// and leaves out the transient fields that are not recomputed from events.
+pub fn canonical_state(state: &State) -> Vec<u8> {
+    StateSnapshot::from(state).encode_to_vec()
+}
+
+/// Asserts that replaying `events` twice yields the same canonical state, and so does
+/// replaying any prefix of `events`, snapshotting the resulting state and replaying
+/// the remaining events on top of the decoded snapshot.
+pub fn assert_deterministic_replay(events: &[Event]) {
+    let expected = canonical_state(&replay(events));
+    assert_eq!(
+        canonical_state(&replay(events)),
+        expected,
+        "replaying the same events twice diverged"
+    );
+
+    for split in 1..=events.len() {
+        let snapshot =
+            StateSnapshot::decode_from_slice(&canonical_state(&replay(&events[..split])))
+                .expect("state snapshot should decode");
+        let state = replay_events_from(Some(snapshot.into()), events[split..].to_vec());
+        assert_eq!(
+            canonical_state(&state),
+            expected,
+            "replay diverged when continuing from a snapshot after {split} events, \
+            the last event before the snapshot is {:?}",
+            events[split - 1].payload
+        );
+    }
+}
+
+/// A representative event log covering every [`EventType`] variant with payloads
+/// that go through the same state transitions as on a live minter.
+pub fn event_sequence() -> Vec<Event> {
+    let payloads = [
+        setup_events(),
+        deposit_events(),
+        scraping_events(),
+        native_withdrawal_events(),
+        erc20_withdrawal_events(),
+        wrapped_icrc_events(),
+        swap_events(),
+        operator_events(),
+    ]
+    .concat();
+    (0_u64..)
+        .zip(payloads)
+        .map(|(i, payload)| Event {
+            timestamp: FIRST_EVENT_TIMESTAMP + i * 1_000_000_000,
+            payload,
+        })
+        .collect()
+}
+
+const FIRST_EVENT_TIMESTAMP: u64 = 1_741_348_218_000_000_000;
+const USDC_SYMBOL: &str = "ckSepoliaUSDC";
+const SWAP_TX_ID: &str = "56-1-1741348218000000000";
+const QUARANTINED_SWAP_TX_ID: &str = "56-1-1741348219000000000";
+const QUARANTINED_DEX_ORDER_TX_ID: &str = "56-1-1741348220000000000";
+
+fn user() -> Principal {
+    "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae"
+        .parse()
+        .unwrap()
+}
+
+fn usdc_ledger_id() -> Principal {
+    Principal::from_text("3sgad-taaaa-aaaar-qaedq-cai").unwrap()
+}
+
+fn icp_ledger_id() -> Principal {
+    Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap()
+}
+
+fn dex_canister_id() -> Principal {
+    Principal::from_text("sosge-5iaaa-aaaag-alcla-cai").unwrap()
+}
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+fn user_address() -> Address {
+    address("0xdd2851Cdd40aE6536831558DD46db62fAc7A844d")
+}
+
+fn destination() -> Address {
+    address("0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34")
+}
+
+fn minter_address() -> Address {
+    address("0x1789F79e95324A47c5Fd6693071188e82E9a3558")
+}
+
+fn usdc_address() -> Address {
+    address("0x1c7d4b196cb0c7b01d743fbc6116a902379c7238")
+}
+
+fn wrapped_icp_address() -> Address {
+    address("0x2f8f2d2ea63b3b0a8f2d7c4b5e3e1a9b8c7d6e5f")
+}
+
+fn swap_contract_address() -> Address {
+    address("0x733a1BEeF5A02990aAD285d7ED93fc1b622EeF1d")
+}
+
+/// Hash of the `n`-th EVM transaction emitting a log scraped by the minter.
+fn transaction_hash(n: u8) -> Hash {
+    let mut hash = [0xab; 32];
+    hash[31] = n;
+    Hash(hash)
+}
+
+fn native_deposit(n: u8) -> ReceivedNativeEvent {
+    ReceivedNativeEvent {
+        transaction_hash: transaction_hash(n),
+        block_number: BlockNumber::new(5_539_800 + n as u128),
+        log_index: LogIndex::from(29_u8),
+        from_address: user_address(),
+        value: Wei::new(10_000_000_000_000_000),
+        principal: user(),
+        subaccount: None,
+    }
+}
+
+fn erc20_deposit() -> ReceivedErc20Event {
+    ReceivedErc20Event {
+        transaction_hash: transaction_hash(10),
+        block_number: BlockNumber::new(5_539_903),
+        log_index: LogIndex::from(0x57_u32),
+        from_address: user_address(),
+        value: Erc20Value::new(5_000_000),
+        principal: user(),
+        erc20_contract_address: usdc_address(),
+        subaccount: None,
+        original_owner: None,
+    }
+}
+
+fn wrapped_icrc_burn(n: u8) -> ReceivedBurnEvent {
+    ReceivedBurnEvent {
+        transaction_hash: transaction_hash(n),
+        block_number: BlockNumber::new(5_539_920 + n as u128),
+        log_index: LogIndex::from(4_u8),
+        from_address: user_address(),
+        value: IcrcValue::new(30_000_000),
+        principal: user(),
+        wrapped_erc20_contract_address: wrapped_icp_address(),
+        icrc_token_principal: icp_ledger_id(),
+        subaccount: None,
+    }
+}
+
+fn received_swap_event() -> ReceivedSwapEvent {
+    ReceivedSwapEvent {
+        transaction_hash: transaction_hash(30),
+        block_number: BlockNumber::new(5_539_950),
+        log_index: LogIndex::from(3_u8),
+        from_address: user_address(),
+        recipient: FixedSizeData([1; 32]),
+        token_in: address("0x6b175474e89094c44da98b954eedeac495271d0f"),
+        token_out: usdc_address(),
+        amount_in: Erc20Value::new(2_000_000),
+        amount_out: Erc20Value::new(1_000_000),
+        bridged_to_minter: true,
+        encoded_swap_data: Data(vec![1, 2, 3]),
+    }
+}
+
+fn source(event: impl Into<ReceivedContractEvent>) -> EventSource {
+    event.into().source()
+}
+
+fn create(
+    request: &WithdrawalRequest,
+    nonce: u64,
+    gas_limit: GasAmount,
+) -> Eip1559TransactionRequest {
+    create_transaction(
+        request,
+        TransactionNonce::new(nonce as u128),
+        GasFeeEstimate {
+            base_fee_per_gas: WeiPerGas::ONE,
+            max_priority_fee_per_gas: WeiPerGas::ONE,
+            base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+        },
+        gas_limit,
+        EvmNetwork::default(),
+    )
+    .expect("BUG: failed to create transaction")
+}
+
+fn sign(transaction: Eip1559TransactionRequest) -> SignedEip1559TransactionRequest {
+    SignedEip1559TransactionRequest::from((
+        transaction,
+        Eip1559Signature {
+            signature_y_parity: false,
+            r: Default::default(),
+            s: Default::default(),
+        },
+    ))
+}
+
+fn receipt(
+    transaction: &SignedEip1559TransactionRequest,
+    status: TransactionStatus,
+) -> TransactionReceipt {
+    TransactionReceipt {
+        block_hash: "0xce67a85c9fb8bc50213815c32814c159fd75160acf7cb8631e8e7b7cf7f1d472"
+            .parse()
+            .unwrap(),
+        block_number: BlockNumber::new(5_540_000),
+        effective_gas_price: WeiPerGas::ONE,
+        gas_used: GasAmount::from(21_000_u32),
+        status,
+        transaction_hash: transaction.hash(),
+    }
+}
+
+/// Creates, signs and finalizes the transaction of `request`.
+fn processed_withdrawal(
+    request: &WithdrawalRequest,
+    nonce: u64,
+    gas_limit: GasAmount,
+    status: TransactionStatus,
+) -> Vec<EventType> {
+    let withdrawal_id = request.native_ledger_burn_index();
+    let transaction = create(request, nonce, gas_limit);
+    let signed = sign(transaction.clone());
+    let transaction_receipt = receipt(&signed, status);
+    vec![
+        EventType::CreatedTransaction {
+            withdrawal_id,
+            transaction,
+        },
+        EventType::SignedTransaction {
+            withdrawal_id,
+            transaction: signed,
+        },
+        EventType::FinalizedTransaction {
+            withdrawal_id,
+            transaction_receipt,
+        },
+    ]
+}
+
+fn reimbursed(
+    burn_in_block: LedgerBurnIndex,
+    reimbursed_amount: Erc20TokenAmount,
+    transaction_hash: Option<Hash>,
+) -> Reimbursed {
+    Reimbursed {
+        reimbursed_in_block: LedgerMintIndex::new(100 + burn_in_block.get()),
+        burn_in_block,
+        reimbursed_amount,
+        transaction_hash,
+        transfer_fee: None,
+    }
+}
+
+fn setup_events() -> Vec<EventType> {
+    vec![
+        EventType::Init(InitArg {
+            evm_network: EvmNetwork::default(),
+            ecdsa_key_name: "test_key_1".to_string(),
+            helper_contract_address: Some("0x7e41257f7b5c3dd3313ef02b1f4c864fe95bec2b".to_string()),
+            native_ledger_id: Principal::from_text("apia6-jaaaa-aaaar-qabma-cai").unwrap(),
+            native_index_id: Principal::from_text("eysav-tyaaa-aaaap-akqfq-cai").unwrap(),
+            block_height: Default::default(),
+            native_minimum_withdrawal_amount: Nat::from(20_000_000_000_000_000_u64),
+            next_transaction_nonce: Default::default(),
+            last_scraped_block_number: Nat::from(5_539_000_u64),
+            native_symbol: "icMatic".to_string(),
+            native_ledger_transfer_fee: Nat::from(10_000_000_000_000_000_u64),
+            min_max_priority_fee_per_gas: Nat::from(15_000_u64),
+            ledger_suite_manager_id: Principal::from_text("kmcdp-4yaaa-aaaag-ats3q-cai").unwrap(),
+            deposit_native_fee: Nat::from(1_000_000_000_000_000_u64),
+            withdrawal_native_fee: Nat::from(5_000_000_u64),
+            fee_collector: None,
+        }),
+        EventType::Upgrade(UpgradeArg {
+            native_minimum_withdrawal_amount: Some(Nat::from(30_000_000_000_000_000_u64)),
+            confirmations: Some(12),
+            gas_limits: Some(vec![GasLimitArg {
+                kind: GasLimitKind::Erc20Withdrawal,
+                gas_limit: Nat::from(90_000_u64),
+            }]),
+            max_fee_per_gas_cap: Some(Nat::from(1_000_000_000_000_u64)),
+            ..Default::default()
+        }),
+        EventType::ChangedConfirmationPolicy {
+            from: CandidConfirmationPolicy::BlockTag(CandidBlockTag::Latest),
+            to: CandidConfirmationPolicy::Confirmations(12),
+        },
+        EventType::AddedErc20Token(ERC20Token {
+            chain_id: EvmNetwork::default(),
+            erc20_contract_address: usdc_address(),
+            erc20_token_symbol: USDC_SYMBOL.parse().unwrap(),
+            erc20_ledger_id: usdc_ledger_id(),
+            transfer_kind: None,
+            original_symbol: None,
+        }),
+    ]
+}
+
+fn deposit_events() -> Vec<EventType> {
+    vec![
+        EventType::AcceptedDeposit(native_deposit(1)),
+        EventType::MintedNative {
+            event_source: source(native_deposit(1)),
+            mint_block_index: LedgerMintIndex::new(1),
+        },
+        EventType::AcceptedErc20Deposit(erc20_deposit()),
+        EventType::MintedErc20 {
+            event_source: source(erc20_deposit()),
+            mint_block_index: LedgerMintIndex::new(2),
+            erc20_token_symbol: USDC_SYMBOL.to_string(),
+            erc20_contract_address: usdc_address(),
+        },
+        EventType::AcceptedDeposit(native_deposit(2)),
+        EventType::QuarantinedDeposit {
+            event_source: source(native_deposit(2)),
+        },
+        EventType::InvalidDeposit {
+            event_source: source(native_deposit(3)),
+            reason: "invalid principal".to_string(),
+        },
+        EventType::AcceptedDeposit(native_deposit(4)),
+        EventType::RemovedLog {
+            event_source: source(native_deposit(4)),
+        },
+        EventType::DepositReorged {
+            event_source: source(native_deposit(4)),
+        },
+    ]
+}
+
+fn scraping_events() -> Vec<EventType> {
+    vec![
+        EventType::SkippedBlock {
+            block_number: BlockNumber::new(5_539_500),
+        },
+        EventType::SyncedToBlock {
+            block_number: BlockNumber::new(5_540_000),
+        },
+        EventType::RescrapedBlockRange {
+            from_block: BlockNumber::new(5_539_500),
+            to_block: BlockNumber::new(5_539_500),
+        },
+        EventType::SkippedBlockRange {
+            from_block: BlockNumber::new(5_539_600),
+            to_block: BlockNumber::new(5_539_699),
+        },
+        EventType::UpgradeGraceScrape {
+            start_block: BlockNumber::new(5_539_900),
+            end_block: BlockNumber::new(5_540_000),
+        },
+    ]
+}
+
+fn native_withdrawal_request(ledger_burn_index: u64) -> NativeWithdrawalRequest {
+    NativeWithdrawalRequest {
+        withdrawal_amount: Wei::new(5_000_000_000_000_000),
+        destination: destination(),
+        ledger_burn_index: LedgerBurnIndex::new(ledger_burn_index),
+        from: user(),
+        from_subaccount: None,
+        created_at: Some(FIRST_EVENT_TIMESTAMP),
+        l1_fee: None,
+        withdrawal_fee: None,
+        is_contract_destination: None,
+    }
+}
+
+/// A failed withdrawal that is reimbursed after its transaction was resubmitted,
+/// a withdrawal that is parked and cancelled, and a withdrawal cancelled by the user.
+fn native_withdrawal_events() -> Vec<EventType> {
+    let failed = native_withdrawal_request(0);
+    let failed_id = failed.ledger_burn_index;
+    let transaction = create(
+        &failed.clone().into(),
+        0,
+        NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+    );
+    let signed = sign(transaction.clone());
+    let resubmitted = Eip1559TransactionRequest {
+        max_fee_per_gas: transaction.max_fee_per_gas.checked_mul(2_u8).unwrap(),
+        ..transaction.clone()
+    };
+    let failed_receipt = receipt(&signed, TransactionStatus::Failure);
+
+    let parked = native_withdrawal_request(1);
+    let parked_id = parked.ledger_burn_index;
+    let stuck = create(
+        &parked.clone().into(),
+        1,
+        NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+    );
+    let cancellation = create_cancellation_transaction(
+        &stuck,
+        minter_address(),
+        GasFeeEstimate {
+            base_fee_per_gas: WeiPerGas::ONE,
+            max_priority_fee_per_gas: WeiPerGas::ONE,
+            base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+        },
+        NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+    );
+    let signed_cancellation = sign(cancellation.clone());
+
+    // reimbursed minus the native ledger transfer fee of 0.01
+    let cancelled = NativeWithdrawalRequest {
+        withdrawal_amount: Wei::new(50_000_000_000_000_000),
+        ..native_withdrawal_request(7)
+    };
+    let cancelled_reimbursement = PendingReimbursementRequest {
+        ledger_burn_index: cancelled.ledger_burn_index,
+        reimbursed_amount: Erc20TokenAmount::new(40_000_000_000_000_000),
+        to: cancelled.from,
+        to_subaccount: None,
+    };
+
+    vec![
+        EventType::AcceptedNativeWithdrawalRequest(failed),
+        EventType::CreatedTransaction {
+            withdrawal_id: failed_id,
+            transaction: transaction.clone(),
+        },
+        EventType::SignedTransaction {
+            withdrawal_id: failed_id,
+            transaction: signed.clone(),
+        },
+        EventType::BroadcastAttempted {
+            withdrawal_id: failed_id,
+            transaction_hash: signed.hash(),
+            nonce: signed.nonce(),
+            timestamp: 1_699_527_697_000_000_000,
+        },
+        EventType::BroadcastReconciled {
+            withdrawal_id: failed_id,
+            transaction_hash: signed.hash(),
+            outcome: BroadcastOutcome::Sent,
+        },
+        EventType::ReplacedTransaction {
+            withdrawal_id: failed_id,
+            transaction: resubmitted,
+        },
+        EventType::FinalizedTransaction {
+            withdrawal_id: failed_id,
+            transaction_receipt: failed_receipt.clone(),
+        },
+        EventType::ReimbursedNativeWithdrawal(reimbursed(
+            failed_id,
+            transaction.amount.change_units(),
+            Some(failed_receipt.transaction_hash),
+        )),
+        EventType::AcceptedNativeWithdrawalRequest(parked),
+        EventType::CreatedTransaction {
+            withdrawal_id: parked_id,
+            transaction: stuck.clone(),
+        },
+        EventType::SignedTransaction {
+            withdrawal_id: parked_id,
+            transaction: sign(stuck),
+        },
+        EventType::ParkedTransaction {
+            withdrawal_id: parked_id,
+            cancellation,
+        },
+        EventType::SignedTransaction {
+            withdrawal_id: parked_id,
+            transaction: signed_cancellation.clone(),
+        },
+        EventType::FinalizedTransaction {
+            withdrawal_id: parked_id,
+            transaction_receipt: receipt(&signed_cancellation, TransactionStatus::Success),
+        },
+        EventType::AcceptedNativeWithdrawalRequest(cancelled),
+        EventType::CancelledWithdrawalRequest(cancelled_reimbursement.clone()),
+        EventType::ReimbursedNativeWithdrawal(reimbursed(
+            cancelled_reimbursement.ledger_burn_index,
+            cancelled_reimbursement.reimbursed_amount,
+            None,
+        )),
+    ]
+}
+
+/// A failed ERC-20 withdrawal whose ERC-20 tokens are reimbursed, and a withdrawal
+/// whose ERC-20 burn failed, so that its native tokens are to be reimbursed.
+fn erc20_withdrawal_events() -> Vec<EventType> {
+    let request = Erc20WithdrawalRequest {
+        max_transaction_fee: Wei::new(30_000_000_000_000_000),
+        withdrawal_amount: Erc20Value::new(4_996_000),
+        destination: destination(),
+        native_ledger_burn_index: LedgerBurnIndex::new(2),
+        erc20_contract_address: usdc_address(),
+        erc20_ledger_id: usdc_ledger_id(),
+        erc20_ledger_burn_index: LedgerBurnIndex::new(2),
+        from: user(),
+        from_subaccount: None,
+        created_at: FIRST_EVENT_TIMESTAMP,
+        l1_fee: Some(Wei::new(10_000_000)),
+        withdrawal_fee: Some(Wei::new(5_000_000)),
+        is_wrapped_mint: Some(false),
+        gas_limit: None,
+    };
+    let withdrawal = WithdrawalRequest::Erc20(request.clone());
+    let mut events = vec![EventType::AcceptedErc20WithdrawalRequest(request.clone())];
+    events.extend(processed_withdrawal(
+        &withdrawal,
+        2,
+        ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+        TransactionStatus::Failure,
+    ));
+    let failed_burn = PendingReimbursementRequest {
+        ledger_burn_index: LedgerBurnIndex::new(3),
+        reimbursed_amount: Erc20TokenAmount::new(30_000_000_000_000_000),
+        to: user(),
+        to_subaccount: None,
+    };
+    events.extend([
+        EventType::ReimbursedErc20Withdrawal {
+            native_ledger_burn_index: request.native_ledger_burn_index,
+            erc20_ledger_id: request.erc20_ledger_id,
+            reimbursed: reimbursed(
+                request.erc20_ledger_burn_index,
+                request.withdrawal_amount.change_units(),
+                None,
+            ),
+        },
+        EventType::FailedErc20WithdrawalRequest(failed_burn.clone()),
+        EventType::QuarantinedReimbursement {
+            index: ReimbursementIndex::Native {
+                ledger_burn_index: failed_burn.ledger_burn_index,
+            },
+        },
+    ]);
+    events
+}
+
+/// ICRC tokens locked to mint their wrapped ERC-20 token, the release of some of them
+/// after their wrapped tokens were burned, and a failed mint whose ICRC tokens are reimbursed.
+fn wrapped_icrc_events() -> Vec<EventType> {
+    let deployed = ReceivedWrappedIcrcDeployedEvent {
+        transaction_hash: transaction_hash(20),
+        block_number: BlockNumber::new(5_539_910),
+        log_index: LogIndex::from(1_u8),
+        base_token: icp_ledger_id(),
+        deployed_wrapped_erc20: wrapped_icp_address(),
+    };
+    let wrap_request = Erc20WithdrawalRequest {
+        max_transaction_fee: Wei::new(30_000_000_000_000_000),
+        withdrawal_amount: Erc20Value::new(100_000_000),
+        destination: destination(),
+        native_ledger_burn_index: LedgerBurnIndex::new(4),
+        erc20_contract_address: wrapped_icp_address(),
+        erc20_ledger_id: icp_ledger_id(),
+        erc20_ledger_burn_index: LedgerBurnIndex::new(9),
+        from: user(),
+        from_subaccount: None,
+        created_at: FIRST_EVENT_TIMESTAMP,
+        l1_fee: None,
+        withdrawal_fee: Some(Wei::new(5_000_000)),
+        is_wrapped_mint: Some(true),
+        gas_limit: None,
+    };
+    let wrap = WithdrawalRequest::Erc20(wrap_request.clone());
+
+    let mut events = vec![
+        EventType::DeployedWrappedIcrcToken(deployed),
+        EventType::AcceptedErc20WithdrawalRequest(wrap_request.clone()),
+        EventType::AcceptedWrappedIcrcBurn(wrapped_icrc_burn(21)),
+        EventType::ReleasedIcrcToken {
+            event_source: source(wrapped_icrc_burn(21)),
+            release_block_index: LedgerReleaseIndex::new(3),
+            released_icrc_token: icp_ledger_id(),
+            wrapped_erc20_contract_address: wrapped_icp_address(),
+            transfer_fee: IcrcValue::new(10_000),
+        },
+        EventType::AcceptedWrappedIcrcBurn(wrapped_icrc_burn(22)),
+        EventType::QuarantinedRelease {
+            event_source: source(wrapped_icrc_burn(22)),
+            release_event: wrapped_icrc_burn(22),
+        },
+        EventType::InvalidEvent {
+            event_source: source(wrapped_icrc_burn(23)),
+            reason: "unsupported wrapped ICRC token".to_string(),
+        },
+    ];
+    events.extend(processed_withdrawal(
+        &wrap,
+        3,
+        ERC20_MINT_TRANSACTION_GAS_LIMIT,
+        TransactionStatus::Failure,
+    ));
+    events.extend([
+        EventType::ReimbursedIcrcWrap {
+            native_ledger_burn_index: wrap_request.native_ledger_burn_index,
+            reimbursed_icrc_token: icp_ledger_id(),
+            reimbursed: Reimbursed {
+                transfer_fee: Some(Erc20TokenAmount::new(10_000)),
+                ..reimbursed(
+                    wrap_request.erc20_ledger_burn_index,
+                    wrap_request.withdrawal_amount.change_units(),
+                    None,
+                )
+            },
+        },
+        EventType::FailedIcrcLockRequest(PendingReimbursementRequest {
+            ledger_burn_index: LedgerBurnIndex::new(5),
+            reimbursed_amount: Erc20TokenAmount::new(30_000_000_000_000_000),
+            to: user(),
+            to_subaccount: None,
+        }),
+    ]);
+    events
+}
+
+fn swap_request(swap_tx_id: &str, native_ledger_burn_index: LedgerBurnIndex) -> ExecuteSwapRequest {
+    ExecuteSwapRequest {
+        max_transaction_fee: Wei::new(1_000_000_000_000_000),
+        erc20_token_in: usdc_address(),
+        erc20_amount_in: Erc20Value::new(500_000),
+        min_amount_out: Erc20Value::new(400_000),
+        recipient: user_address(),
+        deadline: Erc20Value::new(1_741_351_818),
+        commands: vec![],
+        commands_data: vec![],
+        swap_contract: swap_contract_address(),
+        gas_estimate: GasAmount::new(500_000),
+        native_ledger_burn_index,
+        erc20_ledger_id: usdc_ledger_id(),
+        erc20_ledger_burn_index: LedgerBurnIndex::new(7),
+        from: dex_canister_id(),
+        from_subaccount: None,
+        created_at: FIRST_EVENT_TIMESTAMP,
+        l1_fee: None,
+        withdrawal_fee: None,
+        swap_tx_id: swap_tx_id.to_string(),
+        is_refund: false,
+    }
+}
+
+/// Activation of the swap feature, a swap order bridged to the dex and a swap executed
+/// by the minter on behalf of the dex.
+fn swap_events() -> Vec<EventType> {
+    let approve = Erc20Approve {
+        max_transaction_fee: Wei::new(10_000_000_000_000_000),
+        erc20_contract_address: usdc_address(),
+        swap_contract_address: swap_contract_address(),
+        native_ledger_burn_index: LedgerBurnIndex::new(6),
+        from: user(),
+        from_subaccount: None,
+        created_at: FIRST_EVENT_TIMESTAMP,
+        l1_fee: None,
+        withdrawal_fee: None,
+        is_revocation: None,
+    };
+    let approve_request = WithdrawalRequest::Erc20Approve(approve.clone());
+    let mut activation = processed_withdrawal(
+        &approve_request,
+        4,
+        ERC20_APPROVAL_TRANSACTION_GAS_LIMIT,
+        TransactionStatus::Success,
+    );
+    // the swap contract is activated while the approval transaction is pending
+    activation.insert(
+        2,
+        EventType::SwapContractActivated {
+            swap_contract_address: swap_contract_address(),
+            usdc_contract_address: usdc_address(),
+            twin_usdc_ledger_id: usdc_ledger_id(),
+            twin_usdc_decimals: 6,
+            canister_signing_fee_twin_usdc_value: Erc20Value::new(100_000),
+            dex_canister_id: dex_canister_id(),
+        },
+    );
+    // allocated by `ReleasedGasFromGasTankWithUsdc`, see `State::activate_swap_feature`
+    let swap_index = LedgerBurnIndex::new(10_000_000_000_000_000_000);
+    let tx_id = SwapTxId(SWAP_TX_ID.to_string());
+    let swap = swap_request(SWAP_TX_ID, swap_index);
+    let mut executed_swap = processed_withdrawal(
+        &WithdrawalRequest::Swap(swap.clone()),
+        5,
+        swap.gas_estimate,
+        TransactionStatus::Success,
+    );
+    // parsed from the receipt logs right before finalizing the swap
+    executed_swap.insert(
+        2,
+        EventType::RealizedSwapAmountOut {
+            withdrawal_id: swap_index,
+            amount_out: Erc20Value::new(450_000),
+        },
+    );
+
+    let mut events = vec![EventType::AcceptedSwapActivationRequest(approve.clone())];
+    events.extend(activation);
+    events.extend([
+        EventType::ReceivedSwapOrder(received_swap_event()),
+        EventType::MintedToAppicDex {
+            event_source: source(received_swap_event()),
+            mint_block_index: LedgerMintIndex::new(12),
+            minted_token: usdc_ledger_id(),
+            erc20_contract_address: usdc_address(),
+            tx_id: tx_id.clone(),
+        },
+        EventType::NotifiedSwapEventOrderToAppicDex {
+            event_source: source(received_swap_event()),
+            tx_id,
+        },
+        EventType::ReleasedGasFromGasTankWithUsdc {
+            usdc_amount: Erc20Value::new(200_000),
+            gas_amount: Wei::new(1_000_000),
+            swap_tx_id: SWAP_TX_ID.to_string(),
+        },
+        EventType::AcceptedSwapRequest(swap),
+    ]);
+    events.extend(executed_swap);
+    events.extend([
+        EventType::QuarantinedSwapRequest(swap_request(QUARANTINED_SWAP_TX_ID, swap_index)),
+        EventType::QuarantinedDexOrder(DexOrderArgs {
+            tx_id: QUARANTINED_DEX_ORDER_TX_ID.to_string(),
+            amount_in: Nat::from(10_000_000_u64),
+            min_amount_out: Nat::from(9_000_000_u64),
+            commands: vec![],
+            commands_data: vec!["0x01".to_string()],
+            max_gas_fee_usd: None,
+            signing_fee: None,
+            gas_limit: Nat::from(500_000_u64),
+            deadline: Nat::from(1_741_351_818_u64),
+            recipient: "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".to_string(),
+            erc20_ledger_burn_index: Nat::from(42_u64),
+            is_refund: false,
+        }),
+        EventType::GasTankUpdate {
+            usdc_withdrawn: Erc20Value::new(200_000),
+            native_deposited: Wei::new(1_000_000),
+        },
+        // revoking the approval of another spender keeps swapping active
+        EventType::AcceptedApprovalRevocationRequest(Erc20Approve {
+            swap_contract_address: address("0x9f1b6e2a5c2d5f0b3a17c4e8d9b0a6f3e2c1d4b5"),
+            native_ledger_burn_index: LedgerBurnIndex::new(8),
+            is_revocation: Some(true),
+            ..approve
+        }),
+    ]);
+    events
+}
+
+fn operator_events() -> Vec<EventType> {
+    vec![
+        EventType::UpdatedAddressList {
+            list: ListKind::Deny,
+            action: AddOrRemove::Add,
+            addresses: vec![address("0x8576acc5c05d6ce88f4e49bf65bdf0c62f91353c")],
+        },
+        EventType::ProviderDisagreement {
+            method: "eth_getLogs".to_string(),
+            block_range_or_hash: "5539900-5540000".to_string(),
+            per_provider_hash: vec![
+                ("Ankr".to_string(), Hash([1; 32])),
+                ("PublicNode".to_string(), Hash([2; 32])),
+            ],
+            timestamp: FIRST_EVENT_TIMESTAMP,
+        },
+        EventType::ProviderRateLimited {
+            provider: Provider::Ankr,
+            retry_after_ns: FIRST_EVENT_TIMESTAMP + 60_000_000_000,
+        },
+        EventType::ArchivedEvents {
+            from_index: 0,
+            up_to_index: 10,
+            hash: Hash([3; 32]),
+        },
+        EventType::EventLogCompacted {
+            pruned_count: 12,
+            threshold_block: BlockNumber::new(5_539_000),
+        },
+        EventType::FeatureFlagChanged {
+            flag: FeatureFlag::Draining,
+            enabled: true,
+        },
+        EventType::UpgradedWhileNotDrained { in_flight_count: 2 },
+        EventType::FeatureFlagChanged {
+            flag: FeatureFlag::Draining,
+            enabled: false,
+        },
+        EventType::NativeLedgerMetadataMatched(NativeLedgerMetadata {
+            symbol: "icMatic".to_string(),
+            decimals: 18,
+        }),
+        EventType::NativeLedgerMetadataMismatch(NativeLedgerMetadata {
+            symbol: "POL.icp".to_string(),
+            decimals: 18,
+        }),
+        EventType::BackfillCompleted {
+            from_block: BlockNumber::new(5_530_000),
+            to_block: BlockNumber::new(5_530_499),
+            accepted_events: 0,
+            invalid_events: 0,
+            duplicate_events: 3,
+        },
+        EventType::FeeCollected {
+            ledger_id: Principal::from_text("apia6-jaaaa-aaaar-qabma-cai").unwrap(),
+            amount: IcrcValue::new(30_000_000_000_000_000),
+            block_index: LedgerLockIndex::new(15),
+        },
+        EventType::CollectedFeesWithdrawn {
+            ledger_id: Principal::from_text("apia6-jaaaa-aaaar-qabma-cai").unwrap(),
+            to: Account {
+                owner: user(),
+                subaccount: None,
+            },
+            amount: IcrcValue::new(20_000_000_000_000_000),
+            block_index: 16,
+        },
+        EventType::FeeCapExceeded {
+            withdrawal_id: LedgerBurnIndex::new(17),
+            max_fee_per_gas: WeiPerGas::new(1_200_000_000_000),
+            max_transaction_fee: Wei::new(25_200_000_000_000_000),
+            cap: FeeCap {
+                max_fee_per_gas: WeiPerGas::new(1_000_000_000_000),
+                max_transaction_fee: Wei::new(50_000_000_000_000_000),
+            },
+            timestamp: 1_699_527_697_000_000_000,
+        },
+        EventType::QuarantinedWithdrawal {
+            withdrawal_id: LedgerBurnIndex::new(18),
+        },
+        EventType::UnaccountedFeesFound {
+            ledger_id: Principal::from_text("apia6-jaaaa-aaaar-qabma-cai").unwrap(),
+            amount: IcrcValue::new(5_000_000_000_000_000),
+        },
+        EventType::CollectedFeesWithdrawalStarted {
+            ledger_id: Principal::from_text("apia6-jaaaa-aaaar-qabma-cai").unwrap(),
+            withdrawal: PendingFeeWithdrawal {
+                to: Account {
+                    owner: user(),
+                    subaccount: None,
+                },
+                transfer_amount: IcrcValue::new(4_990_000_000_000_000),
+                ledger_fee: IcrcValue::new(10_000_000_000_000),
+                created_at_time: 1_699_527_697_000_000_000,
+                amount: IcrcValue::new(5_000_000_000_000_000),
+            },
+        },
+        EventType::CollectedFeesWithdrawalFailed {
+            ledger_id: Principal::from_text("apia6-jaaaa-aaaar-qabma-cai").unwrap(),
+            reason: "TooOld".to_string(),
+        },
+        EventType::MissingFeesFound {
+            ledger_id: Principal::from_text("apia6-jaaaa-aaaar-qabma-cai").unwrap(),
+            amount: IcrcValue::new(5_000_000_000_000_000),
+        },
+    ]
+}
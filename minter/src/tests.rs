@@ -19,7 +19,7 @@ pub mod dex_types;
 use crate::{
     contract_logs::{types::ReceivedNativeEvent, EventSource},
     erc20::ERC20TokenSymbol,
-    evm_config::EvmNetwork,
+    evm_config::{ConfirmationPolicy, EvmNetwork},
     map::DedupMultiKeyMap,
     numeric::{BlockNumber, LedgerMintIndex, LogIndex, Wei, WeiPerGas},
     rpc_declarations::BlockTag,
@@ -59,11 +59,12 @@ mod get_contract_logs {
     use crate::contract_logs::parser::{LogParser, ReceivedEventsLogParser};
     use crate::contract_logs::swap::swap_logs::ReceivedSwapEvent;
     use crate::contract_logs::types::{ReceivedBurnEvent, ReceivedErc20Event, ReceivedNativeEvent};
-    use crate::contract_logs::{LedgerSubaccount, ReceivedContractEvent};
+    use crate::contract_logs::{EventSignature, LedgerSubaccount, ReceivedContractEvent};
     use crate::erc20::ERC20TokenSymbol;
     use crate::numeric::{BlockNumber, Erc20Value, LogIndex, Wei};
     use crate::rpc_declarations::Data;
     use crate::rpc_declarations::{FixedSizeData, LogEntry};
+    use crate::state::feature_flags::FeatureFlag;
     use crate::state::STATE;
     use crate::tests::test_state;
     use candid::Principal;
@@ -113,13 +114,49 @@ mod get_contract_logs {
 
     #[test]
     fn should_have_correct_topic() {
-        use crate::contract_logs::types::RECEIVED_DEPOSITED_TOKEN_EVENT_TOPIC_OLD_CONTRACT;
+        use crate::contract_logs::types::DepositLog;
 
         //must match event signature in minter.sol
         let event_signature = "DepositLog(address,address,uint256,bytes32,bytes32)";
 
         let topic = Keccak256::hash(event_signature);
-        assert_eq!(topic, RECEIVED_DEPOSITED_TOKEN_EVENT_TOPIC_OLD_CONTRACT)
+        assert_eq!(FixedSizeData(topic), DepositLog::event_signature_hash())
+    }
+
+    #[test]
+    fn should_match_event_signature_hashes_used_in_mocked_logs() {
+        use crate::contract_logs::swap::swap_logs::SwapExecuted;
+        use crate::contract_logs::types::{
+            DepositLog, TokenBurn, TokenBurnWithPermit, WrappedTokenDeployed,
+        };
+
+        for (event_signature_hash, mocked_topic) in [
+            (
+                DepositLog::event_signature_hash(),
+                "0xdeaddf8708b62ae1bf8ec4693b523254aa961b2da6bc5be57f3188ee784d6275",
+            ),
+            (
+                TokenBurn::event_signature_hash(),
+                "0x37199deebd336af9013dbddaaf9a68e337707bb4ed64cb45ed12841af85e0377",
+            ),
+            (
+                TokenBurnWithPermit::event_signature_hash(),
+                "0x8a7eb0ed3712e84ba2c3025b71cf22659d778784ee9a7d7c0e9fe82585ce3c89",
+            ),
+            (
+                WrappedTokenDeployed::event_signature_hash(),
+                "0xe63ddf723173735772522be59b64b9c95be6eb8f14b87948f670ad6f8949ab2e",
+            ),
+            (
+                SwapExecuted::event_signature_hash(),
+                "0xc33dada04354dd803ea44b93af35ba61d4bfa477f5f06c86b6a00cfc0c261bea",
+            ),
+        ] {
+            assert_eq!(
+                event_signature_hash,
+                FixedSizeData::from_str(mocked_topic).unwrap()
+            );
+        }
     }
 
     #[test]
@@ -294,6 +331,7 @@ mod get_contract_logs {
                 .parse()
                 .unwrap(),
             subaccount: None,
+            original_owner: None,
         }
         .into();
 
@@ -339,10 +377,123 @@ mod get_contract_logs {
                 .parse()
                 .unwrap(),
             subaccount: LedgerSubaccount::from_bytes([0xff; 32]),
+            original_owner: None,
+        }
+        .into();
+
+        assert_eq!(parsed_event, expected_event);
+    }
+
+    #[test]
+    fn should_have_correct_permit_deposit_topic() {
+        use crate::contract_logs::types::TokenBurnWithPermit;
+
+        //must match event signature in the new helper contract
+        let event_signature =
+            "TokenBurnWithPermit(address,uint256,bytes32,address,bytes32,address)";
+
+        let topic = Keccak256::hash(event_signature);
+        assert_eq!(
+            FixedSizeData(topic),
+            TokenBurnWithPermit::event_signature_hash()
+        )
+    }
+
+    #[test]
+    fn should_parse_token_burn_erc20_event_without_original_owner() {
+        let state = test_state();
+        STATE.with(|cell| *cell.borrow_mut() = Some(state));
+
+        let event = r#"{
+            "address": "0x7e41257f7b5c3dd3313ef02b1f4c864fe95bec2b",
+            "topics": [
+                "0x37199deebd336af9013dbddaaf9a68e337707bb4ed64cb45ed12841af85e0377",
+                "0x0000000000000000000000005d737f982696fe2fe4ef1c7584e914c3a8e44d54",
+                "0x09efcdab00000000000100000000000000000000000000000000000000000000",
+                "0x000000000000000000000000779877a7b0d9e8603169ddbd7836e478b4624789"
+            ],
+            "data": "0x00000000000000000000000000000000000000000000000000005af3107a40000000000000000000000000000000000000000000000000000000000000000000",
+            "blockNumber": "0x698adb",
+            "transactionHash": "0xf353e17cbcfea236a8b03d2d800205074e1f5014a3ce0f6dedcf128addb6bea4",
+            "transactionIndex": "0x15",
+            "blockHash": "0xeee67434b62fe62182ee51cdaf2693f112994fd3aa4d043c7e4a16fe775c37e3",
+            "logIndex": "0x45",
+            "removed": false
+        }"#;
+        let parsed_event =
+            ReceivedEventsLogParser::parse_log(serde_json::from_str::<LogEntry>(event).unwrap())
+                .unwrap();
+        let expected_event: ReceivedContractEvent = ReceivedErc20Event {
+            transaction_hash: "0xf353e17cbcfea236a8b03d2d800205074e1f5014a3ce0f6dedcf128addb6bea4"
+                .parse()
+                .unwrap(),
+            block_number: BlockNumber::new(6916827),
+            log_index: LogIndex::from(69_u8),
+            from_address: "0x5d737F982696Fe2fE4eF1c7584E914C3A8e44D54"
+                .parse()
+                .unwrap(),
+            value: Erc20Value::from(100_000_000_000_000_u128),
+            principal: Principal::from_str("2chl6-4hpzw-vqaaa-aaaaa-c").unwrap(),
+            erc20_contract_address: "0x779877a7b0d9e8603169ddbd7836e478b4624789"
+                .parse()
+                .unwrap(),
+            subaccount: None,
+            original_owner: None,
         }
         .into();
 
         assert_eq!(parsed_event, expected_event);
+        assert_eq!(parsed_event.from_address(), expected_event.from_address());
+    }
+
+    #[test]
+    fn should_parse_permit_deposit_erc20_event() {
+        let state = test_state();
+        STATE.with(|cell| *cell.borrow_mut() = Some(state));
+
+        let event = r#"{
+            "address": "0x7e41257f7b5c3dd3313ef02b1f4c864fe95bec2b",
+            "topics": [
+                "0x8a7eb0ed3712e84ba2c3025b71cf22659d778784ee9a7d7c0e9fe82585ce3c89",
+                "0x0000000000000000000000001234567890abcdef1234567890abcdef12345678",
+                "0x09efcdab00000000000100000000000000000000000000000000000000000000",
+                "0x000000000000000000000000779877a7b0d9e8603169ddbd7836e478b4624789"
+            ],
+            "data": "0x00000000000000000000000000000000000000000000000000005af3107a4000ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0000000000000000000000005d737f982696fe2fe4ef1c7584e914c3a8e44d54",
+            "blockNumber": "0x698adb",
+            "transactionHash": "0xf353e17cbcfea236a8b03d2d800205074e1f5014a3ce0f6dedcf128addb6bea4",
+            "transactionIndex": "0x15",
+            "blockHash": "0xeee67434b62fe62182ee51cdaf2693f112994fd3aa4d043c7e4a16fe775c37e3",
+            "logIndex": "0x46",
+            "removed": false
+        }"#;
+        let parsed_event =
+            ReceivedEventsLogParser::parse_log(serde_json::from_str::<LogEntry>(event).unwrap())
+                .unwrap();
+        let original_owner: Address = "0x5d737F982696Fe2fE4eF1c7584E914C3A8e44D54"
+            .parse()
+            .unwrap();
+        let expected_event = ReceivedErc20Event {
+            transaction_hash: "0xf353e17cbcfea236a8b03d2d800205074e1f5014a3ce0f6dedcf128addb6bea4"
+                .parse()
+                .unwrap(),
+            block_number: BlockNumber::new(6916827),
+            log_index: LogIndex::from(70_u8),
+            from_address: "0x1234567890AbcdEF1234567890aBcdef12345678"
+                .parse()
+                .unwrap(),
+            value: Erc20Value::from(100_000_000_000_000_u128),
+            principal: Principal::from_str("2chl6-4hpzw-vqaaa-aaaaa-c").unwrap(),
+            erc20_contract_address: "0x779877a7b0d9e8603169ddbd7836e478b4624789"
+                .parse()
+                .unwrap(),
+            subaccount: LedgerSubaccount::from_bytes([0xff; 32]),
+            original_owner: Some(original_owner),
+        };
+
+        assert_eq!(expected_event.depositor(), original_owner);
+        assert_eq!(parsed_event, expected_event.into());
+        assert_eq!(parsed_event.from_address(), original_owner);
     }
 
     #[test]
@@ -367,7 +518,7 @@ mod get_contract_logs {
             Address::from_str("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913").unwrap(),
             ERC20TokenSymbol("icUSDC.base".to_string()),
         );
-        state.is_swapping_active = true;
+        state.feature_flags.set(FeatureFlag::SwapActive, true);
         state.activate_swap_feature(
             (
                 Address::from_str("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913").unwrap(),
@@ -397,7 +548,7 @@ mod get_contract_logs {
             Address::from_str("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913").unwrap(),
             ERC20TokenSymbol("icUSDC.base".to_string()),
         );
-        state.is_swapping_active = true;
+        state.feature_flags.set(FeatureFlag::SwapActive, true);
         state.activate_swap_feature(
             (
                 Address::from_str("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913").unwrap(),
@@ -430,7 +581,7 @@ mod get_contract_logs {
             Address::from_str("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913").unwrap(),
             ERC20TokenSymbol("icUSDC.base".to_string()),
         );
-        state.is_swapping_active = true;
+        state.feature_flags.set(FeatureFlag::SwapActive, true);
         state.activate_swap_feature(
             (
                 Address::from_str("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913").unwrap(),
@@ -474,7 +625,7 @@ mod get_contract_logs {
         let state = test_state();
         STATE.with(|cell| *cell.borrow_mut() = Some(state));
 
-        use crate::contract_logs::{EventSource, EventSourceError, ReceivedContractEventError};
+        use crate::contract_logs::{EventSource, ReceivedContractEventError};
         let event = r#"{
             "address": "0xb44b5e756a894775fc32eddf3314bb1b1944dc34",
             "topics": [
@@ -494,7 +645,7 @@ mod get_contract_logs {
 
         let parsed_event =
             ReceivedEventsLogParser::parse_log(serde_json::from_str::<LogEntry>(event).unwrap());
-        let expected_error = Err(ReceivedContractEventError::InvalidEventSource {
+        let expected_error = Err(ReceivedContractEventError::RemovedLogEntry {
             source: EventSource {
                 transaction_hash:
                     "0x705f826861c802b407843e99af986cfde8749b669e5e0a5a150f4350bcaa9bc3"
@@ -502,9 +653,6 @@ mod get_contract_logs {
                         .unwrap(),
                 log_index: LogIndex::from(39_u8),
             },
-            error: EventSourceError::InvalidEvent(
-                "this event has been removed from the chain".to_string(),
-            ),
         });
         assert_eq!(parsed_event, expected_error);
     }
@@ -704,7 +852,7 @@ mod rlp_encoding {
 }
 
 mod eth_get_block_by_number {
-    use crate::numeric::{BlockNumber, Wei};
+    use crate::numeric::{BlockNumber, BlockTimestamp, Wei};
     use crate::rpc_declarations::{into_nat, Block, Quantity};
 
     #[test]
@@ -1034,10 +1182,38 @@ mod eth_get_block_by_number {
             Block {
                 number: BlockNumber::new(0x10eb3c6),
                 base_fee_per_gas: Wei::new(0x4b85a0fcd),
+                timestamp: BlockTimestamp::new(0x64ba5557),
             }
         )
     }
 
+    #[test]
+    fn should_compute_lag_from_block_timestamp() {
+        use crate::state::block_lag_seconds;
+        use crate::tests::minter_flow_tets::mock_rpc_https_responses::MOCK_BLOCK_NUMBER;
+
+        const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+        let response: serde_json::Value = serde_json::from_str(MOCK_BLOCK_NUMBER).unwrap();
+        let block: Block = serde_json::from_value(response["result"].clone()).unwrap();
+        assert_eq!(block.timestamp, BlockTimestamp::new(0x6744b156));
+
+        let block_time_ns = 0x6744b156_u64 * NANOS_PER_SEC;
+        assert_eq!(block_lag_seconds(block.timestamp, block_time_ns), 0);
+        assert_eq!(
+            block_lag_seconds(
+                block.timestamp,
+                block_time_ns + 14 * 60 * NANOS_PER_SEC + 999_999_999
+            ),
+            14 * 60
+        );
+        // the IC time lagging behind the chain is not a negative lag
+        assert_eq!(
+            block_lag_seconds(block.timestamp, block_time_ns - 5 * NANOS_PER_SEC),
+            0
+        );
+    }
+
     #[test]
     fn should_convert_quantity_to_nat() {
         let quantity = Quantity::new(0x4b85a0fcd); //20_272_779_213 wei
@@ -1164,6 +1340,146 @@ mod eth_fee_history {
     }
 }
 
+mod gas_fee_config {
+    use super::minter_flow_tets::mock_rpc_https_responses::MOCK_BSC_FEE_HISTORY_INNER;
+    use crate::numeric::{GasAmount, WeiPerGas};
+    use crate::rpc_declarations::FeeHistory;
+    use crate::state::STATE;
+    use crate::tests::test_state;
+    use crate::tx::gas_fees::{
+        estimate_transaction_fee, estimate_transaction_fee_with_config, GasFeeConfig,
+    };
+
+    fn bsc_fee_history() -> FeeHistory {
+        serde_json::from_str(MOCK_BSC_FEE_HISTORY_INNER).unwrap()
+    }
+
+    fn config(reward_percentile_index: u8, base_fee_multiplier_percent: u16) -> GasFeeConfig {
+        GasFeeConfig {
+            reward_percentile_index,
+            base_fee_multiplier_percent,
+            min_priority_fee: WeiPerGas::ZERO,
+        }
+    }
+
+    #[test]
+    fn should_select_reward_percentile() {
+        // base fee is zero in the BSC fixture, so max_fee_per_gas is the priority fee.
+        for (index, expected_max_fee_per_gas) in [
+            (0, WeiPerGas::new(100_000_000)),
+            (1, WeiPerGas::new(110_000_000)),
+            (2, WeiPerGas::new(120_000_001)),
+        ] {
+            let estimate =
+                estimate_transaction_fee_with_config(&bsc_fee_history(), &config(index, 200))
+                    .unwrap();
+
+            assert_eq!(estimate.max_priority_fee_per_gas, expected_max_fee_per_gas);
+            assert_eq!(
+                estimate.checked_estimate_max_fee_per_gas(),
+                Some(expected_max_fee_per_gas)
+            );
+        }
+    }
+
+    #[test]
+    fn should_apply_base_fee_multiplier() {
+        let fee_history = FeeHistory {
+            base_fee_per_gas: vec![WeiPerGas::new(1_000_000_000)],
+            ..bsc_fee_history()
+        };
+
+        for (multiplier, expected_max_fee_per_gas) in [
+            (100, WeiPerGas::new(1_110_000_000)),
+            (150, WeiPerGas::new(1_610_000_000)),
+            (200, WeiPerGas::new(2_110_000_000)),
+            (300, WeiPerGas::new(3_110_000_000)),
+        ] {
+            let estimate =
+                estimate_transaction_fee_with_config(&fee_history, &config(1, multiplier)).unwrap();
+
+            assert_eq!(
+                estimate.checked_estimate_max_fee_per_gas(),
+                Some(expected_max_fee_per_gas)
+            );
+        }
+    }
+
+    #[test]
+    fn should_apply_min_priority_fee() {
+        let config = GasFeeConfig {
+            min_priority_fee: WeiPerGas::new(1_000_000_000),
+            ..config(2, 200)
+        };
+
+        let estimate = estimate_transaction_fee_with_config(&bsc_fee_history(), &config).unwrap();
+
+        assert_eq!(
+            estimate.checked_estimate_max_fee_per_gas(),
+            Some(WeiPerGas::new(1_000_000_000))
+        );
+    }
+
+    #[test]
+    fn should_cover_base_fee_and_min_priority_fee() {
+        let fee_history = FeeHistory {
+            base_fee_per_gas: vec![WeiPerGas::new(500_000_000)],
+            ..bsc_fee_history()
+        };
+        let min_priority_fee = WeiPerGas::new(1_000_000_000);
+        let config = GasFeeConfig {
+            min_priority_fee,
+            ..config(0, 100)
+        };
+
+        let price = estimate_transaction_fee_with_config(&fee_history, &config)
+            .unwrap()
+            .to_price(GasAmount::new(21_000));
+
+        assert_eq!(price.max_priority_fee_per_gas, min_priority_fee);
+        assert_eq!(price.max_fee_per_gas, WeiPerGas::new(1_500_000_000));
+    }
+
+    #[test]
+    fn should_fall_back_to_median_when_percentile_is_missing() {
+        // without a config, the estimate is the median of all rewards
+        STATE.with(|cell| *cell.borrow_mut() = Some(test_state()));
+        let median_of_all_rewards = |fee_history: &FeeHistory| {
+            estimate_transaction_fee(fee_history)
+                .unwrap()
+                .max_priority_fee_per_gas
+        };
+        // rewards requested with a single percentile, before the config was set
+        let single_percentile_fee_history = FeeHistory {
+            reward: bsc_fee_history()
+                .reward
+                .into_iter()
+                .map(|block_rewards| block_rewards.into_iter().take(1).collect())
+                .collect(),
+            ..bsc_fee_history()
+        };
+
+        for (fee_history, index) in [(bsc_fee_history(), 3), (single_percentile_fee_history, 2)] {
+            let estimate =
+                estimate_transaction_fee_with_config(&fee_history, &config(index, 200)).unwrap();
+
+            assert_eq!(
+                estimate.max_priority_fee_per_gas,
+                median_of_all_rewards(&fee_history)
+            );
+        }
+    }
+
+    #[test]
+    fn should_validate_config() {
+        assert_eq!(config(0, 100).validate(), Ok(()));
+        assert_eq!(config(2, 300).validate(), Ok(()));
+        assert!(config(0, 99).validate().is_err());
+        assert!(config(0, 301).validate().is_err());
+        assert!(config(3, 200).validate().is_err());
+    }
+}
+
 fn test_state() -> State {
     let mut erc20_tokens = DedupMultiKeyMap::default();
     erc20_tokens
@@ -1201,7 +1517,7 @@ fn test_state() -> State {
             chain_code: vec![2; 32],
         }),
         native_minimum_withdrawal_amount: Wei::new(1_000_000_000_000_000),
-        block_height: BlockTag::Finalized,
+        confirmation_policy: ConfirmationPolicy::BlockTag(BlockTag::Finalized),
         first_scraped_block_number: BlockNumber::new(1_000_001),
         last_scraped_block_number: BlockNumber::new(1_000_000),
         last_observed_block_number: Some(BlockNumber::new(2_000_000)),
@@ -1236,36 +1552,70 @@ fn test_state() -> State {
         invalid_events: btreemap! {
             source("0x05c6ec45699c9a6a4b1a4ea2058b0cee852ea2f19b18fb8313c04bf8156efde4", 11) => InvalidEventReason::InvalidEvent("failed to decode principal from bytes 0x00333c125dc9f41abaf2b8b85d49fdc7ff75b2a4000000000000000000000000".to_string()),
         },
+        removed_log_events: Default::default(),
         withdrawal_transactions: WithdrawalTransactions::new(0_u64.into()),
         pending_withdrawal_principals: Default::default(),
         active_tasks: Default::default(),
+        mints_in_flight: Default::default(),
+        reorged_mints_in_flight: Default::default(),
+        contract_destinations: Default::default(),
         native_balance: Default::default(),
         erc20_balances: Default::default(),
         skipped_blocks: Default::default(),
+        scraping_gaps: Default::default(),
+        last_manual_deposit_checks: Default::default(),
+        last_provider_disagreements: Default::default(),
         last_transaction_price_estimate: None,
+        last_transaction_price_estimate_block: None,
+        current_l1_fee: None,
+        total_ledger_call_cycles_spent: 0,
+        ecdsa_signing_calls: 0,
+        ecdsa_signing_cycles_spent: 0,
         evm_canister_id: "sosge-5iaaa-aaaag-alcla-cai".parse().unwrap(),
         erc20_tokens,
         native_symbol: ERC20TokenSymbol::new("icSepoliaETH".to_string()),
         native_ledger_transfer_fee: Wei::new(2_000_000_000_000_000),
         min_max_priority_fee_per_gas: WeiPerGas::new(1000),
+        gas_fee_config: None,
+        gas_limits: Default::default(),
         ledger_suite_manager_id: None,
         last_observed_block_time: None,
+        last_observed_block_timestamp: None,
+        last_scraped_block_timestamp: None,
+        last_log_scraping_request_time: 0,
         withdrawal_native_fee: None,
         events_to_release: Default::default(),
         released_events: Default::default(),
         quarantined_releases: Default::default(),
         icrc_balances: Default::default(),
         wrapped_icrc_tokens,
+        wrapped_icrc_deployments: Default::default(),
+        wrapped_icrc_metadata: Default::default(),
         dex_canister_id: None,
         twin_usdc_info: None,
         swap_contract_address: None,
-        is_swapping_active: false,
+        feature_flags: Default::default(),
+        fee_collector: None,
+        collected_fees: Default::default(),
+        pending_fee_withdrawals: Default::default(),
+        refund_deadline_window_secs: None,
+        fee_cap_override: None,
+        last_fee_cap_alert: None,
         swap_events_to_mint_to_appic_dex: Default::default(),
         last_native_token_usd_price_estimate: None,
         canister_signing_fee_twin_usdc_amount: None,
         gas_tank: GasTank::default(),
         next_swap_ledger_burn_index: None,
         quarantined_dex_orders: Default::default(),
+        rate_limited_providers: Default::default(),
+        non_standard_erc20_tokens: Default::default(),
+        known_erc20_contracts: Default::default(),
+        token_volumes: Default::default(),
+        daily_stats: Default::default(),
+        earliest_unpruned_block: Default::default(),
+        erc20_original_symbols: Default::default(),
+        native_ledger_metadata: None,
+        onchain_approvals: Default::default(),
         swap_events_to_be_notified: Default::default(),
         notified_swap_events: Default::default(),
     }
@@ -8,23 +8,27 @@ use icrc_ledger_types::{
 
 use crate::{
     candid_types::{
-        wrapped_icrc::{RetrieveWrapIcrcRequest, WrapIcrcArg, WrapIcrcError},
+        wrapped_icrc::{RetrieveWrapIcrcRequest, WrapIcrcArg, WrapIcrcError, WrappedIcrcTokenInfo},
         MinterInfo, RetrieveWithdrawalStatus, TxFinalizedStatus,
     },
     tests::{
-        minter_flow_tets::mock_rpc_https_responses::{
-            generate_and_submit_mock_http_response, MOCK_BLOCK_NUMBER, MOCK_FEE_HISTORY_RESPONSE,
-            MOCK_HIGHER_BLOCK_NUMBER, MOCK_ICRC_RELEASE_REUQEST, MOCK_MINT_WRAPPED_ICRC_RECEIPT,
-            MOCK_SEND_TRANSACTION_SUCCESS, MOCK_TRANSACTION_COUNT_FINALIZED,
-            MOCK_TRANSACTION_COUNT_LATEST, MOCK_WRAPPED_ICRC_DEPLOYED_AND_DEPOSIT,
+        minter_flow_tets::{
+            http_requests_with_method,
+            mock_rpc_https_responses::{
+                generate_and_submit_mock_http_response, submit_last_scraped_block_header,
+                MOCK_BLOCK_NUMBER, MOCK_FEE_HISTORY_RESPONSE, MOCK_HIGHER_BLOCK_NUMBER,
+                MOCK_ICRC_RELEASE_REUQEST, MOCK_MINT_WRAPPED_ICRC_RECEIPT,
+                MOCK_SEND_TRANSACTION_SUCCESS, MOCK_TRANSACTION_COUNT_FINALIZED,
+                MOCK_TRANSACTION_COUNT_LATEST, MOCK_WRAPPED_ICRC_DEPLOYED_AND_DEPOSIT,
+            },
         },
         pocket_ic_helpers::{
-            create_pic, five_ticks, icp_principal,
+            create_pic, decode_wasm_result, five_ticks, icp_principal,
             initialize_minter::create_and_install_minter_plus_dependency_canisters,
             minter_principal, native_ledger_principal, query_call, update_call,
         },
     },
-    SCRAPING_CONTRACT_LOGS_INTERVAL,
+    SCRAPING_CONTRACT_LOGS_INTERVAL, WRAPPED_ICRC_METADATA_REFRESH_INTERVAL,
 };
 
 #[test]
@@ -87,6 +91,11 @@ fn should_release_and_lock() {
     five_ticks(&pic);
     five_ticks(&pic);
 
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
+    five_ticks(&pic);
+
     // Check Native deposit
     let balance = query_call::<Account, Nat>(
         &pic,
@@ -163,6 +172,21 @@ fn should_release_and_lock() {
     five_ticks(&pic);
     five_ticks(&pic);
 
+    let user = Account {
+        owner: Principal::from_text(
+            "b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe",
+        )
+        .unwrap(),
+        subaccount: None,
+    };
+    let balances = || {
+        (
+            query_call::<Account, Nat>(&pic, native_ledger_principal(), "icrc1_balance_of", user),
+            query_call::<Account, Nat>(&pic, icp_principal(), "icrc1_balance_of", user),
+        )
+    };
+    let balances_before = balances();
+
     let _lock_result = update_call::<WrapIcrcArg, Result<RetrieveWrapIcrcRequest, WrapIcrcError>>(
         &pic,
         minter_principal(),
@@ -171,6 +195,7 @@ fn should_release_and_lock() {
             amount: Nat::from(1_000_000_000_u128),
             icrc_ledger_id: icp_principal(),
             recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+            max_native_fee: None,
         },
         Some(
             Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
@@ -178,6 +203,9 @@ fn should_release_and_lock() {
         ),
     );
 
+    // Native fee burnt for the wrap at the current gas estimate
+    let wrap_native_fee = balances_before.0.clone() - balances().0;
+
     five_ticks(&pic);
     five_ticks(&pic);
     five_ticks(&pic);
@@ -253,6 +281,7 @@ fn should_release_and_lock() {
             transaction_hash: "0x51a454c6e327aecd8fcf5c7db7a52e8df7119c9247db5e6c1c5f5eee3be794d1"
                 .to_string(),
             effective_transaction_fee: Some(Nat::from(63000000000000_u128)),
+            realized_amount_out: None,
         });
 
     assert_eq!(
@@ -260,6 +289,70 @@ fn should_release_and_lock() {
         expected_transaction_result
     );
 
+    // Once the gas estimate was raised, a wrap bounded by the native fee of the previous one is
+    // rejected before burning any native fee or locking any ICRC token.
+    let balances_before = balances();
+    pic.advance_time(Duration::from_secs(11));
+
+    let message_id = pic
+        .submit_call(
+            minter_principal(),
+            user.owner,
+            "wrap_icrc",
+            candid::encode_one(WrapIcrcArg {
+                amount: Nat::from(1_000_000_000_u128),
+                icrc_ledger_id: icp_principal(),
+                recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+                max_native_fee: Some(wrap_native_fee.clone()),
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+    five_ticks(&pic);
+
+    // eth_getBlockByNumber polling for a block newer than the one of the last estimate
+    let canister_http_requests = pic.get_canister_http();
+    let new_block = MOCK_HIGHER_BLOCK_NUMBER.replace("0x2BD103A", "0x2BE4EB7");
+    for index in http_requests_with_method(&canister_http_requests, "eth_getBlockByNumber") {
+        generate_and_submit_mock_http_response(&pic, &canister_http_requests, index, &new_block);
+    }
+
+    five_ticks(&pic);
+
+    // eth_feeHistory with ten times the priority fee
+    let canister_http_requests = pic.get_canister_http();
+    let raised_fee_history = MOCK_FEE_HISTORY_RESPONSE.replace("0xb2d05e00", "0x6fc23ac00");
+    for index in http_requests_with_method(&canister_http_requests, "eth_feeHistory") {
+        generate_and_submit_mock_http_response(
+            &pic,
+            &canister_http_requests,
+            index,
+            &raised_fee_history,
+        );
+    }
+
+    let above_maximum_result =
+        decode_wasm_result::<Result<RetrieveWrapIcrcRequest, WrapIcrcError>>(
+            pic.await_call(message_id),
+        )
+        .unwrap();
+
+    match above_maximum_result {
+        Err(WrapIcrcError::NativeFeeAboveMaximum {
+            native_fee,
+            maximum,
+        }) => {
+            assert_eq!(maximum, wrap_native_fee);
+            assert!(native_fee > maximum);
+        }
+        result => panic!("expected NativeFeeAboveMaximum, got {result:?}"),
+    }
+
+    five_ticks(&pic);
+
+    assert_eq!(balances(), balances_before);
+
     pic.advance_time(SCRAPING_CONTRACT_LOGS_INTERVAL);
 
     five_ticks(&pic);
@@ -300,4 +393,30 @@ fn should_release_and_lock() {
     five_ticks(&pic);
 
     let minter_info = query_call::<_, MinterInfo>(&pic, minter_principal(), "get_minter_info", ());
+
+    // The metadata of the base ledger is queried with the next refresh.
+    pic.advance_time(WRAPPED_ICRC_METADATA_REFRESH_INTERVAL);
+    five_ticks(&pic);
+
+    let wrapped_icrc_tokens = query_call::<_, Vec<WrappedIcrcTokenInfo>>(
+        &pic,
+        minter_principal(),
+        "get_wrapped_icrc_tokens",
+        (),
+    );
+    assert_eq!(wrapped_icrc_tokens.len(), 1);
+    let wrapped_icp = &wrapped_icrc_tokens[0];
+    assert_eq!(wrapped_icp.base_token, icp_principal());
+    assert_eq!(
+        wrapped_icp.deployment_transaction_hash,
+        Some("0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef".to_string())
+    );
+    assert_eq!(
+        wrapped_icp.deployment_block_number,
+        Some(Nat::from(0x1a2b3c_u64))
+    );
+    assert!(wrapped_icp.symbol.is_some());
+    assert_eq!(wrapped_icp.decimals, Some(8));
+    assert!(wrapped_icp.transfer_fee.is_some());
+    assert!(wrapped_icp.active);
 }
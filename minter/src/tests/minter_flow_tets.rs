@@ -1,12 +1,24 @@
 use crate::{
     candid_types::{
         chain_data::ChainData,
-        withdraw_erc20::{RetrieveErc20Request, WithdrawErc20Arg, WithdrawErc20Error},
-        withdraw_native::{WithdrawalArg, WithdrawalError},
-        ActivateSwapReqest, DepositStatus, Eip1559TransactionPrice, MinterInfo,
-        RequestScrapingError, RetrieveNativeRequest, RetrieveWithdrawalStatus, TxFinalizedStatus,
+        dex_orders::{DexOrderArgs, DexOrderError, DexOrderQuote},
+        events::{Event, EventPayload, GetEventsArg, GetEventsResult},
+        withdraw_erc20::{LedgerError, RetrieveErc20Request, WithdrawErc20Arg, WithdrawErc20Error},
+        withdraw_native::{CancelError, FinalizedWithdrawal, WithdrawalArg, WithdrawalError},
+        ActivateSwapError, ActivateSwapReqest, BackfillScrapeError, BackfillSummary,
+        CandidFeatureFlag, CandidOnchainApproval, DepositStatus, DrainStatus,
+        Eip1559TransactionPrice, MinterHealth, MinterInfo, RequestScrapingError,
+        RetrieveNativeRequest, RetrieveWithdrawalStatus, RevokeApprovalError, ScrapingGap,
+        SetFeatureFlagError, TxFinalizedStatus,
     },
     evm_config::EvmNetwork,
+    icrc_21::{
+        ConsentMessage, ConsentMessageMetadata, ConsentMessageRequest, ConsentMessageResponse,
+        ConsentMessageSpec, DeviceSpec,
+    },
+    icrc_client::runtime::INTER_CANISTER_CALL_BASE_COST,
+    lifecycle::{MinterArg, UpgradeArg},
+    numeric::Erc20Value,
     tests::{
         lsm_types::{AddErc20Arg, AddErc20Error, Erc20Contract, LedgerInitArg, LedgerManagerInfo},
         minter_flow_tets::mock_rpc_https_responses::{
@@ -16,11 +28,12 @@ use crate::{
             five_ticks, icp_principal, lsm_principal, native_ledger_principal, update_call,
         },
     },
-    APPIC_CONTROLLER_PRINCIPAL, RPC_HELPER_PRINCIPAL, SCRAPING_CONTRACT_LOGS_INTERVAL,
+    APPIC_CONTROLLER_PRINCIPAL, MANUAL_DEPOSIT_CHECK_GLOBAL_INTERVAL,
+    MANUAL_DEPOSIT_CHECK_INTERVAL, RPC_HELPER_PRINCIPAL, SCRAPING_CONTRACT_LOGS_INTERVAL,
 };
 use candid::{Nat, Principal};
 use evm_rpc_client::eth_types::Address;
-use std::{str::FromStr, time::Duration};
+use std::{collections::BTreeSet, str::FromStr, time::Duration};
 
 use icrc_ledger_types::icrc1::{
     account::Account,
@@ -29,17 +42,20 @@ use icrc_ledger_types::icrc1::{
 use icrc_ledger_types::icrc2::approve::{ApproveArgs, ApproveError};
 
 use super::pocket_ic_helpers::{
-    create_pic, initialize_minter::create_and_install_minter_plus_dependency_canisters,
-    minter_principal, query_call,
+    create_pic, decode_wasm_result,
+    initialize_minter::create_and_install_minter_plus_dependency_canisters, minter_principal,
+    query_call, upgrade_minter_canister,
 };
 
 use mock_rpc_https_responses::{
-    generate_and_submit_mock_http_response, MOCK_BLOCK_NUMBER, MOCK_FEE_HISTORY_RESPONSE,
-    MOCK_GET_LOGS, MOCK_GET_LOGS_ERC20, MOCK_HIGHER_BLOCK_NUMBER,
-    MOCK_SECOND_NATIVE_TRANSACTION_RECEIPT, MOCK_SEND_TRANSACTION_ERROR,
+    generate_and_submit_mock_http_response, submit_last_scraped_block_header, MOCK_BLOCK_NUMBER,
+    MOCK_FEE_HISTORY_RESPONSE, MOCK_GET_CODE_EOA, MOCK_GET_LOGS, MOCK_GET_LOGS_EMPTY,
+    MOCK_GET_LOGS_ERC20, MOCK_GET_LOGS_MULTIPLE_DEPOSITS, MOCK_GET_LOGS_RESULT_SET_TOO_LARGE,
+    MOCK_HIGHER_BLOCK_NUMBER, MOCK_SECOND_NATIVE_TRANSACTION_RECEIPT, MOCK_SEND_TRANSACTION_ERROR,
     MOCK_SEND_TRANSACTION_SUCCESS, MOCK_TRANSACTION_COUNT_FINALIZED,
     MOCK_TRANSACTION_COUNT_FINALIZED_ERC20, MOCK_TRANSACTION_COUNT_LATEST,
     MOCK_TRANSACTION_COUNT_LATEST_ERC20, MOCK_TRANSACTION_RECEIPT, MOCK_TRANSACTION_RECEIPT_ERC20,
+    MOCK_TRANSACTION_RECEIPT_NOT_FOUND,
 };
 
 #[test]
@@ -75,6 +91,7 @@ fn should_get_estimated_eip1559_transaction_price() {
         max_fee_per_gas: Nat::from(3000000000_u64),
         max_priority_fee_per_gas: Nat::from(3000000000_u64),
         max_transaction_fee: Nat::from(63000000000000_u64),
+        min_priority_fee_per_gas: Some(Nat::from(3_000_000_000_u64)),
         timestamp: Some(1620328630000000061_u64),
     };
     assert_eq!(expected_price.gas_limit, transaction_price.gas_limit);
@@ -91,6 +108,10 @@ fn should_get_estimated_eip1559_transaction_price() {
         expected_price.max_transaction_fee,
         transaction_price.max_transaction_fee
     );
+    assert_eq!(
+        expected_price.min_priority_fee_per_gas,
+        transaction_price.min_priority_fee_per_gas
+    );
 }
 
 // if there is a block scrape request that is not scraped yet after chain data update, in case the
@@ -140,11 +161,25 @@ fn should_start_log_scraping_after_chain_data_update() {
 
     five_ticks(&pic);
 
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
+    five_ticks(&pic);
+
     let minter_info = query_call::<(), MinterInfo>(&pic, minter_principal(), "get_minter_info", ());
     assert_eq!(
         minter_info.last_scraped_block_number,
         Some(Nat::from(45944644_u128))
     );
+    // MOCK_BLOCK_NUMBER answered both eth_getBlockByNumber requests
+    assert_eq!(
+        minter_info.last_observed_block_timestamp,
+        Some(Nat::from(0x6744b156_u64))
+    );
+    assert_eq!(
+        minter_info.last_scraped_block_timestamp,
+        Some(Nat::from(0x6744b156_u64))
+    );
 
     // request a block to be scraped
     update_call::<Nat, ()>(
@@ -165,6 +200,7 @@ fn should_start_log_scraping_after_chain_data_update() {
             latest_block_number: Nat::from(45944646_u128),
             fee_history: MOCK_BSC_FEE_HISTORY_INNER.to_string(),
             native_token_usd_price: None,
+            latest_block_timestamp: None,
         },
         Some(Principal::from_text(RPC_HELPER_PRINCIPAL).unwrap()),
     );
@@ -176,6 +212,163 @@ fn should_start_log_scraping_after_chain_data_update() {
     assert_eq!(canister_http_requests.len(), 1);
 }
 
+#[test]
+fn should_bisect_block_range_when_get_logs_result_set_too_large() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    // [0] is for eth_feeHistory
+    // [1] is for eth_getBlockByNumber
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_FEE_HISTORY_RESPONSE,
+    );
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 1, MOCK_BLOCK_NUMBER);
+
+    five_ticks(&pic);
+
+    // The whole block range is refused by the provider
+    let canister_http_requests = pic.get_canister_http();
+    let (first_from_block, first_to_block) = get_logs_block_range(&canister_http_requests[0]);
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_GET_LOGS_RESULT_SET_TOO_LARGE,
+    );
+
+    five_ticks(&pic);
+
+    // The lower half is requested next, without retrying the whole range
+    let canister_http_requests = pic.get_canister_http();
+    assert_eq!(canister_http_requests.len(), 1);
+    let (lower_from_block, lower_to_block) = get_logs_block_range(&canister_http_requests[0]);
+    assert_eq!(lower_from_block, first_from_block);
+    assert!(lower_to_block < first_to_block);
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS_EMPTY);
+
+    five_ticks(&pic);
+
+    // Then the upper half
+    let canister_http_requests = pic.get_canister_http();
+    assert_eq!(canister_http_requests.len(), 1);
+    let (upper_from_block, upper_to_block) = get_logs_block_range(&canister_http_requests[0]);
+    assert_eq!(upper_from_block, lower_to_block + 1);
+    assert_eq!(upper_to_block, first_to_block);
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS_EMPTY);
+
+    five_ticks(&pic);
+
+    let minter_info = query_call::<(), MinterInfo>(&pic, minter_principal(), "get_minter_info", ());
+    assert_eq!(
+        minter_info.last_scraped_block_number,
+        Some(Nat::from(first_to_block))
+    );
+}
+
+#[test]
+fn should_retry_rate_limited_get_logs_without_moving_past_the_range() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    // [0] is for eth_feeHistory
+    // [1] is for eth_getBlockByNumber
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_FEE_HISTORY_RESPONSE,
+    );
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 1, MOCK_BLOCK_NUMBER);
+
+    five_ticks(&pic);
+
+    let last_scraped_block_number =
+        query_call::<(), MinterInfo>(&pic, minter_principal(), "get_minter_info", ())
+            .last_scraped_block_number;
+
+    // The provider answers the range with HTTP 429, which is not retried within the scraping
+    let canister_http_requests = pic.get_canister_http();
+    let block_range = get_logs_block_range(&canister_http_requests[0]);
+    submit_too_many_requests_response(&pic, &canister_http_requests[0]);
+
+    five_ticks(&pic);
+
+    assert!(http_requests_with_method(&pic.get_canister_http(), "eth_getLogs").is_empty());
+
+    let minter_info = query_call::<(), MinterInfo>(&pic, minter_principal(), "get_minter_info", ());
+    assert_eq!(
+        minter_info.last_scraped_block_number,
+        last_scraped_block_number
+    );
+    let scraping_gaps =
+        query_call::<(), Vec<ScrapingGap>>(&pic, minter_principal(), "get_scraping_gaps", ());
+    assert_eq!(scraping_gaps, vec![]);
+
+    // The same range is requested again at the next scraping, after the block number
+    // requests of the failed and of the next scraping are answered
+    pic.advance_time(SCRAPING_CONTRACT_LOGS_INTERVAL);
+    let mut get_logs_request = None;
+    for _ in 0..3 {
+        five_ticks(&pic);
+        let canister_http_requests = pic.get_canister_http();
+        if let Some(index) =
+            http_requests_with_method(&canister_http_requests, "eth_getLogs").first()
+        {
+            get_logs_request = Some(canister_http_requests[*index].clone());
+            break;
+        }
+        for index in http_requests_with_method(&canister_http_requests, "eth_getBlockByNumber") {
+            generate_and_submit_mock_http_response(
+                &pic,
+                &canister_http_requests,
+                index,
+                MOCK_BLOCK_NUMBER,
+            );
+        }
+    }
+    let (from_block, _to_block) = get_logs_block_range(&get_logs_request.unwrap());
+    assert_eq!(from_block, block_range.0);
+}
+
+// Answers with HTTP 429 and a Retry-After delay too long to wait for within the same call,
+// so that the minter receives the rate limiting error right away.
+fn submit_too_many_requests_response(
+    pic: &pocket_ic::PocketIc,
+    request: &pocket_ic::common::rest::CanisterHttpRequest,
+) {
+    use pocket_ic::common::rest::{
+        CanisterHttpHeader, CanisterHttpReply, CanisterHttpResponse, MockCanisterHttpResponse,
+    };
+    pic.mock_canister_http_response(MockCanisterHttpResponse {
+        subnet_id: request.subnet_id,
+        request_id: request.request_id,
+        response: CanisterHttpResponse::CanisterHttpReply(CanisterHttpReply {
+            status: 429,
+            headers: vec![CanisterHttpHeader {
+                name: "Retry-After".to_string(),
+                value: "3600".to_string(),
+            }],
+            body: br#"{"error":"Too Many Requests"}"#.to_vec(),
+        }),
+        additional_responses: vec![],
+    });
+}
+
+fn get_logs_block_range(request: &pocket_ic::common::rest::CanisterHttpRequest) -> (u64, u64) {
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(body["method"], "eth_getLogs");
+    let block = |key: &str| {
+        let hex = body["params"][0][key].as_str().unwrap();
+        u64::from_str_radix(hex.trim_start_matches("0x"), 16).unwrap()
+    };
+    (block("fromBlock"), block("toBlock"))
+}
+
 #[test]
 fn should_deposit_and_withdrawal_native() {
     let pic = create_pic();
@@ -220,6 +413,11 @@ fn should_deposit_and_withdrawal_native() {
 
     five_ticks(&pic);
 
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
+    five_ticks(&pic);
+
     // Check deposit
     // Based on the logs there should be 100_000_000_000_000_000 - deposit fees(50_000_000_000_000_u64)= 99_950_000_000_000_000 icBNB minted for Native to b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe
     let balance = query_call::<Account, Nat>(
@@ -295,6 +493,8 @@ fn should_deposit_and_withdrawal_native() {
         WithdrawalArg {
             amount: Nat::from(99_990_000_000_000_000_u128),
             recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+            created_at_time: None,
+            allow_contract_destination: None,
         },
         Some(
             Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
@@ -308,6 +508,12 @@ fn should_deposit_and_withdrawal_native() {
     // Transfer
     assert_eq!(withdrawal_request_result.block_index, Nat::from(2_u64));
 
+    // The burn is the only ledger call accounted for so far
+    let minter_info = query_call::<(), MinterInfo>(&pic, minter_principal(), "get_minter_info", ());
+    let ledger_call_cycles = minter_info.total_ledger_call_cycles_spent.unwrap();
+    assert!(ledger_call_cycles > Nat::from(INTER_CANISTER_CALL_BASE_COST));
+    assert!(ledger_call_cycles < Nat::from(2 * INTER_CANISTER_CALL_BASE_COST));
+
     five_ticks(&pic);
     five_ticks(&pic);
 
@@ -335,6 +541,11 @@ fn should_deposit_and_withdrawal_native() {
         MOCK_TRANSACTION_COUNT_LATEST,
     );
 
+    five_ticks(&pic);
+
+    // The destination is checked for code once the transaction is created
+    submit_mock_http_responses_with_method(&pic, "eth_getCode", MOCK_GET_CODE_EOA);
+
     five_ticks(&pic);
     five_ticks(&pic);
 
@@ -399,6 +610,7 @@ fn should_deposit_and_withdrawal_native() {
             transaction_hash: "0x23e4ac0e4bde9f2c12a3906d7145769a52d96456fca38f3de399a1c0038309fb"
                 .to_string(),
             effective_transaction_fee: Some(Nat::from(63000000000000_u128)),
+            realized_amount_out: None,
         });
 
     assert_eq!(
@@ -408,346 +620,1466 @@ fn should_deposit_and_withdrawal_native() {
 }
 
 #[test]
-fn should_not_deposit_twice() {
+fn should_drain_before_upgrade() {
     let pic = create_pic();
     create_and_install_minter_plus_dependency_canisters(&pic);
 
-    // The deposit http mock flow is as follow
-    // 1st Step: The mock response for get_blockbynumber is generated
-    // 2nd Step: The response for eth_feehistory resonse is generated afterwards,
-    // 3rd Step: The response for eth_getlogs response is generated,
-
-    // At this time there should be 2 http requests:
-    // [0] is for eth_getBlockByNumber
-    // [1] is for eth_feeHistory
+    // [0] is for eth_feeHistory
+    // [1] is for eth_getBlockByNumber
     let canister_http_requests = pic.get_canister_http();
-
-    // 1st Generating mock response for eth_feehistory
     generate_and_submit_mock_http_response(
         &pic,
         &canister_http_requests,
         0,
         MOCK_FEE_HISTORY_RESPONSE,
     );
-
-    // 2nd Generating mock response for eth_getBlockByNumber
     generate_and_submit_mock_http_response(&pic, &canister_http_requests, 1, MOCK_BLOCK_NUMBER);
 
     five_ticks(&pic);
 
-    // 3rd generating mock response for eth_getLogs
-    // At this time there should be 2 http requests:
-    // [0] is for public_node eth_getLogs
-    // [1] is for ankr eth_getLogs
+    // public_node eth_getLogs
     let canister_http_requests = pic.get_canister_http();
-
-    // public_node mock submission
     generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS);
 
     five_ticks(&pic);
 
-    // There should be a gap of at least one minute between each log scraping so we advance time for 1 min
-    pic.advance_time(Duration::from_secs(1 * 60));
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
 
-    // Requesting for another log_scrapping
-    let request_result = update_call::<(), Result<(), RequestScrapingError>>(
+    five_ticks(&pic);
+
+    let user =
+        Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
+            .unwrap();
+    let controller = Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap();
+
+    let _approve_result = update_call::<ApproveArgs, Result<Nat, ApproveError>>(
+        &pic,
+        native_ledger_principal(),
+        "icrc2_approve",
+        ApproveArgs {
+            from_subaccount: None,
+            spender: Account {
+                owner: minter_principal(),
+                subaccount: None,
+            },
+            amount: Nat::from(99_990_000_000_000_000_u128),
+            expected_allowance: None,
+            expires_at: None,
+            fee: None,
+            memo: None,
+            created_at_time: None,
+        },
+        Some(user),
+    )
+    .unwrap();
+
+    five_ticks(&pic);
+
+    let withdraw = || {
+        update_call::<WithdrawalArg, Result<RetrieveNativeRequest, WithdrawalError>>(
+            &pic,
+            minter_principal(),
+            "withdraw_native_token",
+            WithdrawalArg {
+                amount: Nat::from(99_990_000_000_000_000_u128),
+                recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+                created_at_time: None,
+                allow_contract_destination: None,
+            },
+            Some(user),
+        )
+        .unwrap()
+    };
+    let drain_status =
+        || query_call::<(), DrainStatus>(&pic, minter_principal(), "drain_status", ());
+
+    let withdrawal = withdraw().expect("withdrawal should be accepted before draining");
+    assert_eq!(withdrawal.block_index, Nat::from(2_u64));
+
+    // The transaction of the withdrawal was not sent yet
+    let in_flight_count = update_call::<(), u64>(
         &pic,
         minter_principal(),
-        "request_scraping_logs",
+        "prepare_upgrade",
         (),
-        Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
+        Some(controller),
     );
+    assert!(in_flight_count >= 1);
 
-    assert_eq!(request_result, Ok(()));
+    assert_eq!(
+        withdraw(),
+        Err(WithdrawalError::TemporarilyUnavailable(
+            "Minter is draining before an upgrade, try again later".to_string()
+        ))
+    );
+    assert_eq!(
+        update_call::<DexOrderArgs, Result<DexOrderQuote, DexOrderError>>(
+            &pic,
+            minter_principal(),
+            "validate_dex_order",
+            DexOrderArgs {
+                tx_id: "draining".to_string(),
+                amount_in: Nat::from(1_000_000_u64),
+                min_amount_out: Nat::from(1_u8),
+                commands: vec![],
+                commands_data: vec![],
+                max_gas_fee_usd: None,
+                signing_fee: None,
+                gas_limit: Nat::from(500_000_u64),
+                deadline: Nat::from(u32::MAX),
+                recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+                erc20_ledger_burn_index: Nat::from(0_u8),
+                is_refund: false,
+            },
+            None,
+        ),
+        Err(DexOrderError::TemporarilyUnavailable(
+            "Minter is draining before an upgrade, try again later".to_string()
+        ))
+    );
+    let status = drain_status();
+    assert!(status.draining);
+    assert!(!status.safe_to_stop);
 
+    // The pending withdrawal is still processed while draining
+    five_ticks(&pic);
+    five_ticks(&pic);
     five_ticks(&pic);
 
-    // After requesting one more time there should be another log scraping request which means we have to
-    // follow the same steps but this time we will mock http requests with incorrect responses
-    // to check if minter, mints the same request twice or not.
-
-    // At this time there should be 1 http requests:
-    // [0] is for eth_getBlockByNumber
     let canister_http_requests = pic.get_canister_http();
-
-    // 1st Generating mock response for eth_getBlockByNumber
     generate_and_submit_mock_http_response(
         &pic,
         &canister_http_requests,
         0,
-        MOCK_HIGHER_BLOCK_NUMBER,
+        MOCK_TRANSACTION_COUNT_LATEST,
     );
 
     five_ticks(&pic);
 
-    // 3rd generating mock response for eth_getLogs
-    // At this time there should be 2 http requests:
-    // [0] is for public_node eth_getLogs
-    // [1] is for ankr eth_getLogs
+    // The destination is checked for code once the transaction is created
+    submit_mock_http_responses_with_method(&pic, "eth_getCode", MOCK_GET_CODE_EOA);
+
+    five_ticks(&pic);
+    five_ticks(&pic);
+
     let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_SEND_TRANSACTION_SUCCESS,
+    );
 
-    // Generating the same mock eth_getlogs response and the minter should detect that these responses are not correct
-    // public_node mock submission
-    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS);
+    five_ticks(&pic);
+
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_TRANSACTION_COUNT_FINALIZED,
+    );
 
     five_ticks(&pic);
 
-    // Check balance
-    // there should only be 100000000000000000 icBNB minted for Native to b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe
-    // despite receiving two mint events.
-    let balance = query_call::<Account, Nat>(
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
         &pic,
-        native_ledger_principal(),
-        "icrc1_balance_of",
-        Account {
-            owner: Principal::from_text(
-                "b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe",
-            )
-            .unwrap(),
-            subaccount: None,
-        },
+        &canister_http_requests,
+        0,
+        MOCK_TRANSACTION_RECEIPT,
     );
 
-    assert_eq!(balance, Nat::from(100000000000000000_u128));
+    five_ticks(&pic);
+
+    assert!(matches!(
+        update_call::<u64, RetrieveWithdrawalStatus>(
+            &pic,
+            minter_principal(),
+            "retrieve_withdrawal_status",
+            2_u64,
+            None,
+        ),
+        RetrieveWithdrawalStatus::TxFinalized(TxFinalizedStatus::Success { .. })
+    ));
+    assert_eq!(
+        drain_status(),
+        DrainStatus {
+            draining: true,
+            in_flight_count: 0,
+            safe_to_stop: true,
+        }
+    );
 }
 
 #[test]
-fn should_deposit_and_withdrawal_erc20() {
+fn should_reconcile_transaction_sent_during_upgrade() {
     let pic = create_pic();
     create_and_install_minter_plus_dependency_canisters(&pic);
 
-    // The deposit and withdrawal http mock flow is as follow
-    // 1st Step: The mock response for get_blockbynumber is generated
-    // 2nd Step: The response for eth_feehistory resonse is generated afterwards,
-    // so in the time of withdrawal transaction the eip1559 transaction price is available
-    // Not to forget that the price should be refreshed through a second call at the time
-    // 3rd Step: There should two mock responses be generated for eth_getlogs, one for ankr and the other one for public node
-    // 4th Step: After 10 min the response for eth_feehistory resonse is generated afterwards,
-    // 5th Step: There should two mock responses be generated for eth_getlogs, one for ankr and the other one for public node this time with deposit logs
-    // One for native and erc20
-    // 6th Step: The response for sendrawtransaction
-    // 7th Step: An http-outcall for getting the finalized transaction count.
-    // 8th Step: and in the end get transaction receipt should be generate
-
-    // At this time there should be 2 http requests:
-    // [0] is for eth_getBlockByNumber
-    // [1] is for eth_feeHistory
-
+    // [0] is for eth_feeHistory
+    // [1] is for eth_getBlockByNumber
     let canister_http_requests = pic.get_canister_http();
-
-    // 1st Generating mock response for eth_feehistory
     generate_and_submit_mock_http_response(
         &pic,
         &canister_http_requests,
         0,
         MOCK_FEE_HISTORY_RESPONSE,
     );
-
-    // 2nd Generating mock response for eth_getBlockByNumber
     generate_and_submit_mock_http_response(&pic, &canister_http_requests, 1, MOCK_BLOCK_NUMBER);
 
-    five_ticks(&pic);
-    five_ticks(&pic);
     five_ticks(&pic);
 
-    // 3rd generating mock response for eth_getLogs
-    // At this time there should be 2 http requests:
-    // [0] is for public_node eth_getLogs
-    // [1] is for ankr eth_getLogs
+    // public_node eth_getLogs
     let canister_http_requests = pic.get_canister_http();
-
-    // public_node mock submission
     generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS);
 
     five_ticks(&pic);
-    five_ticks(&pic);
-    five_ticks(&pic);
-    five_ticks(&pic);
-    five_ticks(&pic);
+
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
     five_ticks(&pic);
 
-    // Check Native deposit
-    let balance = query_call::<Account, Nat>(
-        &pic,
-        native_ledger_principal(),
-        "icrc1_balance_of",
-        Account {
-            owner: Principal::from_text(
-                "b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe",
-            )
-            .unwrap(),
-            subaccount: None,
-        },
-    );
-
-    assert_eq!(balance, Nat::from(100_000_000_000_000_000_u128));
-
-    // Calling icrc2_approve and giving the permission to lsm for taking funds from users principal
+    let user =
+        Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
+            .unwrap();
     let _approve_result = update_call::<ApproveArgs, Result<Nat, ApproveError>>(
         &pic,
-        icp_principal(),
+        native_ledger_principal(),
         "icrc2_approve",
         ApproveArgs {
             from_subaccount: None,
             spender: Account {
-                owner: lsm_principal(),
+                owner: minter_principal(),
                 subaccount: None,
             },
-            amount: Nat::from(
-                2_500_000_000_u128, // Users balance - approval fee => 99_950_000_000_000_000_u128 - 10_000_000_000_000_u128
-            ),
+            amount: Nat::from(99_990_000_000_000_000_u128),
             expected_allowance: None,
             expires_at: None,
             fee: None,
             memo: None,
             created_at_time: None,
         },
-        Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
+        Some(user),
     )
     .unwrap();
 
     five_ticks(&pic);
 
-    // Add icUSDC to lsm
-    update_call::<AddErc20Arg, Result<(), AddErc20Error>>(
+    let withdrawal = update_call::<WithdrawalArg, Result<RetrieveNativeRequest, WithdrawalError>>(
         &pic,
-        lsm_principal(),
-        "add_erc20_ls",
-        AddErc20Arg {
-            contract: Erc20Contract {
-                chain_id: EvmNetwork::BSC.chain_id().into(),
-                address: "0x84b9B910527Ad5C03A9Ca831909E21e236EA7b06".to_string(),
-            },
-            ledger_init_arg: LedgerInitArg {
-                transfer_fee: Nat::from(100_000_000_000_000_u128),
-                decimals: 18,
-                token_name: "Chain Link on icp".to_string(),
-                token_symbol: "icLINK".to_string(),
-                token_logo: "".to_string(),
-            },
+        minter_principal(),
+        "withdraw_native_token",
+        WithdrawalArg {
+            amount: Nat::from(99_990_000_000_000_000_u128),
+            recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+            created_at_time: None,
+            allow_contract_destination: None,
         },
-        Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
+        Some(user),
     )
-    .unwrap();
-
-    five_ticks(&pic);
+    .unwrap()
+    .expect("withdrawal should be accepted");
+    assert_eq!(withdrawal.block_index, Nat::from(2_u64));
 
-    // Advance time for 1 hour.
-    pic.advance_time(Duration::from_secs(1 * 60));
-
-    five_ticks(&pic);
-    five_ticks(&pic);
-    five_ticks(&pic);
-    five_ticks(&pic);
     five_ticks(&pic);
     five_ticks(&pic);
     five_ticks(&pic);
+
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_TRANSACTION_COUNT_LATEST,
+    );
+
     five_ticks(&pic);
+
+    // The destination is checked for code once the transaction is created
+    submit_mock_http_responses_with_method(&pic, "eth_getCode", MOCK_GET_CODE_EOA);
+
     five_ticks(&pic);
     five_ticks(&pic);
+
+    // The minter is upgraded while awaiting eth_sendRawTransaction
+    let sent_raw_transactions = pending_raw_transactions(&pic);
+    assert!(!sent_raw_transactions.is_empty());
+    upgrade_minter_canister(
+        &pic,
+        minter_principal(),
+        candid::encode_one(MinterArg::UpgradeArg(UpgradeArg::default())).unwrap(),
+    );
+
     five_ticks(&pic);
+
+    // The gas fee estimate is not kept across upgrades
+    submit_mock_http_responses_with_method(&pic, "eth_feeHistory", MOCK_FEE_HISTORY_RESPONSE);
+
     five_ticks(&pic);
+
+    // The transaction count of the minter is not past the nonce of the transaction
+    submit_mock_http_responses_with_method(
+        &pic,
+        "eth_getTransactionCount",
+        MOCK_TRANSACTION_COUNT_LATEST,
+    );
+
     five_ticks(&pic);
+
+    // and the transaction has no receipt, so that it is sent again
+    submit_mock_http_responses_with_method(
+        &pic,
+        "eth_getTransactionReceipt",
+        MOCK_TRANSACTION_RECEIPT_NOT_FOUND,
+    );
+
     five_ticks(&pic);
+
+    // The transaction sent again is the interrupted one, so that its nonce is used only once
+    let resent_raw_transactions = pending_raw_transactions(&pic);
+    assert!(!resent_raw_transactions.is_empty());
+    assert!(resent_raw_transactions
+        .iter()
+        .all(|raw_transaction| raw_transaction == &sent_raw_transactions[0]));
+    submit_mock_http_responses_with_method(
+        &pic,
+        "eth_sendRawTransaction",
+        MOCK_SEND_TRANSACTION_SUCCESS,
+    );
+
     five_ticks(&pic);
+
+    let events = query_call::<GetEventsArg, GetEventsResult>(
+        &pic,
+        minter_principal(),
+        "get_events",
+        GetEventsArg {
+            start: 0,
+            length: 100,
+        },
+    )
+    .events;
+    let attempts: Vec<_> = events
+        .iter()
+        .filter_map(|event| match &event.payload {
+            EventPayload::BroadcastAttempted {
+                transaction_hash,
+                nonce,
+                ..
+            } => Some((transaction_hash.clone(), nonce.clone())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(attempts.len(), 1);
+    assert_eq!(attempts[0].1, Nat::from(0_u8));
+    let outcomes: Vec<_> = events
+        .iter()
+        .filter_map(|event| match &event.payload {
+            EventPayload::BroadcastReconciled {
+                transaction_hash,
+                outcome,
+                ..
+            } => Some((transaction_hash.clone(), outcome.clone())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        outcomes,
+        vec![(attempts[0].0.clone(), "NotMined".to_string())]
+    );
+}
+
+/// Returns the raw transactions of the pending eth_sendRawTransaction HTTP outcalls.
+fn pending_raw_transactions(pic: &pocket_ic::PocketIc) -> Vec<String> {
+    pic.get_canister_http()
+        .iter()
+        .filter_map(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            (body["method"] == "eth_sendRawTransaction")
+                .then(|| body["params"][0].as_str().unwrap().to_string())
+        })
+        .collect()
+}
+
+/// Answers all the pending HTTP outcalls calling the JSON-RPC `method` with `response`.
+fn submit_mock_http_responses_with_method(pic: &pocket_ic::PocketIc, method: &str, response: &str) {
+    let canister_http_requests = pic.get_canister_http();
+    let indexes = http_requests_with_method(&canister_http_requests, method);
+    assert!(!indexes.is_empty(), "no pending {method} request");
+    for index in indexes {
+        generate_and_submit_mock_http_response(pic, &canister_http_requests, index, response);
+    }
+}
+
+#[test]
+fn should_set_feature_flags() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
     five_ticks(&pic);
+
+    let user =
+        Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
+            .unwrap();
+    let set_feature_flag = |flag: &str, enabled: bool| {
+        decode_wasm_result::<Result<(), SetFeatureFlagError>>(pic.update_call(
+            minter_principal(),
+            Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap(),
+            "set_feature_flag",
+            candid::encode_args((flag.to_string(), enabled)).unwrap(),
+        ))
+        .unwrap()
+    };
+    let feature_flags = || {
+        query_call::<(), Vec<CandidFeatureFlag>>(&pic, minter_principal(), "get_feature_flags", ())
+            .into_iter()
+            .map(|flag| (flag.flag, flag.enabled))
+            .collect::<Vec<_>>()
+    };
+    let withdraw = || {
+        update_call::<WithdrawalArg, Result<RetrieveNativeRequest, WithdrawalError>>(
+            &pic,
+            minter_principal(),
+            "withdraw_native_token",
+            WithdrawalArg {
+                amount: Nat::from(99_990_000_000_000_000_u128),
+                recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+                created_at_time: None,
+                allow_contract_destination: None,
+            },
+            Some(user),
+        )
+    };
+
+    assert_eq!(
+        feature_flags(),
+        vec![
+            ("swap_active".to_string(), false),
+            ("draining".to_string(), false)
+        ]
+    );
+    assert_eq!(
+        set_feature_flag("maintenance", true),
+        Err(SetFeatureFlagError::UnknownFlag {
+            flag: "maintenance".to_string(),
+            known_flags: vec!["swap_active".to_string(), "draining".to_string()],
+        })
+    );
+    assert_eq!(
+        set_feature_flag("swap_active", true),
+        Err(SetFeatureFlagError::SwapFeatureNotActivated)
+    );
+
+    set_feature_flag("draining", true).expect("draining should be enabled");
+
+    assert_eq!(
+        feature_flags(),
+        vec![
+            ("swap_active".to_string(), false),
+            ("draining".to_string(), true)
+        ]
+    );
+    assert_eq!(
+        withdraw(),
+        Err(WithdrawalError::TemporarilyUnavailable(
+            "Minter is draining before an upgrade, try again later".to_string()
+        ))
+    );
+    assert!(query_call::<(), DrainStatus>(&pic, minter_principal(), "drain_status", ()).draining);
+
+    set_feature_flag("draining", false).expect("draining should be disabled");
+
+    assert_ne!(
+        withdraw(),
+        Err(WithdrawalError::TemporarilyUnavailable(
+            "Minter is draining before an upgrade, try again later".to_string()
+        ))
+    );
+
+    let events = query_call::<GetEventsArg, GetEventsResult>(
+        &pic,
+        minter_principal(),
+        "get_events",
+        GetEventsArg {
+            start: 0,
+            length: 100,
+        },
+    )
+    .events;
+    let flag_changes: Vec<_> = events
+        .into_iter()
+        .filter_map(|event| match event.payload {
+            EventPayload::FeatureFlagChanged { flag, enabled } => Some((flag, enabled)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        flag_changes,
+        vec![
+            ("draining".to_string(), true),
+            ("draining".to_string(), false)
+        ]
+    );
+}
+
+#[test]
+fn should_deduplicate_native_withdrawal_with_same_created_at_time() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    // At this time there should be 2 http requests:
+    // [0] is for eth_getBlockByNumber
+    // [1] is for eth_feeHistory
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_FEE_HISTORY_RESPONSE,
+    );
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 1, MOCK_BLOCK_NUMBER);
+
     five_ticks(&pic);
+
+    // public_node eth_getLogs
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS);
+
     five_ticks(&pic);
+
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
     five_ticks(&pic);
+
+    let user =
+        Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
+            .unwrap();
+    let user_balance = || {
+        query_call::<Account, Nat>(
+            &pic,
+            native_ledger_principal(),
+            "icrc1_balance_of",
+            Account {
+                owner: user,
+                subaccount: None,
+            },
+        )
+    };
+
+    let _approve_result = update_call::<ApproveArgs, Result<Nat, ApproveError>>(
+        &pic,
+        native_ledger_principal(),
+        "icrc2_approve",
+        ApproveArgs {
+            from_subaccount: None,
+            spender: Account {
+                owner: minter_principal(),
+                subaccount: None,
+            },
+            amount: Nat::from(99_990_000_000_000_000_u128),
+            expected_allowance: None,
+            expires_at: None,
+            fee: None,
+            memo: None,
+            created_at_time: None,
+        },
+        Some(user),
+    )
+    .unwrap();
+
     five_ticks(&pic);
+
+    assert_eq!(user_balance(), Nat::from(99_990_000_000_000_000_u128));
+
+    // The same withdrawal is sent twice, e.g. because the first call timed out on the client side
+    let created_at_time = pic.get_time().as_nanos_since_unix_epoch();
+    let withdrawal_arg = || WithdrawalArg {
+        amount: Nat::from(50_000_000_000_000_000_u128),
+        recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+        created_at_time: Some(created_at_time),
+        allow_contract_destination: None,
+    };
+    let withdraw = |arg: WithdrawalArg| {
+        update_call::<WithdrawalArg, Result<RetrieveNativeRequest, WithdrawalError>>(
+            &pic,
+            minter_principal(),
+            "withdraw_native_token",
+            arg,
+            Some(user),
+        )
+        .unwrap()
+    };
+
+    let first_result = withdraw(withdrawal_arg());
+    let retried_result = withdraw(withdrawal_arg());
+
+    assert_eq!(first_result, retried_result);
+
+    // Only one burn happened
+    assert_eq!(user_balance(), Nat::from(49_990_000_000_000_000_u128));
+}
+
+#[test]
+fn should_not_report_cancelled_withdrawal_as_deduplicated() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    // [0] is for eth_feeHistory
+    // [1] is for eth_getBlockByNumber
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_FEE_HISTORY_RESPONSE,
+    );
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 1, MOCK_BLOCK_NUMBER);
+
     five_ticks(&pic);
+
+    // public_node eth_getLogs
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS);
+
     five_ticks(&pic);
+
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
     five_ticks(&pic);
 
-    // Get icLink ledger id
-    let chain_link_ledger_id =
-        match query_call::<(), LedgerManagerInfo>(&pic, lsm_principal(), "get_lsm_info", ())
-            .managed_canisters
-            .into_iter()
-            .find(|canister| canister.twin_erc20_token_symbol == "icLINK")
-            .unwrap()
-            .ledger
-            .unwrap()
-        {
-            crate::tests::lsm_types::ManagedCanisterStatus::Created { canister_id: _ } => {
-                panic!("Link canister id should be available")
-            }
-            crate::tests::lsm_types::ManagedCanisterStatus::Installed {
-                canister_id,
-                installed_wasm_hash: _,
-            } => canister_id,
-        };
+    let user =
+        Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
+            .unwrap();
+    let user_balance = || {
+        query_call::<Account, Nat>(
+            &pic,
+            native_ledger_principal(),
+            "icrc1_balance_of",
+            Account {
+                owner: user,
+                subaccount: None,
+            },
+        )
+    };
 
-    pic.advance_time(
-        SCRAPING_CONTRACT_LOGS_INTERVAL
-            .checked_sub(Duration::from_secs(1 * 60))
-            .unwrap(),
+    let _approve_result = update_call::<ApproveArgs, Result<Nat, ApproveError>>(
+        &pic,
+        native_ledger_principal(),
+        "icrc2_approve",
+        ApproveArgs {
+            from_subaccount: None,
+            spender: Account {
+                owner: minter_principal(),
+                subaccount: None,
+            },
+            amount: Nat::from(99_990_000_000_000_000_u128),
+            expected_allowance: None,
+            expires_at: None,
+            fee: None,
+            memo: None,
+            created_at_time: None,
+        },
+        Some(user),
+    )
+    .unwrap();
+
+    // No transaction fits into the fee cap, so that the withdrawal stays pending and can be
+    // cancelled
+    upgrade_minter_canister(
+        &pic,
+        minter_principal(),
+        candid::encode_one(MinterArg::UpgradeArg(UpgradeArg {
+            max_transaction_fee_cap: Some(Nat::from(1_u8)),
+            ..Default::default()
+        }))
+        .unwrap(),
+    );
+
+    let answer_http_outcalls = || {
+        for _ in 0..3 {
+            five_ticks(&pic);
+            let canister_http_requests = pic.get_canister_http();
+            for (method, response) in [
+                ("eth_feeHistory", MOCK_FEE_HISTORY_RESPONSE),
+                ("eth_getBlockByNumber", MOCK_BLOCK_NUMBER),
+                ("eth_getLogs", MOCK_GET_LOGS_EMPTY),
+                ("eth_getTransactionCount", MOCK_TRANSACTION_COUNT_LATEST),
+                ("eth_getCode", MOCK_GET_CODE_EOA),
+            ] {
+                for index in http_requests_with_method(&canister_http_requests, method) {
+                    generate_and_submit_mock_http_response(
+                        &pic,
+                        &canister_http_requests,
+                        index,
+                        response,
+                    );
+                }
+            }
+        }
+        five_ticks(&pic);
+    };
+    answer_http_outcalls();
+
+    let created_at_time = pic.get_time().as_nanos_since_unix_epoch();
+    let withdraw = || {
+        update_call::<WithdrawalArg, Result<RetrieveNativeRequest, WithdrawalError>>(
+            &pic,
+            minter_principal(),
+            "withdraw_native_token",
+            WithdrawalArg {
+                amount: Nat::from(50_000_000_000_000_000_u128),
+                recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+                created_at_time: Some(created_at_time),
+                allow_contract_destination: None,
+            },
+            Some(user),
+        )
+    };
+
+    let withdrawal_id = withdraw().unwrap().block_index;
+
+    answer_http_outcalls();
+
+    assert_eq!(
+        update_call::<u64, Result<(), CancelError>>(
+            &pic,
+            minter_principal(),
+            "cancel_withdrawal",
+            u64::try_from(withdrawal_id.0.clone()).unwrap(),
+            Some(user),
+        ),
+        Ok(())
+    );
+
+    five_ticks(&pic);
+
+    // The retried call finds the burn of the cancelled withdrawal, which is never sent
+    let balance_before_retry = user_balance();
+    assert_eq!(
+        withdraw(),
+        Err(WithdrawalError::AlreadyProcessed {
+            duplicate_of: withdrawal_id
+        })
+    );
+    assert_eq!(user_balance(), balance_before_retry);
+}
+
+#[test]
+fn should_not_withdraw_native_to_minter_address() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    five_ticks(&pic);
+
+    let user =
+        Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
+            .unwrap();
+    let result = update_call::<WithdrawalArg, Result<RetrieveNativeRequest, WithdrawalError>>(
+        &pic,
+        minter_principal(),
+        "withdraw_native_token",
+        WithdrawalArg {
+            amount: Nat::from(50_000_000_000_000_000_u128),
+            recipient: "0x3b13DAFE68a5FDe26eACb4064559d97c1e4FB41a".to_string(),
+            created_at_time: None,
+            allow_contract_destination: None,
+        },
+        Some(user),
+    )
+    .unwrap();
+
+    assert_eq!(
+        result,
+        Err(WithdrawalError::InvalidDestination(
+            "Destination 0x3b13DAFE68a5FDe26eACb4064559d97c1e4FB41a is the minter address"
+                .to_string()
+        ))
+    );
+}
+
+#[test]
+fn should_reject_withdrawal_amounts_larger_than_u256() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    five_ticks(&pic);
+
+    let user =
+        Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
+            .unwrap();
+    let u256_max = Nat::from(Erc20Value::MAX);
+    let too_large_amount = u256_max.clone() + Nat::from(1_u8);
+
+    let result = update_call::<WithdrawalArg, Result<RetrieveNativeRequest, WithdrawalError>>(
+        &pic,
+        minter_principal(),
+        "withdraw_native_token",
+        WithdrawalArg {
+            amount: too_large_amount.clone(),
+            recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+            created_at_time: None,
+            allow_contract_destination: None,
+        },
+        Some(user),
+    );
+    assert_eq!(
+        result,
+        Err(WithdrawalError::AmountTooLarge {
+            max_withdrawal_amount: u256_max.clone()
+        })
+    );
+
+    let result = update_call::<WithdrawErc20Arg, Result<RetrieveErc20Request, WithdrawErc20Error>>(
+        &pic,
+        minter_principal(),
+        "withdraw_erc20",
+        WithdrawErc20Arg {
+            amount: too_large_amount,
+            recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+            erc20_ledger_id: native_ledger_principal(),
+        },
+        Some(user),
+    );
+    assert_eq!(
+        result,
+        Err(WithdrawErc20Error::AmountTooLarge {
+            max_withdrawal_amount: u256_max
+        })
+    );
+}
+
+#[test]
+fn should_display_withdrawal_consent_message_in_spanish() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    five_ticks(&pic);
+
+    let consent_message = |language: &str, device_spec: DeviceSpec| {
+        update_call::<ConsentMessageRequest, ConsentMessageResponse>(
+            &pic,
+            minter_principal(),
+            "icrc21_canister_call_consent_message",
+            ConsentMessageRequest {
+                method: "withdraw_native_token".to_string(),
+                arg: candid::encode_one(WithdrawalArg {
+                    amount: Nat::from(1_500_000_000_000_000_000_u128),
+                    recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+                    created_at_time: None,
+                    allow_contract_destination: None,
+                })
+                .unwrap(),
+                user_preferences: ConsentMessageSpec {
+                    metadata: ConsentMessageMetadata {
+                        language: language.to_string(),
+                        utc_offset_minutes: None,
+                    },
+                    device_spec: Some(device_spec),
+                },
+            },
+            None,
+        )
+    };
+
+    let Ok(info) = consent_message("es-ES", DeviceSpec::GenericDisplay) else {
+        panic!("expected a Spanish consent message");
+    };
+    assert_eq!(info.metadata.language, "es");
+    assert_eq!(info.metadata.supported_languages, vec!["en", "es", "zh"]);
+    let ConsentMessage::GenericDisplayMessage(text) = info.consent_message else {
+        panic!("expected a generic display message");
+    };
+    assert!(text.contains("Retirar"), "{text}");
+    assert!(!text.contains("Withdraw"), "{text}");
+
+    let Ok(info) = consent_message("es", DeviceSpec::FieldsDisplay) else {
+        panic!("expected a Spanish consent message");
+    };
+    let ConsentMessage::FieldsDisplayMessage { intent, fields } = info.consent_message else {
+        panic!("expected a fields display message");
+    };
+    assert_eq!(intent, "Retirar token nativo");
+    assert_eq!(
+        fields
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect::<Vec<_>>(),
+        vec!["Destinatario", "Cantidad"]
+    );
+
+    assert!(consent_message("fr", DeviceSpec::GenericDisplay).is_err());
+}
+
+#[test]
+fn should_rate_limit_check_new_deposits() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    let other_user =
+        Principal::from_text("matbl-u2myk-jsllo-b5aw6-bxboq-7oon2-h6wmo-awsxf-pcebc-4wpgx-4qe")
+            .unwrap();
+    let user =
+        Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
+            .unwrap();
+    let check_new_deposits = |caller: Principal| {
+        update_call::<(), Result<(), RequestScrapingError>>(
+            &pic,
+            minter_principal(),
+            "check_new_deposits",
+            (),
+            Some(caller),
+        )
+    };
+
+    assert_eq!(check_new_deposits(other_user), Ok(()));
+    // all callers share a cooldown
+    assert_eq!(
+        check_new_deposits(user),
+        Err(RequestScrapingError::CalledTooManyTimes)
+    );
+
+    pic.advance_time(MANUAL_DEPOSIT_CHECK_GLOBAL_INTERVAL);
+
+    assert_eq!(check_new_deposits(user), Ok(()));
+    assert_eq!(
+        check_new_deposits(other_user),
+        Err(RequestScrapingError::CalledTooManyTimes)
+    );
+
+    pic.advance_time(MANUAL_DEPOSIT_CHECK_INTERVAL);
+
+    assert_eq!(check_new_deposits(other_user), Ok(()));
+
+    // anonymous callers are rejected
+    assert!(pic
+        .update_call(
+            minter_principal(),
+            Principal::anonymous(),
+            "check_new_deposits",
+            candid::encode_args(()).unwrap(),
+        )
+        .is_err());
+}
+
+#[test]
+fn should_instrument_update_endpoints() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    assert_eq!(
+        update_call::<(), Result<(), RequestScrapingError>>(
+            &pic,
+            minter_principal(),
+            "check_new_deposits",
+            (),
+            None,
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        update_call::<u64, RetrieveWithdrawalStatus>(
+            &pic,
+            minter_principal(),
+            "retrieve_withdrawal_status",
+            0,
+            None,
+        ),
+        RetrieveWithdrawalStatus::NotFound
+    );
+
+    let endpoint = |health: &MinterHealth, name: &str| {
+        health
+            .endpoints
+            .iter()
+            .find(|e| e.endpoint == name)
+            .cloned()
+    };
+    let health = query_call::<(), MinterHealth>(&pic, minter_principal(), "get_health", ());
+    let check_new_deposits = endpoint(&health, "check_new_deposits").unwrap();
+    let retrieve_withdrawal_status = endpoint(&health, "retrieve_withdrawal_status").unwrap();
+
+    assert_eq!(check_new_deposits.count, 1);
+    assert_eq!(retrieve_withdrawal_status.count, 1);
+    assert!(check_new_deposits.total_instructions > 0);
+    assert!(retrieve_withdrawal_status.total_instructions > 0);
+    assert_ne!(
+        check_new_deposits.total_instructions,
+        retrieve_withdrawal_status.total_instructions
+    );
+
+    update_call::<(), ()>(
+        &pic,
+        minter_principal(),
+        "reset_instrumentation",
+        (),
+        Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
+    );
+
+    let health = query_call::<(), MinterHealth>(&pic, minter_principal(), "get_health", ());
+    assert_eq!(endpoint(&health, "check_new_deposits"), None);
+    assert_eq!(endpoint(&health, "retrieve_withdrawal_status"), None);
+}
+
+#[test]
+fn should_not_deposit_twice() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    // The deposit http mock flow is as follow
+    // 1st Step: The mock response for get_blockbynumber is generated
+    // 2nd Step: The response for eth_feehistory resonse is generated afterwards,
+    // 3rd Step: The response for eth_getlogs response is generated,
+
+    // At this time there should be 2 http requests:
+    // [0] is for eth_getBlockByNumber
+    // [1] is for eth_feeHistory
+    let canister_http_requests = pic.get_canister_http();
+
+    // 1st Generating mock response for eth_feehistory
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_FEE_HISTORY_RESPONSE,
+    );
+
+    // 2nd Generating mock response for eth_getBlockByNumber
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 1, MOCK_BLOCK_NUMBER);
+
+    five_ticks(&pic);
+
+    // 3rd generating mock response for eth_getLogs
+    // At this time there should be 2 http requests:
+    // [0] is for public_node eth_getLogs
+    // [1] is for ankr eth_getLogs
+    let canister_http_requests = pic.get_canister_http();
+
+    // public_node mock submission
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS);
+
+    five_ticks(&pic);
+
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
+    five_ticks(&pic);
+
+    // There should be a gap of at least one minute between each log scraping so we advance time for 1 min
+    pic.advance_time(Duration::from_secs(1 * 60));
+
+    // Requesting for another log_scrapping
+    let request_result = update_call::<(), Result<(), RequestScrapingError>>(
+        &pic,
+        minter_principal(),
+        "request_scraping_logs",
+        (),
+        Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
+    );
+
+    assert_eq!(request_result, Ok(()));
+
+    five_ticks(&pic);
+
+    // After requesting one more time there should be another log scraping request which means we have to
+    // follow the same steps but this time we will mock http requests with incorrect responses
+    // to check if minter, mints the same request twice or not.
+
+    // At this time there should be 1 http requests:
+    // [0] is for eth_getBlockByNumber
+    let canister_http_requests = pic.get_canister_http();
+
+    // 1st Generating mock response for eth_getBlockByNumber
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_HIGHER_BLOCK_NUMBER,
+    );
+
+    five_ticks(&pic);
+
+    // 3rd generating mock response for eth_getLogs
+    // At this time there should be 2 http requests:
+    // [0] is for public_node eth_getLogs
+    // [1] is for ankr eth_getLogs
+    let canister_http_requests = pic.get_canister_http();
+
+    // Generating the same mock eth_getlogs response and the minter should detect that these responses are not correct
+    // public_node mock submission
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS);
+
+    five_ticks(&pic);
+
+    // Check balance
+    // there should only be 100000000000000000 icBNB minted for Native to b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe
+    // despite receiving two mint events.
+    let balance = query_call::<Account, Nat>(
+        &pic,
+        native_ledger_principal(),
+        "icrc1_balance_of",
+        Account {
+            owner: Principal::from_text(
+                "b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe",
+            )
+            .unwrap(),
+            subaccount: None,
+        },
+    );
+
+    assert_eq!(balance, Nat::from(100000000000000000_u128));
+}
+
+#[test]
+fn should_mint_all_deposits_of_a_single_log_response_once() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    // [0] is for eth_feeHistory
+    // [1] is for eth_getBlockByNumber
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_FEE_HISTORY_RESPONSE,
+    );
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 1, MOCK_BLOCK_NUMBER);
+
+    five_ticks(&pic);
+
+    // [0] is for public_node eth_getLogs
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_GET_LOGS_MULTIPLE_DEPOSITS,
+    );
+
+    five_ticks(&pic);
+    five_ticks(&pic);
+
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
+    five_ticks(&pic);
+
+    let depositor = Account {
+        owner: Principal::from_text(
+            "b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe",
+        )
+        .unwrap(),
+        subaccount: None,
+    };
+
+    // All four deposits of 0.1 should be minted.
+    let balance = query_call::<Account, Nat>(
+        &pic,
+        native_ledger_principal(),
+        "icrc1_balance_of",
+        depositor,
+    );
+    assert_eq!(balance, Nat::from(400000000000000000_u128));
+
+    // Scraping the same logs again must not mint any of the deposits a second time.
+    pic.advance_time(Duration::from_secs(1 * 60));
+    let request_result = update_call::<(), Result<(), RequestScrapingError>>(
+        &pic,
+        minter_principal(),
+        "request_scraping_logs",
+        (),
+        Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
+    );
+    assert_eq!(request_result, Ok(()));
+
+    five_ticks(&pic);
+
+    // [0] is for eth_getBlockByNumber
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_HIGHER_BLOCK_NUMBER,
     );
 
-    five_ticks(&pic);
-    five_ticks(&pic);
     five_ticks(&pic);
 
-    // 4th
+    // [0] is for public_node eth_getLogs
     let canister_http_requests = pic.get_canister_http();
-
-    // Generating mock response for eth_getBlockByNumber
     generate_and_submit_mock_http_response(
         &pic,
         &canister_http_requests,
         0,
-        MOCK_HIGHER_BLOCK_NUMBER,
+        MOCK_GET_LOGS_MULTIPLE_DEPOSITS,
     );
 
     five_ticks(&pic);
     five_ticks(&pic);
-    five_ticks(&pic);
+
+    let balance = query_call::<Account, Nat>(
+        &pic,
+        native_ledger_principal(),
+        "icrc1_balance_of",
+        depositor,
+    );
+    assert_eq!(balance, Nat::from(400000000000000000_u128));
+}
+
+#[test]
+fn should_not_scrape_logs_concurrently() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    // [0] is for eth_feeHistory
+    // [1] is for eth_getBlockByNumber
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_FEE_HISTORY_RESPONSE,
+    );
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 1, MOCK_BLOCK_NUMBER);
+
     five_ticks(&pic);
 
-    // 5th generating mock response for eth_getLogs
-    // At this time there should be 2 http requests:
     // [0] is for public_node eth_getLogs
-    // [1] is for ankr eth_getLogs
     let canister_http_requests = pic.get_canister_http();
-
-    // public_node mock submission
-    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS_ERC20);
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS_EMPTY);
 
     five_ticks(&pic);
+
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
     five_ticks(&pic);
+
+    let request_scraping_logs = || {
+        pic.advance_time(Duration::from_secs(1 * 60));
+        let request_result = update_call::<(), Result<(), RequestScrapingError>>(
+            &pic,
+            minter_principal(),
+            "request_scraping_logs",
+            (),
+            Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
+        );
+        assert_eq!(request_result, Ok(()));
+        five_ticks(&pic);
+    };
+
+    // The first scraping is stuck waiting for the latest block number
+    request_scraping_logs();
+    let canister_http_requests = pic.get_canister_http();
+    let block_number_requests =
+        http_requests_with_method(&canister_http_requests, "eth_getBlockByNumber");
+    assert_eq!(block_number_requests.len(), 1);
+
+    // A second scraping is skipped while the first one holds the guard
+    request_scraping_logs();
+    let canister_http_requests = pic.get_canister_http();
+    let block_number_requests =
+        http_requests_with_method(&canister_http_requests, "eth_getBlockByNumber");
+    assert_eq!(block_number_requests.len(), 1);
+
+    let health = query_call::<(), MinterHealth>(&pic, minter_principal(), "get_health", ());
+    assert!(health.active_tasks.contains(&"ScrapLogs".to_string()));
+
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        block_number_requests[0],
+        MOCK_HIGHER_BLOCK_NUMBER,
+    );
+
     five_ticks(&pic);
+
+    // Logs of the new block range are requested only once
+    let canister_http_requests = pic.get_canister_http();
+    let get_logs_requests = http_requests_with_method(&canister_http_requests, "eth_getLogs");
+    assert_eq!(get_logs_requests.len(), 1);
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        get_logs_requests[0],
+        MOCK_GET_LOGS_EMPTY,
+    );
+
     five_ticks(&pic);
+
+    let health = query_call::<(), MinterHealth>(&pic, minter_principal(), "get_health", ());
+    assert!(!health.active_tasks.contains(&"ScrapLogs".to_string()));
+    assert_eq!(health.scraping_gap_count, 0);
+    assert_eq!(health.invariant_violations, None);
+}
+
+/// Returns the indexes of the pending HTTP outcalls calling the JSON-RPC `method`.
+pub fn http_requests_with_method(
+    requests: &[pocket_ic::common::rest::CanisterHttpRequest],
+    method: &str,
+) -> Vec<usize> {
+    requests
+        .iter()
+        .enumerate()
+        .filter(|(_, request)| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            body["method"] == method
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[test]
+fn should_mint_backfilled_deposit_once_across_overlapping_backfills() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    // [0] is for eth_feeHistory
+    // [1] is for eth_getBlockByNumber
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_FEE_HISTORY_RESPONSE,
+    );
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 1, MOCK_BLOCK_NUMBER);
+
     five_ticks(&pic);
+
+    // The deposit is missed by the regular scraping
+    let canister_http_requests = pic.get_canister_http();
+    let (_, last_scraped_block) = get_logs_block_range(&canister_http_requests[0]);
+    generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS_EMPTY);
+
     five_ticks(&pic);
+
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
     five_ticks(&pic);
 
-    // Check the deposit status
-    let status = query_call::<String, Option<DepositStatus>>(
-        &pic,
-        minter_principal(),
-        "retrieve_deposit_status",
-        String::from("0x0ce8486575f4a3fe725c463ad0c9a3da2484f68305edcec7bea5db26c95aa18c"),
+    let depositor = Account {
+        owner: Principal::from_text(
+            "b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe",
+        )
+        .unwrap(),
+        subaccount: None,
+    };
+    let balance_of_depositor = || {
+        query_call::<Account, Nat>(
+            &pic,
+            native_ledger_principal(),
+            "icrc1_balance_of",
+            depositor,
+        )
+    };
+    assert_eq!(balance_of_depositor(), Nat::from(0_u8));
+
+    let backfill = |from_block: u64, to_block: u64| {
+        let message_id = pic
+            .submit_call(
+                minter_principal(),
+                Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap(),
+                "backfill_scrape",
+                candid::encode_args((Nat::from(from_block), Nat::from(to_block))).unwrap(),
+            )
+            .unwrap();
+
+        five_ticks(&pic);
+
+        // [0] is for public_node eth_getLogs
+        let canister_http_requests = pic.get_canister_http();
+        assert_eq!(
+            get_logs_block_range(&canister_http_requests[0]),
+            (from_block, to_block)
+        );
+        generate_and_submit_mock_http_response(&pic, &canister_http_requests, 0, MOCK_GET_LOGS);
+
+        let result = decode_wasm_result::<Result<BackfillSummary, BackfillScrapeError>>(
+            pic.await_call(message_id),
+        )
+        .unwrap();
+
+        five_ticks(&pic);
+        five_ticks(&pic);
+
+        result
+    };
+
+    assert_eq!(
+        backfill(last_scraped_block - 100, last_scraped_block - 1),
+        Ok(BackfillSummary {
+            accepted_events: 1,
+            invalid_events: 0,
+            duplicate_events: 0,
+        })
     );
+    assert_eq!(balance_of_depositor(), Nat::from(100000000000000000_u128));
 
-    assert_eq!(status, Some(DepositStatus::Minted));
+    // The second range overlaps the first one and finds the same deposit again
+    assert_eq!(
+        backfill(last_scraped_block - 150, last_scraped_block - 50),
+        Ok(BackfillSummary {
+            accepted_events: 0,
+            invalid_events: 0,
+            duplicate_events: 1,
+        })
+    );
+    assert_eq!(balance_of_depositor(), Nat::from(100000000000000000_u128));
 
-    // Check Erc20 icLINK deposit
-    // Based on the logs there should be 3_000_000_000_000_000_000 icLINK minted to b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe
-    let balance = query_call::<Account, Nat>(
-        &pic,
-        chain_link_ledger_id,
-        "icrc1_balance_of",
-        Account {
-            owner: Principal::from_text(
-                "b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe",
-            )
+    let minter_info = query_call::<(), MinterInfo>(&pic, minter_principal(), "get_minter_info", ());
+    assert_eq!(
+        minter_info.last_scraped_block_number,
+        Some(Nat::from(last_scraped_block))
+    );
+
+    // Blocks that were not scraped yet cannot be backfilled
+    let result = decode_wasm_result::<Result<BackfillSummary, BackfillScrapeError>>(
+        pic.update_call(
+            minter_principal(),
+            Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap(),
+            "backfill_scrape",
+            candid::encode_args((
+                Nat::from(last_scraped_block - 10),
+                Nat::from(last_scraped_block),
+            ))
             .unwrap(),
-            subaccount: None,
-        },
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        result,
+        Err(BackfillScrapeError::BlockNotScrapedYet {
+            last_scraped_block_number: Nat::from(last_scraped_block)
+        })
     );
+}
 
-    assert_eq!(balance, Nat::from(3_000_000_000_000_000_000_u128));
-    // assert_eq!(balance, Nat::from(99_950_000_000_000_000_u128));
+#[test]
+fn should_deposit_and_withdrawal_erc20() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+
+    // The deposit and withdrawal http mock flow is as follow
+    // 1st Step: The mock response for get_blockbynumber is generated
+    // 2nd Step: The response for eth_feehistory resonse is generated afterwards,
+    // so in the time of withdrawal transaction the eip1559 transaction price is available
+    // Not to forget that the price should be refreshed through a second call at the time
+    // 3rd Step: There should two mock responses be generated for eth_getlogs, one for ankr and the other one for public node
+    // 4th Step: After 10 min the response for eth_feehistory resonse is generated afterwards,
+    // 5th Step: There should two mock responses be generated for eth_getlogs, one for ankr and the other one for public node this time with deposit logs
+    // One for native and erc20
+    // 6th Step: The response for sendrawtransaction
+    // 7th Step: An http-outcall for getting the finalized transaction count.
+    // 8th Step: and in the end get transaction receipt should be generate
+
+    let chain_link_ledger_id = deposit_native_and_erc20(&pic);
 
     // Withdrawal Section
     // Calling icrc2_approve and giving the permission to minter for taking funds from users principal NATIVE_LEDGER
@@ -809,38 +2141,173 @@ fn should_deposit_and_withdrawal_erc20() {
 
     five_ticks(&pic);
 
-    // Check balance after approval ERC20_LEDGER
-    let balance = query_call::<Account, Nat>(
+    // Check balance after approval ERC20_LEDGER
+    let balance = query_call::<Account, Nat>(
+        &pic,
+        chain_link_ledger_id,
+        "icrc1_balance_of",
+        Account {
+            owner: Principal::from_text(
+                "b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe",
+            )
+            .unwrap(),
+            subaccount: None,
+        },
+    );
+
+    assert_eq!(
+        balance,
+        Nat::from(3_000_000_000_000_000_000_u128 - 100_000_000_000_000_u128)
+    );
+
+    five_ticks(&pic);
+
+    // Making Native the withdrawal request to minter
+    let withdrawal_request_result = update_call::<
+        WithdrawalArg,
+        Result<RetrieveNativeRequest, WithdrawalError>,
+    >(
+        &pic,
+        minter_principal(),
+        "withdraw_native_token",
+        WithdrawalArg {
+            amount: Nat::from(940_000_000_000_000_u128),
+            recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+            created_at_time: None,
+            allow_contract_destination: None,
+        },
+        Some(
+            Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
+                .unwrap(),
+        ),
+    )
+    .unwrap();
+
+    // Minting deposit block 0
+    // Minting deposit fee block 1
+    // Transfer
+    assert_eq!(withdrawal_request_result.block_index, Nat::from(2_u64));
+
+    five_ticks(&pic);
+
+    // Advance time for PROCESS_TOKENS_RETRIEVE_TRANSACTIONS_INTERVAL amount.
+    //pic.advance_time(PROCESS_TOKENS_RETRIEVE_TRANSACTIONS_INTERVAL);
+
+    five_ticks(&pic);
+
+    // At this point there should be an http request for refreshing the fee history
+    // Once there is a withdrawal request, The first attempt should be updating fee history
+    // Cause there should be a maximum gap of 30 seconds between the previous gas fee estimate
+    // we just advance time for amount
+    let canister_http_requests = pic.get_canister_http();
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_FEE_HISTORY_RESPONSE,
+    );
+
+    five_ticks(&pic);
+    five_ticks(&pic);
+    five_ticks(&pic);
+    five_ticks(&pic);
+
+    let canister_http_requests = pic.get_canister_http();
+
+    // Generating the latest transaction count for inserting the correct nonce
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_TRANSACTION_COUNT_LATEST,
+    );
+
+    five_ticks(&pic);
+
+    // The destination is checked for code once the transaction is created
+    submit_mock_http_responses_with_method(&pic, "eth_getCode", MOCK_GET_CODE_EOA);
+
+    five_ticks(&pic);
+    five_ticks(&pic);
+    //
+    // At this point there should be 2 http_requests
+    // [0] public_node eth_sendRawTransaction
+    // [1] ankr eth_sendRawTransaction
+    let canister_http_requests = pic.get_canister_http();
+
+    // public_node request
+    // Trying to simulate real sendrawtransaction since there will only be one successful result and the rest of the nodes will return
+    // one of the failed responses(NonceTooLow,NonceTooHigh,etc..,)
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_SEND_TRANSACTION_SUCCESS,
+    );
+
+    println!("Hellooooooo {}", canister_http_requests.len());
+    five_ticks(&pic);
+
+    // getting the finalized transaction count after sending transaction was successful.
+    let canister_http_requests = pic.get_canister_http();
+
+    generate_and_submit_mock_http_response(
+        &pic,
+        &canister_http_requests,
+        0,
+        MOCK_TRANSACTION_COUNT_FINALIZED,
+    );
+
+    five_ticks(&pic);
+
+    // At this point there should be two requests for eth_getTransactionReceipt
+    // [0] public_node
+    // [1] ankr
+    let canister_http_requests = pic.get_canister_http();
+
+    // public_node
+    generate_and_submit_mock_http_response(
         &pic,
-        chain_link_ledger_id,
-        "icrc1_balance_of",
-        Account {
-            owner: Principal::from_text(
-                "b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe",
-            )
-            .unwrap(),
-            subaccount: None,
-        },
+        &canister_http_requests,
+        0,
+        MOCK_SECOND_NATIVE_TRANSACTION_RECEIPT,
     );
 
-    assert_eq!(
-        balance,
-        Nat::from(3_000_000_000_000_000_000_u128 - 100_000_000_000_000_u128)
+    five_ticks(&pic);
+
+    // The transaction should be included into finalized transaction list.
+    let get_withdrawal_transaction_by_block_index = update_call::<u64, RetrieveWithdrawalStatus>(
+        &pic,
+        minter_principal(),
+        "retrieve_withdrawal_status",
+        2_u64,
+        None,
     );
+    let expected_transaction_result =
+        RetrieveWithdrawalStatus::TxFinalized(TxFinalizedStatus::Success {
+            transaction_hash: "0x1bf19dee9c59944ddaed2252ad483a3df892a009e245330bb681481350926422"
+                .to_string(),
+            effective_transaction_fee: Some(Nat::from(63000000000000_u128)),
+            realized_amount_out: None,
+        });
 
-    five_ticks(&pic);
+    assert_eq!(
+        get_withdrawal_transaction_by_block_index,
+        expected_transaction_result
+    );
 
-    // Making Native the withdrawal request to minter
+    // Making the Erc20 withdrawal request to minter
     let withdrawal_request_result = update_call::<
-        WithdrawalArg,
-        Result<RetrieveNativeRequest, WithdrawalError>,
+        WithdrawErc20Arg,
+        Result<RetrieveErc20Request, WithdrawErc20Error>,
     >(
         &pic,
         minter_principal(),
-        "withdraw_native_token",
-        WithdrawalArg {
-            amount: Nat::from(940_000_000_000_000_u128),
+        "withdraw_erc20",
+        WithdrawErc20Arg {
+            amount: Nat::from(3_000_000_000_000_000_000_u128 - 100_000_000_000_000_u128),
             recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+            erc20_ledger_id: chain_link_ledger_id,
         },
         Some(
             Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
@@ -849,12 +2316,14 @@ fn should_deposit_and_withdrawal_erc20() {
     )
     .unwrap();
 
-    // Minting deposit block 0
-    // Minting deposit fee block 1
-    // Transfer
-    assert_eq!(withdrawal_request_result.block_index, Nat::from(2_u64));
-
-    five_ticks(&pic);
+    assert_eq!(
+        withdrawal_request_result.native_block_index,
+        Nat::from(3_u64)
+    );
+    assert_eq!(
+        withdrawal_request_result.erc20_block_index,
+        Nat::from(2_u64)
+    );
 
     // Advance time for PROCESS_TOKENS_RETRIEVE_TRANSACTIONS_INTERVAL amount.
     //pic.advance_time(PROCESS_TOKENS_RETRIEVE_TRANSACTIONS_INTERVAL);
@@ -865,17 +2334,14 @@ fn should_deposit_and_withdrawal_erc20() {
     // Once there is a withdrawal request, The first attempt should be updating fee history
     // Cause there should be a maximum gap of 30 seconds between the previous gas fee estimate
     // we just advance time for amount
-    let canister_http_requests = pic.get_canister_http();
-    generate_and_submit_mock_http_response(
-        &pic,
-        &canister_http_requests,
-        0,
-        MOCK_FEE_HISTORY_RESPONSE,
-    );
+    //let canister_http_requests = pic.get_canister_http();
+    //generate_and_submit_mock_http_response(
+    //    &pic,
+    //    &canister_http_requests,
+    //    0,
+    //    MOCK_FEE_HISTORY_RESPONSE,
+    //);
 
-    five_ticks(&pic);
-    five_ticks(&pic);
-    five_ticks(&pic);
     five_ticks(&pic);
 
     let canister_http_requests = pic.get_canister_http();
@@ -885,12 +2351,13 @@ fn should_deposit_and_withdrawal_erc20() {
         &pic,
         &canister_http_requests,
         0,
-        MOCK_TRANSACTION_COUNT_LATEST,
+        MOCK_TRANSACTION_COUNT_LATEST_ERC20,
     );
 
     five_ticks(&pic);
     five_ticks(&pic);
-    //
+
+    // https out call for sending raw transaction.
     // At this point there should be 2 http_requests
     // [0] public_node eth_sendRawTransaction
     // [1] ankr eth_sendRawTransaction
@@ -906,7 +2373,6 @@ fn should_deposit_and_withdrawal_erc20() {
         MOCK_SEND_TRANSACTION_SUCCESS,
     );
 
-    println!("Hellooooooo {}", canister_http_requests.len());
     five_ticks(&pic);
 
     // getting the finalized transaction count after sending transaction was successful.
@@ -916,11 +2382,12 @@ fn should_deposit_and_withdrawal_erc20() {
         &pic,
         &canister_http_requests,
         0,
-        MOCK_TRANSACTION_COUNT_FINALIZED,
+        MOCK_TRANSACTION_COUNT_FINALIZED_ERC20,
     );
 
     five_ticks(&pic);
 
+    // Getting the transaction receipt.
     // At this point there should be two requests for eth_getTransactionReceipt
     // [0] public_node
     // [1] ankr
@@ -931,9 +2398,10 @@ fn should_deposit_and_withdrawal_erc20() {
         &pic,
         &canister_http_requests,
         0,
-        MOCK_SECOND_NATIVE_TRANSACTION_RECEIPT,
+        MOCK_TRANSACTION_RECEIPT_ERC20,
     );
 
+    five_ticks(&pic);
     five_ticks(&pic);
 
     // The transaction should be included into finalized transaction list.
@@ -941,14 +2409,15 @@ fn should_deposit_and_withdrawal_erc20() {
         &pic,
         minter_principal(),
         "retrieve_withdrawal_status",
-        2_u64,
+        3_u64,
         None,
     );
     let expected_transaction_result =
         RetrieveWithdrawalStatus::TxFinalized(TxFinalizedStatus::Success {
-            transaction_hash: "0x1bf19dee9c59944ddaed2252ad483a3df892a009e245330bb681481350926422"
+            transaction_hash: "0x54a97b762eca864e89a680c1e116632600dfc634ba80c8bd89689920e1ae99f3"
                 .to_string(),
             effective_transaction_fee: Some(Nat::from(63000000000000_u128)),
+            realized_amount_out: None,
         });
 
     assert_eq!(
@@ -956,133 +2425,552 @@ fn should_deposit_and_withdrawal_erc20() {
         expected_transaction_result
     );
 
-    // Making the Erc20 withdrawal request to minter
-    let withdrawal_request_result = update_call::<
-        WithdrawErc20Arg,
-        Result<RetrieveErc20Request, WithdrawErc20Error>,
-    >(
-        &pic,
+    // The same withdrawal can be looked up by the burn index on the ERC20 ledger.
+    let get_withdrawal_transaction_by_erc20_burn_index =
+        decode_wasm_result::<RetrieveWithdrawalStatus>(pic.update_call(
+            minter_principal(),
+            Principal::anonymous(),
+            "retrieve_withdrawal_status_by_erc20_burn",
+            candid::encode_args((chain_link_ledger_id, 2_u64)).unwrap(),
+        ))
+        .unwrap();
+    assert_eq!(
+        get_withdrawal_transaction_by_erc20_burn_index,
+        expected_transaction_result
+    );
+
+    let unknown_erc20_burn_index = decode_wasm_result::<RetrieveWithdrawalStatus>(pic.update_call(
         minter_principal(),
-        "withdraw_erc20",
-        WithdrawErc20Arg {
+        Principal::anonymous(),
+        "retrieve_withdrawal_status_by_erc20_burn",
+        candid::encode_args((chain_link_ledger_id, 3_u64)).unwrap(),
+    ))
+    .unwrap();
+    assert_eq!(unknown_erc20_burn_index, RetrieveWithdrawalStatus::NotFound);
+
+    // The withdrawal is listed for reconciliation with the values of the mocked receipt.
+    let finalized_erc20_withdrawals =
+        decode_wasm_result::<Vec<FinalizedWithdrawal>>(pic.query_call(
+            minter_principal(),
+            Principal::anonymous(),
+            "get_finalized_transactions",
+            candid::encode_args((Some(chain_link_ledger_id), 0_u64, 10_u64)).unwrap(),
+        ))
+        .unwrap();
+    assert_eq!(finalized_erc20_withdrawals.len(), 1);
+    let erc20_withdrawal = &finalized_erc20_withdrawals[0];
+    assert_eq!(
+        erc20_withdrawal,
+        &FinalizedWithdrawal {
+            native_burn_index: 3,
+            token_symbol: Some("icLINK".to_string()),
+            destination: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
             amount: Nat::from(3_000_000_000_000_000_000_u128 - 100_000_000_000_000_u128),
-            recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
-            erc20_ledger_id: chain_link_ledger_id,
+            transaction_hash: "0x54a97b762eca864e89a680c1e116632600dfc634ba80c8bd89689920e1ae99f3"
+                .to_string(),
+            // gasUsed 0x5208 * effectiveGasPrice 0xb2d05e00
+            effective_fee: Nat::from(63000000000000_u128),
+            l1_fee: None,
+            finalized_at: erc20_withdrawal.finalized_at,
+        }
+    );
+    assert!(erc20_withdrawal.finalized_at <= pic.get_time().as_nanos_since_unix_epoch());
+
+    // Without a token filter, the native withdrawal finalized before is listed after it.
+    let all_finalized_withdrawals = decode_wasm_result::<Vec<FinalizedWithdrawal>>(pic.query_call(
+        minter_principal(),
+        Principal::anonymous(),
+        "get_finalized_transactions",
+        candid::encode_args((None::<Principal>, 0_u64, 10_u64)).unwrap(),
+    ))
+    .unwrap();
+    assert_eq!(
+        all_finalized_withdrawals
+            .iter()
+            .map(|withdrawal| withdrawal.native_burn_index)
+            .collect::<Vec<_>>(),
+        vec![3, 2]
+    );
+}
+
+#[test]
+fn should_cancel_stuck_erc20_withdrawal_and_finalize_later_native_withdrawal() {
+    let pic = create_pic();
+    create_and_install_minter_plus_dependency_canisters(&pic);
+    let chain_link_ledger_id = deposit_native_and_erc20(&pic);
+    let user =
+        Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
+            .unwrap();
+    for (ledger_id, amount) in [
+        (native_ledger_principal(), 99_990_000_000_000_000_u128),
+        (
+            chain_link_ledger_id,
+            3_000_000_000_000_000_000_u128 - 100_000_000_000_000_u128,
+        ),
+    ] {
+        update_call::<ApproveArgs, Result<Nat, ApproveError>>(
+            &pic,
+            ledger_id,
+            "icrc2_approve",
+            ApproveArgs {
+                from_subaccount: None,
+                spender: Account {
+                    owner: minter_principal(),
+                    subaccount: None,
+                },
+                amount: Nat::from(amount),
+                expected_allowance: None,
+                expires_at: None,
+                fee: None,
+                memo: None,
+                created_at_time: None,
+            },
+            Some(user),
+        )
+        .expect("approval should succeed");
+    }
+
+    // The ERC-20 withdrawal gets the first nonce and is never mined.
+    let mut chain = MockChain::new();
+    let message_id = pic
+        .submit_call(
+            minter_principal(),
+            user,
+            "withdraw_erc20",
+            candid::encode_one(WithdrawErc20Arg {
+                amount: Nat::from(1_000_000_000_000_000_000_u128),
+                recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+                erc20_ledger_id: chain_link_ledger_id,
+            })
+            .unwrap(),
+        )
+        .unwrap();
+    chain.answer_http_outcalls(&pic, 3);
+    let erc20_withdrawal = decode_wasm_result::<Result<RetrieveErc20Request, WithdrawErc20Error>>(
+        pic.await_call(message_id),
+    )
+    .unwrap()
+    .expect("ERC-20 withdrawal should be accepted");
+    let erc20_withdrawal_id = erc20_withdrawal.native_block_index;
+    chain.answer_http_outcalls(&pic, 20);
+    assert_eq!(
+        signed_transaction_hashes(&pic, &erc20_withdrawal_id).len(),
+        1
+    );
+
+    // The native withdrawal gets the next nonce and is blocked by the ERC-20 withdrawal.
+    let message_id = pic
+        .submit_call(
+            minter_principal(),
+            user,
+            "withdraw_native_token",
+            candid::encode_one(WithdrawalArg {
+                amount: Nat::from(940_000_000_000_000_u128),
+                recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+                created_at_time: None,
+                allow_contract_destination: None,
+            })
+            .unwrap(),
+        )
+        .unwrap();
+    chain.answer_http_outcalls(&pic, 3);
+    let native_withdrawal = decode_wasm_result::<Result<RetrieveNativeRequest, WithdrawalError>>(
+        pic.await_call(message_id),
+    )
+    .unwrap()
+    .expect("native withdrawal should be accepted");
+    let native_withdrawal_id = native_withdrawal.block_index;
+    chain.answer_http_outcalls(&pic, 20);
+    assert!(!signed_transaction_hashes(&pic, &native_withdrawal_id).is_empty());
+
+    // Once the gas price rises above what the ERC-20 withdrawal was charged,
+    // its transaction is parked and cancelled.
+    chain.fee_history = MOCK_FEE_HISTORY_RESPONSE.replace("0xb2d05e00", "0x174876e800");
+    chain.answer_http_outcalls(&pic, 20);
+    let events = all_events(&pic);
+    assert!(events.iter().any(|event| matches!(
+        &event.payload,
+        EventPayload::ParkedTransaction { withdrawal_id, .. } if withdrawal_id == &erc20_withdrawal_id
+    )));
+    let erc20_hashes = signed_transaction_hashes(&pic, &erc20_withdrawal_id);
+    assert_eq!(erc20_hashes.len(), 2);
+
+    // The cancellation and the native withdrawal are mined.
+    let cancellation_hash = erc20_hashes.last().unwrap().clone();
+    let native_hash = signed_transaction_hashes(&pic, &native_withdrawal_id)
+        .last()
+        .unwrap()
+        .clone();
+    chain.fee_history = MOCK_FEE_HISTORY_RESPONSE.to_string();
+    chain.mined = BTreeSet::from([cancellation_hash.clone(), native_hash.clone()]);
+    chain.transaction_count = 2;
+    chain.answer_http_outcalls(&pic, 20);
+
+    let withdrawal_status = |withdrawal_id: &Nat| {
+        update_call::<u64, RetrieveWithdrawalStatus>(
+            &pic,
+            minter_principal(),
+            "retrieve_withdrawal_status",
+            withdrawal_id.0.clone().try_into().unwrap(),
+            None,
+        )
+    };
+    assert_eq!(
+        withdrawal_status(&native_withdrawal_id),
+        RetrieveWithdrawalStatus::TxFinalized(TxFinalizedStatus::Success {
+            transaction_hash: native_hash,
+            effective_transaction_fee: Some(Nat::from(63_000_000_000_000_u128)),
+            realized_amount_out: None,
+        })
+    );
+    match withdrawal_status(&erc20_withdrawal_id) {
+        RetrieveWithdrawalStatus::TxFinalized(TxFinalizedStatus::Reimbursed {
+            transaction_hash,
+            ..
+        }) => assert_eq!(transaction_hash, cancellation_hash),
+        RetrieveWithdrawalStatus::TxFinalized(TxFinalizedStatus::PendingReimbursement(_)) => {}
+        status => panic!("unexpected status of the cancelled withdrawal: {status:?}"),
+    }
+}
+
+/// Answers the JSON-RPC requests of the minter like a chain on which only the transactions
+/// in `mined` are included, with `transaction_count` as the latest and finalized nonce of the minter.
+struct MockChain {
+    fee_history: String,
+    block_number: u64,
+    transaction_count: u64,
+    mined: BTreeSet<String>,
+}
+
+impl MockChain {
+    fn new() -> Self {
+        Self {
+            fee_history: MOCK_FEE_HISTORY_RESPONSE.to_string(),
+            block_number: 0x3000000,
+            transaction_count: 0,
+            mined: BTreeSet::new(),
+        }
+    }
+
+    fn respond(&mut self, body: &serde_json::Value) -> String {
+        match body["method"].as_str().unwrap_or_default() {
+            "eth_feeHistory" => self.fee_history.clone(),
+            "eth_getBlockByNumber" => {
+                // a new block every time, so that the gas fee estimate is refreshed
+                self.block_number += 1;
+                MOCK_HIGHER_BLOCK_NUMBER.replace("0x2BD103A", &format!("{:#x}", self.block_number))
+            }
+            "eth_getLogs" => MOCK_GET_LOGS_EMPTY.to_string(),
+            "eth_getCode" => MOCK_GET_CODE_EOA.to_string(),
+            "eth_getTransactionCount" => format!(
+                r#"{{"id":1,"jsonrpc":"2.0","result":"{:#x}"}}"#,
+                self.transaction_count
+            ),
+            "eth_sendRawTransaction" => MOCK_SEND_TRANSACTION_SUCCESS.to_string(),
+            "eth_getTransactionReceipt" => {
+                let hash = body["params"][0].as_str().unwrap().to_lowercase();
+                if self.mined.contains(&hash) {
+                    MOCK_TRANSACTION_RECEIPT.replace(
+                        "0x23e4ac0e4bde9f2c12a3906d7145769a52d96456fca38f3de399a1c0038309fb",
+                        &hash,
+                    )
+                } else {
+                    MOCK_TRANSACTION_RECEIPT_NOT_FOUND.to_string()
+                }
+            }
+            _ => r#"{"id":1,"jsonrpc":"2.0","result":null}"#.to_string(),
+        }
+    }
+
+    /// Answers all the pending HTTP outcalls, then lets 15 seconds pass, `rounds` times.
+    fn answer_http_outcalls(&mut self, pic: &pocket_ic::PocketIc, rounds: usize) {
+        for _ in 0..rounds {
+            five_ticks(pic);
+            let canister_http_requests = pic.get_canister_http();
+            for (index, request) in canister_http_requests.iter().enumerate() {
+                let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                let response = self.respond(&body);
+                generate_and_submit_mock_http_response(
+                    pic,
+                    &canister_http_requests,
+                    index,
+                    &response,
+                );
+            }
+            five_ticks(pic);
+            pic.advance_time(Duration::from_secs(15));
+        }
+    }
+}
+
+fn all_events(pic: &pocket_ic::PocketIc) -> Vec<Event> {
+    let mut events = vec![];
+    loop {
+        let result = query_call::<GetEventsArg, GetEventsResult>(
+            pic,
+            minter_principal(),
+            "get_events",
+            GetEventsArg {
+                start: events.len() as u64,
+                length: 100,
+            },
+        );
+        if result.events.is_empty() {
+            return events;
+        }
+        events.extend(result.events);
+    }
+}
+
+/// Hashes of the transactions signed for the given withdrawal, in signing order.
+fn signed_transaction_hashes(pic: &pocket_ic::PocketIc, withdrawal_id: &Nat) -> Vec<String> {
+    all_events(pic)
+        .into_iter()
+        .filter_map(|event| match event.payload {
+            EventPayload::SignedTransaction {
+                withdrawal_id: id,
+                transaction_hash,
+                ..
+            } if &id == withdrawal_id => Some(transaction_hash.to_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Deposits native tokens and icLINK for `b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe`
+/// by answering the HTTP outcalls of the first log scrapings, and returns the icLINK ledger id.
+fn deposit_native_and_erc20(pic: &pocket_ic::PocketIc) -> Principal {
+    // At this time there should be 2 http requests:
+    // [0] is for eth_getBlockByNumber
+    // [1] is for eth_feeHistory
+
+    let canister_http_requests = pic.get_canister_http();
+
+    // 1st Generating mock response for eth_feehistory
+    generate_and_submit_mock_http_response(
+        pic,
+        &canister_http_requests,
+        0,
+        MOCK_FEE_HISTORY_RESPONSE,
+    );
+
+    // 2nd Generating mock response for eth_getBlockByNumber
+    generate_and_submit_mock_http_response(pic, &canister_http_requests, 1, MOCK_BLOCK_NUMBER);
+
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+
+    // 3rd generating mock response for eth_getLogs
+    // At this time there should be 2 http requests:
+    // [0] is for public_node eth_getLogs
+    // [1] is for ankr eth_getLogs
+    let canister_http_requests = pic.get_canister_http();
+
+    // public_node mock submission
+    generate_and_submit_mock_http_response(pic, &canister_http_requests, 0, MOCK_GET_LOGS);
+
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(pic);
+
+    five_ticks(pic);
+
+    // Check Native deposit
+    let balance = query_call::<Account, Nat>(
+        pic,
+        native_ledger_principal(),
+        "icrc1_balance_of",
+        Account {
+            owner: Principal::from_text(
+                "b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe",
+            )
+            .unwrap(),
+            subaccount: None,
+        },
+    );
+
+    assert_eq!(balance, Nat::from(100_000_000_000_000_000_u128));
+
+    // Calling icrc2_approve and giving the permission to lsm for taking funds from users principal
+    let _approve_result = update_call::<ApproveArgs, Result<Nat, ApproveError>>(
+        pic,
+        icp_principal(),
+        "icrc2_approve",
+        ApproveArgs {
+            from_subaccount: None,
+            spender: Account {
+                owner: lsm_principal(),
+                subaccount: None,
+            },
+            amount: Nat::from(
+                2_500_000_000_u128, // Users balance - approval fee => 99_950_000_000_000_000_u128 - 10_000_000_000_000_u128
+            ),
+            expected_allowance: None,
+            expires_at: None,
+            fee: None,
+            memo: None,
+            created_at_time: None,
+        },
+        Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
+    )
+    .unwrap();
+
+    five_ticks(pic);
+
+    // Add icUSDC to lsm
+    update_call::<AddErc20Arg, Result<(), AddErc20Error>>(
+        pic,
+        lsm_principal(),
+        "add_erc20_ls",
+        AddErc20Arg {
+            contract: Erc20Contract {
+                chain_id: EvmNetwork::BSC.chain_id().into(),
+                address: "0x84b9B910527Ad5C03A9Ca831909E21e236EA7b06".to_string(),
+            },
+            ledger_init_arg: LedgerInitArg {
+                transfer_fee: Nat::from(100_000_000_000_000_u128),
+                decimals: 18,
+                token_name: "Chain Link on icp".to_string(),
+                token_symbol: "icLINK".to_string(),
+                token_logo: "".to_string(),
+            },
         },
-        Some(
-            Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
-                .unwrap(),
-        ),
+        Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
     )
     .unwrap();
 
-    assert_eq!(
-        withdrawal_request_result.native_block_index,
-        Nat::from(3_u64)
-    );
-    assert_eq!(
-        withdrawal_request_result.erc20_block_index,
-        Nat::from(2_u64)
-    );
-
-    // Advance time for PROCESS_TOKENS_RETRIEVE_TRANSACTIONS_INTERVAL amount.
-    //pic.advance_time(PROCESS_TOKENS_RETRIEVE_TRANSACTIONS_INTERVAL);
-
-    five_ticks(&pic);
+    five_ticks(pic);
 
-    // At this point there should be an http request for refreshing the fee history
-    // Once there is a withdrawal request, The first attempt should be updating fee history
-    // Cause there should be a maximum gap of 30 seconds between the previous gas fee estimate
-    // we just advance time for amount
-    //let canister_http_requests = pic.get_canister_http();
-    //generate_and_submit_mock_http_response(
-    //    &pic,
-    //    &canister_http_requests,
-    //    0,
-    //    MOCK_FEE_HISTORY_RESPONSE,
-    //);
+    // Advance time for 1 hour.
+    pic.advance_time(Duration::from_secs(1 * 60));
 
-    five_ticks(&pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
 
-    let canister_http_requests = pic.get_canister_http();
+    // Get icLink ledger id
+    let chain_link_ledger_id =
+        match query_call::<(), LedgerManagerInfo>(pic, lsm_principal(), "get_lsm_info", ())
+            .managed_canisters
+            .into_iter()
+            .find(|canister| canister.twin_erc20_token_symbol == "icLINK")
+            .unwrap()
+            .ledger
+            .unwrap()
+        {
+            crate::tests::lsm_types::ManagedCanisterStatus::Created { canister_id: _ } => {
+                panic!("Link canister id should be available")
+            }
+            crate::tests::lsm_types::ManagedCanisterStatus::Installed {
+                canister_id,
+                installed_wasm_hash: _,
+            } => canister_id,
+        };
 
-    // Generating the latest transaction count for inserting the correct nonce
-    generate_and_submit_mock_http_response(
-        &pic,
-        &canister_http_requests,
-        0,
-        MOCK_TRANSACTION_COUNT_LATEST_ERC20,
+    pic.advance_time(
+        SCRAPING_CONTRACT_LOGS_INTERVAL
+            .checked_sub(Duration::from_secs(1 * 60))
+            .unwrap(),
     );
 
-    five_ticks(&pic);
-    five_ticks(&pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
 
-    // https out call for sending raw transaction.
-    // At this point there should be 2 http_requests
-    // [0] public_node eth_sendRawTransaction
-    // [1] ankr eth_sendRawTransaction
+    // 4th
     let canister_http_requests = pic.get_canister_http();
 
-    // public_node request
-    // Trying to simulate real sendrawtransaction since there will only be one successful result and the rest of the nodes will return
-    // one of the failed responses(NonceTooLow,NonceTooHigh,etc..,)
+    // Generating mock response for eth_getBlockByNumber
     generate_and_submit_mock_http_response(
-        &pic,
+        pic,
         &canister_http_requests,
         0,
-        MOCK_SEND_TRANSACTION_SUCCESS,
+        MOCK_HIGHER_BLOCK_NUMBER,
     );
 
-    five_ticks(&pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
 
-    // getting the finalized transaction count after sending transaction was successful.
+    // 5th generating mock response for eth_getLogs
+    // At this time there should be 2 http requests:
+    // [0] is for public_node eth_getLogs
+    // [1] is for ankr eth_getLogs
     let canister_http_requests = pic.get_canister_http();
 
-    generate_and_submit_mock_http_response(
-        &pic,
-        &canister_http_requests,
-        0,
-        MOCK_TRANSACTION_COUNT_FINALIZED_ERC20,
-    );
-
-    five_ticks(&pic);
+    // public_node mock submission
+    generate_and_submit_mock_http_response(pic, &canister_http_requests, 0, MOCK_GET_LOGS_ERC20);
 
-    // Getting the transaction receipt.
-    // At this point there should be two requests for eth_getTransactionReceipt
-    // [0] public_node
-    // [1] ankr
-    let canister_http_requests = pic.get_canister_http();
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
+    five_ticks(pic);
 
-    // public_node
-    generate_and_submit_mock_http_response(
-        &pic,
-        &canister_http_requests,
-        0,
-        MOCK_TRANSACTION_RECEIPT_ERC20,
-    );
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(pic);
 
-    five_ticks(&pic);
-    five_ticks(&pic);
+    five_ticks(pic);
 
-    // The transaction should be included into finalized transaction list.
-    let get_withdrawal_transaction_by_block_index = update_call::<u64, RetrieveWithdrawalStatus>(
-        &pic,
+    // Check the deposit status
+    let status = query_call::<String, Option<DepositStatus>>(
+        pic,
         minter_principal(),
-        "retrieve_withdrawal_status",
-        3_u64,
-        None,
+        "retrieve_deposit_status",
+        String::from("0x0ce8486575f4a3fe725c463ad0c9a3da2484f68305edcec7bea5db26c95aa18c"),
     );
-    let expected_transaction_result =
-        RetrieveWithdrawalStatus::TxFinalized(TxFinalizedStatus::Success {
-            transaction_hash: "0x54a97b762eca864e89a680c1e116632600dfc634ba80c8bd89689920e1ae99f3"
-                .to_string(),
-            effective_transaction_fee: Some(Nat::from(63000000000000_u128)),
-        });
 
-    assert_eq!(
-        get_withdrawal_transaction_by_block_index,
-        expected_transaction_result
+    assert_eq!(status, Some(DepositStatus::Minted));
+
+    // Check Erc20 icLINK deposit
+    // Based on the logs there should be 3_000_000_000_000_000_000 icLINK minted to b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe
+    let balance = query_call::<Account, Nat>(
+        pic,
+        chain_link_ledger_id,
+        "icrc1_balance_of",
+        Account {
+            owner: Principal::from_text(
+                "b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe",
+            )
+            .unwrap(),
+            subaccount: None,
+        },
     );
+
+    assert_eq!(balance, Nat::from(3_000_000_000_000_000_000_u128));
+    // assert_eq!(balance, Nat::from(99_950_000_000_000_000_u128));
+
+    chain_link_ledger_id
 }
 
 #[test]
@@ -1119,6 +3007,11 @@ fn should_activate_swap_feature() {
 
     five_ticks(&pic);
 
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
+    five_ticks(&pic);
+
     // Check deposit
     // Based on the logs there should be 100_000_000_000_000_000 - deposit fees(50_000_000_000_000_u64)= 99_950_000_000_000_000 icBNB minted for Native to b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe
     let balance = query_call::<Account, Nat>(
@@ -1295,6 +3188,8 @@ fn should_activate_swap_feature() {
         WithdrawalArg {
             amount: Nat::from(940_000_000_000_000_u128),
             recipient: "0x3bcE376777eCFeb93953cc6C1bB957fbAcb1A261".to_string(),
+            created_at_time: None,
+            allow_contract_destination: None,
         },
         Some(
             Principal::from_text("b4any-vxcgx-dm654-xhumb-4pl7k-5kysk-qnjlt-w7hcb-2hd2h-ttzpz-fqe")
@@ -1337,6 +3232,11 @@ fn should_activate_swap_feature() {
         MOCK_TRANSACTION_COUNT_LATEST,
     );
 
+    five_ticks(&pic);
+
+    // The destination is checked for code once the transaction is created
+    submit_mock_http_responses_with_method(&pic, "eth_getCode", MOCK_GET_CODE_EOA);
+
     five_ticks(&pic);
     five_ticks(&pic);
     //
@@ -1409,7 +3309,88 @@ fn should_activate_swap_feature() {
 
     let dex_canister_id: Principal = Principal::from_text("nbepk-iyaaa-aaaad-qhlma-cai").unwrap();
 
-    update_call::<ActivateSwapReqest, Nat>(
+    let swap_activation_request = ActivateSwapReqest {
+        twin_usdc_ledger_id: ic_usdc_ledger_id,
+        swap_contract_address: swap_contract_address.to_string(),
+        twin_usdc_decimals: 6,
+        dex_canister_id,
+        canister_signing_fee_twin_usdc_value: Nat::from(50_000_u32),
+    };
+    let activate_swap = |request: ActivateSwapReqest| {
+        update_call::<ActivateSwapReqest, Result<Nat, ActivateSwapError>>(
+            &pic,
+            minter_principal(),
+            "activate_swap_feature",
+            request,
+            Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
+        )
+    };
+
+    // Invalid requests are rejected before anything is burnt
+    assert_eq!(
+        activate_swap(ActivateSwapReqest {
+            twin_usdc_ledger_id: icp_principal(),
+            ..swap_activation_request.clone()
+        }),
+        Err(ActivateSwapError::TokenNotFound {
+            twin_usdc_ledger_id: icp_principal()
+        })
+    );
+    assert!(matches!(
+        activate_swap(ActivateSwapReqest {
+            swap_contract_address: "0xinvalid".to_string(),
+            ..swap_activation_request.clone()
+        }),
+        Err(ActivateSwapError::InvalidSwapContractAddress(_))
+    ));
+    assert!(matches!(
+        activate_swap(ActivateSwapReqest {
+            canister_signing_fee_twin_usdc_value: Nat::from(u128::MAX)
+                * Nat::from(u128::MAX)
+                * Nat::from(2_u8),
+            ..swap_activation_request.clone()
+        }),
+        Err(ActivateSwapError::InvalidFeeValue(_))
+    ));
+
+    // Revoking the allowance makes the native burn fail
+    let set_minter_allowance = |amount: u128| {
+        update_call::<ApproveArgs, Result<Nat, ApproveError>>(
+            &pic,
+            native_ledger_principal(),
+            "icrc2_approve",
+            ApproveArgs {
+                from_subaccount: None,
+                spender: Account {
+                    owner: minter_principal(),
+                    subaccount: None,
+                },
+                amount: Nat::from(amount),
+                expected_allowance: None,
+                expires_at: None,
+                fee: None,
+                memo: None,
+                created_at_time: None,
+            },
+            Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
+        )
+        .unwrap()
+    };
+    set_minter_allowance(0);
+
+    assert!(matches!(
+        activate_swap(swap_activation_request.clone()),
+        Err(ActivateSwapError::NativeLedgerError {
+            error: LedgerError::InsufficientAllowance { .. }
+        })
+    ));
+    let minter_info = query_call::<(), MinterInfo>(&pic, minter_principal(), "get_minter_info", ());
+    assert!(!minter_info.is_swapping_active);
+    assert_eq!(minter_info.swap_contract_address, None);
+
+    set_minter_allowance(1_000_000_000_000_000);
+
+    update_call::<ActivateSwapReqest, Result<Nat, ActivateSwapError>>(
         &pic,
         minter_principal(),
         "activate_swap_feature",
@@ -1421,7 +3402,8 @@ fn should_activate_swap_feature() {
             canister_signing_fee_twin_usdc_value: Nat::from(50_000_u32),
         },
         Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
-    );
+    )
+    .expect("swap activation should succeed");
 
     five_ticks(&pic);
     five_ticks(&pic);
@@ -1505,6 +3487,55 @@ fn should_activate_swap_feature() {
     );
 
     println!("{minter_info:?}");
+
+    let usdc_address = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".to_string();
+    let approval = CandidOnchainApproval {
+        erc20_contract_address: usdc_address.clone(),
+        spender: swap_contract_address.to_string(),
+        granted_at: minter_info.onchain_approvals.as_ref().unwrap()[0].granted_at,
+        transaction_hash: "0x338927f24d89e7dbc8f0063c703f03360bcb6d21c7608c88a0641f7acd4d6999"
+            .to_string(),
+    };
+    assert_eq!(minter_info.onchain_approvals, Some(vec![approval.clone()]));
+    assert_eq!(
+        query_call::<(), Vec<CandidOnchainApproval>>(
+            &pic,
+            minter_principal(),
+            "get_onchain_approvals",
+            ()
+        ),
+        vec![approval.clone()]
+    );
+
+    let revoke_approval = |token: &str, spender: &str| {
+        decode_wasm_result::<Result<Nat, RevokeApprovalError>>(pic.update_call(
+            minter_principal(),
+            Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap(),
+            "revoke_approval",
+            candid::encode_args((token.to_string(), spender.to_string())).unwrap(),
+        ))
+        .unwrap()
+    };
+
+    // Only outstanding approvals can be revoked
+    assert_eq!(
+        revoke_approval(&usdc_address, &Address::ZERO.to_string()),
+        Err(RevokeApprovalError::ApprovalNotFound)
+    );
+
+    set_minter_allowance(1_000_000_000_000_000);
+    revoke_approval(&usdc_address, &swap_contract_address.to_string())
+        .expect("approval revocation should succeed");
+
+    // Swapping is deactivated as soon as the revocation is accepted, the approval is only
+    // cleared once the revocation transaction is finalized.
+    let minter_info = query_call::<(), MinterInfo>(&pic, minter_principal(), "get_minter_info", ());
+    assert!(!minter_info.is_swapping_active);
+    assert_eq!(minter_info.onchain_approvals, Some(vec![approval]));
+    assert_eq!(
+        revoke_approval(&usdc_address, &swap_contract_address.to_string()),
+        Err(RevokeApprovalError::RevocationPending)
+    );
 }
 
 pub mod mock_rpc_https_responses {
@@ -1651,6 +3682,78 @@ pub mod mock_rpc_https_responses {
         ]
     }"#;
 
+    // Four native deposits of 0.1 to the same principal, discovered in one eth_getLogs response.
+    pub const MOCK_GET_LOGS_MULTIPLE_DEPOSITS: &str = r#"{
+        "jsonrpc": "2.0",
+        "id": 3,
+        "result": [
+            {
+                "address": "0x733a1beef5a02990aad285d7ed93fc1b622eef1d",
+                "topics": [
+                    "0xdeaddf8708b62ae1bf8ec4693b523254aa961b2da6bc5be57f3188ee784d6275",
+                    "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    "0x000000000000000000000000000000000000000000000000016345785d8a0000",
+                    "0x1de235c6cf77973d181e3d7f5755892a0d4ae76f9c41d1c7a3ce797e4b020000"
+                ],
+                "data": "0x0000000000000000000000005d737f982696fe2fe4ef1c7584e914c3a8e44d540000000000000000000000000000000000000000000000000000000000000000",
+                "blockNumber": "0x2bd0f45",
+                "transactionHash": "0xcde530df6850bd19f822264791dac4f6730caa8642f65bd3810389bf982babfe",
+                "transactionIndex": "0x4",
+                "blockHash": "0xc1ff7931ceab1152c911cbb033bb5f6dad378263e3849cb7c5d90711fcbe352c",
+                "logIndex": "0x3",
+                "removed": false
+            },
+            {
+                "address": "0x733a1beef5a02990aad285d7ed93fc1b622eef1d",
+                "topics": [
+                    "0xdeaddf8708b62ae1bf8ec4693b523254aa961b2da6bc5be57f3188ee784d6275",
+                    "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    "0x000000000000000000000000000000000000000000000000016345785d8a0000",
+                    "0x1de235c6cf77973d181e3d7f5755892a0d4ae76f9c41d1c7a3ce797e4b020000"
+                ],
+                "data": "0x0000000000000000000000005d737f982696fe2fe4ef1c7584e914c3a8e44d540000000000000000000000000000000000000000000000000000000000000000",
+                "blockNumber": "0x2bd0f45",
+                "transactionHash": "0x5a3b2b5e4f4e1c6e2a0c3f1b8d7e6a5c4b3a29180f7e6d5c4b3a291807f6e5d4",
+                "transactionIndex": "0x4",
+                "blockHash": "0xc1ff7931ceab1152c911cbb033bb5f6dad378263e3849cb7c5d90711fcbe352c",
+                "logIndex": "0x7",
+                "removed": false
+            },
+            {
+                "address": "0x733a1beef5a02990aad285d7ed93fc1b622eef1d",
+                "topics": [
+                    "0xdeaddf8708b62ae1bf8ec4693b523254aa961b2da6bc5be57f3188ee784d6275",
+                    "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    "0x000000000000000000000000000000000000000000000000016345785d8a0000",
+                    "0x1de235c6cf77973d181e3d7f5755892a0d4ae76f9c41d1c7a3ce797e4b020000"
+                ],
+                "data": "0x0000000000000000000000005d737f982696fe2fe4ef1c7584e914c3a8e44d540000000000000000000000000000000000000000000000000000000000000000",
+                "blockNumber": "0x2bd0f45",
+                "transactionHash": "0x9f8e7d6c5b4a39281706f5e4d3c2b1a09f8e7d6c5b4a39281706f5e4d3c2b1a0",
+                "transactionIndex": "0x4",
+                "blockHash": "0xc1ff7931ceab1152c911cbb033bb5f6dad378263e3849cb7c5d90711fcbe352c",
+                "logIndex": "0xa",
+                "removed": false
+            },
+            {
+                "address": "0x733a1beef5a02990aad285d7ed93fc1b622eef1d",
+                "topics": [
+                    "0xdeaddf8708b62ae1bf8ec4693b523254aa961b2da6bc5be57f3188ee784d6275",
+                    "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    "0x000000000000000000000000000000000000000000000000016345785d8a0000",
+                    "0x1de235c6cf77973d181e3d7f5755892a0d4ae76f9c41d1c7a3ce797e4b020000"
+                ],
+                "data": "0x0000000000000000000000005d737f982696fe2fe4ef1c7584e914c3a8e44d540000000000000000000000000000000000000000000000000000000000000000",
+                "blockNumber": "0x2bd0f45",
+                "transactionHash": "0x1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f90a",
+                "transactionIndex": "0x4",
+                "blockHash": "0xc1ff7931ceab1152c911cbb033bb5f6dad378263e3849cb7c5d90711fcbe352c",
+                "logIndex": "0xc",
+                "removed": false
+            }
+        ]
+    }"#;
+
     pub const MOCK_GET_LOGS_ERC20: &str = r#"{
         "jsonrpc": "2.0",
         "id": 3,
@@ -1806,8 +3909,19 @@ pub mod mock_rpc_https_responses {
         "result": []
     }"#;
 
+    pub const MOCK_GET_LOGS_RESULT_SET_TOO_LARGE: &str = r#"{
+        "jsonrpc": "2.0",
+        "id": 1,
+        "error": {
+            "code": -32005,
+            "message": "query returned more than 10000 results"
+        }
+    }"#;
+
     pub const MOCK_TRANSACTION_COUNT_LATEST: &str = r#"{"id":1,"jsonrpc":"2.0","result":"0x0"}"#;
 
+    pub const MOCK_GET_CODE_EOA: &str = r#"{"id":1,"jsonrpc":"2.0","result":"0x"}"#;
+
     pub const MOCK_TRANSACTION_COUNT_BSC_LATEST: &str =
         r#"{"id":1,"jsonrpc":"2.0","result":"0x0"}"#;
     pub const MOCK_TRANSACTION_COUNT_BASE_LATEST: &str =
@@ -1838,6 +3952,9 @@ pub mod mock_rpc_https_responses {
     pub const MOCK_TRANSACTION_COUNT_FINALIZED_SWAP_BASE: &str =
         r#"{"id":1,"jsonrpc":"2.0","result":"0x2"}"#;
 
+    pub const MOCK_TRANSACTION_RECEIPT_NOT_FOUND: &str =
+        r#"{"id":1,"jsonrpc":"2.0","result":null}"#;
+
     pub const MOCK_TRANSACTION_RECEIPT: &str = r#"{
         "jsonrpc": "2.0",
         "id": 1,
@@ -2146,6 +4263,13 @@ pub mod mock_rpc_https_responses {
     ]
 }"#;
 
+    // Every scraping round that scraped new blocks ends by fetching the header of the last
+    // scraped block, for its timestamp.
+    pub fn submit_last_scraped_block_header(pic: &PocketIc) {
+        let canister_http_requests = pic.get_canister_http();
+        generate_and_submit_mock_http_response(pic, &canister_http_requests, 0, MOCK_BLOCK_NUMBER);
+    }
+
     pub fn generate_and_submit_mock_http_response(
         pic: &PocketIc,
         http_requests_list: &[CanisterHttpRequest],
@@ -45,7 +45,10 @@ use super::ledger_arguments::{
 };
 
 use crate::{
-    candid_types::{CandidBlockTag, Erc20Token, GasTankBalance, MinterInfo},
+    candid_types::{
+        CandidBlockTag, CandidConfirmationPolicy, Erc20Token, Erc20TransferKind, GasTankBalance,
+        MinterInfo, PendingWithdrawalStats, WithdrawalTransactionStats,
+    },
     evm_config::EvmNetwork,
     lifecycle::{InitArg, MinterArg, UpgradeArg},
     lsm_client::WasmHash,
@@ -77,6 +80,10 @@ fn should_create_and_install_and_upgrade_minter_canister() {
     five_ticks(&pic);
 
     let minter_info = query_call::<_, MinterInfo>(&pic, canister_id, "get_minter_info", ());
+    let wasm_hash = hex::encode(ic_crypto_sha2::Sha256::hash(MINTER_WASM_BYTES));
+    let canister_version = minter_info
+        .canister_version
+        .expect("canister version should be known");
 
     assert_eq!(
         minter_info,
@@ -114,7 +121,26 @@ fn should_create_and_install_and_upgrade_minter_canister() {
                 usdc_balance: Nat::from(0_u8)
             }),
             last_native_token_usd_price_estimate: None,
-            next_swap_ledger_burn_index: None
+            next_swap_ledger_burn_index: None,
+            total_ledger_call_cycles_spent: Some(Nat::from(0_u8)),
+            confirmation_policy: Some(CandidConfirmationPolicy::BlockTag(CandidBlockTag::Latest)),
+            effective_confirmed_block_number: None,
+            pending_withdrawal_stats: Some(PendingWithdrawalStats::default()),
+            oldest_pending_withdrawal_age_ns: None,
+            token_volumes: Some(vec![]),
+            wasm_hash: Some(wasm_hash.clone()),
+            canister_version: Some(canister_version),
+            onchain_approvals: Some(vec![]),
+            last_observed_block_timestamp: None,
+            last_scraped_block_timestamp: None,
+            last_observed_block_lag_seconds: None,
+            last_scraped_block_lag_seconds: None,
+            ecdsa_signing_calls: Some(0),
+            ecdsa_signing_cycles_spent: Some(Nat::from(0_u8)),
+            estimated_remaining_signing_capacity: minter_info.estimated_remaining_signing_capacity,
+            max_fee_per_gas_cap: Some(Nat::from(500_000_000_000_u64)),
+            max_transaction_fee_cap: Some(Nat::from(50_000_000_000_000_000_u64)),
+            withdrawal_transaction_stats: Some(WithdrawalTransactionStats::default()),
         }
     );
 
@@ -129,6 +155,13 @@ fn should_create_and_install_and_upgrade_minter_canister() {
         min_max_priority_fee_per_gas: None,
         deposit_native_fee: None,
         withdrawal_native_fee: Some(Nat::from(200_000_000_000_000_u64)),
+        gas_fee_config: None,
+        confirmations: None,
+        gas_limits: None,
+        fee_collector: None,
+        refund_deadline_window_secs: None,
+        max_fee_per_gas_cap: None,
+        max_transaction_fee_cap: None,
     });
     let upgrade_bytes = candid::encode_one(upgrade_args).unwrap();
 
@@ -176,9 +209,30 @@ fn should_create_and_install_and_upgrade_minter_canister() {
                 usdc_balance: Nat::from(0_u8)
             }),
             last_native_token_usd_price_estimate: None,
-            next_swap_ledger_burn_index: None
+            next_swap_ledger_burn_index: None,
+            total_ledger_call_cycles_spent: Some(Nat::from(0_u8)),
+            confirmation_policy: Some(CandidConfirmationPolicy::BlockTag(CandidBlockTag::Latest)),
+            effective_confirmed_block_number: None,
+            pending_withdrawal_stats: Some(PendingWithdrawalStats::default()),
+            oldest_pending_withdrawal_age_ns: None,
+            token_volumes: Some(vec![]),
+            wasm_hash: Some(wasm_hash),
+            canister_version: minter_info_after_upgrade.canister_version,
+            onchain_approvals: Some(vec![]),
+            last_observed_block_timestamp: None,
+            last_scraped_block_timestamp: None,
+            last_observed_block_lag_seconds: None,
+            last_scraped_block_lag_seconds: None,
+            ecdsa_signing_calls: Some(0),
+            ecdsa_signing_cycles_spent: Some(Nat::from(0_u8)),
+            estimated_remaining_signing_capacity: minter_info_after_upgrade
+                .estimated_remaining_signing_capacity,
+            max_fee_per_gas_cap: Some(Nat::from(500_000_000_000_u64)),
+            max_transaction_fee_cap: Some(Nat::from(50_000_000_000_000_000_u64)),
+            withdrawal_transaction_stats: Some(WithdrawalTransactionStats::default()),
         }
     );
+    assert!(minter_info_after_upgrade.canister_version > Some(canister_version));
 }
 
 #[test]
@@ -412,7 +466,8 @@ fn should_install_lsm_canister_and_create_ledger_suite() {
         Erc20Token {
             erc20_token_symbol: "icUSDT".to_string(),
             erc20_contract_address: "0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string(),
-            ledger_canister_id: ic_usdt_ledger.0
+            ledger_canister_id: ic_usdt_ledger.0,
+            transfer_kind: Erc20TransferKind::Standard,
         }
     )
 }
@@ -504,6 +559,7 @@ fn install_minter_canister(pic: &PocketIc, canister_id: Principal) {
         ledger_suite_manager_id: "kmcdp-4yaaa-aaaag-ats3q-cai".parse().unwrap(),
         deposit_native_fee: Nat::from(0_u8),
         withdrawal_native_fee: Nat::from(100_000_000_000_000_u64),
+        fee_collector: None,
     });
     let init_bytes = candid::encode_one(init_args).unwrap();
 
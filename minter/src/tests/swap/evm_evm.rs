@@ -10,11 +10,12 @@ use crate::{
     },
     tests::{
         minter_flow_tets::mock_rpc_https_responses::{
-            generate_and_submit_mock_http_response, MOCK_BSC_FEE_HISTORY_INNER,
-            MOCK_FAILED_TRANSACTION_RECEIPT_SWAP_BSC, MOCK_GET_SWAP_CONTRACT_BASE_LOGS,
-            MOCK_SEND_TRANSACTION_SUCCESS, MOCK_SWAP_BASE_BLOCK_NUMBER,
-            MOCK_TRANSACTION_COUNT_FINALIZED_SWAP_BSC, MOCK_TRANSACTION_COUNT_LATEST_SWAP_BSC,
-            MOCK_TRANSACTION_RECEIPT_SWAP_BSC, MOCK_TRANSACTION_RECEIPT_SWAP_BSC_REFUND,
+            generate_and_submit_mock_http_response, submit_last_scraped_block_header,
+            MOCK_BSC_FEE_HISTORY_INNER, MOCK_FAILED_TRANSACTION_RECEIPT_SWAP_BSC,
+            MOCK_GET_SWAP_CONTRACT_BASE_LOGS, MOCK_SEND_TRANSACTION_SUCCESS,
+            MOCK_SWAP_BASE_BLOCK_NUMBER, MOCK_TRANSACTION_COUNT_FINALIZED_SWAP_BSC,
+            MOCK_TRANSACTION_COUNT_LATEST_SWAP_BSC, MOCK_TRANSACTION_RECEIPT_SWAP_BSC,
+            MOCK_TRANSACTION_RECEIPT_SWAP_BSC_REFUND,
         },
         pocket_ic_helpers::{create_pic, five_ticks, query_call, update_call},
         swap::helpers::{
@@ -51,6 +52,7 @@ fn evm_to_evm_swap_happy_path() {
             latest_block_number: Nat::from(23_403_000_u128),
             fee_history: MOCK_BSC_FEE_HISTORY_INNER.into(),
             native_token_usd_price: Some(900.73),
+            latest_block_timestamp: None,
         },
         Some(Principal::from_text(RPC_HELPER_PRINCIPAL).unwrap()),
     );
@@ -90,6 +92,11 @@ fn evm_to_evm_swap_happy_path() {
     five_ticks(&pic);
     five_ticks(&pic);
 
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
+    five_ticks(&pic);
+
     let canister_http_requests = pic.get_canister_http();
 
     // Generating the latest transaction count for inserting the correct nonce
@@ -230,6 +237,7 @@ fn evm_to_evm_swap_refund_path() {
             latest_block_number: Nat::from(23_403_000_u128),
             fee_history: MOCK_BSC_FEE_HISTORY_INNER.into(),
             native_token_usd_price: Some(3000.73),
+            latest_block_timestamp: None,
         },
         Some(Principal::from_text(RPC_HELPER_PRINCIPAL).unwrap()),
     );
@@ -269,6 +277,11 @@ fn evm_to_evm_swap_refund_path() {
     five_ticks(&pic);
     five_ticks(&pic);
 
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
+    five_ticks(&pic);
+
     let canister_http_requests = pic.get_canister_http();
 
     // Generating the latest transaction count for inserting the correct nonce
@@ -405,6 +418,7 @@ fn evm_to_evm_swap_refund_after_failed_evm_swap() {
             latest_block_number: Nat::from(23_403_000_u128),
             fee_history: MOCK_BSC_FEE_HISTORY_INNER.into(),
             native_token_usd_price: Some(900.73),
+            latest_block_timestamp: None,
         },
         Some(Principal::from_text(RPC_HELPER_PRINCIPAL).unwrap()),
     );
@@ -444,6 +458,11 @@ fn evm_to_evm_swap_refund_after_failed_evm_swap() {
     five_ticks(&pic);
     five_ticks(&pic);
 
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(&pic);
+
+    five_ticks(&pic);
+
     let canister_http_requests = pic.get_canister_http();
 
     // Generating the latest transaction count for inserting the correct nonce
@@ -512,6 +531,7 @@ fn evm_to_evm_swap_refund_after_failed_evm_swap() {
             latest_block_number: Nat::from(23_403_000_u128),
             fee_history: MOCK_BSC_FEE_HISTORY_INNER.into(),
             native_token_usd_price: Some(900.73),
+            latest_block_timestamp: None,
         },
         Some(Principal::from_text(RPC_HELPER_PRINCIPAL).unwrap()),
     );
@@ -55,6 +55,7 @@ fn evm_to_icp_swap_happy_path() {
             latest_block_number: Nat::from(23_403_000_u128),
             fee_history: MOCK_BSC_FEE_HISTORY_INNER.into(),
             native_token_usd_price: Some(900.73),
+            latest_block_timestamp: None,
         },
         Some(Principal::from_text(RPC_HELPER_PRINCIPAL).unwrap()),
     );
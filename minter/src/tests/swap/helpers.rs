@@ -9,7 +9,8 @@ use pocket_ic::PocketIc;
 
 use crate::candid_types::chain_data::ChainData;
 use crate::candid_types::{
-    ActivateSwapReqest, AddErc20Token, CandidBlockTag, MinterInfo, RequestScrapingError,
+    ActivateSwapError, ActivateSwapReqest, AddErc20Token, CandidBlockTag, MinterInfo,
+    RequestScrapingError,
 };
 use crate::lifecycle::{InitArg, MinterArg};
 use crate::tests::dex_types::{
@@ -18,14 +19,14 @@ use crate::tests::dex_types::{
 };
 
 use crate::tests::minter_flow_tets::mock_rpc_https_responses::{
-    generate_and_submit_mock_http_response, MOCK_BASE_BLOCK_NUMBER, MOCK_BASE_FEE_HISTORY_INNER,
-    MOCK_BASE_FEE_HISTORY_RESPONSE, MOCK_BASE_HIGHER_BLOCK_NUMBER, MOCK_BSC_BLOCK_NUMBER,
-    MOCK_BSC_FEE_HISTORY_INNER, MOCK_BSC_FEE_HISTORY_RESPONSE, MOCK_BSC_HIGHER_BLOCK_NUMBER,
-    MOCK_GET_BASE_LOGS_ERC20, MOCK_GET_BSC_LOGS_ERC20, MOCK_GET_LOGS_EMPTY,
-    MOCK_SEND_TRANSACTION_SUCCESS, MOCK_TRANSACTION_COUNT_BASE_FINALIZED,
-    MOCK_TRANSACTION_COUNT_BASE_LATEST, MOCK_TRANSACTION_COUNT_BSC_FINALIZED,
-    MOCK_TRANSACTION_COUNT_BSC_LATEST, MOCK_TRANSACTION_RECEIPT_APPROVE_BASE_ERC20,
-    MOCK_TRANSACTION_RECEIPT_APPROVE_BSC_ERC20,
+    generate_and_submit_mock_http_response, submit_last_scraped_block_header,
+    MOCK_BASE_BLOCK_NUMBER, MOCK_BASE_FEE_HISTORY_INNER, MOCK_BASE_FEE_HISTORY_RESPONSE,
+    MOCK_BASE_HIGHER_BLOCK_NUMBER, MOCK_BSC_BLOCK_NUMBER, MOCK_BSC_FEE_HISTORY_INNER,
+    MOCK_BSC_FEE_HISTORY_RESPONSE, MOCK_BSC_HIGHER_BLOCK_NUMBER, MOCK_GET_BASE_LOGS_ERC20,
+    MOCK_GET_BSC_LOGS_ERC20, MOCK_GET_LOGS_EMPTY, MOCK_SEND_TRANSACTION_SUCCESS,
+    MOCK_TRANSACTION_COUNT_BASE_FINALIZED, MOCK_TRANSACTION_COUNT_BASE_LATEST,
+    MOCK_TRANSACTION_COUNT_BSC_FINALIZED, MOCK_TRANSACTION_COUNT_BSC_LATEST,
+    MOCK_TRANSACTION_RECEIPT_APPROVE_BASE_ERC20, MOCK_TRANSACTION_RECEIPT_APPROVE_BSC_ERC20,
 };
 use crate::tests::pocket_ic_helpers::{
     create_evm_rpc_canister, create_icp_ledger_canister, create_lsm_canister, create_pic,
@@ -85,6 +86,7 @@ fn install_bsc_minter_canister(pic: &PocketIc, canister_id: Principal) {
         ledger_suite_manager_id: "kmcdp-4yaaa-aaaag-ats3q-cai".parse().unwrap(),
         deposit_native_fee: Nat::from(0_u8),
         withdrawal_native_fee: Nat::from(100_000_000_000_000_u64),
+        fee_collector: None,
     });
     let init_bytes = candid::encode_one(init_args).unwrap();
 
@@ -205,6 +207,7 @@ fn install_base_minter_canister(pic: &PocketIc, canister_id: Principal) {
         ledger_suite_manager_id: "kmcdp-4yaaa-aaaag-ats3q-cai".parse().unwrap(),
         deposit_native_fee: Nat::from(0_u8),
         withdrawal_native_fee: Nat::from(15_000_000_000_000_u128),
+        fee_collector: None,
     });
     let init_bytes = candid::encode_one(init_args).unwrap();
 
@@ -656,6 +659,11 @@ pub fn install_bsc_minter_and_setup(pic: &PocketIc) {
     five_ticks(pic);
     five_ticks(pic);
 
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(pic);
+
+    five_ticks(pic);
+
     update_call::<AddErc20Token, ()>(
         pic,
         bsc_minter_id,
@@ -663,8 +671,10 @@ pub fn install_bsc_minter_and_setup(pic: &PocketIc) {
         AddErc20Token {
             chain_id: Nat::from(56_u8),
             address: "0x8AC76a51cc950d9822D68b83fE1Ad97B32Cd580d".to_string(),
-            erc20_token_symbol: "icUSDC.bsc".to_string(),
+            erc20_token_symbol: "icUSDCbsc".to_string(),
             erc20_ledger_id: ic_usdc_bsc_principal(),
+            transfer_kind: None,
+            symbol_collision_policy: None,
         },
         Some(Principal::from_text("kmcdp-4yaaa-aaaag-ats3q-cai").unwrap()),
     );
@@ -717,6 +727,11 @@ pub fn install_bsc_minter_and_setup(pic: &PocketIc) {
 
     five_ticks(pic);
 
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(pic);
+
+    five_ticks(pic);
+
     // update the gas estimate, block number and native usdc price
     update_call::<ChainData, ()>(
         pic,
@@ -726,6 +741,7 @@ pub fn install_bsc_minter_and_setup(pic: &PocketIc) {
             latest_block_number: Nat::from(23_402_978_u128),
             fee_history: MOCK_BSC_FEE_HISTORY_INNER.into(),
             native_token_usd_price: Some(1001.73),
+            latest_block_timestamp: None,
         },
         Some(Principal::from_text(RPC_HELPER_PRINCIPAL).unwrap()),
     );
@@ -760,7 +776,7 @@ pub fn install_bsc_minter_and_setup(pic: &PocketIc) {
 
     let dex_canister_id: Principal = Principal::from_text("nbepk-iyaaa-aaaad-qhlma-cai").unwrap();
 
-    update_call::<ActivateSwapReqest, Nat>(
+    update_call::<ActivateSwapReqest, Result<Nat, ActivateSwapError>>(
         pic,
         bsc_minter_id,
         "activate_swap_feature",
@@ -773,7 +789,8 @@ pub fn install_bsc_minter_and_setup(pic: &PocketIc) {
             canister_signing_fee_twin_usdc_value: Nat::from(30_000_000_000_000_000_u128),
         },
         Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
-    );
+    )
+    .expect("swap activation should succeed");
 
     five_ticks(pic);
     five_ticks(pic);
@@ -889,6 +906,11 @@ pub fn install_base_minter_and_setup(pic: &PocketIc) {
 
     five_ticks(pic);
 
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(pic);
+
+    five_ticks(pic);
+
     // install icUSDC.base
     // Calling icrc2_approve and giving the permission to lsm for taking funds from users principal
     let _approve_result = update_call::<ApproveArgs, Result<Nat, ApproveError>>(
@@ -921,8 +943,10 @@ pub fn install_base_minter_and_setup(pic: &PocketIc) {
         AddErc20Token {
             chain_id: Nat::from(8453_u128),
             address: "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".to_string(),
-            erc20_token_symbol: "icUSDC.base".to_string(),
+            erc20_token_symbol: "icUSDCbase".to_string(),
             erc20_ledger_id: ic_usdc_base_principal(),
+            transfer_kind: None,
+            symbol_collision_policy: None,
         },
         Some(Principal::from_text("kmcdp-4yaaa-aaaag-ats3q-cai").unwrap()),
     );
@@ -975,6 +999,11 @@ pub fn install_base_minter_and_setup(pic: &PocketIc) {
     five_ticks(pic);
     five_ticks(pic);
 
+    // eth_getBlockByNumber for the timestamp of the last scraped block
+    submit_last_scraped_block_header(pic);
+
+    five_ticks(pic);
+
     // update the gas estimate, block number and native usdc price
     update_call::<ChainData, ()>(
         pic,
@@ -984,6 +1013,7 @@ pub fn install_base_minter_and_setup(pic: &PocketIc) {
             latest_block_number: Nat::from(572_530_890_u128),
             fee_history: MOCK_BASE_FEE_HISTORY_INNER.into(),
             native_token_usd_price: Some(4475.43),
+            latest_block_timestamp: None,
         },
         Some(Principal::from_text(RPC_HELPER_PRINCIPAL).unwrap()),
     );
@@ -1018,7 +1048,7 @@ pub fn install_base_minter_and_setup(pic: &PocketIc) {
 
     let dex_canister_id: Principal = Principal::from_text("nbepk-iyaaa-aaaad-qhlma-cai").unwrap();
 
-    update_call::<ActivateSwapReqest, Nat>(
+    update_call::<ActivateSwapReqest, Result<Nat, ActivateSwapError>>(
         pic,
         base_minter_id,
         "activate_swap_feature",
@@ -1031,7 +1061,8 @@ pub fn install_base_minter_and_setup(pic: &PocketIc) {
             canister_signing_fee_twin_usdc_value: Nat::from(30_000_u128),
         },
         Some(Principal::from_text(APPIC_CONTROLLER_PRINCIPAL).unwrap()),
-    );
+    )
+    .expect("swap activation should succeed");
 
     five_ticks(pic);
     five_ticks(pic);
@@ -33,6 +33,7 @@ fn icp_to_evm_swap_happy_path() {
             latest_block_number: Nat::from(572_530_960_u128),
             fee_history: MOCK_BASE_FEE_HISTORY_INNER.into(),
             native_token_usd_price: Some(4021.73),
+            latest_block_timestamp: None,
         },
         Some(Principal::from_text(RPC_HELPER_PRINCIPAL).unwrap()),
     );
@@ -1,3 +1,4 @@
+pub mod cancellation;
 pub mod gas_fees;
 pub mod gas_usd;
 
@@ -124,9 +125,11 @@ impl AsRef<Eip1559TransactionRequest> for Eip1559TransactionRequest {
 
 /// Generic struct that wraps a transaction and its associated resubmission strategy.
 /// This is used for managing transactions that may need to be resubmitted due to network conditions.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
 pub struct Resubmittable<T> {
-    pub transaction: T,                     // The transaction being wrapped.
+    #[n(0)]
+    pub transaction: T, // The transaction being wrapped.
+    #[n(1)]
     pub resubmission: ResubmissionStrategy, // Strategy to use when resubmitting the transaction.
 }
 
@@ -155,10 +158,20 @@ impl<T> AsRef<T> for Resubmittable<T> {
 
 /// Enum representing different strategies for resubmitting a transaction.
 /// These strategies determine how to adjust the transaction parameters, such as fees, during resubmission.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
 pub enum ResubmissionStrategy {
-    ReduceEthAmount { withdrawal_amount: Wei }, // Strategy to reduce the Ether amount sent to cover fees.
-    GuaranteeEthAmount { allowed_max_transaction_fee: Wei }, // Strategy to ensure a specific amount of Ether is sent, regardless of fees.
+    // Strategy to reduce the Ether amount sent to cover fees.
+    #[n(0)]
+    ReduceEthAmount {
+        #[n(0)]
+        withdrawal_amount: Wei,
+    },
+    // Strategy to ensure a specific amount of Ether is sent, regardless of fees.
+    #[n(1)]
+    GuaranteeEthAmount {
+        #[n(0)]
+        allowed_max_transaction_fee: Wei,
+    },
 }
 
 // Implements methods for `ResubmissionStrategy` to retrieve the maximum allowed transaction fee based on the strategy.
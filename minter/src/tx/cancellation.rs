@@ -0,0 +1,143 @@
+use super::gas_fees::GasFeeEstimate;
+use super::{AccessList, Eip1559TransactionRequest};
+use crate::numeric::{GasAmount, Wei, WeiPerGas};
+use evm_rpc_client::eth_types::Address;
+
+/// Builds a transaction that frees the nonce of a stuck transaction.
+///
+/// The cancellation transfers zero native tokens from the minter to itself with the same nonce
+/// as the stuck transaction, so that whichever of the two gets mined the nonce is consumed.
+/// Nodes only accept a replacement if both fees are bumped by at least 10%, hence the
+/// cancellation pays at least 10% more than the stuck transaction per unit of gas.
+/// `gas_limit` is the gas limit of a native withdrawal on the network.
+pub fn create_cancellation_transaction(
+    stuck_transaction: &Eip1559TransactionRequest,
+    minter_address: Address,
+    gas_fee_estimate: GasFeeEstimate,
+    gas_limit: GasAmount,
+) -> Eip1559TransactionRequest {
+    let max_priority_fee_per_gas = plus_10_percent(stuck_transaction.max_priority_fee_per_gas)
+        .max(gas_fee_estimate.max_priority_fee_per_gas);
+    let max_fee_per_gas = plus_10_percent(stuck_transaction.max_fee_per_gas).max(
+        GasFeeEstimate {
+            max_priority_fee_per_gas,
+            ..gas_fee_estimate
+        }
+        .min_max_fee_per_gas(),
+    );
+    Eip1559TransactionRequest {
+        chain_id: stuck_transaction.chain_id,
+        nonce: stuck_transaction.nonce,
+        max_priority_fee_per_gas,
+        max_fee_per_gas,
+        gas_limit,
+        destination: minter_address,
+        amount: Wei::ZERO,
+        data: vec![],
+        access_list: AccessList::new(),
+    }
+}
+
+/// Returns true if the transaction is a cancellation sent to the given minter address.
+pub fn is_cancellation_transaction(
+    transaction: &Eip1559TransactionRequest,
+    minter_address: &Address,
+) -> bool {
+    &transaction.destination == minter_address
+        && transaction.amount == Wei::ZERO
+        && transaction.data.is_empty()
+}
+
+fn plus_10_percent(amount: WeiPerGas) -> WeiPerGas {
+    amount
+        .checked_add(
+            amount
+                .checked_div_ceil(10_u8)
+                .expect("BUG: must be Some() because divisor is non-zero"),
+        )
+        .unwrap_or(WeiPerGas::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_config::NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT;
+    use crate::numeric::TransactionNonce;
+    use crate::tx::gas_fees::DEFAULT_BASE_FEE_MULTIPLIER_PERCENT;
+    use std::str::FromStr;
+
+    const MINTER_ADDRESS: &str = "0x1789F79e95324A47c5Fd6693071188e82E9a3558";
+
+    fn stuck_erc20_transaction() -> Eip1559TransactionRequest {
+        Eip1559TransactionRequest {
+            chain_id: 56,
+            nonce: TransactionNonce::from(7_u8),
+            max_priority_fee_per_gas: WeiPerGas::new(1_000_000_000),
+            max_fee_per_gas: WeiPerGas::new(3_000_000_000),
+            gas_limit: GasAmount::new(66_000),
+            destination: Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap(),
+            amount: Wei::ZERO,
+            data: hex::decode("a9059cbb000000000000000000000000b44b5e756a894775fc32eddf3314bb1b1944dc340000000000000000000000000000000000000000000000000000000000000001").unwrap(),
+            access_list: AccessList::new(),
+        }
+    }
+
+    #[test]
+    fn should_replace_stuck_transaction_with_self_transfer() {
+        let minter_address = Address::from_str(MINTER_ADDRESS).unwrap();
+        let stuck = stuck_erc20_transaction();
+        let low_fee_estimate = GasFeeEstimate {
+            base_fee_per_gas: WeiPerGas::ONE,
+            max_priority_fee_per_gas: WeiPerGas::ONE,
+            base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+        };
+
+        let cancellation = create_cancellation_transaction(
+            &stuck,
+            minter_address,
+            low_fee_estimate,
+            NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+        );
+
+        assert_eq!(
+            cancellation,
+            Eip1559TransactionRequest {
+                chain_id: stuck.chain_id,
+                nonce: stuck.nonce,
+                max_priority_fee_per_gas: WeiPerGas::new(1_100_000_000),
+                max_fee_per_gas: WeiPerGas::new(3_300_000_000),
+                gas_limit: NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+                destination: minter_address,
+                amount: Wei::ZERO,
+                data: vec![],
+                access_list: AccessList::new(),
+            }
+        );
+        assert!(is_cancellation_transaction(&cancellation, &minter_address));
+        assert!(!is_cancellation_transaction(&stuck, &minter_address));
+    }
+
+    #[test]
+    fn should_follow_current_fees_when_higher() {
+        let minter_address = Address::from_str(MINTER_ADDRESS).unwrap();
+        let stuck = stuck_erc20_transaction();
+        let high_fee_estimate = GasFeeEstimate {
+            base_fee_per_gas: WeiPerGas::new(10_000_000_000),
+            max_priority_fee_per_gas: WeiPerGas::new(2_000_000_000),
+            base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+        };
+
+        let cancellation = create_cancellation_transaction(
+            &stuck,
+            minter_address,
+            high_fee_estimate,
+            NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+        );
+
+        assert_eq!(
+            cancellation.max_priority_fee_per_gas,
+            WeiPerGas::new(2_000_000_000)
+        );
+        assert_eq!(cancellation.max_fee_per_gas, WeiPerGas::new(12_000_000_000));
+    }
+}
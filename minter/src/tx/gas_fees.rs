@@ -1,41 +1,168 @@
 use crate::{
+    evm_config::{EvmNetwork, GasLimitKind},
     guard::TimerGuard,
+    lifecycle::GasFeeConfigArg,
     logs::{DEBUG, INFO},
-    numeric::{GasAmount, Wei, WeiPerGas},
+    numeric::{BlockNumber, GasAmount, Wei, WeiPerGas},
     rpc_client::{MultiCallError, RpcClient},
-    rpc_declarations::{BlockSpec, BlockTag, CallParams, FeeHistory, FeeHistoryParams, Quantity},
-    state::{mutate_state, read_state, TaskType},
-    withdraw::{
-        ERC20_APPROVAL_TRANSACTION_GAS_LIMIT, ERC20_MINT_TRANSACTION_GAS_LIMIT,
-        ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+    rpc_declarations::{
+        Block, BlockSpec, BlockTag, CallParams, FeeHistory, FeeHistoryParams, Quantity,
     },
+    state::{mutate_state, read_state, TaskType},
 };
-use evm_rpc_client::{eth_types::Address, Hex};
+use evm_rpc_client::eth_types::Address;
 use ic_canister_log::log;
+use minicbor::{Decode, Encode};
 use std::str::FromStr;
 
+/// Multiplier applied to the base fee when no [`GasFeeConfig`] is set, i.e. the base fee is doubled.
+pub const DEFAULT_BASE_FEE_MULTIPLIER_PERCENT: u16 = 200;
+pub const MIN_BASE_FEE_MULTIPLIER_PERCENT: u16 = 100;
+pub const MAX_BASE_FEE_MULTIPLIER_PERCENT: u16 = 300;
+
+/// Upper bound on the fees of the transactions sent by the minter, so that an inflated
+/// fee estimate cannot drain the minter's native balance or the gas tank.
+///
+/// Defaults to the caps of the network, see [`EvmNetwork::default_max_fee_per_gas_cap`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Encode, Decode)]
+pub struct FeeCap {
+    #[n(0)]
+    pub max_fee_per_gas: WeiPerGas,
+    #[n(1)]
+    pub max_transaction_fee: Wei,
+}
+
+impl FeeCap {
+    pub fn for_network(network: EvmNetwork) -> Self {
+        Self {
+            max_fee_per_gas: network.default_max_fee_per_gas_cap(),
+            max_transaction_fee: network.default_max_transaction_fee_cap(),
+        }
+    }
+
+    /// Returns an error if `max_fee_per_gas`, or the maximum fee of a transaction
+    /// with the given gas limit, exceeds the cap.
+    pub fn check(
+        &self,
+        max_fee_per_gas: WeiPerGas,
+        gas_limit: GasAmount,
+    ) -> Result<(), FeeCapExceeded> {
+        let max_transaction_fee = max_fee_per_gas
+            .transaction_cost(gas_limit)
+            .unwrap_or(Wei::MAX);
+        if max_fee_per_gas > self.max_fee_per_gas || max_transaction_fee > self.max_transaction_fee
+        {
+            return Err(FeeCapExceeded {
+                max_fee_per_gas,
+                max_transaction_fee,
+                cap: *self,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeCapExceeded {
+    pub max_fee_per_gas: WeiPerGas,
+    pub max_transaction_fee: Wei,
+    pub cap: FeeCap,
+}
+
+/// Reward percentiles requested in `eth_feeHistory` when a [`GasFeeConfig`] is set.
+/// `GasFeeConfig::reward_percentile_index` selects one of them.
+pub const FEE_HISTORY_REWARD_PERCENTILES: [u8; 3] = [50, 75, 90];
+
+/// Operator settings for estimating transaction fees from the fee history.
+///
+/// Set via the upgrade arguments, see [`GasFeeConfigArg`].
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Decode)]
+pub struct GasFeeConfig {
+    /// Index into the reward percentiles returned for each block of the fee history.
+    #[n(0)]
+    pub reward_percentile_index: u8,
+    /// Percentage of the next block base fee included in `max_fee_per_gas`.
+    #[n(1)]
+    pub base_fee_multiplier_percent: u16,
+    /// Lower bound for `max_priority_fee_per_gas`.
+    #[n(2)]
+    pub min_priority_fee: WeiPerGas,
+}
+
+impl GasFeeConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reward_percentile_index as usize >= FEE_HISTORY_REWARD_PERCENTILES.len() {
+            return Err(format!(
+                "reward_percentile_index must be lower than {}, got {}",
+                FEE_HISTORY_REWARD_PERCENTILES.len(),
+                self.reward_percentile_index
+            ));
+        }
+        if !(MIN_BASE_FEE_MULTIPLIER_PERCENT..=MAX_BASE_FEE_MULTIPLIER_PERCENT)
+            .contains(&self.base_fee_multiplier_percent)
+        {
+            return Err(format!(
+                "base_fee_multiplier_percent must be between {MIN_BASE_FEE_MULTIPLIER_PERCENT} and {MAX_BASE_FEE_MULTIPLIER_PERCENT}, got {}",
+                self.base_fee_multiplier_percent
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<GasFeeConfigArg> for GasFeeConfig {
+    type Error = String;
+
+    fn try_from(arg: GasFeeConfigArg) -> Result<Self, Self::Error> {
+        let min_priority_fee = WeiPerGas::try_from(arg.min_priority_fee)
+            .map_err(|e| format!("invalid min_priority_fee: {e}"))?;
+        let config = GasFeeConfig {
+            reward_percentile_index: arg.reward_percentile_index,
+            base_fee_multiplier_percent: arg.base_fee_multiplier_percent,
+            min_priority_fee,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
 /// Represents an estimate of gas fees.
 ///
-/// Contains the base fee per gas and the maximum priority fee per gas.
+/// Contains the base fee per gas, the maximum priority fee per gas and
+/// the percentage of the base fee included in the maximum fee per gas.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct GasFeeEstimate {
     pub base_fee_per_gas: WeiPerGas,
     pub max_priority_fee_per_gas: WeiPerGas,
+    pub base_fee_multiplier_percent: u16,
 }
 
 impl GasFeeEstimate {
-    /// Computes the maximum fee per gas by doubling the base fee and adding the priority fee.
+    /// Computes the maximum fee per gas by applying the base fee multiplier and adding the priority fee.
     ///
     /// # Returns
     /// An `Option` containing the estimated maximum fee per gas if it does not overflow, otherwise `None`.
     pub fn checked_estimate_max_fee_per_gas(&self) -> Option<WeiPerGas> {
-        self.base_fee_per_gas
-            .checked_mul(2_u8)
+        self.checked_multiplied_base_fee()
             .and_then(|base_fee_estimate| {
                 base_fee_estimate.checked_add(self.max_priority_fee_per_gas)
             })
     }
 
+    // Splits the base fee so that multiplying does not overflow earlier than the result does.
+    fn checked_multiplied_base_fee(&self) -> Option<WeiPerGas> {
+        let multiplier = self.base_fee_multiplier_percent;
+        let hundreds = self.base_fee_per_gas.checked_div_floor(100_u8)?;
+        let remainder = self
+            .base_fee_per_gas
+            .checked_sub(hundreds.checked_mul(100_u8)?)?;
+        hundreds.checked_mul(multiplier)?.checked_add(
+            remainder
+                .checked_mul(multiplier)?
+                .checked_div_ceil(100_u8)?,
+        )
+    }
+
     /// Estimates the maximum fee per gas. Falls back to `WeiPerGas::MAX` if the calculation fails.
     ///
     /// # Returns
@@ -55,11 +182,24 @@ impl GasFeeEstimate {
     pub fn to_price(self, gas_limit: GasAmount) -> TransactionPrice {
         TransactionPrice {
             gas_limit,
-            max_fee_per_gas: self.estimate_max_fee_per_gas(),
+            max_fee_per_gas: self
+                .estimate_max_fee_per_gas()
+                .max(self.min_max_fee_per_gas()),
             max_priority_fee_per_gas: self.max_priority_fee_per_gas,
         }
     }
 
+    /// Same as [`Self::to_price`], unless the price exceeds `cap`.
+    pub fn to_capped_price(
+        self,
+        gas_limit: GasAmount,
+        cap: &FeeCap,
+    ) -> Result<TransactionPrice, FeeCapExceeded> {
+        let price = self.to_price(gas_limit);
+        cap.check(price.max_fee_per_gas, price.gas_limit)?;
+        Ok(price)
+    }
+
     /// Computes the minimum of the maximum fee per gas by adding base and priority fees.
     /// Falls back to `WeiPerGas::MAX` if the calculation fails.
     ///
@@ -139,14 +279,17 @@ impl TransactionPrice {
 
 /// Asynchronously refreshes the gas fee estimate.
 ///
-/// Uses a cached estimate if it is recent enough. Otherwise, fetches the latest fee history and recalculates the estimate.
+/// Uses a cached estimate if it is recent enough or if no new block was produced since it was computed.
+/// Otherwise, fetches the latest fee history and recalculates the estimate.
 ///
 /// # Returns
 /// An `Option` containing the new `GasFeeEstimate` if successful, or `None` if the refresh fails.
 pub async fn lazy_refresh_gas_fee_estimate() -> Option<GasFeeEstimate> {
     const MAX_AGE_NS: u64 = 10_000_000_000_u64; // 10 seconds
 
-    async fn do_refresh() -> Option<GasFeeEstimate> {
+    async fn do_refresh(
+        cached_estimate: Option<(GasFeeEstimate, BlockNumber)>,
+    ) -> Option<GasFeeEstimate> {
         let _guard = match TimerGuard::new(TaskType::RefreshGasFeeEstimate) {
             Ok(guard) => guard,
             Err(e) => {
@@ -158,6 +301,29 @@ pub async fn lazy_refresh_gas_fee_estimate() -> Option<GasFeeEstimate> {
             }
         };
 
+        if let Some((estimate, last_block)) = cached_estimate {
+            match poll_for_new_block(last_block).await {
+                Ok(None) => {
+                    log!(
+                        DEBUG,
+                        "[refresh_gas_fee_estimate]: No new block since {last_block}, reusing the last estimate",
+                    );
+                    mutate_state(|s| {
+                        s.last_transaction_price_estimate =
+                            Some((ic_cdk::api::time(), estimate.clone()));
+                    });
+                    return Some(estimate);
+                }
+                Ok(Some(_block)) => {}
+                Err(e) => {
+                    log!(
+                        DEBUG,
+                        "[refresh_gas_fee_estimate]: Failed polling for a new block: {e:?}",
+                    );
+                }
+            }
+        }
+
         let mut attempts = 0;
         const MAX_ATTEMPTS: u32 = 3;
 
@@ -189,6 +355,7 @@ pub async fn lazy_refresh_gas_fee_estimate() -> Option<GasFeeEstimate> {
                 mutate_state(|s| {
                     s.last_transaction_price_estimate =
                         Some((ic_cdk::api::time(), estimate.clone()));
+                    s.last_transaction_price_estimate_block = Some(fee_history.newest_block());
                 });
                 estimate
             }
@@ -205,32 +372,62 @@ pub async fn lazy_refresh_gas_fee_estimate() -> Option<GasFeeEstimate> {
             "[refresh_gas_fee_estimate]: Estimated transaction fee: {:?}",
             gas_fee_estimate,
         );
+        if read_state(|s| s.evm_network) == EvmNetwork::Base {
+            refresh_l1_fee().await;
+        }
         Some(gas_fee_estimate)
     }
 
     async fn get_fee_history() -> Result<FeeHistory, MultiCallError<FeeHistory>> {
+        let (client, reward_percentiles) = read_state(|s| {
+            let reward_percentiles = match s.gas_fee_config {
+                Some(_) => FEE_HISTORY_REWARD_PERCENTILES.to_vec(),
+                None => vec![50],
+            };
+            (
+                RpcClient::from_state_one_provider(
+                    s,
+                    crate::rpc_client::providers::Provider::PublicNode,
+                ),
+                reward_percentiles,
+            )
+        });
+        client
+            .fee_history(FeeHistoryParams {
+                block_count: Quantity::from(5_u8),
+                highest_block: BlockSpec::Tag(BlockTag::Latest),
+                reward_percentiles,
+            })
+            .await
+    }
+
+    async fn poll_for_new_block(
+        last_block: BlockNumber,
+    ) -> Result<Option<Block>, MultiCallError<Option<Block>>> {
         read_state(|s| {
             RpcClient::from_state_one_provider(
                 s,
                 crate::rpc_client::providers::Provider::PublicNode,
             )
         })
-        .fee_history(FeeHistoryParams {
-            block_count: Quantity::from(5_u8),
-            highest_block: BlockSpec::Tag(BlockTag::Latest),
-            reward_percentiles: vec![50],
-        })
+        .poll_for_new_block(last_block)
         .await
     }
 
     let now_ns = ic_cdk::api::time();
-    match read_state(|s| s.last_transaction_price_estimate.clone()) {
-        Some((last_estimate_timestamp_ns, estimate))
+    match read_state(|s| {
+        (
+            s.last_transaction_price_estimate.clone(),
+            s.last_transaction_price_estimate_block,
+        )
+    }) {
+        (Some((last_estimate_timestamp_ns, estimate)), _)
             if now_ns < last_estimate_timestamp_ns.saturating_add(MAX_AGE_NS) =>
         {
             Some(estimate)
         }
-        _ => do_refresh().await,
+        (Some((_, estimate)), Some(last_block)) => do_refresh(Some((estimate, last_block))).await,
+        _ => do_refresh(None).await,
     }
 }
 
@@ -246,6 +443,9 @@ pub enum TransactionFeeEstimationError {
 /// Determines the base fee per gas for the next block and computes the maximum priority fee based on historic values.
 /// Returns an estimate of the gas fee.
 ///
+/// Uses the [`GasFeeConfig`] from the state if one is set, otherwise the median of all rewards,
+/// a doubled base fee and the minimum priority fee of the network.
+///
 /// # Arguments
 /// * `fee_history` - The fee history to use for estimation.
 ///
@@ -254,9 +454,46 @@ pub enum TransactionFeeEstimationError {
 pub fn estimate_transaction_fee(
     fee_history: &FeeHistory,
 ) -> Result<GasFeeEstimate, TransactionFeeEstimationError> {
-    let min_max_priority_fee_per_gas: WeiPerGas =
-        read_state(|state| state.min_max_priority_fee_per_gas); // Different on each network
+    let (min_max_priority_fee_per_gas, gas_fee_config) = read_state(|state| {
+        (
+            state.min_max_priority_fee_per_gas, // Different on each network
+            state.gas_fee_config.clone(),
+        )
+    });
+
+    match gas_fee_config {
+        Some(config) => estimate_transaction_fee_with_config(fee_history, &config),
+        None => estimate(
+            fee_history,
+            None,
+            DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+            min_max_priority_fee_per_gas,
+        ),
+    }
+}
+
+/// Estimates the transaction fee based on fee history using the given [`GasFeeConfig`].
+///
+/// The priority fee is the median over all blocks of the reward at `reward_percentile_index`,
+/// or the median of all rewards if some block has no reward at that index.
+pub fn estimate_transaction_fee_with_config(
+    fee_history: &FeeHistory,
+    config: &GasFeeConfig,
+) -> Result<GasFeeEstimate, TransactionFeeEstimationError> {
+    estimate(
+        fee_history,
+        Some(config.reward_percentile_index),
+        config.base_fee_multiplier_percent,
+        config.min_priority_fee,
+    )
+}
 
+fn estimate(
+    fee_history: &FeeHistory,
+    reward_percentile_index: Option<u8>,
+    base_fee_multiplier_percent: u16,
+    min_max_priority_fee_per_gas: WeiPerGas,
+) -> Result<GasFeeEstimate, TransactionFeeEstimationError> {
     let base_fee_per_gas_next_block = *fee_history.base_fee_per_gas.last().ok_or(
         TransactionFeeEstimationError::InvalidFeeHistory(
             "base_fee_per_gas should not be empty to be able to evaluate transaction price"
@@ -265,7 +502,19 @@ pub fn estimate_transaction_fee(
     )?;
 
     let max_priority_fee_per_gas = {
-        let mut rewards: Vec<&WeiPerGas> = fee_history.reward.iter().flatten().collect();
+        let selected_rewards: Option<Vec<&WeiPerGas>> = reward_percentile_index.and_then(|index| {
+            fee_history
+                .reward
+                .iter()
+                .map(|block_rewards| block_rewards.get(index as usize))
+                .collect()
+        });
+        // Falls back to the median of all rewards when the fee history lacks the selected
+        // percentile, e.g. when it was requested before the config was set.
+        let mut rewards: Vec<&WeiPerGas> = match selected_rewards {
+            Some(rewards) => rewards,
+            None => fee_history.reward.iter().flatten().collect(),
+        };
         let historic_max_priority_fee_per_gas =
             **median(&mut rewards).ok_or(TransactionFeeEstimationError::InvalidFeeHistory(
                 "should be non-empty with rewards of the last 5 blocks".to_string(),
@@ -276,6 +525,7 @@ pub fn estimate_transaction_fee(
     let gas_fee_estimate = GasFeeEstimate {
         base_fee_per_gas: base_fee_per_gas_next_block,
         max_priority_fee_per_gas,
+        base_fee_multiplier_percent,
     };
 
     if gas_fee_estimate
@@ -295,7 +545,7 @@ pub async fn estimate_erc20_transaction_fee() -> Option<Wei> {
         .await
         .map(|gas_fee_estimate| {
             gas_fee_estimate
-                .to_price(ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT)
+                .to_price(read_state(|s| s.gas_limit(GasLimitKind::Erc20Withdrawal)))
                 .max_transaction_fee()
         })
 }
@@ -305,7 +555,7 @@ pub async fn estimate_icrc_wrap_transaction_fee() -> Option<Wei> {
         .await
         .map(|gas_fee_estimate| {
             gas_fee_estimate
-                .to_price(ERC20_MINT_TRANSACTION_GAS_LIMIT)
+                .to_price(read_state(|s| s.gas_limit(GasLimitKind::WrappedIcrcMint)))
                 .max_transaction_fee()
         })
 }
@@ -315,7 +565,7 @@ pub async fn estimate_usdc_approval_fee() -> Option<Wei> {
         .await
         .map(|gas_fee_estimate| {
             gas_fee_estimate
-                .to_price(ERC20_APPROVAL_TRANSACTION_GAS_LIMIT)
+                .to_price(read_state(|s| s.gas_limit(GasLimitKind::Erc20Approval)))
                 .max_transaction_fee()
         })
 }
@@ -323,11 +573,14 @@ pub async fn estimate_usdc_approval_fee() -> Option<Wei> {
 pub async fn estimate_dex_order_fee(gas_estimate: GasAmount) -> Option<Wei> {
     lazy_refresh_gas_fee_estimate()
         .await
-        .map(|gas_fee_estimate| {
-            gas_fee_estimate
-                .to_price(gas_estimate)
-                .max_transaction_fee()
-        })
+        .map(|gas_fee_estimate| dex_order_fee(gas_fee_estimate, gas_estimate))
+}
+
+/// Maximum fee of a dex order transaction with the given gas limit.
+pub fn dex_order_fee(gas_fee_estimate: GasFeeEstimate, gas_estimate: GasAmount) -> Wei {
+    gas_fee_estimate
+        .to_price(gas_estimate)
+        .max_transaction_fee()
 }
 
 /// Computes the median of a slice of values.
@@ -356,14 +609,101 @@ fn median<T: Ord>(values: &mut [T]) -> Option<&T> {
 // ERC-20 Transfer (EIP-1559):
 // Typical Size: 172–180 bytes.
 
-const ORACLE_ADDRESS: &str = "0xb528D11cC114E026F138fE568744c6D45ce6Da7A";
-
 const GET_L1_GAS_FUNCTION_SELECTOR: [u8; 4] = hex_literal::hex!("49948e0e");
 
 const SAMPLE_ERC20_TRANSFER_TX:&str="02f86c0185059682f008503b9aca00825208946b175474e89094c44da98b954eedeac495271d0f80b844a9059cbb0000000000000000000000005a3c9f349cbf0d6b4c7a5671e3d06ce72c826b6b000000000000000000000000000000000000000000000000000000000016345785d8a0000c080a09d14d0d3aabc124ff0c6875b1d6db7a632a53cd0b62e94942be6b850a05af21a074bc3c2cf34378480b44843544f3d0430c8131f64cbaec99a256468e00dbf5d6";
 
 const SAMPLE_CALLDATA_FOR_GET_L1_FEE:&str="49948e0e000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000b602f86c0185059682f008503b9aca00825208946b175474e89094c44da98b954eedeac495271d0f80b844a9059cbb0000000000000000000000005a3c9f349cbf0d6b4c7a5671e3d06ce72c826b6b000000000000000000000000000000000000000000000000000000000016345785d8a0000c080a09d14d0d3aabc124ff0c6875b1d6db7a632a53cd0b62e94942be6b850a05af21a074bc3c2cf34378480b44843544f3d0430c8131f64cbaec99a256468e00dbf5d600000000000000000000";
 
+/// Buffer added to the fetched L1 fee, since it moves with the L1 base fee until the
+/// transaction is included.
+pub const L1_FEE_BUFFER_PERCENT: u8 = 10;
+
+/// Predeployed `GasPriceOracle` contract of OP stack chains such as Base.
+const GAS_PRICE_ORACLE_ADDRESS: &str = "0x420000000000000000000000000000000000000F";
+
+/// L1 fee of the sample ERC-20 transfer as returned by `getL1Fee(bytes)`, which applies the
+/// current Ecotone base fee and blob base fee scalars to the compressed transaction size.
+pub fn l1_fee_call_data() -> Vec<u8> {
+    hex::decode(SAMPLE_CALLDATA_FOR_GET_L1_FEE)
+        .expect("BUG: sample getL1Fee call data should be valid hex")
+}
+
+/// `eth_call` parameters querying `getL1Fee(bytes)` on the gas price oracle at the latest block.
+pub fn l1_fee_call_params(chain_id: u64) -> CallParams {
+    CallParams {
+        transaction: crate::rpc_declarations::TransactionRequestParams {
+            tx_type: None,
+            nonce: None,
+            to: Some(
+                Address::from_str(GAS_PRICE_ORACLE_ADDRESS)
+                    .expect("BUG: gas price oracle address should be valid"),
+            ),
+            from: None,
+            gas: None,
+            value: None,
+            input: Some(l1_fee_call_data()),
+            gas_price: None,
+            max_priority_fee_per_gas: None,
+            max_fee_per_gas: None,
+            max_fee_per_blob_gas: None,
+            access_list: None,
+            blob_versioned_hashes: None,
+            blobs: None,
+            chain_id: Some(chain_id),
+        },
+        block: Some(BlockSpec::Tag(BlockTag::Latest)),
+    }
+}
+
+/// Decodes the `uint256` returned by `getL1Fee(bytes)`.
+pub fn parse_l1_fee_response(response: &[u8]) -> Result<Wei, String> {
+    let word: [u8; 32] = response
+        .try_into()
+        .map_err(|_| format!("expected a 32-byte uint256, got {} bytes", response.len()))?;
+    Ok(Wei::from_be_bytes(word))
+}
+
+/// Fetches the current L1 fee of the sample transaction from the gas price oracle and stores
+/// it in the state. If the call fails, the last fetched value is kept.
+async fn refresh_l1_fee() {
+    let chain_id = read_state(|s| s.evm_network()).chain_id();
+    let result = read_state(RpcClient::from_state_all_providers)
+        .eth_call(l1_fee_call_params(chain_id))
+        .await;
+    match result {
+        Ok(response) => match parse_l1_fee_response(response.as_ref()) {
+            Ok(l1_fee) => mutate_state(|s| s.current_l1_fee = Some(l1_fee)),
+            Err(e) => log!(INFO, "[refresh_l1_fee]: Failed decoding l1 fee: {e}"),
+        },
+        Err(e) => log!(INFO, "[refresh_l1_fee]: Failed retrieving l1 fee: {e:?}"),
+    }
+}
+
+/// L1 fee charged on top of the transaction fee, only required on Base.
+///
+/// The last fetched L1 fee of the sample transaction plus [`L1_FEE_BUFFER_PERCENT`], or
+/// [`DEFAULT_L1_BASE_GAS_FEE`] if none was fetched since the last upgrade.
+pub fn l1_fee_estimate(evm_network: EvmNetwork, current_l1_fee: Option<Wei>) -> Option<Wei> {
+    match evm_network {
+        EvmNetwork::Base => Some(
+            current_l1_fee
+                .map(|l1_fee| {
+                    l1_fee
+                        .checked_add(
+                            l1_fee
+                                .checked_mul(L1_FEE_BUFFER_PERCENT)
+                                .and_then(|buffer| buffer.checked_div_floor(100_u8))
+                                .unwrap_or(Wei::ZERO),
+                        )
+                        .unwrap_or(Wei::MAX)
+                })
+                .unwrap_or(DEFAULT_L1_BASE_GAS_FEE),
+        ),
+        _ => None,
+    }
+}
+
 // Components of the Transaction
 // Transaction Type: 0x02 (EIP-1559).
 // Chain ID: 0x2119 (Base).
@@ -380,82 +720,9 @@ const SAMPLE_CALLDATA_FOR_GET_L1_FEE:&str="49948e0e00000000000000000000000000000
 // Sample erc20 tx is slightly longer than native transfer, so we choose erc20 transfer for both of
 // them to add some buffer for native transfer as well
 
-/// Asynchronously refreshes the l1 fee estimate.
-///
-/// fetches the latest l1_fee from gas_fee oracle.
-/// Add a 10% buffer to the fetched estimate to make a safe zone.
-///
-/// # Returns
-/// An `Option` containing the new  l1 fee estimate in `Wei` if successful, or `None` if the fetch fails.
-// we se default l1 fee instead of fetching it on-chain becuase its high enough to cover the any
-// sort of transaction cost.
+/// L1 fee charged on Base until it is fetched from the gas price oracle, high enough to cover
+/// the L1 fee of any transaction.
 pub const DEFAULT_L1_BASE_GAS_FEE: Wei = Wei::new(1000000000000_u128);
-pub async fn lazy_fetch_l1_fee_estimate() -> Option<Wei> {
-    let mut attempts = 0;
-    const MAX_ATTEMPTS: u32 = 3;
-
-    let l1_fee = loop {
-        match get_l1_fee().await {
-            Ok(fee_estimate) => break fee_estimate.to_string(), // Exit loop on success
-            Err(e) => {
-                attempts += 1;
-                log!(
-                    DEBUG,
-                    "[fetch_l1_fee_estimate]: Failed retrieving l1 fee: {e:?} attempt {}/{} failed",
-                    attempts,
-                    MAX_ATTEMPTS
-                );
-
-                if attempts >= MAX_ATTEMPTS {
-                    log!(
-                        DEBUG,
-                        "[fetch_l1_fee_estimate]: max retries reached. Skipping l1 fee estimation."
-                    );
-                    return None;
-                }
-            }
-        }
-    };
-
-    async fn get_l1_fee() -> Result<Hex, MultiCallError<Hex>> {
-        let chain_id = read_state(|s| s.evm_network()).chain_id();
-
-        read_state(RpcClient::from_state_all_providers)
-            .eth_call(CallParams {
-                transaction: crate::rpc_declarations::TransactionRequestParams {
-                    tx_type: None,
-                    nonce: None,
-                    to: Some(
-                        Address::from_str(ORACLE_ADDRESS)
-                            .expect("sould not fail converting oracle address"),
-                    ),
-                    from: None,
-                    gas: None,
-                    value: None,
-                    input: Some(
-                        hex::decode(SAMPLE_CALLDATA_FOR_GET_L1_FEE)
-                            .expect("bug: Failed to decode dummy transactioncall data"),
-                    ),
-                    gas_price: None,
-                    max_priority_fee_per_gas: None,
-                    max_fee_per_gas: None,
-                    max_fee_per_blob_gas: None,
-                    access_list: None,
-                    blob_versioned_hashes: None,
-                    blobs: None,
-                    chain_id: Some(chain_id),
-                },
-                block: Some(BlockSpec::Tag(BlockTag::Latest)),
-            })
-            .await
-    }
-
-    Some(parse_l1_fee_resposne(l1_fee))
-}
-
-fn parse_l1_fee_resposne(l1_fee_string: String) -> Wei {
-    Wei::from_str_hex(&l1_fee_string).expect("expected a correct unint 256 hex string")
-}
 
 #[test]
 fn check_inpts() {
@@ -493,3 +760,42 @@ fn check_inpts() {
         Hex::from(hex::decode(SAMPLE_CALLDATA_FOR_GET_L1_FEE).expect("Failed to convert to hex"))
     );
 }
+
+#[test]
+fn should_encode_l1_fee_call() {
+    let params = l1_fee_call_params(8453);
+
+    assert_eq!(&l1_fee_call_data()[..4], GET_L1_GAS_FUNCTION_SELECTOR);
+    assert_eq!(params.transaction.input, Some(l1_fee_call_data()));
+    assert_eq!(
+        params.transaction.to,
+        Some(Address::from_str("0x420000000000000000000000000000000000000F").unwrap())
+    );
+    assert_eq!(params.transaction.chain_id, Some(8453));
+    assert_eq!(params.block, Some(BlockSpec::Tag(BlockTag::Latest)));
+}
+
+#[test]
+fn should_parse_l1_fee_response() {
+    let mut response = [0_u8; 32];
+    response[28..].copy_from_slice(&1_000_000_000_u32.to_be_bytes());
+
+    assert_eq!(
+        parse_l1_fee_response(&response),
+        Ok(Wei::new(1_000_000_000))
+    );
+    assert!(parse_l1_fee_response(&response[1..]).is_err());
+}
+
+#[test]
+fn should_fall_back_to_default_l1_fee() {
+    assert_eq!(l1_fee_estimate(EvmNetwork::Ethereum, Some(Wei::ONE)), None);
+    assert_eq!(
+        l1_fee_estimate(EvmNetwork::Base, None),
+        Some(DEFAULT_L1_BASE_GAS_FEE)
+    );
+    assert_eq!(
+        l1_fee_estimate(EvmNetwork::Base, Some(Wei::new(1_000_000_000))),
+        Some(Wei::new(1_100_000_000))
+    );
+}
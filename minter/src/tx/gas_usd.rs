@@ -6,6 +6,26 @@ use rust_decimal::MathematicalOps;
 #[derive(Clone, Copy, Debug)]
 pub struct MaxFeeUsd(pub Decimal);
 
+/// Number of decimals of a twin USDC token, fixed at compile time so that amounts
+/// of tokens with different decimals cannot be mixed up.
+pub trait DecimalsFactor {
+    const DECIMALS: u8;
+}
+
+/// Twin USDC with 6 decimals, as on most chains.
+pub struct Decimals6;
+
+/// Twin USDC with 18 decimals, as on BSC.
+pub struct Decimals18;
+
+impl DecimalsFactor for Decimals6 {
+    const DECIMALS: u8 = 6;
+}
+
+impl DecimalsFactor for Decimals18 {
+    const DECIMALS: u8 = 18;
+}
+
 impl MaxFeeUsd {
     pub fn new(max_fee_str: &str) -> Result<Self, String> {
         Ok(MaxFeeUsd(
@@ -58,6 +78,32 @@ impl MaxFeeUsd {
         Ok(Wei::from(amount_u128))
     }
 
+    pub fn usdc6_from_native_wei(
+        native_amount: Wei,
+        native_price_usd: f64,
+    ) -> Result<Erc20Value, String> {
+        Self::from_native_wei::<Decimals6>(native_amount, native_price_usd)
+    }
+
+    pub fn usdc18_from_native_wei(
+        native_amount: Wei,
+        native_price_usd: f64,
+    ) -> Result<Erc20Value, String> {
+        Self::from_native_wei::<Decimals18>(native_amount, native_price_usd)
+    }
+
+    /// Value of `native_amount` in a twin USDC token with `D` decimals.
+    pub fn from_native_wei<D: DecimalsFactor>(
+        native_amount: Wei,
+        native_price_usd: f64,
+    ) -> Result<Erc20Value, String> {
+        Self::twin_usdc_from_native_wei(native_amount, native_price_usd, D::DECIMALS)
+    }
+
+    /// Value of `native_amount` in a twin USDC token with `decimals` decimals.
+    ///
+    /// Only for decimals known at runtime, i.e. those of the twin USDC configured when the swap
+    /// feature is activated, use [`MaxFeeUsd::from_native_wei`] otherwise.
     pub fn twin_usdc_from_native_wei(
         native_amount: Wei,
         native_price_usd: f64,
@@ -199,51 +245,80 @@ mod tests {
         assert_eq!(result, Wei::from(333_333_333_333_333_333u128)); // 1 / 3 * 10^18 ≈ 0.333... * 10^18, truncated
     }
     #[test]
-    fn test_twin_usdc_from_native_wei_happy_path() {
+    fn test_usdc6_from_native_wei_happy_path() {
         let native_amount = Wei::from(1_000_000_000_000_000_000u128); // 1 native
-        let result = MaxFeeUsd::twin_usdc_from_native_wei(native_amount, 1.0, 6).unwrap();
+        let result = MaxFeeUsd::usdc6_from_native_wei(native_amount, 1.0).unwrap();
         assert_eq!(result, Erc20Value::from(1_000_000u128)); // 1 * 1 * 10^6
     }
     #[test]
-    fn test_twin_usdc_from_native_wei_another_happy_path() {
+    fn test_usdc6_from_native_wei_another_happy_path() {
         let native_amount = Wei::from(2_000_000_000_000_000_000u128); // 2 native
-        let result = MaxFeeUsd::twin_usdc_from_native_wei(native_amount, 3.5, 2).unwrap();
-        assert_eq!(result, Erc20Value::from(700u128)); // 2 * 3.5 * 10^2 = 700
+        let result = MaxFeeUsd::usdc6_from_native_wei(native_amount, 3.5).unwrap();
+        assert_eq!(result, Erc20Value::from(7_000_000u128)); // 2 * 3.5 * 10^6
     }
     #[test]
-    fn test_twin_usdc_from_native_wei_zero_decimals() {
+    fn test_usdc18_from_native_wei_happy_path() {
         let native_amount = Wei::from(1_000_000_000_000_000_000u128);
-        let result = MaxFeeUsd::twin_usdc_from_native_wei(native_amount, 5.0, 0).unwrap();
-        assert_eq!(result, Erc20Value::from(5u128)); // 1 * 5 * 10^0 = 5
+        let result = MaxFeeUsd::usdc18_from_native_wei(native_amount, 5.0).unwrap();
+        assert_eq!(result, Erc20Value::from(5_000_000_000_000_000_000u128)); // 1 * 5 * 10^18
+    }
+    #[test]
+    fn test_usdc18_and_usdc6_differ_by_twelve_decimals() {
+        let native_amount = Wei::from(300_000_000_000_000u128); // 0.0003 native
+        let usdc6 = MaxFeeUsd::usdc6_from_native_wei(native_amount, 600.0).unwrap();
+        let usdc18 = MaxFeeUsd::usdc18_from_native_wei(native_amount, 600.0).unwrap();
+        assert_eq!(usdc6, Erc20Value::from(180_000u128)); // 0.18 * 10^6
+        assert_eq!(usdc18, Erc20Value::from(180_000_000_000_000_000u128)); // 0.18 * 10^18
+        assert_eq!(
+            MaxFeeUsd::from_native_wei::<Decimals6>(native_amount, 600.0),
+            Ok(usdc6)
+        );
+        assert_eq!(
+            MaxFeeUsd::from_native_wei::<Decimals18>(native_amount, 600.0),
+            Ok(usdc18)
+        );
+        assert_eq!(Decimals18::DECIMALS - Decimals6::DECIMALS, 12);
+    }
+    #[test]
+    fn test_twin_usdc_from_native_wei_with_runtime_decimals() {
+        let native_amount = Wei::from(2_000_000_000_000_000_000u128); // 2 native
+        let result = MaxFeeUsd::twin_usdc_from_native_wei(native_amount, 3.5, 2).unwrap();
+        assert_eq!(result, Erc20Value::from(700u128)); // 2 * 3.5 * 10^2 = 700
+        let result = MaxFeeUsd::twin_usdc_from_native_wei(native_amount, 3.5, 0).unwrap();
+        assert_eq!(result, Erc20Value::from(7u128)); // 2 * 3.5 * 10^0 = 7
+        assert_eq!(
+            MaxFeeUsd::twin_usdc_from_native_wei(native_amount, 3.5, 6),
+            MaxFeeUsd::usdc6_from_native_wei(native_amount, 3.5)
+        );
     }
     #[test]
-    fn test_twin_usdc_from_native_wei_negative_price() {
+    fn test_usdc6_from_native_wei_negative_price() {
         let native_amount = Wei::from(1u128);
-        let result = MaxFeeUsd::twin_usdc_from_native_wei(native_amount, -1.0, 6);
+        let result = MaxFeeUsd::usdc6_from_native_wei(native_amount, -1.0);
         assert_eq!(result, Err("Native price must be positive".to_string()));
     }
     #[test]
-    fn test_twin_usdc_from_native_wei_zero_price() {
+    fn test_usdc6_from_native_wei_zero_price() {
         let native_amount = Wei::from(1u128);
-        let result = MaxFeeUsd::twin_usdc_from_native_wei(native_amount, 0.0, 6);
+        let result = MaxFeeUsd::usdc6_from_native_wei(native_amount, 0.0);
         assert_eq!(result, Err("Native price must be positive".to_string()));
     }
     #[test]
-    fn test_twin_usdc_from_native_wei_invalid_price_nan() {
+    fn test_usdc6_from_native_wei_invalid_price_nan() {
         let native_amount = Wei::from(1u128);
-        let result = MaxFeeUsd::twin_usdc_from_native_wei(native_amount, f64::NAN, 6);
+        let result = MaxFeeUsd::usdc6_from_native_wei(native_amount, f64::NAN);
         assert_eq!(result, Err("Invalid native price value".to_string()));
     }
     #[test]
-    fn test_twin_usdc_from_native_wei_too_large() {
+    fn test_usdc18_from_native_wei_too_large() {
         let native_amount = Wei::from(u128::MAX);
-        let result = MaxFeeUsd::twin_usdc_from_native_wei(native_amount, 1.0, 0);
+        let result = MaxFeeUsd::usdc18_from_native_wei(native_amount, 1.0);
         assert!(result.is_err())
     }
     #[test]
-    fn test_twin_usdc_from_native_wei_fractional_truncation() {
+    fn test_usdc6_from_native_wei_fractional_truncation() {
         let native_amount = Wei::from(1u128); // 1 wei
-        let result = MaxFeeUsd::twin_usdc_from_native_wei(native_amount, 3.0, 0).unwrap();
+        let result = MaxFeeUsd::usdc6_from_native_wei(native_amount, 3.0).unwrap();
         assert_eq!(result, Erc20Value::from(0u128)); // truncated to 0
     }
 }
@@ -1,57 +1,68 @@
-use crate::evm_config::EvmNetwork;
+#[cfg(test)]
+mod tests;
+
+use crate::candid_types::dex_orders::RefundableDexOrderArgs;
+use crate::candid_types::withdraw_native::CancelError;
+use crate::candid_types::RetrieveWithdrawalStatus;
+use crate::erc20::transfer_log::contains_transfer_log;
+use crate::evm_config::{EvmNetwork, GasLimitKind, GasLimits};
 use crate::guard::TimerGuard;
 use crate::icrc_client::runtime::IcrcBoundedRuntime;
+use crate::instrumentation::instrument;
 use crate::logs::{DEBUG, INFO};
 use crate::numeric::{
-    Erc20TokenAmount, Erc20Value, GasAmount, LedgerBurnIndex, LedgerMintIndex, Wei,
+    Erc20TokenAmount, Erc20Value, GasAmount, LedgerBurnIndex, LedgerMintIndex, TransactionNonce,
+    Wei,
 };
 use crate::rpc_client::providers::Provider;
 use crate::rpc_client::{MultiCallError, RpcClient};
-use crate::rpc_declarations::{SendRawTransactionResult, TransactionReceipt};
+use crate::rpc_declarations::{
+    SendRawTransactionResult, TransactionReceiptWithLogs, TransactionStatus,
+};
 use crate::state::audit::{process_event, EventType};
 use crate::state::balances::release_gas_from_tank_with_usdc;
 use crate::state::transactions::{
-    create_transaction, CreateTransactionError, ExecuteSwapRequest, Reimbursed, ReimbursementIndex,
+    create_transaction, BroadcastAttempt, BroadcastOutcome, CreateTransactionError,
+    ExecuteSwapRequest, PendingReimbursementRequest, Reimbursed, ReimbursementIndex,
     ReimbursementRequest, WithdrawalRequest,
 };
 use crate::state::{mutate_state, State, TaskType};
 use crate::swap::build_dex_swap_refund_request;
-use crate::tx::gas_fees::{lazy_refresh_gas_fee_estimate, GasFeeEstimate, DEFAULT_L1_BASE_GAS_FEE};
-use crate::tx::gas_usd::MaxFeeUsd;
+use crate::swap::receipt_logs::realized_amount_out;
+use crate::tx::cancellation::create_cancellation_transaction;
+use crate::tx::gas_fees::{
+    l1_fee_estimate, lazy_refresh_gas_fee_estimate, FeeCapExceeded, GasFeeEstimate,
+};
+use crate::tx::SignedEip1559TransactionRequest;
 use crate::{numeric::TransactionCount, state::read_state};
-use candid::Nat;
+use candid::{Nat, Principal};
+use evm_rpc_client::eth_types::Address;
 use futures::future::join_all;
 use ic_canister_log::log;
 use icrc_ledger_client::ICRC1Client;
 use icrc_ledger_types::icrc1::account::Account;
-use icrc_ledger_types::icrc1::transfer::TransferArg;
+use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError};
 use num_traits::ToPrimitive;
 use scopeguard;
 use scopeguard::ScopeGuard;
 use std::collections::{BTreeMap, BTreeSet};
 use std::iter::zip;
 
-const WITHDRAWAL_REQUESTS_BATCH_SIZE: usize = 5;
-const TRANSACTIONS_TO_SIGN_BATCH_SIZE: usize = 5;
-const TRANSACTIONS_TO_SEND_BATCH_SIZE: usize = 5;
-
-// 21000 is fixed for native tokens, however 65000 is idle for ERC20s but some ERC20 contracts have
-// more complicated logic that requires maximum of 100000 Gas.
-pub const NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT: GasAmount = GasAmount::new(21_000);
-pub const ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT: GasAmount = GasAmount::new(66_000);
-
-pub const ERC20_APPROVAL_TRANSACTION_GAS_LIMIT: GasAmount = GasAmount::new(70_000);
-
-// used for mining wrapped icrc transactions
-pub const ERC20_MINT_TRANSACTION_GAS_LIMIT: GasAmount = GasAmount::new(100_000);
-
-pub const REFUND_FAILED_SWAP_GAS_LIMIT: GasAmount = GasAmount::new(120_000);
+/// Maximum number of withdrawals moved forward by each step of a single
+/// [`process_retrieve_tokens_requests`] run. Signing and sending are done concurrently
+/// for the whole batch.
+pub const MAX_BATCH_SIZE: usize = 10;
+const WITHDRAWAL_REQUESTS_BATCH_SIZE: usize = MAX_BATCH_SIZE;
+const TRANSACTIONS_TO_SIGN_BATCH_SIZE: usize = MAX_BATCH_SIZE;
+const TRANSACTIONS_TO_SEND_BATCH_SIZE: usize = MAX_BATCH_SIZE;
 
-// the deadline is valid for 20 years and it is used for the the failed swaps that will be
-// converted to usdc transfer
-pub const UNLIMITED_DEADLINE: Erc20Value = Erc20Value::new(2388441600);
+/// Number of times the transaction with the lowest pending nonce can be replaced before it is
+/// parked and cancelled, so that it stops blocking the transactions with higher nonces.
+/// A transaction whose fee is capped is parked as soon as it can no longer be replaced.
+pub const MAX_TRANSACTION_REPLACEMENTS: usize = 5;
 
 pub async fn process_reimbursement() {
+    let _instrumentation = instrument("process_reimbursement");
     let _guard = match TimerGuard::new(TaskType::Reimbursement) {
         Ok(guard) => guard,
         Err(e) => {
@@ -112,20 +123,19 @@ pub async fn process_reimbursement() {
         let amount = match transfer_fee {
             Some(fee) => Nat::from(
                 reimbursement_request
-                    .reimbursed_amount
+                    .reimbursed_amount()
                     .checked_sub(fee)
                     .unwrap_or(Erc20TokenAmount::ZERO),
             ),
-            None => Nat::from(reimbursement_request.reimbursed_amount),
+            None => Nat::from(reimbursement_request.reimbursed_amount()),
         };
 
         let args = TransferArg {
             from_subaccount: None,
             to: Account {
-                owner: reimbursement_request.to,
+                owner: reimbursement_request.to(),
                 subaccount: reimbursement_request
-                    .to_subaccount
-                    .as_ref()
+                    .to_subaccount()
                     .map(|subaccount| subaccount.0),
             },
             fee: transfer_fee.map(Nat::from),
@@ -140,10 +150,19 @@ pub async fn process_reimbursement() {
                     .to_u64()
                     .expect("block index should fit into u64"),
                 Ok(Err(err)) => {
-                    log!(
-                        INFO,
-                        "[process_reimbursement] Failed to mint native token {err}"
-                    );
+                    match &err {
+                        TransferError::GenericError {
+                            error_code,
+                            message,
+                        } => log!(
+                            INFO,
+                            "[process_reimbursement] Ledger {ledger_canister_id} rejected reimbursement {index:?} with error code {error_code}: {message}, will retry"
+                        ),
+                        _ => log!(
+                            INFO,
+                            "[process_reimbursement] Failed to mint native token {err}"
+                        ),
+                    }
                     error_count += 1;
                     // minting failed, defuse guard
                     ScopeGuard::into_inner(prevent_double_minting_guard);
@@ -164,10 +183,10 @@ pub async fn process_reimbursement() {
             0_u64
         };
         let reimbursed = Reimbursed {
-            burn_in_block: reimbursement_request.ledger_burn_index,
+            burn_in_block: reimbursement_request.ledger_burn_index(),
             reimbursed_in_block: LedgerMintIndex::new(block_index),
-            reimbursed_amount: reimbursement_request.reimbursed_amount,
-            transaction_hash: reimbursement_request.transaction_hash,
+            reimbursed_amount: reimbursement_request.reimbursed_amount(),
+            transaction_hash: reimbursement_request.transaction_hash(),
             transfer_fee,
         };
         let event = match index {
@@ -209,7 +228,7 @@ async fn process_failed_swaps(gas_fee_estimate: GasFeeEstimate) {
     if read_state(|s| {
         (s.withdrawal_transactions.is_failed_swaps_requests_empty()
             && s.quarantined_dex_orders.is_empty())
-            || !s.is_swapping_active
+            || !s.is_swapping_active()
     }) {
         return;
     }
@@ -221,6 +240,7 @@ async fn process_failed_swaps(gas_fee_estimate: GasFeeEstimate) {
         signing_fee,
         swap_contract_address,
         dex_canister_id,
+        refund_gas_limit,
     ) = read_state(|s| {
         (
             s.evm_network(),
@@ -229,6 +249,7 @@ async fn process_failed_swaps(gas_fee_estimate: GasFeeEstimate) {
             s.canister_signing_fee_twin_usdc_amount,
             s.swap_contract_address,
             s.dex_canister_id,
+            s.gas_limit(GasLimitKind::RefundFailedSwap),
         )
     });
 
@@ -249,25 +270,27 @@ async fn process_failed_swaps(gas_fee_estimate: GasFeeEstimate) {
         dex_canister_id.expect("BUG: dex canister id should be available if swapping is active");
 
     let erc20_tx_fee = gas_fee_estimate
-        .to_price(REFUND_FAILED_SWAP_GAS_LIMIT)
+        .to_price(refund_gas_limit)
         .max_transaction_fee();
 
-    let l1_fee = match evm_network {
-        EvmNetwork::Base => Some(DEFAULT_L1_BASE_GAS_FEE),
-        _ => None,
-    };
+    let l1_fee = l1_fee_estimate(evm_network, read_state(|s| s.current_l1_fee));
 
     let fee_to_be_deducted = erc20_tx_fee
         .checked_add(l1_fee.unwrap_or(Wei::ZERO))
         .expect("Bug: Tx_fee plus l1_fee should fit in u256");
 
-    let max_gas_fee_twin_usdc = match MaxFeeUsd::twin_usdc_from_native_wei(
-        fee_to_be_deducted,
-        last_native_token_usd_price_estimate.1,
-        twin_usdc_info.decimals,
-    ) {
+    let native_token_usd_price = last_native_token_usd_price_estimate.1;
+    let max_gas_fee_twin_usdc =
+        twin_usdc_info.usdc_from_native_wei(fee_to_be_deducted, native_token_usd_price);
+    let max_gas_fee_twin_usdc = match max_gas_fee_twin_usdc {
         Ok(usdc_amount) => usdc_amount,
-        Err(_) => return,
+        Err(e) => {
+            log!(
+                INFO,
+                "[create_refund_swap_erquest]: Failed to convert the refund gas fee to twin USDC: {e}"
+            );
+            return;
+        }
     };
 
     let all_twin_usdc_fees = max_gas_fee_twin_usdc
@@ -278,9 +301,19 @@ async fn process_failed_swaps(gas_fee_estimate: GasFeeEstimate) {
     for (_previous_native_ledger_burn_index, request) in
         read_state(|s| s.withdrawal_transactions.failed_swap_requests())
     {
+        let now = ic_cdk::api::time();
+        // The fees of a refund that expired before being mined were already deducted from its
+        // amount, the gas tank pays for the next attempt.
+        let is_expired_refund =
+            request.is_plain_refund() && request.deadline <= Erc20Value::from(now / 1_000_000_000);
+        let twin_usdc_fees = if is_expired_refund {
+            Erc20Value::ZERO
+        } else {
+            all_twin_usdc_fees
+        };
         let amount_in = request
             .erc20_amount_in
-            .checked_sub(all_twin_usdc_fees)
+            .checked_sub(twin_usdc_fees)
             .unwrap_or(Erc20Value::ZERO);
 
         if amount_in == Erc20Value::ZERO {
@@ -295,7 +328,7 @@ async fn process_failed_swaps(gas_fee_estimate: GasFeeEstimate) {
         }
 
         let native_ledger_burn_index = match release_gas_from_tank_with_usdc(
-            all_twin_usdc_fees,
+            twin_usdc_fees,
             fee_to_be_deducted,
             request.swap_tx_id.clone(),
         ) {
@@ -310,8 +343,6 @@ async fn process_failed_swaps(gas_fee_estimate: GasFeeEstimate) {
             }
         };
 
-        let now = ic_cdk::api::time();
-
         let request = ExecuteSwapRequest {
             max_transaction_fee: erc20_tx_fee,
             native_ledger_burn_index,
@@ -327,11 +358,11 @@ async fn process_failed_swaps(gas_fee_estimate: GasFeeEstimate) {
             erc20_amount_in: amount_in,
             min_amount_out: amount_in,
             recipient: request.recipient,
-            deadline: UNLIMITED_DEADLINE,
+            deadline: read_state(|s| s.refund_deadline(now)),
             commands: vec![],
             commands_data: vec![],
             swap_contract: swap_contract_address,
-            gas_estimate: REFUND_FAILED_SWAP_GAS_LIMIT,
+            gas_estimate: refund_gas_limit,
             is_refund: true,
         };
 
@@ -352,6 +383,18 @@ async fn process_failed_swaps(gas_fee_estimate: GasFeeEstimate) {
             "[dex_order]: Building swap request for Quarantined Dex Order with tx_id: {:?}",
             tx_id
         );
+        let dex_order = match RefundableDexOrderArgs::try_from(dex_order) {
+            Ok(dex_order) => dex_order,
+            Err(err) => {
+                log!(
+                    INFO,
+                    "[dex_order]: Skipping Quarantined Dex Order with tx_id: {:?} due to invalid arguments: {:?}",
+                    tx_id,
+                    err
+                );
+                continue;
+            }
+        };
         if let Ok(refund_swap_request) = build_dex_swap_refund_request(
             &dex_order,
             &twin_usdc_info,
@@ -374,7 +417,79 @@ async fn process_failed_swaps(gas_fee_estimate: GasFeeEstimate) {
     }
 }
 
+/// Cancels the native withdrawal request identified by `ledger_burn_index` on behalf of `caller`
+/// and schedules the reimbursement of the burned amount minus the ledger transfer fee.
+///
+/// The [`TaskType::RetrieveEth`] guard ensures that the request cannot be cancelled while
+/// [`process_retrieve_tokens_requests`] may be creating a transaction for it.
+pub fn cancel_withdrawal_request(
+    caller: Principal,
+    ledger_burn_index: LedgerBurnIndex,
+) -> Result<(), CancelError> {
+    let _guard = TimerGuard::new(TaskType::RetrieveEth).map_err(|e| {
+        CancelError::TemporarilyUnavailable(format!(
+            "Withdrawal requests are being processed: {e:?}"
+        ))
+    })?;
+
+    mutate_state(|s| {
+        let reimbursement_request = withdrawal_cancellation(s, caller, ledger_burn_index)?;
+        log!(
+            INFO,
+            "[cancel_withdrawal_request]: cancelling withdrawal request {ledger_burn_index}, reimbursing {reimbursement_request:?}",
+        );
+        process_event(
+            s,
+            EventType::CancelledWithdrawalRequest(reimbursement_request),
+        );
+        Ok(())
+    })
+}
+
+/// Returns the reimbursement of the withdrawal request to cancel, if `caller` may cancel it.
+///
+/// Only native withdrawal requests for which no transaction was created yet can be cancelled.
+pub fn withdrawal_cancellation(
+    state: &State,
+    caller: Principal,
+    ledger_burn_index: LedgerBurnIndex,
+) -> Result<PendingReimbursementRequest, CancelError> {
+    let request = match state
+        .withdrawal_transactions
+        .pending_withdrawal_request(&ledger_burn_index)
+    {
+        Some(request) => request,
+        None => {
+            return match state
+                .withdrawal_transactions
+                .transaction_status(&ledger_burn_index)
+            {
+                RetrieveWithdrawalStatus::NotFound => Err(CancelError::NotFound),
+                status => Err(CancelError::TooLate { status }),
+            }
+        }
+    };
+    if request.from() != caller {
+        return Err(CancelError::NotOwner);
+    }
+    let request = match request {
+        WithdrawalRequest::Native(request) => request,
+        _ => return Err(CancelError::NotCancellable),
+    };
+    let reimbursed_amount = request
+        .withdrawal_amount
+        .checked_sub(state.native_ledger_transfer_fee)
+        .unwrap_or(Wei::ZERO);
+    Ok(PendingReimbursementRequest {
+        ledger_burn_index,
+        reimbursed_amount: reimbursed_amount.change_units(),
+        to: request.from,
+        to_subaccount: request.from_subaccount.clone(),
+    })
+}
+
 pub async fn process_retrieve_tokens_requests() {
+    let _instrumentation = instrument("process_retrieve_tokens_requests");
     let _guard = match TimerGuard::new(TaskType::RetrieveEth) {
         Ok(guard) => guard,
         Err(e) => {
@@ -404,8 +519,10 @@ pub async fn process_retrieve_tokens_requests() {
     };
 
     let latest_transaction_count = latest_transaction_count().await;
+    reconcile_broadcast_attempts(latest_transaction_count).await;
+    park_stuck_transaction(latest_transaction_count, &gas_fee_estimate).await;
     resubmit_transactions_batch(latest_transaction_count, &gas_fee_estimate).await;
-    create_transactions_batch(gas_fee_estimate.clone());
+    create_transactions_batch(gas_fee_estimate.clone()).await;
     sign_transactions_batch().await;
     send_transactions_batch(latest_transaction_count).await;
     finalize_transactions_batch().await;
@@ -419,6 +536,43 @@ pub async fn process_retrieve_tokens_requests() {
     }
 }
 
+/// Returns whether code is deployed at `destination`, or `None` if it could not be checked.
+/// The result is cached for [`crate::state::CONTRACT_DESTINATION_CACHE_TTL_NANOS`].
+async fn is_contract_destination(destination: Address) -> Option<bool> {
+    let now = ic_cdk::api::time();
+    if let Some(is_contract) = read_state(|s| s.cached_is_contract_destination(&destination, now)) {
+        return Some(is_contract);
+    }
+    match read_state(|s| RpcClient::from_state_custom_providers(s, vec![Provider::Alchemy]))
+        .get_code(destination)
+        .await
+    {
+        Ok(code) => {
+            let is_contract = !code.as_ref().is_empty();
+            mutate_state(|s| s.record_is_contract_destination(destination, is_contract, now));
+            Some(is_contract)
+        }
+        Err(e) => {
+            log!(
+                INFO,
+                "[is_contract_destination]: failed to get the code at {destination}: {e:?}"
+            );
+            None
+        }
+    }
+}
+
+/// Returns the request with whether code is deployed at its destination, for native
+/// withdrawals whose destination was not checked when they were accepted.
+async fn with_checked_contract_destination(mut request: WithdrawalRequest) -> WithdrawalRequest {
+    if let WithdrawalRequest::Native(request) = &mut request {
+        if request.is_contract_destination.is_none() {
+            request.is_contract_destination = is_contract_destination(request.destination).await;
+        }
+    }
+    request
+}
+
 async fn latest_transaction_count() -> Option<TransactionCount> {
     match read_state(|s| RpcClient::from_state_custom_providers(s, vec![Provider::Alchemy]))
         .get_latest_transaction_count(crate::state::minter_address().await)
@@ -432,6 +586,50 @@ async fn latest_transaction_count() -> Option<TransactionCount> {
     }
 }
 
+async fn park_stuck_transaction(
+    latest_transaction_count: Option<TransactionCount>,
+    gas_fee_estimate: &GasFeeEstimate,
+) {
+    let latest_transaction_count = match latest_transaction_count {
+        Some(latest_transaction_count) => latest_transaction_count,
+        None => {
+            return;
+        }
+    };
+    let minter_address = crate::state::minter_address().await;
+    let (withdrawal_id, stuck_transaction) = match read_state(|s| {
+        s.withdrawal_transactions.transaction_to_park(
+            latest_transaction_count,
+            gas_fee_estimate,
+            MAX_TRANSACTION_REPLACEMENTS,
+        )
+    }) {
+        Some(stuck) => stuck,
+        None => {
+            return;
+        }
+    };
+    let cancellation = create_cancellation_transaction(
+        &stuck_transaction,
+        minter_address,
+        gas_fee_estimate.clone(),
+        read_state(|s| s.gas_limit(GasLimitKind::NativeWithdrawal)),
+    );
+    log!(
+        INFO,
+        "[park_stuck_transaction]: transaction {stuck_transaction:?} of withdrawal {withdrawal_id} was replaced {MAX_TRANSACTION_REPLACEMENTS} times or its fee is capped, cancelling it with {cancellation:?}"
+    );
+    mutate_state(|s| {
+        process_event(
+            s,
+            EventType::ParkedTransaction {
+                withdrawal_id,
+                cancellation,
+            },
+        )
+    });
+}
+
 async fn resubmit_transactions_batch(
     latest_transaction_count: Option<TransactionCount>,
     gas_fee_estimate: &GasFeeEstimate,
@@ -473,17 +671,37 @@ async fn resubmit_transactions_batch(
     }
 }
 
-fn create_transactions_batch(gas_fee_estimate: GasFeeEstimate) {
+async fn create_transactions_batch(gas_fee_estimate: GasFeeEstimate) {
     for request in read_state(|s| {
         s.withdrawal_transactions
             .withdrawal_requests_batch(WITHDRAWAL_REQUESTS_BATCH_SIZE)
     }) {
         log!(DEBUG, "[create_transactions_batch]: processing {request:?}",);
         let evm_network = read_state(State::evm_network);
+        // The destination is only checked once the tokens are burned, so that a failing
+        // withdrawal does not pay for the outcall. The pending request is left untouched.
+        let checked_request = with_checked_contract_destination(request.clone()).await;
         let nonce = read_state(|s| s.withdrawal_transactions.next_transaction_nonce());
-        let gas_limit = estimate_gas_limit(&request);
+        let gas_limit =
+            read_state(|s| estimate_gas_limit(&checked_request, evm_network, &s.gas_limits));
+        if let Err(exceeded) = gas_fee_estimate
+            .clone()
+            .to_capped_price(gas_limit, &read_state(State::fee_cap))
+        {
+            log!(
+                INFO,
+                "[create_transactions_batch]: Transaction fees of withdrawal request with burn index {} exceed the fee cap: {exceeded:?}. Request moved back to end of queue.",
+                request.native_ledger_burn_index()
+            );
+            alert_fee_cap_exceeded(request.native_ledger_burn_index(), exceeded);
+            mutate_state(|s| {
+                s.withdrawal_transactions
+                    .reschedule_withdrawal_request(request)
+            });
+            continue;
+        }
         match create_transaction(
-            &request,
+            &with_refund_deadline(request.clone(), ic_cdk::api::time()),
             nonce,
             gas_fee_estimate.clone(),
             gas_limit,
@@ -523,15 +741,41 @@ fn create_transactions_batch(gas_fee_estimate: GasFeeEstimate) {
     }
 }
 
+/// Sets the deadline of a refund when its transaction is created, so that the time the refund
+/// waited in the queue does not shorten it.
+fn with_refund_deadline(request: WithdrawalRequest, now_ns: u64) -> WithdrawalRequest {
+    match request {
+        WithdrawalRequest::Swap(mut request) if request.is_plain_refund() => {
+            request.deadline = read_state(|s| s.refund_deadline(now_ns));
+            WithdrawalRequest::Swap(request)
+        }
+        request => request,
+    }
+}
+
 async fn sign_transactions_batch() {
     let transactions_batch: Vec<_> = read_state(|s| {
         s.withdrawal_transactions
             .transactions_to_sign_batch(TRANSACTIONS_TO_SIGN_BATCH_SIZE)
     });
     log!(DEBUG, "Signing transactions {transactions_batch:?}");
+    let fee_cap = read_state(State::fee_cap);
     let results = join_all(
         transactions_batch
             .into_iter()
+            .filter(|(withdrawal_id, tx)| {
+                match fee_cap.check(tx.max_fee_per_gas, tx.gas_limit) {
+                    Ok(()) => true,
+                    Err(exceeded) => {
+                        log!(
+                            INFO,
+                            "Not signing transaction {tx:?}, its fees exceed the fee cap: {exceeded:?}. Will retry later."
+                        );
+                        alert_fee_cap_exceeded(*withdrawal_id, exceeded);
+                        false
+                    }
+                }
+            })
             .map(|(withdrawal_id, tx)| async move { (withdrawal_id, tx.sign().await) }),
     )
     .await;
@@ -561,6 +805,26 @@ async fn sign_transactions_batch() {
     }
 }
 
+/// Records a [`EventType::FeeCapExceeded`] alert, unless one was recorded
+/// less than [`crate::FEE_CAP_ALERT_INTERVAL`] ago.
+fn alert_fee_cap_exceeded(withdrawal_id: LedgerBurnIndex, exceeded: FeeCapExceeded) {
+    mutate_state(|s| {
+        let now = ic_cdk::api::time();
+        if s.can_record_fee_cap_alert(now) {
+            process_event(
+                s,
+                EventType::FeeCapExceeded {
+                    withdrawal_id,
+                    max_fee_per_gas: exceeded.max_fee_per_gas,
+                    max_transaction_fee: exceeded.max_transaction_fee,
+                    cap: exceeded.cap,
+                    timestamp: now,
+                },
+            );
+        }
+    });
+}
+
 async fn send_transactions_batch(latest_transaction_count: Option<TransactionCount>) {
     let latest_transaction_count = match latest_transaction_count {
         Some(latest_transaction_count) => latest_transaction_count,
@@ -574,17 +838,40 @@ async fn send_transactions_batch(latest_transaction_count: Option<TransactionCou
     });
 
     log!(INFO, "Transactions to send {:?}", transactions_to_send);
+    // Recorded before awaiting the providers, see `reconcile_broadcast_attempts`.
+    mutate_state(|s| {
+        for (withdrawal_id, signed_tx) in &transactions_to_send {
+            let is_first_attempt = s
+                .withdrawal_transactions
+                .get_broadcast_attempt(withdrawal_id)
+                .map_or(true, |attempt| attempt.transaction_hash != signed_tx.hash());
+            if is_first_attempt {
+                process_event(
+                    s,
+                    EventType::BroadcastAttempted {
+                        withdrawal_id: *withdrawal_id,
+                        transaction_hash: signed_tx.hash(),
+                        nonce: signed_tx.nonce(),
+                        timestamp: ic_cdk::api::time(),
+                    },
+                );
+            }
+        }
+    });
     let rpc_client =
         read_state(|s| RpcClient::from_state_custom_providers(s, vec![Provider::Alchemy]));
     let results = join_all(
         transactions_to_send
             .iter()
-            .map(|tx| rpc_client.send_raw_transaction(tx.raw_transaction_hex())),
+            .map(|(_withdrawal_id, tx)| rpc_client.send_raw_transaction(tx.raw_transaction_hex())),
     )
     .await;
 
-    for (signed_tx, result) in zip(transactions_to_send, results) {
+    for ((withdrawal_id, signed_tx), result) in zip(transactions_to_send, results) {
         log!(DEBUG, "Sent transaction {signed_tx:?}: {result:?}");
+        if result.is_ok() {
+            record_broadcast_outcome(withdrawal_id, &signed_tx, BroadcastOutcome::Sent);
+        }
         match result {
             Ok(SendRawTransactionResult::Ok) | Ok(SendRawTransactionResult::NonceTooLow) => {
                 // In case of resubmission we may hit the case of SendRawTransactionResult::NonceTooLow
@@ -606,6 +893,117 @@ async fn send_transactions_batch(latest_transaction_count: Option<TransactionCou
     }
 }
 
+/// Records the outcome of the broadcast attempt of `signed_tx`, unless it is already known.
+fn record_broadcast_outcome(
+    withdrawal_id: LedgerBurnIndex,
+    signed_tx: &SignedEip1559TransactionRequest,
+    outcome: BroadcastOutcome,
+) {
+    mutate_state(|s| {
+        let is_unreconciled = s
+            .withdrawal_transactions
+            .get_broadcast_attempt(&withdrawal_id)
+            .is_some_and(|attempt| {
+                attempt.transaction_hash == signed_tx.hash() && attempt.outcome.is_none()
+            });
+        if is_unreconciled {
+            process_event(
+                s,
+                EventType::BroadcastReconciled {
+                    withdrawal_id,
+                    transaction_hash: signed_tx.hash(),
+                    outcome,
+                },
+            );
+        }
+    });
+}
+
+/// Determines the outcome of the broadcast attempts without one, i.e. the sends that were
+/// interrupted by an upgrade or a trap, or whose providers could not be reached.
+/// Until then, the transactions of those withdrawals are neither sent again nor replaced,
+/// see [`WithdrawalTransactions::has_unreconciled_broadcast_attempt`].
+///
+/// [`WithdrawalTransactions::has_unreconciled_broadcast_attempt`]: crate::state::transactions::WithdrawalTransactions::has_unreconciled_broadcast_attempt
+async fn reconcile_broadcast_attempts(latest_transaction_count: Option<TransactionCount>) {
+    let latest_transaction_count = match latest_transaction_count {
+        Some(latest_transaction_count) => latest_transaction_count,
+        None => {
+            return;
+        }
+    };
+    let attempts = read_state(|s| s.withdrawal_transactions.unreconciled_broadcast_attempts());
+    if attempts.is_empty() {
+        return;
+    }
+    let rpc_client =
+        read_state(|s| RpcClient::from_state_custom_providers(s, vec![Provider::Alchemy]));
+
+    for (withdrawal_id, attempt, signed_tx) in attempts {
+        match broadcast_outcome(&rpc_client, latest_transaction_count, &attempt, &signed_tx).await {
+            Some(outcome) => {
+                log!(
+                    INFO,
+                    "[reconcile_broadcast_attempts]: transaction {} of withdrawal {withdrawal_id} with nonce {}: {outcome}",
+                    attempt.transaction_hash,
+                    attempt.nonce
+                );
+                record_broadcast_outcome(withdrawal_id, &signed_tx, outcome);
+            }
+            None => log!(
+                INFO,
+                "[reconcile_broadcast_attempts]: outcome of transaction {} of withdrawal {withdrawal_id} still unknown",
+                attempt.transaction_hash
+            ),
+        }
+    }
+}
+
+/// The transaction is only sent again if it provably did not land: the transaction count of
+/// the minter is not past its nonce and it has no receipt.
+async fn broadcast_outcome(
+    rpc_client: &RpcClient,
+    latest_transaction_count: TransactionCount,
+    attempt: &BroadcastAttempt,
+    signed_tx: &SignedEip1559TransactionRequest,
+) -> Option<BroadcastOutcome> {
+    let first_pending_tx_nonce: TransactionNonce = latest_transaction_count.change_units();
+    if attempt.nonce < first_pending_tx_nonce {
+        return Some(BroadcastOutcome::Mined);
+    }
+    match rpc_client
+        .get_transaction_receipt(attempt.transaction_hash)
+        .await
+    {
+        Ok(Some(_receipt)) => return Some(BroadcastOutcome::Mined),
+        Ok(None) => {}
+        Err(e) => {
+            log!(
+                INFO,
+                "[reconcile_broadcast_attempts]: failed to get the receipt of {}: {e:?}. Will retry later",
+                attempt.transaction_hash
+            );
+            return None;
+        }
+    }
+    match rpc_client
+        .send_raw_transaction(signed_tx.raw_transaction_hex())
+        .await
+    {
+        // another transaction with the same nonce was mined in the meantime
+        Ok(SendRawTransactionResult::NonceTooLow) => Some(BroadcastOutcome::Mined),
+        Ok(_) => Some(BroadcastOutcome::NotMined),
+        Err(e) => {
+            log!(
+                INFO,
+                "[reconcile_broadcast_attempts]: failed to send {} again: {e:?}. Will retry later",
+                attempt.transaction_hash
+            );
+            None
+        }
+    }
+}
+
 async fn finalize_transactions_batch() {
     if read_state(|s| s.withdrawal_transactions.is_sent_tx_empty()) {
         return;
@@ -629,7 +1027,8 @@ async fn finalize_transactions_batch() {
                     .map(|hash| rpc_client.get_transaction_receipt(*hash)),
             )
             .await;
-            let mut receipts: BTreeMap<LedgerBurnIndex, TransactionReceipt> = BTreeMap::new();
+            let mut receipts: BTreeMap<LedgerBurnIndex, TransactionReceiptWithLogs> =
+                BTreeMap::new();
             for ((hash, withdrawal_id), result) in zip(txs_to_finalize, results) {
                 match result {
                     Ok(Some(receipt)) => {
@@ -665,15 +1064,30 @@ async fn finalize_transactions_batch() {
                 expected_finalized_withdrawal_ids, actual_finalized_withdrawal_ids,
                 "ERROR: unexpected transaction receipts for some withdrawal IDs"
             );
-            for (withdrawal_id, transaction_receipt) in receipts {
+            for (withdrawal_id, receipt_with_logs) in receipts {
                 mutate_state(|s| {
+                    if let Some(amount_out) =
+                        realized_swap_amount_out(s, &withdrawal_id, &receipt_with_logs)
+                    {
+                        process_event(
+                            s,
+                            EventType::RealizedSwapAmountOut {
+                                withdrawal_id,
+                                amount_out,
+                            },
+                        );
+                    }
+                    let quarantine = is_missing_transfer_log(s, &withdrawal_id, &receipt_with_logs);
                     process_event(
                         s,
                         EventType::FinalizedTransaction {
                             withdrawal_id,
-                            transaction_receipt,
+                            transaction_receipt: receipt_with_logs.receipt,
                         },
                     );
+                    if quarantine {
+                        process_event(s, EventType::QuarantinedWithdrawal { withdrawal_id });
+                    }
                 });
             }
         }
@@ -683,6 +1097,84 @@ async fn finalize_transactions_batch() {
         }
     }
 }
+
+/// Returns true if the given withdrawal must be quarantined for manual review because its
+/// successful transaction transferred a non-standard ERC-20 token without the expected
+/// `Transfer` event from the minter to the destination.
+///
+/// Such tokens may not revert when the transfer fails, but the receipt is still recorded as
+/// is: reimbursing the user automatically would pay them twice if the transfer happened.
+fn is_missing_transfer_log(
+    state: &State,
+    withdrawal_id: &LedgerBurnIndex,
+    receipt_with_logs: &TransactionReceiptWithLogs,
+) -> bool {
+    let TransactionReceiptWithLogs { receipt, logs } = receipt_with_logs;
+    if receipt.status != TransactionStatus::Success {
+        return false;
+    }
+    let Some(WithdrawalRequest::Erc20(request)) = state
+        .withdrawal_transactions
+        .get_processed_withdrawal_request(withdrawal_id)
+    else {
+        return false;
+    };
+    if !state.is_non_standard_erc20_token(&request.erc20_contract_address) {
+        return false;
+    }
+    let has_transfer_log = state.minter_address().is_some_and(|minter_address| {
+        contains_transfer_log(
+            logs,
+            &request.erc20_contract_address,
+            &minter_address,
+            &request.destination,
+            request.withdrawal_amount,
+        )
+    });
+    if !has_transfer_log {
+        log!(
+            INFO,
+            "No Transfer log of {} {} to {} in transaction {} for withdrawal ID {withdrawal_id}. Quarantining it",
+            request.withdrawal_amount,
+            request.erc20_contract_address,
+            request.destination,
+            receipt.transaction_hash,
+        );
+    }
+    !has_transfer_log
+}
+
+/// Returns the amount of output tokens received by the recipient of a successful swap,
+/// or `None` if the withdrawal is not a swap or if its receipt contains no matching logs.
+fn realized_swap_amount_out(
+    state: &State,
+    withdrawal_id: &LedgerBurnIndex,
+    receipt_with_logs: &TransactionReceiptWithLogs,
+) -> Option<Erc20Value> {
+    let TransactionReceiptWithLogs { receipt, logs } = receipt_with_logs;
+    if receipt.status != TransactionStatus::Success {
+        return None;
+    }
+    let Some(WithdrawalRequest::Swap(request)) = state
+        .withdrawal_transactions
+        .get_processed_withdrawal_request(withdrawal_id)
+    else {
+        return None;
+    };
+    let amount_out =
+        realized_amount_out(request, &state.evm_network().wrapped_native_token(), logs);
+    if amount_out.is_none() {
+        log!(
+            INFO,
+            "No output token log to {} in transaction {} for swap {}. The realized amount out is unknown",
+            request.recipient,
+            receipt.transaction_hash,
+            request.swap_tx_id,
+        );
+    }
+    amount_out
+}
+
 async fn finalized_transaction_count() -> Result<TransactionCount, MultiCallError<TransactionCount>>
 {
     let evm_netowrk = read_state(|s| s.evm_network());
@@ -700,17 +1192,33 @@ async fn finalized_transaction_count() -> Result<TransactionCount, MultiCallErro
     }
 }
 
-pub fn estimate_gas_limit(withdrawal_request: &WithdrawalRequest) -> GasAmount {
-    match withdrawal_request {
-        WithdrawalRequest::Native(_) => NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
-        WithdrawalRequest::Erc20(request) => {
-            if request.is_wrapped_mint.unwrap_or_default() {
-                ERC20_MINT_TRANSACTION_GAS_LIMIT
+pub fn estimate_gas_limit(
+    withdrawal_request: &WithdrawalRequest,
+    evm_network: EvmNetwork,
+    gas_limits: &GasLimits,
+) -> GasAmount {
+    let kind = match withdrawal_request {
+        WithdrawalRequest::Native(request) => {
+            if request.is_contract_destination.unwrap_or_default() {
+                GasLimitKind::NativeWithdrawalToContract
             } else {
-                ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT
+                GasLimitKind::NativeWithdrawal
             }
         }
-        WithdrawalRequest::Erc20Approve(_) => ERC20_APPROVAL_TRANSACTION_GAS_LIMIT,
-        WithdrawalRequest::Swap(request) => request.gas_estimate,
-    }
+        WithdrawalRequest::Erc20(request) => {
+            let kind = if request.is_wrapped_mint.unwrap_or_default() {
+                GasLimitKind::WrappedIcrcMint
+            } else {
+                GasLimitKind::Erc20Withdrawal
+            };
+            // the transaction fee was charged with the gas limit at the time of the request,
+            // which an upgrade may have changed since
+            return request
+                .gas_limit
+                .unwrap_or_else(|| GasLimits::default_gas_limit(evm_network, kind));
+        }
+        WithdrawalRequest::Erc20Approve(_) => GasLimitKind::Erc20Approval,
+        WithdrawalRequest::Swap(request) => return request.gas_estimate,
+    };
+    gas_limits.get(evm_network, kind)
 }
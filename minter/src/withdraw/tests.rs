@@ -0,0 +1,377 @@
+mod cancel_withdrawal {
+    use crate::candid_types::withdraw_native::CancelError;
+    use crate::candid_types::RetrieveWithdrawalStatus;
+    use crate::evm_config::{EvmNetwork, NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT};
+    use crate::guard::TimerGuard;
+    use crate::numeric::{Erc20TokenAmount, LedgerBurnIndex, TransactionNonce, Wei, WeiPerGas};
+    use crate::state::audit::apply_state_transition;
+    use crate::state::event::EventType;
+    use crate::state::tests::initial_state;
+    use crate::state::transactions::{
+        create_transaction, NativeWithdrawalRequest, PendingReimbursementRequest,
+        ReimbursementIndex, WithdrawalRequest,
+    };
+    use crate::state::{mutate_state, read_state, State, TaskType, STATE};
+    use crate::tx::gas_fees::{GasFeeEstimate, DEFAULT_BASE_FEE_MULTIPLIER_PERCENT};
+    use crate::withdraw::{cancel_withdrawal_request, withdrawal_cancellation};
+    use candid::Principal;
+    use evm_rpc_client::eth_types::Address;
+    use std::str::FromStr;
+
+    const BURN_INDEX: LedgerBurnIndex = LedgerBurnIndex::new(15);
+
+    #[test]
+    fn should_cancel_pending_withdrawal_request() {
+        let mut state = initial_state();
+        accept_withdrawal_request(&mut state);
+
+        let reimbursement = withdrawal_cancellation(&state, user(), BURN_INDEX)
+            .expect("pending request should be cancellable");
+        assert_eq!(
+            reimbursement,
+            PendingReimbursementRequest {
+                ledger_burn_index: BURN_INDEX,
+                // 0.1 native tokens minus the ledger transfer fee of 0.01
+                reimbursed_amount: Erc20TokenAmount::new(90_000_000_000_000_000),
+                to: user(),
+                to_subaccount: None,
+            }
+        );
+
+        apply_state_transition(
+            &mut state,
+            &EventType::CancelledWithdrawalRequest(reimbursement.clone()),
+        );
+
+        assert_eq!(
+            state
+                .withdrawal_transactions
+                .transaction_status(&BURN_INDEX),
+            RetrieveWithdrawalStatus::NotFound
+        );
+        assert_eq!(
+            state
+                .withdrawal_transactions
+                .reimbursement_requests_iter()
+                .map(|(index, request)| (index.clone(), request.clone()))
+                .collect::<Vec<_>>(),
+            vec![(
+                ReimbursementIndex::Native {
+                    ledger_burn_index: BURN_INDEX
+                },
+                reimbursement.into()
+            )]
+        );
+        assert_eq!(
+            withdrawal_cancellation(&state, user(), BURN_INDEX),
+            Err(CancelError::NotFound)
+        );
+    }
+
+    #[test]
+    fn should_only_cancel_own_withdrawal_request() {
+        let mut state = initial_state();
+        accept_withdrawal_request(&mut state);
+
+        assert_eq!(
+            withdrawal_cancellation(&state, Principal::anonymous(), BURN_INDEX),
+            Err(CancelError::NotOwner)
+        );
+        assert_eq!(
+            withdrawal_cancellation(&state, user(), LedgerBurnIndex::new(16)),
+            Err(CancelError::NotFound)
+        );
+    }
+
+    #[test]
+    fn should_be_too_late_once_transaction_created() {
+        let mut state = initial_state();
+        let request = accept_withdrawal_request(&mut state);
+        let transaction = create_transaction(
+            &request.into(),
+            TransactionNonce::ZERO,
+            GasFeeEstimate {
+                base_fee_per_gas: WeiPerGas::ONE,
+                max_priority_fee_per_gas: WeiPerGas::ONE,
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+            },
+            NATIVE_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+            EvmNetwork::default(),
+        )
+        .unwrap();
+        apply_state_transition(
+            &mut state,
+            &EventType::CreatedTransaction {
+                withdrawal_id: BURN_INDEX,
+                transaction,
+            },
+        );
+
+        assert_eq!(
+            withdrawal_cancellation(&state, user(), BURN_INDEX),
+            Err(CancelError::TooLate {
+                status: RetrieveWithdrawalStatus::TxCreated
+            })
+        );
+    }
+
+    #[test]
+    fn should_not_cancel_while_transactions_are_being_created() {
+        let mut state = initial_state();
+        accept_withdrawal_request(&mut state);
+        STATE.with(|s| *s.borrow_mut() = Some(state));
+
+        let processing_guard =
+            TimerGuard::new(TaskType::RetrieveEth).expect("can retrieve timer guard");
+
+        assert!(matches!(
+            cancel_withdrawal_request(user(), BURN_INDEX),
+            Err(CancelError::TemporarilyUnavailable(_))
+        ));
+        assert_eq!(
+            read_state(|s| s.withdrawal_transactions.transaction_status(&BURN_INDEX)),
+            RetrieveWithdrawalStatus::Pending
+        );
+
+        drop(processing_guard);
+        let reimbursement = read_state(|s| withdrawal_cancellation(s, user(), BURN_INDEX)).unwrap();
+        mutate_state(|s| {
+            apply_state_transition(s, &EventType::CancelledWithdrawalRequest(reimbursement))
+        });
+
+        assert_eq!(
+            read_state(|s| s.withdrawal_transactions.withdrawal_requests_batch(5)),
+            vec![]
+        );
+    }
+
+    fn accept_withdrawal_request(state: &mut State) -> NativeWithdrawalRequest {
+        let request = NativeWithdrawalRequest {
+            withdrawal_amount: Wei::new(100_000_000_000_000_000),
+            destination: Address::from_str("0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34").unwrap(),
+            ledger_burn_index: BURN_INDEX,
+            from: user(),
+            from_subaccount: None,
+            created_at: Some(1_699_527_697_000_000_000),
+            l1_fee: None,
+            withdrawal_fee: None,
+            is_contract_destination: None,
+        };
+        apply_state_transition(
+            state,
+            &EventType::AcceptedNativeWithdrawalRequest(request.clone()),
+        );
+        assert_eq!(
+            state
+                .withdrawal_transactions
+                .pending_withdrawal_request(&BURN_INDEX),
+            Some(&WithdrawalRequest::Native(request.clone()))
+        );
+        request
+    }
+
+    fn user() -> Principal {
+        Principal::from_text("k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae")
+            .unwrap()
+    }
+}
+
+mod missing_transfer_log {
+    use crate::candid_types::Erc20TransferKind;
+    use crate::erc20::transfer_log::TRANSFER_EVENT_TOPIC;
+    use crate::erc20::ERC20Token;
+    use crate::evm_config::{EvmNetwork, ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT};
+    use crate::numeric::{
+        BlockNumber, Erc20Value, GasAmount, LedgerBurnIndex, TransactionNonce, Wei, WeiPerGas,
+    };
+    use crate::rpc_declarations::{
+        Data, FixedSizeData, LogEntry, TransactionReceipt, TransactionReceiptWithLogs,
+        TransactionStatus,
+    };
+    use crate::state::audit::apply_state_transition;
+    use crate::state::event::EventType;
+    use crate::state::tests::initial_state;
+    use crate::state::transactions::{create_transaction, Erc20WithdrawalRequest};
+    use crate::state::State;
+    use crate::tx::gas_fees::{GasFeeEstimate, DEFAULT_BASE_FEE_MULTIPLIER_PERCENT};
+    use crate::withdraw::is_missing_transfer_log;
+    use candid::Principal;
+    use evm_rpc_client::eth_types::Address;
+    use ic_management_canister_types::EcdsaPublicKeyResult;
+    use std::str::FromStr;
+
+    const WITHDRAWAL_ID: LedgerBurnIndex = LedgerBurnIndex::new(7);
+
+    #[test]
+    fn should_quarantine_successful_withdrawal_without_transfer_log() {
+        let state = state_with_withdrawal(Erc20TransferKind::NonStandard);
+
+        assert!(is_missing_transfer_log(
+            &state,
+            &WITHDRAWAL_ID,
+            &receipt(TransactionStatus::Success, vec![])
+        ));
+        assert!(!is_missing_transfer_log(
+            &state,
+            &WITHDRAWAL_ID,
+            &receipt(
+                TransactionStatus::Success,
+                vec![transfer_log(minter_address())]
+            )
+        ));
+    }
+
+    #[test]
+    fn should_quarantine_withdrawal_with_transfer_log_not_from_minter() {
+        let state = state_with_withdrawal(Erc20TransferKind::NonStandard);
+
+        assert!(is_missing_transfer_log(
+            &state,
+            &WITHDRAWAL_ID,
+            &receipt(
+                TransactionStatus::Success,
+                vec![transfer_log(destination())]
+            )
+        ));
+    }
+
+    #[test]
+    fn should_not_quarantine_failed_or_standard_withdrawals() {
+        let non_standard = state_with_withdrawal(Erc20TransferKind::NonStandard);
+        assert!(!is_missing_transfer_log(
+            &non_standard,
+            &WITHDRAWAL_ID,
+            &receipt(TransactionStatus::Failure, vec![])
+        ));
+
+        let standard = state_with_withdrawal(Erc20TransferKind::Standard);
+        assert!(!is_missing_transfer_log(
+            &standard,
+            &WITHDRAWAL_ID,
+            &receipt(TransactionStatus::Success, vec![])
+        ));
+    }
+
+    fn state_with_withdrawal(transfer_kind: Erc20TransferKind) -> State {
+        let mut state = initial_state();
+        // Compressed public key of the secret key 1.
+        state.ecdsa_public_key = Some(EcdsaPublicKeyResult {
+            public_key: hex::decode(
+                "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            )
+            .unwrap(),
+            chain_code: vec![2; 32],
+        });
+        apply_state_transition(
+            &mut state,
+            &EventType::AddedErc20Token(ERC20Token {
+                chain_id: state.evm_network,
+                erc20_contract_address: token(),
+                erc20_token_symbol: "ckUSDT".parse().unwrap(),
+                erc20_ledger_id: ledger_id(),
+                transfer_kind: Some(transfer_kind),
+                original_symbol: None,
+            }),
+        );
+        let request = Erc20WithdrawalRequest {
+            max_transaction_fee: Wei::new(30_000_000_000_000_000),
+            withdrawal_amount: amount(),
+            destination: destination(),
+            native_ledger_burn_index: WITHDRAWAL_ID,
+            erc20_contract_address: token(),
+            erc20_ledger_id: ledger_id(),
+            erc20_ledger_burn_index: LedgerBurnIndex::new(2),
+            from: Principal::from_text(
+                "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae",
+            )
+            .unwrap(),
+            from_subaccount: None,
+            created_at: 1_699_527_697_000_000_000,
+            l1_fee: None,
+            withdrawal_fee: None,
+            is_wrapped_mint: Some(false),
+            gas_limit: None,
+        };
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedErc20WithdrawalRequest(request.clone()),
+        );
+        let transaction = create_transaction(
+            &request.into(),
+            TransactionNonce::ZERO,
+            GasFeeEstimate {
+                base_fee_per_gas: WeiPerGas::ONE,
+                max_priority_fee_per_gas: WeiPerGas::ONE,
+                base_fee_multiplier_percent: DEFAULT_BASE_FEE_MULTIPLIER_PERCENT,
+            },
+            ERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+            EvmNetwork::default(),
+        )
+        .unwrap();
+        apply_state_transition(
+            &mut state,
+            &EventType::CreatedTransaction {
+                withdrawal_id: WITHDRAWAL_ID,
+                transaction,
+            },
+        );
+        state
+    }
+
+    fn receipt(status: TransactionStatus, logs: Vec<LogEntry>) -> TransactionReceiptWithLogs {
+        TransactionReceiptWithLogs {
+            receipt: TransactionReceipt {
+                block_hash: "0xce67a85c9fb8bc50213815c32814c159fd75160acf7cb8631e8e7b7cf7f1d472"
+                    .parse()
+                    .unwrap(),
+                block_number: BlockNumber::new(5_540_000),
+                effective_gas_price: WeiPerGas::ONE,
+                gas_used: GasAmount::from(50_000_u32),
+                status,
+                transaction_hash:
+                    "0x06afc3c693dc2ba2c19b5c287c4dddce040d766bea5fd13c8a7268b04aa94f2d"
+                        .parse()
+                        .unwrap(),
+            },
+            logs,
+        }
+    }
+
+    fn transfer_log(from: Address) -> LogEntry {
+        LogEntry {
+            address: token(),
+            topics: vec![
+                FixedSizeData(TRANSFER_EVENT_TOPIC),
+                FixedSizeData(<[u8; 32]>::from(&from)),
+                FixedSizeData(<[u8; 32]>::from(&destination())),
+            ],
+            data: Data(amount().to_be_bytes().to_vec()),
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            block_hash: None,
+            log_index: None,
+            removed: false,
+        }
+    }
+
+    fn minter_address() -> Address {
+        Address::from_str("0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf").unwrap()
+    }
+
+    fn token() -> Address {
+        Address::from_str("0x55d398326f99059fF775485246999027B3197955").unwrap()
+    }
+
+    fn destination() -> Address {
+        Address::from_str("0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34").unwrap()
+    }
+
+    fn ledger_id() -> Principal {
+        Principal::from_text("3sgad-taaaa-aaaar-qaedq-cai").unwrap()
+    }
+
+    fn amount() -> Erc20Value {
+        Erc20Value::new(1_500_000_000_000_000_000)
+    }
+}